@@ -2,11 +2,22 @@
 //!
 //! Этот модуль отвечает за загрузку и валидацию конфигурации клиента.
 
+use llp_core::access::AccessKey;
 use llp_core::packet::MimicryProfile;
+use llp_mimicry::dns::{ClientOptions, RecordType};
+use llp_mimicry::aggregate::AggregationConfig;
+use llp_mimicry::cover::{CoverConfig, CoverTraffic};
+use llp_mimicry::shaper::{ShapingConfig, TrafficShaper};
+use llp_mimicry::template::TemplateDir;
+use llp_mimicry::{PacketWrapper, Role};
+use llp_core::session::{
+    DEFAULT_REPLAY_WINDOW_SIZE, MAX_REPLAY_WINDOW_SIZE, MIN_REPLAY_WINDOW_SIZE,
+};
 use serde::{Deserialize, Serialize};
-use std::net::{IpAddr, Ipv4Addr};
-use std::path::Path;
-use std::time::Duration;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Конфигурация клиента LLP
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +31,30 @@ pub struct ClientConfig {
     /// Настройки безопасности
     pub security: SecurityConfig,
 
+    /// Внешний TLS слой
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// WebSocket транспорт
+    #[serde(default)]
+    pub websocket: WebSocketConfig,
+
+    /// DNS туннель
+    #[serde(default)]
+    pub dns: DnsConfig,
+
+    /// Отправка на сервер по паттерну профиля мимикрии
+    #[serde(default)]
+    pub shaping: ShapingConfig,
+
+    /// Cover traffic в простое (LLP пакеты с расширением COVER)
+    #[serde(default)]
+    pub cover: CoverConfig,
+
+    /// Агрегация пакетов в сообщения размера профиля
+    #[serde(default)]
+    pub aggregation: AggregationConfig,
+
     /// Настройки логирования
     pub logging: LoggingConfig,
 }
@@ -84,6 +119,87 @@ pub struct SecurityConfig {
     /// Верификация сертификата сервера
     #[serde(default = "default_verify_server")]
     pub verify_server: bool,
+
+    /// Размер окна replay protection (количество пакетов)
+    #[serde(default = "default_replay_window_size")]
+    pub replay_window_size: usize,
+
+    /// Каталог TOML шаблонов профилей мимикрии (`*.toml`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile_templates_dir: Option<PathBuf>,
+
+    /// Интервал проверки изменений шаблонов (секунды, 0 — без перезагрузки)
+    #[serde(default = "default_profile_reload_interval")]
+    pub profile_reload_interval_secs: u64,
+
+    /// Ключ доступа сервера (hex, 32 байта): подключение начинается с
+    /// подписанного им токена (должен совпадать с сервером)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_key: Option<String>,
+}
+
+/// Внешний TLS 1.3 слой
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// Включить TLS поверх TCP (должно совпадать с сервером)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// PEM сертификат сервера или его CA (нужен при verify_server = true)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<PathBuf>,
+
+    /// SNI вместо имени из профиля мимикрии
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_name: Option<String>,
+
+    /// Предлагать HTTP/2 (ALPN `h2`); если сервер его не принимает,
+    /// соединение остаётся на HTTP/1.1
+    #[serde(default)]
+    pub http2: bool,
+}
+
+/// WebSocket транспорт для работы за CDN и обратными прокси
+///
+/// Подключение открывается запросом HTTP Upgrade с заголовками профиля
+/// мимикрии, токен доступа передаётся в `Sec-WebSocket-Protocol`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketConfig {
+    /// Подключаться по WebSocket (должно совпадать с сервером)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Путь запроса Upgrade
+    #[serde(default = "default_websocket_path")]
+    pub path: String,
+
+    /// `Host` вместо домена из профиля мимикрии (например, домен CDN)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+}
+
+/// DNS туннель для сетей, где проходит только DNS
+///
+/// Поток LLP идёт в запросах к зоне `domain` через резолвер и в ответах
+/// сервера, авторитетного для этой зоны. Адрес `[server]` не
+/// используется, слой TLS не поддерживается.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsConfig {
+    /// Подключаться через DNS туннель
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Зона туннеля (должна совпадать с сервером)
+    #[serde(default)]
+    pub domain: String,
+
+    /// Резолвер `ip:port` (по умолчанию — первый nameserver из /etc/resolv.conf)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolver: Option<String>,
+
+    /// Тип записи ответа: `txt` или `null`
+    #[serde(default = "default_dns_record")]
+    pub record: String,
 }
 
 /// Настройки логирования
@@ -157,6 +273,22 @@ fn default_verify_server() -> bool {
     true
 }
 
+fn default_replay_window_size() -> usize {
+    DEFAULT_REPLAY_WINDOW_SIZE
+}
+
+fn default_profile_reload_interval() -> u64 {
+    5
+}
+
+fn default_dns_record() -> String {
+    RecordType::default().name().to_string()
+}
+
+fn default_websocket_path() -> String {
+    "/ws".to_string()
+}
+
 fn default_log_level() -> String {
     "info".to_string()
 }
@@ -182,6 +314,12 @@ impl Default for ClientConfig {
             server: ServerConfig::default(),
             vpn: VpnConfig::default(),
             security: SecurityConfig::default(),
+            tls: TlsConfig::default(),
+            websocket: WebSocketConfig::default(),
+            dns: DnsConfig::default(),
+            shaping: ShapingConfig::default(),
+            cover: CoverConfig::default(),
+            aggregation: AggregationConfig::default(),
             logging: LoggingConfig::default(),
         }
     }
@@ -217,6 +355,31 @@ impl Default for SecurityConfig {
             mimicry_profile: default_mimicry_profile(),
             keepalive_interval_secs: default_keepalive_interval(),
             verify_server: default_verify_server(),
+            replay_window_size: default_replay_window_size(),
+            profile_templates_dir: None,
+            profile_reload_interval_secs: default_profile_reload_interval(),
+            access_key: None,
+        }
+    }
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_websocket_path(),
+            host: None,
+        }
+    }
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            domain: String::new(),
+            resolver: None,
+            record: default_dns_record(),
         }
     }
 }
@@ -259,31 +422,190 @@ impl ClientConfig {
             anyhow::bail!("Порт сервера не может быть 0");
         }
 
-        // Проверка профиля мимикрии
-        self.parse_mimicry_profile()?;
+        // Проверка профиля мимикрии. Имя из каталога шаблонов известно
+        // только после их загрузки, поэтому тогда его проверяет main
+        if self.security.profile_templates_dir.is_none() {
+            self.parse_mimicry_profile()?;
+        }
 
         // Проверка MTU
         if self.vpn.mtu < 576 || self.vpn.mtu > 9000 {
             anyhow::bail!("MTU должен быть в диапазоне 576-9000");
         }
 
+        // Проверка окна replay protection
+        if self.security.replay_window_size < MIN_REPLAY_WINDOW_SIZE
+            || self.security.replay_window_size > MAX_REPLAY_WINDOW_SIZE
+        {
+            anyhow::bail!(
+                "replay_window_size должен быть в диапазоне {}-{}",
+                MIN_REPLAY_WINDOW_SIZE,
+                MAX_REPLAY_WINDOW_SIZE
+            );
+        }
+
+        // Проверка TLS
+        if self.tls.enabled {
+            if self.security.verify_server && self.tls.ca_cert.is_none() {
+                anyhow::bail!("tls.ca_cert обязателен при verify_server = true");
+            }
+            if let Some(name) = &self.tls.server_name {
+                llp_mimicry::tls::check_server_name(name)?;
+            }
+        }
+        if self.tls.http2 {
+            if !self.tls.enabled {
+                anyhow::bail!("tls.http2 требует tls.enabled = true");
+            }
+            if self.websocket.enabled {
+                anyhow::bail!("WebSocket не работает поверх tls.http2");
+            }
+        }
+
+        // Проверка ключа доступа
+        self.access_key()?;
+
+        // Проверка пути WebSocket
+        if self.websocket.enabled && !self.websocket.path.starts_with('/') {
+            anyhow::bail!("websocket.path должен начинаться с '/'");
+        }
+
+        // Проверка DNS туннеля
+        if self.dns.enabled {
+            if self.tls.enabled {
+                anyhow::bail!("DNS туннель не поддерживает [tls]");
+            }
+            if self.dns.domain.trim_end_matches('.').is_empty() {
+                anyhow::bail!("dns.domain должен быть задан");
+            }
+            self.dns_record()?;
+            if let Some(resolver) = &self.dns.resolver {
+                resolver
+                    .parse::<SocketAddr>()
+                    .map_err(|_| anyhow::anyhow!("dns.resolver должен быть ip:port"))?;
+            }
+        }
+
+        // Планировщик отправки, cover traffic и агрегация
+        self.shaping.validate()?;
+        self.cover.validate(self.vpn.mtu as usize)?;
+        self.aggregation.validate()?;
+
         Ok(())
     }
 
+    /// Тип записи ответа DNS туннеля
+    pub fn dns_record(&self) -> Result<RecordType, anyhow::Error> {
+        RecordType::from_name(&self.dns.record)
+            .ok_or_else(|| anyhow::anyhow!("Неизвестный тип записи DNS: {}", self.dns.record))
+    }
+
+    /// Параметры клиента DNS туннеля
+    ///
+    /// Без `dns.resolver` берётся первый nameserver из /etc/resolv.conf.
+    pub fn dns_options(&self) -> Result<ClientOptions, anyhow::Error> {
+        let resolver = match &self.dns.resolver {
+            Some(resolver) => resolver.parse()?,
+            None => {
+                let conf = std::fs::read_to_string("/etc/resolv.conf")?;
+                let ip: IpAddr = conf
+                    .lines()
+                    .filter_map(|line| line.strip_prefix("nameserver"))
+                    .find_map(|value| value.trim().parse().ok())
+                    .ok_or_else(|| anyhow::anyhow!("В /etc/resolv.conf нет nameserver"))?;
+                SocketAddr::new(ip, 53)
+            }
+        };
+        let mut options = ClientOptions::new(resolver, self.dns.domain.clone());
+        options.record = self.dns_record()?;
+        Ok(options)
+    }
+
+    /// Ключ доступа (None — сервер не требует токен)
+    pub fn access_key(&self) -> Result<Option<AccessKey>, anyhow::Error> {
+        match &self.security.access_key {
+            Some(value) => AccessKey::from_hex(value)
+                .map(Some)
+                .ok_or_else(|| anyhow::anyhow!("access_key должен быть hex строкой из 32 байт")),
+            None => Ok(None),
+        }
+    }
+
     /// Получить адрес сервера
     pub fn server_address(&self) -> String {
         format!("{}:{}", self.server.host, self.server.port)
     }
 
+    /// Загрузить шаблоны профилей мимикрии в глобальный реестр
+    ///
+    /// Возвращает каталог для перезагрузки на лету, если он задан.
+    pub fn load_profile_templates(&self) -> Result<Option<TemplateDir>, anyhow::Error> {
+        match &self.security.profile_templates_dir {
+            Some(dir) => Ok(Some(llp_mimicry::template::load_global(dir)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Интервал перезагрузки шаблонов профилей (None — отключена)
+    pub fn profile_reload_interval(&self) -> Option<Duration> {
+        match self.security.profile_reload_interval_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
     /// Парсинг профиля мимикрии из строки
+    ///
+    /// Имя ищется в глобальном реестре профилей, поэтому доступны и
+    /// зарегистрированные пользовательские профили.
     pub fn parse_mimicry_profile(&self) -> Result<MimicryProfile, anyhow::Error> {
-        match self.security.mimicry_profile.as_str() {
-            "none" => Ok(MimicryProfile::None),
-            "vk_video" => Ok(MimicryProfile::VkVideo),
-            "yandex_music" => Ok(MimicryProfile::YandexMusic),
-            "rutube" => Ok(MimicryProfile::RuTube),
-            unknown => anyhow::bail!("Неизвестный профиль мимикрии: {}", unknown),
+        let name = self.security.mimicry_profile.as_str();
+        llp_mimicry::registry::profile_by_name(name)
+            .ok_or_else(|| anyhow::anyhow!("Неизвестный профиль мимикрии: {}", name))
+    }
+
+    /// Конфигурация TLS клиента с ALPN и отпечатком профиля (None — TLS выключен)
+    pub fn tls_client_config(
+        &self,
+        alpn: Vec<Vec<u8>>,
+        fingerprint: llp_mimicry::tls::Fingerprint,
+    ) -> Result<Option<Arc<rustls::ClientConfig>>, anyhow::Error> {
+        if !self.tls.enabled {
+            return Ok(None);
         }
+        let config = match (&self.tls.ca_cert, self.security.verify_server) {
+            (Some(path), true) => {
+                let roots = llp_mimicry::tls::load_certs(path)?;
+                llp_mimicry::tls::client_config(&roots, alpn, fingerprint)?
+            }
+            (None, true) => anyhow::bail!("tls.ca_cert обязателен при verify_server = true"),
+            (_, false) => llp_mimicry::tls::client_config_unverified(alpn, fingerprint)?,
+        };
+        Ok(Some(config))
+    }
+
+    /// Планировщик отправки на сервер для профиля мимикрии
+    ///
+    /// `None` — планирование выключено или профиль не задаёт паттерн.
+    pub fn traffic_shaper(&self) -> Result<Option<TrafficShaper>, anyhow::Error> {
+        if !self.shaping.enabled {
+            return Ok(None);
+        }
+        let timing = PacketWrapper::try_new(self.parse_mimicry_profile()?)?.timing_profile();
+        Ok(timing.map(|timing| TrafficShaper::new(timing, self.shaping.options())))
+    }
+
+    /// Генератор cover traffic для профиля мимикрии
+    ///
+    /// `None` — cover traffic выключен или профиль не задаёт кривую битрейта.
+    pub fn cover_traffic(&self) -> Result<Option<CoverTraffic>, anyhow::Error> {
+        if !self.cover.enabled {
+            return Ok(None);
+        }
+        let curve = PacketWrapper::try_new(self.parse_mimicry_profile()?)?.bitrate_curve();
+        Ok(curve.map(|curve| {
+            CoverTraffic::new(curve, self.cover.options(), Role::Client, Instant::now())
+        }))
     }
 
     /// Получить таймаут подключения
@@ -327,6 +649,18 @@ mod tests {
         assert!(config.parse_mimicry_profile().is_err());
     }
 
+    #[test]
+    fn test_template_profile_checked_after_loading() {
+        let mut config = ClientConfig::default();
+        config.security.mimicry_profile = "custom_stream".to_string();
+        assert!(config.validate().is_err());
+
+        // Шаблоны загружает main, валидация реестр не трогает
+        config.security.profile_templates_dir = Some(PathBuf::from("profiles"));
+        assert!(config.validate().is_ok());
+        assert!(llp_mimicry::registry::profile_by_name("custom_stream").is_none());
+    }
+
     #[test]
     fn test_server_address() {
         let mut config = ClientConfig::default();
@@ -356,6 +690,77 @@ mod tests {
         // Невалидный MTU
         config.vpn.mtu = 100;
         assert!(config.validate().is_err());
+        config.vpn.mtu = 1420;
+
+        // HTTP/2 выбирается только по ALPN внутри TLS
+        config.tls.http2 = true;
+        assert!(config.validate().is_err());
+
+        // TLS с проверкой сервера требует сертификат
+        config.tls.enabled = true;
+        assert!(config.validate().is_err());
+        config.security.verify_server = false;
+        assert!(config.validate().is_ok());
+        config.tls.server_name = Some("bad name".to_string());
+        assert!(config.validate().is_err());
+        config.tls.server_name = None;
+
+        // Ключ доступа — 32 байта в hex
+        config.security.access_key = Some("ab".repeat(32));
+        assert!(config.validate().is_ok());
+        config.security.access_key = Some("ab".repeat(16));
+        assert!(config.validate().is_err());
+        config.security.access_key = None;
+
+        // Путь WebSocket без ведущего '/'
+        config.websocket.enabled = true;
+        config.websocket.path = "ws".to_string();
+        assert!(config.validate().is_err());
+        config.websocket.path = "/ws".to_string();
+        assert!(config.validate().is_err(), "WebSocket поверх HTTP/2");
+        config.tls.http2 = false;
+        assert!(config.validate().is_ok());
+
+        // DNS туннель: зона, тип записи и резолвер
+        config.dns.enabled = true;
+        assert!(config.validate().is_err());
+        config.dns.domain = "t.example.com".to_string();
+        config.dns.resolver = Some("127.0.0.1:5353".to_string());
+        assert!(config.validate().is_err(), "DNS туннель без TLS");
+        config.tls.enabled = false;
+        assert!(config.validate().is_ok());
+        let options = config.dns_options().unwrap();
+        assert_eq!(options.resolver.port(), 5353);
+        assert_eq!(options.record, RecordType::Txt);
+        config.dns.record = "aaaa".to_string();
+        assert!(config.validate().is_err());
+        config.dns.record = "null".to_string();
+        config.dns.resolver = Some("localhost".to_string());
+        assert!(config.validate().is_err());
+        config.dns = DnsConfig::default();
+    }
+
+    #[test]
+    fn test_traffic_shaper() {
+        let mut config = ClientConfig::default();
+        config.security.mimicry_profile = "yandex_music".to_string();
+        config.shaping.bypass_size = 0;
+        let shaper = config.traffic_shaper().unwrap().unwrap();
+        assert_eq!(shaper.options().bypass_size, 0);
+
+        config.shaping.enabled = false;
+        assert!(config.traffic_shaper().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cover_traffic() {
+        let mut config = ClientConfig::default();
+        assert!(config.cover_traffic().unwrap().is_none());
+
+        config.cover.enabled = true;
+        config.cover.max_mb_per_hour = 10;
+        let cover = config.cover_traffic().unwrap().unwrap();
+        assert_eq!(cover.options().max_bytes_per_hour, 10 * 1024 * 1024);
     }
 
     #[test]
@@ -2,18 +2,33 @@
 //!
 //! Этот модуль отвечает за:
 //! - Установление TCP подключения к серверу
+//! - Открытие WebSocket, если он выбран транспортом
+//! - HTTP/2, если TLS слой согласовал ALPN `h2`
 //! - Выполнение handshake
 //! - Отправку и получение LLP пакетов
+//! - Обработку alert от сервера и штатное закрытие сессии
 //! - Автоматическое переподключение
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use llp_core::{
+    access::AccessToken,
+    alert::{Alert, AlertAction, AlertCode},
+    clock::{Clock, SystemClock},
+    error::SessionError,
+    extension::{Extension, Extensions},
     handshake::ClientHandshake,
-    packet::{LlpPacket, MimicryProfile, PacketFlags, PacketHeader},
+    packet::{LlpPacket, MimicryProfile, PacketFlags},
     session::Session,
+    LlpError,
 };
-use llp_mimicry::PacketWrapper;
+use llp_mimicry::codec::{self, HttpDecoder};
+use llp_mimicry::dns;
+use llp_mimicry::h2::{self, H2Session};
+use llp_mimicry::tls::{self, Transport};
+use llp_mimicry::ws::{self, WsCodec};
+use llp_mimicry::{PacketWrapper, Role};
 use rand::rngs::OsRng;
+use std::collections::VecDeque;
 use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
@@ -50,18 +65,38 @@ pub struct ConnectionInfo {
     pub mimicry_profile: MimicryProfile,
     /// Количество попыток переподключения
     pub reconnect_attempts: u32,
+    /// Код последнего fatal alert от сервера
+    pub last_alert: Option<AlertCode>,
+}
+
+/// Извлечь код alert, если ошибка означает закрытие сессии сервером
+pub fn peer_alert_code(error: &(dyn std::error::Error + 'static)) -> Option<AlertCode> {
+    match error.downcast_ref::<LlpError>()? {
+        LlpError::SessionError(SessionError::ClosedByPeer { code, .. }) => Some(*code),
+        _ => None,
+    }
 }
 
 /// Подключение к серверу LLP
 pub struct ServerConnection {
     /// Конфигурация
     config: Arc<ClientConfig>,
-    /// TCP stream
-    stream: Option<TcpStream>,
+    /// TCP или TLS поток
+    stream: Option<Transport>,
+    /// Кадры WebSocket (None — обычный поток)
+    websocket: Option<WsCodec>,
+    /// HTTP/2 соединение (None — HTTP/1.1 мимикрия)
+    h2: Option<H2Session>,
     /// Информация о подключении
     info: Arc<RwLock<ConnectionInfo>>,
     /// Wrapper для мимикрии
     wrapper: Option<PacketWrapper>,
+    /// Декодер HTTP сообщений от сервера
+    decoder: HttpDecoder,
+    /// Пакеты, уже извлечённые из потокового ответа
+    pending: VecDeque<Bytes>,
+    /// Кадры pong WebSocket, ждущие записи в [`Self::send_pending`]
+    outgoing: BytesMut,
     /// Сессия
     session: Option<Session>,
 }
@@ -78,17 +113,43 @@ impl ServerConnection {
             session_id: None,
             mimicry_profile,
             reconnect_attempts: 0,
+            last_alert: None,
         };
 
         Self {
             config,
             stream: None,
+            websocket: None,
+            h2: None,
             info: Arc::new(RwLock::new(info)),
             wrapper: None,
+            decoder: HttpDecoder::new(),
+            pending: VecDeque::new(),
+            outgoing: BytesMut::new(),
             session: None,
         }
     }
 
+    /// Подключение поверх открытого потока и установленной сессии
+    ///
+    /// Handshake уже выполнен; пакеты идут через мимикрию профиля сессии
+    /// так же, как после [`Self::connect`].
+    pub fn with_session(config: Arc<ClientConfig>, stream: Transport, session: Session) -> Self {
+        let info = ConnectionInfo {
+            state: ConnectionState::Connected,
+            session_id: Some(session.session_id()),
+            mimicry_profile: session.mimicry_profile(),
+            reconnect_attempts: 0,
+            last_alert: None,
+        };
+
+        let mut connection = Self::new(config);
+        connection.info = Arc::new(RwLock::new(info));
+        connection.stream = Some(stream);
+        connection.start_session(session);
+        connection
+    }
+
     /// Получить информацию о подключении
     pub fn info(&self) -> Arc<RwLock<ConnectionInfo>> {
         Arc::clone(&self.info)
@@ -97,17 +158,54 @@ impl ServerConnection {
     /// Подключиться к серверу
     pub async fn connect(&mut self) -> Result<()> {
         self.set_state(ConnectionState::Connecting).await;
+        self.pending.clear();
+        self.outgoing.clear();
+
+        let mut stream = if self.config.dns.enabled {
+            info!(
+                "Подключение через DNS туннель: зона {}",
+                self.config.dns.domain
+            );
+            let stream = tokio::time::timeout(
+                self.config.connection_timeout(),
+                dns::connect(self.config.dns_options()?),
+            )
+            .await??;
+            info!("✓ DNS туннель открыт");
+            Transport::Tunnel(stream)
+        } else {
+            info!("Подключение к серверу: {}", self.config.server_address());
+
+            // Подключение TCP
+            let stream = tokio::time::timeout(
+                self.config.connection_timeout(),
+                TcpStream::connect(self.config.server_address()),
+            )
+            .await??;
+
+            info!("✓ TCP подключение установлено");
+
+            self.open_transport(stream).await?
+        };
 
-        info!("Подключение к серверу: {}", self.config.server_address());
-
-        // Подключение TCP
-        let stream = tokio::time::timeout(
-            self.config.connection_timeout(),
-            TcpStream::connect(self.config.server_address()),
-        )
-        .await??;
-
-        info!("✓ TCP подключение установлено");
+        // Токен доступа: без него сервер отдаёт подключение обычному сайту
+        let token = self
+            .config
+            .access_key()?
+            .map(|key| key.token(&mut OsRng, SystemClock.unix_secs()));
+        if self.config.websocket.enabled {
+            self.websocket = Some(self.upgrade(&mut stream, token.as_ref()).await?);
+        } else if let Some(token) = token {
+            stream.write_all(&token).await?;
+        }
+        // HTTP/2 начинается после токена: префейс уходит с первым сообщением
+        self.h2 = if stream.alpn_protocol() == Some(h2::ALPN_H2) {
+            info!("✓ HTTP/2 согласован (ALPN h2)");
+            let profile = self.config.parse_mimicry_profile()?;
+            Some(H2Session::try_new(profile, Role::Client)?)
+        } else {
+            None
+        };
 
         self.stream = Some(stream);
         self.set_state(ConnectionState::Handshaking).await;
@@ -121,19 +219,21 @@ impl ServerConnection {
         );
 
         // Создание сессии
-        let session = Session::new(session_id, session_key, mimicry_profile);
+        let session = Session::with_replay_window(
+            session_id,
+            session_key,
+            mimicry_profile,
+            self.config.security.replay_window_size,
+        );
 
-        // Создание wrapper
-        let wrapper = PacketWrapper::new(mimicry_profile);
-
-        self.session = Some(session);
-        self.wrapper = Some(wrapper);
+        self.start_session(session);
 
         {
             let mut info = self.info.write().await;
             info.session_id = Some(session_id);
             info.mimicry_profile = mimicry_profile;
             info.reconnect_attempts = 0;
+            info.last_alert = None;
         }
 
         self.set_state(ConnectionState::Connected).await;
@@ -143,6 +243,19 @@ impl ServerConnection {
         Ok(())
     }
 
+    /// Начать обмен пакетами в установленной сессии
+    fn start_session(&mut self, session: Session) {
+        // Клиент передаёт данные в HTTP запросах
+        let mut wrapper = PacketWrapper::new(session.mimicry_profile()).with_role(Role::Client);
+        if let Some(options) = self.config.aggregation.options() {
+            wrapper = wrapper.with_aggregation(options);
+        }
+
+        self.session = Some(session);
+        self.wrapper = Some(wrapper);
+        self.decoder = HttpDecoder::new().with_streaming(true);
+    }
+
     /// Переподключиться к серверу
     pub async fn reconnect(&mut self) -> Result<()> {
         {
@@ -152,22 +265,38 @@ impl ServerConnection {
 
         self.set_state(ConnectionState::Reconnecting).await;
 
-        let attempts = {
+        let (attempts, last_alert) = {
             let info = self.info.read().await;
-            info.reconnect_attempts
+            (info.reconnect_attempts, info.last_alert)
         };
 
         if attempts > self.config.server.reconnect_attempts {
             return Err("Превышено количество попыток переподключения".into());
         }
 
+        let action = last_alert.map(AlertCode::recommended_action);
+        if action == Some(AlertAction::GiveUp) {
+            return Err(format!(
+                "Сервер закрыл сессию ({}), переподключение не выполняется",
+                last_alert.unwrap()
+            )
+            .into());
+        }
+
         info!("Попытка переподключения #{}", attempts);
 
-        // Задержка перед переподключением
-        tokio::time::sleep(self.config.reconnect_delay()).await;
+        // Задержка перед переподключением; при перегрузке или остановке
+        // сервера — экспоненциальная
+        let mut delay = self.config.reconnect_delay();
+        if action == Some(AlertAction::ReconnectWithBackoff) {
+            delay *= 1 << attempts.min(5);
+        }
+        tokio::time::sleep(delay).await;
 
         // Закрытие старого подключения
         self.stream = None;
+        self.websocket = None;
+        self.h2 = None;
         self.session = None;
         self.wrapper = None;
 
@@ -177,172 +306,424 @@ impl ServerConnection {
 
     /// Отправить IP пакет на сервер
     pub async fn send_packet(&mut self, ip_packet: &[u8]) -> Result<()> {
+        self.send_packets(&[ip_packet]).await
+    }
+
+    /// Отправить пачку IP пакетов на сервер
+    ///
+    /// С агрегацией пачка уходит сообщениями размера chunk профиля,
+    /// иначе — по сообщению на пакет.
+    pub async fn send_packets<P: AsRef<[u8]>>(&mut self, ip_packets: &[P]) -> Result<()> {
         let session = self.session.as_mut().ok_or("Нет активной сессии")?;
         let wrapper = self.wrapper.as_mut().ok_or("Нет wrapper")?;
         let stream = self.stream.as_mut().ok_or("Нет подключения")?;
 
-        // Создание заголовка LLP пакета
-        let session_id = session.session_id();
-        let sequence_number = session.current_tx_sequence();
+        let mut serialized = Vec::with_capacity(ip_packets.len());
+        for ip_packet in ip_packets {
+            serialized.push(Self::seal_packet(session, ip_packet.as_ref())?);
+        }
 
-        let header = PacketHeader::new(
-            PacketFlags::DATA,
-            session_id,
-            sequence_number,
-            session.mimicry_profile(),
-        );
+        // По WebSocket пакет идёт целиком в бинарном сообщении
+        if let Some(websocket) = self.websocket.as_mut() {
+            for packet in &serialized {
+                ws::write_message(stream, websocket, packet).await?;
+                debug!("→ Отправлен пакет: {} байт (WebSocket)", packet.len());
+            }
+            return Ok(());
+        }
 
-        // Шифрование payload
-        let aad = {
-            let mut buf = bytes::BytesMut::new();
-            header.serialize(&mut buf);
-            buf.freeze()
+        // По HTTP/2 пакеты идут в DATA кадрах POST запросов
+        if let Some(session) = self.h2.as_mut() {
+            for packet in &serialized {
+                session.send(packet)?;
+                debug!("→ Отправлен пакет: {} байт (HTTP/2)", packet.len());
+            }
+            h2::write_output(stream, session).await?;
+            return Ok(());
+        }
+
+        // Обёртывание в мимикрию
+        let messages = wrapper.wrap_batch(&serialized)?;
+
+        // Отправка
+        for wrapped in &messages {
+            codec::write_frame(stream, wrapper.is_http(), wrapped).await?;
+            debug!("→ Отправлен пакет: {} байт", wrapped.len());
+        }
+
+        Ok(())
+    }
+
+    /// Зашифровать IP пакет в сериализованный LLP пакет
+    ///
+    /// Формат тот же, что у сервера: [`Session::seal_packet`].
+    fn seal_packet(session: &mut Session, ip_packet: &[u8]) -> Result<Bytes> {
+        // Фиктивный пакет cover traffic идёт как CONTROL с расширением COVER
+        let llp_packet = if llp_core::cover::is_cover(ip_packet) {
+            let mut extensions = Extensions::new();
+            extensions.insert(Extension::cover())?;
+            session.seal_packet_with_extensions(PacketFlags::CONTROL, extensions, ip_packet)?
+        } else {
+            session.seal_packet(PacketFlags::DATA, ip_packet)?
         };
 
-        let (encrypted_payload, _) = session.encrypt_payload(ip_packet, &aad)?;
+        Ok(llp_packet.serialize()?)
+    }
 
-        // Создание LLP пакета
-        let llp_packet = LlpPacket::new(
-            header,
-            Bytes::from(encrypted_payload),
-            Bytes::new(), // TODO: Добавить padding
-            [0u8; 16],    // TODO: Вычислить auth tag
-        )?;
+    /// Получить IP пакет от сервера
+    pub async fn receive_packet(&mut self) -> Result<Bytes> {
+        loop {
+            if let Some(packet) = self.take_received().await? {
+                return Ok(packet);
+            }
+            self.send_pending().await?;
+            self.read_incoming().await?;
+        }
+    }
 
-        // Сериализация
-        let serialized = llp_packet.serialize()?;
+    /// Отправить записи, которые нельзя прерывать: опрос сервера, pong и
+    /// кадры HTTP/2
+    ///
+    /// Оборванная на середине запись ломает поток, поэтому в цикле с
+    /// `tokio::select!` этот метод вызывается до `select!`, а в ветке
+    /// остаётся только [`Self::read_incoming`].
+    pub async fn send_pending(&mut self) -> Result<()> {
+        let wrapper = self.wrapper.as_mut().ok_or("Нет wrapper")?;
+        let stream = self.stream.as_mut().ok_or("Нет подключения")?;
 
-        // Обёртывание в мимикрию
-        let wrapped = wrapper.wrap(&serialized)?;
+        if !self.outgoing.is_empty() {
+            stream.write_all(&self.outgoing).await?;
+            stream.flush().await?;
+            self.outgoing.clear();
+        }
 
-        // Отправка
-        stream.write_u32(wrapped.len() as u32).await?;
-        stream.write_all(&wrapped).await?;
-        stream.flush().await?;
+        // Подтверждения и новые GET запросы, накопленные при чтении
+        if let Some(session) = self.h2.as_mut() {
+            h2::write_output(stream, session).await?;
+            return Ok(());
+        }
 
-        debug!("→ Отправлен пакет: {} байт", wrapped.len());
+        // Сервер отвечает только на запросы: без открытого запроса
+        // отправляем опрос без payload
+        if self.websocket.is_none() && self.pending.is_empty() && wrapper.needs_poll() {
+            let poll = wrapper.poll_request()?;
+            codec::write_frame(stream, wrapper.is_http(), &poll).await?;
+        }
 
         Ok(())
     }
 
-    /// Получить IP пакет от сервера
-    pub async fn receive_packet(&mut self) -> Result<Bytes> {
-        let session = self.session.as_mut().ok_or("Нет активной сессии")?;
-        let wrapper = self.wrapper.as_ref().ok_or("Нет wrapper")?;
+    /// Прочитать одно сообщение сервера или часть потокового ответа
+    ///
+    /// Извлечённые пакеты забирает [`Self::take_received`]. Метод только
+    /// читает из потока и безопасен для отмены в `tokio::select!`; если
+    /// пакеты уже прочитаны, он завершается сразу.
+    pub async fn read_incoming(&mut self) -> Result<()> {
+        if !self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let wrapper = self.wrapper.as_mut().ok_or("Нет wrapper")?;
         let stream = self.stream.as_mut().ok_or("Нет подключения")?;
 
-        // Чтение размера
-        let len = stream.read_u32().await? as usize;
+        // Pong не пишется здесь, а ждёт send_pending
+        if let Some(websocket) = self.websocket.as_mut() {
+            loop {
+                match websocket.decode()? {
+                    Some(ws::Message::Binary(message)) => {
+                        self.pending.push_back(message);
+                        return Ok(());
+                    }
+                    Some(ws::Message::Ping(payload)) => {
+                        self.outgoing.extend_from_slice(&websocket.pong(&payload));
+                    }
+                    Some(ws::Message::Pong(_)) => {}
+                    Some(ws::Message::Close(_)) => {
+                        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+                    }
+                    None => {
+                        let mut buf = BytesMut::with_capacity(16 * 1024);
+                        if stream.read_buf(&mut buf).await? == 0 {
+                            return Err(
+                                std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()
+                            );
+                        }
+                        websocket.extend(&buf);
+                    }
+                }
+            }
+        }
 
-        if len > 65536 {
-            return Err("Пакет слишком большой".into());
+        // Ответные кадры HTTP/2 остаются в сессии до send_pending
+        if let Some(session) = self.h2.as_mut() {
+            let mut buf = BytesMut::with_capacity(16 * 1024);
+            if stream.read_buf(&mut buf).await? == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+            self.pending.extend(session.receive(&buf)?);
+            return Ok(());
         }
 
-        // Чтение данных
-        let mut buf = vec![0u8; len];
-        stream.read_exact(&mut buf).await?;
+        // Чтение сообщения или части потокового ответа
+        let frame = codec::read_stream_frame(stream, &mut self.decoder, wrapper.is_http()).await?;
+        self.pending.extend(wrapper.unwrap_frame(frame)?);
+        Ok(())
+    }
 
-        debug!("← Получен пакет: {} байт", len);
+    /// Расшифровать следующий пакет, прочитанный [`Self::read_incoming`]
+    ///
+    /// `Ok(None)` — прочитанных пакетов нет. Пустой пакет — фиктивный
+    /// пакет или warning alert, данных для TUN нет.
+    pub async fn take_received(&mut self) -> Result<Option<Bytes>> {
+        let Some(unwrapped) = self.pending.pop_front() else {
+            return Ok(None);
+        };
+        let session = self.session.as_mut().ok_or("Нет активной сессии")?;
 
-        // Распаковка мимикрии
-        let unwrapped = wrapper.unwrap(&buf)?;
+        debug!("← Получен пакет: {} байт", unwrapped.len());
 
         // Десериализация LLP пакета
         let llp_packet = LlpPacket::deserialize(&unwrapped)?;
 
         // Расшифровка
-        let aad = {
-            let mut buf = bytes::BytesMut::new();
-            llp_packet.header.serialize(&mut buf);
-            buf.freeze()
-        };
+        let plaintext = session.open_packet(&llp_packet)?;
 
-        let plaintext = session.decrypt_payload(
-            &llp_packet.encrypted_payload,
-            &aad,
-            llp_packet.header.sequence_number,
-        )?;
+        // Фиктивный пакет cover traffic: данных для TUN нет
+        if llp_packet.is_cover() {
+            debug!("← Отброшен фиктивный пакет: {} байт", plaintext.len());
+            return Ok(Some(Bytes::new()));
+        }
+
+        // Alert от сервера: fatal закрывает сессию, warning только логируется
+        match session.process_alert(llp_packet.header.flags, &plaintext) {
+            Ok(Some(alert)) => {
+                warn!("Получен {} от сервера", alert);
+                Ok(Some(Bytes::new()))
+            }
+            Ok(None) => Ok(Some(Bytes::from(plaintext))),
+            Err(e) => {
+                if let Some(code) = peer_alert_code(&e) {
+                    warn!("Сервер закрыл сессию: {}", code);
+                    self.info.write().await.last_alert = Some(code);
+                    self.session = None;
+                }
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Штатно закрыть сессию, отправив серверу close_notify
+    pub async fn close(&mut self) -> Result<()> {
+        if let (Some(session), Some(wrapper), Some(stream)) = (
+            self.session.as_mut(),
+            self.wrapper.as_mut(),
+            self.stream.as_mut(),
+        ) {
+            let packet = session.seal_alert(&Alert::close_notify())?;
+
+            match (self.websocket.as_mut(), self.h2.as_mut()) {
+                (Some(websocket), _) => {
+                    ws::write_message(stream, websocket, &packet.serialize()?).await?;
+                    stream
+                        .write_all(&websocket.close(ws::close_code::NORMAL))
+                        .await?;
+                    stream.flush().await?;
+                }
+                (None, Some(session)) => {
+                    session.send(&packet.serialize()?)?;
+                    session.close();
+                    h2::write_output(stream, session).await?;
+                }
+                (None, None) => {
+                    let wrapped = wrapper.wrap(&packet.serialize()?)?;
+                    codec::write_frame(stream, wrapper.is_http(), &wrapped).await?;
+                }
+            }
+
+            debug!("→ Отправлен close_notify");
+        }
+
+        self.stream = None;
+        self.websocket = None;
+        self.h2 = None;
+        self.session = None;
+        self.wrapper = None;
+        self.set_state(ConnectionState::Disconnected).await;
 
-        Ok(Bytes::from(plaintext))
+        Ok(())
     }
 
     /// Отправить keepalive
     pub async fn send_keepalive(&mut self) -> Result<()> {
-        let session = self.session.as_ref().ok_or("Нет активной сессии")?;
+        let session = self.session.as_mut().ok_or("Нет активной сессии")?;
+        let wrapper = self.wrapper.as_mut().ok_or("Нет wrapper")?;
         let stream = self.stream.as_mut().ok_or("Нет подключения")?;
 
-        let header = PacketHeader::new(
-            PacketFlags::KEEPALIVE,
-            session.session_id(),
-            session.current_tx_sequence(),
-            session.mimicry_profile(),
-        );
+        // По WebSocket keepalive — ping, на который сервер отвечает pong
+        if let Some(websocket) = self.websocket.as_mut() {
+            stream.write_all(&websocket.ping(&[])).await?;
+            stream.flush().await?;
+            debug!("→ Отправлен WebSocket ping");
+            return Ok(());
+        }
 
-        // Отправка пустого keepalive пакета
-        // TODO: Реализовать полностью
+        // Пустой KEEPALIVE пакет в запросе мимикрии или POST запросе HTTP/2
+        let packet = session.seal_packet(PacketFlags::KEEPALIVE, &[])?;
+        if let Some(session) = self.h2.as_mut() {
+            h2::write_message(stream, session, &packet.serialize()?).await?;
+            debug!("→ Отправлен keepalive (HTTP/2)");
+            return Ok(());
+        }
+        let wrapped = wrapper.wrap(&packet.serialize()?)?;
+        codec::write_frame(stream, wrapper.is_http(), &wrapped).await?;
 
         debug!("→ Отправлен keepalive");
 
         Ok(())
     }
 
+    /// Идёт ли обмен по HTTP/2 (сервер согласовал ALPN `h2`)
+    pub fn is_http2(&self) -> bool {
+        self.h2.is_some()
+    }
+
     /// Проверить, подключено ли
     pub async fn is_connected(&self) -> bool {
         let info = self.info.read().await;
         info.state == ConnectionState::Connected
     }
 
+    /// Поднять внешний TLS слой, если он включён
+    ///
+    /// SNI, ALPN и порядок cipher suites и групп ClientHello берутся из
+    /// профиля мимикрии конфигурации (SNI можно переопределить в `[tls]`).
+    /// С `tls.http2` первым в ALPN предлагается `h2`.
+    async fn open_transport(&self, stream: TcpStream) -> Result<Transport> {
+        let profile = PacketWrapper::try_new(self.config.parse_mimicry_profile()?)?;
+        let mut alpn = profile.alpn_protocols();
+        if self.config.tls.http2 && !alpn.iter().any(|protocol| protocol == h2::ALPN_H2) {
+            alpn.insert(0, h2::ALPN_H2.to_vec());
+        }
+        let Some(tls_config) = self
+            .config
+            .tls_client_config(alpn, profile.tls_fingerprint())?
+        else {
+            return Ok(Transport::Plain(stream));
+        };
+
+        let server_name = self
+            .config
+            .tls
+            .server_name
+            .as_deref()
+            .or(profile.tls_server_name())
+            .ok_or("Профиль мимикрии не задаёт SNI, укажите tls.server_name")?;
+
+        let transport = tokio::time::timeout(
+            self.config.connection_timeout(),
+            tls::connect(stream, tls_config, server_name),
+        )
+        .await??;
+
+        info!(
+            "✓ TLS 1.3 установлен: SNI {}, порядок шифров {}",
+            server_name,
+            profile.tls_fingerprint().name()
+        );
+        Ok(transport)
+    }
+
+    /// Открыть WebSocket запросом HTTP Upgrade с заголовками профиля
+    ///
+    /// Токен доступа передаётся подпротоколом в `Sec-WebSocket-Protocol`.
+    async fn upgrade(
+        &self,
+        stream: &mut Transport,
+        token: Option<&AccessToken>,
+    ) -> Result<WsCodec> {
+        let mut wrapper = PacketWrapper::try_new(self.config.parse_mimicry_profile()?)?;
+        let key = ws::generate_key(&mut wrapper.child_rng());
+        let protocol = token.map(|token| ws::encode_protocol(token));
+        let request = ws::upgrade_request(
+            &mut wrapper,
+            self.config.websocket.host.as_deref(),
+            &self.config.websocket.path,
+            &key,
+            protocol.as_deref(),
+        )?;
+        stream.write_all(&request).await?;
+        stream.flush().await?;
+
+        let mut buf = BytesMut::new();
+        let head_len = tokio::time::timeout(
+            self.config.connection_timeout(),
+            ws::read_head(stream, &mut buf, ws::MAX_HEAD_SIZE),
+        )
+        .await??
+        .ok_or("Сервер не ответил на WebSocket Upgrade")?;
+        ws::check_upgrade_response(&buf[..head_len], &key)?;
+
+        info!("✓ WebSocket открыт: {}", self.config.websocket.path);
+
+        // Маски кадров — из того же источника случайности, что и заголовки
+        let mut codec = WsCodec::client().with_rng(wrapper.child_rng());
+        codec.extend(&buf[head_len..]);
+        Ok(codec)
+    }
+
     /// Выполнить handshake с сервером
     async fn perform_handshake(
         &mut self,
     ) -> Result<(u64, MimicryProfile, llp_core::crypto::SessionKey)> {
         let mut rng = OsRng;
         let stream = self.stream.as_mut().ok_or("Нет подключения")?;
+        let websocket = &mut self.websocket;
 
         let mimicry_profile = self.config.parse_mimicry_profile()?;
 
+        // Handshake идёт в запросах мимикрии, как и данные: на проводе
+        // HTTP с первого сообщения
+        let mut wrapper = PacketWrapper::try_new(mimicry_profile)?.with_role(Role::Client);
+        let mut framing = Framing {
+            websocket: websocket.as_mut(),
+            h2: self.h2.as_mut(),
+            received: &mut self.pending,
+            wrapper: &mut wrapper,
+            decoder: HttpDecoder::new(),
+        };
+
         // Создание client handshake
         let mut client_handshake = ClientHandshake::new(&mut rng, mimicry_profile);
 
         // 1. Отправка CLIENT_HELLO
         let client_hello = client_handshake.start(&mut rng)?;
-        stream.write_u32(client_hello.len() as u32).await?;
-        stream.write_all(&client_hello).await?;
-        stream.flush().await?;
+        framing.write_message(stream, &client_hello).await?;
 
         debug!("→ Отправлен CLIENT_HELLO ({} байт)", client_hello.len());
 
         // 2. Получение SERVER_HELLO
-        let server_hello_len = stream.read_u32().await? as usize;
-        if server_hello_len > 4096 {
-            return Err("SERVER_HELLO слишком большой".into());
-        }
-
-        let mut server_hello_buf = vec![0u8; server_hello_len];
-        stream.read_exact(&mut server_hello_buf).await?;
+        let server_hello_buf = framing
+            .read_message(stream, 4096)
+            .await
+            .map_err(|e| format!("SERVER_HELLO: {}", e))?;
 
-        debug!("← Получен SERVER_HELLO ({} байт)", server_hello_len);
+        debug!("← Получен SERVER_HELLO ({} байт)", server_hello_buf.len());
 
         let session_id = client_handshake.process_server_hello(&server_hello_buf)?;
 
         // 3. Отправка CLIENT_VERIFY
         let client_verify = client_handshake.send_client_verify()?;
-        stream.write_u32(client_verify.len() as u32).await?;
-        stream.write_all(&client_verify).await?;
-        stream.flush().await?;
+        framing.write_message(stream, &client_verify).await?;
 
         debug!("→ Отправлен CLIENT_VERIFY ({} байт)", client_verify.len());
 
         // 4. Получение SERVER_VERIFY
-        let server_verify_len = stream.read_u32().await? as usize;
-        if server_verify_len > 1024 {
-            return Err("SERVER_VERIFY слишком большой".into());
-        }
-
-        let mut server_verify_buf = vec![0u8; server_verify_len];
-        stream.read_exact(&mut server_verify_buf).await?;
+        let server_verify_buf = framing
+            .read_message(stream, 1024)
+            .await
+            .map_err(|e| format!("SERVER_VERIFY: {}", e))?;
 
-        debug!("← Получен SERVER_VERIFY ({} байт)", server_verify_len);
+        debug!("← Получен SERVER_VERIFY ({} байт)", server_verify_buf.len());
 
         client_handshake.process_server_verify(&server_verify_buf)?;
 
@@ -362,6 +743,61 @@ impl ServerConnection {
     }
 }
 
+/// Разметка сообщений handshake: сообщения WebSocket, HTTP/2 или мимикрии
+struct Framing<'a> {
+    /// Кадры WebSocket (None — сообщения мимикрии)
+    websocket: Option<&'a mut WsCodec>,
+    /// HTTP/2 соединение (None — сообщения мимикрии)
+    h2: Option<&'a mut H2Session>,
+    /// Пакеты HTTP/2, прочитанные вместе с сообщением handshake; после
+    /// handshake — очередь пакетов подключения
+    received: &'a mut VecDeque<Bytes>,
+    /// Обёртка профиля в роли клиента
+    wrapper: &'a mut PacketWrapper,
+    /// Декодер ответов сервера
+    decoder: HttpDecoder,
+}
+
+impl Framing<'_> {
+    /// Записать сообщение handshake в запросе мимикрии, POST запросе HTTP/2
+    /// или в сообщении WebSocket
+    async fn write_message(&mut self, stream: &mut Transport, message: &[u8]) -> Result<()> {
+        if let Some(websocket) = self.websocket.as_mut() {
+            ws::write_message(stream, websocket, message).await?;
+        } else if let Some(session) = self.h2.as_mut() {
+            h2::write_message(stream, session, message).await?;
+        } else {
+            let request = self.wrapper.wrap(message)?;
+            codec::write_frame(stream, self.wrapper.is_http(), &request).await?;
+        }
+        Ok(())
+    }
+
+    /// Прочитать сообщение handshake не длиннее `max` байт
+    async fn read_message(&mut self, stream: &mut Transport, max: usize) -> Result<Vec<u8>> {
+        let message = if let Some(websocket) = self.websocket.as_mut() {
+            ws::read_message(stream, websocket).await?
+        } else if let Some(session) = self.h2.as_mut() {
+            // Данные сервера могут прийти вместе с SERVER_VERIFY
+            loop {
+                if let Some(message) = self.received.pop_front() {
+                    break message;
+                }
+                self.received
+                    .extend(h2::read_messages(stream, session).await?);
+            }
+        } else {
+            let http = self.wrapper.is_http();
+            let response = codec::read_frame(stream, &mut self.decoder, http).await?;
+            self.wrapper.unwrap(&response)?
+        };
+        if message.len() > max {
+            return Err(format!("сообщение слишком большое: {} байт", message.len()).into());
+        }
+        Ok(message.to_vec())
+    }
+}
+
 impl Drop for ServerConnection {
     fn drop(&mut self) {
         if self.stream.is_some() {
@@ -390,9 +826,26 @@ mod tests {
             session_id: None,
             mimicry_profile: MimicryProfile::None,
             reconnect_attempts: 0,
+            last_alert: None,
         };
 
         assert_eq!(info.state, ConnectionState::Disconnected);
         assert!(info.session_id.is_none());
     }
+
+    #[test]
+    fn test_peer_alert_code() {
+        let closed: Box<dyn std::error::Error + Send + Sync> = Box::new(LlpError::from(
+            SessionError::ClosedByPeer {
+                session_id: 1,
+                code: AlertCode::ServerShutdown,
+            },
+        ));
+        assert_eq!(peer_alert_code(&*closed), Some(AlertCode::ServerShutdown));
+
+        // Локальные ошибки не считаются alert от сервера
+        let local: Box<dyn std::error::Error + Send + Sync> =
+            Box::new(LlpError::from(SessionError::SessionExpired { session_id: 1 }));
+        assert_eq!(peer_alert_code(&*local), None);
+    }
 }
@@ -15,9 +15,11 @@ pub use config::ClientConfig;
 pub use connection::{ConnectionInfo, ConnectionState, ServerConnection};
 pub use tunnel::TunInterface;
 
+use bytes::Bytes;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::RwLock;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
@@ -98,22 +100,43 @@ impl VpnClient {
             }
         });
 
+        // Отправка пачками по паттерну профиля мимикрии
+        let mut shaper = self.config.traffic_shaper()?;
+        if let Some(shaper) = &shaper {
+            info!(
+                "Планировщик отправки включён: задержка до {} мс",
+                shaper.options().max_latency.as_millis()
+            );
+        }
+
+        // Фиктивный трафик в простое по кривой битрейта профиля
+        let mut cover = self.config.cover_traffic()?;
+        if cover.is_some() {
+            info!("Cover traffic включён");
+        }
+
         // Основной цикл
         loop {
+            // Опрос сервера и pong пишутся до select!: оборванная другой
+            // веткой запись сломала бы поток
+            Self::send_pending(&connection).await?;
+
             tokio::select! {
                 // Чтение из TUN → отправка на сервер
                 result = tunnel.read_packet() => {
                     match result {
                         Ok(packet) => {
-                            let mut conn = connection.write().await;
-                            if let Err(e) = conn.send_packet(&packet).await {
-                                error!("Ошибка отправки пакета: {}", e);
-
-                                // Попытка переподключения
-                                if let Err(e) = conn.reconnect().await {
-                                    error!("Не удалось переподключиться: {}", e);
-                                    return Err(e);
-                                }
+                            if let Some(cover) = cover.as_mut() {
+                                cover.record_activity(Instant::now());
+                            }
+
+                            // Пакет ждёт своей пачки, если не идёт в обход очереди
+                            let packet = match shaper.as_mut() {
+                                Some(shaper) => shaper.push(packet, Instant::now()),
+                                None => Some(packet),
+                            };
+                            if let Some(packet) = packet {
+                                Self::send_packets(&connection, &[packet]).await?;
                             }
                         }
                         Err(e) => {
@@ -123,13 +146,60 @@ impl VpnClient {
                     }
                 }
 
-                // Получение от сервера → запись в TUN
+                // Очередь планировщика → отправка на сервер
+                _ = async {
+                    match &shaper {
+                        Some(shaper) => shaper.wait().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    // Пачка целиком, чтобы агрегация могла объединить пакеты
+                    if let Some(shaper) = shaper.as_mut() {
+                        let burst: Vec<_> =
+                            std::iter::from_fn(|| shaper.poll(Instant::now())).collect();
+                        Self::send_packets(&connection, &burst).await?;
+                    }
+                }
+
+                // Простой → фиктивные пакеты на сервер
+                _ = async {
+                    match &cover {
+                        Some(cover) => cover.wait().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    if let Some(cover) = cover.as_mut() {
+                        let mut ready = Vec::new();
+                        while let Some(len) = cover.poll(Instant::now()) {
+                            let packet = llp_core::cover::payload(len);
+                            let packet = match shaper.as_mut() {
+                                Some(shaper) => shaper.push(packet, Instant::now()),
+                                None => Some(packet),
+                            };
+                            ready.extend(packet);
+                        }
+                        Self::send_packets(&connection, &ready).await?;
+                    }
+                }
+
+                // Получение от сервера → запись в TUN; ветка только читает
                 result = async {
                     let mut conn = connection.write().await;
-                    conn.receive_packet().await
+                    conn.read_incoming().await
                 } => {
+                    let result = match result {
+                        Ok(()) => connection.write().await.take_received().await,
+                        Err(e) => Err(e),
+                    };
                     match result {
-                        Ok(packet) => {
+                        // Ответ без пакетов: следующий опрос уйдёт до select!
+                        Ok(None) => {}
+                        // Warning alert или фиктивный пакет — данных для TUN нет
+                        Ok(Some(packet)) if packet.is_empty() => {}
+                        Ok(Some(packet)) => {
+                            if let Some(cover) = cover.as_mut() {
+                                cover.record_activity(Instant::now());
+                            }
                             if let Err(e) = tunnel.write_packet(&packet).await {
                                 error!("Ошибка записи в TUN: {}", e);
                             }
@@ -137,7 +207,7 @@ impl VpnClient {
                         Err(e) => {
                             error!("Ошибка получения пакета: {}", e);
 
-                            // Попытка переподключения
+                            // Попытка переподключения (с учётом alert от сервера)
                             let mut conn = connection.write().await;
                             if let Err(e) = conn.reconnect().await {
                                 error!("Не удалось переподключиться: {}", e);
@@ -150,6 +220,39 @@ impl VpnClient {
         }
     }
 
+    /// Отправить IP пакеты на сервер, переподключившись при ошибке
+    async fn send_packets(connection: &RwLock<ServerConnection>, packets: &[Bytes]) -> Result<()> {
+        if packets.is_empty() {
+            return Ok(());
+        }
+        let mut conn = connection.write().await;
+        if let Err(e) = conn.send_packets(packets).await {
+            error!("Ошибка отправки пакета: {}", e);
+
+            // Попытка переподключения
+            if let Err(e) = conn.reconnect().await {
+                error!("Не удалось переподключиться: {}", e);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Отправить опрос сервера и pong, переподключившись при ошибке
+    async fn send_pending(connection: &RwLock<ServerConnection>) -> Result<()> {
+        let mut conn = connection.write().await;
+        if let Err(e) = conn.send_pending().await {
+            error!("Ошибка отправки опроса: {}", e);
+
+            // Попытка переподключения (с учётом alert от сервера)
+            if let Err(e) = conn.reconnect().await {
+                error!("Не удалось переподключиться: {}", e);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
     /// Получить информацию о подключении
     pub fn connection_info(&self) -> Arc<RwLock<ConnectionInfo>> {
         let conn_lock = self.connection.blocking_read();
@@ -160,6 +263,11 @@ impl VpnClient {
     pub async fn disconnect(&mut self) -> Result<()> {
         info!("Отключение от VPN...");
 
+        // Уведомляем сервер о штатном закрытии сессии
+        if let Err(e) = self.connection.write().await.close().await {
+            warn!("Не удалось отправить close_notify: {}", e);
+        }
+
         self.tunnel = None;
         self.connection = Arc::new(RwLock::new(ServerConnection::new(Arc::clone(
             &self.config,
@@ -137,6 +137,25 @@ async fn main() {
     info!("Конфигурация:");
     info!("  • Сервер: {}", config.server_address());
     info!("  • Профиль мимикрии: {}", config.security.mimicry_profile);
+
+    // Шаблоны профилей мимикрии перечитываются на лету
+    match config.load_profile_templates() {
+        Ok(Some(templates)) => {
+            info!("  • Шаблоны профилей: {}", templates.path().display());
+            if let Some(interval) = config.profile_reload_interval() {
+                templates.spawn_reloader(interval);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("Ошибка загрузки шаблонов профилей: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Err(e) = config.parse_mimicry_profile() {
+        error!("{}", e);
+        std::process::exit(1);
+    }
     info!("  • TUN интерфейс: {}", config.vpn.tun_name);
     info!("  • MTU: {}", config.vpn.mtu);
 
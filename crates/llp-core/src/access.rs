@@ -0,0 +1,226 @@
+//! Токен доступа: аутентификация подключения в первых байтах
+//!
+//! Клиент и сервер знают общий ключ доступа ([`AccessKey`]). Первые
+//! [`ACCESS_TOKEN_SIZE`] байт потока клиента — токен:
+//!
+//! ```text
+//! nonce (16) || timestamp (u64 BE, секунды) || HMAC-SHA256(key, context || nonce || timestamp)[..16]
+//! ```
+//!
+//! Без ключа токен неотличим от случайных байт, поэтому сервер может
+//! отдать любое подключение без верного токена обычному веб-серверу, не
+//! раскрывая LLP. Повтор подсмотренного токена отсекает [`ReplayFilter`].
+
+use rand::{CryptoRng, RngCore};
+use std::collections::HashMap;
+use zeroize::ZeroizeOnDrop;
+
+use crate::crypto::{hmac_sha256, random_array};
+
+/// Размер ключа доступа
+pub const ACCESS_KEY_SIZE: usize = 32;
+
+/// Размер nonce токена
+pub const ACCESS_NONCE_SIZE: usize = 16;
+
+/// Размер тега токена
+const ACCESS_TAG_SIZE: usize = 16;
+
+/// Размер токена доступа
+pub const ACCESS_TOKEN_SIZE: usize = ACCESS_NONCE_SIZE + 8 + ACCESS_TAG_SIZE;
+
+/// Допустимое расхождение времени клиента и сервера по умолчанию (секунды)
+pub const DEFAULT_ACCESS_DRIFT_SECS: u64 = 120;
+
+/// Контекст HMAC токена
+const ACCESS_CONTEXT: &[u8] = b"llp-access-v1";
+
+/// Токен доступа
+pub type AccessToken = [u8; ACCESS_TOKEN_SIZE];
+
+/// Общий ключ доступа клиента и сервера (автоматически зануляется)
+#[derive(Clone, ZeroizeOnDrop)]
+pub struct AccessKey([u8; ACCESS_KEY_SIZE]);
+
+impl AccessKey {
+    /// Ключ из байтов
+    pub fn from_bytes(bytes: [u8; ACCESS_KEY_SIZE]) -> Self {
+        Self(bytes)
+    }
+
+    /// Ключ из hex строки (64 символа)
+    pub fn from_hex(value: &str) -> Option<Self> {
+        let bytes = hex::decode(value.trim()).ok()?;
+        Some(Self(bytes.try_into().ok()?))
+    }
+
+    /// Сгенерировать случайный ключ
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self(random_array(rng))
+    }
+
+    /// Hex представление ключа (для конфигурации)
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    /// Выпустить токен для момента `unix_secs`
+    pub fn token<R: RngCore + CryptoRng>(&self, rng: &mut R, unix_secs: u64) -> AccessToken {
+        let nonce: [u8; ACCESS_NONCE_SIZE] = random_array(rng);
+        let mut token = [0u8; ACCESS_TOKEN_SIZE];
+        token[..ACCESS_NONCE_SIZE].copy_from_slice(&nonce);
+        token[ACCESS_NONCE_SIZE..ACCESS_NONCE_SIZE + 8].copy_from_slice(&unix_secs.to_be_bytes());
+        let tag = self.tag(&token[..ACCESS_NONCE_SIZE + 8]);
+        token[ACCESS_NONCE_SIZE + 8..].copy_from_slice(&tag);
+        token
+    }
+
+    /// Проверить токен: тег и расхождение времени не больше `max_drift_secs`
+    ///
+    /// Возвращает nonce и timestamp токена для [`ReplayFilter`].
+    pub fn verify(
+        &self,
+        token: &[u8],
+        unix_secs: u64,
+        max_drift_secs: u64,
+    ) -> Option<([u8; ACCESS_NONCE_SIZE], u64)> {
+        if token.len() != ACCESS_TOKEN_SIZE {
+            return None;
+        }
+        let (signed, tag) = token.split_at(ACCESS_NONCE_SIZE + 8);
+        let expected = self.tag(signed);
+        // Сравнение без раннего выхода
+        let diff = expected
+            .iter()
+            .zip(tag)
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        if diff != 0 {
+            return None;
+        }
+
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(&signed[ACCESS_NONCE_SIZE..]);
+        let timestamp = u64::from_be_bytes(timestamp);
+        if timestamp.abs_diff(unix_secs) > max_drift_secs {
+            return None;
+        }
+
+        let mut nonce = [0u8; ACCESS_NONCE_SIZE];
+        nonce.copy_from_slice(&signed[..ACCESS_NONCE_SIZE]);
+        Some((nonce, timestamp))
+    }
+
+    fn tag(&self, signed: &[u8]) -> [u8; ACCESS_TAG_SIZE] {
+        let mut data = Vec::with_capacity(ACCESS_CONTEXT.len() + signed.len());
+        data.extend_from_slice(ACCESS_CONTEXT);
+        data.extend_from_slice(signed);
+        let mac = hmac_sha256(&self.0, &data);
+        let mut tag = [0u8; ACCESS_TAG_SIZE];
+        tag.copy_from_slice(&mac[..ACCESS_TAG_SIZE]);
+        tag
+    }
+}
+
+impl std::fmt::Debug for AccessKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AccessKey(..)")
+    }
+}
+
+/// Фильтр повторов токенов доступа
+///
+/// Помнит nonce принятых токенов, пока их timestamp не выйдет за окно
+/// допустимого расхождения времени: более старый токен отклонит уже
+/// [`AccessKey::verify`].
+#[derive(Debug)]
+pub struct ReplayFilter {
+    seen: HashMap<[u8; ACCESS_NONCE_SIZE], u64>,
+    max_drift_secs: u64,
+}
+
+impl ReplayFilter {
+    /// Фильтр для окна `max_drift_secs`
+    pub fn new(max_drift_secs: u64) -> Self {
+        Self {
+            seen: HashMap::new(),
+            max_drift_secs,
+        }
+    }
+
+    /// Отметить nonce; `false`, если он уже встречался
+    pub fn check(
+        &mut self,
+        nonce: [u8; ACCESS_NONCE_SIZE],
+        timestamp: u64,
+        unix_secs: u64,
+    ) -> bool {
+        let window = self.max_drift_secs;
+        self.seen
+            .retain(|_, seen| seen.saturating_add(window) >= unix_secs);
+        self.seen.insert(nonce, timestamp).is_none()
+    }
+
+    /// Количество запомненных nonce
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Пуст ли фильтр
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_token_round_trip() {
+        let key = AccessKey::generate(&mut OsRng);
+        let token = key.token(&mut OsRng, 1_700_000_000);
+
+        let (_, timestamp) = key.verify(&token, 1_700_000_050, 120).unwrap();
+        assert_eq!(timestamp, 1_700_000_000);
+        assert!(key.verify(&token, 1_700_000_500, 120).is_none());
+
+        let other = AccessKey::generate(&mut OsRng);
+        assert!(other.verify(&token, 1_700_000_000, 120).is_none());
+
+        let mut tampered = token;
+        tampered[ACCESS_NONCE_SIZE] ^= 1;
+        assert!(key.verify(&tampered, 1_700_000_000, 120).is_none());
+        assert!(key
+            .verify(b"GET / HTTP/1.1\r\nHost: vkvideo.ru\r\n\r\n", 0, 120)
+            .is_none());
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let key = AccessKey::generate(&mut OsRng);
+        let parsed = AccessKey::from_hex(&key.to_hex()).unwrap();
+        let token = key.token(&mut OsRng, 10);
+        assert!(parsed.verify(&token, 10, 0).is_some());
+
+        assert!(AccessKey::from_hex("abcd").is_none());
+        assert!(AccessKey::from_hex(&"zz".repeat(32)).is_none());
+    }
+
+    #[test]
+    fn test_replay_filter() {
+        let key = AccessKey::generate(&mut OsRng);
+        let token = key.token(&mut OsRng, 1000);
+        let (nonce, timestamp) = key.verify(&token, 1000, 120).unwrap();
+
+        let mut filter = ReplayFilter::new(120);
+        assert!(filter.check(nonce, timestamp, 1000));
+        assert!(!filter.check(nonce, timestamp, 1001));
+
+        // Вне окна nonce забывается
+        let fresh = key.token(&mut OsRng, 2000);
+        let (fresh_nonce, fresh_ts) = key.verify(&fresh, 2000, 120).unwrap();
+        assert!(filter.check(fresh_nonce, fresh_ts, 2000));
+        assert_eq!(filter.len(), 1);
+    }
+}
@@ -0,0 +1,424 @@
+//! Alert и close сообщения протокола LLP
+//!
+//! Alert сообщает другой стороне причину отказа или закрытия сессии.
+//! Каждой причине соответствует стабильный числовой код [`AlertCode`],
+//! который не меняется между версиями и может использоваться клиентом
+//! для выбора реакции (переподключиться, подождать или сдаться).
+//!
+//! Alert передаётся только внутри зашифрованного канала сессии
+//! (ChaCha20-Poly1305), поэтому его нельзя подделать без сессионного ключа.
+//! Ошибки до завершения handshake alert не порождают: ключа ещё нет,
+//! а неаутентифицированный ответ только помогает активному зондированию.
+//!
+//! Формат сообщения (plaintext внутри зашифрованного payload):
+//! ```text
+//! ┌──────────────┬──────────────┬──────────────────────────────┐
+//! │ Type (8)=0x15│  Level (8)   │         Code (16)            │
+//! ├──────────────┴──────────────┼──────────────────────────────┤
+//! │     Reason Length (16)      │   Reason (UTF-8, 0-256)      │
+//! └─────────────────────────────┴──────────────────────────────┘
+//! ```
+//!
+//! Старший полубайт типа (`0x1`) не совпадает с версией IPv4/IPv6,
+//! поэтому alert можно отличить от туннелируемого IP пакета.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::fmt;
+
+use crate::error::{CryptoError, HandshakeError, LlpError, PacketError, Result, SessionError};
+
+/// Тип управляющего сообщения «alert»
+pub const ALERT_CONTROL_TYPE: u8 = 0x15;
+
+/// Размер фиксированной части alert сообщения
+pub const ALERT_HEADER_SIZE: usize = 6; // 1 + 1 + 2 + 2
+
+/// Максимальная длина текстового пояснения (байт)
+pub const MAX_ALERT_REASON_SIZE: usize = 256;
+
+/// Стабильные коды причин alert
+///
+/// Значения являются частью wire-формата и не должны переиспользоваться.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum AlertCode {
+    /// Штатное закрытие сессии
+    CloseNotify = 0,
+    /// Сессия истекла
+    SessionExpired = 1,
+    /// Превышен лимит сессий на сервере
+    TooManySessions = 2,
+    /// Пакеты не проходят аутентификацию
+    AuthenticationFailed = 3,
+    /// Неподдерживаемая версия протокола
+    UnsupportedVersion = 4,
+    /// Сервер завершает работу
+    ServerShutdown = 5,
+    /// Не получен keepalive
+    KeepaliveTimeout = 6,
+    /// Повтор или устаревший sequence number
+    ReplayDetected = 7,
+    /// Timestamp пакета вне допустимого окна
+    InvalidTimestamp = 8,
+    /// Требуется rekey
+    RekeyRequired = 9,
+    /// Ошибка handshake
+    HandshakeFailed = 10,
+    /// Нарушение формата протокола
+    ProtocolError = 11,
+    /// Внутренняя ошибка
+    InternalError = 255,
+}
+
+impl AlertCode {
+    /// Преобразование из u16
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(AlertCode::CloseNotify),
+            1 => Some(AlertCode::SessionExpired),
+            2 => Some(AlertCode::TooManySessions),
+            3 => Some(AlertCode::AuthenticationFailed),
+            4 => Some(AlertCode::UnsupportedVersion),
+            5 => Some(AlertCode::ServerShutdown),
+            6 => Some(AlertCode::KeepaliveTimeout),
+            7 => Some(AlertCode::ReplayDetected),
+            8 => Some(AlertCode::InvalidTimestamp),
+            9 => Some(AlertCode::RekeyRequired),
+            10 => Some(AlertCode::HandshakeFailed),
+            11 => Some(AlertCode::ProtocolError),
+            255 => Some(AlertCode::InternalError),
+            _ => None,
+        }
+    }
+
+    /// Преобразование в u16
+    pub fn to_u16(self) -> u16 {
+        self as u16
+    }
+
+    /// Уровень alert по умолчанию для этого кода
+    pub fn default_level(self) -> AlertLevel {
+        match self {
+            AlertCode::ReplayDetected | AlertCode::InvalidTimestamp | AlertCode::RekeyRequired => {
+                AlertLevel::Warning
+            }
+            _ => AlertLevel::Fatal,
+        }
+    }
+
+    /// Рекомендуемая реакция получателя
+    pub fn recommended_action(self) -> AlertAction {
+        match self {
+            AlertCode::ReplayDetected | AlertCode::InvalidTimestamp => AlertAction::Continue,
+            // Ключи сессии разошлись — новый handshake их восстановит
+            AlertCode::SessionExpired
+            | AlertCode::KeepaliveTimeout
+            | AlertCode::RekeyRequired
+            | AlertCode::AuthenticationFailed
+            | AlertCode::InternalError => AlertAction::Reconnect,
+            AlertCode::TooManySessions | AlertCode::ServerShutdown => {
+                AlertAction::ReconnectWithBackoff
+            }
+            AlertCode::CloseNotify
+            | AlertCode::UnsupportedVersion
+            | AlertCode::HandshakeFailed
+            | AlertCode::ProtocolError => AlertAction::GiveUp,
+        }
+    }
+}
+
+impl fmt::Display for AlertCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AlertCode::CloseNotify => "close_notify",
+            AlertCode::SessionExpired => "session_expired",
+            AlertCode::TooManySessions => "too_many_sessions",
+            AlertCode::AuthenticationFailed => "authentication_failed",
+            AlertCode::UnsupportedVersion => "unsupported_version",
+            AlertCode::ServerShutdown => "server_shutdown",
+            AlertCode::KeepaliveTimeout => "keepalive_timeout",
+            AlertCode::ReplayDetected => "replay_detected",
+            AlertCode::InvalidTimestamp => "invalid_timestamp",
+            AlertCode::RekeyRequired => "rekey_required",
+            AlertCode::HandshakeFailed => "handshake_failed",
+            AlertCode::ProtocolError => "protocol_error",
+            AlertCode::InternalError => "internal_error",
+        };
+        write!(f, "{} ({})", name, self.to_u16())
+    }
+}
+
+impl From<&LlpError> for AlertCode {
+    fn from(error: &LlpError) -> Self {
+        match error {
+            LlpError::SessionError(e) => match e {
+                SessionError::SessionNotFound { .. } | SessionError::SessionExpired { .. } => {
+                    AlertCode::SessionExpired
+                }
+                SessionError::TooManySessions { .. } => AlertCode::TooManySessions,
+                SessionError::DuplicateSequenceNumber { .. }
+                | SessionError::SequenceOutOfWindow { .. } => AlertCode::ReplayDetected,
+                SessionError::InvalidTimestamp { .. } => AlertCode::InvalidTimestamp,
+                SessionError::KeepaliveTimeout { .. } => AlertCode::KeepaliveTimeout,
+                SessionError::RekeyRequired { .. } | SessionError::RekeyFailed { .. } => {
+                    AlertCode::RekeyRequired
+                }
+                SessionError::ClosedByPeer { code, .. } => *code,
+                SessionError::SessionAlreadyExists { .. } => AlertCode::InternalError,
+            },
+            LlpError::CryptoError(e) => match e {
+                CryptoError::DecryptionError
+                | CryptoError::AuthenticationError
+                | CryptoError::SignatureVerificationError => AlertCode::AuthenticationFailed,
+                _ => AlertCode::InternalError,
+            },
+            LlpError::HandshakeError(e) => match e {
+                HandshakeError::VerificationFailed => AlertCode::AuthenticationFailed,
+                _ => AlertCode::HandshakeFailed,
+            },
+            LlpError::PacketError(e) => match e {
+                PacketError::UnsupportedVersion(_) => AlertCode::UnsupportedVersion,
+                _ => AlertCode::ProtocolError,
+            },
+            LlpError::Io(_) | LlpError::Other(_) => AlertCode::InternalError,
+        }
+    }
+}
+
+/// Уровень alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum AlertLevel {
+    /// Предупреждение: сессия продолжает работу
+    Warning = 1,
+    /// Фатальная ошибка: отправитель закрывает сессию
+    Fatal = 2,
+}
+
+impl AlertLevel {
+    /// Преобразование из u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(AlertLevel::Warning),
+            2 => Some(AlertLevel::Fatal),
+            _ => None,
+        }
+    }
+}
+
+/// Рекомендуемая реакция на полученный alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertAction {
+    /// Продолжить работу в текущей сессии
+    Continue,
+    /// Сразу установить новую сессию
+    Reconnect,
+    /// Переподключиться с увеличенной задержкой
+    ReconnectWithBackoff,
+    /// Не переподключаться автоматически
+    GiveUp,
+}
+
+/// Alert сообщение
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    /// Уровень
+    pub level: AlertLevel,
+    /// Код причины
+    pub code: AlertCode,
+    /// Необязательное текстовое пояснение (для логов)
+    pub reason: String,
+}
+
+impl Alert {
+    /// Создать alert с уровнем по умолчанию для кода
+    pub fn new(code: AlertCode) -> Self {
+        Self {
+            level: code.default_level(),
+            code,
+            reason: String::new(),
+        }
+    }
+
+    /// Штатное закрытие сессии
+    pub fn close_notify() -> Self {
+        Self::new(AlertCode::CloseNotify)
+    }
+
+    /// Создать alert из ошибки протокола
+    pub fn from_error(error: &LlpError) -> Self {
+        Self::new(AlertCode::from(error))
+    }
+
+    /// Добавить текстовое пояснение (обрезается до [`MAX_ALERT_REASON_SIZE`])
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        let mut reason = reason.into();
+        if reason.len() > MAX_ALERT_REASON_SIZE {
+            let mut end = MAX_ALERT_REASON_SIZE;
+            while !reason.is_char_boundary(end) {
+                end -= 1;
+            }
+            reason.truncate(end);
+        }
+        self.reason = reason;
+        self
+    }
+
+    /// Фатален ли alert (отправитель закрывает сессию)
+    pub fn is_fatal(&self) -> bool {
+        self.level == AlertLevel::Fatal
+    }
+
+    /// Проверить, является ли plaintext alert сообщением
+    pub fn is_alert(plaintext: &[u8]) -> bool {
+        plaintext.first() == Some(&ALERT_CONTROL_TYPE)
+    }
+
+    /// Сериализация alert
+    pub fn serialize(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(ALERT_HEADER_SIZE + self.reason.len());
+        buf.put_u8(ALERT_CONTROL_TYPE);
+        buf.put_u8(self.level as u8);
+        buf.put_u16(self.code.to_u16());
+        buf.put_u16(self.reason.len() as u16);
+        buf.put_slice(self.reason.as_bytes());
+        buf.freeze()
+    }
+
+    /// Десериализация alert
+    pub fn deserialize(data: &[u8]) -> Result<Self> {
+        if data.len() < ALERT_HEADER_SIZE {
+            return Err(PacketError::InsufficientData {
+                required: ALERT_HEADER_SIZE,
+                available: data.len(),
+            }
+            .into());
+        }
+
+        let mut buf = data;
+        if buf.get_u8() != ALERT_CONTROL_TYPE {
+            return Err(PacketError::HeaderParseError.into());
+        }
+
+        let level = AlertLevel::from_u8(buf.get_u8()).ok_or(PacketError::HeaderParseError)?;
+        let code_raw = buf.get_u16();
+        let code = AlertCode::from_u16(code_raw).ok_or(PacketError::UnknownAlertCode(code_raw))?;
+
+        let reason_len = buf.get_u16() as usize;
+        if reason_len > MAX_ALERT_REASON_SIZE || buf.remaining() != reason_len {
+            return Err(PacketError::InvalidPayloadSize {
+                declared: reason_len,
+                actual: buf.remaining(),
+            }
+            .into());
+        }
+
+        let reason = String::from_utf8(buf.to_vec())
+            .map_err(|_| PacketError::SerializationError("reason не в UTF-8".to_string()))?;
+
+        Ok(Self {
+            level,
+            code,
+            reason,
+        })
+    }
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level {
+            AlertLevel::Warning => "warning",
+            AlertLevel::Fatal => "fatal",
+        };
+        if self.reason.is_empty() {
+            write!(f, "{} alert: {}", level, self.code)
+        } else {
+            write!(f, "{} alert: {} — {}", level, self.code, self.reason)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_round_trip() {
+        let alert = Alert::new(AlertCode::ServerShutdown).with_reason("плановое обслуживание");
+        let bytes = alert.serialize();
+
+        assert!(Alert::is_alert(&bytes));
+        assert_eq!(Alert::deserialize(&bytes).unwrap(), alert);
+    }
+
+    #[test]
+    fn test_alert_codes_stable() {
+        assert_eq!(AlertCode::CloseNotify.to_u16(), 0);
+        assert_eq!(AlertCode::SessionExpired.to_u16(), 1);
+        assert_eq!(AlertCode::TooManySessions.to_u16(), 2);
+        assert_eq!(AlertCode::AuthenticationFailed.to_u16(), 3);
+        assert_eq!(AlertCode::UnsupportedVersion.to_u16(), 4);
+        assert_eq!(AlertCode::ServerShutdown.to_u16(), 5);
+        assert_eq!(AlertCode::InternalError.to_u16(), 255);
+
+        for raw in 0..=u16::MAX {
+            if let Some(code) = AlertCode::from_u16(raw) {
+                assert_eq!(code.to_u16(), raw);
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_mapping() {
+        let expired: LlpError = SessionError::SessionExpired { session_id: 1 }.into();
+        assert_eq!(AlertCode::from(&expired), AlertCode::SessionExpired);
+
+        let full: LlpError = SessionError::TooManySessions { current: 10, max: 10 }.into();
+        assert_eq!(AlertCode::from(&full), AlertCode::TooManySessions);
+
+        let auth: LlpError = CryptoError::DecryptionError.into();
+        assert_eq!(AlertCode::from(&auth), AlertCode::AuthenticationFailed);
+
+        let version: LlpError = PacketError::UnsupportedVersion(9).into();
+        assert_eq!(AlertCode::from(&version), AlertCode::UnsupportedVersion);
+
+        let replay: LlpError = SessionError::DuplicateSequenceNumber { session_id: 1, seq: 5 }.into();
+        let alert = Alert::from_error(&replay);
+        assert_eq!(alert.code, AlertCode::ReplayDetected);
+        assert!(!alert.is_fatal());
+    }
+
+    #[test]
+    fn test_recommended_actions() {
+        assert_eq!(AlertCode::SessionExpired.recommended_action(), AlertAction::Reconnect);
+        assert_eq!(
+            AlertCode::ServerShutdown.recommended_action(),
+            AlertAction::ReconnectWithBackoff
+        );
+        assert_eq!(
+            AlertCode::AuthenticationFailed.recommended_action(),
+            AlertAction::Reconnect
+        );
+        assert_eq!(AlertCode::ProtocolError.recommended_action(), AlertAction::GiveUp);
+    }
+
+    #[test]
+    fn test_alert_rejects_malformed() {
+        assert!(Alert::deserialize(&[ALERT_CONTROL_TYPE, 2, 0]).is_err());
+
+        // Неизвестный код
+        let mut bytes = Alert::close_notify().serialize().to_vec();
+        bytes[2..4].copy_from_slice(&1000u16.to_be_bytes());
+        assert!(Alert::deserialize(&bytes).is_err());
+
+        // IP пакет не является alert
+        assert!(!Alert::is_alert(&[0x45, 0x00, 0x00, 0x14]));
+    }
+
+    #[test]
+    fn test_reason_truncated_on_char_boundary() {
+        let alert = Alert::close_notify().with_reason("я".repeat(200));
+        assert!(alert.reason.len() <= MAX_ALERT_REASON_SIZE);
+        assert!(Alert::deserialize(&alert.serialize()).is_ok());
+    }
+}
@@ -0,0 +1,172 @@
+//! LLP conformance runner
+//!
+//! Генерация и проверка known-answer test vectors протокола.
+//!
+//! ```text
+//! llp-conformance generate [OUT]            записать набор векторов (stdout без OUT)
+//! llp-conformance verify [VECTORS]          проверить эту реализацию (по умолчанию —
+//!                                           опубликованный набор)
+//! llp-conformance run VECTORS -- CMD [ARGS] проверить внешнюю реализацию
+//! ```
+//!
+//! ## Протокол `run`
+//!
+//! Runner запускает `CMD` и для каждого вектора пишет в его stdin одну строку
+//! JSON `{"name": ..., "type": ..., "input": {...}}`. Реализация отвечает
+//! одной строкой JSON в stdout: поля результата (как `expected` в файле),
+//! `{"error": "<код>"}` при отказе или `{"unsupported": true}`, если тип
+//! вектора не реализован (вектор пропускается).
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, ExitCode, Stdio};
+
+use llp_core::vectors::{self, Fields, TestVector, VectorSet};
+use serde_json::{json, Value};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("generate") => generate(args.get(1)),
+        Some("verify") => verify(args.get(1)),
+        Some("run") => run_external(&args[1..]),
+        _ => {
+            eprintln!("Использование:");
+            eprintln!("  llp-conformance generate [OUT]");
+            eprintln!("  llp-conformance verify [VECTORS]");
+            eprintln!("  llp-conformance run VECTORS -- CMD [ARGS...]");
+            return ExitCode::from(2);
+        }
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Ошибка: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+type RunResult = Result<bool, Box<dyn std::error::Error>>;
+
+/// Записать канонический набор векторов
+fn generate(out: Option<&String>) -> RunResult {
+    let json = vectors::generate().to_json();
+    match out {
+        Some(path) => {
+            std::fs::write(path, json)?;
+            eprintln!("Test vectors сохранены в: {}", path);
+        }
+        None => print!("{}", json),
+    }
+    Ok(true)
+}
+
+/// Проверить эту реализацию по набору векторов
+fn verify(path: Option<&String>) -> RunResult {
+    let set = load(path)?;
+    let mut report = Report::default();
+
+    for vector in &set.vectors {
+        match vector.check() {
+            Ok(()) => report.pass(vector),
+            Err(actual) => report.fail(vector, &actual),
+        }
+    }
+
+    Ok(report.finish())
+}
+
+/// Проверить внешнюю реализацию через stdin/stdout
+fn run_external(args: &[String]) -> RunResult {
+    let (path, command) = match args {
+        [path, separator, command @ ..] if separator == "--" && !command.is_empty() => {
+            (path, command)
+        }
+        _ => return Err("ожидается: run VECTORS -- CMD [ARGS...]".into()),
+    };
+
+    let set = load(Some(path))?;
+    let mut child = Command::new(&command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().ok_or("нет stdin у процесса")?;
+    let mut stdout = BufReader::new(child.stdout.take().ok_or("нет stdout у процесса")?);
+    let mut report = Report::default();
+
+    for vector in &set.vectors {
+        let request = json!({
+            "name": vector.name,
+            "type": vector.vector_type,
+            "input": vector.input,
+        });
+        writeln!(stdin, "{}", request)?;
+        stdin.flush()?;
+
+        let mut line = String::new();
+        if stdout.read_line(&mut line)? == 0 {
+            return Err(format!("реализация завершилась на векторе {}", vector.name).into());
+        }
+
+        let actual: Fields = serde_json::from_str(line.trim())?;
+        if actual.get("unsupported") == Some(&Value::Bool(true)) {
+            report.skip(vector);
+        } else if actual == vector.expected {
+            report.pass(vector);
+        } else {
+            report.fail(vector, &actual);
+        }
+    }
+
+    drop(stdin);
+    child.wait()?;
+    Ok(report.finish())
+}
+
+fn load(path: Option<&String>) -> Result<VectorSet, Box<dyn std::error::Error>> {
+    let set = match path {
+        Some(path) => VectorSet::from_json(&std::fs::read_to_string(path)?)?,
+        None => VectorSet::published()?,
+    };
+    Ok(set)
+}
+
+/// Итоги прогона
+#[derive(Default)]
+struct Report {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+impl Report {
+    fn pass(&mut self, vector: &TestVector) {
+        self.passed += 1;
+        println!("PASS {}", vector.name);
+    }
+
+    fn skip(&mut self, vector: &TestVector) {
+        self.skipped += 1;
+        println!("SKIP {}", vector.name);
+    }
+
+    fn fail(&mut self, vector: &TestVector, actual: &Fields) {
+        self.failed += 1;
+        println!("FAIL {}", vector.name);
+        println!("  ожидалось: {}", json!(vector.expected));
+        println!("  получено:  {}", json!(actual));
+    }
+
+    fn finish(self) -> bool {
+        println!(
+            "\nИтого: {} пройдено, {} провалено, {} пропущено",
+            self.passed, self.failed, self.skipped
+        );
+        self.failed == 0
+    }
+}
@@ -0,0 +1,139 @@
+//! Источники времени для протокола
+//!
+//! Вся логика, зависящая от времени (timeout, keepalive, истечение сессий,
+//! проверка timestamp), получает текущее время через трейт [`Clock`].
+//! В рабочем режиме используется [`SystemClock`], в тестах и симуляции —
+//! [`SimulatedClock`], время которого сдвигается вручную.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Источник времени
+pub trait Clock: Send + Sync {
+    /// Монотонное время (для таймаутов и интервалов)
+    fn now(&self) -> Instant;
+
+    /// Время с начала Unix-эпохи (для timestamp в пакетах)
+    fn unix_time(&self) -> Duration;
+
+    /// Время с начала Unix-эпохи в секундах
+    fn unix_secs(&self) -> u64 {
+        self.unix_time().as_secs()
+    }
+}
+
+/// Разделяемый источник времени
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Системные часы (реальное время)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// Создать разделяемый экземпляр системных часов
+    pub fn shared() -> SharedClock {
+        Arc::new(SystemClock)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn unix_time(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+}
+
+/// Симулируемые часы
+///
+/// Время стоит на месте, пока его явно не сдвинут через [`advance`].
+/// Клоны разделяют одно и то же состояние, поэтому несколько участников
+/// симуляции видят общее виртуальное время.
+///
+/// [`advance`]: SimulatedClock::advance
+#[derive(Debug, Clone)]
+pub struct SimulatedClock {
+    /// Точка отсчёта монотонного времени
+    base_instant: Instant,
+    /// Unix-время в точке отсчёта
+    base_unix: Duration,
+    /// Прошедшее виртуальное время
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl SimulatedClock {
+    /// Создать часы, начинающие отсчёт с указанного Unix-времени (секунды)
+    pub fn new(start_unix_secs: u64) -> Self {
+        Self {
+            base_instant: Instant::now(),
+            base_unix: Duration::from_secs(start_unix_secs),
+            elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    /// Сдвинуть виртуальное время вперёд
+    pub fn advance(&self, delta: Duration) {
+        let mut elapsed = self.elapsed.lock().unwrap();
+        *elapsed += delta;
+    }
+
+    /// Сколько виртуального времени прошло с момента создания
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+
+    /// Получить разделяемую ссылку на эти часы
+    pub fn shared(&self) -> SharedClock {
+        Arc::new(self.clone())
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> Instant {
+        self.base_instant + self.elapsed()
+    }
+
+    fn unix_time(&self) -> Duration {
+        self.base_unix + self.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_clock() {
+        let clock = SystemClock;
+        assert!(clock.unix_secs() > 1_600_000_000);
+
+        let a = clock.now();
+        let b = clock.now();
+        assert!(b >= a);
+    }
+
+    #[test]
+    fn test_simulated_clock_advance() {
+        let clock = SimulatedClock::new(1_700_000_000);
+        let start = clock.now();
+
+        assert_eq!(clock.unix_secs(), 1_700_000_000);
+
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(clock.now() - start, Duration::from_secs(3600));
+        assert_eq!(clock.unix_secs(), 1_700_003_600);
+    }
+
+    #[test]
+    fn test_simulated_clock_shared_state() {
+        let clock = SimulatedClock::new(0);
+        let shared = clock.shared();
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(shared.unix_time(), Duration::from_millis(1500));
+    }
+}
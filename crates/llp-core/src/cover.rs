@@ -0,0 +1,57 @@
+//! Фиктивные пакеты cover traffic
+//!
+//! Пока туннель простаивает, стороны отправляют фиктивные пакеты, чтобы
+//! наблюдаемый поток сохранял форму трафика профиля мимикрии. Фиктивный
+//! пакет зашифрован как обычный, поэтому снаружи неотличим от данных;
+//! получатель отбрасывает его после проверки auth tag.
+//!
+//! LLP пакет с фиктивным payload — CONTROL пакет с расширением
+//! [`ExtensionType::COVER`], в UDP датаграмме (без заголовка LLP) — как управляющее сообщение типа
+//! [`COVER_CONTROL_TYPE`], подобно alert:
+//! ```text
+//! ┌──────────────┬──────────────────────────────────┐
+//! │ Type (8)=0x16│        Filler (нули, 0..)        │
+//! └──────────────┴──────────────────────────────────┘
+//! ```
+//!
+//! [`ExtensionType::COVER`]: crate::extension::ExtensionType::COVER
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// Тип управляющего сообщения «cover»
+///
+/// Как и у alert, старший полубайт не совпадает с версией IPv4/IPv6.
+pub const COVER_CONTROL_TYPE: u8 = 0x16;
+
+/// Plaintext фиктивного пакета длиной `len` байт (не меньше одного)
+pub fn payload(len: usize) -> Bytes {
+    let mut buf = BytesMut::with_capacity(len.max(1));
+    buf.put_u8(COVER_CONTROL_TYPE);
+    buf.put_bytes(0, len.saturating_sub(1));
+    buf.freeze()
+}
+
+/// Является ли plaintext фиктивным пакетом
+pub fn is_cover(plaintext: &[u8]) -> bool {
+    plaintext.first() == Some(&COVER_CONTROL_TYPE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::Alert;
+
+    #[test]
+    fn test_cover_payload() {
+        let cover = payload(1200);
+        assert_eq!(cover.len(), 1200);
+        assert!(is_cover(&cover));
+        assert!(!Alert::is_alert(&cover));
+        assert_eq!(payload(0).len(), 1);
+
+        // IP пакеты не принимаются за фиктивные
+        assert!(!is_cover(&[0x45, 0x00, 0x00, 0x14]));
+        assert!(!is_cover(&[0x60, 0x00, 0x00, 0x00]));
+        assert!(!is_cover(&[]));
+    }
+}
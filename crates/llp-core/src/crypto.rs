@@ -104,6 +104,11 @@ pub struct SharedSecret {
 }
 
 impl SharedSecret {
+    /// Создать из байтов (для test vectors и внешних реализаций X25519)
+    pub fn from_bytes(bytes: &[u8; X25519_KEY_SIZE]) -> Self {
+        Self { bytes: *bytes }
+    }
+
     /// Получить байты общего секрета
     pub fn as_bytes(&self) -> &[u8; X25519_KEY_SIZE] {
         &self.bytes
@@ -265,6 +270,27 @@ impl AeadCipher {
         Ok(plaintext)
     }
 
+    /// Зашифровать данные с явно заданным счётчиком nonce
+    ///
+    /// Внутренний счётчик не изменяется. Используется там, где счётчик
+    /// передаётся на проводе (UDP датаграммы) и в test vectors.
+    pub fn encrypt_at(&self, plaintext: &[u8], aad: &[u8], nonce_counter: u64) -> Result<Vec<u8>> {
+        let nonce = ChaCha20Nonce::new(self.nonce.session_id as u64, nonce_counter);
+        let nonce_bytes = nonce.as_bytes();
+
+        let payload = Payload {
+            msg: plaintext,
+            aad,
+        };
+
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), payload)
+            .map_err(|_| CryptoError::EncryptionError)?;
+
+        Ok(ciphertext)
+    }
+
     /// Получить текущий счётчик nonce
     pub fn nonce_counter(&self) -> u64 {
         self.nonce.counter()
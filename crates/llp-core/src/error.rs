@@ -5,6 +5,8 @@
 
 use thiserror::Error;
 
+use crate::alert::AlertCode;
+
 /// Основной тип ошибок протокола LLP
 #[derive(Error, Debug)]
 pub enum LlpError {
@@ -75,6 +77,26 @@ pub enum PacketError {
     /// Ошибка сериализации
     #[error("Ошибка сериализации пакета: {0}")]
     SerializationError(String),
+
+    /// Неизвестный код alert
+    #[error("Неизвестный код alert: {0}")]
+    UnknownAlertCode(u16),
+
+    /// Неизвестное критическое extension header
+    #[error("Неподдерживаемое критическое расширение 0x{0:04x}")]
+    UnsupportedCriticalExtension(u16),
+
+    /// Расширение встречается в цепочке повторно
+    #[error("Повторное расширение 0x{0:04x} в заголовке")]
+    DuplicateExtension(u16),
+
+    /// Некорректное значение известного расширения
+    #[error("Некорректное значение расширения 0x{0:04x}")]
+    InvalidExtension(u16),
+
+    /// Некорректный формат блока extension headers
+    #[error("Некорректный блок extension headers")]
+    InvalidExtensionBlock,
 }
 
 /// Ошибки криптографических операций
@@ -182,11 +204,11 @@ pub enum SessionError {
 
     /// Дублирующийся sequence number (replay attack)
     #[error("Дублирующийся sequence number {seq} в сессии {session_id} (replay attack?)")]
-    DuplicateSequenceNumber { session_id: u64, seq: u32 },
+    DuplicateSequenceNumber { session_id: u64, seq: u64 },
 
     /// Sequence number вне окна приёма
     #[error("Sequence number {seq} вне окна приёма для сессии {session_id}")]
-    SequenceOutOfWindow { session_id: u64, seq: u32 },
+    SequenceOutOfWindow { session_id: u64, seq: u64 },
 
     /// Превышен лимит активных сессий
     #[error("Превышен лимит активных сессий: {current} > {max}")]
@@ -211,6 +233,15 @@ pub enum SessionError {
     /// Keepalive timeout
     #[error("Keepalive timeout для сессии {session_id}")]
     KeepaliveTimeout { session_id: u64 },
+
+    /// Сессия закрыта другой стороной (получен fatal alert)
+    #[error("Сессия {session_id} закрыта другой стороной: {code}")]
+    ClosedByPeer {
+        /// Идентификатор сессии
+        session_id: u64,
+        /// Код причины из полученного alert
+        code: AlertCode,
+    },
 }
 
 /// Псевдоним для Result с ошибкой LLP
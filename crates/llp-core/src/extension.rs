@@ -0,0 +1,417 @@
+//! Extension headers пакета LLP
+//!
+//! Если в заголовке установлен флаг [`PacketFlags::EXTENSIONS`], сразу
+//! после фиксированных 24 байт заголовка идёт блок расширений — цепочка
+//! TLV записей. Блок является частью заголовка, поэтому входит в AAD
+//! и защищён auth tag так же, как фиксированные поля.
+//!
+//! Формат блока:
+//! ```text
+//! ┌──────────────────────────────┐
+//! │   Extensions Length (16)     │  длина всех записей в байтах
+//! ├──────────────────────────────┼──────────────────────────────┐
+//! │         Type (16)            │         Length (16)          │
+//! ├──────────────────────────────┴──────────────────────────────┤
+//! │                      Value (Length байт)                    │
+//! └─────────────────────────────────────────────────────────────┘
+//!   ... следующая запись ...
+//! ```
+//!
+//! Правила обработки:
+//! - Старший бит типа (`0x8000`) означает «критическое» расширение.
+//!   Получатель, не знающий критическое расширение, обязан отбросить пакет.
+//! - Неизвестные некритические расширения пропускаются, но сохраняются
+//!   в заголовке, чтобы AAD при расшифровке совпал побайтно.
+//! - Повтор одного типа в цепочке и пустой блок при установленном флаге
+//!   считаются ошибкой формата.
+//! - Известные расширения проверяются на корректность значения.
+//!
+//! Новые возможности добавляются регистрацией нового типа, без изменения
+//! [`PROTOCOL_VERSION`](crate::packet::PROTOCOL_VERSION).
+//!
+//! [`PacketFlags::EXTENSIONS`]: crate::packet::PacketFlags::EXTENSIONS
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::fmt;
+
+use crate::error::{PacketError, Result};
+
+/// Размер поля длины блока расширений
+pub const EXTENSIONS_LENGTH_SIZE: usize = 2;
+
+/// Размер заголовка одной TLV записи (type + length)
+pub const EXTENSION_RECORD_HEADER_SIZE: usize = 4;
+
+/// Максимальный размер блока расширений (без поля длины)
+pub const MAX_EXTENSIONS_SIZE: usize = 512;
+
+/// Бит «критического» расширения в типе
+pub const CRITICAL_BIT: u16 = 0x8000;
+
+/// Тип extension header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExtensionType(pub u16);
+
+impl ExtensionType {
+    /// ECN метка (1 байт, codepoint 0-3)
+    pub const ECN: Self = Self(0x0001);
+    /// Идентификатор FEC группы (u32 group id, u8 индекс, u8 размер группы)
+    pub const FEC_GROUP: Self = Self(0x0002);
+    /// Идентификатор соединения (1-20 байт), критическое
+    pub const CONNECTION_ID: Self = Self(CRITICAL_BIT | 0x0001);
+    /// Идентификатор сетевого пути (u8), критическое
+    pub const PATH_ID: Self = Self(CRITICAL_BIT | 0x0002);
+    /// Фиктивный пакет cover traffic (пустое значение), критическое:
+    /// получатель, не знающий тип, отбрасывает пакет, как и фиктивный
+    pub const COVER: Self = Self(CRITICAL_BIT | 0x0003);
+
+    /// Известные этой реализации типы
+    pub const KNOWN: &'static [Self] = &[
+        Self::ECN,
+        Self::FEC_GROUP,
+        Self::CONNECTION_ID,
+        Self::PATH_ID,
+        Self::COVER,
+    ];
+
+    /// Критическое ли расширение
+    pub fn is_critical(self) -> bool {
+        self.0 & CRITICAL_BIT != 0
+    }
+
+    /// Известен ли тип этой реализации
+    pub fn is_known(self) -> bool {
+        Self::KNOWN.contains(&self)
+    }
+
+    /// Проверить значение известного расширения
+    fn validate(self, value: &[u8]) -> Result<()> {
+        let valid = match self {
+            Self::ECN => value.len() == 1 && value[0] <= 3,
+            Self::FEC_GROUP => value.len() == 6 && value[4] < value[5],
+            Self::CONNECTION_ID => (1..=20).contains(&value.len()),
+            Self::PATH_ID => value.len() == 1,
+            Self::COVER => value.is_empty(),
+            _ => true,
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(PacketError::InvalidExtension(self.0).into())
+        }
+    }
+}
+
+impl fmt::Display for ExtensionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::ECN => write!(f, "ECN"),
+            Self::FEC_GROUP => write!(f, "FEC_GROUP"),
+            Self::CONNECTION_ID => write!(f, "CONNECTION_ID"),
+            Self::PATH_ID => write!(f, "PATH_ID"),
+            Self::COVER => write!(f, "COVER"),
+            Self(raw) => write!(f, "0x{:04x}", raw),
+        }
+    }
+}
+
+/// Одна запись extension header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension {
+    /// Тип расширения
+    pub ext_type: ExtensionType,
+    /// Значение
+    pub value: Bytes,
+}
+
+impl Extension {
+    /// Создать расширение с произвольным значением
+    pub fn new(ext_type: ExtensionType, value: impl Into<Bytes>) -> Self {
+        Self {
+            ext_type,
+            value: value.into(),
+        }
+    }
+
+    /// ECN метка
+    pub fn ecn(codepoint: u8) -> Self {
+        Self::new(ExtensionType::ECN, vec![codepoint & 0b11])
+    }
+
+    /// Позиция пакета в FEC группе
+    pub fn fec_group(group_id: u32, index: u8, group_size: u8) -> Self {
+        let mut value = BytesMut::with_capacity(6);
+        value.put_u32(group_id);
+        value.put_u8(index);
+        value.put_u8(group_size);
+        Self::new(ExtensionType::FEC_GROUP, value.freeze())
+    }
+
+    /// Идентификатор соединения
+    pub fn connection_id(id: &[u8]) -> Self {
+        Self::new(ExtensionType::CONNECTION_ID, Bytes::copy_from_slice(id))
+    }
+
+    /// Идентификатор сетевого пути
+    pub fn path_id(path: u8) -> Self {
+        Self::new(ExtensionType::PATH_ID, vec![path])
+    }
+
+    /// Отметка фиктивного пакета
+    pub fn cover() -> Self {
+        Self::new(ExtensionType::COVER, Bytes::new())
+    }
+
+    /// Размер записи на проводе
+    pub fn wire_size(&self) -> usize {
+        EXTENSION_RECORD_HEADER_SIZE + self.value.len()
+    }
+}
+
+/// Цепочка extension headers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Extensions {
+    records: Vec<Extension>,
+}
+
+impl Extensions {
+    /// Пустая цепочка
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Пуста ли цепочка
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Количество записей
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Добавить расширение
+    ///
+    /// Заменяет существующую запись того же типа.
+    pub fn insert(&mut self, extension: Extension) -> Result<()> {
+        extension.ext_type.validate(&extension.value)?;
+
+        self.records.retain(|e| e.ext_type != extension.ext_type);
+        self.records.push(extension);
+
+        if self.records_size() > MAX_EXTENSIONS_SIZE {
+            self.records.pop();
+            return Err(PacketError::PacketTooLarge {
+                size: self.records_size(),
+                max: MAX_EXTENSIONS_SIZE,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Найти расширение по типу
+    pub fn get(&self, ext_type: ExtensionType) -> Option<&Extension> {
+        self.records.iter().find(|e| e.ext_type == ext_type)
+    }
+
+    /// Итератор по записям в порядке следования на проводе
+    pub fn iter(&self) -> impl Iterator<Item = &Extension> {
+        self.records.iter()
+    }
+
+    /// Записи, тип которых неизвестен этой реализации (только некритические)
+    pub fn unknown(&self) -> impl Iterator<Item = &Extension> {
+        self.records.iter().filter(|e| !e.ext_type.is_known())
+    }
+
+    /// Размер блока на проводе (0, если цепочка пуста)
+    pub fn wire_size(&self) -> usize {
+        if self.is_empty() {
+            0
+        } else {
+            EXTENSIONS_LENGTH_SIZE + self.records_size()
+        }
+    }
+
+    /// Сериализовать блок (ничего не пишет для пустой цепочки)
+    pub fn serialize(&self, buf: &mut BytesMut) {
+        if self.is_empty() {
+            return;
+        }
+
+        buf.put_u16(self.records_size() as u16);
+        for record in &self.records {
+            buf.put_u16(record.ext_type.0);
+            buf.put_u16(record.value.len() as u16);
+            buf.put_slice(&record.value);
+        }
+    }
+
+    /// Разобрать блок расширений, применяя правила обработки
+    pub fn deserialize(buf: &mut impl Buf) -> Result<Self> {
+        if buf.remaining() < EXTENSIONS_LENGTH_SIZE {
+            return Err(PacketError::InsufficientData {
+                required: EXTENSIONS_LENGTH_SIZE,
+                available: buf.remaining(),
+            }
+            .into());
+        }
+
+        let total = buf.get_u16() as usize;
+        if total == 0 || total > MAX_EXTENSIONS_SIZE {
+            return Err(PacketError::InvalidExtensionBlock.into());
+        }
+        if buf.remaining() < total {
+            return Err(PacketError::InsufficientData {
+                required: total,
+                available: buf.remaining(),
+            }
+            .into());
+        }
+
+        let mut block = buf.copy_to_bytes(total);
+        let mut records: Vec<Extension> = Vec::new();
+
+        while block.has_remaining() {
+            if block.remaining() < EXTENSION_RECORD_HEADER_SIZE {
+                return Err(PacketError::InvalidExtensionBlock.into());
+            }
+
+            let ext_type = ExtensionType(block.get_u16());
+            let len = block.get_u16() as usize;
+            if block.remaining() < len {
+                return Err(PacketError::InvalidExtensionBlock.into());
+            }
+            let value = block.split_to(len);
+
+            if records.iter().any(|e| e.ext_type == ext_type) {
+                return Err(PacketError::DuplicateExtension(ext_type.0).into());
+            }
+
+            if ext_type.is_known() {
+                ext_type.validate(&value)?;
+            } else if ext_type.is_critical() {
+                return Err(PacketError::UnsupportedCriticalExtension(ext_type.0).into());
+            }
+
+            records.push(Extension { ext_type, value });
+        }
+
+        Ok(Self { records })
+    }
+
+    fn records_size(&self) -> usize {
+        self.records.iter().map(Extension::wire_size).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(extensions: &Extensions) -> Result<Extensions> {
+        let mut buf = BytesMut::new();
+        extensions.serialize(&mut buf);
+        assert_eq!(buf.len(), extensions.wire_size());
+        Extensions::deserialize(&mut buf.freeze())
+    }
+
+    #[test]
+    fn test_extensions_round_trip() {
+        let mut extensions = Extensions::new();
+        extensions.insert(Extension::connection_id(&[1, 2, 3, 4])).unwrap();
+        extensions.insert(Extension::ecn(2)).unwrap();
+        extensions.insert(Extension::fec_group(77, 1, 4)).unwrap();
+        extensions.insert(Extension::path_id(3)).unwrap();
+        extensions.insert(Extension::cover()).unwrap();
+
+        let parsed = round_trip(&extensions).unwrap();
+        assert_eq!(parsed, extensions);
+        assert_eq!(
+            &parsed.get(ExtensionType::CONNECTION_ID).unwrap().value[..],
+            &[1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn test_unknown_ignorable_extension_kept() {
+        let mut extensions = Extensions::new();
+        extensions
+            .insert(Extension::new(ExtensionType(0x0042), vec![9, 9]))
+            .unwrap();
+
+        let parsed = round_trip(&extensions).unwrap();
+        assert_eq!(parsed.unknown().count(), 1);
+        assert_eq!(parsed, extensions);
+    }
+
+    #[test]
+    fn test_unknown_critical_extension_rejected() {
+        let mut extensions = Extensions::new();
+        extensions
+            .insert(Extension::new(ExtensionType(0x8042), vec![1]))
+            .unwrap();
+
+        assert!(matches!(
+            round_trip(&extensions),
+            Err(crate::LlpError::PacketError(
+                PacketError::UnsupportedCriticalExtension(0x8042)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_malformed_blocks_rejected() {
+        // Дублирующийся тип
+        let mut buf = BytesMut::new();
+        buf.put_u16(10);
+        buf.put_u16(0x0001);
+        buf.put_u16(1);
+        buf.put_u8(1);
+        buf.put_u16(0x0001);
+        buf.put_u16(1);
+        buf.put_u8(2);
+        assert!(Extensions::deserialize(&mut buf.freeze()).is_err());
+
+        // Запись выходит за границу блока
+        let mut buf = BytesMut::new();
+        buf.put_u16(5);
+        buf.put_u16(0x0042);
+        buf.put_u16(8);
+        buf.put_u8(0);
+        assert!(Extensions::deserialize(&mut buf.freeze()).is_err());
+
+        // Пустой блок при установленном флаге
+        let mut buf = BytesMut::new();
+        buf.put_u16(0);
+        assert!(Extensions::deserialize(&mut buf.freeze()).is_err());
+
+        // Некорректное значение известного расширения
+        let mut buf = BytesMut::new();
+        buf.put_u16(5);
+        buf.put_u16(ExtensionType::ECN.0);
+        buf.put_u16(1);
+        buf.put_u8(7);
+        assert!(Extensions::deserialize(&mut buf.freeze()).is_err());
+    }
+
+    #[test]
+    fn test_insert_limits() {
+        let mut extensions = Extensions::new();
+        assert!(extensions.insert(Extension::connection_id(&[0u8; 21])).is_err());
+        assert!(extensions
+            .insert(Extension::new(ExtensionType::COVER, vec![0]))
+            .is_err());
+
+        extensions
+            .insert(Extension::new(ExtensionType(0x0100), vec![0u8; 400]))
+            .unwrap();
+        assert!(extensions
+            .insert(Extension::new(ExtensionType(0x0101), vec![0u8; 200]))
+            .is_err());
+        assert_eq!(extensions.len(), 1);
+    }
+}
@@ -35,7 +35,7 @@ use crate::packet::MimicryProfile;
 const HMAC_TAG_SIZE: usize = 32;
 
 /// Информация для HKDF деривации ключа
-const HKDF_INFO: &[u8] = b"llp-session-key-v1";
+pub(crate) const HKDF_INFO: &[u8] = b"llp-session-key-v1";
 
 /// Тип сообщения handshake
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -331,6 +331,17 @@ pub enum HandshakeState {
     ClientVerifyReceived,
 }
 
+/// Соль HKDF для сессионного ключа: `client_random || server_random`
+pub fn session_salt(
+    client_random: &[u8; RANDOM_SIZE],
+    server_random: &[u8; RANDOM_SIZE],
+) -> Vec<u8> {
+    let mut salt = Vec::with_capacity(RANDOM_SIZE * 2);
+    salt.extend_from_slice(client_random);
+    salt.extend_from_slice(server_random);
+    salt
+}
+
 /// Handshake контекст для клиента
 pub struct ClientHandshake {
     state: HandshakeState,
@@ -396,9 +407,7 @@ impl ClientHandshake {
 
         // Деривация сессионного ключа через HKDF
         let client_hello = self.client_hello.as_ref().unwrap();
-        let mut salt = Vec::with_capacity(RANDOM_SIZE * 2);
-        salt.extend_from_slice(&client_hello.client_random);
-        salt.extend_from_slice(&server_hello.server_random);
+        let salt = session_salt(&client_hello.client_random, &server_hello.server_random);
 
         let session_key = shared_secret.derive_session_key(&salt, HKDF_INFO)?;
 
@@ -528,9 +537,7 @@ impl ServerHandshake {
         let server_hello = ServerHello::new(rng, &self.server_key, self.session_id);
 
         // Деривация сессионного ключа
-        let mut salt = Vec::with_capacity(RANDOM_SIZE * 2);
-        salt.extend_from_slice(&client_hello.client_random);
-        salt.extend_from_slice(&server_hello.server_random);
+        let salt = session_salt(&client_hello.client_random, &server_hello.server_random);
 
         let session_key = shared_secret.derive_session_key(&salt, HKDF_INFO)?;
 
@@ -14,9 +14,16 @@
 //! ## Структура
 //!
 //! - [`packet`]: Формат пакета LLP и сериализация
+//! - [`extension`]: Extension headers (TLV после заголовка пакета)
 //! - [`crypto`]: Криптографические примитивы
 //! - [`handshake`]: Протокол установления соединения
 //! - [`session`]: Управление сессиями
+//! - [`access`]: Токен доступа в первых байтах подключения
+//! - [`alert`]: Alert и close сообщения с кодами причин
+//! - [`clock`]: Источники времени (системные и симулируемые часы)
+//! - [`cover`]: Фиктивные пакеты cover traffic для простоя туннеля
+//! - [`sim`]: Детерминированная симуляция двух участников в виртуальном времени
+//! - [`vectors`]: Known-answer test vectors для проверки совместимости реализаций
 //! - [`error`]: Типы ошибок
 //!
 //! ## Пример использования
@@ -73,11 +80,18 @@
 #![warn(clippy::all)]
 #![allow(clippy::single_component_path_imports)]
 
+pub mod access;
+pub mod alert;
+pub mod clock;
+pub mod cover;
 pub mod crypto;
 pub mod error;
+pub mod extension;
 pub mod handshake;
 pub mod packet;
 pub mod session;
+pub mod sim;
+pub mod vectors;
 
 // Re-экспорт основных типов для удобства
 pub use error::{LlpError, Result};
@@ -5,6 +5,7 @@
 //! - Зашифрованный payload
 //! - Случайный padding для защиты от анализа размера
 //! - Auth tag для аутентификации
+//! - Необязательные extension headers (см. [`crate::extension`])
 //!
 //! Формат пакета:
 //! ```text
@@ -13,12 +14,14 @@
 //! ├──────────────┴──────────────┴──────────────────────────────┤
 //! │                     Session ID (64)                        │
 //! ├────────────────────────────────────────────────────────────┤
-//! │                   Sequence Number (32)                     │
+//! │                   Sequence Number (64)                     │
 //! ├────────────────────────────────────────────────────────────┤
 //! │                     Timestamp (32)                         │
 //! ├────────────────────────────────────────────────────────────┤
 //! │   Mimicry Profile (16)      │    Padding Length (16)       │
 //! ├────────────────────────────────────────────────────────────┤
+//! │       Extension Headers (если установлен EXTENSIONS)       │
+//! ├────────────────────────────────────────────────────────────┤
 //! │              Encrypted Payload (variable)                  │
 //! ├────────────────────────────────────────────────────────────┤
 //! │                  Random Padding (0-1024)                   │
@@ -31,13 +34,15 @@ use bitflags::bitflags;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::fmt;
 
+use crate::clock::{Clock, SystemClock};
 use crate::error::{PacketError, Result};
+use crate::extension::{Extension, ExtensionType, Extensions};
 
 /// Текущая версия протокола LLP
 pub const PROTOCOL_VERSION: u8 = 1;
 
-/// Размер заголовка пакета (без payload и auth tag)
-pub const HEADER_SIZE: usize = 24; // 1 + 1 + 2 + 8 + 4 + 4 + 2 + 2
+/// Размер фиксированной части заголовка (без extension headers, payload и auth tag)
+pub const HEADER_SIZE: usize = 28; // 1 + 1 + 2 + 8 + 8 + 4 + 2 + 2
 
 /// Размер auth tag (Poly1305)
 pub const AUTH_TAG_SIZE: usize = 16;
@@ -72,7 +77,9 @@ bitflags! {
         const KEEPALIVE  = 0b0010_0000;
         /// Запрос на rekey
         const REKEY      = 0b0100_0000;
-        /// Зарезервировано для будущего использования
+        /// После заголовка следует блок extension headers
+        const EXTENSIONS = 0b1000_0000;
+        /// Прежнее имя флага [`PacketFlags::EXTENSIONS`]
         const RESERVED   = 0b1000_0000;
     }
 }
@@ -101,22 +108,32 @@ impl fmt::Display for PacketFlags {
         if self.contains(PacketFlags::REKEY) {
             flags.push("REKEY");
         }
+        if self.contains(PacketFlags::EXTENSIONS) {
+            flags.push("EXT");
+        }
         write!(f, "{}", flags.join("|"))
     }
 }
 
+/// Первый идентификатор диапазона пользовательских профилей мимикрии
+///
+/// Идентификаторы ниже зарезервированы за встроенными профилями.
+pub const CUSTOM_PROFILE_ID_MIN: u16 = 0x8000;
+
 /// Идентификатор профиля мимикрии
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[repr(u16)]
 pub enum MimicryProfile {
     /// Без мимикрии (чистый протокол)
-    None = 0,
+    None,
     /// Имитация VK Video
-    VkVideo = 1,
+    VkVideo,
     /// Имитация Яндекс.Музыка
-    YandexMusic = 2,
+    YandexMusic,
     /// Имитация RuTube
-    RuTube = 3,
+    RuTube,
+    /// Пользовательский профиль (ID из диапазона [`CUSTOM_PROFILE_ID_MIN`]..=0xFFFF),
+    /// реализация регистрируется в реестре профилей llp-mimicry
+    Custom(u16),
 }
 
 impl MimicryProfile {
@@ -127,13 +144,20 @@ impl MimicryProfile {
             1 => Some(MimicryProfile::VkVideo),
             2 => Some(MimicryProfile::YandexMusic),
             3 => Some(MimicryProfile::RuTube),
+            id if id >= CUSTOM_PROFILE_ID_MIN => Some(MimicryProfile::Custom(id)),
             _ => None,
         }
     }
 
     /// Преобразование в u16
     pub fn to_u16(self) -> u16 {
-        self as u16
+        match self {
+            MimicryProfile::None => 0,
+            MimicryProfile::VkVideo => 1,
+            MimicryProfile::YandexMusic => 2,
+            MimicryProfile::RuTube => 3,
+            MimicryProfile::Custom(id) => id,
+        }
     }
 }
 
@@ -144,6 +168,7 @@ impl fmt::Display for MimicryProfile {
             MimicryProfile::VkVideo => write!(f, "VK Video"),
             MimicryProfile::YandexMusic => write!(f, "Yandex Music"),
             MimicryProfile::RuTube => write!(f, "RuTube"),
+            MimicryProfile::Custom(id) => write!(f, "Custom(0x{:04x})", id),
         }
     }
 }
@@ -159,14 +184,16 @@ pub struct PacketHeader {
     pub payload_length: u16,
     /// Идентификатор сессии
     pub session_id: u64,
-    /// Порядковый номер пакета
-    pub sequence_number: u32,
+    /// Порядковый номер пакета (он же счётчик nonce)
+    pub sequence_number: u64,
     /// Unix timestamp (секунды)
     pub timestamp: u32,
     /// Профиль мимикрии
     pub mimicry_profile: MimicryProfile,
     /// Длина padding
     pub padding_length: u16,
+    /// Extension headers (входят в AAD вместе с фиксированной частью)
+    pub extensions: Extensions,
 }
 
 impl PacketHeader {
@@ -174,8 +201,19 @@ impl PacketHeader {
     pub fn new(
         flags: PacketFlags,
         session_id: u64,
-        sequence_number: u32,
+        sequence_number: u64,
         mimicry_profile: MimicryProfile,
+    ) -> Self {
+        Self::with_clock(flags, session_id, sequence_number, mimicry_profile, &SystemClock)
+    }
+
+    /// Создать новый заголовок с timestamp из указанного источника времени
+    pub fn with_clock(
+        flags: PacketFlags,
+        session_id: u64,
+        sequence_number: u64,
+        mimicry_profile: MimicryProfile,
+        clock: &dyn Clock,
     ) -> Self {
         Self {
             version: PROTOCOL_VERSION,
@@ -183,25 +221,40 @@ impl PacketHeader {
             payload_length: 0,
             session_id,
             sequence_number,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as u32,
+            timestamp: clock.unix_secs() as u32,
             mimicry_profile,
             padding_length: 0,
+            extensions: Extensions::new(),
         }
     }
 
+    /// Добавить extension header и установить флаг EXTENSIONS
+    pub fn add_extension(&mut self, extension: Extension) -> Result<()> {
+        self.extensions.insert(extension)?;
+        self.flags.insert(PacketFlags::EXTENSIONS);
+        Ok(())
+    }
+
+    /// Размер заголовка на проводе с учётом extension headers
+    pub fn wire_size(&self) -> usize {
+        HEADER_SIZE + self.extensions.wire_size()
+    }
+
     /// Сериализовать заголовок в байты
     pub fn serialize(&self, buf: &mut BytesMut) {
+        // Флаг EXTENSIONS всегда соответствует наличию блока расширений
+        let mut flags = self.flags;
+        flags.set(PacketFlags::EXTENSIONS, !self.extensions.is_empty());
+
         buf.put_u8(self.version);
-        buf.put_u8(self.flags.bits());
+        buf.put_u8(flags.bits());
         buf.put_u16(self.payload_length);
         buf.put_u64(self.session_id);
-        buf.put_u32(self.sequence_number);
+        buf.put_u64(self.sequence_number);
         buf.put_u32(self.timestamp);
         buf.put_u16(self.mimicry_profile.to_u16());
         buf.put_u16(self.padding_length);
+        self.extensions.serialize(buf);
     }
 
     /// Десериализовать заголовок из байтов
@@ -225,7 +278,7 @@ impl PacketHeader {
 
         let payload_length = buf.get_u16();
         let session_id = buf.get_u64();
-        let sequence_number = buf.get_u32();
+        let sequence_number = buf.get_u64();
         let timestamp = buf.get_u32();
 
         let mimicry_profile_id = buf.get_u16();
@@ -243,6 +296,18 @@ impl PacketHeader {
             .into());
         }
 
+        let extensions = if flags.contains(PacketFlags::EXTENSIONS) {
+            Extensions::deserialize(buf)?
+        } else {
+            Extensions::new()
+        };
+
+        // Фиктивный пакет не может быть keepalive
+        if flags.contains(PacketFlags::KEEPALIVE) && extensions.get(ExtensionType::COVER).is_some()
+        {
+            return Err(PacketError::InvalidFlags(flags_bits).into());
+        }
+
         Ok(Self {
             version,
             flags,
@@ -252,6 +317,7 @@ impl PacketHeader {
             timestamp,
             mimicry_profile,
             padding_length,
+            extensions,
         })
     }
 }
@@ -304,7 +370,7 @@ impl LlpPacket {
 
     /// Получить общий размер пакета
     pub fn total_size(&self) -> usize {
-        HEADER_SIZE
+        self.header.wire_size()
             + self.encrypted_payload.len()
             + self.padding.len()
             + AUTH_TAG_SIZE
@@ -313,7 +379,7 @@ impl LlpPacket {
     /// Сериализовать пакет в байты
     ///
     /// # Формат
-    /// [Header][Encrypted Payload][Padding][Auth Tag]
+    /// [Header][Extensions][Encrypted Payload][Padding][Auth Tag]
     pub fn serialize(&self) -> Result<Bytes> {
         let total_size = self.total_size();
         if total_size > MAX_PACKET_SIZE {
@@ -357,7 +423,8 @@ impl LlpPacket {
         let header = PacketHeader::deserialize(&mut cursor)?;
 
         // Вычисляем ожидаемый размер пакета
-        let expected_size = HEADER_SIZE
+        let header_size = header.wire_size();
+        let expected_size = header_size
             + header.payload_length as usize
             + header.padding_length as usize
             + AUTH_TAG_SIZE;
@@ -371,7 +438,7 @@ impl LlpPacket {
         }
 
         // Извлекаем payload
-        buf.advance(HEADER_SIZE);
+        buf.advance(header_size);
         let encrypted_payload = buf.split_to(header.payload_length as usize);
 
         // Извлекаем padding
@@ -424,6 +491,15 @@ impl LlpPacket {
         self.header.flags.contains(PacketFlags::KEEPALIVE)
     }
 
+    /// Проверить, является ли пакет фиктивным (cover traffic)
+    ///
+    /// Фиктивный пакет — CONTROL пакет с расширением
+    /// [`ExtensionType::COVER`]; получатель отбрасывает его после проверки
+    /// auth tag.
+    pub fn is_cover(&self) -> bool {
+        self.header.extensions.get(ExtensionType::COVER).is_some()
+    }
+
     /// Проверить, требуется ли rekey
     pub fn is_rekey(&self) -> bool {
         self.header.flags.contains(PacketFlags::REKEY)
@@ -469,6 +545,12 @@ mod tests {
         );
         assert_eq!(MimicryProfile::VkVideo.to_u16(), 1);
         assert_eq!(MimicryProfile::from_u16(999), None);
+
+        assert_eq!(
+            MimicryProfile::from_u16(0x8001),
+            Some(MimicryProfile::Custom(0x8001))
+        );
+        assert_eq!(MimicryProfile::Custom(0x8001).to_u16(), 0x8001);
     }
 
     #[test]
@@ -476,7 +558,7 @@ mod tests {
         let header = PacketHeader::new(
             PacketFlags::DATA,
             12345,
-            67890,
+            (1 << 40) + 67890,
             MimicryProfile::VkVideo,
         );
 
@@ -491,10 +573,66 @@ mod tests {
         assert_eq!(deserialized.version, PROTOCOL_VERSION);
         assert_eq!(deserialized.flags, PacketFlags::DATA);
         assert_eq!(deserialized.session_id, 12345);
-        assert_eq!(deserialized.sequence_number, 67890);
+        assert_eq!(deserialized.sequence_number, (1 << 40) + 67890);
         assert_eq!(deserialized.mimicry_profile, MimicryProfile::VkVideo);
     }
 
+    #[test]
+    fn test_header_with_extensions() {
+        use crate::extension::{Extension, ExtensionType};
+
+        let mut header = PacketHeader::new(PacketFlags::DATA, 1, 2, MimicryProfile::None);
+        header.add_extension(Extension::connection_id(&[0xAA; 8])).unwrap();
+        header.add_extension(Extension::ecn(1)).unwrap();
+        assert!(header.flags.contains(PacketFlags::EXTENSIONS));
+
+        let mut buf = BytesMut::new();
+        header.serialize(&mut buf);
+        assert_eq!(buf.len(), header.wire_size());
+        assert_eq!(buf.len(), HEADER_SIZE + 2 + 12 + 5);
+
+        let mut cursor = buf.freeze();
+        let deserialized = PacketHeader::deserialize(&mut cursor).unwrap();
+        assert_eq!(deserialized, header);
+        assert!(deserialized.extensions.get(ExtensionType::ECN).is_some());
+    }
+
+    #[test]
+    fn test_packet_with_extensions_round_trip() {
+        use crate::extension::Extension;
+
+        let mut header = PacketHeader::new(PacketFlags::DATA, 7, 8, MimicryProfile::VkVideo);
+        header.add_extension(Extension::path_id(2)).unwrap();
+
+        let packet = LlpPacket::new(
+            header,
+            Bytes::from_static(b"payload"),
+            Bytes::from_static(b"pad"),
+            [0x11u8; AUTH_TAG_SIZE],
+        )
+        .unwrap();
+
+        let serialized = packet.serialize().unwrap();
+        assert_eq!(serialized.len(), packet.total_size());
+
+        let deserialized = LlpPacket::deserialize(&serialized).unwrap();
+        assert_eq!(deserialized.header.extensions, packet.header.extensions);
+        assert_eq!(&deserialized.encrypted_payload[..], b"payload");
+        assert_eq!(&deserialized.padding[..], b"pad");
+    }
+
+    #[test]
+    fn test_extension_flag_without_block_rejected() {
+        let header = PacketHeader::new(PacketFlags::DATA, 1, 2, MimicryProfile::None);
+        let mut buf = BytesMut::new();
+        header.serialize(&mut buf);
+
+        // Флаг установлен, но блок расширений отсутствует
+        buf[1] |= PacketFlags::EXTENSIONS.bits();
+        let mut cursor = buf.freeze();
+        assert!(PacketHeader::deserialize(&mut cursor).is_err());
+    }
+
     #[test]
     fn test_packet_serialization_deserialization() {
         let header = PacketHeader::new(
@@ -556,7 +694,7 @@ mod tests {
         buf.put_u8(PacketFlags::DATA.bits());
         buf.put_u16(0);
         buf.put_u64(0);
-        buf.put_u32(0);
+        buf.put_u64(0);
         buf.put_u32(0);
         buf.put_u16(0);
         buf.put_u16(0);
@@ -598,6 +736,39 @@ mod tests {
 
         assert!(keepalive_packet.is_keepalive());
         assert!(!keepalive_packet.is_data());
+        assert!(!keepalive_packet.is_cover());
+        assert!(!packet.is_cover());
+
+        // Фиктивный пакет: CONTROL с расширением COVER
+        let mut cover_header = PacketHeader::new(PacketFlags::CONTROL, 1, 2, MimicryProfile::None);
+        cover_header.add_extension(Extension::cover()).unwrap();
+        let cover_packet = LlpPacket::new(
+            cover_header,
+            Bytes::new(),
+            Bytes::new(),
+            [0u8; AUTH_TAG_SIZE],
+        )
+        .unwrap();
+        assert!(cover_packet.is_cover());
+        assert!(!cover_packet.is_keepalive());
+        assert!(!cover_packet.is_data());
+    }
+
+    #[test]
+    fn test_cover_keepalive_exclusive() {
+        // Ни один флаг не означает «фиктивный»: keepalive со всеми
+        // флагами не становится фиктивным пакетом
+        let all = PacketHeader::new(PacketFlags::all(), 1, 2, MimicryProfile::None);
+        let packet = LlpPacket::new(all, Bytes::new(), Bytes::new(), [0u8; AUTH_TAG_SIZE]).unwrap();
+        assert!(packet.is_keepalive());
+        assert!(!packet.is_cover());
+
+        // Заголовок keepalive с расширением COVER отвергается
+        let mut header = PacketHeader::new(PacketFlags::KEEPALIVE, 1, 2, MimicryProfile::None);
+        header.add_extension(Extension::cover()).unwrap();
+        let mut buf = BytesMut::new();
+        header.serialize(&mut buf);
+        assert!(PacketHeader::deserialize(&mut buf.freeze()).is_err());
     }
 
     #[test]
@@ -7,15 +7,32 @@
 //! - Timeout и keepalive
 //! - Rekey mechanism
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
+use bytes::{Bytes, BytesMut};
+
+use crate::alert::Alert;
+use crate::clock::{SharedClock, SystemClock};
 use crate::crypto::{AeadCipher, SessionKey};
-use crate::error::{Result, SessionError};
-use crate::packet::MimicryProfile;
+use crate::error::{CryptoError, Result, SessionError};
+use crate::extension::Extensions;
+use crate::packet::{LlpPacket, MimicryProfile, PacketFlags, PacketHeader, AUTH_TAG_SIZE};
+
+/// Размер окна для replay protection по умолчанию (количество пакетов)
+pub const DEFAULT_REPLAY_WINDOW_SIZE: usize = 2048;
+
+/// Минимальный размер окна replay protection
+pub const MIN_REPLAY_WINDOW_SIZE: usize = 64;
 
-/// Размер окна для replay protection (количество пакетов)
-const REPLAY_WINDOW_SIZE: usize = 256;
+/// Максимальный размер окна replay protection
+pub const MAX_REPLAY_WINDOW_SIZE: usize = 1 << 16;
+
+/// Номер пакета, после которого сессии нужен rekey
+///
+/// Предел использования ключа ChaCha20-Poly1305 (2^62 пакетов, как в
+/// RFC 9001 §6.6); сам номер и nonce занимают 64 бита.
+const REKEY_SEQUENCE: u64 = 1 << 62;
 
 /// Максимальное количество одновременных сессий
 const MAX_SESSIONS: usize = 1000;
@@ -51,12 +68,14 @@ pub struct Session {
     last_activity: Instant,
     /// Время последнего полученного keepalive
     last_keepalive: Instant,
-    /// Счётчик отправленных пакетов
-    tx_sequence: u32,
+    /// Счётчик отправленных пакетов (номер следующего пакета и nonce)
+    tx_sequence: u64,
     /// Окно для replay protection входящих пакетов
     rx_replay_window: ReplayWindow,
     /// Требуется ли rekey
     rekey_required: bool,
+    /// Источник времени
+    clock: SharedClock,
 }
 
 impl Session {
@@ -65,10 +84,42 @@ impl Session {
         session_id: u64,
         session_key: SessionKey,
         mimicry_profile: MimicryProfile,
+    ) -> Self {
+        Self::with_replay_window(
+            session_id,
+            session_key,
+            mimicry_profile,
+            DEFAULT_REPLAY_WINDOW_SIZE,
+        )
+    }
+
+    /// Создать новую сессию с заданным размером окна replay protection
+    pub fn with_replay_window(
+        session_id: u64,
+        session_key: SessionKey,
+        mimicry_profile: MimicryProfile,
+        replay_window_size: usize,
+    ) -> Self {
+        Self::with_clock(
+            session_id,
+            session_key,
+            mimicry_profile,
+            replay_window_size,
+            SystemClock::shared(),
+        )
+    }
+
+    /// Создать новую сессию с внешним источником времени
+    pub fn with_clock(
+        session_id: u64,
+        session_key: SessionKey,
+        mimicry_profile: MimicryProfile,
+        replay_window_size: usize,
+        clock: SharedClock,
     ) -> Self {
         let tx_cipher = AeadCipher::new(&session_key, session_id);
         let rx_cipher = AeadCipher::new(&session_key, session_id);
-        let now = Instant::now();
+        let now = clock.now();
 
         Self {
             session_id,
@@ -80,8 +131,9 @@ impl Session {
             last_activity: now,
             last_keepalive: now,
             tx_sequence: 0,
-            rx_replay_window: ReplayWindow::new(REPLAY_WINDOW_SIZE),
+            rx_replay_window: ReplayWindow::new(replay_window_size),
             rekey_required: false,
+            clock,
         }
     }
 
@@ -97,10 +149,11 @@ impl Session {
 
     /// Зашифровать payload для отправки
     ///
-    /// Возвращает (encrypted_payload, sequence_number)
-    pub fn encrypt_payload(&mut self, plaintext: &[u8], aad: &[u8]) -> Result<(Vec<u8>, u32)> {
-        let ciphertext = self.tx_cipher.encrypt(plaintext, aad)?;
+    /// Nonce строится из sequence number пакета. Возвращает
+    /// (encrypted_payload, sequence_number)
+    pub fn encrypt_payload(&mut self, plaintext: &[u8], aad: &[u8]) -> Result<(Vec<u8>, u64)> {
         let sequence = self.tx_sequence;
+        let ciphertext = self.tx_cipher.encrypt_at(plaintext, aad, sequence)?;
 
         self.tx_sequence = self
             .tx_sequence
@@ -109,12 +162,12 @@ impl Session {
                 session_id: self.session_id,
             })?;
 
-        // Проверка на необходимость rekey (каждые 2^31 пакетов)
-        if self.tx_sequence >= (1u32 << 31) {
+        // Проверка на необходимость rekey
+        if self.tx_sequence >= REKEY_SEQUENCE {
             self.rekey_required = true;
         }
 
-        self.last_activity = Instant::now();
+        self.last_activity = self.clock.now();
         Ok((ciphertext, sequence))
     }
 
@@ -123,10 +176,10 @@ impl Session {
         &mut self,
         ciphertext: &[u8],
         aad: &[u8],
-        sequence_number: u32,
+        sequence_number: u64,
     ) -> Result<Vec<u8>> {
         // Replay protection: проверка через sliding window
-        if !self.rx_replay_window.check_and_update(sequence_number) {
+        if !self.rx_replay_window.check(sequence_number) {
             return Err(SessionError::DuplicateSequenceNumber {
                 session_id: self.session_id,
                 seq: sequence_number,
@@ -136,20 +189,114 @@ impl Session {
 
         let plaintext = self
             .rx_cipher
-            .decrypt(ciphertext, aad, sequence_number as u64)?;
+            .decrypt(ciphertext, aad, sequence_number)?;
+
+        // Окно сдвигается только после успешной аутентификации пакета
+        self.rx_replay_window.update(sequence_number);
 
-        self.last_activity = Instant::now();
-        self.last_keepalive = Instant::now();
+        let now = self.clock.now();
+        self.last_activity = now;
+        self.last_keepalive = now;
 
         Ok(plaintext)
     }
 
+    /// Зашифровать payload и собрать LLP пакет
+    ///
+    /// Заголовок (с итоговыми длинами) используется как AAD,
+    /// auth tag переносится в отдельное поле пакета.
+    pub fn seal_packet(&mut self, flags: PacketFlags, plaintext: &[u8]) -> Result<LlpPacket> {
+        self.seal_packet_with_extensions(flags, Extensions::new(), plaintext)
+    }
+
+    /// Зашифровать payload и собрать LLP пакет с extension headers
+    ///
+    /// Блок расширений сериализуется вместе с заголовком и поэтому
+    /// аутентифицируется как часть AAD.
+    pub fn seal_packet_with_extensions(
+        &mut self,
+        flags: PacketFlags,
+        extensions: Extensions,
+        plaintext: &[u8],
+    ) -> Result<LlpPacket> {
+        let mut header = PacketHeader::with_clock(
+            flags,
+            self.session_id,
+            self.tx_sequence,
+            self.mimicry_profile,
+            self.clock.as_ref(),
+        );
+        header.flags.set(PacketFlags::EXTENSIONS, !extensions.is_empty());
+        header.extensions = extensions;
+        header.payload_length = plaintext.len() as u16;
+        header.padding_length = 0;
+
+        let mut aad = BytesMut::new();
+        header.serialize(&mut aad);
+
+        let (mut ciphertext, _) = self.encrypt_payload(plaintext, &aad)?;
+        let tag_offset = ciphertext.len() - AUTH_TAG_SIZE;
+
+        let mut auth_tag = [0u8; AUTH_TAG_SIZE];
+        auth_tag.copy_from_slice(&ciphertext[tag_offset..]);
+        ciphertext.truncate(tag_offset);
+
+        LlpPacket::new(header, Bytes::from(ciphertext), Bytes::new(), auth_tag)
+    }
+
+    /// Проверить и расшифровать LLP пакет, собранный [`Session::seal_packet`]
+    pub fn open_packet(&mut self, packet: &LlpPacket) -> Result<Vec<u8>> {
+        if packet.header.session_id != self.session_id {
+            return Err(CryptoError::AuthenticationError.into());
+        }
+
+        self.validate_timestamp(packet.header.timestamp)?;
+
+        let mut header = packet.header.clone();
+        header.payload_length = packet.encrypted_payload.len() as u16;
+        header.padding_length = packet.padding.len() as u16;
+
+        let mut aad = BytesMut::new();
+        header.serialize(&mut aad);
+
+        let mut ciphertext =
+            Vec::with_capacity(packet.encrypted_payload.len() + AUTH_TAG_SIZE);
+        ciphertext.extend_from_slice(&packet.encrypted_payload);
+        ciphertext.extend_from_slice(&packet.auth_tag);
+
+        self.decrypt_payload(&ciphertext, &aad, header.sequence_number)
+    }
+
+    /// Зашифровать alert в CONTROL пакет
+    pub fn seal_alert(&mut self, alert: &Alert) -> Result<LlpPacket> {
+        self.seal_packet(PacketFlags::CONTROL, &alert.serialize())
+    }
+
+    /// Обработать alert в расшифрованном пакете
+    ///
+    /// Возвращает `Ok(None)`, если пакет не является alert,
+    /// `Ok(Some(alert))` для предупреждений и
+    /// [`SessionError::ClosedByPeer`] для fatal alert.
+    pub fn process_alert(&self, flags: PacketFlags, plaintext: &[u8]) -> Result<Option<Alert>> {
+        if !flags.contains(PacketFlags::CONTROL) || !Alert::is_alert(plaintext) {
+            return Ok(None);
+        }
+
+        let alert = Alert::deserialize(plaintext)?;
+        if alert.is_fatal() {
+            return Err(SessionError::ClosedByPeer {
+                session_id: self.session_id,
+                code: alert.code,
+            }
+            .into());
+        }
+
+        Ok(Some(alert))
+    }
+
     /// Проверить timestamp пакета
     pub fn validate_timestamp(&self, packet_timestamp: u32) -> Result<()> {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as i64;
+        let now = self.clock.unix_secs() as i64;
 
         let packet_time = packet_timestamp as i64;
         let delta = (now - packet_time).abs();
@@ -167,17 +314,17 @@ impl Session {
 
     /// Проверить, истекла ли сессия
     pub fn is_expired(&self, lifetime: Duration) -> bool {
-        self.created_at.elapsed() > lifetime
+        self.elapsed_since(self.created_at) > lifetime
     }
 
     /// Проверить, требуется ли keepalive
     pub fn needs_keepalive(&self) -> bool {
-        self.last_activity.elapsed() > KEEPALIVE_INTERVAL
+        self.elapsed_since(self.last_activity) > KEEPALIVE_INTERVAL
     }
 
     /// Проверить keepalive timeout
     pub fn is_keepalive_timeout(&self) -> bool {
-        self.last_keepalive.elapsed() > KEEPALIVE_TIMEOUT
+        self.elapsed_since(self.last_keepalive) > KEEPALIVE_TIMEOUT
     }
 
     /// Проверить, требуется ли rekey
@@ -187,102 +334,148 @@ impl Session {
 
     /// Отметить, что keepalive получен
     pub fn mark_keepalive_received(&mut self) {
-        self.last_keepalive = Instant::now();
-        self.last_activity = Instant::now();
+        let now = self.clock.now();
+        self.last_keepalive = now;
+        self.last_activity = now;
     }
 
     /// Получить время с последней активности
     pub fn idle_time(&self) -> Duration {
-        self.last_activity.elapsed()
+        self.elapsed_since(self.last_activity)
     }
 
     /// Получить текущий TX sequence number
-    pub fn current_tx_sequence(&self) -> u32 {
+    pub fn current_tx_sequence(&self) -> u64 {
         self.tx_sequence
     }
+
+    /// Окно replay protection входящих пакетов
+    pub fn replay_window(&self) -> &ReplayWindow {
+        &self.rx_replay_window
+    }
+
+    /// Получить источник времени сессии
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    /// Время, прошедшее с указанного момента по часам сессии
+    fn elapsed_since(&self, instant: Instant) -> Duration {
+        self.clock.now().saturating_duration_since(instant)
+    }
 }
 
 /// Sliding window для replay protection
 ///
-/// Использует битовую маску для отслеживания полученных пакетов
-/// в окне размером REPLAY_WINDOW_SIZE.
-struct ReplayWindow {
-    /// Максимальный полученный sequence number
-    highest_seq: u32,
-    /// Битовая маска для отслеживания полученных пакетов
-    window: VecDeque<bool>,
-    /// Размер окна
-    window_size: usize,
+/// Битовая карта фиксированного размера поверх 64-битного счётчика.
+/// Биты хранятся в кольцевом буфере слов `u64`, поэтому сдвиг окна
+/// стоит O(1) относительно величины скачка sequence number: очищаются
+/// только слова, вышедшие за пределы окна (не больше их общего числа).
+#[derive(Debug, Clone)]
+pub struct ReplayWindow {
+    /// Максимальный принятый sequence number
+    highest_seq: u64,
+    /// Кольцевая битовая карта принятых пакетов
+    bitmap: Box<[u64]>,
+    /// Размер окна в битах
+    window_size: u64,
 }
 
 impl ReplayWindow {
-    /// Создать новое окно
-    fn new(window_size: usize) -> Self {
+    /// Создать новое окно размером `window_size` бит
+    ///
+    /// Размер округляется вверх до кратного 64 и ограничивается
+    /// диапазоном [`MIN_REPLAY_WINDOW_SIZE`, `MAX_REPLAY_WINDOW_SIZE`].
+    pub fn new(window_size: usize) -> Self {
+        let window_size = window_size
+            .clamp(MIN_REPLAY_WINDOW_SIZE, MAX_REPLAY_WINDOW_SIZE)
+            .next_multiple_of(64);
+
+        // Одно дополнительное слово, чтобы частично заполненное текущее
+        // слово не вытесняло самые старые номера из окна
+        let words = window_size / 64 + 1;
+
         Self {
             highest_seq: 0,
-            window: VecDeque::with_capacity(window_size),
-            window_size,
+            bitmap: vec![0u64; words].into_boxed_slice(),
+            window_size: window_size as u64,
         }
     }
 
-    /// Проверить и обновить окно
+    /// Размер окна в битах
+    pub fn window_size(&self) -> usize {
+        self.window_size as usize
+    }
+
+    /// Максимальный принятый sequence number
+    pub fn highest_seq(&self) -> u64 {
+        self.highest_seq
+    }
+
+    /// Проверить sequence number, не изменяя окно
     ///
-    /// Возвращает true, если пакет новый и должен быть обработан.
-    /// Возвращает false, если пакет дублирующийся (replay attack).
-    fn check_and_update(&mut self, seq: u32) -> bool {
-        // Первый пакет
-        if self.window.is_empty() {
-            self.highest_seq = seq;
-            self.window.push_back(true);
+    /// Возвращает false для дубликатов и пакетов старше окна.
+    /// Используется до аутентификации пакета, чтобы поддельные
+    /// пакеты не могли сдвинуть окно.
+    pub fn check(&self, seq: u64) -> bool {
+        if seq > self.highest_seq {
             return true;
         }
 
-        // Пакет с sequence больше текущего максимума
-        if seq > self.highest_seq {
-            let diff = (seq - self.highest_seq) as usize;
-
-            if diff >= self.window_size {
-                // Окно сдвигается полностью
-                self.window.clear();
-                self.window.push_back(true);
-            } else {
-                // Добавляем false для пропущенных пакетов
-                for _ in 0..diff - 1 {
-                    self.window.push_back(false);
-                    if self.window.len() > self.window_size {
-                        self.window.pop_front();
-                    }
-                }
-                // Добавляем текущий пакет
-                self.window.push_back(true);
-                if self.window.len() > self.window_size {
-                    self.window.pop_front();
-                }
-            }
-
-            self.highest_seq = seq;
-            return true;
+        if self.highest_seq - seq >= self.window_size {
+            return false;
         }
 
-        // Пакет внутри окна
-        let diff = (self.highest_seq - seq) as usize;
+        let (word, bit) = self.position(seq);
+        self.bitmap[word] & bit == 0
+    }
 
-        if diff >= self.window.len() {
-            // Пакет слишком старый, вне окна
+    /// Отметить sequence number как принятый
+    ///
+    /// Вызывается после успешной расшифровки пакета.
+    /// Возвращает false, если номер уже был принят или вне окна.
+    pub fn update(&mut self, seq: u64) -> bool {
+        if !self.check(seq) {
             return false;
         }
 
-        let index = self.window.len() - 1 - diff;
+        if seq > self.highest_seq {
+            let words = self.bitmap.len() as u64;
+            let current = self.highest_seq / 64;
+            let target = seq / 64;
+            let advance = (target - current).min(words);
+
+            for i in 1..=advance {
+                self.bitmap[((current + i) % words) as usize] = 0;
+            }
 
-        // Проверка дубликата
-        if self.window[index] {
-            return false; // Дубликат
+            self.highest_seq = seq;
         }
 
-        // Отмечаем пакет как полученный
-        self.window[index] = true;
+        let (word, bit) = self.position(seq);
+        self.bitmap[word] |= bit;
         true
     }
+
+    /// Проверить и обновить окно за один шаг
+    ///
+    /// Возвращает true, если пакет новый и должен быть обработан.
+    /// Возвращает false, если пакет дублирующийся (replay attack).
+    pub fn check_and_update(&mut self, seq: u64) -> bool {
+        self.update(seq)
+    }
+
+    /// Индекс слова и маска бита для sequence number
+    fn position(&self, seq: u64) -> (usize, u64) {
+        let word = (seq / 64) % self.bitmap.len() as u64;
+        (word as usize, 1u64 << (seq % 64))
+    }
+}
+
+impl Default for ReplayWindow {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPLAY_WINDOW_SIZE)
+    }
 }
 
 /// Менеджер сессий
@@ -291,6 +484,10 @@ pub struct SessionManager {
     sessions: HashMap<u64, Session>,
     /// Время жизни сессии
     session_lifetime: Duration,
+    /// Размер окна replay protection для новых сессий
+    replay_window_size: usize,
+    /// Источник времени для новых сессий
+    clock: SharedClock,
 }
 
 impl SessionManager {
@@ -299,6 +496,8 @@ impl SessionManager {
         Self {
             sessions: HashMap::new(),
             session_lifetime: DEFAULT_SESSION_LIFETIME,
+            replay_window_size: DEFAULT_REPLAY_WINDOW_SIZE,
+            clock: SystemClock::shared(),
         }
     }
 
@@ -307,9 +506,21 @@ impl SessionManager {
         Self {
             sessions: HashMap::new(),
             session_lifetime,
+            replay_window_size: DEFAULT_REPLAY_WINDOW_SIZE,
+            clock: SystemClock::shared(),
         }
     }
 
+    /// Установить размер окна replay protection для новых сессий
+    pub fn set_replay_window_size(&mut self, replay_window_size: usize) {
+        self.replay_window_size = replay_window_size;
+    }
+
+    /// Установить источник времени для новых сессий
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
+    }
+
     /// Добавить новую сессию
     pub fn add_session(
         &mut self,
@@ -331,7 +542,13 @@ impl SessionManager {
             return Err(SessionError::SessionAlreadyExists { session_id }.into());
         }
 
-        let session = Session::new(session_id, session_key, mimicry_profile);
+        let session = Session::with_clock(
+            session_id,
+            session_key,
+            mimicry_profile,
+            self.replay_window_size,
+            self.clock.clone(),
+        );
         self.sessions.insert(session_id, session);
 
         Ok(())
@@ -361,14 +578,40 @@ impl SessionManager {
 
     /// Очистить истёкшие сессии
     pub fn cleanup_expired(&mut self) -> usize {
-        let initial_count = self.sessions.len();
+        self.take_expired().len()
+    }
 
-        self.sessions.retain(|_, session| {
-            !session.is_expired(self.session_lifetime)
-                && !session.is_keepalive_timeout()
+    /// Удалить истёкшие сессии и вернуть причину удаления каждой
+    ///
+    /// Причина — [`SessionError::SessionExpired`] или
+    /// [`SessionError::KeepaliveTimeout`]; по ней можно построить alert.
+    pub fn take_expired(&mut self) -> Vec<SessionError> {
+        let mut expired = Vec::new();
+        let lifetime = self.session_lifetime;
+
+        self.sessions.retain(|&session_id, session| {
+            match expiry_reason(session_id, session, lifetime) {
+                Some(reason) => {
+                    expired.push(reason);
+                    false
+                }
+                None => true,
+            }
         });
 
-        initial_count - self.sessions.len()
+        expired
+    }
+
+    /// Истёкшие сессии без удаления
+    ///
+    /// Позволяет сначала зашифровать alert ключом сессии, а затем удалить её.
+    pub fn expired(&self) -> Vec<SessionError> {
+        self.sessions
+            .iter()
+            .filter_map(|(&session_id, session)| {
+                expiry_reason(session_id, session, self.session_lifetime)
+            })
+            .collect()
     }
 
     /// Получить список сессий, требующих keepalive
@@ -406,6 +649,17 @@ impl Default for SessionManager {
     }
 }
 
+/// Причина истечения сессии: срок жизни или отсутствие keepalive
+fn expiry_reason(session_id: u64, session: &Session, lifetime: Duration) -> Option<SessionError> {
+    if session.is_expired(lifetime) {
+        Some(SessionError::SessionExpired { session_id })
+    } else if session.is_keepalive_timeout() {
+        Some(SessionError::KeepaliveTimeout { session_id })
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -439,6 +693,25 @@ mod tests {
         assert_eq!(&decrypted, plaintext);
     }
 
+    #[test]
+    fn test_sequence_beyond_32_bits() {
+        let key = SessionKey::random(&mut OsRng);
+        let mut client = Session::new(7, key.clone(), MimicryProfile::None);
+        let mut server = Session::new(7, key, MimicryProfile::None);
+
+        // Номер пакета, заголовок и nonce не обрываются на 2^32
+        client.tx_sequence = u64::from(u32::MAX) + 5;
+        let packet = client.seal_packet(PacketFlags::DATA, b"far").unwrap();
+        assert_eq!(packet.header.sequence_number, u64::from(u32::MAX) + 5);
+        let received = LlpPacket::deserialize(&packet.serialize().unwrap()).unwrap();
+        assert_eq!(server.open_packet(&received).unwrap(), b"far");
+        assert!(!client.needs_rekey());
+
+        client.tx_sequence = REKEY_SEQUENCE - 1;
+        client.seal_packet(PacketFlags::DATA, b"last").unwrap();
+        assert!(client.needs_rekey());
+    }
+
     #[test]
     fn test_replay_protection() {
         let mut rng = OsRng;
@@ -484,6 +757,88 @@ mod tests {
         assert!(!window.check_and_update(10));
     }
 
+    #[test]
+    fn test_replay_window_large_jump() {
+        let mut window = ReplayWindow::new(2048);
+
+        assert!(window.check_and_update(5));
+
+        // Скачок далеко за пределы окна не требует O(gap) работы
+        let far = u64::MAX - 10;
+        assert!(window.check_and_update(far));
+        assert_eq!(window.highest_seq(), far);
+
+        // Старые номера теперь вне окна
+        assert!(!window.check_and_update(5));
+
+        // Номера позади максимума, но в окне, принимаются один раз
+        assert!(window.check_and_update(far - 2047));
+        assert!(!window.check_and_update(far - 2047));
+        assert!(!window.check_and_update(far - 2048));
+    }
+
+    #[test]
+    fn test_replay_window_reordering() {
+        let mut window = ReplayWindow::new(2048);
+
+        // Пакеты в обратном порядке внутри окна
+        for seq in (0..2048u64).rev() {
+            assert!(window.check_and_update(seq), "seq {} отклонён", seq);
+        }
+        for seq in 0..2048u64 {
+            assert!(!window.check_and_update(seq), "дубликат {} принят", seq);
+        }
+
+        // Сдвиг на половину окна освобождает новые номера,
+        // но не возвращает старые дубликаты
+        assert!(window.check_and_update(3071));
+        assert!(window.check_and_update(3000));
+        assert!(!window.check_and_update(1023));
+        assert!(!window.check_and_update(1100));
+    }
+
+    #[test]
+    fn test_replay_window_check_does_not_mutate() {
+        let mut window = ReplayWindow::new(64);
+
+        assert!(window.check(100));
+        assert!(window.check(100));
+        assert_eq!(window.highest_seq(), 0);
+
+        assert!(window.update(100));
+        assert!(!window.check(100));
+    }
+
+    #[test]
+    fn test_replay_window_size_rounding() {
+        assert_eq!(ReplayWindow::new(1).window_size(), MIN_REPLAY_WINDOW_SIZE);
+        assert_eq!(ReplayWindow::new(100).window_size(), 128);
+        assert_eq!(ReplayWindow::default().window_size(), DEFAULT_REPLAY_WINDOW_SIZE);
+        assert_eq!(
+            ReplayWindow::new(usize::MAX).window_size(),
+            MAX_REPLAY_WINDOW_SIZE
+        );
+    }
+
+    #[test]
+    fn test_forged_packet_does_not_advance_window() {
+        let mut rng = OsRng;
+        let key = SessionKey::random(&mut rng);
+        let mut session = Session::new(1, key, MimicryProfile::None);
+
+        let (ciphertext, seq) = session.encrypt_payload(b"data", b"aad").unwrap();
+
+        // Поддельный пакет с большим sequence number не проходит аутентификацию
+        assert!(session
+            .decrypt_payload(b"garbage ciphertext", b"aad", 1_000_000)
+            .is_err());
+
+        // Легитимный пакет по-прежнему принимается
+        assert!(session
+            .decrypt_payload(&ciphertext, b"aad", u64::from(seq))
+            .is_ok());
+    }
+
     #[test]
     fn test_session_manager() {
         let mut rng = OsRng;
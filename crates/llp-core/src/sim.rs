@@ -0,0 +1,413 @@
+//! Детерминированная симуляция протокола в виртуальном времени
+//!
+//! [`SimulatedLink`] соединяет клиента и сервер напрямую, без сети:
+//! handshake выполняется с RNG, инициализированным фиксированным seed,
+//! а обе сессии используют общие [`SimulatedClock`]. Это позволяет
+//! прогнать часы работы протокола (keepalive, timeout, истечение сессий)
+//! за миллисекунды реального времени и получить воспроизводимый результат.
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::alert::Alert;
+use crate::clock::SimulatedClock;
+use crate::error::Result;
+use crate::handshake::{ClientHandshake, ServerHandshake};
+use crate::packet::{LlpPacket, MimicryProfile, PacketFlags};
+use crate::session::{Session, DEFAULT_REPLAY_WINDOW_SIZE};
+
+/// Направление передачи в симуляции
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// От клиента к серверу
+    ClientToServer,
+    /// От сервера к клиенту
+    ServerToClient,
+}
+
+/// Статистика прогона симуляции
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimStats {
+    /// Доставлено пакетов с данными
+    pub data_packets: u64,
+    /// Доставлено keepalive пакетов
+    pub keepalives: u64,
+    /// Передано байт на проводе (сериализованные LLP пакеты)
+    pub wire_bytes: u64,
+    /// Прошло виртуального времени
+    pub elapsed: Duration,
+}
+
+/// Пара клиент-сервер, соединённая в виртуальном времени
+pub struct SimulatedLink {
+    /// Общие виртуальные часы
+    clock: SimulatedClock,
+    /// Детерминированный генератор случайных чисел
+    rng: StdRng,
+    /// Сессия клиента
+    client: Session,
+    /// Сессия сервера
+    server: Session,
+    /// Накопленная статистика
+    stats: SimStats,
+}
+
+impl SimulatedLink {
+    /// Выполнить handshake и создать соединённую пару сессий
+    ///
+    /// Одинаковые `seed` и `start_unix_secs` дают побайтно одинаковые
+    /// ключи, session_id и пакеты.
+    pub fn establish(seed: u64, start_unix_secs: u64, profile: MimicryProfile) -> Result<Self> {
+        let clock = SimulatedClock::new(start_unix_secs);
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let session_id = rand::Rng::gen::<u64>(&mut rng);
+        let mut client_hs = ClientHandshake::new(&mut rng, profile);
+        let mut server_hs = ServerHandshake::new(&mut rng, session_id);
+
+        let client_hello = client_hs.start(&mut rng)?;
+        let (server_hello, profile) = server_hs.process_client_hello(&mut rng, &client_hello)?;
+        client_hs.process_server_hello(&server_hello)?;
+        let client_verify = client_hs.send_client_verify()?;
+        server_hs.process_client_verify(&client_verify)?;
+        let server_verify = server_hs.send_server_verify()?;
+        client_hs.process_server_verify(&server_verify)?;
+
+        let client_key = client_hs
+            .session_key()
+            .ok_or("Клиент не получил сессионный ключ")?
+            .clone();
+        let server_key = server_hs
+            .session_key()
+            .ok_or("Сервер не получил сессионный ключ")?
+            .clone();
+
+        let client = Session::with_clock(
+            session_id,
+            client_key,
+            profile,
+            DEFAULT_REPLAY_WINDOW_SIZE,
+            clock.shared(),
+        );
+        let server = Session::with_clock(
+            session_id,
+            server_key,
+            profile,
+            DEFAULT_REPLAY_WINDOW_SIZE,
+            clock.shared(),
+        );
+
+        Ok(Self {
+            clock,
+            rng,
+            client,
+            server,
+            stats: SimStats::default(),
+        })
+    }
+
+    /// Общие виртуальные часы
+    pub fn clock(&self) -> &SimulatedClock {
+        &self.clock
+    }
+
+    /// Детерминированный RNG симуляции
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Сессия клиента
+    pub fn client(&self) -> &Session {
+        &self.client
+    }
+
+    /// Сессия сервера
+    pub fn server(&self) -> &Session {
+        &self.server
+    }
+
+    /// Накопленная статистика
+    pub fn stats(&self) -> &SimStats {
+        &self.stats
+    }
+
+    /// Сдвинуть виртуальное время
+    pub fn advance(&mut self, delta: Duration) {
+        self.clock.advance(delta);
+        self.stats.elapsed += delta;
+    }
+
+    /// Передать пакет с данными и вернуть расшифрованный payload
+    pub fn send(&mut self, direction: Direction, payload: &[u8]) -> Result<Vec<u8>> {
+        let plaintext = self.transmit(direction, PacketFlags::DATA, payload)?;
+        self.stats.data_packets += 1;
+        Ok(plaintext)
+    }
+
+    /// Передать keepalive пакет
+    pub fn send_keepalive(&mut self, direction: Direction) -> Result<()> {
+        self.transmit(direction, PacketFlags::KEEPALIVE, &[])?;
+        self.stats.keepalives += 1;
+        Ok(())
+    }
+
+    /// Передать alert и обработать его на принимающей стороне
+    ///
+    /// Fatal alert возвращается как [`crate::error::SessionError::ClosedByPeer`].
+    pub fn send_alert(&mut self, direction: Direction, alert: &Alert) -> Result<Option<Alert>> {
+        let plaintext = self.transmit(direction, PacketFlags::CONTROL, &alert.serialize())?;
+        let receiver = match direction {
+            Direction::ClientToServer => &self.server,
+            Direction::ServerToClient => &self.client,
+        };
+        receiver.process_alert(PacketFlags::CONTROL, &plaintext)
+    }
+
+    /// Прогнать симуляцию на `duration` с шагом `tick`
+    ///
+    /// На каждом шаге вызывается `on_tick` (например, для генерации
+    /// трафика). Затем клиент отправляет keepalive, если его сессия
+    /// простаивает дольше интервала keepalive, а сервер отвечает на него
+    /// своим keepalive — иначе у клиента истёк бы keepalive timeout.
+    pub fn run<F>(&mut self, duration: Duration, tick: Duration, mut on_tick: F) -> Result<()>
+    where
+        F: FnMut(&mut Self) -> Result<()>,
+    {
+        let mut remaining = duration;
+
+        while !remaining.is_zero() {
+            let step = tick.min(remaining);
+            self.advance(step);
+            remaining -= step;
+
+            on_tick(self)?;
+
+            if self.client.needs_keepalive() {
+                self.send_keepalive(Direction::ClientToServer)?;
+                self.send_keepalive(Direction::ServerToClient)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Зашифровать, сериализовать, разобрать и расшифровать один пакет
+    fn transmit(
+        &mut self,
+        direction: Direction,
+        flags: PacketFlags,
+        payload: &[u8],
+    ) -> Result<Vec<u8>> {
+        let (sender, receiver) = match direction {
+            Direction::ClientToServer => (&mut self.client, &mut self.server),
+            Direction::ServerToClient => (&mut self.server, &mut self.client),
+        };
+
+        let packet = sender.seal_packet(flags, payload)?;
+        let wire: Bytes = packet.serialize()?;
+        self.stats.wire_bytes += wire.len() as u64;
+
+        let received = LlpPacket::deserialize(&wire)?;
+        receiver.open_packet(&received)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionManager;
+
+    const START: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_deterministic_establish() {
+        let mut a = SimulatedLink::establish(42, START, MimicryProfile::VkVideo).unwrap();
+        let mut b = SimulatedLink::establish(42, START, MimicryProfile::VkVideo).unwrap();
+
+        assert_eq!(a.client().session_id(), b.client().session_id());
+
+        let pa = a.client.seal_packet(PacketFlags::DATA, b"hello").unwrap();
+        let pb = b.client.seal_packet(PacketFlags::DATA, b"hello").unwrap();
+        assert_eq!(pa.serialize().unwrap(), pb.serialize().unwrap());
+
+        let c = SimulatedLink::establish(43, START, MimicryProfile::VkVideo).unwrap();
+        assert_ne!(a.client().session_id(), c.client().session_id());
+    }
+
+    #[test]
+    fn test_data_round_trip() {
+        let mut link = SimulatedLink::establish(1, START, MimicryProfile::None).unwrap();
+
+        let received = link.send(Direction::ClientToServer, b"ping").unwrap();
+        assert_eq!(received, b"ping");
+
+        let received = link.send(Direction::ServerToClient, b"pong").unwrap();
+        assert_eq!(received, b"pong");
+
+        assert_eq!(link.stats().data_packets, 2);
+    }
+
+    #[test]
+    fn test_hours_of_keepalive() {
+        let mut link = SimulatedLink::establish(7, START, MimicryProfile::RuTube).unwrap();
+
+        // 12 часов виртуального времени с шагом 1 секунда
+        link.run(Duration::from_secs(12 * 3600), Duration::from_secs(1), |_| Ok(()))
+            .unwrap();
+
+        assert_eq!(link.clock().elapsed(), Duration::from_secs(12 * 3600));
+        assert!(!link.client().is_keepalive_timeout());
+        assert!(!link.server().is_keepalive_timeout());
+
+        // Обмен keepalive происходит примерно раз в 31 секунду
+        let expected = 2 * 12 * 3600 / 31;
+        let keepalives = link.stats().keepalives;
+        assert!(keepalives >= expected - 10 && keepalives <= expected + 10);
+    }
+
+    #[test]
+    fn test_keepalive_timeout_without_traffic() {
+        let mut link = SimulatedLink::establish(3, START, MimicryProfile::None).unwrap();
+        link.send(Direction::ClientToServer, b"data").unwrap();
+
+        link.advance(Duration::from_secs(89));
+        assert!(!link.server().is_keepalive_timeout());
+
+        link.advance(Duration::from_secs(2));
+        assert!(link.server().is_keepalive_timeout());
+    }
+
+    #[test]
+    fn test_stale_timestamp_rejected() {
+        let mut link = SimulatedLink::establish(5, START, MimicryProfile::None).unwrap();
+
+        let packet = link.client.seal_packet(PacketFlags::DATA, b"late").unwrap();
+
+        // Пакет «задержался» в сети дольше допустимого drift
+        link.advance(Duration::from_secs(10 * 60));
+        assert!(link.server.open_packet(&packet).is_err());
+    }
+
+    #[test]
+    fn test_alerts_delivered_authenticated() {
+        use crate::alert::{AlertAction, AlertCode};
+        use crate::error::{LlpError, SessionError};
+
+        let mut link = SimulatedLink::establish(11, START, MimicryProfile::None).unwrap();
+
+        let warning = Alert::new(AlertCode::InvalidTimestamp);
+        let received = link.send_alert(Direction::ServerToClient, &warning).unwrap();
+        assert_eq!(received, Some(warning));
+
+        let err = link
+            .send_alert(Direction::ServerToClient, &Alert::new(AlertCode::ServerShutdown))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LlpError::SessionError(SessionError::ClosedByPeer {
+                code: AlertCode::ServerShutdown,
+                ..
+            })
+        ));
+        assert_eq!(
+            AlertCode::from(&err).recommended_action(),
+            AlertAction::ReconnectWithBackoff
+        );
+
+        // Подделанный alert не проходит аутентификацию
+        let mut packet = link
+            .client
+            .seal_alert(&Alert::new(AlertCode::SessionExpired))
+            .unwrap();
+        let mut forged = packet.encrypted_payload.to_vec();
+        forged[3] ^= 0x01;
+        packet.encrypted_payload = Bytes::from(forged);
+        assert!(link.server.open_packet(&packet).is_err());
+    }
+
+    #[test]
+    fn test_extensions_authenticated() {
+        use crate::extension::{Extension, Extensions};
+
+        let mut link = SimulatedLink::establish(13, START, MimicryProfile::None).unwrap();
+
+        let mut extensions = Extensions::new();
+        extensions.insert(Extension::connection_id(&[1, 2, 3])).unwrap();
+        extensions.insert(Extension::fec_group(5, 0, 3)).unwrap();
+
+        let packet = link
+            .client
+            .seal_packet_with_extensions(PacketFlags::DATA, extensions.clone(), b"data")
+            .unwrap();
+        let wire = packet.serialize().unwrap();
+
+        let received = LlpPacket::deserialize(&wire).unwrap();
+        assert_eq!(received.header.extensions, extensions);
+        assert_eq!(link.server.open_packet(&received).unwrap(), b"data");
+
+        // Изменение значения расширения ломает аутентификацию
+        let packet = link
+            .client
+            .seal_packet_with_extensions(PacketFlags::DATA, extensions, b"data")
+            .unwrap();
+        let mut tampered = packet.clone();
+        tampered
+            .header
+            .add_extension(Extension::connection_id(&[9, 9, 9]))
+            .unwrap();
+        assert!(link.server.open_packet(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_session_expiry_in_virtual_time() {
+        let clock = SimulatedClock::new(START);
+        let mut manager = SessionManager::with_lifetime(Duration::from_secs(24 * 3600));
+        manager.set_clock(clock.shared());
+
+        let key = crate::crypto::SessionKey::from_bytes(&[7u8; 32]);
+        manager.add_session(1, key, MimicryProfile::None).unwrap();
+
+        // Keepalive держит сессию, пока не истечёт её время жизни
+        for _ in 0..(24 * 60) {
+            clock.advance(Duration::from_secs(60));
+            manager.get_session_mut(1).unwrap().mark_keepalive_received();
+            assert_eq!(manager.cleanup_expired(), 0);
+        }
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(manager.cleanup_expired(), 1);
+        assert!(!manager.has_session(1));
+    }
+
+    #[test]
+    fn test_take_expired_reports_reason() {
+        let clock = SimulatedClock::new(START);
+        let mut manager = SessionManager::with_lifetime(Duration::from_secs(3600));
+        manager.set_clock(clock.shared());
+
+        let key = crate::crypto::SessionKey::from_bytes(&[7u8; 32]);
+        manager.add_session(1, key.clone(), MimicryProfile::None).unwrap();
+        manager.add_session(2, key, MimicryProfile::None).unwrap();
+
+        // Сессия 1 получает keepalive, сессия 2 молчит
+        for _ in 0..4 {
+            clock.advance(Duration::from_secs(25));
+            manager.get_session_mut(1).unwrap().mark_keepalive_received();
+        }
+
+        // expired() только сообщает о сессии, не удаляя её
+        assert_eq!(manager.expired().len(), 1);
+        assert!(manager.has_session(2));
+
+        let expired = manager.take_expired();
+        assert_eq!(expired.len(), 1);
+        assert!(matches!(
+            expired[0],
+            crate::error::SessionError::KeepaliveTimeout { session_id: 2 }
+        ));
+        assert!(manager.has_session(1));
+    }
+}
@@ -0,0 +1,967 @@
+//! Known-answer test vectors протокола
+//!
+//! Детерминированные входные данные (фиксированные ключи, random, timestamp)
+//! и точные байты результата для каждого сообщения handshake, HKDF, пакета,
+//! alert, extension headers и UDP датаграммы сервера.
+//!
+//! Результаты вычисляются рабочим кодом: сообщения handshake —
+//! [`ClientHandshake`]/[`ServerHandshake`] с RNG, выдающим ключи и random
+//! из входа вектора, пакеты — [`Session`] с часами на timestamp вектора.
+//! Поэтому расхождение рабочего кода с опубликованным набором ловится
+//! проверкой векторов.
+//!
+//! Опубликованный набор лежит в `crates/llp-core/vectors/llp-v1.json`;
+//! бинарь `llp-conformance` генерирует его, проверяет эту реализацию
+//! и гоняет по нему внешние реализации (C#, C и т.д.).
+//!
+//! ## Формат
+//!
+//! ```text
+//! {
+//!   "format_version": 1,
+//!   "protocol_version": 1,
+//!   "vectors": [
+//!     { "name": "...", "type": "packet_seal", "description": "...",
+//!       "input": { ... }, "expected": { ... } }
+//!   ]
+//! }
+//! ```
+//!
+//! Байтовые поля — hex в нижнем регистре, целые — числа JSON. Если вектор
+//! должен быть отклонён, `expected` равен `{"error": "<код>"}` (см.
+//! [`error_code`]).
+
+use std::collections::BTreeMap;
+
+use bytes::BytesMut;
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::alert::{Alert, AlertCode, AlertLevel};
+use crate::clock::SimulatedClock;
+use crate::crypto::{
+    AeadCipher, SessionKey, SharedSecret, X25519Key, CHACHA20_NONCE_SIZE, RANDOM_SIZE,
+    X25519_KEY_SIZE,
+};
+use crate::error::{CryptoError, HandshakeError, LlpError, PacketError, Result};
+use crate::extension::{Extension, ExtensionType, Extensions};
+use crate::handshake::{
+    self, ClientHandshake, ClientHello, ClientVerify, ServerHandshake, ServerHello, ServerVerify,
+    HKDF_INFO,
+};
+use crate::packet::{LlpPacket, MimicryProfile, PacketFlags, PROTOCOL_VERSION};
+use crate::session::{Session, DEFAULT_REPLAY_WINDOW_SIZE};
+
+/// Версия формата файла test vectors
+pub const VECTORS_FORMAT_VERSION: u32 = 1;
+
+/// Опубликованный набор test vectors
+pub const PUBLISHED_VECTORS: &str = include_str!("../vectors/llp-v1.json");
+
+/// Поля входа или ожидаемого результата вектора
+pub type Fields = BTreeMap<String, Value>;
+
+/// Тип вектора (операция, которую должна выполнить реализация)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorType {
+    /// Публичный ключ X25519 из секретного
+    X25519PublicKey,
+    /// Общий секрет X25519
+    X25519SharedSecret,
+    /// HKDF-SHA256 деривация сессионного ключа
+    HkdfSessionKey,
+    /// Сообщение CLIENT_HELLO
+    ClientHello,
+    /// Сообщение SERVER_HELLO
+    ServerHello,
+    /// Сообщение CLIENT_VERIFY (HMAC transcript)
+    ClientVerify,
+    /// Сообщение SERVER_VERIFY (HMAC transcript)
+    ServerVerify,
+    /// Полный handshake: все сообщения и сессионный ключ обеих сторон
+    Handshake,
+    /// Сериализация блока extension headers
+    ExtensionsSerialize,
+    /// Разбор блока extension headers с правилами обработки
+    ExtensionsParse,
+    /// Сериализация alert
+    Alert,
+    /// Шифрование и сериализация LLP пакета
+    PacketSeal,
+    /// Разбор и расшифровка LLP пакета
+    PacketOpen,
+    /// UDP датаграмма сервера `[nonce:12][ciphertext+tag]`
+    DatagramSeal,
+}
+
+/// Один test vector
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestVector {
+    /// Уникальное имя
+    pub name: String,
+    /// Операция
+    #[serde(rename = "type")]
+    pub vector_type: VectorType,
+    /// Что проверяет вектор
+    pub description: String,
+    /// Входные данные
+    pub input: Fields,
+    /// Ожидаемый результат
+    pub expected: Fields,
+}
+
+impl TestVector {
+    /// Проверить вектор на этой реализации; возвращает фактический результат при расхождении
+    pub fn check(&self) -> std::result::Result<(), Fields> {
+        let actual = run(self.vector_type, &self.input);
+        if actual == self.expected {
+            Ok(())
+        } else {
+            Err(actual)
+        }
+    }
+}
+
+/// Набор test vectors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorSet {
+    /// Версия формата файла
+    pub format_version: u32,
+    /// Версия протокола LLP
+    pub protocol_version: u8,
+    /// Векторы
+    pub vectors: Vec<TestVector>,
+}
+
+impl VectorSet {
+    /// Разобрать набор из JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| LlpError::Other(format!("Некорректный файл test vectors: {}", e)))
+    }
+
+    /// Сериализовать в JSON (формат опубликованного файла)
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).expect("VectorSet сериализуем");
+        json.push('\n');
+        json
+    }
+
+    /// Опубликованный набор
+    pub fn published() -> Result<Self> {
+        Self::from_json(PUBLISHED_VECTORS)
+    }
+}
+
+/// Стабильный код ошибки для векторов, которые должны быть отклонены
+pub fn error_code(error: &LlpError) -> &'static str {
+    match error {
+        LlpError::CryptoError(CryptoError::DecryptionError | CryptoError::AuthenticationError) => {
+            "authentication"
+        }
+        LlpError::PacketError(PacketError::UnsupportedVersion(_)) => "unsupported_version",
+        LlpError::PacketError(PacketError::UnsupportedCriticalExtension(_)) => {
+            "unsupported_critical_extension"
+        }
+        LlpError::PacketError(PacketError::DuplicateExtension(_)) => "duplicate_extension",
+        LlpError::PacketError(PacketError::InvalidExtension(_)) => "invalid_extension",
+        LlpError::PacketError(_) => "malformed",
+        LlpError::HandshakeError(HandshakeError::VerificationFailed) => "verification_failed",
+        LlpError::HandshakeError(HandshakeError::UnsupportedMimicryProfile(_)) => {
+            "unsupported_mimicry_profile"
+        }
+        LlpError::Other(_) => "invalid_input",
+        _ => "error",
+    }
+}
+
+/// Выполнить операцию вектора; ошибка превращается в `{"error": "<код>"}`
+pub fn run(vector_type: VectorType, input: &Fields) -> Fields {
+    evaluate(vector_type, input).unwrap_or_else(|e| fields([("error", json!(error_code(&e)))]))
+}
+
+/// Выполнить операцию вектора
+pub fn evaluate(vector_type: VectorType, input: &Fields) -> Result<Fields> {
+    match vector_type {
+        VectorType::X25519PublicKey => {
+            let key = X25519Key::from_bytes(&bytes_field(input, "private_key")?)?;
+            Ok(fields([("public_key", hex_value(key.public_bytes()))]))
+        }
+        VectorType::X25519SharedSecret => {
+            let key = X25519Key::from_bytes(&bytes_field(input, "private_key")?)?;
+            let peer: [u8; X25519_KEY_SIZE] = array_field(input, "peer_public_key")?;
+            let shared = key.diffie_hellman(&x25519_dalek::PublicKey::from(peer));
+            Ok(fields([("shared_secret", hex_value(shared.as_bytes()))]))
+        }
+        VectorType::HkdfSessionKey => {
+            let shared = SharedSecret::from_bytes(&array_field(input, "shared_secret")?);
+            let salt = handshake::session_salt(
+                &array_field(input, "client_random")?,
+                &array_field(input, "server_random")?,
+            );
+            let key = shared.derive_session_key(&salt, &bytes_field(input, "info")?)?;
+            Ok(fields([
+                ("salt", hex_value(&salt)),
+                ("session_key", hex_value(key.as_bytes())),
+            ]))
+        }
+        VectorType::ClientHello => {
+            let mut rng = ScriptedRng::new(&[
+                &array_field::<X25519_KEY_SIZE>(input, "private_key")?,
+                &array_field::<RANDOM_SIZE>(input, "client_random")?,
+            ]);
+            let mut client =
+                ClientHandshake::new(&mut rng, profile_field(input, "mimicry_profile")?);
+            Ok(fields([("message", hex_value(client.start(&mut rng)?))]))
+        }
+        VectorType::ServerHello => {
+            let key = X25519Key::from_bytes(&bytes_field(input, "private_key")?)?;
+            let hello = ServerHello {
+                server_public_key: key.public_bytes(),
+                server_random: array_field(input, "server_random")?,
+                session_id: u64_field(input, "session_id")?,
+            };
+            Ok(fields([("message", hex_value(hello.serialize()))]))
+        }
+        VectorType::ClientVerify | VectorType::ServerVerify => {
+            let key = SessionKey::from_bytes(&array_field(input, "session_key")?);
+            let client_hello = ClientHello::deserialize(&bytes_field(input, "client_hello")?)?;
+            let server_hello = ServerHello::deserialize(&bytes_field(input, "server_hello")?)?;
+
+            let mut transcript = client_hello.serialize().to_vec();
+            transcript.extend_from_slice(&server_hello.serialize());
+
+            let message = if vector_type == VectorType::ClientVerify {
+                ClientVerify::new(&key, &transcript).serialize()
+            } else {
+                ServerVerify::new(&key, &transcript).serialize()
+            };
+            Ok(fields([("message", hex_value(message))]))
+        }
+        VectorType::Handshake => {
+            let mut client_rng = ScriptedRng::new(&[
+                &array_field::<X25519_KEY_SIZE>(input, "client_private_key")?,
+                &array_field::<RANDOM_SIZE>(input, "client_random")?,
+            ]);
+            let mut server_rng = ScriptedRng::new(&[
+                &array_field::<X25519_KEY_SIZE>(input, "server_private_key")?,
+                &array_field::<RANDOM_SIZE>(input, "server_random")?,
+            ]);
+
+            let mut client =
+                ClientHandshake::new(&mut client_rng, profile_field(input, "mimicry_profile")?);
+            let mut server = ServerHandshake::new(&mut server_rng, u64_field(input, "session_id")?);
+
+            let client_hello = client.start(&mut client_rng)?;
+            let (server_hello, _) = server.process_client_hello(&mut server_rng, &client_hello)?;
+            client.process_server_hello(&server_hello)?;
+            let client_verify = client.send_client_verify()?;
+            server.process_client_verify(&client_verify)?;
+            let server_verify = server.send_server_verify()?;
+            client.process_server_verify(&server_verify)?;
+
+            let (Some(client_key), Some(server_key)) = (client.session_key(), server.session_key())
+            else {
+                return Err(HandshakeError::InvalidState("handshake не завершён".into()).into());
+            };
+            if client_key.as_bytes() != server_key.as_bytes() {
+                return Err(HandshakeError::VerificationFailed.into());
+            }
+
+            Ok(fields([
+                ("client_hello", hex_value(client_hello)),
+                ("server_hello", hex_value(server_hello)),
+                ("client_verify", hex_value(client_verify)),
+                ("server_verify", hex_value(server_verify)),
+                ("session_key", hex_value(client_key.as_bytes())),
+            ]))
+        }
+        VectorType::ExtensionsSerialize => {
+            let extensions = extensions_from_records(input.get("records"))?;
+            let mut block = BytesMut::new();
+            extensions.serialize(&mut block);
+            Ok(fields([("block", hex_value(block))]))
+        }
+        VectorType::ExtensionsParse => {
+            let block = bytes_field(input, "block")?;
+            let extensions = Extensions::deserialize(&mut block.as_slice())?;
+            Ok(fields([("records", records_value(&extensions))]))
+        }
+        VectorType::Alert => {
+            let level = u64_field(input, "level")?;
+            let code = u64_field(input, "code")?;
+            let alert = Alert {
+                level: u8::try_from(level)
+                    .ok()
+                    .and_then(AlertLevel::from_u8)
+                    .ok_or_else(|| invalid_field("level"))?,
+                code: u16::try_from(code)
+                    .ok()
+                    .and_then(AlertCode::from_u16)
+                    .ok_or_else(|| invalid_field("code"))?,
+                reason: string_field(input, "reason")?,
+            };
+            Ok(fields([("plaintext", hex_value(alert.serialize()))]))
+        }
+        VectorType::PacketSeal => {
+            let key = SessionKey::from_bytes(&array_field(input, "session_key")?);
+            let flags_bits = u8_field(input, "flags")?;
+            let flags =
+                PacketFlags::from_bits(flags_bits).ok_or(PacketError::InvalidFlags(flags_bits))?;
+            let block = bytes_field(input, "extensions")?;
+            let extensions = if block.is_empty() {
+                Extensions::new()
+            } else {
+                Extensions::deserialize(&mut block.as_slice())?
+            };
+            let plaintext = bytes_field(input, "plaintext")?;
+            let sequence_number = u64_field(input, "sequence_number")?;
+            if sequence_number > MAX_VECTOR_SEQUENCE {
+                return Err(invalid_field("sequence_number"));
+            }
+
+            let mut session = vector_session(
+                key,
+                u64_field(input, "session_id")?,
+                profile_field(input, "mimicry_profile")?,
+                u32_field(input, "timestamp")?,
+            );
+            // Сессия нумерует пакеты сама: пропускаем предыдущие номера
+            for _ in 0..sequence_number {
+                session.seal_packet(PacketFlags::DATA, &[])?;
+            }
+
+            let packet = session.seal_packet_with_extensions(flags, extensions, &plaintext)?;
+            Ok(fields([("packet", hex_value(packet.serialize()?))]))
+        }
+        VectorType::PacketOpen => {
+            let key = SessionKey::from_bytes(&array_field(input, "session_key")?);
+            let packet = LlpPacket::deserialize(&bytes_field(input, "packet")?)?;
+            let mut session = vector_session(
+                key,
+                packet.header.session_id,
+                packet.header.mimicry_profile,
+                packet.header.timestamp,
+            );
+            let plaintext = session.open_packet(&packet)?;
+            Ok(fields([
+                ("flags", json!(packet.header.flags.bits())),
+                ("session_id", json!(packet.header.session_id)),
+                ("sequence_number", json!(packet.header.sequence_number)),
+                ("extensions", records_value(&packet.header.extensions)),
+                ("plaintext", hex_value(plaintext)),
+            ]))
+        }
+        VectorType::DatagramSeal => {
+            let key = SessionKey::from_bytes(&array_field(input, "session_key")?);
+            let session_id = u64_field(input, "session_id")?;
+            let counter = u64_field(input, "counter")?;
+
+            let cipher = AeadCipher::new(&key, session_id);
+            let ciphertext = cipher.encrypt_at(&bytes_field(input, "plaintext")?, &[], counter)?;
+
+            let mut datagram = Vec::with_capacity(CHACHA20_NONCE_SIZE + ciphertext.len());
+            datagram.extend_from_slice(&counter.to_le_bytes());
+            datagram.extend_from_slice(&((session_id & 0xFFFF_FFFF) as u32).to_le_bytes());
+            datagram.extend_from_slice(&ciphertext);
+            Ok(fields([("datagram", hex_value(datagram))]))
+        }
+    }
+}
+
+/// Сгенерировать канонический набор test vectors
+pub fn generate() -> VectorSet {
+    let mut builder = Builder::default();
+
+    let client_private = pattern(0x01);
+    let server_private = pattern(0x41);
+    let client_random = pattern(0x81);
+    let server_random = pattern(0xC1);
+    let session_id: u64 = 0x0102_0304_A1B2_C3D4;
+    let timestamp: u32 = 1_700_000_000;
+
+    // X25519
+    let client_public = builder.push(
+        "x25519_client_public_key",
+        VectorType::X25519PublicKey,
+        "Публичный ключ клиента из фиксированного секретного ключа",
+        fields([("private_key", hex_value(client_private))]),
+    )["public_key"]
+        .clone();
+    let server_public = builder.push(
+        "x25519_server_public_key",
+        VectorType::X25519PublicKey,
+        "Публичный ключ сервера из фиксированного секретного ключа",
+        fields([("private_key", hex_value(server_private))]),
+    )["public_key"]
+        .clone();
+    let shared_secret = builder.push(
+        "x25519_shared_secret_client",
+        VectorType::X25519SharedSecret,
+        "Общий секрет на стороне клиента",
+        fields([
+            ("private_key", hex_value(client_private)),
+            ("peer_public_key", server_public),
+        ]),
+    )["shared_secret"]
+        .clone();
+    builder.push(
+        "x25519_shared_secret_server",
+        VectorType::X25519SharedSecret,
+        "Общий секрет на стороне сервера совпадает с клиентским",
+        fields([
+            ("private_key", hex_value(server_private)),
+            ("peer_public_key", client_public),
+        ]),
+    );
+
+    // HKDF
+    let session_key = builder.push(
+        "hkdf_session_key",
+        VectorType::HkdfSessionKey,
+        "HKDF-SHA256: salt = client_random || server_random, info = \"llp-session-key-v1\"",
+        fields([
+            ("shared_secret", shared_secret),
+            ("client_random", hex_value(client_random)),
+            ("server_random", hex_value(server_random)),
+            ("info", hex_value(HKDF_INFO)),
+        ]),
+    )["session_key"]
+        .clone();
+
+    // Handshake
+    let mut client_hello = Value::Null;
+    for profile in [
+        MimicryProfile::None,
+        MimicryProfile::VkVideo,
+        MimicryProfile::YandexMusic,
+        MimicryProfile::RuTube,
+    ] {
+        let message = builder.push(
+            &format!("client_hello_profile_{}", profile.to_u16()),
+            VectorType::ClientHello,
+            &format!("CLIENT_HELLO с профилем мимикрии {}", profile),
+            fields([
+                ("private_key", hex_value(client_private)),
+                ("client_random", hex_value(client_random)),
+                ("mimicry_profile", json!(profile.to_u16())),
+            ]),
+        )["message"]
+            .clone();
+        if profile == MimicryProfile::VkVideo {
+            client_hello = message;
+        }
+    }
+    let server_hello = builder.push(
+        "server_hello",
+        VectorType::ServerHello,
+        "SERVER_HELLO: session_id в big-endian",
+        fields([
+            ("private_key", hex_value(server_private)),
+            ("server_random", hex_value(server_random)),
+            ("session_id", json!(session_id)),
+        ]),
+    )["message"]
+        .clone();
+    let verify_input = fields([
+        ("session_key", session_key.clone()),
+        ("client_hello", client_hello),
+        ("server_hello", server_hello),
+    ]);
+    builder.push(
+        "client_verify",
+        VectorType::ClientVerify,
+        "HMAC-SHA256(session_key, CLIENT_HELLO || SERVER_HELLO)",
+        verify_input.clone(),
+    );
+    builder.push(
+        "server_verify",
+        VectorType::ServerVerify,
+        "HMAC-SHA256(session_key, CLIENT_HELLO || SERVER_HELLO)",
+        verify_input,
+    );
+    builder.push(
+        "handshake_full",
+        VectorType::Handshake,
+        "Полный обмен CLIENT_HELLO → SERVER_HELLO → CLIENT_VERIFY → SERVER_VERIFY",
+        fields([
+            ("client_private_key", hex_value(client_private)),
+            ("client_random", hex_value(client_random)),
+            ("server_private_key", hex_value(server_private)),
+            ("server_random", hex_value(server_random)),
+            ("session_id", json!(session_id)),
+            ("mimicry_profile", json!(MimicryProfile::VkVideo.to_u16())),
+        ]),
+    );
+
+    // Extension headers
+    let ext_block = builder.push(
+        "extensions_serialize",
+        VectorType::ExtensionsSerialize,
+        "Блок ECN + FEC группа + connection id в порядке вставки",
+        fields([(
+            "records",
+            json!([
+                { "type": ExtensionType::ECN.0, "value": "02" },
+                { "type": ExtensionType::FEC_GROUP.0, "value": "0000002a0308" },
+                { "type": ExtensionType::CONNECTION_ID.0, "value": "c0ffee00c0ffee00" },
+            ]),
+        )]),
+    )["block"]
+        .clone();
+    builder.push(
+        "extensions_parse_unknown_ignorable",
+        VectorType::ExtensionsParse,
+        "Неизвестный некритический тип сохраняется",
+        fields([("block", json!("000b0001000102007f0002abcd"))]),
+    );
+    builder.push(
+        "extensions_parse_unknown_critical",
+        VectorType::ExtensionsParse,
+        "Неизвестный критический тип (старший бит) отклоняется",
+        fields([("block", json!("00058abc000100"))]),
+    );
+    builder.push(
+        "extensions_parse_duplicate",
+        VectorType::ExtensionsParse,
+        "Повтор типа отклоняется",
+        fields([("block", json!("000a00010001010001000102"))]),
+    );
+
+    // Alert
+    for (name, level, code, reason) in [
+        ("alert_close_notify", AlertLevel::Fatal, AlertCode::CloseNotify, ""),
+        ("alert_server_shutdown", AlertLevel::Fatal, AlertCode::ServerShutdown, "maintenance"),
+        ("alert_replay_warning", AlertLevel::Warning, AlertCode::ReplayDetected, ""),
+    ] {
+        builder.push(
+            name,
+            VectorType::Alert,
+            &format!("Alert {} ({:?})", code, level),
+            fields([
+                ("level", json!(level as u8)),
+                ("code", json!(code.to_u16())),
+                ("reason", json!(reason)),
+            ]),
+        );
+    }
+
+    // Пакеты
+    let packet_input = |flags: PacketFlags, seq: u64, extensions: Value, plaintext: Value| {
+        fields([
+            ("session_key", session_key.clone()),
+            ("session_id", json!(session_id)),
+            ("sequence_number", json!(seq)),
+            ("timestamp", json!(timestamp)),
+            ("flags", json!(flags.bits())),
+            ("mimicry_profile", json!(MimicryProfile::VkVideo.to_u16())),
+            ("extensions", extensions),
+            ("plaintext", plaintext),
+        ])
+    };
+    let ip_packet = json!("4500001c000040004011b7cb0a0800020a080001");
+
+    let data_packet = builder.push(
+        "packet_seal_data_seq0",
+        VectorType::PacketSeal,
+        "DATA пакет, sequence 0: AAD = сериализованный заголовок, nonce = seq LE || session_id LE",
+        packet_input(PacketFlags::DATA, 0, json!(""), ip_packet.clone()),
+    )["packet"]
+        .clone();
+    builder.push(
+        "packet_seal_data_seq1",
+        VectorType::PacketSeal,
+        "DATA пакет, sequence 1",
+        packet_input(PacketFlags::DATA, 1, json!(""), ip_packet.clone()),
+    );
+    builder.push(
+        "packet_seal_keepalive",
+        VectorType::PacketSeal,
+        "KEEPALIVE с пустым payload",
+        packet_input(PacketFlags::KEEPALIVE, 2, json!(""), json!("")),
+    );
+    let alert_plaintext = hex_value(Alert::close_notify().serialize());
+    builder.push(
+        "packet_seal_alert",
+        VectorType::PacketSeal,
+        "CONTROL пакет с alert close_notify",
+        packet_input(PacketFlags::CONTROL, 3, json!(""), alert_plaintext),
+    );
+    let ext_packet = builder.push(
+        "packet_seal_extensions",
+        VectorType::PacketSeal,
+        "DATA пакет с extension headers (флаг EXTENSIONS, блок входит в AAD)",
+        packet_input(PacketFlags::DATA, 4, ext_block, ip_packet),
+    )["packet"]
+        .clone();
+
+    builder.push(
+        "packet_open_data",
+        VectorType::PacketOpen,
+        "Расшифровка DATA пакета",
+        fields([("session_key", session_key.clone()), ("packet", data_packet.clone())]),
+    );
+    builder.push(
+        "packet_open_extensions",
+        VectorType::PacketOpen,
+        "Расшифровка пакета с extension headers",
+        fields([("session_key", session_key.clone()), ("packet", ext_packet.clone())]),
+    );
+    builder.push(
+        "packet_open_tampered_tag",
+        VectorType::PacketOpen,
+        "Изменённый auth tag отклоняется",
+        fields([
+            ("session_key", session_key.clone()),
+            ("packet", flip_last_byte(&data_packet)),
+        ]),
+    );
+    builder.push(
+        "packet_open_tampered_extensions",
+        VectorType::PacketOpen,
+        "Изменённое значение extension header отклоняется (блок аутентифицирован)",
+        fields([
+            ("session_key", session_key.clone()),
+            ("packet", flip_byte(&ext_packet, HEADER_EXT_VALUE_OFFSET)),
+        ]),
+    );
+
+    // UDP датаграммы сервера
+    for counter in [0u64, 7] {
+        builder.push(
+            &format!("datagram_seal_counter{}", counter),
+            VectorType::DatagramSeal,
+            "UDP датаграмма: [counter LE (8) || session_id low32 LE (4)][ciphertext+tag], AAD пуст",
+            fields([
+                ("session_key", session_key.clone()),
+                ("session_id", json!(session_id)),
+                ("counter", json!(counter)),
+                ("plaintext", json!("4500001c000040004011b7cb0a0800010a080002")),
+            ]),
+        );
+    }
+
+    VectorSet {
+        format_version: VECTORS_FORMAT_VERSION,
+        protocol_version: PROTOCOL_VERSION,
+        vectors: builder.vectors,
+    }
+}
+
+/// Смещение байта значения ECN в пакете с extension headers:
+/// фиксированный заголовок + длина блока (2) + тип и длина записи (4)
+const HEADER_EXT_VALUE_OFFSET: usize = crate::packet::HEADER_SIZE + 2 + 4;
+
+/// Наибольший номер пакета в векторе `packet_seal`: сессия доходит
+/// до него, запечатывая пустые пакеты
+const MAX_VECTOR_SEQUENCE: u64 = 1 << 16;
+
+/// Сессия вектора: часы стоят на `timestamp`, номера пакетов идут с нуля
+fn vector_session(
+    key: SessionKey,
+    session_id: u64,
+    profile: MimicryProfile,
+    timestamp: u32,
+) -> Session {
+    let clock = SimulatedClock::new(u64::from(timestamp)).shared();
+    Session::with_clock(session_id, key, profile, DEFAULT_REPLAY_WINDOW_SIZE, clock)
+}
+
+/// RNG, выдающий заранее заданные байты (секретный ключ, затем random)
+///
+/// Handshake берёт из RNG ровно эти значения, поэтому с ним рабочие
+/// [`ClientHandshake`]/[`ServerHandshake`] строят сообщения вектора.
+struct ScriptedRng {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl ScriptedRng {
+    fn new(parts: &[&[u8]]) -> Self {
+        Self {
+            bytes: parts.concat(),
+            position: 0,
+        }
+    }
+}
+
+impl RngCore for ScriptedRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0u8; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        // Сценарий исчерпан — дальше нули, а не panic на входе вектора
+        let available = self.bytes.len().saturating_sub(self.position).min(dest.len());
+        dest[..available].copy_from_slice(&self.bytes[self.position..self.position + available]);
+        dest[available..].fill(0);
+        self.position += dest.len();
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for ScriptedRng {}
+
+/// Накопитель векторов: вычисляет ожидаемый результат этой реализацией
+#[derive(Default)]
+struct Builder {
+    vectors: Vec<TestVector>,
+}
+
+impl Builder {
+    fn push(
+        &mut self,
+        name: &str,
+        vector_type: VectorType,
+        description: &str,
+        input: Fields,
+    ) -> Fields {
+        let expected = run(vector_type, &input);
+        self.vectors.push(TestVector {
+            name: name.to_string(),
+            vector_type,
+            description: description.to_string(),
+            input,
+            expected: expected.clone(),
+        });
+        expected
+    }
+}
+
+/// 32 байта `start, start+1, ...`
+fn pattern(start: u8) -> [u8; 32] {
+    std::array::from_fn(|i| start.wrapping_add(i as u8))
+}
+
+fn flip_byte(packet: &Value, offset: usize) -> Value {
+    let mut bytes = hex::decode(packet.as_str().unwrap_or_default()).unwrap_or_default();
+    if let Some(byte) = bytes.get_mut(offset) {
+        *byte ^= 0x01;
+    }
+    hex_value(bytes)
+}
+
+fn flip_last_byte(packet: &Value) -> Value {
+    let len = packet.as_str().map_or(0, |s| s.len() / 2);
+    flip_byte(packet, len.saturating_sub(1))
+}
+
+fn fields<const N: usize>(entries: [(&str, Value); N]) -> Fields {
+    entries
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect()
+}
+
+fn hex_value(bytes: impl AsRef<[u8]>) -> Value {
+    Value::String(hex::encode(bytes))
+}
+
+fn invalid_field(name: &str) -> LlpError {
+    LlpError::Other(format!("Некорректное поле вектора `{}`", name))
+}
+
+fn field<'a>(input: &'a Fields, name: &str) -> Result<&'a Value> {
+    input
+        .get(name)
+        .ok_or_else(|| LlpError::Other(format!("Нет поля вектора `{}`", name)))
+}
+
+fn bytes_field(input: &Fields, name: &str) -> Result<Vec<u8>> {
+    field(input, name)?
+        .as_str()
+        .and_then(|s| hex::decode(s).ok())
+        .ok_or_else(|| invalid_field(name))
+}
+
+fn array_field<const N: usize>(input: &Fields, name: &str) -> Result<[u8; N]> {
+    bytes_field(input, name)?
+        .try_into()
+        .map_err(|_| invalid_field(name))
+}
+
+fn string_field(input: &Fields, name: &str) -> Result<String> {
+    field(input, name)?
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| invalid_field(name))
+}
+
+fn u64_field(input: &Fields, name: &str) -> Result<u64> {
+    field(input, name)?.as_u64().ok_or_else(|| invalid_field(name))
+}
+
+fn u32_field(input: &Fields, name: &str) -> Result<u32> {
+    u32::try_from(u64_field(input, name)?).map_err(|_| invalid_field(name))
+}
+
+fn u8_field(input: &Fields, name: &str) -> Result<u8> {
+    u8::try_from(u64_field(input, name)?).map_err(|_| invalid_field(name))
+}
+
+fn profile_field(input: &Fields, name: &str) -> Result<MimicryProfile> {
+    let id = u16::try_from(u64_field(input, name)?).map_err(|_| invalid_field(name))?;
+    Ok(MimicryProfile::from_u16(id).ok_or(HandshakeError::UnsupportedMimicryProfile(id))?)
+}
+
+fn extensions_from_records(records: Option<&Value>) -> Result<Extensions> {
+    let records = records
+        .and_then(Value::as_array)
+        .ok_or_else(|| invalid_field("records"))?;
+
+    let mut extensions = Extensions::new();
+    for record in records {
+        let ext_type = record
+            .get("type")
+            .and_then(Value::as_u64)
+            .and_then(|t| u16::try_from(t).ok())
+            .ok_or_else(|| invalid_field("records.type"))?;
+        let value = record
+            .get("value")
+            .and_then(Value::as_str)
+            .and_then(|v| hex::decode(v).ok())
+            .ok_or_else(|| invalid_field("records.value"))?;
+        extensions.insert(Extension::new(ExtensionType(ext_type), value))?;
+    }
+    Ok(extensions)
+}
+
+fn records_value(extensions: &Extensions) -> Value {
+    Value::Array(
+        extensions
+            .iter()
+            .map(|e| json!({ "type": e.ext_type.0, "value": hex::encode(&e.value) }))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector<'a>(set: &'a VectorSet, name: &str) -> &'a TestVector {
+        set.vectors.iter().find(|v| v.name == name).unwrap()
+    }
+
+    fn expected_bytes(set: &VectorSet, name: &str, field: &str) -> Vec<u8> {
+        hex::decode(vector(set, name).expected[field].as_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_published_vectors_up_to_date() {
+        // При изменении формата: cargo run -p llp-core --bin llp-conformance -- generate crates/llp-core/vectors/llp-v1.json
+        assert_eq!(VectorSet::published().unwrap(), generate());
+    }
+
+    #[test]
+    fn test_all_vectors_pass() {
+        for vector in VectorSet::published().unwrap().vectors {
+            assert!(vector.check().is_ok(), "{}", vector.name);
+        }
+    }
+
+    #[test]
+    fn test_negative_vectors_rejected() {
+        let set = generate();
+        for (name, code) in [
+            ("extensions_parse_unknown_critical", "unsupported_critical_extension"),
+            ("extensions_parse_duplicate", "duplicate_extension"),
+            ("packet_open_tampered_tag", "authentication"),
+            ("packet_open_tampered_extensions", "authentication"),
+        ] {
+            assert_eq!(vector(&set, name).expected["error"], json!(code), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_handshake_state_machines_match_vectors() {
+        let set = generate();
+        let client_private = pattern(0x01);
+        let server_private = pattern(0x41);
+
+        let mut client_rng = ScriptedRng::new(&[&client_private, &pattern(0x81)]);
+        let mut client = ClientHandshake::new(&mut client_rng, MimicryProfile::VkVideo);
+        let client_hello = client.start(&mut client_rng).unwrap();
+        assert_eq!(
+            client_hello.to_vec(),
+            expected_bytes(&set, "client_hello_profile_1", "message")
+        );
+
+        let mut server_rng = ScriptedRng::new(&[&server_private, &pattern(0xC1)]);
+        let mut server = ServerHandshake::new(&mut server_rng, 0x0102_0304_A1B2_C3D4);
+        let (server_hello, _) = server
+            .process_client_hello(&mut server_rng, &client_hello)
+            .unwrap();
+        assert_eq!(server_hello.to_vec(), expected_bytes(&set, "server_hello", "message"));
+
+        client.process_server_hello(&server_hello).unwrap();
+        let client_verify = client.send_client_verify().unwrap();
+        assert_eq!(client_verify.to_vec(), expected_bytes(&set, "client_verify", "message"));
+
+        server.process_client_verify(&client_verify).unwrap();
+        let server_verify = server.send_server_verify().unwrap();
+        assert_eq!(server_verify.to_vec(), expected_bytes(&set, "server_verify", "message"));
+        client.process_server_verify(&server_verify).unwrap();
+
+        assert_eq!(
+            client.session_key().unwrap().as_bytes().to_vec(),
+            expected_bytes(&set, "hkdf_session_key", "session_key")
+        );
+    }
+
+    #[test]
+    fn test_session_matches_packet_vectors() {
+        let set = generate();
+        let key = expected_bytes(&set, "hkdf_session_key", "session_key");
+        let key = SessionKey::from_bytes(&key.try_into().unwrap());
+        let clock = SimulatedClock::new(1_700_000_000);
+
+        let mut session = Session::with_clock(
+            0x0102_0304_A1B2_C3D4,
+            key.clone(),
+            MimicryProfile::VkVideo,
+            DEFAULT_REPLAY_WINDOW_SIZE,
+            clock.shared(),
+        );
+
+        let plaintext =
+            hex::decode(vector(&set, "packet_seal_data_seq0").input["plaintext"].as_str().unwrap())
+                .unwrap();
+        for name in ["packet_seal_data_seq0", "packet_seal_data_seq1"] {
+            let packet = session.seal_packet(PacketFlags::DATA, &plaintext).unwrap();
+            assert_eq!(
+                packet.serialize().unwrap().to_vec(),
+                expected_bytes(&set, name, "packet"),
+                "{}",
+                name
+            );
+        }
+
+        // Обратное направление: сессия принимает пакет из вектора
+        let mut peer = Session::with_clock(
+            0x0102_0304_A1B2_C3D4,
+            key,
+            MimicryProfile::VkVideo,
+            DEFAULT_REPLAY_WINDOW_SIZE,
+            clock.shared(),
+        );
+        let packet =
+            LlpPacket::deserialize(&expected_bytes(&set, "packet_seal_extensions", "packet"))
+                .unwrap();
+        assert_eq!(peer.open_packet(&packet).unwrap(), plaintext);
+    }
+}
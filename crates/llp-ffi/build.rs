@@ -0,0 +1,35 @@
+//! Генерация C заголовка `llp.h` через cbindgen
+//!
+//! Заголовок пишется в `OUT_DIR`: обычная сборка не меняет дерево
+//! исходников. Копия `include/llp.h` в репозитории обновляется явно
+//! (`cargo build -p llp-ffi --features update-header`), а тест
+//! `test_header_up_to_date` проверяет, что она не отстала от кода.
+
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let header = PathBuf::from(env::var("OUT_DIR").unwrap()).join("llp.h");
+
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml"))
+        .expect("Некорректный cbindgen.toml");
+
+    // Ошибка генерации не должна ломать сборку библиотеки;
+    // без заголовка не соберутся только тесты
+    match cbindgen::generate_with_config(&crate_dir, config) {
+        Ok(bindings) => {
+            bindings.write_to_file(&header);
+            if env::var_os("CARGO_FEATURE_UPDATE_HEADER").is_some() {
+                std::fs::copy(&header, crate_dir.join("include").join("llp.h"))
+                    .expect("Не удалось обновить include/llp.h");
+            }
+        }
+        Err(e) => {
+            println!("cargo:warning=Не удалось сгенерировать llp.h: {}", e);
+        }
+    }
+}
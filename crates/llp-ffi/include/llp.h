@@ -0,0 +1,301 @@
+#ifndef LLP_H
+#define LLP_H
+
+/* Generated with cbindgen:0.26.0 */
+
+/* Файл сгенерирован cbindgen при сборке llp-ffi. Не редактировать вручную. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Роль в HTTP обмене: ответы в обе стороны
+ */
+#define LLP_ROLE_SYMMETRIC 0
+
+/**
+ * Роль в HTTP обмене: клиент (запросы с payload)
+ */
+#define LLP_ROLE_CLIENT 1
+
+/**
+ * Роль в HTTP обмене: сервер (ответы на запросы клиента)
+ */
+#define LLP_ROLE_SERVER 2
+
+/**
+ * Коды результата функций FFI
+ *
+ * Значения стабильны и являются частью ABI.
+ */
+typedef enum LlpStatus {
+  /**
+   * Успех
+   */
+  LLP_STATUS_OK = 0,
+  /**
+   * Передан нулевой указатель
+   */
+  LLP_STATUS_NULL_POINTER = -1,
+  /**
+   * Некорректный аргумент (профиль, размер ключа и т.п.)
+   */
+  LLP_STATUS_INVALID_ARGUMENT = -2,
+  /**
+   * Выходной буфер слишком мал; требуемый размер записан в `out_len`
+   */
+  LLP_STATUS_BUFFER_TOO_SMALL = -3,
+  /**
+   * Ошибка формата пакета
+   */
+  LLP_STATUS_PACKET = -10,
+  /**
+   * Неподдерживаемая версия протокола
+   */
+  LLP_STATUS_UNSUPPORTED_VERSION = -11,
+  /**
+   * Криптографическая ошибка
+   */
+  LLP_STATUS_CRYPTO = -20,
+  /**
+   * Пакет не прошёл аутентификацию
+   */
+  LLP_STATUS_AUTHENTICATION = -21,
+  /**
+   * Ошибка handshake
+   */
+  LLP_STATUS_HANDSHAKE = -30,
+  /**
+   * HMAC handshake не совпал
+   */
+  LLP_STATUS_HANDSHAKE_VERIFICATION = -31,
+  /**
+   * Ошибка сессии
+   */
+  LLP_STATUS_SESSION = -40,
+  /**
+   * Повтор или устаревший sequence number
+   */
+  LLP_STATUS_REPLAY = -41,
+  /**
+   * Timestamp пакета вне допустимого окна
+   */
+  LLP_STATUS_TIMESTAMP = -42,
+  /**
+   * Сессия закрыта другой стороной (получен fatal alert)
+   */
+  LLP_STATUS_SESSION_CLOSED = -43,
+  /**
+   * Требуется rekey
+   */
+  LLP_STATUS_REKEY_REQUIRED = -44,
+  /**
+   * Ошибка мимикрии (обёртывание/разбор HTTP)
+   */
+  LLP_STATUS_MIMICRY = -50,
+  /**
+   * Ошибка ввода-вывода
+   */
+  LLP_STATUS_IO = -60,
+  /**
+   * Внутренняя ошибка (в том числе паника)
+   */
+  LLP_STATUS_INTERNAL = -99,
+} LlpStatus;
+
+/**
+ * Непрозрачный handle клиентского handshake
+ */
+typedef struct LlpClientHandshake LlpClientHandshake;
+
+/**
+ * Непрозрачный handle LLP сессии
+ */
+typedef struct LlpSession LlpSession;
+
+/**
+ * Непрозрачный handle обёртки мимикрии
+ */
+typedef struct LlpWrapper LlpWrapper;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Версия библиотеки (статическая C строка, освобождать не нужно)
+ */
+const char *llp_version(void);
+
+/**
+ * Скопировать текст последней ошибки текущего потока (UTF-8, с `\0`)
+ *
+ * Возвращает длину сообщения без завершающего нуля; 0 — ошибки не было.
+ * Если `buf_len` недостаточно, сообщение обрезается.
+ */
+size_t llp_last_error_message(char *buf,
+                              size_t buf_len);
+
+/**
+ * Создать клиентский handshake для профиля мимикрии (0-3)
+ */
+enum LlpStatus llp_client_handshake_new(uint16_t profile, struct LlpClientHandshake **out);
+
+/**
+ * Сформировать CLIENT_HELLO
+ */
+enum LlpStatus llp_client_handshake_start(struct LlpClientHandshake *handshake,
+                                          uint8_t *out,
+                                          size_t out_cap,
+                                          size_t *out_len);
+
+/**
+ * Обработать SERVER_HELLO; в `*session_id` записывается идентификатор сессии
+ */
+enum LlpStatus llp_client_handshake_process_server_hello(struct LlpClientHandshake *handshake,
+                                                         const uint8_t *data,
+                                                         size_t len,
+                                                         uint64_t *session_id);
+
+/**
+ * Сформировать CLIENT_VERIFY
+ */
+enum LlpStatus llp_client_handshake_client_verify(struct LlpClientHandshake *handshake,
+                                                  uint8_t *out,
+                                                  size_t out_cap,
+                                                  size_t *out_len);
+
+/**
+ * Обработать SERVER_VERIFY и завершить handshake
+ */
+enum LlpStatus llp_client_handshake_process_server_verify(struct LlpClientHandshake *handshake,
+                                                          const uint8_t *data,
+                                                          size_t len);
+
+/**
+ * Создать сессию из завершённого handshake
+ *
+ * `replay_window_size` — размер окна replay protection (0 — по умолчанию).
+ * Handshake остаётся валидным и должен быть освобождён отдельно.
+ */
+enum LlpStatus llp_client_handshake_into_session(struct LlpClientHandshake *handshake,
+                                                 size_t replay_window_size,
+                                                 struct LlpSession **out);
+
+/**
+ * Освободить handshake (null допустим)
+ */
+void llp_client_handshake_free(struct LlpClientHandshake *handshake);
+
+/**
+ * Создать сессию из 32-байтного ключа ChaCha20-Poly1305
+ */
+enum LlpStatus llp_session_new(uint64_t session_id,
+                               const uint8_t *key,
+                               uint16_t profile,
+                               size_t replay_window_size,
+                               struct LlpSession **out);
+
+/**
+ * Зашифровать payload и сериализовать LLP пакет
+ *
+ * `flags` — биты `PacketFlags` (DATA = 0x01, CONTROL = 0x02, KEEPALIVE = 0x08).
+ */
+enum LlpStatus llp_session_encrypt_packet(struct LlpSession *session,
+                                          uint8_t flags,
+                                          const uint8_t *plaintext,
+                                          size_t plaintext_len,
+                                          uint8_t *out,
+                                          size_t out_cap,
+                                          size_t *out_len);
+
+/**
+ * Разобрать и расшифровать LLP пакет
+ *
+ * Флаги пакета записываются в `*flags_out` (если не null). Fatal alert
+ * от другой стороны возвращается как `LLP_STATUS_SESSION_CLOSED`.
+ *
+ * Ёмкость `out` проверяется до расшифровки: при
+ * `LLP_STATUS_BUFFER_TOO_SMALL` пакет не считается принятым, и его можно
+ * передать повторно с буфером размера `*out_len`.
+ */
+enum LlpStatus llp_session_decrypt_packet(struct LlpSession *session,
+                                          const uint8_t *packet,
+                                          size_t packet_len,
+                                          uint8_t *out,
+                                          size_t out_cap,
+                                          size_t *out_len,
+                                          uint8_t *flags_out);
+
+/**
+ * ID сессии (0 для null handle)
+ */
+uint64_t llp_session_id(const struct LlpSession *session);
+
+/**
+ * Нужно ли отправить keepalive (false для null handle)
+ */
+bool llp_session_needs_keepalive(const struct LlpSession *session);
+
+/**
+ * Освободить сессию (null допустим)
+ */
+void llp_session_free(struct LlpSession *session);
+
+/**
+ * Создать обёртку для профиля мимикрии (0-3 или зарегистрированный пользовательский)
+ */
+enum LlpStatus llp_wrapper_new(uint16_t profile,
+                               struct LlpWrapper **out);
+
+/**
+ * Создать обёртку с ролью в HTTP обмене (`LLP_ROLE_*`)
+ */
+enum LlpStatus llp_wrapper_new_with_role(uint16_t profile, uint8_t role, struct LlpWrapper **out);
+
+/**
+ * Нужно ли клиенту отправить опрос (`llp_wrapper_poll_request`)
+ */
+bool llp_wrapper_needs_poll(const struct LlpWrapper *wrapper);
+
+/**
+ * Сформировать запрос клиента без payload
+ */
+enum LlpStatus llp_wrapper_poll_request(struct LlpWrapper *wrapper,
+                                        uint8_t *out,
+                                        size_t out_cap,
+                                        size_t *out_len);
+
+/**
+ * Обернуть сериализованный LLP пакет в HTTP-трафик
+ */
+enum LlpStatus llp_wrapper_wrap(struct LlpWrapper *wrapper,
+                                const uint8_t *packet,
+                                size_t packet_len,
+                                uint8_t *out,
+                                size_t out_cap,
+                                size_t *out_len);
+
+/**
+ * Извлечь LLP пакет из HTTP-трафика
+ */
+enum LlpStatus llp_wrapper_unwrap(struct LlpWrapper *wrapper,
+                                  const uint8_t *data,
+                                  size_t data_len,
+                                  uint8_t *out,
+                                  size_t out_cap,
+                                  size_t *out_len);
+
+/**
+ * Освободить обёртку (null допустим)
+ */
+void llp_wrapper_free(struct LlpWrapper *wrapper);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* LLP_H */
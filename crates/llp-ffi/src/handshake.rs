@@ -0,0 +1,293 @@
+//! Клиентский handshake через C ABI
+//!
+//! Последовательность вызовов:
+//! 1. [`llp_client_handshake_new`]
+//! 2. [`llp_client_handshake_start`] → CLIENT_HELLO для отправки серверу
+//! 3. [`llp_client_handshake_process_server_hello`] ← SERVER_HELLO
+//! 4. [`llp_client_handshake_client_verify`] → CLIENT_VERIFY
+//! 5. [`llp_client_handshake_process_server_verify`] ← SERVER_VERIFY
+//! 6. [`llp_client_handshake_into_session`] → готовая [`LlpSession`]
+//! 7. [`llp_client_handshake_free`]
+//!
+//! Если буфер для CLIENT_HELLO или CLIENT_VERIFY оказался мал, сообщение
+//! сохраняется в handle и возвращается при повторном вызове с большим
+//! буфером — состояние handshake при этом не сдвигается повторно.
+
+use bytes::Bytes;
+use llp_core::handshake::ClientHandshake;
+use llp_core::packet::MimicryProfile;
+use rand::rngs::OsRng;
+
+use crate::session::LlpSession;
+use crate::{
+    ffi_call, handle_mut, input, write_output, write_value, FfiError, LlpStatus,
+};
+
+/// Непрозрачный handle клиентского handshake
+pub struct LlpClientHandshake {
+    inner: ClientHandshake,
+    profile: MimicryProfile,
+    /// CLIENT_HELLO, не поместившийся в буфер вызывающей стороны
+    pending_hello: Option<Bytes>,
+    /// CLIENT_VERIFY, не поместившийся в буфер вызывающей стороны
+    pending_verify: Option<Bytes>,
+}
+
+/// Разобрать идентификатор профиля мимикрии
+pub(crate) fn parse_profile(profile: u16) -> Result<MimicryProfile, FfiError> {
+    MimicryProfile::from_u16(profile).ok_or_else(|| {
+        FfiError::new(
+            LlpStatus::InvalidArgument,
+            format!("Неизвестный профиль мимикрии: {}", profile),
+        )
+    })
+}
+
+/// Создать клиентский handshake для профиля мимикрии (0-3)
+#[no_mangle]
+pub unsafe extern "C" fn llp_client_handshake_new(
+    profile: u16,
+    out: *mut *mut LlpClientHandshake,
+) -> LlpStatus {
+    ffi_call(|| {
+        let profile = parse_profile(profile)?;
+        let handshake = Box::new(LlpClientHandshake {
+            inner: ClientHandshake::new(&mut OsRng, profile),
+            profile,
+            pending_hello: None,
+            pending_verify: None,
+        });
+        write_value(out, Box::into_raw(handshake))
+    })
+}
+
+/// Сформировать CLIENT_HELLO
+#[no_mangle]
+pub unsafe extern "C" fn llp_client_handshake_start(
+    handshake: *mut LlpClientHandshake,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> LlpStatus {
+    ffi_call(|| {
+        let handshake = handle_mut(handshake)?;
+        let client_hello = match handshake.pending_hello.take() {
+            Some(message) => message,
+            None => handshake.inner.start(&mut OsRng)?,
+        };
+
+        write_output(&client_hello, out, out_cap, out_len).inspect_err(|_| {
+            handshake.pending_hello = Some(client_hello.clone());
+        })
+    })
+}
+
+/// Обработать SERVER_HELLO; в `*session_id` записывается идентификатор сессии
+#[no_mangle]
+pub unsafe extern "C" fn llp_client_handshake_process_server_hello(
+    handshake: *mut LlpClientHandshake,
+    data: *const u8,
+    len: usize,
+    session_id: *mut u64,
+) -> LlpStatus {
+    ffi_call(|| {
+        let handshake = handle_mut(handshake)?;
+        let id = handshake.inner.process_server_hello(input(data, len)?)?;
+        if !session_id.is_null() {
+            write_value(session_id, id)?;
+        }
+        Ok(())
+    })
+}
+
+/// Сформировать CLIENT_VERIFY
+#[no_mangle]
+pub unsafe extern "C" fn llp_client_handshake_client_verify(
+    handshake: *mut LlpClientHandshake,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> LlpStatus {
+    ffi_call(|| {
+        let handshake = handle_mut(handshake)?;
+        let client_verify = match handshake.pending_verify.take() {
+            Some(message) => message,
+            None => handshake.inner.send_client_verify()?,
+        };
+
+        write_output(&client_verify, out, out_cap, out_len).inspect_err(|_| {
+            handshake.pending_verify = Some(client_verify.clone());
+        })
+    })
+}
+
+/// Обработать SERVER_VERIFY и завершить handshake
+#[no_mangle]
+pub unsafe extern "C" fn llp_client_handshake_process_server_verify(
+    handshake: *mut LlpClientHandshake,
+    data: *const u8,
+    len: usize,
+) -> LlpStatus {
+    ffi_call(|| {
+        let handshake = handle_mut(handshake)?;
+        handshake.inner.process_server_verify(input(data, len)?)?;
+        Ok(())
+    })
+}
+
+/// Создать сессию из завершённого handshake
+///
+/// `replay_window_size` — размер окна replay protection (0 — по умолчанию).
+/// Handshake остаётся валидным и должен быть освобождён отдельно.
+#[no_mangle]
+pub unsafe extern "C" fn llp_client_handshake_into_session(
+    handshake: *mut LlpClientHandshake,
+    replay_window_size: usize,
+    out: *mut *mut LlpSession,
+) -> LlpStatus {
+    ffi_call(|| {
+        let handshake = handle_mut(handshake)?;
+
+        let (Some(session_id), Some(key)) =
+            (handshake.inner.session_id(), handshake.inner.session_key())
+        else {
+            return Err(FfiError::new(
+                LlpStatus::Handshake,
+                "Handshake не завершён",
+            ));
+        };
+
+        let session = LlpSession::new(session_id, key.clone(), handshake.profile, replay_window_size);
+        write_value(out, Box::into_raw(Box::new(session)))
+    })
+}
+
+/// Освободить handshake (null допустим)
+#[no_mangle]
+pub unsafe extern "C" fn llp_client_handshake_free(handshake: *mut LlpClientHandshake) {
+    if !handshake.is_null() {
+        drop(Box::from_raw(handshake));
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use llp_core::handshake::ServerHandshake;
+    use std::ptr;
+
+    /// Выполнить handshake через FFI против Rust сервера
+    pub(crate) fn establish() -> (*mut LlpSession, llp_core::crypto::SessionKey, u64) {
+        unsafe {
+            let mut client = ptr::null_mut();
+            assert_eq!(llp_client_handshake_new(1, &mut client), LlpStatus::Ok);
+
+            let mut buf = [0u8; 1024];
+            let mut len = 0usize;
+            assert_eq!(
+                llp_client_handshake_start(client, buf.as_mut_ptr(), buf.len(), &mut len),
+                LlpStatus::Ok
+            );
+
+            let mut server = ServerHandshake::new(&mut OsRng, 4242);
+            let (server_hello, _) = server
+                .process_client_hello(&mut OsRng, &buf[..len])
+                .unwrap();
+
+            let mut session_id = 0u64;
+            assert_eq!(
+                llp_client_handshake_process_server_hello(
+                    client,
+                    server_hello.as_ptr(),
+                    server_hello.len(),
+                    &mut session_id,
+                ),
+                LlpStatus::Ok
+            );
+            assert_eq!(session_id, 4242);
+
+            assert_eq!(
+                llp_client_handshake_client_verify(client, buf.as_mut_ptr(), buf.len(), &mut len),
+                LlpStatus::Ok
+            );
+            server.process_client_verify(&buf[..len]).unwrap();
+
+            let server_verify = server.send_server_verify().unwrap();
+            assert_eq!(
+                llp_client_handshake_process_server_verify(
+                    client,
+                    server_verify.as_ptr(),
+                    server_verify.len(),
+                ),
+                LlpStatus::Ok
+            );
+
+            let mut session = ptr::null_mut();
+            assert_eq!(
+                llp_client_handshake_into_session(client, 0, &mut session),
+                LlpStatus::Ok
+            );
+            llp_client_handshake_free(client);
+
+            (session, server.session_key().unwrap().clone(), session_id)
+        }
+    }
+
+    #[test]
+    fn test_handshake_over_ffi() {
+        let (session, _, _) = establish();
+        assert!(!session.is_null());
+        unsafe { crate::session::llp_session_free(session) };
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        unsafe {
+            let mut client = ptr::null_mut();
+            assert_eq!(
+                llp_client_handshake_new(99, &mut client),
+                LlpStatus::InvalidArgument
+            );
+            assert_eq!(
+                llp_client_handshake_new(0, ptr::null_mut()),
+                LlpStatus::NullPointer
+            );
+
+            let mut len = 0usize;
+            assert_eq!(
+                llp_client_handshake_start(ptr::null_mut(), ptr::null_mut(), 0, &mut len),
+                LlpStatus::NullPointer
+            );
+        }
+    }
+
+    #[test]
+    fn test_buffer_too_small_reports_required_size() {
+        unsafe {
+            let mut client = ptr::null_mut();
+            llp_client_handshake_new(0, &mut client);
+
+            let mut len = 0usize;
+            assert_eq!(
+                llp_client_handshake_start(client, ptr::null_mut(), 0, &mut len),
+                LlpStatus::BufferTooSmall
+            );
+            assert!(len > 0);
+
+            // Повторный вызов с достаточным буфером возвращает то же сообщение
+            let mut buf = vec![0u8; len];
+            assert_eq!(
+                llp_client_handshake_start(client, buf.as_mut_ptr(), buf.len(), &mut len),
+                LlpStatus::Ok
+            );
+            assert_eq!(len, buf.len());
+
+            let mut session = ptr::null_mut();
+            assert_eq!(
+                llp_client_handshake_into_session(client, 0, &mut session),
+                LlpStatus::Handshake
+            );
+            llp_client_handshake_free(client);
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! # LLP FFI (llp-ffi)
+//!
+//! Стабильный C ABI поверх llp-core и llp-mimicry, чтобы клиенты на других
+//! языках (C#, C, Swift, Kotlin) использовали каноническую реализацию
+//! handshake, шифрования пакетов и мимикрии вместо собственных копий.
+//!
+//! ## Соглашения
+//!
+//! - Все объекты — непрозрачные handles (`LlpClientHandshake`, `LlpSession`,
+//!   `LlpWrapper`), создаются функциями `*_new` и освобождаются `*_free`.
+//! - Все функции возвращают [`LlpStatus`]; `LLP_STATUS_OK` (0) — успех,
+//!   отрицательные значения — ошибки. Текст последней ошибки потока
+//!   доступен через [`llp_last_error_message`].
+//! - Выходные данные пишутся в буфер вызывающей стороны `(out, out_cap)`,
+//!   фактическая длина — в `*out_len`. Если буфер мал, возвращается
+//!   `LLP_STATUS_BUFFER_TOO_SMALL`, а в `*out_len` — требуемый размер.
+//! - Паника внутри Rust не пересекает границу FFI: она превращается
+//!   в `LLP_STATUS_INTERNAL`.
+//! - Handles не потокобезопасны: один handle нельзя использовать
+//!   из нескольких потоков одновременно.
+//!
+//! C заголовок `llp.h` генерируется cbindgen при сборке в `OUT_DIR`.
+//! Копия `include/llp.h` в репозитории обновляется командой
+//! `cargo build -p llp-ffi --features update-header`.
+
+#![warn(missing_docs)]
+#![warn(clippy::all)]
+// Границы FFI работают с сырыми указателями по определению; проверки
+// на null и размеры выполняются в каждой функции
+#![allow(clippy::missing_safety_doc)]
+
+pub mod handshake;
+pub mod session;
+pub mod wrapper;
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use llp_core::error::{CryptoError, HandshakeError, LlpError, SessionError};
+use llp_mimicry::MimicryError;
+
+pub use handshake::LlpClientHandshake;
+pub use session::LlpSession;
+pub use wrapper::LlpWrapper;
+
+/// Коды результата функций FFI
+///
+/// Значения стабильны и являются частью ABI.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlpStatus {
+    /// Успех
+    Ok = 0,
+    /// Передан нулевой указатель
+    NullPointer = -1,
+    /// Некорректный аргумент (профиль, размер ключа и т.п.)
+    InvalidArgument = -2,
+    /// Выходной буфер слишком мал; требуемый размер записан в `out_len`
+    BufferTooSmall = -3,
+    /// Ошибка формата пакета
+    Packet = -10,
+    /// Неподдерживаемая версия протокола
+    UnsupportedVersion = -11,
+    /// Криптографическая ошибка
+    Crypto = -20,
+    /// Пакет не прошёл аутентификацию
+    Authentication = -21,
+    /// Ошибка handshake
+    Handshake = -30,
+    /// HMAC handshake не совпал
+    HandshakeVerification = -31,
+    /// Ошибка сессии
+    Session = -40,
+    /// Повтор или устаревший sequence number
+    Replay = -41,
+    /// Timestamp пакета вне допустимого окна
+    Timestamp = -42,
+    /// Сессия закрыта другой стороной (получен fatal alert)
+    SessionClosed = -43,
+    /// Требуется rekey
+    RekeyRequired = -44,
+    /// Ошибка мимикрии (обёртывание/разбор HTTP)
+    Mimicry = -50,
+    /// Ошибка ввода-вывода
+    Io = -60,
+    /// Внутренняя ошибка (в том числе паника)
+    Internal = -99,
+}
+
+impl From<&LlpError> for LlpStatus {
+    fn from(error: &LlpError) -> Self {
+        match error {
+            LlpError::PacketError(llp_core::error::PacketError::UnsupportedVersion(_)) => {
+                LlpStatus::UnsupportedVersion
+            }
+            LlpError::PacketError(_) => LlpStatus::Packet,
+            LlpError::CryptoError(
+                CryptoError::DecryptionError | CryptoError::AuthenticationError,
+            ) => LlpStatus::Authentication,
+            LlpError::CryptoError(CryptoError::InvalidKeySize { .. }) => {
+                LlpStatus::InvalidArgument
+            }
+            LlpError::CryptoError(_) => LlpStatus::Crypto,
+            LlpError::HandshakeError(HandshakeError::VerificationFailed) => {
+                LlpStatus::HandshakeVerification
+            }
+            LlpError::HandshakeError(HandshakeError::UnsupportedMimicryProfile(_)) => {
+                LlpStatus::InvalidArgument
+            }
+            LlpError::HandshakeError(_) => LlpStatus::Handshake,
+            LlpError::SessionError(e) => match e {
+                SessionError::DuplicateSequenceNumber { .. }
+                | SessionError::SequenceOutOfWindow { .. } => LlpStatus::Replay,
+                SessionError::InvalidTimestamp { .. } => LlpStatus::Timestamp,
+                SessionError::ClosedByPeer { .. } => LlpStatus::SessionClosed,
+                SessionError::RekeyRequired { .. } | SessionError::RekeyFailed { .. } => {
+                    LlpStatus::RekeyRequired
+                }
+                _ => LlpStatus::Session,
+            },
+            LlpError::Io(_) => LlpStatus::Io,
+            LlpError::Other(_) => LlpStatus::Internal,
+        }
+    }
+}
+
+/// Ошибка внутри FFI вызова: код и текст для [`llp_last_error_message`]
+pub(crate) struct FfiError {
+    status: LlpStatus,
+    message: String,
+}
+
+impl FfiError {
+    pub(crate) fn new(status: LlpStatus, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    /// Выходной буфер мал; требуемый размер уже записан в `out_len`
+    fn buffer_too_small(required: usize) -> Self {
+        Self::new(
+            LlpStatus::BufferTooSmall,
+            format!("Выходной буфер слишком мал: требуется {} байт", required),
+        )
+    }
+}
+
+impl From<LlpError> for FfiError {
+    fn from(error: LlpError) -> Self {
+        Self::new(LlpStatus::from(&error), error.to_string())
+    }
+}
+
+impl From<MimicryError> for FfiError {
+    fn from(error: MimicryError) -> Self {
+        Self::new(LlpStatus::Mimicry, error.to_string())
+    }
+}
+
+pub(crate) type FfiResult<T> = std::result::Result<T, FfiError>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Выполнить тело FFI функции: перехват паники и запись последней ошибки
+pub(crate) fn ffi_call<F>(f: F) -> LlpStatus
+where
+    F: FnOnce() -> FfiResult<()>,
+{
+    let result = catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| {
+        Err(FfiError::new(
+            LlpStatus::Internal,
+            "Паника внутри llp-ffi",
+        ))
+    });
+
+    match result {
+        Ok(()) => {
+            LAST_ERROR.with(|e| *e.borrow_mut() = None);
+            LlpStatus::Ok
+        }
+        Err(error) => {
+            let message = CString::new(error.message.replace('\0', " ")).unwrap_or_default();
+            LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+            error.status
+        }
+    }
+}
+
+/// Получить ссылку на объект за handle
+pub(crate) unsafe fn handle_mut<'a, T>(ptr: *mut T) -> FfiResult<&'a mut T> {
+    ptr.as_mut()
+        .ok_or_else(|| FfiError::new(LlpStatus::NullPointer, "Нулевой handle"))
+}
+
+/// Получить входной срез из пары (указатель, длина)
+pub(crate) unsafe fn input<'a>(data: *const u8, len: usize) -> FfiResult<&'a [u8]> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if data.is_null() {
+        return Err(FfiError::new(LlpStatus::NullPointer, "Нулевой входной буфер"));
+    }
+    Ok(std::slice::from_raw_parts(data, len))
+}
+
+/// Проверить, что результат длины `len` поместится в буфер
+///
+/// Необходимый размер записывается в `*out_len` в любом случае.
+pub(crate) unsafe fn reserve_output(
+    len: usize,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> FfiResult<()> {
+    if out_len.is_null() {
+        return Err(FfiError::new(LlpStatus::NullPointer, "Нулевой out_len"));
+    }
+    *out_len = len;
+
+    if len > out_cap {
+        return Err(FfiError::buffer_too_small(len));
+    }
+    Ok(())
+}
+
+/// Записать результат в буфер вызывающей стороны
+pub(crate) unsafe fn write_output(
+    bytes: &[u8],
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> FfiResult<()> {
+    reserve_output(bytes.len(), out_cap, out_len)?;
+    if !bytes.is_empty() {
+        if out.is_null() {
+            return Err(FfiError::new(LlpStatus::NullPointer, "Нулевой выходной буфер"));
+        }
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len());
+    }
+    Ok(())
+}
+
+/// Записать значение по выходному указателю
+pub(crate) unsafe fn write_value<T>(out: *mut T, value: T) -> FfiResult<()> {
+    if out.is_null() {
+        return Err(FfiError::new(LlpStatus::NullPointer, "Нулевой выходной указатель"));
+    }
+    out.write(value);
+    Ok(())
+}
+
+/// Версия библиотеки (статическая C строка, освобождать не нужно)
+#[no_mangle]
+pub extern "C" fn llp_version() -> *const c_char {
+    static VERSION: &CStr = match CStr::from_bytes_with_nul(
+        concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes(),
+    ) {
+        Ok(version) => version,
+        Err(_) => panic!("Некорректная версия"),
+    };
+    VERSION.as_ptr()
+}
+
+/// Скопировать текст последней ошибки текущего потока (UTF-8, с `\0`)
+///
+/// Возвращает длину сообщения без завершающего нуля; 0 — ошибки не было.
+/// Если `buf_len` недостаточно, сообщение обрезается.
+#[no_mangle]
+pub unsafe extern "C" fn llp_last_error_message(buf: *mut c_char, buf_len: usize) -> usize {
+    LAST_ERROR.with(|e| {
+        let error = e.borrow();
+        let Some(message) = error.as_ref() else {
+            return 0;
+        };
+
+        let bytes = message.as_bytes();
+        if !buf.is_null() && buf_len > 0 {
+            let n = bytes.len().min(buf_len - 1);
+            std::ptr::copy_nonoverlapping(bytes.as_ptr() as *const c_char, buf, n);
+            *buf.add(n) = 0;
+        }
+        bytes.len()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/llp.h"));
+        let committed = include_str!("../include/llp.h");
+        assert!(
+            generated == committed,
+            "include/llp.h устарел: cargo build -p llp-ffi --features update-header"
+        );
+    }
+
+    #[test]
+    fn test_status_mapping() {
+        let auth = LlpError::from(CryptoError::DecryptionError);
+        assert_eq!(LlpStatus::from(&auth), LlpStatus::Authentication);
+
+        let replay = LlpError::from(SessionError::DuplicateSequenceNumber {
+            session_id: 1,
+            seq: 1,
+        });
+        assert_eq!(LlpStatus::from(&replay), LlpStatus::Replay);
+
+        let hmac = LlpError::from(HandshakeError::VerificationFailed);
+        assert_eq!(LlpStatus::from(&hmac), LlpStatus::HandshakeVerification);
+    }
+
+    #[test]
+    fn test_last_error_message() {
+        let status = ffi_call(|| Err(FfiError::new(LlpStatus::Session, "тест")));
+        assert_eq!(status, LlpStatus::Session);
+
+        let mut buf = [0 as c_char; 64];
+        let len = unsafe { llp_last_error_message(buf.as_mut_ptr(), buf.len()) };
+        let message = unsafe { CStr::from_ptr(buf.as_ptr()) };
+        assert_eq!(message.to_str().unwrap(), "тест");
+        assert_eq!(len, "тест".len());
+
+        assert_eq!(ffi_call(|| Ok(())), LlpStatus::Ok);
+        assert_eq!(unsafe { llp_last_error_message(buf.as_mut_ptr(), buf.len()) }, 0);
+    }
+
+    #[test]
+    fn test_panic_does_not_cross_boundary() {
+        let status = ffi_call(|| panic!("boom"));
+        assert_eq!(status, LlpStatus::Internal);
+    }
+
+    #[test]
+    fn test_version() {
+        let version = unsafe { CStr::from_ptr(llp_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    }
+}
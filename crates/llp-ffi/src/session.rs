@@ -0,0 +1,338 @@
+//! Шифрование LLP пакетов через C ABI
+//!
+//! [`LlpSession`] создаётся либо из завершённого handshake
+//! ([`crate::handshake::llp_client_handshake_into_session`]), либо напрямую
+//! из 32-байтного ключа через [`llp_session_new`].
+
+use llp_core::crypto::{SessionKey, CHACHA20_KEY_SIZE};
+use llp_core::packet::{LlpPacket, MimicryProfile, PacketFlags};
+use llp_core::session::{Session, DEFAULT_REPLAY_WINDOW_SIZE};
+
+use crate::handshake::parse_profile;
+use crate::{
+    ffi_call, handle_mut, input, reserve_output, write_output, write_value, FfiError, LlpStatus,
+};
+
+/// Непрозрачный handle LLP сессии
+pub struct LlpSession {
+    inner: Session,
+}
+
+impl LlpSession {
+    /// Создать сессию; `replay_window_size == 0` — размер окна по умолчанию
+    pub(crate) fn new(
+        session_id: u64,
+        key: SessionKey,
+        profile: MimicryProfile,
+        replay_window_size: usize,
+    ) -> Self {
+        let replay_window_size = if replay_window_size == 0 {
+            DEFAULT_REPLAY_WINDOW_SIZE
+        } else {
+            replay_window_size
+        };
+
+        Self {
+            inner: Session::with_replay_window(session_id, key, profile, replay_window_size),
+        }
+    }
+}
+
+/// Создать сессию из 32-байтного ключа ChaCha20-Poly1305
+#[no_mangle]
+pub unsafe extern "C" fn llp_session_new(
+    session_id: u64,
+    key: *const u8,
+    profile: u16,
+    replay_window_size: usize,
+    out: *mut *mut LlpSession,
+) -> LlpStatus {
+    ffi_call(|| {
+        if key.is_null() {
+            return Err(FfiError::new(LlpStatus::NullPointer, "Нулевой ключ"));
+        }
+        let key = &*(key as *const [u8; CHACHA20_KEY_SIZE]);
+        let profile = parse_profile(profile)?;
+
+        let session = LlpSession::new(
+            session_id,
+            SessionKey::from_bytes(key),
+            profile,
+            replay_window_size,
+        );
+        write_value(out, Box::into_raw(Box::new(session)))
+    })
+}
+
+/// Зашифровать payload и сериализовать LLP пакет
+///
+/// `flags` — биты `PacketFlags` (DATA = 0x01, CONTROL = 0x02, KEEPALIVE = 0x08).
+#[no_mangle]
+pub unsafe extern "C" fn llp_session_encrypt_packet(
+    session: *mut LlpSession,
+    flags: u8,
+    plaintext: *const u8,
+    plaintext_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> LlpStatus {
+    ffi_call(|| {
+        let session = handle_mut(session)?;
+        let flags = PacketFlags::from_bits_truncate(flags);
+        let packet = session
+            .inner
+            .seal_packet(flags, input(plaintext, plaintext_len)?)?;
+        write_output(&packet.serialize()?, out, out_cap, out_len)
+    })
+}
+
+/// Разобрать и расшифровать LLP пакет
+///
+/// Флаги пакета записываются в `*flags_out` (если не null). Fatal alert
+/// от другой стороны возвращается как `LLP_STATUS_SESSION_CLOSED`.
+///
+/// Ёмкость `out` проверяется до расшифровки: при
+/// `LLP_STATUS_BUFFER_TOO_SMALL` пакет не считается принятым, и его можно
+/// передать повторно с буфером размера `*out_len`.
+#[no_mangle]
+pub unsafe extern "C" fn llp_session_decrypt_packet(
+    session: *mut LlpSession,
+    packet: *const u8,
+    packet_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+    flags_out: *mut u8,
+) -> LlpStatus {
+    ffi_call(|| {
+        let session = handle_mut(session)?;
+        let packet = LlpPacket::deserialize(input(packet, packet_len)?)?;
+        // Окно replay сдвигается при расшифровке, поэтому буфер проверяется
+        // заранее: plaintext той же длины, что и зашифрованный payload
+        reserve_output(packet.encrypted_payload.len(), out_cap, out_len)?;
+        let plaintext = session.inner.open_packet(&packet)?;
+        session.inner.process_alert(packet.header.flags, &plaintext)?;
+
+        if !flags_out.is_null() {
+            write_value(flags_out, packet.header.flags.bits())?;
+        }
+        write_output(&plaintext, out, out_cap, out_len)
+    })
+}
+
+/// ID сессии (0 для null handle)
+#[no_mangle]
+pub unsafe extern "C" fn llp_session_id(session: *const LlpSession) -> u64 {
+    session.as_ref().map_or(0, |s| s.inner.session_id())
+}
+
+/// Нужно ли отправить keepalive (false для null handle)
+#[no_mangle]
+pub unsafe extern "C" fn llp_session_needs_keepalive(session: *const LlpSession) -> bool {
+    session.as_ref().is_some_and(|s| s.inner.needs_keepalive())
+}
+
+/// Освободить сессию (null допустим)
+#[no_mangle]
+pub unsafe extern "C" fn llp_session_free(session: *mut LlpSession) {
+    if !session.is_null() {
+        drop(Box::from_raw(session));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handshake::tests::establish;
+    use llp_core::alert::{Alert, AlertCode};
+    use std::ptr;
+
+    #[test]
+    fn test_round_trip_with_rust_peer() {
+        let (session, server_key, session_id) = establish();
+        let mut server = Session::new(session_id, server_key, MimicryProfile::YandexMusic);
+
+        unsafe {
+            let payload = b"ip packet";
+            let mut buf = [0u8; 256];
+            let mut len = 0usize;
+            assert_eq!(
+                llp_session_encrypt_packet(
+                    session,
+                    PacketFlags::DATA.bits(),
+                    payload.as_ptr(),
+                    payload.len(),
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut len,
+                ),
+                LlpStatus::Ok
+            );
+
+            let packet = LlpPacket::deserialize(&buf[..len]).unwrap();
+            assert_eq!(server.open_packet(&packet).unwrap(), payload);
+
+            // Обратное направление
+            let reply = server.seal_packet(PacketFlags::DATA, b"reply").unwrap();
+            let reply = reply.serialize().unwrap();
+            let mut flags = 0u8;
+            assert_eq!(
+                llp_session_decrypt_packet(
+                    session,
+                    reply.as_ptr(),
+                    reply.len(),
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut len,
+                    &mut flags,
+                ),
+                LlpStatus::Ok
+            );
+            assert_eq!(&buf[..len], b"reply");
+            assert_eq!(flags, PacketFlags::DATA.bits());
+
+            // Повтор того же пакета отклоняется
+            assert_eq!(
+                llp_session_decrypt_packet(
+                    session,
+                    reply.as_ptr(),
+                    reply.len(),
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut len,
+                    ptr::null_mut(),
+                ),
+                LlpStatus::Replay
+            );
+
+            llp_session_free(session);
+        }
+    }
+
+    #[test]
+    fn test_retry_after_small_buffer() {
+        let key = [4u8; CHACHA20_KEY_SIZE];
+        let mut peer = Session::new(11, SessionKey::from_bytes(&key), MimicryProfile::VkVideo);
+
+        unsafe {
+            let mut session = ptr::null_mut();
+            llp_session_new(11, key.as_ptr(), 0, 0, &mut session);
+
+            let packet = peer
+                .seal_packet(PacketFlags::DATA, b"longer payload")
+                .unwrap()
+                .serialize()
+                .unwrap();
+
+            let mut small = [0u8; 4];
+            let mut len = 0usize;
+            assert_eq!(
+                llp_session_decrypt_packet(
+                    session,
+                    packet.as_ptr(),
+                    packet.len(),
+                    small.as_mut_ptr(),
+                    small.len(),
+                    &mut len,
+                    ptr::null_mut(),
+                ),
+                LlpStatus::BufferTooSmall
+            );
+            assert_eq!(len, b"longer payload".len());
+
+            // Тот же пакет с буфером нужного размера не считается повтором
+            let mut buf = vec![0u8; len];
+            assert_eq!(
+                llp_session_decrypt_packet(
+                    session,
+                    packet.as_ptr(),
+                    packet.len(),
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut len,
+                    ptr::null_mut(),
+                ),
+                LlpStatus::Ok
+            );
+            assert_eq!(&buf[..len], b"longer payload");
+
+            llp_session_free(session);
+        }
+    }
+
+    #[test]
+    fn test_tampered_packet_rejected() {
+        let key = [7u8; CHACHA20_KEY_SIZE];
+        let mut peer = Session::new(9, SessionKey::from_bytes(&key), MimicryProfile::VkVideo);
+
+        unsafe {
+            let mut session = ptr::null_mut();
+            assert_eq!(
+                llp_session_new(9, key.as_ptr(), 0, 0, &mut session),
+                LlpStatus::Ok
+            );
+            assert_eq!(llp_session_id(session), 9);
+
+            let mut packet = peer
+                .seal_packet(PacketFlags::DATA, b"payload")
+                .unwrap()
+                .serialize()
+                .unwrap()
+                .to_vec();
+            let last = packet.len() - 1;
+            packet[last] ^= 0xFF;
+
+            let mut buf = [0u8; 64];
+            let mut len = 0usize;
+            assert_eq!(
+                llp_session_decrypt_packet(
+                    session,
+                    packet.as_ptr(),
+                    packet.len(),
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut len,
+                    ptr::null_mut(),
+                ),
+                LlpStatus::Authentication
+            );
+
+            llp_session_free(session);
+        }
+    }
+
+    #[test]
+    fn test_fatal_alert_closes_session() {
+        let key = [3u8; CHACHA20_KEY_SIZE];
+        let mut peer = Session::new(5, SessionKey::from_bytes(&key), MimicryProfile::RuTube);
+
+        unsafe {
+            let mut session = ptr::null_mut();
+            llp_session_new(5, key.as_ptr(), 2, 0, &mut session);
+
+            let alert = peer
+                .seal_alert(&Alert::new(AlertCode::ServerShutdown))
+                .unwrap()
+                .serialize()
+                .unwrap();
+
+            let mut buf = [0u8; 64];
+            let mut len = 0usize;
+            assert_eq!(
+                llp_session_decrypt_packet(
+                    session,
+                    alert.as_ptr(),
+                    alert.len(),
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut len,
+                    ptr::null_mut(),
+                ),
+                LlpStatus::SessionClosed
+            );
+
+            llp_session_free(session);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Мимикрия через C ABI
+//!
+//! [`LlpWrapper`] упаковывает сериализованные LLP пакеты в HTTP-трафик
+//! выбранного профиля и извлекает их обратно.
+
+use llp_mimicry::{PacketWrapper, Role};
+
+use crate::handshake::parse_profile;
+use crate::{ffi_call, handle_mut, input, write_output, write_value, FfiError, LlpStatus};
+
+/// Роль в HTTP обмене: ответы в обе стороны
+pub const LLP_ROLE_SYMMETRIC: u8 = 0;
+/// Роль в HTTP обмене: клиент (запросы с payload)
+pub const LLP_ROLE_CLIENT: u8 = 1;
+/// Роль в HTTP обмене: сервер (ответы на запросы клиента)
+pub const LLP_ROLE_SERVER: u8 = 2;
+
+/// Непрозрачный handle обёртки мимикрии
+pub struct LlpWrapper {
+    inner: PacketWrapper,
+}
+
+/// Создать обёртку для профиля мимикрии (0-3 или зарегистрированный пользовательский)
+#[no_mangle]
+pub unsafe extern "C" fn llp_wrapper_new(profile: u16, out: *mut *mut LlpWrapper) -> LlpStatus {
+    ffi_call(|| {
+        let profile = parse_profile(profile)?;
+        let wrapper = Box::new(LlpWrapper {
+            inner: PacketWrapper::try_new(profile)?,
+        });
+        write_value(out, Box::into_raw(wrapper))
+    })
+}
+
+/// Создать обёртку с ролью в HTTP обмене (`LLP_ROLE_*`)
+#[no_mangle]
+pub unsafe extern "C" fn llp_wrapper_new_with_role(
+    profile: u16,
+    role: u8,
+    out: *mut *mut LlpWrapper,
+) -> LlpStatus {
+    ffi_call(|| {
+        let profile = parse_profile(profile)?;
+        let role = match role {
+            LLP_ROLE_SYMMETRIC => Role::Symmetric,
+            LLP_ROLE_CLIENT => Role::Client,
+            LLP_ROLE_SERVER => Role::Server,
+            other => {
+                return Err(FfiError::new(
+                    LlpStatus::InvalidArgument,
+                    format!("Неизвестная роль: {}", other),
+                ))
+            }
+        };
+        let wrapper = Box::new(LlpWrapper {
+            inner: PacketWrapper::try_new(profile)?.with_role(role),
+        });
+        write_value(out, Box::into_raw(wrapper))
+    })
+}
+
+/// Нужно ли клиенту отправить опрос (`llp_wrapper_poll_request`)
+#[no_mangle]
+pub unsafe extern "C" fn llp_wrapper_needs_poll(wrapper: *const LlpWrapper) -> bool {
+    wrapper.as_ref().is_some_and(|w| w.inner.needs_poll())
+}
+
+/// Сформировать запрос клиента без payload
+#[no_mangle]
+pub unsafe extern "C" fn llp_wrapper_poll_request(
+    wrapper: *mut LlpWrapper,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> LlpStatus {
+    ffi_call(|| {
+        let wrapper = handle_mut(wrapper)?;
+        let request = wrapper.inner.poll_request()?;
+        write_output(&request, out, out_cap, out_len)
+    })
+}
+
+/// Обернуть сериализованный LLP пакет в HTTP-трафик
+#[no_mangle]
+pub unsafe extern "C" fn llp_wrapper_wrap(
+    wrapper: *mut LlpWrapper,
+    packet: *const u8,
+    packet_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> LlpStatus {
+    ffi_call(|| {
+        let wrapper = handle_mut(wrapper)?;
+        let wrapped = wrapper.inner.wrap(input(packet, packet_len)?)?;
+        write_output(&wrapped, out, out_cap, out_len)
+    })
+}
+
+/// Извлечь LLP пакет из HTTP-трафика
+#[no_mangle]
+pub unsafe extern "C" fn llp_wrapper_unwrap(
+    wrapper: *mut LlpWrapper,
+    data: *const u8,
+    data_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> LlpStatus {
+    ffi_call(|| {
+        let wrapper = handle_mut(wrapper)?;
+        let packet = wrapper.inner.unwrap(input(data, data_len)?)?;
+        write_output(&packet, out, out_cap, out_len)
+    })
+}
+
+/// Освободить обёртку (null допустим)
+#[no_mangle]
+pub unsafe extern "C" fn llp_wrapper_free(wrapper: *mut LlpWrapper) {
+    if !wrapper.is_null() {
+        drop(Box::from_raw(wrapper));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn test_wrap_unwrap_round_trip() {
+        unsafe {
+            let mut wrapper = ptr::null_mut();
+            assert_eq!(llp_wrapper_new(1, &mut wrapper), LlpStatus::Ok);
+
+            let packet = b"serialized llp packet";
+            let mut wrapped = vec![0u8; 4096];
+            let mut wrapped_len = 0usize;
+            assert_eq!(
+                llp_wrapper_wrap(
+                    wrapper,
+                    packet.as_ptr(),
+                    packet.len(),
+                    wrapped.as_mut_ptr(),
+                    wrapped.len(),
+                    &mut wrapped_len,
+                ),
+                LlpStatus::Ok
+            );
+            assert!(wrapped.starts_with(b"HTTP/1.1"));
+
+            let mut buf = [0u8; 64];
+            let mut len = 0usize;
+            assert_eq!(
+                llp_wrapper_unwrap(
+                    wrapper,
+                    wrapped.as_ptr(),
+                    wrapped_len,
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut len,
+                ),
+                LlpStatus::Ok
+            );
+            assert_eq!(&buf[..len], packet);
+
+            llp_wrapper_free(wrapper);
+        }
+    }
+
+    #[test]
+    fn test_unwrap_garbage_is_mimicry_error() {
+        unsafe {
+            let mut wrapper = ptr::null_mut();
+            llp_wrapper_new(3, &mut wrapper);
+
+            let garbage = b"not http at all";
+            let mut buf = [0u8; 64];
+            let mut len = 0usize;
+            assert_eq!(
+                llp_wrapper_unwrap(
+                    wrapper,
+                    garbage.as_ptr(),
+                    garbage.len(),
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut len,
+                ),
+                LlpStatus::Mimicry
+            );
+
+            llp_wrapper_free(wrapper);
+        }
+    }
+
+    #[test]
+    fn test_client_server_roles() {
+        unsafe {
+            let mut client = ptr::null_mut();
+            let mut server = ptr::null_mut();
+            assert_eq!(llp_wrapper_new_with_role(2, LLP_ROLE_CLIENT, &mut client), LlpStatus::Ok);
+            assert_eq!(llp_wrapper_new_with_role(2, LLP_ROLE_SERVER, &mut server), LlpStatus::Ok);
+            assert_eq!(
+                llp_wrapper_new_with_role(2, 7, &mut ptr::null_mut()),
+                LlpStatus::InvalidArgument
+            );
+
+            assert!(llp_wrapper_needs_poll(client));
+            let mut request = vec![0u8; 4096];
+            let mut request_len = 0usize;
+            assert_eq!(
+                llp_wrapper_poll_request(client, request.as_mut_ptr(), request.len(), &mut request_len),
+                LlpStatus::Ok
+            );
+            assert!(request.starts_with(b"GET "));
+
+            let mut buf = [0u8; 64];
+            let mut len = 1usize;
+            assert_eq!(
+                llp_wrapper_unwrap(server, request.as_ptr(), request_len, buf.as_mut_ptr(), buf.len(), &mut len),
+                LlpStatus::Ok
+            );
+            assert_eq!(len, 0);
+
+            llp_wrapper_free(client);
+            llp_wrapper_free(server);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Агрегация пакетов в сообщения размера профиля
+//!
+//! Без агрегации каждое HTTP сообщение несёт ровно один LLP пакет не
+//! длиннее MTU, и размеры ответов не похожи на сегменты видео. В режиме
+//! агрегации пакеты с префиксом длины (формат [`crate::stream`])
+//! складываются в общий поток, который нарезается на тела сообщений
+//! размера [`crate::Profile::recommended_chunk_size`]:
+//!
+//! ```text
+//! пакеты:    [len|A] [len|B] [len|C] [len|D] ...
+//! сообщения: ├──── тело 1 ────┼──── тело 2 ──── ...
+//! ```
+//!
+//! Пакет может начаться в одном сообщении и закончиться в следующем;
+//! получатель собирает пакеты [`crate::stream::StreamDecoder`] без потерь.
+//! Последнее сообщение пачки короче, если данных меньше целевого размера.
+
+use bytes::{Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{MimicryError, Result};
+use crate::stream::{self, StreamDecoder};
+
+/// Предельный размер тела сообщения по умолчанию
+pub const DEFAULT_MAX_MESSAGE: usize = 256 * 1024;
+
+/// Параметры агрегации
+#[derive(Debug, Clone)]
+pub struct AggregationOptions {
+    /// Предельный размер тела сообщения (размер профиля ограничивается им)
+    pub max_message: usize,
+}
+
+impl Default for AggregationOptions {
+    fn default() -> Self {
+        Self {
+            max_message: DEFAULT_MAX_MESSAGE,
+        }
+    }
+}
+
+/// Секция `[aggregation]` конфигурации клиента и сервера
+///
+/// Режим должен совпадать у клиента и сервера.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregationConfig {
+    /// Объединять пакеты в сообщения
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Предельный размер тела сообщения (КБ)
+    #[serde(default = "default_max_message_kb")]
+    pub max_message_kb: usize,
+}
+
+fn default_max_message_kb() -> usize {
+    DEFAULT_MAX_MESSAGE / 1024
+}
+
+impl Default for AggregationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_message_kb: default_max_message_kb(),
+        }
+    }
+}
+
+impl AggregationConfig {
+    /// Параметры агрегации (`None` — агрегация выключена)
+    pub fn options(&self) -> Option<AggregationOptions> {
+        self.enabled.then(|| AggregationOptions {
+            max_message: self.max_message_kb * 1024,
+        })
+    }
+
+    /// Проверить значения секции
+    pub fn validate(&self) -> Result<()> {
+        if self.enabled && self.max_message_kb == 0 {
+            return Err(MimicryError::InvalidConfig(
+                "aggregation.max_message_kb должен быть > 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Состояние агрегации одной обёртки: отправка и приём
+pub struct Aggregator {
+    options: AggregationOptions,
+    /// Пакеты с префиксом длины, ещё не попавшие в сообщение
+    pending: BytesMut,
+    /// Целевой размер тела следующего сообщения
+    next_size: Option<usize>,
+    /// Сборка пакетов из тел полученных сообщений
+    decoder: StreamDecoder,
+}
+
+impl Aggregator {
+    /// Агрегатор с заданными параметрами
+    pub fn new(options: AggregationOptions) -> Self {
+        Self {
+            options,
+            pending: BytesMut::new(),
+            next_size: None,
+            decoder: StreamDecoder::new(),
+        }
+    }
+
+    /// Параметры агрегации
+    pub fn options(&self) -> &AggregationOptions {
+        &self.options
+    }
+
+    /// Добавить LLP пакет к отправке
+    pub fn push(&mut self, packet: &[u8]) -> Result<()> {
+        stream::put_packet(&mut self.pending, packet)
+    }
+
+    /// Данных, ещё не попавших в сообщение
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Забрать тело следующего сообщения
+    ///
+    /// `size` даёт целевой размер (обычно размер chunk профиля) и
+    /// вызывается, только когда прошлый целевой размер исчерпан. Без
+    /// `flush` остаток меньше целевого размера ждёт следующих пакетов;
+    /// с `flush` он уходит коротким сообщением.
+    pub fn next_body(&mut self, flush: bool, size: impl FnOnce() -> usize) -> Option<Bytes> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let max = self.options.max_message.max(1);
+        let target = *self.next_size.get_or_insert_with(|| size().clamp(1, max));
+        if self.pending.len() >= target {
+            self.next_size = None;
+            return Some(self.pending.split_to(target).freeze());
+        }
+        if flush {
+            return Some(self.pending.split().freeze());
+        }
+        None
+    }
+
+    /// Забрать все накопленные данные одним телом
+    pub fn take_all(&mut self) -> Bytes {
+        self.pending.split().freeze()
+    }
+
+    /// Извлечь пакеты из тела полученного сообщения
+    ///
+    /// Незавершённый пакет остаётся до следующего сообщения.
+    pub fn unpack(&mut self, body: &[u8]) -> Result<Vec<Bytes>> {
+        self.decoder.extend(body);
+        let mut packets = Vec::new();
+        while let Some(packet) = self.decoder.next_packet()? {
+            packets.push(packet);
+        }
+        Ok(packets)
+    }
+
+    /// Есть ли незавершённый полученный пакет
+    pub fn has_partial(&self) -> bool {
+        self.decoder.has_partial()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(len: usize, tag: u8) -> Vec<u8> {
+        vec![tag; len]
+    }
+
+    #[test]
+    fn test_aggregation_config() {
+        let mut config = AggregationConfig::default();
+        assert!(config.options().is_none());
+        assert_eq!(config.max_message_kb * 1024, DEFAULT_MAX_MESSAGE);
+
+        // Агрегация в пустые сообщения
+        config.enabled = true;
+        config.max_message_kb = 0;
+        assert!(config.validate().is_err());
+        config.max_message_kb = 64;
+        assert!(config.validate().is_ok());
+        assert_eq!(config.options().unwrap().max_message, 64 * 1024);
+    }
+
+    #[test]
+    fn test_bodies_follow_target_size() {
+        let mut aggregator = Aggregator::new(AggregationOptions::default());
+        for tag in 0..10 {
+            aggregator.push(&packet(1400, tag)).unwrap();
+        }
+        let total = aggregator.pending();
+
+        let mut sizes = [4000, 5000].into_iter();
+        let first = aggregator
+            .next_body(false, || sizes.next().unwrap())
+            .unwrap();
+        assert_eq!(first.len(), 4000);
+        let second = aggregator
+            .next_body(false, || sizes.next().unwrap())
+            .unwrap();
+        assert_eq!(second.len(), 5000);
+
+        // Остаток меньше целевого размера ждёт, пока нет flush
+        assert!(aggregator.next_body(false, || 8000).is_none());
+        let rest = aggregator.next_body(true, || unreachable!()).unwrap();
+        assert_eq!(rest.len(), total - 9000);
+        assert!(aggregator.next_body(true, || 8000).is_none());
+
+        // Пакеты собираются без потерь, в том числе через границы сообщений
+        let mut receiver = Aggregator::new(AggregationOptions::default());
+        let mut received = Vec::new();
+        for body in [first, second, rest] {
+            received.extend(receiver.unpack(&body).unwrap());
+        }
+        assert!(!receiver.has_partial());
+        assert_eq!(received.len(), 10);
+        for (tag, packet) in received.iter().enumerate() {
+            assert_eq!(&packet[..], &self::packet(1400, tag as u8)[..]);
+        }
+    }
+
+    #[test]
+    fn test_target_size_capped() {
+        let mut aggregator = Aggregator::new(AggregationOptions { max_message: 2000 });
+        aggregator.push(&packet(5000, 1)).unwrap();
+
+        // Большой пакет делится между сообщениями
+        let bodies: Vec<_> =
+            std::iter::from_fn(|| aggregator.next_body(true, || 64 * 1024)).collect();
+        assert_eq!(
+            bodies.iter().map(Bytes::len).collect::<Vec<_>>(),
+            vec![2000, 2000, 1004]
+        );
+
+        let mut receiver = Aggregator::new(AggregationOptions::default());
+        assert!(receiver.unpack(&bodies[0]).unwrap().is_empty());
+        assert!(receiver.has_partial());
+        assert!(receiver.unpack(&bodies[1]).unwrap().is_empty());
+        let packets = receiver.unpack(&bodies[2]).unwrap();
+        assert_eq!(packets, vec![Bytes::from(packet(5000, 1))]);
+    }
+}
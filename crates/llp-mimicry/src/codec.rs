@@ -0,0 +1,666 @@
+//! Разбор потока HTTP/1.1 сообщений
+//!
+//! [`HttpDecoder`] выделяет из TCP потока целые HTTP сообщения по
+//! `Content-Length` или `Transfer-Encoding: chunked`, накапливая данные
+//! между чтениями (`httparse::Status::Partial`). На проводе — только
+//! валидный HTTP, без двоичных префиксов длины.
+//!
+//! В потоковом режиме ([`HttpDecoder::with_streaming`]) chunked ответ
+//! не накапливается целиком, а отдаётся частями по мере прихода
+//! ([`Frame`]): так один долгий ответ несёт много LLP пакетов.
+//!
+//! Handshake LLP идёт через те же [`write_frame`] и [`read_frame`], что и
+//! данные, поэтому HTTP на проводе с первого байта. Префикс длины `u32`
+//! остаётся только у профиля `none` ([`crate::Profile::http_exchange`] =
+//! false), который трафик не маскирует; его сообщения тоже копятся в
+//! буфере [`HttpDecoder`].
+
+use bytes::{Buf, Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::error::{MimicryError, Result};
+
+/// Максимальное количество заголовков в сообщении
+const MAX_HEADERS: usize = 64;
+
+/// Максимальная длина строки размера chunk
+const MAX_CHUNK_LINE: usize = 1024;
+
+/// Ограничения декодера
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecoderLimits {
+    /// Максимальный размер стартовой строки и заголовков (байт)
+    pub max_head: usize,
+    /// Максимальный размер тела (байт)
+    pub max_body: usize,
+}
+
+impl Default for DecoderLimits {
+    fn default() -> Self {
+        Self {
+            max_head: 16 * 1024,
+            max_body: 1024 * 1024,
+        }
+    }
+}
+
+/// Длина тела по заголовкам
+#[derive(Debug, Clone, Copy)]
+enum BodyLength {
+    Fixed(usize),
+    Chunked,
+}
+
+/// Разобранный заголовок сообщения
+#[derive(Debug, Clone, Copy)]
+struct Head {
+    len: usize,
+    body: BodyLength,
+    is_response: bool,
+}
+
+/// Позиция внутри тела потокового chunked ответа
+#[derive(Debug, Clone, Copy)]
+enum ChunkState {
+    /// Ожидается строка размера chunk
+    Size,
+    /// Осталось байт данных текущего chunk
+    Data(usize),
+    /// Ожидается CRLF после данных chunk
+    DataEnd,
+    /// Trailer после последнего chunk
+    Trailer,
+}
+
+/// Результат разбора потока
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    /// Целое сообщение: заголовок и тело
+    Message(Bytes),
+    /// Заголовок потокового chunked ответа
+    StreamHead(Bytes),
+    /// Очередные данные тела потокового ответа (без chunk разметки)
+    StreamData(Bytes),
+    /// Последний chunk потокового ответа
+    StreamEnd,
+}
+
+/// Инкрементальный декодер HTTP/1.1 сообщений
+///
+/// Данные добавляются через [`Self::extend`], готовые сообщения
+/// забираются через [`Self::decode`]. Сообщение возвращается как
+/// заголовок и тело подряд; chunked тело возвращается уже собранным.
+#[derive(Debug, Default)]
+pub struct HttpDecoder {
+    buf: BytesMut,
+    limits: DecoderLimits,
+    /// Разобранный заголовок текущего сообщения
+    head: Option<Head>,
+    /// Отдавать chunked ответы частями
+    streaming: bool,
+    /// Состояние открытого потокового ответа
+    stream: Option<ChunkState>,
+}
+
+impl HttpDecoder {
+    /// Декодер с ограничениями по умолчанию
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Декодер с заданными ограничениями
+    pub fn with_limits(limits: DecoderLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Включить потоковый режим для chunked ответов
+    ///
+    /// Такие ответы отдаются через [`Self::decode_frame`] частями;
+    /// chunked запросы по-прежнему собираются целиком.
+    pub fn with_streaming(mut self, enabled: bool) -> Self {
+        self.streaming = enabled;
+        self
+    }
+
+    /// Открыт ли потоковый ответ
+    pub fn in_stream(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Ограничения декодера
+    pub fn limits(&self) -> DecoderLimits {
+        self.limits
+    }
+
+    /// Добавить прочитанные из потока данные
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Количество накопленных, ещё не разобранных байт
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Извлечь следующее целое сообщение
+    ///
+    /// `Ok(None)` — данных пока недостаточно. Ошибка означает, что поток
+    /// не является корректным HTTP или нарушает ограничения; продолжать
+    /// чтение после неё нельзя.
+    pub fn decode(&mut self) -> Result<Option<Bytes>> {
+        match self.decode_frame()? {
+            Some(Frame::Message(message)) => Ok(Some(message)),
+            Some(_) => Err(MimicryError::ParseError(
+                "потоковый ответ вне потокового режима".to_string(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Извлечь сообщение с префиксом длины `u32` (профиль без мимикрии)
+    fn decode_prefixed(&mut self) -> Result<Option<Bytes>> {
+        let Some(prefix) = self.buf.get(..4) else {
+            return Ok(None);
+        };
+        let len = u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+        if len > self.limits.max_body {
+            return Err(MimicryError::ParseError(format!(
+                "сообщение {} байт больше ограничения {}",
+                len, self.limits.max_body
+            )));
+        }
+        if self.buf.len() - 4 < len {
+            return Ok(None);
+        }
+        self.buf.advance(4);
+        Ok(Some(self.buf.split_to(len).freeze()))
+    }
+
+    /// Извлечь следующее сообщение или часть потокового ответа
+    ///
+    /// Без потокового режима возвращает только [`Frame::Message`].
+    pub fn decode_frame(&mut self) -> Result<Option<Frame>> {
+        if let Some(state) = self.stream {
+            return self.decode_stream(state);
+        }
+
+        let head = match self.head {
+            Some(head) => head,
+            None => match self.parse_head()? {
+                Some(head) => {
+                    self.head = Some(head);
+                    head
+                }
+                None => return Ok(None),
+            },
+        };
+        let head_len = head.len;
+
+        if self.streaming && head.is_response && matches!(head.body, BodyLength::Chunked) {
+            self.head = None;
+            self.stream = Some(ChunkState::Size);
+            return Ok(Some(Frame::StreamHead(self.buf.split_to(head_len).freeze())));
+        }
+
+        let message = match head.body {
+            BodyLength::Fixed(len) => {
+                if self.buf.len() < head_len + len {
+                    return Ok(None);
+                }
+                self.buf.split_to(head_len + len).freeze()
+            }
+            BodyLength::Chunked => {
+                let Some((consumed, body)) =
+                    parse_chunked(&self.buf[head_len..], self.limits.max_body)?
+                else {
+                    return Ok(None);
+                };
+                let mut message = BytesMut::with_capacity(head_len + body.len());
+                message.extend_from_slice(&self.buf[..head_len]);
+                message.extend_from_slice(&body);
+                self.buf.advance(head_len + consumed);
+                message.freeze()
+            }
+        };
+
+        self.head = None;
+        Ok(Some(Frame::Message(message)))
+    }
+
+    /// Продолжить разбор тела потокового ответа
+    fn decode_stream(&mut self, mut state: ChunkState) -> Result<Option<Frame>> {
+        let frame = loop {
+            match state {
+                ChunkState::Size => {
+                    let Some(line_len) = find_crlf(&self.buf, MAX_CHUNK_LINE)? else {
+                        break None;
+                    };
+                    let size = parse_chunk_size(&self.buf[..line_len])?;
+                    self.buf.advance(line_len + 2);
+                    state = if size == 0 {
+                        ChunkState::Trailer
+                    } else if size > self.limits.max_body {
+                        return Err(MimicryError::ParseError(format!(
+                            "chunk {} байт больше ограничения {}",
+                            size, self.limits.max_body
+                        )));
+                    } else {
+                        ChunkState::Data(size)
+                    };
+                }
+                ChunkState::Data(remaining) => {
+                    if self.buf.is_empty() {
+                        break None;
+                    }
+                    let take = remaining.min(self.buf.len());
+                    state = if take == remaining {
+                        ChunkState::DataEnd
+                    } else {
+                        ChunkState::Data(remaining - take)
+                    };
+                    break Some(Frame::StreamData(self.buf.split_to(take).freeze()));
+                }
+                ChunkState::DataEnd => {
+                    if self.buf.len() < 2 {
+                        break None;
+                    }
+                    if &self.buf[..2] != b"\r\n" {
+                        return Err(MimicryError::ParseError("нет CRLF после chunk".to_string()));
+                    }
+                    self.buf.advance(2);
+                    state = ChunkState::Size;
+                }
+                ChunkState::Trailer => {
+                    let Some(line_len) = find_crlf(&self.buf, MAX_CHUNK_LINE)? else {
+                        break None;
+                    };
+                    self.buf.advance(line_len + 2);
+                    if line_len == 0 {
+                        self.stream = None;
+                        return Ok(Some(Frame::StreamEnd));
+                    }
+                }
+            }
+        };
+
+        self.stream = Some(state);
+        Ok(frame)
+    }
+
+    /// Разобрать заголовок сообщения в начале буфера
+    fn parse_head(&self) -> Result<Option<Head>> {
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+
+        let (status, headers, is_response, code) = if self.buf.starts_with(b"HTTP/") {
+            let mut resp = httparse::Response::new(&mut headers);
+            let status = resp.parse(&self.buf).map_err(parse_error)?;
+            let code = resp.code;
+            (status, resp.headers, true, code)
+        } else if self.buf.len() < 5 && b"HTTP/".starts_with(&self.buf) {
+            return Ok(None);
+        } else {
+            let mut req = httparse::Request::new(&mut headers);
+            let status = req.parse(&self.buf).map_err(parse_error)?;
+            (status, req.headers, false, None)
+        };
+
+        let head_len = match status {
+            httparse::Status::Complete(len) => len,
+            httparse::Status::Partial => {
+                if self.buf.len() > self.limits.max_head {
+                    return Err(MimicryError::ParseError(format!(
+                        "заголовок HTTP больше {} байт",
+                        self.limits.max_head
+                    )));
+                }
+                return Ok(None);
+            }
+        };
+        if head_len > self.limits.max_head {
+            return Err(MimicryError::ParseError(format!(
+                "заголовок HTTP больше {} байт",
+                self.limits.max_head
+            )));
+        }
+
+        let mut content_length: Option<usize> = None;
+        let mut chunked = false;
+        for header in headers.iter() {
+            if header.name.eq_ignore_ascii_case("content-length") {
+                let value = std::str::from_utf8(header.value)
+                    .ok()
+                    .and_then(|v| v.trim().parse::<usize>().ok())
+                    .ok_or_else(|| {
+                        MimicryError::ParseError("некорректный Content-Length".to_string())
+                    })?;
+                if content_length.is_some_and(|existing| existing != value) {
+                    return Err(MimicryError::ParseError(
+                        "противоречивые Content-Length".to_string(),
+                    ));
+                }
+                content_length = Some(value);
+            } else if header.name.eq_ignore_ascii_case("transfer-encoding") {
+                let value = String::from_utf8_lossy(header.value).to_ascii_lowercase();
+                if value.trim().rsplit(',').next().map(str::trim) != Some("chunked") {
+                    return Err(MimicryError::ParseError(format!(
+                        "неподдерживаемый Transfer-Encoding: {}",
+                        value
+                    )));
+                }
+                chunked = true;
+            }
+        }
+
+        let body = match (content_length, chunked) {
+            (Some(_), true) => {
+                return Err(MimicryError::ParseError(
+                    "Content-Length вместе с Transfer-Encoding".to_string(),
+                ));
+            }
+            (Some(len), false) if len > self.limits.max_body => {
+                return Err(MimicryError::ParseError(format!(
+                    "тело HTTP {} байт больше ограничения {}",
+                    len, self.limits.max_body
+                )));
+            }
+            (Some(len), false) => BodyLength::Fixed(len),
+            (None, true) => BodyLength::Chunked,
+            // Ответ без длины читался бы до закрытия соединения
+            (None, false) if is_response && !matches!(code, Some(100..=199 | 204 | 304)) => {
+                return Err(MimicryError::ParseError(
+                    "ответ без Content-Length".to_string(),
+                ));
+            }
+            (None, false) => BodyLength::Fixed(0),
+        };
+
+        Ok(Some(Head {
+            len: head_len,
+            body,
+            is_response,
+        }))
+    }
+}
+
+fn parse_error(error: httparse::Error) -> MimicryError {
+    MimicryError::ParseError(format!("HTTP parse error: {:?}", error))
+}
+
+/// Разобрать chunked тело: (потреблено байт, собранное тело)
+fn parse_chunked(data: &[u8], max_body: usize) -> Result<Option<(usize, Vec<u8>)>> {
+    let mut pos = 0;
+    let mut body = Vec::new();
+
+    loop {
+        let Some(line_len) = find_crlf(&data[pos..], MAX_CHUNK_LINE)? else {
+            return Ok(None);
+        };
+        let line_end = pos.checked_add(line_len).ok_or_else(chunk_overflow)?;
+        let size = parse_chunk_size(&data[pos..line_end])?;
+        pos = line_end.checked_add(2).ok_or_else(chunk_overflow)?;
+
+        if size == 0 {
+            // Trailer: строки до пустой
+            loop {
+                let Some(line_len) = find_crlf(&data[pos..], MAX_CHUNK_LINE)? else {
+                    return Ok(None);
+                };
+                pos = pos.checked_add(line_len + 2).ok_or_else(chunk_overflow)?;
+                if line_len == 0 {
+                    return Ok(Some((pos, body)));
+                }
+            }
+        }
+
+        // Размер chunk приходит из сети: сравнение до любого сложения
+        if size > max_body.saturating_sub(body.len()) {
+            return Err(MimicryError::ParseError(format!(
+                "тело HTTP больше ограничения {}",
+                max_body
+            )));
+        }
+        let data_end = pos.checked_add(size).ok_or_else(chunk_overflow)?;
+        let chunk_end = data_end.checked_add(2).ok_or_else(chunk_overflow)?;
+        if data.len() < chunk_end {
+            return Ok(None);
+        }
+        if &data[data_end..chunk_end] != b"\r\n" {
+            return Err(MimicryError::ParseError("нет CRLF после chunk".to_string()));
+        }
+        body.extend_from_slice(&data[pos..data_end]);
+        pos = chunk_end;
+    }
+}
+
+/// Ошибка переполнения позиции в chunked теле
+fn chunk_overflow() -> MimicryError {
+    MimicryError::ParseError("некорректный размер chunk".to_string())
+}
+
+/// Размер chunk из строки `<hex>[;ext]`
+fn parse_chunk_size(line: &[u8]) -> Result<usize> {
+    let line = std::str::from_utf8(line)
+        .map_err(|_| MimicryError::ParseError("некорректная строка chunk".to_string()))?;
+    let size_hex = line.split(';').next().unwrap_or_default().trim();
+    usize::from_str_radix(size_hex, 16)
+        .map_err(|_| MimicryError::ParseError(format!("некорректный размер chunk: {}", line)))
+}
+
+/// Позиция CRLF в пределах `max_line` байт
+fn find_crlf(data: &[u8], max_line: usize) -> Result<Option<usize>> {
+    match data.windows(2).position(|w| w == b"\r\n") {
+        Some(pos) if pos <= max_line => Ok(Some(pos)),
+        Some(_) => Err(MimicryError::ParseError("слишком длинная строка chunk".to_string())),
+        None if data.len() > max_line => {
+            Err(MimicryError::ParseError("слишком длинная строка chunk".to_string()))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Записать сообщение обёртки в поток
+///
+/// HTTP сообщения пишутся как есть; без мимикрии (`http == false`)
+/// перед сообщением пишется длина `u32`.
+pub async fn write_frame<W>(writer: &mut W, http: bool, message: &[u8]) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    if !http {
+        writer.write_u32(message.len() as u32).await?;
+    }
+    writer.write_all(message).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Прочитать следующее сообщение из потока
+///
+/// Данные, прочитанные сверх сообщения, остаются в `decoder` до
+/// следующего вызова.
+pub async fn read_frame<R>(reader: &mut R, decoder: &mut HttpDecoder, http: bool) -> Result<Bytes>
+where
+    R: AsyncRead + Unpin,
+{
+    loop {
+        let message = match http {
+            true => decoder.decode()?,
+            false => decoder.decode_prefixed()?,
+        };
+        if let Some(message) = message {
+            return Ok(message);
+        }
+        fill(reader, decoder).await?;
+    }
+}
+
+/// Прочитать следующее сообщение или часть потокового ответа
+///
+/// В отличие от [`read_frame`] отдаёт chunked ответы частями, если у
+/// `decoder` включён потоковый режим.
+pub async fn read_stream_frame<R>(
+    reader: &mut R,
+    decoder: &mut HttpDecoder,
+    http: bool,
+) -> Result<Frame>
+where
+    R: AsyncRead + Unpin,
+{
+    if !http {
+        return read_frame(reader, decoder, http).await.map(Frame::Message);
+    }
+
+    loop {
+        if let Some(frame) = decoder.decode_frame()? {
+            return Ok(frame);
+        }
+        fill(reader, decoder).await?;
+    }
+}
+
+/// Дочитать данные из потока в буфер декодера
+async fn fill<R>(reader: &mut R, decoder: &mut HttpDecoder) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+{
+    if reader.read_buf(&mut decoder.buf).await? == 0 {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_length_across_reads() {
+        let message = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+        let mut decoder = HttpDecoder::new();
+
+        // Подаём по одному байту: до последнего байта сообщения нет
+        for byte in &message[..message.len() - 1] {
+            decoder.extend(&[*byte]);
+            assert!(decoder.decode().unwrap().is_none());
+        }
+        decoder.extend(&message[message.len() - 1..]);
+        assert_eq!(&decoder.decode().unwrap().unwrap()[..], &message[..]);
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn test_pipelined_messages() {
+        let mut decoder = HttpDecoder::new();
+        decoder.extend(b"GET /a HTTP/1.1\r\nHost: x\r\n\r\n");
+        decoder.extend(b"POST /b HTTP/1.1\r\nContent-Length: 3\r\n\r\nabcHTTP/1.1 204 No Content\r\n\r\n");
+
+        let first = decoder.decode().unwrap().unwrap();
+        assert!(first.starts_with(b"GET /a"));
+        let second = decoder.decode().unwrap().unwrap();
+        assert!(second.ends_with(b"\r\n\r\nabc"));
+        let third = decoder.decode().unwrap().unwrap();
+        assert!(third.starts_with(b"HTTP/1.1 204"));
+        assert!(decoder.decode().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_chunked_body() {
+        let mut decoder = HttpDecoder::new();
+        let message = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                        4\r\nWiki\r\n6;ext=1\r\npedia \r\n0\r\nX-Trailer: 1\r\n\r\nHTTP/";
+        decoder.extend(&message[..60]);
+        assert!(decoder.decode().unwrap().is_none());
+        decoder.extend(&message[60..]);
+
+        let decoded = decoder.decode().unwrap().unwrap();
+        assert!(decoded.ends_with(b"\r\n\r\nWikipedia "));
+        assert_eq!(decoder.buffered(), 5);
+    }
+
+    #[test]
+    fn test_huge_chunk_size_rejected() {
+        // Размер второго chunk переполнял сложение позиции
+        let mut decoder = HttpDecoder::new();
+        decoder.extend(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n");
+        decoder.extend(b"1\r\na\r\nffffffffffffffff\r\nxx");
+        assert!(decoder.decode().is_err());
+    }
+
+    #[test]
+    fn test_limits_and_malformed() {
+        let limits = DecoderLimits {
+            max_head: 64,
+            max_body: 16,
+        };
+
+        let cases: [&[u8]; 6] = [
+            b"HTTP/1.1 200 OK\r\nContent-Length: 17\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\nTransfer-Encoding: chunked\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\nContent-Length: 2\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nServer: nginx\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n20\r\n",
+            b"\x00\x00\x01\x2cHTTP/1.1 200 OK\r\n\r\n",
+        ];
+        for case in cases {
+            let mut decoder = HttpDecoder::with_limits(limits);
+            decoder.extend(case);
+            assert!(decoder.decode().is_err(), "{:?}", String::from_utf8_lossy(case));
+        }
+
+        let mut decoder = HttpDecoder::with_limits(limits);
+        decoder.extend(&[b'A'; 65]);
+        assert!(decoder.decode().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_write_frames() {
+        use crate::{PacketWrapper, Role};
+        use llp_core::packet::MimicryProfile;
+
+        let (mut client_io, mut server_io) = tokio::io::duplex(64);
+        let mut client = PacketWrapper::new(MimicryProfile::RuTube).with_role(Role::Client);
+        let mut server = PacketWrapper::new(MimicryProfile::RuTube).with_role(Role::Server);
+
+        let writer = tokio::spawn(async move {
+            for packet in [&b"first"[..], &b"second"[..]] {
+                let request = client.wrap(packet).unwrap();
+                write_frame(&mut client_io, true, &request).await.unwrap();
+            }
+        });
+
+        let mut decoder = HttpDecoder::new();
+        for expected in [&b"first"[..], &b"second"[..]] {
+            let message = read_frame(&mut server_io, &mut decoder, true).await.unwrap();
+            assert!(!message.starts_with(b"\x00"));
+            assert_eq!(&server.unwrap(&message).unwrap()[..], expected);
+        }
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prefixed_frames_across_reads() {
+        // Профиль без мимикрии: префикс длины копится в буфере декодера
+        let (mut client_io, mut server_io) = tokio::io::duplex(3);
+        let writer = tokio::spawn(async move {
+            for message in [&b"first"[..], &b""[..], &b"second"[..]] {
+                write_frame(&mut client_io, false, message).await.unwrap();
+            }
+            client_io.write_all(&[0xFF; 4]).await.unwrap();
+        });
+
+        let mut decoder = HttpDecoder::new();
+        for expected in [&b"first"[..], &b""[..], &b"second"[..]] {
+            let message = read_frame(&mut server_io, &mut decoder, false)
+                .await
+                .unwrap();
+            assert_eq!(&message[..], expected);
+        }
+        assert!(read_frame(&mut server_io, &mut decoder, false)
+            .await
+            .is_err());
+        writer.await.unwrap();
+    }
+}
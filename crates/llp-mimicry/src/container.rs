@@ -0,0 +1,547 @@
+//! Медиаконтейнеры для тел ответов
+//!
+//! Ответ с `Content-Type: video/mp2t`, тело которого — голый ciphertext
+//! без единого sync байта MPEG-TS, выдаёт себя при поверхностной проверке
+//! содержимого. Кодировщики этого модуля укладывают payload в структурно
+//! корректный контейнер, выбранный по `Content-Type` ответа
+//! ([`Container::for_content_type`]):
+//!
+//! ```text
+//! video/mp2t          MPEG-TS: PAT, PMT, PES H.264 в пакетах по 188 байт
+//! audio/mpeg          MP3: кадры MPEG-1 Layer III 128 кбит/с, 44.1 кГц
+//! audio/aac           AAC: кадры ADTS (AAC LC, 44.1 кГц, стерео)
+//! video/mp4, audio/mp4,
+//! video/iso.segment   fMP4: фрагмент moof + mdat
+//! ```
+//!
+//! Получатель выбирает декодер по тому же заголовку ([`decode_body`]),
+//! поэтому payload извлекается без потерь.
+
+use bytes::{BufMut, Bytes, BytesMut};
+use rand::RngCore;
+
+use crate::error::{MimicryError, Result};
+
+/// Размер пакета MPEG-TS
+pub const TS_PACKET_SIZE: usize = 188;
+
+/// Sync байт пакета MPEG-TS
+const TS_SYNC: u8 = 0x47;
+
+/// Полезная нагрузка пакета MPEG-TS без заголовка
+const TS_PAYLOAD: usize = TS_PACKET_SIZE - 4;
+
+/// PID таблицы PAT
+const PAT_PID: u16 = 0x0000;
+
+/// PID таблицы PMT (как у ffmpeg)
+const PMT_PID: u16 = 0x1000;
+
+/// PID видеопотока
+const VIDEO_PID: u16 = 0x0100;
+
+/// stream_type H.264 в PMT
+const STREAM_TYPE_H264: u8 = 0x1B;
+
+/// Начало заголовка PES видеопотока
+const PES_VIDEO_START: [u8; 4] = [0x00, 0x00, 0x01, 0xE0];
+
+/// Размер кадра MP3: 144 * 128000 / 44100 без padding
+const MP3_FRAME_SIZE: usize = 417;
+
+/// Заголовок кадра: MPEG-1 Layer III, без CRC, 128 кбит/с, 44.1 кГц, joint stereo
+const MP3_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x44];
+
+/// Side info MPEG-1 стерео
+const MP3_SIDE_INFO: usize = 32;
+
+/// Данные кадра MP3 после заголовка и side info
+const MP3_DATA: usize = MP3_FRAME_SIZE - MP3_HEADER.len() - MP3_SIDE_INFO;
+
+/// Заголовок ADTS без CRC
+const ADTS_HEADER: usize = 7;
+
+/// Размер кадра ADTS: ~1024 отсчёта при 128 кбит/с и 44.1 кГц
+const ADTS_FRAME_SIZE: usize = 372;
+
+/// Размер sample во фрагменте fMP4
+const FMP4_SAMPLE_SIZE: usize = 4096;
+
+/// Контейнер тела ответа
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    /// Тело без контейнера
+    Raw,
+    /// MPEG-TS (`video/mp2t`)
+    MpegTs,
+    /// Кадры MP3 (`audio/mpeg`)
+    Mp3,
+    /// Кадры AAC ADTS (`audio/aac`)
+    Adts,
+    /// Фрагмент fMP4 (`video/mp4`, `audio/mp4`, `video/iso.segment`)
+    Fmp4,
+}
+
+impl Container {
+    /// Контейнер для значения заголовка `Content-Type`
+    pub fn for_content_type(content_type: &str) -> Self {
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        match mime.to_ascii_lowercase().as_str() {
+            "video/mp2t" => Self::MpegTs,
+            "audio/mpeg" | "audio/mp3" => Self::Mp3,
+            "audio/aac" | "audio/aacp" => Self::Adts,
+            "video/mp4" | "audio/mp4" | "video/iso.segment" => Self::Fmp4,
+            _ => Self::Raw,
+        }
+    }
+
+    /// Уложить payload в контейнер
+    ///
+    /// `rng` задаёт метки времени (PTS, decode time) и номер фрагмента.
+    pub fn encode<R: RngCore + ?Sized>(self, payload: &[u8], rng: &mut R) -> Bytes {
+        match self {
+            Self::Raw => Bytes::copy_from_slice(payload),
+            Self::MpegTs => encode_ts(payload, rng),
+            Self::Mp3 => encode_mp3(payload),
+            Self::Adts => encode_adts(payload),
+            Self::Fmp4 => encode_fmp4(payload, rng),
+        }
+    }
+
+    /// Извлечь payload из тела
+    pub fn decode(self, body: &[u8]) -> Result<Bytes> {
+        match self {
+            Self::Raw => Ok(Bytes::copy_from_slice(body)),
+            Self::MpegTs => decode_ts(body),
+            Self::Mp3 => decode_mp3(body),
+            Self::Adts => decode_adts(body),
+            Self::Fmp4 => decode_fmp4(body),
+        }
+    }
+}
+
+/// Извлечь payload из тела HTTP ответа по его `Content-Type`
+pub fn decode_body(headers: &[httparse::Header], body: &[u8]) -> Result<Bytes> {
+    let content_type = headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case("content-type"))
+        .and_then(|header| std::str::from_utf8(header.value).ok())
+        .unwrap_or_default();
+    Container::for_content_type(content_type).decode(body)
+}
+
+fn invalid<T>(container: &str, reason: &str) -> Result<T> {
+    Err(MimicryError::UnwrapError(format!(
+        "{}: {}",
+        container, reason
+    )))
+}
+
+/// CRC-32/MPEG-2 секций PSI
+fn crc32_mpeg(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= (*byte as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Секция PSI: table_id, длина, тело и CRC
+fn psi_section(table_id: u8, body: &[u8]) -> BytesMut {
+    let mut section = BytesMut::with_capacity(body.len() + 7);
+    section.put_u8(table_id);
+    // section_syntax_indicator = 1, reserved, длина с учётом CRC
+    section.put_u16(0xB000 | (body.len() + 4) as u16);
+    section.put_slice(body);
+    let crc = crc32_mpeg(&section);
+    section.put_u32(crc);
+    section
+}
+
+/// Таблица PAT: программа 1 с PMT на [`PMT_PID`]
+fn pat_section() -> BytesMut {
+    let mut body = BytesMut::new();
+    body.put_u16(0x0001); // transport_stream_id
+    body.put_u8(0xC1); // version 0, current_next_indicator
+    body.put_u8(0x00); // section_number
+    body.put_u8(0x00); // last_section_number
+    body.put_u16(0x0001); // program_number
+    body.put_u16(0xE000 | PMT_PID);
+    psi_section(0x00, &body)
+}
+
+/// Таблица PMT: один поток H.264 на [`VIDEO_PID`]
+fn pmt_section() -> BytesMut {
+    let mut body = BytesMut::new();
+    body.put_u16(0x0001); // program_number
+    body.put_u8(0xC1);
+    body.put_u8(0x00);
+    body.put_u8(0x00);
+    body.put_u16(0xE000 | VIDEO_PID); // PCR_PID
+    body.put_u16(0xF000); // program_info_length = 0
+    body.put_u8(STREAM_TYPE_H264);
+    body.put_u16(0xE000 | VIDEO_PID);
+    body.put_u16(0xF000); // ES_info_length = 0
+    psi_section(0x02, &body)
+}
+
+/// Пакет MPEG-TS; короткие данные дополняются stuffing в adaptation field
+fn put_ts_packet(out: &mut BytesMut, pid: u16, start: bool, counter: u8, data: &[u8]) {
+    let stuffing = TS_PAYLOAD - data.len();
+    out.put_u8(TS_SYNC);
+    out.put_u16(((start as u16) << 14) | pid);
+    if stuffing == 0 {
+        out.put_u8(0x10 | (counter & 0x0F));
+    } else {
+        out.put_u8(0x30 | (counter & 0x0F));
+        out.put_u8((stuffing - 1) as u8);
+        if stuffing > 1 {
+            out.put_u8(0x00); // флаги adaptation field
+            out.put_bytes(0xFF, stuffing - 2);
+        }
+    }
+    out.put_slice(data);
+}
+
+/// Пакет с секцией PSI: pointer_field и заполнение 0xFF
+fn put_psi_packet(out: &mut BytesMut, pid: u16, section: &[u8]) {
+    out.put_u8(TS_SYNC);
+    out.put_u16(0x4000 | pid);
+    out.put_u8(0x10);
+    out.put_u8(0x00); // pointer_field
+    out.put_slice(section);
+    out.put_bytes(0xFF, TS_PAYLOAD - 1 - section.len());
+}
+
+/// PTS в 5 байтах заголовка PES (префикс '0010' и marker биты)
+fn put_pts(out: &mut BytesMut, pts: u64) {
+    out.put_u8(0x21 | ((pts >> 29) & 0x0E) as u8);
+    out.put_u8((pts >> 22) as u8);
+    out.put_u8(0x01 | ((pts >> 14) & 0xFE) as u8);
+    out.put_u8((pts >> 7) as u8);
+    out.put_u8(0x01 | ((pts << 1) & 0xFE) as u8);
+}
+
+fn encode_ts<R: RngCore + ?Sized>(payload: &[u8], rng: &mut R) -> Bytes {
+    let mut pes = BytesMut::with_capacity(payload.len() + 14);
+    pes.put_slice(&PES_VIDEO_START);
+    pes.put_u16(0); // длина не ограничена (видео)
+    pes.put_u8(0x80); // marker '10'
+    pes.put_u8(0x80); // есть PTS
+    pes.put_u8(5); // PES_header_data_length
+    put_pts(&mut pes, rng.next_u64() & ((1 << 33) - 1));
+    pes.put_slice(payload);
+
+    let packets = 2 + pes.len().div_ceil(TS_PAYLOAD);
+    let mut out = BytesMut::with_capacity(packets * TS_PACKET_SIZE);
+    put_psi_packet(&mut out, PAT_PID, &pat_section());
+    put_psi_packet(&mut out, PMT_PID, &pmt_section());
+    for (counter, data) in pes.chunks(TS_PAYLOAD).enumerate() {
+        put_ts_packet(&mut out, VIDEO_PID, counter == 0, counter as u8, data);
+    }
+    out.freeze()
+}
+
+fn decode_ts(body: &[u8]) -> Result<Bytes> {
+    if body.is_empty() || !body.len().is_multiple_of(TS_PACKET_SIZE) {
+        return invalid("MPEG-TS", "длина не кратна 188");
+    }
+
+    let mut pes = BytesMut::with_capacity(body.len());
+    for packet in body.chunks(TS_PACKET_SIZE) {
+        if packet[0] != TS_SYNC {
+            return invalid("MPEG-TS", "нет sync байта");
+        }
+        let pid = u16::from_be_bytes([packet[1] & 0x1F, packet[2]]);
+        let control = (packet[3] >> 4) & 0x03;
+        if pid != VIDEO_PID || control & 0x01 == 0 {
+            continue;
+        }
+        let start = match control & 0x02 {
+            0 => 4,
+            _ => 5 + packet[4] as usize,
+        };
+        if start > TS_PACKET_SIZE {
+            return invalid("MPEG-TS", "adaptation field длиннее пакета");
+        }
+        pes.put_slice(&packet[start..]);
+    }
+
+    if pes.len() < 9 || pes[..4] != PES_VIDEO_START {
+        return invalid("MPEG-TS", "нет заголовка PES");
+    }
+    let header = 9 + pes[8] as usize;
+    if pes.len() < header {
+        return invalid("MPEG-TS", "обрезанный заголовок PES");
+    }
+    Ok(pes.freeze().slice(header..))
+}
+
+/// Кадры MP3 с payload в main data
+///
+/// Side info нулевая (part2_3_length = 0): декодер воспроизводит тишину,
+/// а данные кадра считает ancillary data. Перед payload идёт его длина.
+fn encode_mp3(payload: &[u8]) -> Bytes {
+    let mut data = BytesMut::with_capacity(payload.len() + 4);
+    data.put_u32(payload.len() as u32);
+    data.put_slice(payload);
+
+    let frames = data.len().div_ceil(MP3_DATA);
+    let mut out = BytesMut::with_capacity(frames * MP3_FRAME_SIZE);
+    for chunk in data.chunks(MP3_DATA) {
+        out.put_slice(&MP3_HEADER);
+        out.put_bytes(0, MP3_SIDE_INFO);
+        out.put_slice(chunk);
+        out.put_bytes(0, MP3_DATA - chunk.len());
+    }
+    out.freeze()
+}
+
+fn decode_mp3(body: &[u8]) -> Result<Bytes> {
+    if body.is_empty() || !body.len().is_multiple_of(MP3_FRAME_SIZE) {
+        return invalid("MP3", "длина не кратна размеру кадра");
+    }
+
+    let mut data = BytesMut::with_capacity(body.len());
+    for frame in body.chunks(MP3_FRAME_SIZE) {
+        if frame[..MP3_HEADER.len()] != MP3_HEADER {
+            return invalid("MP3", "неверный заголовок кадра");
+        }
+        data.put_slice(&frame[MP3_HEADER.len() + MP3_SIDE_INFO..]);
+    }
+
+    let len = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    if len > data.len() - 4 {
+        return invalid("MP3", "длина payload больше данных кадров");
+    }
+    Ok(data.freeze().slice(4..4 + len))
+}
+
+/// Заголовок ADTS: AAC LC, 44.1 кГц, стерео, без CRC
+fn put_adts_header(out: &mut BytesMut, frame_len: usize) {
+    out.put_u8(0xFF);
+    out.put_u8(0xF1);
+    out.put_u8(0x50);
+    out.put_u8(0x80 | ((frame_len >> 11) & 0x03) as u8);
+    out.put_u8((frame_len >> 3) as u8);
+    out.put_u8((((frame_len & 0x07) << 5) | 0x1F) as u8);
+    out.put_u8(0xFC);
+}
+
+fn encode_adts(payload: &[u8]) -> Bytes {
+    let data = ADTS_FRAME_SIZE - ADTS_HEADER;
+    let frames = payload.len().div_ceil(data).max(1);
+    let mut out = BytesMut::with_capacity(payload.len() + frames * ADTS_HEADER);
+    if payload.is_empty() {
+        put_adts_header(&mut out, ADTS_HEADER);
+    }
+    for chunk in payload.chunks(data) {
+        put_adts_header(&mut out, ADTS_HEADER + chunk.len());
+        out.put_slice(chunk);
+    }
+    out.freeze()
+}
+
+fn decode_adts(mut body: &[u8]) -> Result<Bytes> {
+    if body.is_empty() {
+        return invalid("ADTS", "нет кадров");
+    }
+
+    let mut payload = BytesMut::with_capacity(body.len());
+    while !body.is_empty() {
+        if body.len() < ADTS_HEADER || body[0] != 0xFF || body[1] & 0xF6 != 0xF0 {
+            return invalid("ADTS", "неверный заголовок кадра");
+        }
+        let header = if body[1] & 0x01 != 0 { 7 } else { 9 };
+        let frame_len =
+            ((body[3] as usize & 0x03) << 11) | ((body[4] as usize) << 3) | (body[5] as usize >> 5);
+        if frame_len < header || frame_len > body.len() {
+            return invalid("ADTS", "неверная длина кадра");
+        }
+        payload.put_slice(&body[header..frame_len]);
+        body = &body[frame_len..];
+    }
+    Ok(payload.freeze())
+}
+
+/// Box с полным заголовком (version + flags)
+fn put_full_box(out: &mut BytesMut, kind: &[u8; 4], version_flags: u32, body: &[u8]) {
+    out.put_u32((12 + body.len()) as u32);
+    out.put_slice(kind);
+    out.put_u32(version_flags);
+    out.put_slice(body);
+}
+
+fn encode_fmp4<R: RngCore + ?Sized>(payload: &[u8], rng: &mut R) -> Bytes {
+    let samples: Vec<&[u8]> = payload.chunks(FMP4_SAMPLE_SIZE).collect();
+
+    let mut traf = BytesMut::new();
+    // tfhd: track 1, default-base-is-moof
+    put_full_box(&mut traf, b"tfhd", 0x0002_0000, &1u32.to_be_bytes());
+    // tfdt версии 1: baseMediaDecodeTime
+    let base_time = rng.next_u64() >> 24;
+    put_full_box(&mut traf, b"tfdt", 0x0100_0000, &base_time.to_be_bytes());
+
+    let mfhd_size = 16;
+    let trun_size = 20 + 4 * samples.len();
+    let moof_size = 8 + mfhd_size + 8 + traf.len() + trun_size;
+
+    // trun: data-offset-present, sample-size-present
+    let mut trun = BytesMut::with_capacity(trun_size - 12);
+    trun.put_u32(samples.len() as u32);
+    trun.put_i32((moof_size + 8) as i32);
+    for sample in &samples {
+        trun.put_u32(sample.len() as u32);
+    }
+    put_full_box(&mut traf, b"trun", 0x0000_0201, &trun);
+
+    let mut out = BytesMut::with_capacity(moof_size + 8 + payload.len());
+    out.put_u32(moof_size as u32);
+    out.put_slice(b"moof");
+    let sequence = rng.next_u32() >> 8;
+    put_full_box(&mut out, b"mfhd", 0, &sequence.to_be_bytes());
+    out.put_u32((8 + traf.len()) as u32);
+    out.put_slice(b"traf");
+    out.put_slice(&traf);
+
+    out.put_u32((8 + payload.len()) as u32);
+    out.put_slice(b"mdat");
+    out.put_slice(payload);
+    out.freeze()
+}
+
+fn decode_fmp4(mut body: &[u8]) -> Result<Bytes> {
+    let mut moof = false;
+    while body.len() >= 8 {
+        let size = u32::from_be_bytes([body[0], body[1], body[2], body[3]]) as usize;
+        if size < 8 || size > body.len() {
+            return invalid("fMP4", "неверный размер box");
+        }
+        match &body[4..8] {
+            b"moof" => moof = true,
+            b"mdat" if moof => return Ok(Bytes::copy_from_slice(&body[8..size])),
+            b"mdat" => return invalid("fMP4", "mdat без moof"),
+            _ => {}
+        }
+        body = &body[size..];
+    }
+    invalid("fMP4", "нет mdat")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const ALL: [Container; 5] = [
+        Container::Raw,
+        Container::MpegTs,
+        Container::Mp3,
+        Container::Adts,
+        Container::Fmp4,
+    ];
+
+    #[test]
+    fn test_content_types() {
+        assert_eq!(Container::for_content_type("video/mp2t"), Container::MpegTs);
+        assert_eq!(Container::for_content_type("Audio/MPEG"), Container::Mp3);
+        assert_eq!(Container::for_content_type("audio/aac"), Container::Adts);
+        assert_eq!(
+            Container::for_content_type("video/mp4; codecs=\"avc1.64001f\""),
+            Container::Fmp4
+        );
+        assert_eq!(
+            Container::for_content_type("video/iso.segment"),
+            Container::Fmp4
+        );
+        assert_eq!(
+            Container::for_content_type("application/octet-stream"),
+            Container::Raw
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for container in ALL {
+            for len in [0, 1, 170, 184, 381, 1400, 65 * 1024] {
+                let payload: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+                let body = container.encode(&payload, &mut rng);
+                let decoded = container.decode(&body).unwrap();
+                assert_eq!(&decoded[..], &payload[..], "{:?} {}", container, len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mpeg_ts_structure() {
+        // PAT как у ffmpeg: программа 1, PMT на 0x1000
+        assert_eq!(
+            &pat_section()[..],
+            &[
+                0x00, 0xB0, 0x0D, 0x00, 0x01, 0xC1, 0x00, 0x00, 0x00, 0x01, 0xF0, 0x00, 0x2A, 0xB1,
+                0x04, 0xB2
+            ]
+        );
+        assert_eq!(crc32_mpeg(&pmt_section()), 0);
+
+        let body = Container::MpegTs.encode(&[0xAB; 1000], &mut StdRng::seed_from_u64(1));
+        assert_eq!(body.len() % TS_PACKET_SIZE, 0);
+        for (i, packet) in body.chunks(TS_PACKET_SIZE).enumerate() {
+            assert_eq!(packet[0], TS_SYNC);
+            let pid = u16::from_be_bytes([packet[1] & 0x1F, packet[2]]);
+            let expected = match i {
+                0 => PAT_PID,
+                1 => PMT_PID,
+                _ => VIDEO_PID,
+            };
+            assert_eq!(pid, expected);
+        }
+        assert_eq!(&body[2 * TS_PACKET_SIZE + 4..][..4], &PES_VIDEO_START);
+    }
+
+    #[test]
+    fn test_audio_frames() {
+        let mp3 = Container::Mp3.encode(&[1; 1000], &mut StdRng::seed_from_u64(1));
+        assert_eq!(mp3.len() % MP3_FRAME_SIZE, 0);
+        for frame in mp3.chunks(MP3_FRAME_SIZE) {
+            assert_eq!(&frame[..4], &MP3_HEADER);
+        }
+
+        let adts = Container::Adts.encode(&[1; 1000], &mut StdRng::seed_from_u64(1));
+        assert_eq!(&adts[..2], &[0xFF, 0xF1]);
+        assert_eq!(&adts[ADTS_FRAME_SIZE..][..2], &[0xFF, 0xF1]);
+        assert_eq!(adts.len(), 1000 + 3 * ADTS_HEADER);
+    }
+
+    #[test]
+    fn test_fmp4_boxes() {
+        let body = Container::Fmp4.encode(&[2; 10_000], &mut StdRng::seed_from_u64(1));
+        assert_eq!(&body[4..8], b"moof");
+        let moof = u32::from_be_bytes([body[0], body[1], body[2], body[3]]) as usize;
+        assert_eq!(&body[moof + 4..moof + 8], b"mdat");
+        assert_eq!(body.len(), moof + 8 + 10_000);
+
+        // trun.data_offset указывает на начало данных mdat
+        let trun = body.windows(4).position(|w| w == b"trun").unwrap();
+        assert_eq!(&body[trun + 8..trun + 12], &3u32.to_be_bytes());
+        let offset = i32::from_be_bytes(body[trun + 12..trun + 16].try_into().unwrap());
+        assert_eq!(offset as usize, moof + 8);
+    }
+
+    #[test]
+    fn test_decode_rejects_raw_ciphertext() {
+        let ciphertext = [0x5Au8; 376];
+        for container in &ALL[1..] {
+            assert!(container.decode(&ciphertext).is_err(), "{:?}", container);
+        }
+    }
+}
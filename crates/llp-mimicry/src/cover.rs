@@ -0,0 +1,472 @@
+//! Cover traffic во время простоя туннеля
+//!
+//! Видеопоток, замолкающий вместе с пользователем, не похож на
+//! видеопоток. [`CoverTraffic`] после `idle_after` тишины выдаёт размеры
+//! фиктивных пакетов ([`llp_core::cover`]) так, чтобы поток следовал
+//! кривой битрейта профиля ([`BitrateCurve`]): загрузка сегмента на
+//! повышенном битрейте, затем пауза до следующего сегмента.
+//!
+//! ```text
+//! битрейт
+//!   ▲  ┌──┐        ┌──┐        ┌──┐
+//!   │  │  │        │  │        │  │
+//!   └──┴──┴────────┴──┴────────┴──┴──▶ время
+//!      └── period ─┘
+//!      duty · period
+//! ```
+//!
+//! Потолки `max_rate` и `max_bytes_per_hour` ограничивают расход для
+//! лимитных подключений. Генератор не держит таймеров: вызывающий
+//! сообщает о реальном трафике ([`CoverTraffic::record_activity`]), ждёт
+//! [`CoverTraffic::wait`] и забирает пакеты ([`CoverTraffic::poll`]).
+
+use rand::rngs::OsRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::error::{MimicryError, Result};
+use crate::exchange::Role;
+use crate::profiles::BoxedRng;
+
+/// Шаг начисления кредита в простое
+pub const COVER_TICK: Duration = Duration::from_millis(20);
+
+/// Окно потолка объёма
+const BUDGET_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Предельный кредит: не больше этой доли секунды на целевом битрейте
+const MAX_CREDIT_SECS: f64 = 0.25;
+
+/// Кривая целевого битрейта профиля
+///
+/// В начале каждого периода идёт загрузка сегмента (`duty` периода) с
+/// битрейтом `mean_kbps / duty`, остаток периода — тишина. Клиент
+/// отправляет долю `upload_ratio` битрейта сервера (запросы и ACK).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BitrateCurve {
+    /// Средний битрейт потока сервера (кбит/с)
+    pub mean_kbps: u32,
+    /// Период загрузки сегмента
+    pub period: Duration,
+    /// Доля периода, в которую идёт загрузка (`0.0 < duty <= 1.0`)
+    pub duty: f64,
+    /// Доля битрейта сервера в потоке клиента (`0.0..=1.0`)
+    pub upload_ratio: f64,
+}
+
+impl BitrateCurve {
+    /// Видео: сегменты по 4 секунды на 2.5 Мбит/с
+    pub fn video_streaming() -> Self {
+        Self {
+            mean_kbps: 2500,
+            period: Duration::from_secs(4),
+            duty: 0.35,
+            upload_ratio: 0.02,
+        }
+    }
+
+    /// Аудио: прогрессивная загрузка трека 256 кбит/с
+    pub fn audio_streaming() -> Self {
+        Self {
+            mean_kbps: 256,
+            period: Duration::from_secs(10),
+            duty: 0.5,
+            upload_ratio: 0.02,
+        }
+    }
+
+    /// Проверить параметры кривой
+    pub fn is_valid(&self) -> bool {
+        !self.period.is_zero()
+            && self.duty > 0.0
+            && self.duty <= 1.0
+            && (0.0..=1.0).contains(&self.upload_ratio)
+    }
+
+    /// Целевой битрейт стороны `role` через `elapsed` от начала потока (бит/с)
+    pub fn rate_at(&self, elapsed: Duration, role: Role) -> f64 {
+        let period = self.period.as_secs_f64();
+        let phase = (elapsed.as_secs_f64() % period) / period;
+        if phase >= self.duty {
+            return 0.0;
+        }
+        let rate = f64::from(self.mean_kbps) * 1000.0 / self.duty;
+        match role {
+            Role::Client => rate * self.upload_ratio,
+            _ => rate,
+        }
+    }
+}
+
+/// Параметры генератора cover traffic
+#[derive(Debug, Clone)]
+pub struct CoverOptions {
+    /// Тишина, после которой начинается cover traffic
+    pub idle_after: Duration,
+    /// Потолок битрейта (бит/с, 0 — только кривая профиля)
+    pub max_rate: u64,
+    /// Потолок объёма за час (байт, 0 — без потолка)
+    pub max_bytes_per_hour: u64,
+    /// Наибольший размер фиктивного пакета (байт)
+    pub packet_size: usize,
+}
+
+impl Default for CoverOptions {
+    fn default() -> Self {
+        Self {
+            idle_after: Duration::from_secs(1),
+            max_rate: 0,
+            max_bytes_per_hour: 0,
+            packet_size: 1200,
+        }
+    }
+}
+
+/// Наименьший размер фиктивного пакета в настройках
+pub const MIN_COVER_PACKET: usize = 64;
+
+/// Секция `[cover]` конфигурации клиента и сервера
+///
+/// Потолки `max_kbps` и `max_mb_per_hour` ограничивают расход на лимитных
+/// подключениях.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverConfig {
+    /// Отправлять фиктивные пакеты в простое
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Тишина, после которой начинается cover traffic (мс)
+    #[serde(default = "default_idle_after")]
+    pub idle_after_ms: u64,
+
+    /// Потолок битрейта (кбит/с, 0 — только кривая профиля)
+    #[serde(default)]
+    pub max_kbps: u64,
+
+    /// Потолок объёма за час (МБ, 0 — без потолка)
+    #[serde(default)]
+    pub max_mb_per_hour: u64,
+
+    /// Наибольший размер фиктивного пакета (байт, не больше MTU)
+    #[serde(default = "default_packet_size")]
+    pub packet_size: usize,
+}
+
+fn default_idle_after() -> u64 {
+    1000
+}
+
+fn default_packet_size() -> usize {
+    1200
+}
+
+impl Default for CoverConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_after_ms: default_idle_after(),
+            max_kbps: 0,
+            max_mb_per_hour: 0,
+            packet_size: default_packet_size(),
+        }
+    }
+}
+
+impl CoverConfig {
+    /// Параметры генератора cover traffic
+    pub fn options(&self) -> CoverOptions {
+        CoverOptions {
+            idle_after: Duration::from_millis(self.idle_after_ms),
+            max_rate: self.max_kbps * 1000,
+            max_bytes_per_hour: self.max_mb_per_hour * 1024 * 1024,
+            packet_size: self.packet_size,
+        }
+    }
+
+    /// Проверить значения секции для туннеля с MTU `mtu`
+    pub fn validate(&self, mtu: usize) -> Result<()> {
+        if self.enabled && !(MIN_COVER_PACKET..=mtu).contains(&self.packet_size) {
+            return Err(MimicryError::InvalidConfig(
+                "cover.packet_size должен быть в диапазоне 64-MTU".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Генератор фиктивных пакетов по кривой битрейта
+pub struct CoverTraffic {
+    curve: BitrateCurve,
+    options: CoverOptions,
+    role: Role,
+    rng: BoxedRng,
+    /// Начало отсчёта кривой
+    started: Instant,
+    /// Последний реальный пакет
+    last_activity: Instant,
+    /// Последнее начисление кредита
+    last_tick: Instant,
+    /// Накопленный кредит (байт)
+    credit: f64,
+    /// Размер следующего пакета
+    next_size: usize,
+    /// Начало текущего окна потолка объёма
+    window_start: Instant,
+    /// Отправлено в текущем окне (байт)
+    window_bytes: u64,
+    /// Отправлено за всё время (байт)
+    sent_bytes: u64,
+}
+
+impl CoverTraffic {
+    /// Генератор для стороны `role`, поток начинается в `now`
+    pub fn new(curve: BitrateCurve, options: CoverOptions, role: Role, now: Instant) -> Self {
+        Self::with_rng(curve, options, role, now, Box::new(OsRng))
+    }
+
+    /// Генератор с заданным источником случайности
+    pub fn with_rng(
+        curve: BitrateCurve,
+        options: CoverOptions,
+        role: Role,
+        now: Instant,
+        mut rng: BoxedRng,
+    ) -> Self {
+        let next_size = random_size(&mut rng, options.packet_size);
+        Self {
+            curve,
+            options,
+            role,
+            rng,
+            started: now,
+            last_activity: now,
+            last_tick: now,
+            credit: 0.0,
+            next_size,
+            window_start: now,
+            window_bytes: 0,
+            sent_bytes: 0,
+        }
+    }
+
+    /// Параметры генератора
+    pub fn options(&self) -> &CoverOptions {
+        &self.options
+    }
+
+    /// Фиктивных байт отправлено за всё время
+    pub fn sent_bytes(&self) -> u64 {
+        self.sent_bytes
+    }
+
+    /// Отметить реальный трафик: cover traffic замолкает до нового простоя
+    pub fn record_activity(&mut self, now: Instant) {
+        self.last_activity = now;
+        self.last_tick = now;
+        self.credit = 0.0;
+    }
+
+    /// Момент следующей проверки
+    ///
+    /// До простоя — его начало, при исчерпанном потолке объёма — конец
+    /// часового окна, иначе — следующий шаг [`COVER_TICK`].
+    pub fn next_poll(&self) -> Instant {
+        let idle_from = self.last_activity + self.options.idle_after;
+        if self.last_tick < idle_from {
+            return idle_from;
+        }
+        if self.budget_left() < self.next_size as u64 {
+            return self.window_start + BUDGET_WINDOW;
+        }
+        self.last_tick + COVER_TICK
+    }
+
+    /// Размер очередного фиктивного пакета, если он положен в `now`
+    pub fn poll(&mut self, now: Instant) -> Option<usize> {
+        if now < self.last_activity + self.options.idle_after {
+            return None;
+        }
+        if now.duration_since(self.window_start) >= BUDGET_WINDOW {
+            self.window_start = now;
+            self.window_bytes = 0;
+        }
+
+        let tick_start = self
+            .last_tick
+            .max(self.last_activity + self.options.idle_after);
+        if now > tick_start {
+            let rate = self.rate_at(now);
+            let elapsed = now.duration_since(tick_start).as_secs_f64();
+            // Кредит не копится сверх доли секунды, но вмещает хотя бы
+            // один пакет, иначе низкий битрейт не дал бы ни одного
+            let limit = if rate > 0.0 {
+                (rate / 8.0 * MAX_CREDIT_SECS).max(self.options.packet_size as f64)
+            } else {
+                0.0
+            };
+            self.credit = (self.credit + rate / 8.0 * elapsed).min(limit);
+            self.last_tick = now;
+        }
+
+        let size = self.next_size;
+        if self.credit < size as f64 || self.budget_left() < size as u64 {
+            return None;
+        }
+        self.credit -= size as f64;
+        self.window_bytes += size as u64;
+        self.sent_bytes += size as u64;
+        self.next_size = random_size(&mut self.rng, self.options.packet_size);
+        Some(size)
+    }
+
+    /// Дождаться следующей проверки
+    ///
+    /// Отмена безопасна: вызов подходит для ветки `tokio::select!`.
+    pub async fn wait(&self) {
+        tokio::time::sleep_until(self.next_poll().into()).await
+    }
+
+    /// Целевой битрейт с учётом потолка (бит/с)
+    fn rate_at(&self, now: Instant) -> f64 {
+        let rate = self
+            .curve
+            .rate_at(now.duration_since(self.started), self.role);
+        match self.options.max_rate {
+            0 => rate,
+            cap => rate.min(cap as f64),
+        }
+    }
+
+    /// Остаток потолка объёма в текущем окне
+    fn budget_left(&self) -> u64 {
+        match self.options.max_bytes_per_hour {
+            0 => u64::MAX,
+            cap => cap.saturating_sub(self.window_bytes),
+        }
+    }
+}
+
+/// Размер фиктивного пакета: от половины до полного `max`
+fn random_size(rng: &mut BoxedRng, max: usize) -> usize {
+    let max = max.max(2);
+    rng.gen_range(max / 2..=max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn cover(options: CoverOptions, role: Role, now: Instant) -> CoverTraffic {
+        CoverTraffic::with_rng(
+            BitrateCurve::video_streaming(),
+            options,
+            role,
+            now,
+            Box::new(StdRng::seed_from_u64(3)),
+        )
+    }
+
+    #[test]
+    fn test_cover_config() {
+        let mut config: CoverConfig = toml::from_str("enabled = true\nmax_kbps = 256").unwrap();
+        assert_eq!(config.options().max_rate, 256_000);
+        assert_eq!(config.options().idle_after, Duration::from_secs(1));
+        config.max_mb_per_hour = 10;
+        assert_eq!(config.options().max_bytes_per_hour, 10 * 1024 * 1024);
+
+        // Фиктивные пакеты больше MTU
+        config.packet_size = 9000;
+        assert!(config.validate(1400).is_err());
+        config.packet_size = 1200;
+        assert!(config.validate(1400).is_ok());
+        config.packet_size = 32;
+        assert!(config.validate(1400).is_err());
+        config.enabled = false;
+        assert!(config.validate(1400).is_ok());
+    }
+
+    /// Прогнать генератор `duration` с шагом [`COVER_TICK`], вернув объём
+    fn run(cover: &mut CoverTraffic, from: Instant, duration: Duration) -> u64 {
+        let mut total = 0;
+        let mut now = from;
+        while now < from + duration {
+            while let Some(size) = cover.poll(now) {
+                total += size as u64;
+            }
+            now += COVER_TICK;
+        }
+        total
+    }
+
+    #[test]
+    fn test_curve_shape() {
+        let curve = BitrateCurve::video_streaming();
+        assert!(curve.is_valid());
+        let burst = curve.rate_at(Duration::from_millis(100), Role::Server);
+        assert!((burst - 2_500_000.0 / 0.35).abs() < 1.0);
+        assert_eq!(curve.rate_at(Duration::from_secs(3), Role::Server), 0.0);
+        assert!(curve.rate_at(Duration::from_secs(4), Role::Server) > 0.0);
+        assert!(curve.rate_at(Duration::from_millis(100), Role::Client) < burst / 10.0);
+
+        let broken = BitrateCurve { duty: 0.0, ..curve };
+        assert!(!broken.is_valid());
+    }
+
+    #[test]
+    fn test_follows_curve_when_idle() {
+        let start = Instant::now();
+        let mut cover = cover(CoverOptions::default(), Role::Server, start);
+
+        // Пока не наступил простой, пакетов нет
+        assert!(cover.poll(start + Duration::from_millis(500)).is_none());
+        assert_eq!(cover.next_poll(), start + Duration::from_secs(1));
+
+        // За 8 секунд простоя средний битрейт близок к кривой
+        let idle = start + Duration::from_secs(1);
+        let total = run(&mut cover, idle, Duration::from_secs(8));
+        let kbps = total * 8 / 8 / 1000;
+        assert!((1500..=3000).contains(&kbps), "{} кбит/с", kbps);
+        assert_eq!(cover.sent_bytes(), total);
+
+        // Реальный трафик прерывает cover traffic
+        let active = idle + Duration::from_secs(8);
+        cover.record_activity(active);
+        assert!(cover.poll(active + Duration::from_millis(100)).is_none());
+    }
+
+    #[test]
+    fn test_rate_cap() {
+        let start = Instant::now();
+        let options = CoverOptions {
+            idle_after: Duration::ZERO,
+            max_rate: 64_000,
+            ..Default::default()
+        };
+        let mut cover = cover(options, Role::Server, start);
+        let total = run(&mut cover, start, Duration::from_secs(8));
+        // Загрузка по кривой идёт 2.8 с из 8, и всё это время — не выше потолка
+        assert!(total <= 64_000 / 8 * 3, "{} байт", total);
+        assert!(total > 0);
+    }
+
+    #[test]
+    fn test_hourly_budget() {
+        let start = Instant::now();
+        let options = CoverOptions {
+            idle_after: Duration::ZERO,
+            max_bytes_per_hour: 10_000,
+            ..Default::default()
+        };
+        let mut cover = cover(options, Role::Server, start);
+        let total = run(&mut cover, start, Duration::from_secs(4));
+        assert!(total <= 10_000 && total > 8_000, "{} байт", total);
+
+        // До конца часа пакетов больше нет, затем окно обновляется
+        assert_eq!(cover.next_poll(), start + BUDGET_WINDOW);
+        let next_hour = start + BUDGET_WINDOW;
+        assert!(run(&mut cover, next_hour, Duration::from_secs(1)) > 0);
+    }
+}
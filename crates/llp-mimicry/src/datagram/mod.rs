@@ -0,0 +1,116 @@
+//! Маскировка UDP датаграмм
+//!
+//! UDP listener сервера обменивается датаграммами `[nonce:12][ciphertext]`
+//! без какой-либо мимикрии. [`DatagramDisguise`] оборачивает каждую
+//! датаграмму LLP в заголовки распространённого UDP протокола и снимает
+//! их при приёме. Режим выбирается в конфигурации ([`DatagramMode`]):
+//!
+//! - `quic` — пакеты QUIC v1, как у большей части трафика UDP/443
+//!   ([`quic`])
+//! - `webrtc` — проверки связности STUN и медиапоток SRTP видеозвонка
+//!   ([`webrtc`])
+//!
+//! Маскировка не выполняет ввод-вывод и хранит состояние одного
+//! собеседника: у сервера — по экземпляру на адрес клиента.
+
+use std::net::SocketAddr;
+
+use bytes::Bytes;
+
+use crate::error::{MimicryError, Result};
+use crate::exchange::Role;
+
+pub mod quic;
+pub mod webrtc;
+
+pub use quic::QuicDisguise;
+pub use webrtc::WebRtcDisguise;
+
+/// Маскировка датаграмм одного UDP собеседника
+pub trait DatagramDisguise: Send {
+    /// Обернуть датаграмму LLP
+    fn wrap(&mut self, payload: &[u8]) -> Result<Bytes>;
+
+    /// Снять маскировку с принятой датаграммы
+    ///
+    /// `None` — служебная датаграмма маскировки без данных LLP.
+    fn unwrap(&mut self, datagram: &[u8]) -> Result<Option<Bytes>>;
+
+    /// Handshake LLP завершён: дальше идут данные сессии
+    fn establish(&mut self);
+
+    /// Служебные датаграммы маскировки (например, STUN), накопленные
+    /// `wrap` и `unwrap`: их нужно отправить собеседнику раньше
+    /// следующей обёрнутой датаграммы
+    fn take_control(&mut self) -> Vec<Bytes> {
+        Vec::new()
+    }
+}
+
+/// Режим маскировки UDP датаграмм
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DatagramMode {
+    /// Датаграммы LLP без изменений
+    #[default]
+    None,
+    /// QUIC v1: длинные заголовки на время handshake, затем короткие
+    Quic,
+    /// STUN и SRTP видеозвонка
+    WebRtc,
+}
+
+impl DatagramMode {
+    /// Имя режима в конфигурации
+    pub fn name(&self) -> &'static str {
+        match self {
+            DatagramMode::None => "none",
+            DatagramMode::Quic => "quic",
+            DatagramMode::WebRtc => "webrtc",
+        }
+    }
+
+    /// Режим по имени из конфигурации
+    pub fn from_name(name: &str) -> Option<Self> {
+        [DatagramMode::None, DatagramMode::Quic, DatagramMode::WebRtc]
+            .into_iter()
+            .find(|mode| mode.name() == name)
+    }
+
+    /// Маскировка для роли и адреса собеседника (`None` — датаграммы идут
+    /// без изменений)
+    pub fn create(
+        &self,
+        role: Role,
+        peer: SocketAddr,
+    ) -> Result<Option<Box<dyn DatagramDisguise>>> {
+        if role == Role::Symmetric {
+            return Err(MimicryError::WrapError(
+                "маскировка датаграмм требует роль клиента или сервера".to_string(),
+            ));
+        }
+        Ok(match self {
+            DatagramMode::None => None,
+            DatagramMode::Quic => Some(Box::new(QuicDisguise::new(role))),
+            DatagramMode::WebRtc => Some(Box::new(WebRtcDisguise::new(role, peer))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_names() {
+        for mode in [DatagramMode::None, DatagramMode::Quic, DatagramMode::WebRtc] {
+            assert_eq!(DatagramMode::from_name(mode.name()), Some(mode));
+        }
+        assert_eq!(DatagramMode::from_name("tcp"), None);
+
+        let peer: SocketAddr = "127.0.0.1:443".parse().unwrap();
+        assert!(DatagramMode::None.create(Role::Server, peer).unwrap().is_none());
+        assert!(DatagramMode::Quic.create(Role::Client, peer).unwrap().is_some());
+        assert!(DatagramMode::WebRtc.create(Role::Server, peer).unwrap().is_some());
+        assert!(DatagramMode::Quic.create(Role::Symmetric, peer).is_err());
+    }
+}
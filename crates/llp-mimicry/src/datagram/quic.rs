@@ -0,0 +1,597 @@
+//! Датаграммы в виде пакетов QUIC v1 (RFC 9000)
+//!
+//! Большая часть трафика UDP/443 — QUIC, поэтому датаграмма LLP
+//! оформляется как пакет QUIC:
+//!
+//! - пока идёт handshake LLP — пакеты с длинным заголовком: первые
+//!   датаграммы сторон — Initial, следующие — Handshake; Initial клиента
+//!   дополняется до 1200 байт (RFC 9000, раздел 14.1);
+//! - после [`DatagramDisguise::establish`] — короткий заголовок 1-RTT.
+//!
+//! Идентификаторы соединения — как у Chrome и серверов Google: клиент
+//! выбирает случайный 8-байтный DCID первого Initial и пустой SCID,
+//! сервер отвечает своим 8-байтным CID, которым клиент дальше адресует
+//! пакеты. Номера пакетов растут в каждом пространстве отдельно, их длина
+//! (1–4 байта) зависит от номера.
+//!
+//! ```text
+//! Initial:  [11000PP][version][dcid len][dcid][scid len][scid][token len=0][length][pn][payload]
+//! 1-RTT:    [01000PP][dcid][pn][payload]
+//! payload:  [varint длина][датаграмма LLP][случайное дополнение]
+//! ```
+//!
+//! Заголовки защищены как в RFC 9001 (раздел 5.4): младшие биты первого
+//! байта и номер пакета маскируются AES-128 по образцу из 16 байт за
+//! номером. Ключи выводятся из DCID первого Initial клиента так же, как
+//! ключи Initial (раздел 5.2), поэтому DPI снимает защиту с заголовков
+//! Initial, как у настоящего QUIC. Короткие пакеты дополняются до длины
+//! образца.
+//!
+//! Имитация неполная: payload не зашифрован ключами Initial, и DPI,
+//! расшифровывающий Initial ради SNI, получит ошибку аутентификации вместо
+//! кадра CRYPTO с ClientHello. Пакеты Handshake и 1-RTT защищены теми же
+//! ключами Initial, хотя настоящий QUIC выводит для них свои.
+
+use bytes::{BufMut, Bytes, BytesMut};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use ring::aead::quic::{HeaderProtectionKey, AES_128};
+use sha2::Sha256;
+
+use super::DatagramDisguise;
+use crate::error::{MimicryError, Result};
+use crate::exchange::Role;
+use crate::profiles::BoxedRng;
+
+/// Версия QUIC v1
+pub const QUIC_VERSION_1: u32 = 0x0000_0001;
+
+/// Минимальный размер датаграммы с Initial клиента
+pub const MIN_INITIAL_DATAGRAM: usize = 1200;
+
+/// Длина CID сервера
+pub const SERVER_CID_LEN: usize = 8;
+
+/// Максимальная длина CID (RFC 9000, раздел 17.2)
+const MAX_CID_LEN: usize = 20;
+
+/// Типы пакетов с длинным заголовком
+const LONG_INITIAL: u8 = 0x0;
+const LONG_HANDSHAKE: u8 = 0x2;
+
+/// Соль ключей Initial QUIC v1 (RFC 9001, раздел 5.2)
+const INITIAL_SALT: [u8; 20] = [
+    0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17, 0x9a, 0xe6, 0xa4, 0xc8, 0x0c, 0xad,
+    0xcc, 0xbb, 0x7f, 0x0a,
+];
+
+/// Длина образца для маски заголовка
+const SAMPLE_LEN: usize = 16;
+
+/// Защищаемые биты первого байта длинного и короткого заголовков
+const LONG_HEADER_MASK: u8 = 0x0F;
+const SHORT_HEADER_MASK: u8 = 0x1F;
+
+/// Маскировка датаграмм под QUIC v1
+pub struct QuicDisguise {
+    role: Role,
+    rng: BoxedRng,
+    /// Собственный CID: DCID входящих пакетов
+    local_cid: Vec<u8>,
+    /// CID собеседника: DCID исходящих пакетов
+    remote_cid: Vec<u8>,
+    /// DCID первого Initial клиента (сервер принимает его до смены CID)
+    original_cid: Option<Vec<u8>>,
+    /// Ключи защиты заголовков (у сервера — после первого Initial)
+    keys: Option<HeaderKeys>,
+    /// Принят ли хотя бы один пакет собеседника
+    peer_seen: bool,
+    established: bool,
+    initial_pn: u64,
+    handshake_pn: u64,
+    app_pn: u64,
+}
+
+impl QuicDisguise {
+    /// Маскировка для роли со случайными CID
+    pub fn new(role: Role) -> Self {
+        Self::with_rng(role, Box::new(OsRng))
+    }
+
+    /// Маскировка с заданным источником случайности
+    pub fn with_rng(role: Role, mut rng: BoxedRng) -> Self {
+        let mut random_cid = || {
+            let mut cid = vec![0u8; SERVER_CID_LEN];
+            rng.fill_bytes(&mut cid);
+            cid
+        };
+        let (local_cid, remote_cid) = match role {
+            Role::Client => (Vec::new(), random_cid()),
+            _ => (random_cid(), Vec::new()),
+        };
+        let keys = match role {
+            Role::Client => Some(HeaderKeys::initial(role, &remote_cid)),
+            _ => None,
+        };
+        Self {
+            role,
+            rng,
+            local_cid,
+            remote_cid,
+            original_cid: None,
+            keys,
+            peer_seen: false,
+            established: false,
+            initial_pn: 0,
+            handshake_pn: 0,
+            app_pn: 0,
+        }
+    }
+
+    /// Собственный CID
+    pub fn local_cid(&self) -> &[u8] {
+        &self.local_cid
+    }
+
+    /// CID, которым адресуются исходящие пакеты
+    pub fn remote_cid(&self) -> &[u8] {
+        &self.remote_cid
+    }
+
+    /// Переведена ли маскировка на короткие заголовки
+    pub fn is_established(&self) -> bool {
+        self.established
+    }
+
+    fn wrap_long(&mut self, payload: &[u8]) -> Result<Bytes> {
+        let initial = match self.role {
+            Role::Client => !self.peer_seen,
+            _ => self.initial_pn == 0,
+        };
+        let (kind, pn) = if initial {
+            self.initial_pn += 1;
+            (LONG_INITIAL, self.initial_pn - 1)
+        } else {
+            self.handshake_pn += 1;
+            (LONG_HANDSHAKE, self.handshake_pn - 1)
+        };
+        let pn_len = packet_number_len(pn);
+
+        let mut out = BytesMut::with_capacity(MIN_INITIAL_DATAGRAM);
+        out.put_u8(0xC0 | kind << 4 | (pn_len as u8 - 1));
+        out.put_u32(QUIC_VERSION_1);
+        out.put_u8(self.remote_cid.len() as u8);
+        out.put_slice(&self.remote_cid);
+        out.put_u8(self.local_cid.len() as u8);
+        out.put_slice(&self.local_cid);
+        if kind == LONG_INITIAL {
+            put_varint(&mut out, 0);
+        }
+
+        let inner = varint_len(payload.len() as u64) + payload.len();
+        // Поле Length всегда двухбайтовое, как у Chrome
+        let header = out.len() + 2 + pn_len;
+        let padding = match (self.role, kind) {
+            (Role::Client, LONG_INITIAL) => MIN_INITIAL_DATAGRAM.saturating_sub(header + inner),
+            _ => sample_padding(pn_len + inner),
+        };
+        out.put_u16(0x4000 | (pn_len + inner + padding) as u16);
+        let pn_offset = out.len();
+        put_packet_number(&mut out, pn, pn_len);
+        self.put_payload(&mut out, payload, padding);
+        protect(&self.keys()?.local, &mut out, pn_offset, LONG_HEADER_MASK)?;
+        Ok(out.freeze())
+    }
+
+    fn wrap_short(&mut self, payload: &[u8]) -> Result<Bytes> {
+        let pn = self.app_pn;
+        self.app_pn += 1;
+        let pn_len = packet_number_len(pn);
+        let inner = varint_len(payload.len() as u64) + payload.len();
+
+        let mut out = BytesMut::with_capacity(1 + self.remote_cid.len() + pn_len + inner);
+        out.put_u8(0x40 | (pn_len as u8 - 1));
+        out.put_slice(&self.remote_cid);
+        let pn_offset = out.len();
+        put_packet_number(&mut out, pn, pn_len);
+        self.put_payload(&mut out, payload, sample_padding(pn_len + inner));
+        protect(&self.keys()?.local, &mut out, pn_offset, SHORT_HEADER_MASK)?;
+        Ok(out.freeze())
+    }
+
+    fn keys(&self) -> Result<&HeaderKeys> {
+        self.keys
+            .as_ref()
+            .ok_or_else(|| quic_error("нет ключей защиты заголовков до Initial клиента"))
+    }
+
+    fn put_payload(&mut self, out: &mut BytesMut, payload: &[u8], padding: usize) {
+        put_varint(out, payload.len() as u64);
+        out.put_slice(payload);
+        let start = out.len();
+        out.resize(start + padding, 0);
+        self.rng.fill_bytes(&mut out[start..]);
+    }
+
+    fn unwrap_long(&mut self, datagram: &[u8]) -> Result<Bytes> {
+        let mut reader = Reader(datagram);
+        let first = reader.u8()?;
+        if first & 0x40 == 0 {
+            return Err(quic_error("сброшен фиксированный бит"));
+        }
+        if reader.u32()? != QUIC_VERSION_1 {
+            return Err(quic_error("неподдерживаемая версия"));
+        }
+        let dcid = reader.cid()?;
+        let scid = reader.cid()?;
+        let kind = (first >> 4) & 0x3;
+        match kind {
+            LONG_INITIAL => {
+                let token = reader.varint()? as usize;
+                reader.take(token)?;
+            }
+            LONG_HANDSHAKE => {}
+            _ => return Err(quic_error("неожиданный тип длинного заголовка")),
+        }
+
+        if dcid != self.local_cid.as_slice() {
+            match (&self.original_cid, self.role) {
+                (Some(original), _) if dcid == original.as_slice() => {}
+                (None, Role::Server) if kind == LONG_INITIAL => {
+                    if dcid.len() < SERVER_CID_LEN {
+                        return Err(quic_error("DCID Initial короче 8 байт"));
+                    }
+                    self.original_cid = Some(dcid.to_vec());
+                    self.keys = Some(HeaderKeys::initial(self.role, dcid));
+                }
+                _ => return Err(quic_error("чужой DCID")),
+            }
+        }
+
+        let length = reader.varint()? as usize;
+        let pn_offset = datagram.len() - reader.0.len();
+        reader.take(length)?;
+        let mut packet = datagram[..pn_offset + length].to_vec();
+        let keys = self.keys()?;
+        let pn_len = unprotect(&keys.remote, &mut packet, pn_offset, LONG_HEADER_MASK)?;
+        let payload = Reader(&packet[pn_offset..]).skip_then_payload(pn_len)?;
+
+        if !self.peer_seen {
+            // Клиент дальше адресует пакеты CID сервера
+            self.remote_cid = scid.to_vec();
+            self.peer_seen = true;
+        }
+        Ok(payload)
+    }
+
+    fn unwrap_short(&mut self, datagram: &[u8]) -> Result<Bytes> {
+        let mut reader = Reader(datagram);
+        let first = reader.u8()?;
+        if first & 0x40 == 0 {
+            return Err(quic_error("сброшен фиксированный бит"));
+        }
+        if reader.take(self.local_cid.len())? != self.local_cid.as_slice() {
+            return Err(quic_error("чужой DCID"));
+        }
+        let pn_offset = 1 + self.local_cid.len();
+        let mut packet = datagram.to_vec();
+        let keys = self.keys()?;
+        let pn_len = unprotect(&keys.remote, &mut packet, pn_offset, SHORT_HEADER_MASK)?;
+        Reader(&packet[pn_offset..]).skip_then_payload(pn_len)
+    }
+}
+
+impl DatagramDisguise for QuicDisguise {
+    fn wrap(&mut self, payload: &[u8]) -> Result<Bytes> {
+        if payload.len() > 0x3FFF - 16 {
+            return Err(MimicryError::WrapError(format!(
+                "датаграмма {} байт не помещается в пакет QUIC",
+                payload.len()
+            )));
+        }
+        if self.established {
+            self.wrap_short(payload)
+        } else {
+            self.wrap_long(payload)
+        }
+    }
+
+    fn unwrap(&mut self, datagram: &[u8]) -> Result<Option<Bytes>> {
+        match datagram.first() {
+            Some(first) if first & 0x80 != 0 => self.unwrap_long(datagram).map(Some),
+            Some(_) => self.unwrap_short(datagram).map(Some),
+            None => Err(quic_error("пустая датаграмма")),
+        }
+    }
+
+    fn establish(&mut self) {
+        self.established = true;
+    }
+}
+
+/// Ключи защиты заголовков исходящих и входящих пакетов
+struct HeaderKeys {
+    local: HeaderProtectionKey,
+    remote: HeaderProtectionKey,
+}
+
+impl HeaderKeys {
+    /// Ключи Initial из DCID первого Initial клиента (RFC 9001, раздел 5.2)
+    fn initial(role: Role, dcid: &[u8]) -> Self {
+        let (initial_secret, _) = Hkdf::<Sha256>::extract(Some(&INITIAL_SALT), dcid);
+        let hp = |label: &[u8]| {
+            let secret = expand_label(&initial_secret, label, 32);
+            let key = expand_label(&secret, b"quic hp", 16);
+            HeaderProtectionKey::new(&AES_128, &key).expect("ключ AES-128 из 16 байт")
+        };
+        let (local, remote) = match role {
+            Role::Client => (hp(b"client in"), hp(b"server in")),
+            _ => (hp(b"server in"), hp(b"client in")),
+        };
+        Self { local, remote }
+    }
+}
+
+/// HKDF-Expand-Label TLS 1.3 с пустым контекстом (RFC 8446, раздел 7.1)
+fn expand_label(secret: &[u8], label: &[u8], len: usize) -> Vec<u8> {
+    let mut info = Vec::with_capacity(4 + 6 + label.len());
+    info.extend_from_slice(&(len as u16).to_be_bytes());
+    info.push((6 + label.len()) as u8);
+    info.extend_from_slice(b"tls13 ");
+    info.extend_from_slice(label);
+    info.push(0);
+
+    let hkdf = Hkdf::<Sha256>::from_prk(secret).expect("секрет SHA-256 из 32 байт");
+    let mut out = vec![0u8; len];
+    hkdf.expand(&info, &mut out)
+        .expect("длина не больше 255 блоков");
+    out
+}
+
+/// Маска заголовка по образцу, начинающемуся через 4 байта после номера пакета
+fn header_mask(key: &HeaderProtectionKey, packet: &[u8], pn_offset: usize) -> Result<[u8; 5]> {
+    let sample = packet
+        .get(pn_offset + 4..pn_offset + 4 + SAMPLE_LEN)
+        .ok_or_else(|| quic_error("пакет короче образца защиты заголовка"))?;
+    key.new_mask(sample)
+        .map_err(|_| quic_error("маска защиты заголовка не вычислена"))
+}
+
+/// Наложить защиту заголовка на собранный пакет
+fn protect(key: &HeaderProtectionKey, packet: &mut [u8], pn_offset: usize, bits: u8) -> Result<()> {
+    let mask = header_mask(key, packet, pn_offset)?;
+    let pn_len = ((packet[0] & 0x3) + 1) as usize;
+    packet[0] ^= mask[0] & bits;
+    xor_packet_number(&mut packet[pn_offset..pn_offset + pn_len], &mask);
+    Ok(())
+}
+
+/// Снять защиту заголовка, вернув длину номера пакета
+fn unprotect(
+    key: &HeaderProtectionKey,
+    packet: &mut [u8],
+    pn_offset: usize,
+    bits: u8,
+) -> Result<usize> {
+    let mask = header_mask(key, packet, pn_offset)?;
+    packet[0] ^= mask[0] & bits;
+    let pn_len = ((packet[0] & 0x3) + 1) as usize;
+    xor_packet_number(&mut packet[pn_offset..pn_offset + pn_len], &mask);
+    Ok(pn_len)
+}
+
+fn xor_packet_number(pn: &mut [u8], mask: &[u8; 5]) {
+    for (byte, mask) in pn.iter_mut().zip(&mask[1..]) {
+        *byte ^= mask;
+    }
+}
+
+/// Дополнение, без которого за номером пакета не хватит байт на образец
+fn sample_padding(protected: usize) -> usize {
+    (4 + SAMPLE_LEN).saturating_sub(protected)
+}
+
+/// Длина номера пакета в заголовке
+fn packet_number_len(pn: u64) -> usize {
+    match pn {
+        0..=0x7F => 1,
+        0x80..=0x7FFF => 2,
+        0x8000..=0x7F_FFFF => 3,
+        _ => 4,
+    }
+}
+
+/// Младшие `len` байт номера пакета
+fn put_packet_number(out: &mut BytesMut, pn: u64, len: usize) {
+    out.put_slice(&pn.to_be_bytes()[8 - len..]);
+}
+
+/// Длина varint QUIC (RFC 9000, раздел 16)
+fn varint_len(value: u64) -> usize {
+    match value {
+        0..=0x3F => 1,
+        0x40..=0x3FFF => 2,
+        0x4000..=0x3FFF_FFFF => 4,
+        _ => 8,
+    }
+}
+
+fn put_varint(out: &mut BytesMut, value: u64) {
+    match varint_len(value) {
+        1 => out.put_u8(value as u8),
+        2 => out.put_u16(0x4000 | value as u16),
+        4 => out.put_u32(0x8000_0000 | value as u32),
+        _ => out.put_u64(0xC000_0000_0000_0000 | value),
+    }
+}
+
+fn quic_error(message: &str) -> MimicryError {
+    MimicryError::ParseError(format!("QUIC: {}", message))
+}
+
+/// Чтение полей пакета с проверкой границ
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(quic_error("обрыв пакета"));
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn cid(&mut self) -> Result<&'a [u8]> {
+        let len = self.u8()? as usize;
+        if len > MAX_CID_LEN {
+            return Err(quic_error("CID длиннее 20 байт"));
+        }
+        self.take(len)
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let first = self.u8()?;
+        let len = 1 << (first >> 6);
+        let mut value = (first & 0x3F) as u64;
+        for byte in self.take(len - 1)? {
+            value = value << 8 | *byte as u64;
+        }
+        Ok(value)
+    }
+
+    /// Датаграмма LLP из payload пакета после номера длиной `pn_len`
+    /// (дополнение отбрасывается)
+    fn skip_then_payload(&mut self, pn_len: usize) -> Result<Bytes> {
+        self.take(pn_len)?;
+        let len = self.varint()? as usize;
+        Ok(Bytes::copy_from_slice(self.take(len)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn pair() -> (QuicDisguise, QuicDisguise) {
+        (
+            QuicDisguise::with_rng(Role::Client, Box::new(StdRng::seed_from_u64(1))),
+            QuicDisguise::with_rng(Role::Server, Box::new(StdRng::seed_from_u64(2))),
+        )
+    }
+
+    #[test]
+    fn test_handshake_then_short_headers() {
+        let (mut client, mut server) = pair();
+        let original = client.remote_cid().to_vec();
+
+        // CLIENT_HELLO: Initial, дополненный до 1200 байт
+        let hello = client.wrap(b"client hello").unwrap();
+        assert_eq!(hello.len(), MIN_INITIAL_DATAGRAM);
+        assert_eq!(hello[0] & 0xF0, 0xC0);
+        assert_eq!(&hello[1..5], &QUIC_VERSION_1.to_be_bytes());
+        assert_eq!(hello[5] as usize, SERVER_CID_LEN);
+        assert_eq!(&hello[6..14], original.as_slice());
+        assert_eq!(hello[14], 0, "пустой SCID клиента");
+        assert_eq!(&server.unwrap(&hello).unwrap().unwrap()[..], b"client hello");
+
+        // SERVER_HELLO: Initial сервера с его CID в SCID
+        let reply = server.wrap(b"server hello").unwrap();
+        assert_eq!(reply[0] & 0xF0, 0xC0);
+        assert_eq!(reply[5], 0);
+        assert_eq!(reply[6] as usize, SERVER_CID_LEN);
+        assert_eq!(&reply[7..15], server.local_cid());
+        assert_eq!(&client.unwrap(&reply).unwrap().unwrap()[..], b"server hello");
+        assert_eq!(client.remote_cid(), server.local_cid());
+
+        // CLIENT_VERIFY и SERVER_VERIFY — пакеты Handshake
+        let verify = client.wrap(b"client verify").unwrap();
+        assert_eq!(verify[0] & 0xF0, 0xE0);
+        assert_eq!(&verify[6..14], server.local_cid());
+        assert_eq!(&server.unwrap(&verify).unwrap().unwrap()[..], b"client verify");
+        let verify = server.wrap(b"server verify").unwrap();
+        assert_eq!(verify[0] & 0xF0, 0xE0);
+        assert_eq!(&client.unwrap(&verify).unwrap().unwrap()[..], b"server verify");
+
+        client.establish();
+        server.establish();
+
+        let data = client.wrap(&[0xAB; 100]).unwrap();
+        assert_eq!(data[0] & 0xC0, 0x40);
+        assert_eq!(&data[1..9], server.local_cid());
+        assert_eq!(&server.unwrap(&data).unwrap().unwrap()[..], &[0xAB; 100][..]);
+        let data = server.wrap(b"down").unwrap();
+        assert_eq!(data[0] & 0xC0, 0x40);
+        assert_eq!(&client.unwrap(&data).unwrap().unwrap()[..], b"down");
+    }
+
+    #[test]
+    fn test_packet_number_length_grows() {
+        let (mut client, _) = pair();
+        client.establish();
+        let keys = HeaderKeys::initial(Role::Client, client.remote_cid());
+        let local = &keys.local;
+
+        let mut first = client.wrap(b"x").unwrap().to_vec();
+        assert_eq!(first.len(), 1 + 8 + 4 + SAMPLE_LEN, "дополнение до образца");
+        assert_eq!(unprotect(local, &mut first, 9, SHORT_HEADER_MASK).unwrap(), 1);
+        for _ in 0..200 {
+            client.wrap(b"x").unwrap();
+        }
+        let mut later = client.wrap(b"x").unwrap().to_vec();
+        assert_eq!(unprotect(local, &mut later, 9, SHORT_HEADER_MASK).unwrap(), 2);
+        assert_eq!(&later[9..11], &201u16.to_be_bytes());
+    }
+
+    #[test]
+    fn test_header_protection_rfc9001_keys() {
+        // RFC 9001, приложение A: маски по образцам Initial клиента и сервера
+        let dcid = hex::decode("8394c8f03e515708").unwrap();
+        let keys = HeaderKeys::initial(Role::Client, &dcid);
+        let sample = hex::decode("d1b1c98dd7689fb8ec11d242b123dc9b").unwrap();
+        assert_eq!(hex::encode(keys.local.new_mask(&sample).unwrap()), "437b9aec36");
+        let sample = hex::decode("2cd0991cd25b0aac406a5816b6394100").unwrap();
+        assert_eq!(hex::encode(keys.remote.new_mask(&sample).unwrap()), "2ec0d8356a");
+    }
+
+    #[test]
+    fn test_packet_numbers_masked() {
+        let (mut client, _) = pair();
+        client.establish();
+        let packets: Vec<_> = (0..8).map(|_| client.wrap(b"same").unwrap()).collect();
+        let numbers: std::collections::HashSet<_> = packets.iter().map(|p| p[9]).collect();
+        assert!(numbers.len() > 1, "номера пакетов в открытом виде не видны");
+        assert!(packets.iter().any(|p| p[0] & 0x1F != 0), "биты первого байта замаскированы");
+    }
+
+    #[test]
+    fn test_foreign_packets_rejected() {
+        let (mut client, mut server) = pair();
+        assert!(server.unwrap(b"").is_err());
+        assert!(server.unwrap(b"GET / HTTP/1.1\r\n\r\n").is_err());
+
+        let mut hello = client.wrap(b"hello").unwrap().to_vec();
+        hello[4] = 2;
+        assert!(server.unwrap(&hello).is_err(), "другая версия");
+        hello[4] = 1;
+        hello.truncate(40);
+        assert!(server.unwrap(&hello).is_err(), "обрезанный пакет");
+
+        // Короткий заголовок с чужим DCID
+        let mut other = QuicDisguise::new(Role::Client);
+        other.establish();
+        assert!(server.unwrap(&other.wrap(b"data").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,542 @@
+//! Датаграммы в виде медиапотока WebRTC: STUN и SRTP
+//!
+//! Видеозвонки (VK Звонки и подобные сервисы) — долгие UDP потоки из
+//! проверок связности ICE (STUN) и пакетов SRTP. Сервер ведёт себя как
+//! ICE-lite медиасервер: сам проверок не отправляет, а отвечает на
+//! binding request клиента. Клиент начинает сессию с binding request
+//! (ICE-CONTROLLING, USE-CANDIDATE) и повторяет его каждые
+//! [`CONSENT_INTERVAL`], как проверку согласия (RFC 7675).
+//!
+//! Датаграммы LLP идут в пакетах RTP (RFC 3550) с хвостом
+//! аутентификации SRTP (HMAC-SHA1-80, RFC 3711). Короткие датаграммы —
+//! аудиопоток Opus (payload type 111, часы 48 кГц, шаг 20 мс), остальные —
+//! видеопоток VP8 (payload type 96, часы 90 кГц). У каждого потока
+//! постоянный SSRC, номера последовательности растут на единицу,
+//! метки времени — по реальному времени с начала сессии.
+//!
+//! ```text
+//! STUN:  [type:2][length:2][0x2112A442][transaction id:12][атрибуты]
+//! SRTP:  [10M/PT][seq:2][timestamp:4][ssrc:4][датаграмма LLP][auth tag:10]
+//! ```
+//!
+//! Payload SRTP зашифрован — как и шифротекст LLP. MESSAGE-INTEGRITY
+//! STUN и тег SRTP без ключей ICE/DTLS не проверить, поэтому они
+//! случайны; FINGERPRINT STUN (CRC-32) вычисляется честно.
+
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use super::DatagramDisguise;
+use crate::error::{MimicryError, Result};
+use crate::exchange::Role;
+use crate::profiles::BoxedRng;
+
+/// Magic cookie STUN (RFC 5389)
+pub const STUN_MAGIC_COOKIE: u32 = 0x2112_A442;
+
+/// Интервал проверок согласия клиента
+pub const CONSENT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Датаграммы до этой длины идут в аудиопотоке
+pub const AUDIO_MAX_PAYLOAD: usize = 200;
+
+/// Payload type Opus
+pub const AUDIO_PAYLOAD_TYPE: u8 = 111;
+
+/// Payload type VP8
+pub const VIDEO_PAYLOAD_TYPE: u8 = 96;
+
+/// Длина тега аутентификации SRTP (HMAC-SHA1-80)
+pub const SRTP_AUTH_TAG_LEN: usize = 10;
+
+/// Размер пакета видео, с которого кадр считается незаконченным
+const VIDEO_FULL_PACKET: usize = 1000;
+
+/// Типы сообщений STUN
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+
+/// Атрибуты STUN
+mod attr {
+    pub const USERNAME: u16 = 0x0006;
+    pub const MESSAGE_INTEGRITY: u16 = 0x0008;
+    pub const XOR_MAPPED_ADDRESS: u16 = 0x0020;
+    pub const PRIORITY: u16 = 0x0024;
+    pub const USE_CANDIDATE: u16 = 0x0025;
+    pub const FINGERPRINT: u16 = 0x8028;
+    pub const ICE_CONTROLLING: u16 = 0x802A;
+    pub const GOOG_NETWORK_INFO: u16 = 0xC057;
+}
+
+/// XOR для FINGERPRINT (RFC 5389, раздел 15.5)
+const FINGERPRINT_XOR: u32 = 0x5354_554E;
+
+/// Приоритет host кандидата UDP, как у Chrome
+const HOST_PRIORITY: u32 = 0x6E7F_1EFF;
+
+/// Один поток RTP
+struct RtpStream {
+    ssrc: u32,
+    payload_type: u8,
+    sequence: u16,
+    timestamp_base: u32,
+    clock_rate: u32,
+    /// Шаг метки времени (длительность кадра), 0 — без квантования
+    frame_ticks: u32,
+}
+
+impl RtpStream {
+    fn new(rng: &mut BoxedRng, payload_type: u8, clock_rate: u32, frame_ticks: u32) -> Self {
+        Self {
+            ssrc: rng.next_u32(),
+            payload_type,
+            sequence: rng.next_u32() as u16 & 0x7FFF,
+            timestamp_base: rng.next_u32(),
+            clock_rate,
+            frame_ticks,
+        }
+    }
+
+    fn timestamp(&self, elapsed: Duration) -> u32 {
+        let mut ticks = (elapsed.as_micros() * self.clock_rate as u128 / 1_000_000) as u32;
+        if self.frame_ticks > 0 {
+            ticks -= ticks % self.frame_ticks;
+        }
+        self.timestamp_base.wrapping_add(ticks)
+    }
+}
+
+/// Маскировка датаграмм под STUN и SRTP видеозвонка
+pub struct WebRtcDisguise {
+    role: Role,
+    rng: BoxedRng,
+    /// Адрес собеседника (для XOR-MAPPED-ADDRESS ответа сервера)
+    peer: SocketAddr,
+    /// USERNAME проверок клиента: `ufrag сервера:ufrag клиента`
+    username: String,
+    tie_breaker: u64,
+    /// Transaction ID проверок без ответа
+    pending: Vec<[u8; 12]>,
+    /// Служебные датаграммы к отправке
+    control: Vec<Bytes>,
+    last_check: Option<Instant>,
+    /// Подтверждена ли связность ответом сервера
+    connected: bool,
+    started: Instant,
+    audio: RtpStream,
+    video: RtpStream,
+}
+
+impl WebRtcDisguise {
+    /// Маскировка для роли и адреса собеседника
+    pub fn new(role: Role, peer: SocketAddr) -> Self {
+        Self::with_rng(role, peer, Box::new(OsRng))
+    }
+
+    /// Маскировка с заданным источником случайности
+    pub fn with_rng(role: Role, peer: SocketAddr, mut rng: BoxedRng) -> Self {
+        let username = format!("{}:{}", ice_ufrag(&mut rng), ice_ufrag(&mut rng));
+        let tie_breaker = rng.next_u64();
+        let audio = RtpStream::new(&mut rng, AUDIO_PAYLOAD_TYPE, 48_000, 960);
+        let video = RtpStream::new(&mut rng, VIDEO_PAYLOAD_TYPE, 90_000, 0);
+        Self {
+            role,
+            rng,
+            peer,
+            username,
+            tie_breaker,
+            pending: Vec::new(),
+            control: Vec::new(),
+            last_check: None,
+            connected: false,
+            started: Instant::now(),
+            audio,
+            video,
+        }
+    }
+
+    /// SSRC аудио- и видеопотока
+    pub fn ssrcs(&self) -> (u32, u32) {
+        (self.audio.ssrc, self.video.ssrc)
+    }
+
+    /// Получен ли ответ на проверку связности
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Binding request клиента
+    fn binding_request(&mut self) -> Bytes {
+        let mut transaction = [0u8; 12];
+        self.rng.fill_bytes(&mut transaction);
+        if self.pending.len() >= 8 {
+            self.pending.remove(0);
+        }
+        self.pending.push(transaction);
+
+        let mut attrs = BytesMut::with_capacity(96);
+        put_attr(&mut attrs, attr::USERNAME, self.username.as_bytes());
+        put_attr(
+            &mut attrs,
+            attr::GOOG_NETWORK_INFO,
+            &[0x00, 0x01, 0x00, 0x0A],
+        );
+        put_attr(
+            &mut attrs,
+            attr::ICE_CONTROLLING,
+            &self.tie_breaker.to_be_bytes(),
+        );
+        if !self.connected {
+            put_attr(&mut attrs, attr::USE_CANDIDATE, &[]);
+        }
+        put_attr(&mut attrs, attr::PRIORITY, &HOST_PRIORITY.to_be_bytes());
+        self.stun_message(BINDING_REQUEST, &transaction, attrs)
+    }
+
+    /// Binding success response сервера
+    fn binding_success(&mut self, transaction: &[u8; 12]) -> Bytes {
+        let mut mapped = BytesMut::with_capacity(20);
+        mapped.put_u8(0);
+        let port = self.peer.port() ^ (STUN_MAGIC_COOKIE >> 16) as u16;
+        let mut mask = STUN_MAGIC_COOKIE.to_be_bytes().to_vec();
+        mask.extend_from_slice(transaction);
+        match self.peer {
+            SocketAddr::V4(addr) => {
+                mapped.put_u8(0x01);
+                mapped.put_u16(port);
+                for (byte, key) in addr.ip().octets().iter().zip(&mask) {
+                    mapped.put_u8(byte ^ key);
+                }
+            }
+            SocketAddr::V6(addr) => {
+                mapped.put_u8(0x02);
+                mapped.put_u16(port);
+                for (byte, key) in addr.ip().octets().iter().zip(&mask) {
+                    mapped.put_u8(byte ^ key);
+                }
+            }
+        }
+
+        let mut attrs = BytesMut::with_capacity(64);
+        put_attr(&mut attrs, attr::XOR_MAPPED_ADDRESS, &mapped);
+        self.stun_message(BINDING_SUCCESS, transaction, attrs)
+    }
+
+    /// Сообщение STUN с MESSAGE-INTEGRITY и FINGERPRINT
+    fn stun_message(&mut self, kind: u16, transaction: &[u8; 12], mut attrs: BytesMut) -> Bytes {
+        let mut integrity = [0u8; 20];
+        self.rng.fill_bytes(&mut integrity);
+        put_attr(&mut attrs, attr::MESSAGE_INTEGRITY, &integrity);
+
+        let mut out = BytesMut::with_capacity(20 + attrs.len() + 8);
+        out.put_u16(kind);
+        // Длина учитывает FINGERPRINT, который добавляется последним
+        out.put_u16((attrs.len() + 8) as u16);
+        out.put_u32(STUN_MAGIC_COOKIE);
+        out.put_slice(transaction);
+        out.put_slice(&attrs);
+        let fingerprint = crc32(&out) ^ FINGERPRINT_XOR;
+        put_attr(&mut out, attr::FINGERPRINT, &fingerprint.to_be_bytes());
+        out.freeze()
+    }
+
+    fn unwrap_stun(&mut self, datagram: &[u8]) -> Result<()> {
+        if datagram.len() < 28 || !datagram.len().is_multiple_of(4) {
+            return Err(webrtc_error("некорректная длина STUN"));
+        }
+        let kind = u16::from_be_bytes([datagram[0], datagram[1]]);
+        let length = u16::from_be_bytes([datagram[2], datagram[3]]) as usize;
+        if length + 20 != datagram.len() {
+            return Err(webrtc_error("длина STUN не совпадает с датаграммой"));
+        }
+
+        let (body, tail) = datagram.split_at(datagram.len() - 8);
+        if u16::from_be_bytes([tail[0], tail[1]]) != attr::FINGERPRINT
+            || u32::from_be_bytes([tail[4], tail[5], tail[6], tail[7]])
+                != crc32(body) ^ FINGERPRINT_XOR
+        {
+            return Err(webrtc_error("неверный FINGERPRINT"));
+        }
+
+        let mut transaction = [0u8; 12];
+        transaction.copy_from_slice(&datagram[8..20]);
+        match (kind, self.role) {
+            (BINDING_REQUEST, Role::Server) => {
+                let response = self.binding_success(&transaction);
+                self.control.push(response);
+                self.connected = true;
+            }
+            (BINDING_SUCCESS, Role::Client) => {
+                let Some(index) = self.pending.iter().position(|t| *t == transaction) else {
+                    return Err(webrtc_error("ответ на неизвестную проверку"));
+                };
+                self.pending.remove(index);
+                self.connected = true;
+            }
+            _ => return Err(webrtc_error("неожиданное сообщение STUN")),
+        }
+        Ok(())
+    }
+
+    fn unwrap_rtp(&mut self, datagram: &[u8]) -> Result<Bytes> {
+        if datagram.len() < 12 + SRTP_AUTH_TAG_LEN {
+            return Err(webrtc_error("обрыв пакета RTP"));
+        }
+        let first = datagram[0];
+        if first >> 6 != 2 {
+            return Err(webrtc_error("версия RTP не 2"));
+        }
+        let payload_type = datagram[1] & 0x7F;
+        if payload_type != AUDIO_PAYLOAD_TYPE && payload_type != VIDEO_PAYLOAD_TYPE {
+            return Err(webrtc_error("неизвестный payload type"));
+        }
+
+        let mut start = 12 + 4 * (first & 0x0F) as usize;
+        let mut end = datagram.len() - SRTP_AUTH_TAG_LEN;
+        if first & 0x10 != 0 {
+            // Заголовок расширения: [profile:2][длина в словах:2]
+            let words = datagram
+                .get(start + 2..start + 4)
+                .ok_or_else(|| webrtc_error("обрыв расширения RTP"))?;
+            start += 4 + 4 * u16::from_be_bytes([words[0], words[1]]) as usize;
+        }
+        if first & 0x20 != 0 && end > start {
+            end = end.saturating_sub(datagram[end - 1] as usize);
+        }
+        if start > end {
+            return Err(webrtc_error("обрыв пакета RTP"));
+        }
+        Ok(Bytes::copy_from_slice(&datagram[start..end]))
+    }
+}
+
+impl DatagramDisguise for WebRtcDisguise {
+    fn wrap(&mut self, payload: &[u8]) -> Result<Bytes> {
+        if payload.len() > u16::MAX as usize - 12 - SRTP_AUTH_TAG_LEN {
+            return Err(MimicryError::WrapError(format!(
+                "датаграмма {} байт не помещается в пакет RTP",
+                payload.len()
+            )));
+        }
+
+        // Проверка связности предшествует медиа и повторяется как consent
+        if self.role == Role::Client
+            && self
+                .last_check
+                .is_none_or(|last| last.elapsed() >= CONSENT_INTERVAL)
+        {
+            let request = self.binding_request();
+            self.control.push(request);
+            self.last_check = Some(Instant::now());
+        }
+
+        let elapsed = self.started.elapsed();
+        let audio = payload.len() <= AUDIO_MAX_PAYLOAD;
+        let stream = if audio {
+            &mut self.audio
+        } else {
+            &mut self.video
+        };
+        // Маркер видео — последний (неполный) пакет кадра, аудио — без маркера
+        let marker = !audio && payload.len() < VIDEO_FULL_PACKET;
+
+        let mut out = BytesMut::with_capacity(12 + payload.len() + SRTP_AUTH_TAG_LEN);
+        out.put_u8(0x80);
+        out.put_u8((marker as u8) << 7 | stream.payload_type);
+        out.put_u16(stream.sequence);
+        out.put_u32(stream.timestamp(elapsed));
+        out.put_u32(stream.ssrc);
+        out.put_slice(payload);
+        stream.sequence = stream.sequence.wrapping_add(1);
+
+        let start = out.len();
+        out.resize(start + SRTP_AUTH_TAG_LEN, 0);
+        self.rng.fill_bytes(&mut out[start..]);
+        Ok(out.freeze())
+    }
+
+    fn unwrap(&mut self, datagram: &[u8]) -> Result<Option<Bytes>> {
+        // Демультиплексирование по первому байту (RFC 7983)
+        match datagram.first() {
+            Some(0..=3) => self.unwrap_stun(datagram).map(|()| None),
+            Some(128..=191) => self.unwrap_rtp(datagram).map(Some),
+            Some(_) => Err(webrtc_error("не STUN и не RTP")),
+            None => Err(webrtc_error("пустая датаграмма")),
+        }
+    }
+
+    fn establish(&mut self) {}
+
+    fn take_control(&mut self) -> Vec<Bytes> {
+        std::mem::take(&mut self.control)
+    }
+}
+
+/// ICE ufrag из 4 символов, как у браузеров
+fn ice_ufrag(rng: &mut BoxedRng) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    (0..4)
+        .map(|_| ALPHABET[rng.next_u32() as usize % ALPHABET.len()] as char)
+        .collect()
+}
+
+/// Атрибут STUN с выравниванием значения до 4 байт
+fn put_attr(out: &mut BytesMut, kind: u16, value: &[u8]) {
+    out.put_u16(kind);
+    out.put_u16(value.len() as u16);
+    out.put_slice(value);
+    let padding = (4 - value.len() % 4) % 4;
+    out.put_bytes(0, padding);
+}
+
+/// CRC-32 (IEEE 802.3), как в FINGERPRINT STUN
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn webrtc_error(message: &str) -> MimicryError {
+    MimicryError::ParseError(format!("WebRTC: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn pair() -> (WebRtcDisguise, WebRtcDisguise) {
+        let client_addr: SocketAddr = "203.0.113.7:50000".parse().unwrap();
+        let server_addr: SocketAddr = "198.51.100.1:3478".parse().unwrap();
+        (
+            WebRtcDisguise::with_rng(
+                Role::Client,
+                server_addr,
+                Box::new(StdRng::seed_from_u64(1)),
+            ),
+            WebRtcDisguise::with_rng(
+                Role::Server,
+                client_addr,
+                Box::new(StdRng::seed_from_u64(2)),
+            ),
+        )
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_stun_exchange_precedes_media() {
+        let (mut client, mut server) = pair();
+
+        let hello = client.wrap(b"client hello").unwrap();
+        let control = client.take_control();
+        assert_eq!(control.len(), 1);
+        let request = &control[0];
+        assert_eq!(&request[..2], &BINDING_REQUEST.to_be_bytes());
+        assert_eq!(&request[4..8], &STUN_MAGIC_COOKIE.to_be_bytes());
+
+        // Сервер отвечает на проверку и не отдаёт данных
+        assert!(server.unwrap(request).unwrap().is_none());
+        let responses = server.take_control();
+        assert_eq!(responses.len(), 1);
+        let response = &responses[0];
+        assert_eq!(&response[..2], &BINDING_SUCCESS.to_be_bytes());
+        assert_eq!(&response[8..20], &request[8..20]);
+
+        // XOR-MAPPED-ADDRESS — адрес клиента
+        assert_eq!(&response[20..22], &attr::XOR_MAPPED_ADDRESS.to_be_bytes());
+        let port = u16::from_be_bytes([response[26], response[27]]) ^ 0x2112;
+        assert_eq!(port, 50000);
+        assert_eq!(response[28] ^ 0x21, 203);
+
+        assert_eq!(
+            &server.unwrap(&hello).unwrap().unwrap()[..],
+            b"client hello"
+        );
+        assert!(client.unwrap(response).unwrap().is_none());
+        assert!(client.is_connected());
+        // Повторный ответ на ту же проверку не принимается
+        assert!(client.unwrap(response).is_err());
+
+        let reply = server.wrap(b"server hello").unwrap();
+        assert!(server.take_control().is_empty());
+        assert_eq!(
+            &client.unwrap(&reply).unwrap().unwrap()[..],
+            b"server hello"
+        );
+
+        // Следующая проверка согласия — только через CONSENT_INTERVAL
+        client.wrap(b"data").unwrap();
+        assert!(client.take_control().is_empty());
+    }
+
+    #[test]
+    fn test_rtp_streams() {
+        let (mut client, mut server) = pair();
+        let (audio_ssrc, video_ssrc) = client.ssrcs();
+
+        let mut last: Option<(u16, u32)> = None;
+        for _ in 0..3 {
+            let packet = client.wrap(&[0x11; 1200]).unwrap();
+            assert_eq!(packet.len(), 12 + 1200 + SRTP_AUTH_TAG_LEN);
+            assert_eq!(packet[0], 0x80);
+            assert_eq!(packet[1], VIDEO_PAYLOAD_TYPE);
+            assert_eq!(&packet[8..12], &video_ssrc.to_be_bytes());
+
+            let sequence = u16::from_be_bytes([packet[2], packet[3]]);
+            let timestamp = u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]);
+            if let Some((prev_seq, prev_ts)) = last {
+                assert_eq!(sequence, prev_seq.wrapping_add(1));
+                assert!(timestamp.wrapping_sub(prev_ts) < 90_000);
+            }
+            last = Some((sequence, timestamp));
+            assert_eq!(
+                &server.unwrap(&packet).unwrap().unwrap()[..],
+                &[0x11; 1200][..]
+            );
+        }
+
+        // Хвост кадра — с маркером, короткие датаграммы — аудио
+        let tail = client.wrap(&[0x22; 500]).unwrap();
+        assert_eq!(tail[1], 0x80 | VIDEO_PAYLOAD_TYPE);
+        let voice = client.wrap(&[0x33; 80]).unwrap();
+        assert_eq!(voice[1], AUDIO_PAYLOAD_TYPE);
+        assert_eq!(&voice[8..12], &audio_ssrc.to_be_bytes());
+        assert_eq!(
+            &server.unwrap(&voice).unwrap().unwrap()[..],
+            &[0x33; 80][..]
+        );
+    }
+
+    #[test]
+    fn test_foreign_datagrams_rejected() {
+        let (mut client, mut server) = pair();
+        client.wrap(b"hello").unwrap();
+        let mut request = client.take_control().remove(0).to_vec();
+
+        request[24] ^= 1;
+        assert!(server.unwrap(&request).is_err(), "испорчен FINGERPRINT");
+        assert!(server.unwrap(&[0x47; 40]).is_err(), "не STUN и не RTP");
+
+        let mut packet = client.wrap(&[0u8; 300]).unwrap().to_vec();
+        packet[1] = 0;
+        assert!(server.unwrap(&packet).is_err(), "payload type PCMU");
+        // Клиент не отвечает на проверки, сервер их не получает в ответ
+        let request = server.binding_request();
+        assert!(client.unwrap(&request).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+//! Клиент DNS туннеля поверх UDP сокета
+//!
+//! [`connect`] открывает сессию через резолвер и возвращает поток, в
+//! который пишется и из которого читается поток LLP. Фоновая задача
+//! держит не больше одного запроса в полёте: повторяет его по таймауту,
+//! а в простое опрашивает сервер с растущим до `poll_max` интервалом.
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc::{self, error::TryRecvError};
+use tracing::debug;
+
+use super::{RecordType, TunnelClient};
+use crate::error::Result;
+
+/// Сколько неподтверждённых данных клиента держит туннель
+const UPSTREAM_WINDOW: usize = 64 * 1024;
+
+/// Буфер потока между приложением и туннелем
+const STREAM_BUFFER: usize = 64 * 1024;
+
+/// Параметры клиента туннеля
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    /// Резолвер, через который идут запросы
+    pub resolver: SocketAddr,
+    /// Домен, для которого сервер LLP авторитетен
+    pub domain: String,
+    /// Тип записи ответа
+    pub record: RecordType,
+    /// Интервал опроса сразу после обмена данными
+    pub poll_min: Duration,
+    /// Предельный интервал опроса в простое
+    pub poll_max: Duration,
+    /// Ожидание ответа на запрос до повтора
+    pub query_timeout: Duration,
+    /// Запросов без ответа подряд до разрыва сессии
+    pub max_failures: u32,
+}
+
+impl ClientOptions {
+    /// Параметры по умолчанию для резолвера и домена
+    pub fn new(resolver: SocketAddr, domain: impl Into<String>) -> Self {
+        Self {
+            resolver,
+            domain: domain.into(),
+            record: RecordType::default(),
+            poll_min: Duration::from_millis(20),
+            poll_max: Duration::from_secs(1),
+            query_timeout: Duration::from_secs(2),
+            max_failures: 8,
+        }
+    }
+}
+
+/// Открыть сессию туннеля
+///
+/// Первый опрос должен получить ответ сервера: иначе туннель
+/// недоступен и возвращается ошибка.
+pub async fn connect(options: ClientOptions) -> Result<DuplexStream> {
+    let bind: SocketAddr = if options.resolver.is_ipv4() {
+        "0.0.0.0:0".parse().expect("корректный адрес")
+    } else {
+        "[::]:0".parse().expect("корректный адрес")
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(options.resolver).await?;
+    let mut tunnel = TunnelClient::new(&options.domain, options.record)?;
+
+    let mut attempt = 0;
+    loop {
+        match exchange(&socket, &mut tunnel, options.query_timeout).await {
+            Ok(_) => break,
+            Err(e) if attempt + 1 < options.max_failures.min(3) => {
+                debug!("Первый запрос DNS туннеля без ответа: {}", e);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let (local, remote) = tokio::io::duplex(STREAM_BUFFER);
+    tokio::spawn(drive(socket, tunnel, remote, options));
+    Ok(local)
+}
+
+/// Один запрос и ответ на него (ответы на прежние запросы пропускаются)
+async fn exchange(
+    socket: &UdpSocket,
+    tunnel: &mut TunnelClient,
+    timeout: Duration,
+) -> Result<Bytes> {
+    let (id, query) = tunnel.query()?;
+    socket.send(&query).await?;
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut buf = vec![0u8; u16::MAX as usize];
+    loop {
+        let len = tokio::time::timeout_at(deadline, socket.recv(&mut buf))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "нет ответа DNS"))??;
+        if len >= 2 && u16::from_be_bytes([buf[0], buf[1]]) == id {
+            return tunnel.handle_response(&buf[..len]);
+        }
+    }
+}
+
+/// Перекачка между потоком приложения и запросами туннеля
+async fn drive(
+    socket: UdpSocket,
+    mut tunnel: TunnelClient,
+    stream: DuplexStream,
+    options: ClientOptions,
+) {
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let (tx, mut rx) = mpsc::channel::<Vec<u8>>(16);
+    let read_task = tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        loop {
+            match reader.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if tx.send(buf[..n].to_vec()).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let mut idle = options.poll_min;
+    let mut closed = false;
+    let mut failures = 0;
+    loop {
+        while !closed && tunnel.pending() < UPSTREAM_WINDOW {
+            match rx.try_recv() {
+                Ok(data) => tunnel.push(&data),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => closed = true,
+            }
+        }
+        if closed && tunnel.pending() == 0 {
+            break;
+        }
+
+        let received = match exchange(&socket, &mut tunnel, options.query_timeout).await {
+            Ok(received) => {
+                failures = 0;
+                received
+            }
+            Err(e) => {
+                failures += 1;
+                debug!("Запрос DNS туннеля не удался ({}): {}", failures, e);
+                if failures >= options.max_failures {
+                    break;
+                }
+                continue;
+            }
+        };
+        if !received.is_empty() && writer.write_all(&received).await.is_err() {
+            break;
+        }
+
+        if !received.is_empty() || tunnel.pending() > 0 {
+            idle = options.poll_min;
+            continue;
+        }
+        if closed {
+            break;
+        }
+        match tokio::time::timeout(idle, rx.recv()).await {
+            Ok(Some(data)) => {
+                tunnel.push(&data);
+                idle = options.poll_min;
+            }
+            Ok(None) => closed = true,
+            Err(_) => idle = (idle * 2).min(options.poll_max),
+        }
+    }
+
+    // Без задачи чтения поток приложения получает конец данных
+    read_task.abort();
+    debug!("Сессия DNS туннеля {:08x} завершена", tunnel.session());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tunnel::Upstream;
+    use super::super::{encode_response, parse_query, rcode, TunnelSession};
+    use super::*;
+    use std::collections::HashMap;
+
+    const DOMAIN: &str = "t.example.com";
+
+    /// Авторитетный сервер зоны, возвращающий данные клиента обратно
+    async fn echo_authority() -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut sessions: HashMap<u32, TunnelSession> = HashMap::new();
+            let mut buf = vec![0u8; 4096];
+            loop {
+                let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+                let question = parse_query(&buf[..len]).unwrap();
+                let upstream = Upstream::from_name(&question.name, DOMAIN).unwrap();
+                let session = sessions.entry(upstream.session).or_default();
+                let data = session.receive(&upstream);
+                session.send(&data);
+                let reply = session.reply(question.answer_capacity(RecordType::Txt));
+                let response =
+                    encode_response(&question, rcode::NO_ERROR, Some((RecordType::Txt, &reply)))
+                        .unwrap();
+                socket.send_to(&response, peer).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    /// Заглушка резолвера: пересылает запросы авторитетному серверу со
+    /// своими идентификаторами и теряет каждый пятый запрос
+    async fn resolver_stub(authority: SocketAddr) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let upstream = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            upstream.connect(authority).await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let mut counter = 0u16;
+            loop {
+                let (len, client) = socket.recv_from(&mut buf).await.unwrap();
+                counter = counter.wrapping_add(1);
+                if counter.is_multiple_of(5) {
+                    continue;
+                }
+                let id = [buf[0], buf[1]];
+                buf[..2].copy_from_slice(&counter.to_be_bytes());
+                upstream.send(&buf[..len]).await.unwrap();
+                let len = upstream.recv(&mut buf).await.unwrap();
+                buf[..2].copy_from_slice(&id);
+                socket.send_to(&buf[..len], client).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_stream_through_resolver_stub() {
+        let resolver = resolver_stub(echo_authority().await).await;
+        let mut options = ClientOptions::new(resolver, DOMAIN);
+        options.query_timeout = Duration::from_millis(100);
+        let mut stream = connect(options).await.unwrap();
+
+        let data: Vec<u8> = (0..3000u32).map(|i| (i * 13) as u8).collect();
+        stream.write_all(&data).await.unwrap();
+        let mut echoed = vec![0u8; data.len()];
+        tokio::time::timeout(Duration::from_secs(20), stream.read_exact(&mut echoed))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(echoed, data);
+    }
+
+    #[tokio::test]
+    async fn test_connect_fails_without_server() {
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut options = ClientOptions::new(silent.local_addr().unwrap(), DOMAIN);
+        options.query_timeout = Duration::from_millis(50);
+        assert!(connect(options).await.is_err());
+    }
+}
@@ -0,0 +1,559 @@
+//! DNS туннель: резервный транспорт для сетей, где проходит только DNS
+//!
+//! За captive portal или при белых списках до сервера не доходит ни один
+//! HTTP профиль, но DNS запросы через локальный резолвер обычно
+//! разрешены. Туннель передаёт поток LLP в DNS запросах к поддоменам
+//! настроенного домена и в ответах на них; сервер LLP — авторитетный
+//! сервер этого домена.
+//!
+//! - Данные клиента идут в имени запроса: base32 метки перед доменом
+//!   ([`tunnel`]).
+//! - Данные сервера — в записях TXT или NULL ответа ([`RecordType`]).
+//! - Сервер не может отправить ответ без запроса, поэтому клиент
+//!   опрашивает его и в простое ([`client`]).
+//!
+//! ```text
+//! запрос:  <base32(nonce, сессия, смещения, данные)>.<метки по 63>.t.example.com TXT
+//! ответ:   TXT "<подтверждение, смещение, данные сервера>"
+//! ```
+//!
+//! Модуль содержит кодек DNS сообщений в объёме, нужном туннелю
+//! (RFC 1035, EDNS0 из RFC 6891): один вопрос, одна запись ответа.
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::error::{MimicryError, Result};
+
+pub mod client;
+pub mod tunnel;
+
+pub use client::{connect, ClientOptions};
+pub use tunnel::{TunnelClient, TunnelSession};
+
+/// Максимальная длина доменного имени в текстовом виде
+pub const MAX_NAME_LEN: usize = 253;
+
+/// Максимальная длина метки
+pub const MAX_LABEL_LEN: usize = 63;
+
+/// Размер UDP ответа без EDNS0
+pub const CLASSIC_UDP_SIZE: u16 = 512;
+
+/// Размер UDP, который объявляет клиент и принимает сервер (DNS Flag Day 2020)
+pub const EDNS_UDP_SIZE: u16 = 1232;
+
+/// Класс IN
+const CLASS_IN: u16 = 1;
+
+/// Тип OPT псевдозаписи EDNS0
+const TYPE_OPT: u16 = 41;
+
+/// Коды ответа
+pub mod rcode {
+    /// Без ошибок
+    pub const NO_ERROR: u8 = 0;
+    /// Ошибка формата запроса
+    pub const FORM_ERR: u8 = 1;
+    /// Имя не существует
+    pub const NX_DOMAIN: u8 = 3;
+    /// Сервер отказывается отвечать (чужая зона)
+    pub const REFUSED: u8 = 5;
+}
+
+/// Тип записи с данными сервера
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RecordType {
+    /// TXT: строки до 255 байт, проходит через любой резолвер
+    #[default]
+    Txt,
+    /// NULL: произвольные байты без разметки
+    Null,
+}
+
+impl RecordType {
+    /// Имя типа в конфигурации
+    pub fn name(&self) -> &'static str {
+        match self {
+            RecordType::Txt => "txt",
+            RecordType::Null => "null",
+        }
+    }
+
+    /// Тип по имени из конфигурации
+    pub fn from_name(name: &str) -> Option<Self> {
+        [RecordType::Txt, RecordType::Null]
+            .into_iter()
+            .find(|record| record.name() == name)
+    }
+
+    /// Код типа в DNS сообщении
+    pub fn code(&self) -> u16 {
+        match self {
+            RecordType::Txt => 16,
+            RecordType::Null => 10,
+        }
+    }
+
+    /// Тип по коду из DNS сообщения
+    pub fn from_code(code: u16) -> Option<Self> {
+        [RecordType::Txt, RecordType::Null]
+            .into_iter()
+            .find(|record| record.code() == code)
+    }
+
+    /// Длина RDATA для `len` байт данных
+    pub fn rdata_len(&self, len: usize) -> usize {
+        match self {
+            // Каждые 255 байт — отдельная строка со своим байтом длины
+            RecordType::Txt => len + len.div_ceil(255).max(1),
+            RecordType::Null => len,
+        }
+    }
+}
+
+/// Вопрос DNS запроса
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    /// Идентификатор запроса
+    pub id: u16,
+    /// Имя без завершающей точки, в регистре запроса
+    pub name: String,
+    /// Код типа
+    pub qtype: u16,
+    /// Размер UDP из EDNS0 (без OPT — 512)
+    pub udp_size: u16,
+    /// Был ли в запросе OPT
+    pub edns: bool,
+    /// Флаг RD запроса
+    pub recursion_desired: bool,
+}
+
+impl Question {
+    /// Длина вопроса в сообщении
+    fn wire_len(&self) -> usize {
+        name_wire_len(&self.name) + 4
+    }
+
+    /// Сколько байт данных поместится в ответ с записью `record`
+    pub fn answer_capacity(&self, record: RecordType) -> usize {
+        let size = self.udp_size.clamp(CLASSIC_UDP_SIZE, EDNS_UDP_SIZE) as usize;
+        let fixed = 12 + self.wire_len() + 12 + if self.edns { 11 } else { 0 };
+        let rdata = size.saturating_sub(fixed);
+        let mut len = rdata;
+        while len > 0 && record.rdata_len(len) > rdata {
+            len -= 1;
+        }
+        len
+    }
+}
+
+/// Длина имени в сообщении (метки с байтами длины и корневой ноль)
+pub fn name_wire_len(name: &str) -> usize {
+    if name.is_empty() {
+        1
+    } else {
+        name.len() + 2
+    }
+}
+
+/// Закодировать запрос с одним вопросом и OPT ([`EDNS_UDP_SIZE`])
+pub fn encode_query(id: u16, name: &str, qtype: u16) -> Result<Bytes> {
+    let mut out = BytesMut::with_capacity(12 + name_wire_len(name) + 4 + 11);
+    out.put_u16(id);
+    out.put_u16(0x0100); // RD
+    out.put_u16(1);
+    out.put_u16(0);
+    out.put_u16(0);
+    out.put_u16(1);
+    put_name(&mut out, name)?;
+    out.put_u16(qtype);
+    out.put_u16(CLASS_IN);
+    put_opt(&mut out);
+    Ok(out.freeze())
+}
+
+/// Разобрать запрос: ровно один вопрос класса IN
+pub fn parse_query(message: &[u8]) -> Result<Question> {
+    let mut reader = Reader::new(message);
+    let id = reader.u16()?;
+    let flags = reader.u16()?;
+    if flags & 0x8000 != 0 {
+        return Err(dns_error("ожидался запрос, получен ответ"));
+    }
+    if (flags >> 11) & 0xF != 0 {
+        return Err(dns_error("неподдерживаемый opcode"));
+    }
+    let qdcount = reader.u16()?;
+    let ancount = reader.u16()?;
+    let nscount = reader.u16()?;
+    let arcount = reader.u16()?;
+    if qdcount != 1 || ancount != 0 || nscount != 0 {
+        return Err(dns_error("ожидался один вопрос"));
+    }
+
+    let name = reader.name()?;
+    let qtype = reader.u16()?;
+    if reader.u16()? != CLASS_IN {
+        return Err(dns_error("класс не IN"));
+    }
+
+    let mut question = Question {
+        id,
+        name,
+        qtype,
+        udp_size: CLASSIC_UDP_SIZE,
+        edns: false,
+        recursion_desired: flags & 0x0100 != 0,
+    };
+    for _ in 0..arcount {
+        reader.name()?;
+        let kind = reader.u16()?;
+        let class = reader.u16()?;
+        reader.take(4)?;
+        let rdlen = reader.u16()? as usize;
+        reader.take(rdlen)?;
+        if kind == TYPE_OPT {
+            // Класс OPT — размер UDP отправителя
+            question.edns = true;
+            question.udp_size = class.max(CLASSIC_UDP_SIZE);
+        }
+    }
+    Ok(question)
+}
+
+/// Закодировать авторитетный ответ на вопрос
+///
+/// С `answer` ответ содержит одну запись `record` с TTL 0, чтобы
+/// резолверы не кэшировали данные туннеля.
+pub fn encode_response(
+    question: &Question,
+    rcode: u8,
+    answer: Option<(RecordType, &[u8])>,
+) -> Result<Bytes> {
+    let mut out = BytesMut::with_capacity(CLASSIC_UDP_SIZE as usize);
+    out.put_u16(question.id);
+    // QR, AA, RD копируется из запроса
+    let rd = if question.recursion_desired {
+        0x0100
+    } else {
+        0
+    };
+    out.put_u16(0x8400 | rd | (rcode & 0xF) as u16);
+    out.put_u16(1);
+    out.put_u16(answer.is_some() as u16);
+    out.put_u16(0);
+    out.put_u16(question.edns as u16);
+    put_name(&mut out, &question.name)?;
+    out.put_u16(question.qtype);
+    out.put_u16(CLASS_IN);
+
+    if let Some((record, data)) = answer {
+        out.put_u16(0xC00C); // указатель на имя вопроса
+        out.put_u16(record.code());
+        out.put_u16(CLASS_IN);
+        out.put_u32(0);
+        let rdlen = record.rdata_len(data.len());
+        if rdlen > u16::MAX as usize {
+            return Err(dns_error("данные не помещаются в запись"));
+        }
+        out.put_u16(rdlen as u16);
+        match record {
+            RecordType::Txt if data.is_empty() => out.put_u8(0),
+            RecordType::Txt => {
+                for chunk in data.chunks(255) {
+                    out.put_u8(chunk.len() as u8);
+                    out.put_slice(chunk);
+                }
+            }
+            RecordType::Null => out.put_slice(data),
+        }
+    }
+    if question.edns {
+        put_opt(&mut out);
+    }
+    Ok(out.freeze())
+}
+
+/// Ответ сервера, разобранный клиентом
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// Идентификатор запроса
+    pub id: u16,
+    /// Код ответа
+    pub rcode: u8,
+    /// Данные первой записи TXT или NULL (строки TXT склеены)
+    pub data: Option<Bytes>,
+}
+
+/// Разобрать ответ на запрос
+pub fn parse_response(message: &[u8]) -> Result<Response> {
+    let mut reader = Reader::new(message);
+    let id = reader.u16()?;
+    let flags = reader.u16()?;
+    if flags & 0x8000 == 0 {
+        return Err(dns_error("ожидался ответ"));
+    }
+    if flags & 0x0200 != 0 {
+        return Err(dns_error("ответ обрезан (TC)"));
+    }
+    let qdcount = reader.u16()?;
+    let ancount = reader.u16()?;
+    reader.take(4)?;
+    for _ in 0..qdcount {
+        reader.name()?;
+        reader.take(4)?;
+    }
+
+    let mut data = None;
+    for _ in 0..ancount {
+        reader.name()?;
+        let kind = reader.u16()?;
+        reader.take(6)?;
+        let rdlen = reader.u16()? as usize;
+        let rdata = reader.take(rdlen)?;
+        if data.is_some() {
+            continue;
+        }
+        data = match RecordType::from_code(kind) {
+            Some(RecordType::Txt) => Some(join_txt(rdata)?),
+            Some(RecordType::Null) => Some(Bytes::copy_from_slice(rdata)),
+            None => None,
+        };
+    }
+    Ok(Response {
+        id,
+        rcode: (flags & 0xF) as u8,
+        data,
+    })
+}
+
+/// Склеить строки TXT записи
+fn join_txt(mut rdata: &[u8]) -> Result<Bytes> {
+    let mut out = BytesMut::with_capacity(rdata.len());
+    while let Some((&len, rest)) = rdata.split_first() {
+        let len = len as usize;
+        if rest.len() < len {
+            return Err(dns_error("обрыв строки TXT"));
+        }
+        out.put_slice(&rest[..len]);
+        rdata = &rest[len..];
+    }
+    Ok(out.freeze())
+}
+
+fn put_name(out: &mut BytesMut, name: &str) -> Result<()> {
+    if name.len() > MAX_NAME_LEN {
+        return Err(dns_error("имя длиннее 253 символов"));
+    }
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        if label.len() > MAX_LABEL_LEN {
+            return Err(dns_error("метка длиннее 63 символов"));
+        }
+        out.put_u8(label.len() as u8);
+        out.put_slice(label.as_bytes());
+    }
+    out.put_u8(0);
+    Ok(())
+}
+
+/// OPT псевдозапись EDNS0 без опций
+fn put_opt(out: &mut BytesMut) {
+    out.put_u8(0);
+    out.put_u16(TYPE_OPT);
+    out.put_u16(EDNS_UDP_SIZE);
+    out.put_u32(0);
+    out.put_u16(0);
+}
+
+/// Алфавит base32 (RFC 4648) в нижнем регистре
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Закодировать байты в base32 без дополнения
+pub fn encode_base32(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for byte in data {
+        buffer = buffer << 8 | *byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[(buffer >> bits) as usize & 0x1F] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[(buffer << (5 - bits)) as usize & 0x1F] as char);
+    }
+    out
+}
+
+/// Декодировать base32 без дополнения; регистр не важен (резолверы
+/// меняют его по draft-vixie-dnsext-dns0x20)
+pub fn decode_base32(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 5 / 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for ch in text.bytes() {
+        let value = match ch.to_ascii_lowercase() {
+            c @ b'a'..=b'z' => c - b'a',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = buffer << 5 | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+fn dns_error(message: &str) -> MimicryError {
+    MimicryError::ParseError(format!("DNS: {}", message))
+}
+
+/// Чтение полей сообщения с проверкой границ
+struct Reader<'a> {
+    message: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(message: &'a [u8]) -> Self {
+        Self { message, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos + len;
+        let bytes = self
+            .message
+            .get(self.pos..end)
+            .ok_or_else(|| dns_error("обрыв сообщения"))?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Имя с поддержкой указателей сжатия
+    fn name(&mut self) -> Result<String> {
+        let mut labels: Vec<&str> = Vec::new();
+        let mut pos = self.pos;
+        let mut jumped = false;
+        let mut jumps = 0;
+        loop {
+            let len = *self
+                .message
+                .get(pos)
+                .ok_or_else(|| dns_error("обрыв имени"))? as usize;
+            match len {
+                0 => {
+                    if !jumped {
+                        self.pos = pos + 1;
+                    }
+                    break;
+                }
+                1..=63 => {
+                    let label = self
+                        .message
+                        .get(pos + 1..pos + 1 + len)
+                        .ok_or_else(|| dns_error("обрыв метки"))?;
+                    labels
+                        .push(std::str::from_utf8(label).map_err(|_| dns_error("метка не ASCII"))?);
+                    pos += 1 + len;
+                }
+                0xC0..=0xFF => {
+                    let low = *self
+                        .message
+                        .get(pos + 1)
+                        .ok_or_else(|| dns_error("обрыв указателя"))?;
+                    jumps += 1;
+                    if jumps > 16 {
+                        return Err(dns_error("цикл указателей сжатия"));
+                    }
+                    if !jumped {
+                        self.pos = pos + 2;
+                        jumped = true;
+                    }
+                    pos = ((len & 0x3F) << 8) | low as usize;
+                }
+                _ => return Err(dns_error("неизвестный тип метки")),
+            }
+        }
+        let name = labels.join(".");
+        if name.len() > MAX_NAME_LEN {
+            return Err(dns_error("имя длиннее 253 символов"));
+        }
+        Ok(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base32_rfc4648_vectors() {
+        for (plain, encoded) in [
+            ("", ""),
+            ("f", "my"),
+            ("fo", "mzxq"),
+            ("foo", "mzxw6"),
+            ("foob", "mzxw6yq"),
+            ("fooba", "mzxw6ytb"),
+            ("foobar", "mzxw6ytboi"),
+        ] {
+            assert_eq!(encode_base32(plain.as_bytes()), encoded);
+            assert_eq!(decode_base32(encoded).unwrap(), plain.as_bytes());
+        }
+        assert_eq!(decode_base32("MzXw6YtB").unwrap(), b"fooba");
+        assert!(decode_base32("mzxw1").is_none());
+    }
+
+    #[test]
+    fn test_query_response_roundtrip() {
+        let query = encode_query(0x1234, "abc.T.Example.com", 16).unwrap();
+        let question = parse_query(&query).unwrap();
+        assert_eq!(question.id, 0x1234);
+        assert_eq!(question.name, "abc.T.Example.com");
+        assert_eq!(question.qtype, 16);
+        assert!(question.edns);
+        assert_eq!(question.udp_size, EDNS_UDP_SIZE);
+
+        for record in [RecordType::Txt, RecordType::Null] {
+            let capacity = question.answer_capacity(record);
+            let data = vec![0xA5; capacity];
+            let response =
+                encode_response(&question, rcode::NO_ERROR, Some((record, &data))).unwrap();
+            assert_eq!(response.len(), EDNS_UDP_SIZE as usize);
+
+            let parsed = parse_response(&response).unwrap();
+            assert_eq!(parsed.id, 0x1234);
+            assert_eq!(parsed.rcode, rcode::NO_ERROR);
+            assert_eq!(parsed.data.unwrap(), data);
+        }
+
+        let refused = encode_response(&question, rcode::REFUSED, None).unwrap();
+        let parsed = parse_response(&refused).unwrap();
+        assert_eq!(parsed.rcode, rcode::REFUSED);
+        assert!(parsed.data.is_none());
+    }
+
+    #[test]
+    fn test_malformed_messages_rejected() {
+        let query = encode_query(1, "a.example.com", 16).unwrap();
+        assert!(parse_query(&query[..20]).is_err());
+        assert!(parse_response(&query).is_err(), "запрос вместо ответа");
+
+        // Указатель сжатия на самого себя
+        let mut looped = query[..12].to_vec();
+        looped.extend_from_slice(&[0xC0, 0x0C, 0x00, 0x10, 0x00, 0x01]);
+        assert!(parse_query(&looped).is_err());
+
+        assert!(encode_query(1, &"a".repeat(64), 16).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+//! Надёжный поток поверх DNS запросов и ответов
+//!
+//! Клиент в каждом запросе передаёт смещение своих данных в потоке и
+//! подтверждает принятые данные сервера; сервер в ответе подтверждает
+//! данные клиента и отправляет свои, начиная с первого
+//! неподтверждённого байта. Потерянный запрос или ответ просто
+//! повторяется: смещения отбрасывают уже принятые байты, а ответ на
+//! повтор запроса совпадает с исходным.
+//!
+//! ```text
+//! имя запроса:  base32([nonce:2][сессия:4][смещение:4][подтверждение:4][данные]).<домен>
+//! данные ответа: [подтверждение:4][смещение:4][данные]
+//! ```
+//!
+//! Случайный nonce делает имена неповторимыми, чтобы резолвер не отдал
+//! ответ из кэша. Сессию сервер заводит по первому запросу с нулевыми
+//! смещениями.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use super::{decode_base32, dns_error, encode_base32, RecordType, MAX_LABEL_LEN, MAX_NAME_LEN};
+use crate::error::{MimicryError, Result};
+use crate::profiles::BoxedRng;
+
+/// Заголовок данных запроса
+pub const QUERY_HEADER_LEN: usize = 14;
+
+/// Заголовок данных ответа
+pub const RESPONSE_HEADER_LEN: usize = 8;
+
+/// Принадлежит ли имя зоне `domain` (регистр не важен)
+pub fn in_zone(name: &str, domain: &str) -> bool {
+    subdomain(name, domain).is_some() || name.eq_ignore_ascii_case(domain)
+}
+
+/// Метки имени перед `domain`
+fn subdomain<'a>(name: &'a str, domain: &str) -> Option<&'a str> {
+    let split = name.len().checked_sub(domain.len() + 1)?;
+    if !name.is_char_boundary(split) {
+        return None;
+    }
+    let (labels, suffix) = name.split_at(split);
+    (suffix.as_bytes()[0] == b'.' && suffix[1..].eq_ignore_ascii_case(domain)).then_some(labels)
+}
+
+/// Сколько байт данных клиента помещается в имя запроса под `domain`
+pub fn query_capacity(domain: &str) -> usize {
+    // Символы base32 и точки между метками до точки перед доменом
+    let budget = MAX_NAME_LEN.saturating_sub(domain.len() + 1);
+    let mut chars = budget;
+    while chars > 0 && chars + chars.div_ceil(MAX_LABEL_LEN) - 1 > budget {
+        chars -= 1;
+    }
+    (chars * 5 / 8).saturating_sub(QUERY_HEADER_LEN)
+}
+
+/// Данные запроса клиента
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upstream {
+    /// Идентификатор сессии
+    pub session: u32,
+    /// Смещение `data` в потоке клиента
+    pub offset: u32,
+    /// Сколько байт потока сервера клиент принял
+    pub ack: u32,
+    /// Данные клиента
+    pub data: Bytes,
+}
+
+impl Upstream {
+    /// Разобрать имя запроса в зоне `domain`
+    pub fn from_name(name: &str, domain: &str) -> Result<Self> {
+        let labels = subdomain(name, domain).ok_or_else(|| dns_error("имя вне зоны туннеля"))?;
+        let encoded: String = labels.split('.').collect();
+        let payload = decode_base32(&encoded).ok_or_else(|| dns_error("метки не base32"))?;
+        if payload.len() < QUERY_HEADER_LEN {
+            return Err(dns_error("короткий запрос туннеля"));
+        }
+        let mut payload = Bytes::from(payload);
+        payload.advance(2);
+        Ok(Self {
+            session: payload.get_u32(),
+            offset: payload.get_u32(),
+            ack: payload.get_u32(),
+            data: payload,
+        })
+    }
+}
+
+/// Клиентская сторона туннеля
+pub struct TunnelClient {
+    domain: String,
+    record: RecordType,
+    rng: BoxedRng,
+    session: u32,
+    capacity: usize,
+    /// Неподтверждённые данные клиента, начиная со смещения `up_base`
+    upstream: BytesMut,
+    up_base: u32,
+    /// Сколько байт потока сервера принято
+    down_received: u32,
+    /// Идентификатор ожидаемого ответа
+    pending_id: Option<u16>,
+}
+
+impl TunnelClient {
+    /// Туннель через зону `domain` со случайной сессией
+    pub fn new(domain: &str, record: RecordType) -> Result<Self> {
+        Self::with_rng(domain, record, Box::new(OsRng))
+    }
+
+    /// Туннель с заданным источником случайности
+    pub fn with_rng(domain: &str, record: RecordType, mut rng: BoxedRng) -> Result<Self> {
+        let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+        let capacity = query_capacity(&domain);
+        if domain.is_empty() || capacity == 0 {
+            return Err(MimicryError::InvalidFormat(format!(
+                "домен туннеля \"{}\" не оставляет места для данных",
+                domain
+            )));
+        }
+        Ok(Self {
+            domain,
+            record,
+            session: rng.next_u32(),
+            rng,
+            capacity,
+            upstream: BytesMut::new(),
+            up_base: 0,
+            down_received: 0,
+            pending_id: None,
+        })
+    }
+
+    /// Идентификатор сессии
+    pub fn session(&self) -> u32 {
+        self.session
+    }
+
+    /// Сколько байт данных клиента уходит в одном запросе
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Поставить данные клиента в очередь
+    pub fn push(&mut self, data: &[u8]) {
+        self.upstream.extend_from_slice(data);
+    }
+
+    /// Данные клиента, ещё не подтверждённые сервером
+    pub fn pending(&self) -> usize {
+        self.upstream.len()
+    }
+
+    /// Следующий запрос: неподтверждённые данные или пустой опрос
+    ///
+    /// Возвращает идентификатор запроса и сообщение.
+    pub fn query(&mut self) -> Result<(u16, Bytes)> {
+        let len = self.upstream.len().min(self.capacity);
+        let mut payload = Vec::with_capacity(QUERY_HEADER_LEN + len);
+        payload.put_u16(self.rng.next_u32() as u16);
+        payload.put_u32(self.session);
+        payload.put_u32(self.up_base);
+        payload.put_u32(self.down_received);
+        payload.extend_from_slice(&self.upstream[..len]);
+
+        let encoded = encode_base32(&payload);
+        let mut name = String::with_capacity(MAX_NAME_LEN);
+        for label in encoded.as_bytes().chunks(MAX_LABEL_LEN) {
+            // base32 — ASCII, метки режутся по границам символов
+            name.push_str(std::str::from_utf8(label).unwrap_or_default());
+            name.push('.');
+        }
+        name.push_str(&self.domain);
+
+        let id = self.rng.next_u32() as u16;
+        self.pending_id = Some(id);
+        Ok((id, super::encode_query(id, &name, self.record.code())?))
+    }
+
+    /// Обработать ответ на последний запрос
+    ///
+    /// Возвращает новые данные сервера (повторно принятые отбрасываются).
+    pub fn handle_response(&mut self, message: &[u8]) -> Result<Bytes> {
+        let response = super::parse_response(message)?;
+        if Some(response.id) != self.pending_id {
+            return Err(dns_error("ответ на другой запрос"));
+        }
+        if response.rcode != super::rcode::NO_ERROR {
+            return Err(dns_error(&format!(
+                "сервер ответил кодом {}",
+                response.rcode
+            )));
+        }
+        let mut data = response
+            .data
+            .ok_or_else(|| dns_error("ответ без данных туннеля"))?;
+        if data.len() < RESPONSE_HEADER_LEN {
+            return Err(dns_error("короткий ответ туннеля"));
+        }
+        self.pending_id = None;
+
+        let ack = data.get_u32();
+        let offset = data.get_u32();
+
+        let acked = ack.wrapping_sub(self.up_base) as usize;
+        if acked <= self.upstream.len() {
+            self.upstream.advance(acked);
+            self.up_base = ack;
+        }
+
+        let seen = self.down_received.wrapping_sub(offset) as usize;
+        if seen > data.len() {
+            // Ответ из будущего или давно устаревший
+            return Ok(Bytes::new());
+        }
+        data.advance(seen);
+        self.down_received = self.down_received.wrapping_add(data.len() as u32);
+        Ok(data)
+    }
+}
+
+/// Серверная сторона одной сессии туннеля
+#[derive(Debug, Default)]
+pub struct TunnelSession {
+    /// Сколько байт потока клиента принято
+    up_received: u32,
+    /// Неподтверждённые данные сервера, начиная со смещения `down_base`
+    downstream: BytesMut,
+    down_base: u32,
+}
+
+impl TunnelSession {
+    /// Новая сессия (первый запрос клиента — с нулевыми смещениями)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Принять запрос клиента
+    ///
+    /// Возвращает новые данные клиента; подтверждённые клиентом данные
+    /// сервера отбрасываются.
+    pub fn receive(&mut self, upstream: &Upstream) -> Bytes {
+        let acked = upstream.ack.wrapping_sub(self.down_base) as usize;
+        if acked <= self.downstream.len() {
+            self.downstream.advance(acked);
+            self.down_base = upstream.ack;
+        }
+
+        let seen = self.up_received.wrapping_sub(upstream.offset) as usize;
+        if seen > upstream.data.len() {
+            return Bytes::new();
+        }
+        let data = upstream.data.slice(seen..);
+        self.up_received = self.up_received.wrapping_add(data.len() as u32);
+        data
+    }
+
+    /// Поставить данные сервера в очередь
+    pub fn send(&mut self, data: &[u8]) {
+        self.downstream.extend_from_slice(data);
+    }
+
+    /// Данные сервера, ещё не подтверждённые клиентом
+    pub fn pending(&self) -> usize {
+        self.downstream.len()
+    }
+
+    /// Данные для ответа, помещающегося в `capacity` байт
+    pub fn reply(&self, capacity: usize) -> Bytes {
+        let len = self
+            .downstream
+            .len()
+            .min(capacity.saturating_sub(RESPONSE_HEADER_LEN));
+        let mut out = BytesMut::with_capacity(RESPONSE_HEADER_LEN + len);
+        out.put_u32(self.up_received);
+        out.put_u32(self.down_base);
+        out.put_slice(&self.downstream[..len]);
+        out.freeze()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{encode_response, parse_query, rcode};
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const DOMAIN: &str = "t.example.com";
+
+    /// Ответ авторитетного сервера на запрос клиента
+    fn serve(session: &mut TunnelSession, query: &[u8], received: &mut Vec<u8>) -> Bytes {
+        let question = parse_query(query).unwrap();
+        let upstream = Upstream::from_name(&question.name, DOMAIN).unwrap();
+        received.extend_from_slice(&session.receive(&upstream));
+        let reply = session.reply(question.answer_capacity(RecordType::Txt));
+        encode_response(&question, rcode::NO_ERROR, Some((RecordType::Txt, &reply))).unwrap()
+    }
+
+    #[test]
+    fn test_query_names_fit_limits() {
+        let mut client =
+            TunnelClient::with_rng(DOMAIN, RecordType::Txt, Box::new(StdRng::seed_from_u64(1)))
+                .unwrap();
+        client.push(&[0xFF; 1000]);
+        let (_, query) = client.query().unwrap();
+        let question = parse_query(&query).unwrap();
+        assert!(question.name.len() <= MAX_NAME_LEN);
+        assert!(question.name.ends_with(".t.example.com"));
+        assert!(question
+            .name
+            .split('.')
+            .all(|label| label.len() <= MAX_LABEL_LEN));
+
+        let upstream = Upstream::from_name(&question.name.to_uppercase(), DOMAIN).unwrap();
+        assert_eq!(upstream.session, client.session());
+        assert_eq!(upstream.data.len(), client.capacity());
+
+        assert!(in_zone("abc.T.EXAMPLE.com", DOMAIN));
+        assert!(in_zone("t.example.com", DOMAIN));
+        assert!(!in_zone("abc.xt.example.com", DOMAIN));
+        assert!(TunnelClient::new(&"a".repeat(250), RecordType::Txt).is_err());
+    }
+
+    #[test]
+    fn test_stream_survives_lost_messages() {
+        let mut client =
+            TunnelClient::with_rng(DOMAIN, RecordType::Txt, Box::new(StdRng::seed_from_u64(2)))
+                .unwrap();
+        let mut session = TunnelSession::new();
+        let up: Vec<u8> = (0..2000u32).map(|i| i as u8).collect();
+        let down: Vec<u8> = (0..3000u32).map(|i| (i * 7) as u8).collect();
+        client.push(&up);
+        session.send(&down);
+
+        let mut received_up = Vec::new();
+        let mut received_down = Vec::new();
+        for round in 0..200 {
+            if client.pending() == 0 && session.pending() == 0 {
+                break;
+            }
+            let (_, query) = client.query().unwrap();
+            if round % 5 == 1 {
+                continue; // запрос потерян
+            }
+            let response = serve(&mut session, &query, &mut received_up);
+            if round % 7 == 3 {
+                // Ответ потерян, резолвер повторяет тот же запрос
+                let response = serve(&mut session, &query, &mut received_up);
+                received_down.extend_from_slice(&client.handle_response(&response).unwrap());
+                continue;
+            }
+            received_down.extend_from_slice(&client.handle_response(&response).unwrap());
+        }
+        assert_eq!(received_up, up);
+        assert_eq!(received_down, down);
+    }
+
+    #[test]
+    fn test_foreign_response_rejected() {
+        let mut client = TunnelClient::new(DOMAIN, RecordType::Null).unwrap();
+        let (_, query) = client.query().unwrap();
+        let mut question = parse_query(&query).unwrap();
+        question.id = question.id.wrapping_add(1);
+        let reply = TunnelSession::new().reply(100);
+        let response =
+            encode_response(&question, rcode::NO_ERROR, Some((RecordType::Null, &reply))).unwrap();
+        assert!(client.handle_response(&response).is_err());
+
+        question.id = question.id.wrapping_sub(1);
+        let response = encode_response(&question, rcode::NX_DOMAIN, None).unwrap();
+        assert!(client.handle_response(&response).is_err());
+    }
+}
@@ -13,6 +13,14 @@ pub enum MimicryError {
     #[error("Неподдерживаемый профиль мимикрии: {0}")]
     UnsupportedProfile(String),
 
+    /// Профиль с таким ID или именем уже зарегистрирован
+    #[error("Профиль мимикрии уже зарегистрирован: {0}")]
+    DuplicateProfile(String),
+
+    /// Некорректный шаблон профиля
+    #[error("Некорректный шаблон профиля: {0}")]
+    InvalidTemplate(String),
+
     /// Некорректный формат данных
     #[error("Некорректный формат данных: {0}")]
     InvalidFormat(String),
@@ -24,6 +32,18 @@ pub enum MimicryError {
     /// Ошибка извлечения пакета
     #[error("Ошибка извлечения пакета: {0}")]
     UnwrapError(String),
+
+    /// Недопустимое значение настройки
+    #[error("Некорректная настройка: {0}")]
+    InvalidConfig(String),
+
+    /// Ошибка TLS (конфигурация, сертификаты, SNI)
+    #[error("Ошибка TLS: {0}")]
+    Tls(String),
+
+    /// Ошибка ввода-вывода при чтении или записи потока
+    #[error("Ошибка ввода-вывода: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 /// Псевдоним для Result с MimicryError
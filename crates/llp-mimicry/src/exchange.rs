@@ -0,0 +1,141 @@
+//! Двусторонний HTTP обмен
+//!
+//! Клиент передаёт LLP пакеты в запросах (тело POST, параметр query
+//! или cookie Range GET), сервер — в ответах на эти запросы. Каждый
+//! ответ сервера отвечает на ранее полученный запрос, как в настоящем
+//! HTTP/1.1 соединении.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use bytes::Bytes;
+use serde::Deserialize;
+
+use crate::error::{MimicryError, Result};
+
+/// Максимальный размер пакета, передаваемого в query или cookie
+///
+/// Пакеты крупнее отправляются в теле POST: длинные URL и cookie
+/// выглядят подозрительно и обрезаются прокси.
+pub const MAX_INLINE_PAYLOAD: usize = 4096;
+
+/// Роль участника HTTP обмена
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Role {
+    /// Ответы в обе стороны (совместимость: роль должна совпадать у обеих сторон)
+    #[default]
+    Symmetric,
+    /// Клиент: отправляет запросы, получает ответы
+    Client,
+    /// Сервер: получает запросы, отвечает на них
+    Server,
+}
+
+/// Место payload в запросе клиента
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Carrier {
+    /// Тело запроса (POST с Content-Length)
+    Body,
+    /// Параметр query строки (base64url)
+    Query(String),
+    /// Cookie (base64url)
+    Cookie(String),
+}
+
+/// Закодировать пакет для query или cookie
+pub fn encode_inline(packet: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(packet)
+}
+
+/// Извлечь LLP пакет из HTTP запроса клиента
+///
+/// Непустое тело имеет приоритет (так приходят крупные пакеты); иначе
+/// payload ищется в `carrier`. Запрос без payload (опрос сервера)
+/// даёт пустой результат.
+pub fn extract_request_payload(data: &[u8], carrier: &Carrier) -> Result<Bytes> {
+    let mut headers = [httparse::EMPTY_HEADER; 32];
+    let mut req = httparse::Request::new(&mut headers);
+
+    let header_size = match req
+        .parse(data)
+        .map_err(|e| MimicryError::ParseError(format!("HTTP parse error: {:?}", e)))?
+    {
+        httparse::Status::Complete(size) => size,
+        httparse::Status::Partial => {
+            return Err(MimicryError::ParseError("Incomplete HTTP request".to_string()));
+        }
+    };
+
+    if header_size < data.len() {
+        return Ok(Bytes::copy_from_slice(&data[header_size..]));
+    }
+
+    let value = match carrier {
+        Carrier::Body => None,
+        Carrier::Query(name) => req
+            .path
+            .and_then(|path| path.split_once('?'))
+            .and_then(|(_, query)| find_pair(query.split('&'), name)),
+        Carrier::Cookie(name) => req
+            .headers
+            .iter()
+            .filter(|h| h.name.eq_ignore_ascii_case("cookie"))
+            .filter_map(|h| std::str::from_utf8(h.value).ok())
+            .find_map(|cookies| find_pair(cookies.split(';'), name)),
+    };
+
+    match value {
+        Some(encoded) => URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map(Bytes::from)
+            .map_err(|e| MimicryError::UnwrapError(format!("base64: {}", e))),
+        None => Ok(Bytes::new()),
+    }
+}
+
+/// Значение пары `name=value` из списка
+fn find_pair<'a>(mut pairs: impl Iterator<Item = &'a str>, name: &str) -> Option<&'a str> {
+    pairs.find_map(|pair| match pair.trim().split_once('=') {
+        Some((key, value)) if key == name => Some(value),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_from_body() {
+        let request = b"POST /stats HTTP/1.1\r\nHost: a\r\nContent-Length: 3\r\n\r\nabc";
+        let payload = extract_request_payload(request, &Carrier::Query("sign".into())).unwrap();
+        assert_eq!(&payload[..], b"abc");
+    }
+
+    #[test]
+    fn test_extract_from_query_and_cookie() {
+        let encoded = encode_inline(b"\x00\xffllp");
+
+        let request = format!("GET /track?a=1&sign={}&b=2 HTTP/1.1\r\nHost: a\r\n\r\n", encoded);
+        let payload =
+            extract_request_payload(request.as_bytes(), &Carrier::Query("sign".into())).unwrap();
+        assert_eq!(&payload[..], b"\x00\xffllp");
+
+        let request = format!(
+            "GET /seg.ts HTTP/1.1\r\nHost: a\r\nCookie: uid=1; rt_sid={}\r\n\r\n",
+            encoded
+        );
+        let payload =
+            extract_request_payload(request.as_bytes(), &Carrier::Cookie("rt_sid".into())).unwrap();
+        assert_eq!(&payload[..], b"\x00\xffllp");
+    }
+
+    #[test]
+    fn test_poll_request_is_empty() {
+        let request = b"GET /track?a=1 HTTP/1.1\r\nHost: a\r\n\r\n";
+        assert!(extract_request_payload(request, &Carrier::Query("sign".into()))
+            .unwrap()
+            .is_empty());
+        assert!(extract_request_payload(request, &Carrier::Body).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,348 @@
+//! Кадры HTTP/2 (RFC 9113, раздел 4)
+//!
+//! Кадр — 9 байт заголовка (длина 24 бита, тип, флаги, идентификатор
+//! потока 31 бит) и payload. [`FrameDecoder`] выделяет кадры из потока
+//! байт так же, как [`crate::codec::HttpDecoder`] выделяет HTTP/1.1
+//! сообщения.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::error::{MimicryError, Result};
+
+/// Префейс клиента перед первым кадром
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Размер заголовка кадра
+pub const FRAME_HEADER_SIZE: usize = 9;
+
+/// Максимальный размер payload кадра по умолчанию (`SETTINGS_MAX_FRAME_SIZE`)
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = 16_384;
+
+/// Начальный размер окна управления потоком по умолчанию
+pub const DEFAULT_WINDOW_SIZE: u32 = 65_535;
+
+/// Максимальный размер окна управления потоком
+pub const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+
+/// Типы кадров
+pub mod kind {
+    /// Данные потока
+    pub const DATA: u8 = 0x0;
+    /// Блок заголовков, открывающий поток
+    pub const HEADERS: u8 = 0x1;
+    /// Приоритет потока
+    pub const PRIORITY: u8 = 0x2;
+    /// Сброс потока
+    pub const RST_STREAM: u8 = 0x3;
+    /// Параметры соединения
+    pub const SETTINGS: u8 = 0x4;
+    /// Server push
+    pub const PUSH_PROMISE: u8 = 0x5;
+    /// Проверка соединения
+    pub const PING: u8 = 0x6;
+    /// Закрытие соединения
+    pub const GOAWAY: u8 = 0x7;
+    /// Увеличение окна управления потоком
+    pub const WINDOW_UPDATE: u8 = 0x8;
+    /// Продолжение блока заголовков
+    pub const CONTINUATION: u8 = 0x9;
+}
+
+/// Флаги кадров
+pub mod flags {
+    /// Последний кадр потока (DATA, HEADERS)
+    pub const END_STREAM: u8 = 0x1;
+    /// Подтверждение (SETTINGS, PING)
+    pub const ACK: u8 = 0x1;
+    /// Блок заголовков завершён (HEADERS, CONTINUATION)
+    pub const END_HEADERS: u8 = 0x4;
+    /// Payload с дополнением (DATA, HEADERS)
+    pub const PADDED: u8 = 0x8;
+    /// HEADERS содержит приоритет
+    pub const PRIORITY: u8 = 0x20;
+}
+
+/// Идентификаторы параметров SETTINGS
+pub mod setting {
+    /// Размер динамической таблицы HPACK
+    pub const HEADER_TABLE_SIZE: u16 = 0x1;
+    /// Разрешён ли server push
+    pub const ENABLE_PUSH: u16 = 0x2;
+    /// Максимум одновременных потоков
+    pub const MAX_CONCURRENT_STREAMS: u16 = 0x3;
+    /// Начальное окно потока
+    pub const INITIAL_WINDOW_SIZE: u16 = 0x4;
+    /// Максимальный размер payload кадра
+    pub const MAX_FRAME_SIZE: u16 = 0x5;
+    /// Максимальный размер списка заголовков
+    pub const MAX_HEADER_LIST_SIZE: u16 = 0x6;
+}
+
+/// Коды ошибок RST_STREAM и GOAWAY
+pub mod error_code {
+    /// Без ошибки
+    pub const NO_ERROR: u32 = 0x0;
+    /// Нарушение протокола
+    pub const PROTOCOL_ERROR: u32 = 0x1;
+    /// Нарушение управления потоком
+    pub const FLOW_CONTROL_ERROR: u32 = 0x3;
+    /// Поток отменён
+    pub const CANCEL: u32 = 0x8;
+}
+
+/// Кадр HTTP/2
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawFrame {
+    /// Тип кадра ([`kind`])
+    pub kind: u8,
+    /// Флаги ([`flags`])
+    pub flags: u8,
+    /// Идентификатор потока (0 — соединение)
+    pub stream_id: u32,
+    /// Payload
+    pub payload: Bytes,
+}
+
+impl RawFrame {
+    /// Новый кадр
+    pub fn new(kind: u8, flags: u8, stream_id: u32, payload: impl Into<Bytes>) -> Self {
+        Self {
+            kind,
+            flags,
+            stream_id,
+            payload: payload.into(),
+        }
+    }
+
+    /// Установлен ли флаг
+    pub fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Записать кадр в `out`
+    pub fn encode(&self, out: &mut BytesMut) {
+        encode_frame(out, self.kind, self.flags, self.stream_id, &self.payload);
+    }
+
+    /// Payload без дополнения и приоритета (DATA, HEADERS)
+    pub fn body(&self) -> Result<Bytes> {
+        let mut payload = self.payload.clone();
+        let mut padding = 0;
+        if self.has_flag(flags::PADDED) {
+            if payload.is_empty() {
+                return Err(frame_error("нет длины дополнения"));
+            }
+            padding = payload.get_u8() as usize;
+        }
+        if self.kind == kind::HEADERS && self.has_flag(flags::PRIORITY) {
+            if payload.len() < 5 {
+                return Err(frame_error("обрыв приоритета HEADERS"));
+            }
+            payload.advance(5);
+        }
+        if padding > payload.len() {
+            return Err(frame_error("дополнение длиннее payload"));
+        }
+        payload.truncate(payload.len() - padding);
+        Ok(payload)
+    }
+}
+
+/// Записать кадр в `out`
+pub fn encode_frame(out: &mut BytesMut, kind: u8, flags: u8, stream_id: u32, payload: &[u8]) {
+    let len = payload.len() as u32;
+    out.put_slice(&len.to_be_bytes()[1..]);
+    out.put_u8(kind);
+    out.put_u8(flags);
+    out.put_u32(stream_id & MAX_WINDOW_SIZE);
+    out.put_slice(payload);
+}
+
+/// Payload кадра SETTINGS
+pub fn settings_payload(settings: &[(u16, u32)]) -> Bytes {
+    let mut payload = BytesMut::with_capacity(settings.len() * 6);
+    for (id, value) in settings {
+        payload.put_u16(*id);
+        payload.put_u32(*value);
+    }
+    payload.freeze()
+}
+
+/// Разобрать payload кадра SETTINGS
+pub fn parse_settings(payload: &[u8]) -> Result<Vec<(u16, u32)>> {
+    if !payload.len().is_multiple_of(6) {
+        return Err(frame_error("размер SETTINGS не кратен 6"));
+    }
+    Ok(payload
+        .chunks_exact(6)
+        .map(|entry| {
+            (
+                u16::from_be_bytes([entry[0], entry[1]]),
+                u32::from_be_bytes([entry[2], entry[3], entry[4], entry[5]]),
+            )
+        })
+        .collect())
+}
+
+/// Разобрать payload кадра WINDOW_UPDATE
+pub fn parse_window_update(payload: &[u8]) -> Result<u32> {
+    let bytes: [u8; 4] = payload
+        .try_into()
+        .map_err(|_| frame_error("размер WINDOW_UPDATE не равен 4"))?;
+    match u32::from_be_bytes(bytes) & MAX_WINDOW_SIZE {
+        0 => Err(frame_error("WINDOW_UPDATE с нулевым приращением")),
+        increment => Ok(increment),
+    }
+}
+
+fn frame_error(message: &str) -> MimicryError {
+    MimicryError::ParseError(format!("HTTP/2: {}", message))
+}
+
+/// Инкрементальный декодер кадров
+#[derive(Debug)]
+pub struct FrameDecoder {
+    buf: BytesMut,
+    max_frame_size: u32,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FRAME_SIZE)
+    }
+}
+
+impl FrameDecoder {
+    /// Декодер, принимающий payload до `max_frame_size` байт
+    pub fn new(max_frame_size: u32) -> Self {
+        Self {
+            buf: BytesMut::new(),
+            max_frame_size,
+        }
+    }
+
+    /// Добавить прочитанные данные
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Количество накопленных байт
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Снять префейс клиента
+    ///
+    /// `Ok(false)` — данных пока меньше длины префейса.
+    pub fn take_preface(&mut self) -> Result<bool> {
+        let available = self.buf.len().min(PREFACE.len());
+        if self.buf[..available] != PREFACE[..available] {
+            return Err(frame_error("неверный префейс клиента"));
+        }
+        if available < PREFACE.len() {
+            return Ok(false);
+        }
+        self.buf.advance(PREFACE.len());
+        Ok(true)
+    }
+
+    /// Извлечь следующий целый кадр
+    pub fn decode(&mut self) -> Result<Option<RawFrame>> {
+        if self.buf.len() < FRAME_HEADER_SIZE {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes([0, self.buf[0], self.buf[1], self.buf[2]]);
+        if len > self.max_frame_size {
+            return Err(frame_error(&format!(
+                "кадр {} байт больше {}",
+                len, self.max_frame_size
+            )));
+        }
+        if self.buf.len() < FRAME_HEADER_SIZE + len as usize {
+            return Ok(None);
+        }
+        let mut header = self.buf.split_to(FRAME_HEADER_SIZE);
+        header.advance(3);
+        let kind = header.get_u8();
+        let flags = header.get_u8();
+        let stream_id = header.get_u32() & MAX_WINDOW_SIZE;
+        let payload = self.buf.split_to(len as usize).freeze();
+        Ok(Some(RawFrame {
+            kind,
+            flags,
+            stream_id,
+            payload,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_round_trip_across_reads() {
+        let mut out = BytesMut::new();
+        out.put_slice(PREFACE);
+        encode_frame(
+            &mut out,
+            kind::SETTINGS,
+            0,
+            0,
+            &settings_payload(&[(setting::INITIAL_WINDOW_SIZE, 6_291_456)]),
+        );
+        encode_frame(&mut out, kind::DATA, flags::END_STREAM, 3, b"llp");
+
+        let mut decoder = FrameDecoder::default();
+        let mut frames = Vec::new();
+        let mut preface = false;
+        for byte in out.iter() {
+            decoder.extend(&[*byte]);
+            if !preface {
+                preface = decoder.take_preface().unwrap();
+                continue;
+            }
+            while let Some(frame) = decoder.decode().unwrap() {
+                frames.push(frame);
+            }
+        }
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(
+            parse_settings(&frames[0].payload).unwrap(),
+            vec![(setting::INITIAL_WINDOW_SIZE, 6_291_456)]
+        );
+        assert_eq!(
+            frames[1],
+            RawFrame::new(kind::DATA, flags::END_STREAM, 3, &b"llp"[..])
+        );
+    }
+
+    #[test]
+    fn test_padding_and_priority_stripped() {
+        let frame = RawFrame::new(
+            kind::HEADERS,
+            flags::PADDED | flags::PRIORITY | flags::END_HEADERS,
+            1,
+            &b"\x02\x80\x00\x00\x00\xffblock\x00\x00"[..],
+        );
+        assert_eq!(&frame.body().unwrap()[..], b"block");
+
+        let bad = RawFrame::new(kind::DATA, flags::PADDED, 1, &b"\x09data"[..]);
+        assert!(bad.body().is_err());
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let mut decoder = FrameDecoder::default();
+        decoder.extend(b"GET / HTTP/1.1\r\n");
+        assert!(decoder.take_preface().is_err());
+
+        let mut decoder = FrameDecoder::new(16);
+        decoder.extend(&[0, 0, 17, 0, 0, 0, 0, 0, 1]);
+        assert!(decoder.decode().is_err());
+
+        assert!(parse_window_update(&[0, 0, 0, 0]).is_err());
+        assert!(parse_settings(&[0; 5]).is_err());
+    }
+}
@@ -0,0 +1,599 @@
+//! HPACK: сжатие заголовков HTTP/2 (RFC 7541)
+//!
+//! Кодировщик ведёт себя как у браузеров: известные заголовки
+//! ссылаются на статическую таблицу, остальные добавляются в
+//! динамическую, строки сжимаются кодом Хаффмана. Декодер понимает все
+//! представления, включая изменение размера таблицы.
+
+use bytes::{BufMut, BytesMut};
+use std::collections::{HashMap, VecDeque};
+use std::sync::OnceLock;
+
+use crate::error::{MimicryError, Result};
+
+/// Размер динамической таблицы по умолчанию (`SETTINGS_HEADER_TABLE_SIZE`)
+pub const DEFAULT_TABLE_SIZE: usize = 4096;
+
+/// Накладные расходы записи динамической таблицы (байт)
+const ENTRY_OVERHEAD: usize = 32;
+
+/// Максимальный размер декодированной строки
+const MAX_STRING: usize = 64 * 1024;
+
+/// Заголовок: имя (в нижнем регистре) и значение
+pub type Header = (String, String);
+
+/// Статическая таблица (RFC 7541, приложение A)
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// Коды Хаффмана (код, длина в битах) для байтов 0..=255 и EOS (RFC 7541, приложение B)
+#[rustfmt::skip]
+const HUFFMAN_CODES: [(u32, u8); 257] = [
+    (0x1ff8, 13), (0x7fffd8, 23), (0xfffffe2, 28), (0xfffffe3, 28),
+    (0xfffffe4, 28), (0xfffffe5, 28), (0xfffffe6, 28), (0xfffffe7, 28),
+    (0xfffffe8, 28), (0xffffea, 24), (0x3ffffffc, 30), (0xfffffe9, 28),
+    (0xfffffea, 28), (0x3ffffffd, 30), (0xfffffeb, 28), (0xfffffec, 28),
+    (0xfffffed, 28), (0xfffffee, 28), (0xfffffef, 28), (0xffffff0, 28),
+    (0xffffff1, 28), (0xffffff2, 28), (0x3ffffffe, 30), (0xffffff3, 28),
+    (0xffffff4, 28), (0xffffff5, 28), (0xffffff6, 28), (0xffffff7, 28),
+    (0xffffff8, 28), (0xffffff9, 28), (0xffffffa, 28), (0xffffffb, 28),
+    (0x14, 6), (0x3f8, 10), (0x3f9, 10), (0xffa, 12),
+    (0x1ff9, 13), (0x15, 6), (0xf8, 8), (0x7fa, 11),
+    (0x3fa, 10), (0x3fb, 10), (0xf9, 8), (0x7fb, 11),
+    (0xfa, 8), (0x16, 6), (0x17, 6), (0x18, 6),
+    (0x0, 5), (0x1, 5), (0x2, 5), (0x19, 6),
+    (0x1a, 6), (0x1b, 6), (0x1c, 6), (0x1d, 6),
+    (0x1e, 6), (0x1f, 6), (0x5c, 7), (0xfb, 8),
+    (0x7ffc, 15), (0x20, 6), (0xffb, 12), (0x3fc, 10),
+    (0x1ffa, 13), (0x21, 6), (0x5d, 7), (0x5e, 7),
+    (0x5f, 7), (0x60, 7), (0x61, 7), (0x62, 7),
+    (0x63, 7), (0x64, 7), (0x65, 7), (0x66, 7),
+    (0x67, 7), (0x68, 7), (0x69, 7), (0x6a, 7),
+    (0x6b, 7), (0x6c, 7), (0x6d, 7), (0x6e, 7),
+    (0x6f, 7), (0x70, 7), (0x71, 7), (0x72, 7),
+    (0xfc, 8), (0x73, 7), (0xfd, 8), (0x1ffb, 13),
+    (0x7fff0, 19), (0x1ffc, 13), (0x3ffc, 14), (0x22, 6),
+    (0x7ffd, 15), (0x3, 5), (0x23, 6), (0x4, 5),
+    (0x24, 6), (0x5, 5), (0x25, 6), (0x26, 6),
+    (0x27, 6), (0x6, 5), (0x74, 7), (0x75, 7),
+    (0x28, 6), (0x29, 6), (0x2a, 6), (0x7, 5),
+    (0x2b, 6), (0x76, 7), (0x2c, 6), (0x8, 5),
+    (0x9, 5), (0x2d, 6), (0x77, 7), (0x78, 7),
+    (0x79, 7), (0x7a, 7), (0x7b, 7), (0x7ffe, 15),
+    (0x7fc, 11), (0x3ffd, 14), (0x1ffd, 13), (0xffffffc, 28),
+    (0xfffe6, 20), (0x3fffd2, 22), (0xfffe7, 20), (0xfffe8, 20),
+    (0x3fffd3, 22), (0x3fffd4, 22), (0x3fffd5, 22), (0x7fffd9, 23),
+    (0x3fffd6, 22), (0x7fffda, 23), (0x7fffdb, 23), (0x7fffdc, 23),
+    (0x7fffdd, 23), (0x7fffde, 23), (0xffffeb, 24), (0x7fffdf, 23),
+    (0xffffec, 24), (0xffffed, 24), (0x3fffd7, 22), (0x7fffe0, 23),
+    (0xffffee, 24), (0x7fffe1, 23), (0x7fffe2, 23), (0x7fffe3, 23),
+    (0x7fffe4, 23), (0x1fffdc, 21), (0x3fffd8, 22), (0x7fffe5, 23),
+    (0x3fffd9, 22), (0x7fffe6, 23), (0x7fffe7, 23), (0xffffef, 24),
+    (0x3fffda, 22), (0x1fffdd, 21), (0xfffe9, 20), (0x3fffdb, 22),
+    (0x3fffdc, 22), (0x7fffe8, 23), (0x7fffe9, 23), (0x1fffde, 21),
+    (0x7fffea, 23), (0x3fffdd, 22), (0x3fffde, 22), (0xfffff0, 24),
+    (0x1fffdf, 21), (0x3fffdf, 22), (0x7fffeb, 23), (0x7fffec, 23),
+    (0x1fffe0, 21), (0x1fffe1, 21), (0x3fffe0, 22), (0x1fffe2, 21),
+    (0x7fffed, 23), (0x3fffe1, 22), (0x7fffee, 23), (0x7fffef, 23),
+    (0xfffea, 20), (0x3fffe2, 22), (0x3fffe3, 22), (0x3fffe4, 22),
+    (0x7ffff0, 23), (0x3fffe5, 22), (0x3fffe6, 22), (0x7ffff1, 23),
+    (0x3ffffe0, 26), (0x3ffffe1, 26), (0xfffeb, 20), (0x7fff1, 19),
+    (0x3fffe7, 22), (0x7ffff2, 23), (0x3fffe8, 22), (0x1ffffec, 25),
+    (0x3ffffe2, 26), (0x3ffffe3, 26), (0x3ffffe4, 26), (0x7ffffde, 27),
+    (0x7ffffdf, 27), (0x3ffffe5, 26), (0xfffff1, 24), (0x1ffffed, 25),
+    (0x7fff2, 19), (0x1fffe3, 21), (0x3ffffe6, 26), (0x7ffffe0, 27),
+    (0x7ffffe1, 27), (0x3ffffe7, 26), (0x7ffffe2, 27), (0xfffff2, 24),
+    (0x1fffe4, 21), (0x1fffe5, 21), (0x3ffffe8, 26), (0x3ffffe9, 26),
+    (0xffffffd, 28), (0x7ffffe3, 27), (0x7ffffe4, 27), (0x7ffffe5, 27),
+    (0xfffec, 20), (0xfffff3, 24), (0xfffed, 20), (0x1fffe6, 21),
+    (0x3fffe9, 22), (0x1fffe7, 21), (0x1fffe8, 21), (0x7ffff3, 23),
+    (0x3fffea, 22), (0x3fffeb, 22), (0x1ffffee, 25), (0x1ffffef, 25),
+    (0xfffff4, 24), (0xfffff5, 24), (0x3ffffea, 26), (0x7ffff4, 23),
+    (0x3ffffeb, 26), (0x7ffffe6, 27), (0x3ffffec, 26), (0x3ffffed, 26),
+    (0x7ffffe7, 27), (0x7ffffe8, 27), (0x7ffffe9, 27), (0x7ffffea, 27),
+    (0x7ffffeb, 27), (0xffffffe, 28), (0x7ffffec, 27), (0x7ffffed, 27),
+    (0x7ffffee, 27), (0x7ffffef, 27), (0x7fffff0, 27), (0x3ffffee, 26),
+    (0x3fffffff, 30),
+];
+
+/// Символ конца строки в коде Хаффмана
+const EOS: u16 = 256;
+
+/// Динамическая таблица: новые записи в начале
+#[derive(Debug)]
+struct DynamicTable {
+    entries: VecDeque<Header>,
+    size: usize,
+    max_size: usize,
+}
+
+impl DynamicTable {
+    fn new(max_size: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            size: 0,
+            max_size,
+        }
+    }
+
+    fn insert(&mut self, name: String, value: String) {
+        let size = name.len() + value.len() + ENTRY_OVERHEAD;
+        self.size += size;
+        self.entries.push_front((name, value));
+        self.evict();
+    }
+
+    fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.max_size {
+            match self.entries.pop_back() {
+                Some((name, value)) => self.size -= name.len() + value.len() + ENTRY_OVERHEAD,
+                None => break,
+            }
+        }
+    }
+
+    /// Запись по индексу HPACK (статическая таблица, затем динамическая)
+    fn get(&self, index: usize) -> Result<(&str, &str)> {
+        match index {
+            0 => Err(hpack_error("индекс 0")),
+            1..=61 => Ok(STATIC_TABLE[index - 1]),
+            _ => self
+                .entries
+                .get(index - 62)
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .ok_or_else(|| hpack_error(&format!("индекс {} вне таблицы", index))),
+        }
+    }
+
+    /// Индекс точного совпадения и индекс совпадения по имени
+    fn find(&self, name: &str, value: &str) -> (Option<usize>, Option<usize>) {
+        let mut name_index = None;
+        let tables = STATIC_TABLE
+            .iter()
+            .copied()
+            .chain(self.entries.iter().map(|(n, v)| (n.as_str(), v.as_str())));
+        for (i, (n, v)) in tables.enumerate() {
+            if n == name {
+                if v == value {
+                    return (Some(i + 1), name_index.or(Some(i + 1)));
+                }
+                name_index = name_index.or(Some(i + 1));
+            }
+        }
+        (None, name_index)
+    }
+}
+
+/// Кодировщик блока заголовков
+#[derive(Debug)]
+pub struct Encoder {
+    table: DynamicTable,
+    huffman: bool,
+    /// Новый размер таблицы, о котором ещё не сообщено декодеру
+    size_update: Option<usize>,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Encoder {
+    /// Кодировщик с таблицей по умолчанию и кодом Хаффмана
+    pub fn new() -> Self {
+        Self {
+            table: DynamicTable::new(DEFAULT_TABLE_SIZE),
+            huffman: true,
+            size_update: None,
+        }
+    }
+
+    /// Включить или выключить код Хаффмана для строк
+    pub fn with_huffman(mut self, enabled: bool) -> Self {
+        self.huffman = enabled;
+        self
+    }
+
+    /// Ограничить размер таблицы (`SETTINGS_HEADER_TABLE_SIZE` собеседника)
+    pub fn set_max_table_size(&mut self, size: usize) {
+        let size = size.min(DEFAULT_TABLE_SIZE);
+        if size != self.table.max_size {
+            self.table.set_max_size(size);
+            self.size_update = Some(size);
+        }
+    }
+
+    /// Закодировать заголовки в `out`
+    pub fn encode(&mut self, headers: &[Header], out: &mut BytesMut) {
+        if let Some(size) = self.size_update.take() {
+            encode_integer(out, 0x20, 5, size);
+        }
+        for (name, value) in headers {
+            match self.table.find(name, value) {
+                (Some(index), _) => encode_integer(out, 0x80, 7, index),
+                (None, name_index) => {
+                    // Литерал с добавлением в динамическую таблицу
+                    match name_index {
+                        Some(index) => encode_integer(out, 0x40, 6, index),
+                        None => {
+                            out.put_u8(0x40);
+                            self.encode_string(out, name);
+                        }
+                    }
+                    self.encode_string(out, value);
+                    self.table.insert(name.clone(), value.clone());
+                }
+            }
+        }
+    }
+
+    fn encode_string(&self, out: &mut BytesMut, value: &str) {
+        let encoded_len = huffman_len(value.as_bytes());
+        if self.huffman && encoded_len < value.len() {
+            encode_integer(out, 0x80, 7, encoded_len);
+            huffman_encode(out, value.as_bytes());
+        } else {
+            encode_integer(out, 0x00, 7, value.len());
+            out.put_slice(value.as_bytes());
+        }
+    }
+}
+
+/// Декодер блока заголовков
+#[derive(Debug)]
+pub struct Decoder {
+    table: DynamicTable,
+    /// Размер таблицы, объявленный собеседнику
+    max_size: usize,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_TABLE_SIZE)
+    }
+}
+
+impl Decoder {
+    /// Декодер с объявленным размером таблицы `max_size`
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            table: DynamicTable::new(max_size),
+            max_size,
+        }
+    }
+
+    /// Декодировать блок заголовков
+    pub fn decode(&mut self, mut block: &[u8]) -> Result<Vec<Header>> {
+        let mut headers = Vec::new();
+        while let Some(&first) = block.first() {
+            if first & 0x80 != 0 {
+                // Индексированное поле
+                let index = decode_integer(&mut block, 7)?;
+                let (name, value) = self.table.get(index)?;
+                headers.push((name.to_string(), value.to_string()));
+            } else if first & 0xe0 == 0x20 {
+                // Изменение размера динамической таблицы
+                let size = decode_integer(&mut block, 5)?;
+                if size > self.max_size {
+                    return Err(hpack_error(&format!(
+                        "размер таблицы {} больше {}",
+                        size, self.max_size
+                    )));
+                }
+                self.table.set_max_size(size);
+            } else {
+                // Литерал: с индексацией (01), без индексации (0000), никогда не индексировать (0001)
+                let (indexed, prefix) = if first & 0x40 != 0 {
+                    (true, 6)
+                } else {
+                    (false, 4)
+                };
+                let index = decode_integer(&mut block, prefix)?;
+                let name = match index {
+                    0 => decode_string(&mut block)?,
+                    _ => self.table.get(index)?.0.to_string(),
+                };
+                let value = decode_string(&mut block)?;
+                if indexed {
+                    self.table.insert(name.clone(), value.clone());
+                }
+                headers.push((name, value));
+            }
+        }
+        Ok(headers)
+    }
+}
+
+/// Значение псевдозаголовка или заголовка из списка
+pub fn find_header<'a>(headers: &'a [Header], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn hpack_error(message: &str) -> MimicryError {
+    MimicryError::ParseError(format!("HPACK: {}", message))
+}
+
+/// Записать целое с префиксом `prefix` бит; старшие биты первого байта — `flags`
+fn encode_integer(out: &mut BytesMut, flags: u8, prefix: u8, value: usize) {
+    let max = (1usize << prefix) - 1;
+    if value < max {
+        out.put_u8(flags | value as u8);
+        return;
+    }
+    out.put_u8(flags | max as u8);
+    let mut rest = value - max;
+    while rest >= 0x80 {
+        out.put_u8((rest & 0x7f) as u8 | 0x80);
+        rest >>= 7;
+    }
+    out.put_u8(rest as u8);
+}
+
+fn decode_integer(block: &mut &[u8], prefix: u8) -> Result<usize> {
+    let max = (1usize << prefix) - 1;
+    let (&first, rest) = block
+        .split_first()
+        .ok_or_else(|| hpack_error("обрыв целого"))?;
+    *block = rest;
+    let mut value = (first as usize) & max;
+    if value < max {
+        return Ok(value);
+    }
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = block
+            .split_first()
+            .ok_or_else(|| hpack_error("обрыв целого"))?;
+        *block = rest;
+        if shift > 28 {
+            return Err(hpack_error("слишком большое целое"));
+        }
+        value += ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+fn decode_string(block: &mut &[u8]) -> Result<String> {
+    let huffman = block.first().is_some_and(|b| b & 0x80 != 0);
+    let len = decode_integer(block, 7)?;
+    if len > block.len() || len > MAX_STRING {
+        return Err(hpack_error("обрыв строки"));
+    }
+    let (raw, rest) = block.split_at(len);
+    *block = rest;
+    let bytes = if huffman {
+        huffman_decode(raw)?
+    } else {
+        raw.to_vec()
+    };
+    String::from_utf8(bytes).map_err(|_| hpack_error("строка не UTF-8"))
+}
+
+/// Длина строки после кода Хаффмана (байт)
+fn huffman_len(data: &[u8]) -> usize {
+    let bits: usize = data
+        .iter()
+        .map(|&b| HUFFMAN_CODES[b as usize].1 as usize)
+        .sum();
+    bits.div_ceil(8)
+}
+
+fn huffman_encode(out: &mut BytesMut, data: &[u8]) {
+    let mut acc: u64 = 0;
+    let mut bits = 0u32;
+    for &byte in data {
+        let (code, len) = HUFFMAN_CODES[byte as usize];
+        acc = (acc << len) | code as u64;
+        bits += len as u32;
+        while bits >= 8 {
+            bits -= 8;
+            out.put_u8((acc >> bits) as u8);
+        }
+    }
+    if bits > 0 {
+        // Дополнение старшими битами EOS (единицами)
+        out.put_u8(((acc << (8 - bits)) as u8) | (0xff >> bits));
+    }
+}
+
+/// Таблица декодирования: (длина, код) -> символ
+fn huffman_table() -> &'static HashMap<(u8, u32), u16> {
+    static TABLE: OnceLock<HashMap<(u8, u32), u16>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        HUFFMAN_CODES
+            .iter()
+            .enumerate()
+            .map(|(symbol, &(code, len))| ((len, code), symbol as u16))
+            .collect()
+    })
+}
+
+fn huffman_decode(data: &[u8]) -> Result<Vec<u8>> {
+    let table = huffman_table();
+    let mut out = Vec::with_capacity(data.len() * 8 / 5);
+    let mut code = 0u32;
+    let mut len = 0u8;
+    for &byte in data {
+        for shift in (0..8).rev() {
+            code = (code << 1) | ((byte >> shift) & 1) as u32;
+            len += 1;
+            if len < 5 {
+                continue;
+            }
+            match table.get(&(len, code)) {
+                Some(&EOS) => return Err(hpack_error("EOS внутри строки")),
+                Some(&symbol) => {
+                    out.push(symbol as u8);
+                    code = 0;
+                    len = 0;
+                }
+                None if len >= 30 => return Err(hpack_error("неверный код Хаффмана")),
+                None => {}
+            }
+        }
+    }
+    // Остаток — не длиннее 7 бит и только единицы (начало EOS)
+    if len > 7 || code != (1u32 << len) - 1 {
+        return Err(hpack_error("неверное дополнение кода Хаффмана"));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(list: &[(&str, &str)]) -> Vec<Header> {
+        list.iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rfc_request_examples() {
+        // RFC 7541, C.4: запросы с кодом Хаффмана
+        let mut encoder = Encoder::new();
+        let mut out = BytesMut::new();
+        encoder.encode(
+            &headers(&[
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+            ]),
+            &mut out,
+        );
+        assert_eq!(hex::encode(&out), "828684418cf1e3c2e5f23a6ba0ab90f4ff");
+
+        out.clear();
+        encoder.encode(
+            &headers(&[
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+                ("cache-control", "no-cache"),
+            ]),
+            &mut out,
+        );
+        assert_eq!(hex::encode(&out), "828684be5886a8eb10649cbf");
+
+        let mut decoder = Decoder::default();
+        let first = hex::decode("828684418cf1e3c2e5f23a6ba0ab90f4ff").unwrap();
+        assert_eq!(
+            decoder.decode(&first).unwrap()[3],
+            (":authority".into(), "www.example.com".into())
+        );
+        let second = hex::decode("828684be5886a8eb10649cbf").unwrap();
+        assert_eq!(
+            decoder.decode(&second).unwrap()[4],
+            ("cache-control".into(), "no-cache".into())
+        );
+    }
+
+    #[test]
+    fn test_round_trip_with_eviction() {
+        let mut encoder = Encoder::new().with_huffman(false);
+        let mut decoder = Decoder::default();
+        encoder.set_max_table_size(256);
+
+        for i in 0..50 {
+            let list = headers(&[
+                (":status", "206"),
+                ("server", "nginx/1.20.2"),
+                ("x-vk-session", &format!("{:032x}", i)),
+                ("content-type", "video/mp2t"),
+            ]);
+            let mut out = BytesMut::new();
+            encoder.encode(&list, &mut out);
+            assert_eq!(decoder.decode(&out).unwrap(), list);
+        }
+        assert!(decoder.table.size <= 256);
+    }
+
+    #[test]
+    fn test_invalid_blocks() {
+        let mut decoder = Decoder::default();
+        assert!(decoder.decode(&[0x80]).is_err());
+        assert!(decoder.decode(&[0xff, 0x00]).is_err());
+        assert!(decoder.decode(&[0x40, 0x85, 0xff]).is_err());
+        // Изменение размера больше объявленного
+        assert!(decoder.decode(&[0x3f, 0xe1, 0x7f]).is_err());
+        // Дополнение нулями вместо единиц
+        assert!(huffman_decode(&[0x00]).is_err());
+    }
+}
@@ -0,0 +1,1115 @@
+//! HTTP/2 мимикрия
+//!
+//! Веб-клиенты VK и Яндекса работают по HTTP/2, поэтому маскировка только
+//! под HTTP/1.1 выглядит всё более странно. [`H2Session`] ведёт HTTP/2
+//! соединение поверх TLS с ALPN `h2`:
+//!
+//! - префейс клиента, обмен SETTINGS и WINDOW_UPDATE с параметрами
+//!   браузера ([`H2Settings`]) или nginx на стороне сервера;
+//! - заголовки запросов и ответов берутся из профиля мимикрии (его
+//!   HTTP/1.1 сообщения переводятся в псевдозаголовки) и сжимаются HPACK
+//!   ([`hpack`]);
+//! - LLP пакеты с префиксом длины `u32` (как в [`crate::stream`]) идут в
+//!   DATA кадрах нескольких одновременных потоков: сервер отвечает на GET
+//!   запросы клиента, клиент передаёт данные в теле POST запросов;
+//! - отправка соблюдает окна управления потоком собеседника, а принятые
+//!   данные подтверждаются WINDOW_UPDATE по мере чтения.
+//!
+//! Сессия не выполняет ввод-вывод: прочитанные байты передаются в
+//! [`H2Session::receive`], а готовые к записи — забираются через
+//! [`H2Session::take_output`].
+//!
+//! Каждый пакет целиком уходит в один поток и пишется без чередования с
+//! другими пакетами, поэтому порядок пакетов сохраняется.
+//!
+//! Транспорты выбирают HTTP/2, когда TLS слой согласовал ALPN `h2`
+//! ([`ALPN_H2`]); ввод-вывод поверх потока — [`write_message`],
+//! [`write_output`] и [`read_messages`].
+
+use bytes::{BufMut, Bytes, BytesMut};
+use llp_core::packet::MimicryProfile;
+use rand::rngs::OsRng;
+use rand::Rng;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::error::{MimicryError, Result};
+use crate::exchange::Role;
+use crate::profiles::{BoxedRng, Profile};
+use crate::registry;
+use crate::stream::{StreamDecoder, MAX_STREAM_PACKET};
+use crate::tls::Fingerprint;
+
+pub mod frame;
+pub mod hpack;
+
+pub use frame::{FrameDecoder, RawFrame};
+pub use hpack::Header;
+
+use frame::{error_code, flags, kind, setting, DEFAULT_MAX_FRAME_SIZE, DEFAULT_WINDOW_SIZE};
+
+/// Идентификатор HTTP/2 в ALPN
+pub const ALPN_H2: &[u8] = b"h2";
+
+/// Сколько GET запросов клиент держит открытыми для данных сервера
+const DOWNLOAD_STREAMS: usize = 2;
+
+/// Максимум одновременных POST запросов клиента с данными
+const UPLOAD_STREAMS: usize = 2;
+
+/// Заголовки HTTP/1.1, которых не бывает в HTTP/2 (RFC 9113, 8.2.2)
+const CONNECTION_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "te",
+    "content-length",
+];
+
+/// HTTP/2 параметры стороны соединения
+///
+/// Набор SETTINGS, приращение окна соединения, приоритет HEADERS и
+/// порядок псевдозаголовков отличают браузеры друг от друга так же, как
+/// ClientHello (отпечаток Akamai, [`Self::akamai`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct H2Settings {
+    /// Параметры SETTINGS в порядке отправки
+    pub settings: Vec<(u16, u32)>,
+    /// Приращение окна соединения после SETTINGS (0 — без WINDOW_UPDATE)
+    pub connection_window_increment: u32,
+    /// Порядок псевдозаголовков запроса
+    pub pseudo_order: [&'static str; 4],
+    /// Приоритет в HEADERS: exclusive и вес (0..=255 означает 1..=256)
+    pub priority: Option<(bool, u8)>,
+}
+
+impl H2Settings {
+    /// Chrome 120
+    pub fn chrome() -> Self {
+        Self {
+            settings: vec![
+                (setting::HEADER_TABLE_SIZE, 65_536),
+                (setting::ENABLE_PUSH, 0),
+                (setting::INITIAL_WINDOW_SIZE, 6_291_456),
+                (setting::MAX_HEADER_LIST_SIZE, 262_144),
+            ],
+            connection_window_increment: 15_663_105,
+            pseudo_order: [":method", ":authority", ":scheme", ":path"],
+            priority: Some((true, 255)),
+        }
+    }
+
+    /// Firefox 121
+    pub fn firefox() -> Self {
+        Self {
+            settings: vec![
+                (setting::HEADER_TABLE_SIZE, 65_536),
+                (setting::INITIAL_WINDOW_SIZE, 131_072),
+                (setting::MAX_FRAME_SIZE, 16_384),
+            ],
+            connection_window_increment: 12_517_377,
+            pseudo_order: [":method", ":path", ":authority", ":scheme"],
+            priority: None,
+        }
+    }
+
+    /// nginx (сторона сервера)
+    pub fn nginx() -> Self {
+        Self {
+            settings: vec![
+                (setting::MAX_CONCURRENT_STREAMS, 128),
+                (setting::INITIAL_WINDOW_SIZE, 65_536),
+                (setting::MAX_FRAME_SIZE, 16_777_215),
+            ],
+            connection_window_increment: 2_147_418_112,
+            pseudo_order: [":method", ":path", ":scheme", ":authority"],
+            priority: None,
+        }
+    }
+
+    /// Параметры браузера с отпечатком ClientHello `fingerprint`
+    ///
+    /// Без браузерного отпечатка используются параметры Chrome.
+    pub fn for_fingerprint(fingerprint: Fingerprint) -> Self {
+        match fingerprint {
+            Fingerprint::Firefox121 => Self::firefox(),
+            Fingerprint::Rustls | Fingerprint::Chrome120 => Self::chrome(),
+        }
+    }
+
+    /// Значение параметра SETTINGS
+    pub fn get(&self, id: u16) -> Option<u32> {
+        self.settings
+            .iter()
+            .find(|(setting, _)| *setting == id)
+            .map(|(_, value)| *value)
+    }
+
+    /// Начальное окно потока
+    pub fn initial_window_size(&self) -> u32 {
+        self.get(setting::INITIAL_WINDOW_SIZE)
+            .unwrap_or(DEFAULT_WINDOW_SIZE)
+    }
+
+    /// Окно соединения после начального WINDOW_UPDATE
+    pub fn connection_window(&self) -> u32 {
+        DEFAULT_WINDOW_SIZE + self.connection_window_increment
+    }
+
+    /// Отпечаток Akamai: `SETTINGS|WINDOW_UPDATE|PRIORITY|псевдозаголовки`
+    pub fn akamai(&self) -> String {
+        let settings: Vec<String> = self
+            .settings
+            .iter()
+            .map(|(id, value)| format!("{}:{}", id, value))
+            .collect();
+        let pseudo: Vec<&str> = self.pseudo_order.iter().map(|name| &name[1..2]).collect();
+        format!(
+            "{}|{}|0|{}",
+            settings.join(";"),
+            self.connection_window_increment,
+            pseudo.join(",")
+        )
+    }
+}
+
+/// Назначение потока
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StreamKind {
+    /// GET запрос клиента, в ответе на который сервер передаёт данные
+    Download,
+    /// POST запрос клиента с данными в теле
+    Upload,
+}
+
+/// Состояние потока HTTP/2
+#[derive(Debug)]
+struct H2Stream {
+    kind: StreamKind,
+    /// Окно отправки собеседника
+    send_window: i64,
+    /// Принято байт после последнего WINDOW_UPDATE потока
+    unacked: u32,
+    /// Можно ли ещё отправлять (END_STREAM не отправлен)
+    local_open: bool,
+    /// Может ли собеседник ещё отправлять (END_STREAM не получен)
+    remote_open: bool,
+    /// Сервер: заголовок ответа отправлен
+    response_sent: bool,
+    /// Сколько байт данных ещё отправить до END_STREAM
+    budget: usize,
+    /// Сборка пакетов из DATA кадров
+    decoder: StreamDecoder,
+}
+
+/// HTTP/2 соединение, несущее LLP пакеты
+pub struct H2Session {
+    profile: Box<dyn Profile>,
+    role: Role,
+    rng: BoxedRng,
+    local: H2Settings,
+    encoder: hpack::Encoder,
+    decoder: hpack::Decoder,
+    frames: FrameDecoder,
+    /// Сервер ещё ждёт префейс клиента
+    preface_pending: bool,
+    /// Параметры собеседника
+    peer_initial_window: u32,
+    peer_max_frame: u32,
+    peer_max_streams: u32,
+    /// Окно отправки соединения
+    send_window: i64,
+    /// Принято байт после последнего WINDOW_UPDATE соединения
+    recv_unacked: u32,
+    streams: BTreeMap<u32, H2Stream>,
+    /// Клиент: идентификатор следующего потока
+    next_stream_id: u32,
+    /// Наибольший идентификатор потока собеседника
+    last_peer_stream: u32,
+    /// Код GOAWAY собеседника (None — соединение открыто)
+    goaway: Option<u32>,
+    /// Пакеты с префиксом длины, ожидающие отправки
+    queue: VecDeque<Bytes>,
+    /// Поток, в который пишется пакет в начале очереди
+    current: Option<u32>,
+    chunk_counter: u64,
+    out: BytesMut,
+}
+
+impl fmt::Debug for H2Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("H2Session")
+            .field("role", &self.role)
+            .field("streams", &self.streams.len())
+            .field("queued", &self.queue.len())
+            .field("out", &self.out.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl H2Session {
+    /// Сессия для зарегистрированного профиля
+    pub fn try_new(profile: MimicryProfile, role: Role) -> Result<Self> {
+        let profile = registry::global()
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .create(profile.to_u16())?;
+        Self::from_profile(profile, role)
+    }
+
+    /// Сессия поверх готового экземпляра профиля
+    pub fn from_profile(profile: Box<dyn Profile>, role: Role) -> Result<Self> {
+        Self::with_rng(profile, role, Box::new(OsRng))
+    }
+
+    /// Сессия с заданным источником случайности (выбор потоков и их длины)
+    pub fn with_rng(profile: Box<dyn Profile>, role: Role, rng: BoxedRng) -> Result<Self> {
+        let local = match role {
+            Role::Client => H2Settings::for_fingerprint(profile.tls_fingerprint()),
+            Role::Server => H2Settings::nginx(),
+            Role::Symmetric => {
+                return Err(MimicryError::WrapError(
+                    "HTTP/2 требует роль клиента или сервера".to_string(),
+                ))
+            }
+        };
+        let max_frame = local
+            .get(setting::MAX_FRAME_SIZE)
+            .unwrap_or(DEFAULT_MAX_FRAME_SIZE);
+        let table_size = local
+            .get(setting::HEADER_TABLE_SIZE)
+            .map_or(hpack::DEFAULT_TABLE_SIZE, |size| size as usize);
+
+        let mut session = Self {
+            profile,
+            role,
+            rng,
+            encoder: hpack::Encoder::new(),
+            decoder: hpack::Decoder::new(table_size),
+            frames: FrameDecoder::new(max_frame),
+            preface_pending: role == Role::Server,
+            peer_initial_window: DEFAULT_WINDOW_SIZE,
+            peer_max_frame: DEFAULT_MAX_FRAME_SIZE,
+            peer_max_streams: u32::MAX,
+            send_window: DEFAULT_WINDOW_SIZE as i64,
+            recv_unacked: 0,
+            streams: BTreeMap::new(),
+            next_stream_id: 1,
+            last_peer_stream: 0,
+            goaway: None,
+            queue: VecDeque::new(),
+            current: None,
+            chunk_counter: 0,
+            out: BytesMut::new(),
+            local,
+        };
+
+        if role == Role::Client {
+            session.out.put_slice(frame::PREFACE);
+        }
+        let settings = frame::settings_payload(&session.local.settings);
+        frame::encode_frame(&mut session.out, kind::SETTINGS, 0, 0, &settings);
+        if session.local.connection_window_increment > 0 {
+            let increment = session.local.connection_window_increment.to_be_bytes();
+            frame::encode_frame(&mut session.out, kind::WINDOW_UPDATE, 0, 0, &increment);
+        }
+        session.open_downloads()?;
+        Ok(session)
+    }
+
+    /// Роль в соединении
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Собственные HTTP/2 параметры
+    pub fn settings(&self) -> &H2Settings {
+        &self.local
+    }
+
+    /// Поставить LLP пакет в очередь отправки
+    ///
+    /// Пакет уходит, как только позволят окна управления потоком и
+    /// (на сервере) найдётся открытый запрос клиента.
+    pub fn send(&mut self, packet: &[u8]) -> Result<()> {
+        if packet.len() > MAX_STREAM_PACKET {
+            return Err(MimicryError::WrapError(format!(
+                "пакет {} байт больше {} для HTTP/2",
+                packet.len(),
+                MAX_STREAM_PACKET
+            )));
+        }
+        let mut prefixed = BytesMut::with_capacity(4 + packet.len());
+        prefixed.put_u32(packet.len() as u32);
+        prefixed.put_slice(packet);
+        self.queue.push_back(prefixed.freeze());
+        self.flush()
+    }
+
+    /// Обработать прочитанные байты и вернуть принятые LLP пакеты
+    ///
+    /// Пакеты, пришедшие до GOAWAY собеседника, возвращаются (например,
+    /// alert перед закрытием); без них GOAWAY — ошибка.
+    pub fn receive(&mut self, data: &[u8]) -> Result<Vec<Bytes>> {
+        self.frames.extend(data);
+        if self.preface_pending {
+            if !self.frames.take_preface()? {
+                return Ok(Vec::new());
+            }
+            self.preface_pending = false;
+        }
+
+        let mut packets = Vec::new();
+        while self.goaway.is_none() {
+            let Some(frame) = self.frames.decode()? else {
+                break;
+            };
+            self.process(frame, &mut packets)?;
+        }
+        if let Some(code) = self.goaway {
+            if packets.is_empty() {
+                return Err(MimicryError::UnwrapError(format!(
+                    "собеседник закрыл HTTP/2 соединение (GOAWAY, код {})",
+                    code
+                )));
+            }
+            return Ok(packets);
+        }
+
+        self.streams
+            .retain(|_, stream| stream.local_open || stream.remote_open);
+        self.open_downloads()?;
+        self.flush()?;
+        Ok(packets)
+    }
+
+    /// Забрать байты, готовые к записи в соединение
+    pub fn take_output(&mut self) -> Bytes {
+        self.out.split().freeze()
+    }
+
+    /// Объём данных, ждущих окна или запроса клиента (байт)
+    pub fn pending(&self) -> usize {
+        self.queue.iter().map(Bytes::len).sum()
+    }
+
+    /// Количество открытых потоков
+    pub fn open_streams(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// Закрыть соединение кадром GOAWAY
+    pub fn close(&mut self) {
+        let mut payload = BytesMut::with_capacity(8);
+        payload.put_u32(self.last_peer_stream);
+        payload.put_u32(error_code::NO_ERROR);
+        frame::encode_frame(&mut self.out, kind::GOAWAY, 0, 0, &payload);
+    }
+
+    /// Обработать один кадр
+    fn process(&mut self, frame: RawFrame, packets: &mut Vec<Bytes>) -> Result<()> {
+        match frame.kind {
+            kind::SETTINGS => self.on_settings(&frame),
+            kind::WINDOW_UPDATE => {
+                let increment = frame::parse_window_update(&frame.payload)? as i64;
+                match frame.stream_id {
+                    0 => self.send_window += increment,
+                    id => {
+                        if let Some(stream) = self.streams.get_mut(&id) {
+                            stream.send_window += increment;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            kind::PING if !frame.has_flag(flags::ACK) => {
+                frame::encode_frame(&mut self.out, kind::PING, flags::ACK, 0, &frame.payload);
+                Ok(())
+            }
+            kind::HEADERS => self.on_headers(&frame),
+            kind::DATA => self.on_data(&frame, packets),
+            kind::RST_STREAM => {
+                if self.current == Some(frame.stream_id) {
+                    return Err(protocol_error("поток сброшен посреди пакета"));
+                }
+                self.streams.remove(&frame.stream_id);
+                Ok(())
+            }
+            kind::GOAWAY => {
+                let code = frame.payload.get(4..8).map_or(0, |code| {
+                    u32::from_be_bytes([code[0], code[1], code[2], code[3]])
+                });
+                self.goaway = Some(code);
+                Ok(())
+            }
+            kind::PUSH_PROMISE | kind::CONTINUATION => Err(protocol_error(&format!(
+                "неожиданный кадр типа {}",
+                frame.kind
+            ))),
+            // PRIORITY, PING ACK и неизвестные типы кадров игнорируются
+            _ => Ok(()),
+        }
+    }
+
+    fn on_settings(&mut self, frame: &RawFrame) -> Result<()> {
+        if frame.has_flag(flags::ACK) {
+            return Ok(());
+        }
+        for (id, value) in frame::parse_settings(&frame.payload)? {
+            match id {
+                setting::HEADER_TABLE_SIZE => self.encoder.set_max_table_size(value as usize),
+                setting::MAX_CONCURRENT_STREAMS => self.peer_max_streams = value,
+                setting::INITIAL_WINDOW_SIZE => {
+                    if value > frame::MAX_WINDOW_SIZE {
+                        return Err(protocol_error("INITIAL_WINDOW_SIZE больше 2^31-1"));
+                    }
+                    let delta = value as i64 - self.peer_initial_window as i64;
+                    for stream in self.streams.values_mut() {
+                        stream.send_window += delta;
+                    }
+                    self.peer_initial_window = value;
+                }
+                setting::MAX_FRAME_SIZE => {
+                    if !(DEFAULT_MAX_FRAME_SIZE..=16_777_215).contains(&value) {
+                        return Err(protocol_error("неверный MAX_FRAME_SIZE"));
+                    }
+                    self.peer_max_frame = value;
+                }
+                _ => {}
+            }
+        }
+        frame::encode_frame(&mut self.out, kind::SETTINGS, flags::ACK, 0, &[]);
+        Ok(())
+    }
+
+    fn on_headers(&mut self, frame: &RawFrame) -> Result<()> {
+        if !frame.has_flag(flags::END_HEADERS) {
+            return Err(protocol_error("CONTINUATION не поддерживается"));
+        }
+        let headers = self.decoder.decode(&frame.body()?)?;
+        let end_stream = frame.has_flag(flags::END_STREAM);
+        let id = frame.stream_id;
+
+        match self.role {
+            Role::Server => {
+                if id.is_multiple_of(2) || id <= self.last_peer_stream {
+                    return Err(protocol_error(&format!("неверный поток клиента {}", id)));
+                }
+                self.last_peer_stream = id;
+                // GET без тела ждёт ответа с данными, POST несёт данные клиента
+                let kind = match hpack::find_header(&headers, ":method") {
+                    Some("GET") if end_stream => StreamKind::Download,
+                    Some(_) => StreamKind::Upload,
+                    None => return Err(protocol_error("запрос без :method")),
+                };
+                let budget = self.profile.recommended_chunk_size();
+                self.streams.insert(
+                    id,
+                    H2Stream::new(kind, self.peer_initial_window, !end_stream, budget),
+                );
+                if end_stream && kind == StreamKind::Upload {
+                    self.finish_upload(id)?;
+                }
+            }
+            _ => {
+                let status = hpack::find_header(&headers, ":status")
+                    .ok_or_else(|| protocol_error("ответ без :status"))?;
+                if !status.starts_with('2') {
+                    return Err(MimicryError::UnwrapError(format!(
+                        "HTTP/2 ответ со статусом {}",
+                        status
+                    )));
+                }
+                if let (Some(stream), true) = (self.streams.get_mut(&id), end_stream) {
+                    stream.remote_open = false;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn on_data(&mut self, frame: &RawFrame, packets: &mut Vec<Bytes>) -> Result<()> {
+        let id = frame.stream_id;
+        let len = frame.payload.len() as u32;
+        let body = frame.body()?;
+        let end_stream = frame.has_flag(flags::END_STREAM);
+
+        // Подтверждение окна соединения, когда прочитана половина
+        self.recv_unacked += len;
+        if self.recv_unacked >= self.local.connection_window() / 2 {
+            let increment = std::mem::take(&mut self.recv_unacked);
+            frame::encode_frame(
+                &mut self.out,
+                kind::WINDOW_UPDATE,
+                0,
+                0,
+                &increment.to_be_bytes(),
+            );
+        }
+
+        let initial_window = self.local.initial_window_size();
+        let stream = self
+            .streams
+            .get_mut(&id)
+            .filter(|stream| stream.remote_open)
+            .ok_or_else(|| protocol_error(&format!("DATA в закрытом потоке {}", id)))?;
+
+        stream.decoder.extend(&body);
+        while let Some(packet) = stream.decoder.next_packet()? {
+            packets.push(packet);
+        }
+
+        if end_stream {
+            if stream.decoder.has_partial() {
+                return Err(MimicryError::UnwrapError(
+                    "поток закрыт посреди пакета".to_string(),
+                ));
+            }
+            stream.remote_open = false;
+            if self.role == Role::Server && stream.kind == StreamKind::Upload {
+                return self.finish_upload(id);
+            }
+            return Ok(());
+        }
+
+        stream.unacked += len;
+        if stream.unacked >= initial_window / 2 {
+            let increment = std::mem::take(&mut stream.unacked);
+            frame::encode_frame(
+                &mut self.out,
+                kind::WINDOW_UPDATE,
+                0,
+                id,
+                &increment.to_be_bytes(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Сервер: ответить на завершённый POST запрос клиента
+    fn finish_upload(&mut self, id: u32) -> Result<()> {
+        let message = self.profile.wrap(&[])?;
+        let mut headers = response_headers(&message)?;
+        headers.retain(|(name, _)| !name.starts_with("content-"));
+        if let Some(status) = headers.first_mut() {
+            status.1 = "204".to_string();
+        }
+        self.write_headers(id, &headers, true, None);
+        if let Some(stream) = self.streams.get_mut(&id) {
+            stream.local_open = false;
+        }
+        Ok(())
+    }
+
+    /// Клиент: держать открытыми GET запросы для данных сервера
+    fn open_downloads(&mut self) -> Result<()> {
+        if self.role != Role::Client {
+            return Ok(());
+        }
+        let waiting = self
+            .streams
+            .values()
+            .filter(|stream| stream.kind == StreamKind::Download && stream.remote_open)
+            .count();
+        for _ in waiting..DOWNLOAD_STREAMS {
+            if self.streams.len() >= self.peer_max_streams as usize {
+                break;
+            }
+            let request = self.profile.generate_request(self.chunk_counter)?;
+            self.open_stream(StreamKind::Download, &request)?;
+        }
+        Ok(())
+    }
+
+    /// Клиент: открыть поток запросом из HTTP/1.1 сообщения профиля
+    fn open_stream(&mut self, kind: StreamKind, request: &[u8]) -> Result<u32> {
+        let id = self.next_stream_id;
+        self.next_stream_id += 2;
+        self.chunk_counter += 1;
+
+        let headers = request_headers(request, &self.local.pseudo_order)?;
+        let end_stream = kind == StreamKind::Download;
+        let priority = self.local.priority;
+        self.write_headers(id, &headers, end_stream, priority);
+
+        let budget = self.profile.recommended_chunk_size();
+        let mut stream = H2Stream::new(kind, self.peer_initial_window, true, budget);
+        stream.local_open = !end_stream;
+        self.streams.insert(id, stream);
+        Ok(id)
+    }
+
+    /// Записать HEADERS с блоком HPACK
+    fn write_headers(
+        &mut self,
+        id: u32,
+        headers: &[Header],
+        end_stream: bool,
+        priority: Option<(bool, u8)>,
+    ) {
+        let mut payload = BytesMut::new();
+        let mut frame_flags = flags::END_HEADERS;
+        if let Some((exclusive, weight)) = priority {
+            payload.put_u32(if exclusive { 1 << 31 } else { 0 });
+            payload.put_u8(weight);
+            frame_flags |= flags::PRIORITY;
+        }
+        if end_stream {
+            frame_flags |= flags::END_STREAM;
+        }
+        self.encoder.encode(headers, &mut payload);
+        frame::encode_frame(&mut self.out, kind::HEADERS, frame_flags, id, &payload);
+    }
+
+    /// Отправить пакеты из очереди, пока позволяют окна
+    fn flush(&mut self) -> Result<()> {
+        while let Some(mut packet) = self.queue.pop_front() {
+            let id = match self.current {
+                Some(id) => id,
+                None => match self.pick_stream()? {
+                    Some(id) => id,
+                    None => {
+                        self.queue.push_front(packet);
+                        return Ok(());
+                    }
+                },
+            };
+            self.current = Some(id);
+
+            let max_frame = self.peer_max_frame as i64;
+            let stream = self
+                .streams
+                .get_mut(&id)
+                .ok_or_else(|| protocol_error("поток отправки закрыт"))?;
+            while !packet.is_empty() {
+                let window = self.send_window.min(stream.send_window).min(max_frame);
+                if window <= 0 {
+                    self.queue.push_front(packet);
+                    return Ok(());
+                }
+                let chunk = packet.split_to(packet.len().min(window as usize));
+                frame::encode_frame(&mut self.out, kind::DATA, 0, id, &chunk);
+                self.send_window -= chunk.len() as i64;
+                stream.send_window -= chunk.len() as i64;
+                stream.budget = stream.budget.saturating_sub(chunk.len());
+            }
+            self.current = None;
+
+            // Объём ответа (или тела POST) исчерпан — поток завершается
+            if stream.budget == 0 {
+                frame::encode_frame(&mut self.out, kind::DATA, flags::END_STREAM, id, &[]);
+                stream.local_open = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Выбрать поток для следующего пакета
+    fn pick_stream(&mut self) -> Result<Option<u32>> {
+        let wanted = match self.role {
+            Role::Client => StreamKind::Upload,
+            _ => StreamKind::Download,
+        };
+        let mut candidates: Vec<u32> = self
+            .streams
+            .iter()
+            .filter(|(_, stream)| stream.kind == wanted && stream.local_open)
+            .map(|(id, _)| *id)
+            .collect();
+
+        if self.role == Role::Client
+            && candidates.len() < UPLOAD_STREAMS
+            && self.streams.len() < self.peer_max_streams as usize
+        {
+            let request = self.profile.wrap_request(&[], self.chunk_counter)?;
+            candidates.push(self.open_stream(StreamKind::Upload, &request)?);
+        }
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+
+        let id = candidates[self.rng.gen_range(0..candidates.len())];
+        if self.role == Role::Server && !self.streams[&id].response_sent {
+            let head = match self.profile.stream_head() {
+                Some(head) => head,
+                None => self.profile.wrap(&[])?,
+            };
+            let headers = response_headers(&head)?;
+            self.write_headers(id, &headers, false, None);
+            if let Some(stream) = self.streams.get_mut(&id) {
+                stream.response_sent = true;
+            }
+        }
+        Ok(Some(id))
+    }
+}
+
+impl H2Stream {
+    fn new(kind: StreamKind, send_window: u32, remote_open: bool, budget: usize) -> Self {
+        Self {
+            kind,
+            send_window: send_window as i64,
+            unacked: 0,
+            local_open: true,
+            remote_open,
+            response_sent: false,
+            budget: budget.max(1),
+            decoder: StreamDecoder::new(),
+        }
+    }
+}
+
+fn protocol_error(message: &str) -> MimicryError {
+    MimicryError::ParseError(format!("HTTP/2: {}", message))
+}
+
+/// Заголовки HTTP/2 запроса из HTTP/1.1 запроса профиля
+///
+/// `Host` становится `:authority`, имена приводятся к нижнему регистру,
+/// заголовки соединения HTTP/1.1 отбрасываются.
+pub fn request_headers(message: &[u8], pseudo_order: &[&str; 4]) -> Result<Vec<Header>> {
+    let mut parsed = [httparse::EMPTY_HEADER; 64];
+    let mut request = httparse::Request::new(&mut parsed);
+    if request
+        .parse(message)
+        .map_err(|e| MimicryError::ParseError(format!("HTTP parse error: {:?}", e)))?
+        .is_partial()
+    {
+        return Err(MimicryError::ParseError(
+            "Incomplete HTTP request".to_string(),
+        ));
+    }
+
+    let authority = request
+        .headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case("host"))
+        .map(|h| String::from_utf8_lossy(h.value).into_owned())
+        .unwrap_or_default();
+    let mut headers: Vec<Header> = pseudo_order
+        .iter()
+        .map(|name| {
+            let value = match *name {
+                ":method" => request.method.unwrap_or("GET").to_string(),
+                ":authority" => authority.clone(),
+                ":scheme" => "https".to_string(),
+                _ => request.path.unwrap_or("/").to_string(),
+            };
+            (name.to_string(), value)
+        })
+        .collect();
+    headers.extend(regular_headers(request.headers));
+    Ok(headers)
+}
+
+/// Заголовки HTTP/2 ответа из HTTP/1.1 ответа профиля
+pub fn response_headers(message: &[u8]) -> Result<Vec<Header>> {
+    let mut parsed = [httparse::EMPTY_HEADER; 64];
+    let mut response = httparse::Response::new(&mut parsed);
+    if response
+        .parse(message)
+        .map_err(|e| MimicryError::ParseError(format!("HTTP parse error: {:?}", e)))?
+        .is_partial()
+    {
+        return Err(MimicryError::ParseError(
+            "Incomplete HTTP response".to_string(),
+        ));
+    }
+
+    let status = response.code.unwrap_or(200).to_string();
+    let mut headers = vec![(":status".to_string(), status)];
+    headers.extend(regular_headers(response.headers));
+    Ok(headers)
+}
+
+/// Отправить LLP пакет вместе с накопленными кадрами соединения
+pub async fn write_message<W>(writer: &mut W, session: &mut H2Session, packet: &[u8]) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    session.send(packet)?;
+    write_output(writer, session).await
+}
+
+/// Записать кадры, накопленные сессией: SETTINGS, подтверждения окон,
+/// новые запросы и DATA
+pub async fn write_output<W>(writer: &mut W, session: &mut H2Session) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let output = session.take_output();
+    if !output.is_empty() {
+        writer.write_all(&output).await?;
+        writer.flush().await?;
+    }
+    Ok(())
+}
+
+/// Прочитать хотя бы один LLP пакет
+///
+/// Служебные кадры, которые сессия отправляет в ответ (SETTINGS ACK,
+/// WINDOW_UPDATE, PING ACK), записываются сразу. Конец потока — ошибка
+/// `UnexpectedEof`.
+pub async fn read_messages<S>(stream: &mut S, session: &mut H2Session) -> Result<Vec<Bytes>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut buf = BytesMut::with_capacity(16 * 1024);
+    loop {
+        buf.clear();
+        if stream.read_buf(&mut buf).await? == 0 {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        let packets = session.receive(&buf)?;
+        write_output(stream, session).await?;
+        if !packets.is_empty() {
+            return Ok(packets);
+        }
+    }
+}
+
+fn regular_headers<'a>(headers: &'a [httparse::Header<'a>]) -> impl Iterator<Item = Header> + 'a {
+    headers
+        .iter()
+        .map(|h| {
+            (
+                h.name.to_ascii_lowercase(),
+                String::from_utf8_lossy(h.value).into_owned(),
+            )
+        })
+        .filter(|(name, _)| !CONNECTION_HEADERS.contains(&name.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn session(profile: MimicryProfile, role: Role, seed: u64) -> H2Session {
+        let profile = registry::global()
+            .read()
+            .unwrap()
+            .create(profile.to_u16())
+            .unwrap();
+        H2Session::with_rng(profile, role, Box::new(StdRng::seed_from_u64(seed))).unwrap()
+    }
+
+    /// Передавать вывод сторон друг другу, пока обмен не затихнет
+    fn pump(
+        client: &mut H2Session,
+        server: &mut H2Session,
+    ) -> (Vec<Bytes>, Vec<Bytes>, Vec<RawFrame>) {
+        let (mut to_client, mut to_server, mut frames) = (Vec::new(), Vec::new(), Vec::new());
+        let mut inspect = FrameDecoder::new(16_777_215);
+        loop {
+            let from_client = client.take_output();
+            let from_server = server.take_output();
+            if from_client.is_empty() && from_server.is_empty() {
+                return (to_client, to_server, frames);
+            }
+            inspect.extend(&from_server);
+            while let Some(frame) = inspect.decode().unwrap() {
+                frames.push(frame);
+            }
+            to_server.extend(server.receive(&from_client).unwrap());
+            to_client.extend(client.receive(&from_server).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_chrome_preface_and_headers() {
+        let mut client = session(MimicryProfile::VkVideo, Role::Client, 1);
+        assert_eq!(
+            client.settings().akamai(),
+            "1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p"
+        );
+
+        let output = client.take_output();
+        assert!(output.starts_with(frame::PREFACE));
+        let mut frames = FrameDecoder::default();
+        frames.extend(&output[frame::PREFACE.len()..]);
+
+        let settings = frames.decode().unwrap().unwrap();
+        assert_eq!(settings.kind, kind::SETTINGS);
+        assert_eq!(
+            frame::parse_settings(&settings.payload).unwrap(),
+            H2Settings::chrome().settings
+        );
+        let update = frames.decode().unwrap().unwrap();
+        assert_eq!(
+            frame::parse_window_update(&update.payload).unwrap(),
+            15_663_105
+        );
+
+        // Два GET запроса с заголовками профиля
+        let mut decoder = hpack::Decoder::new(65_536);
+        for id in [1, 3] {
+            let headers = frames.decode().unwrap().unwrap();
+            assert_eq!((headers.kind, headers.stream_id), (kind::HEADERS, id));
+            assert!(headers.has_flag(flags::END_STREAM));
+            assert!(headers.has_flag(flags::PRIORITY));
+            let list = decoder.decode(&headers.body().unwrap()).unwrap();
+            let names: Vec<&str> = list.iter().take(4).map(|(n, _)| n.as_str()).collect();
+            assert_eq!(names, [":method", ":authority", ":scheme", ":path"]);
+            assert_eq!(hpack::find_header(&list, ":authority"), Some("vkvideo.ru"));
+            assert!(hpack::find_header(&list, ":path")
+                .unwrap()
+                .starts_with("/video/chunk_"));
+            assert!(hpack::find_header(&list, "connection").is_none());
+            assert!(hpack::find_header(&list, "user-agent").is_some());
+        }
+        assert!(frames.decode().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_packets_across_concurrent_streams() {
+        let mut client = session(MimicryProfile::VkVideo, Role::Client, 2);
+        let mut server = session(MimicryProfile::VkVideo, Role::Server, 3);
+        let (_, _, handshake) = pump(&mut client, &mut server);
+        assert!(handshake
+            .iter()
+            .any(|f| f.kind == kind::SETTINGS && f.has_flag(flags::ACK)));
+
+        let up: Vec<Vec<u8>> = (0..40u32)
+            .map(|i| vec![i as u8; 100 + i as usize * 300])
+            .collect();
+        let down: Vec<Vec<u8>> = (0..40u32)
+            .map(|i| vec![!i as u8; 9000 - i as usize * 200])
+            .collect();
+        for (a, b) in up.iter().zip(&down) {
+            client.send(a).unwrap();
+            server.send(b).unwrap();
+        }
+        let (to_client, to_server, frames) = pump(&mut client, &mut server);
+
+        assert_eq!(to_server, up);
+        assert_eq!(to_client, down);
+        assert_eq!(client.pending() + server.pending(), 0);
+
+        // Ответы сервера: заголовки профиля и DATA в нескольких потоках
+        let data_streams: std::collections::BTreeSet<u32> = frames
+            .iter()
+            .filter(|f| f.kind == kind::DATA && !f.payload.is_empty())
+            .map(|f| f.stream_id)
+            .collect();
+        assert!(data_streams.len() >= 2);
+        assert!(frames.iter().all(|f| f.payload.len() <= 16_384));
+    }
+
+    #[test]
+    fn test_flow_control_blocks_sender() {
+        let mut client = session(MimicryProfile::YandexMusic, Role::Client, 4);
+        let mut server = session(MimicryProfile::YandexMusic, Role::Server, 5);
+
+        // Сервер молчит: клиент знает только окно по умолчанию (65535 на поток)
+        let packets: Vec<Vec<u8>> = (0..40u8).map(|i| vec![i; 10_000]).collect();
+        for packet in &packets {
+            client.send(packet).unwrap();
+        }
+        let mut frames = FrameDecoder::default();
+        let output = client.take_output();
+        frames.extend(&output[frame::PREFACE.len()..]);
+        let mut sent = 0;
+        while let Some(frame) = frames.decode().unwrap() {
+            if frame.kind == kind::DATA {
+                sent += frame.payload.len();
+            }
+        }
+        assert!(sent <= DEFAULT_WINDOW_SIZE as usize);
+        assert!(client.pending() > 0);
+
+        // WINDOW_UPDATE сервера открывает окна, и очередь уходит полностью
+        let mut received = server.receive(&output).unwrap();
+        let (_, rest, _) = pump(&mut client, &mut server);
+        received.extend(rest);
+        assert_eq!(received, packets);
+        assert_eq!(client.pending(), 0);
+    }
+
+    #[test]
+    fn test_server_waits_for_request() {
+        let mut server = session(MimicryProfile::RuTube, Role::Server, 6);
+        server.send(b"early").unwrap();
+        assert_eq!(server.pending(), 9);
+
+        assert!(server.receive(b"GET / HTTP/1.1\r\n\r\n").is_err());
+        assert!(H2Session::try_new(MimicryProfile::RuTube, Role::Symmetric).is_err());
+
+        let mut client = session(MimicryProfile::RuTube, Role::Client, 7);
+        let mut server = session(MimicryProfile::RuTube, Role::Server, 8);
+        server.send(b"early").unwrap();
+        let (to_client, _, frames) = pump(&mut client, &mut server);
+        assert_eq!(to_client, vec![Bytes::from_static(b"early")]);
+
+        let mut decoder = hpack::Decoder::default();
+        let response = frames.iter().find(|f| f.kind == kind::HEADERS).unwrap();
+        let headers = decoder.decode(&response.body().unwrap()).unwrap();
+        assert_eq!(headers[0], (":status".to_string(), "200".to_string()));
+        assert_eq!(hpack::find_header(&headers, "server"), Some("nginx/1.21.6"));
+        assert!(hpack::find_header(&headers, "transfer-encoding").is_none());
+    }
+
+    #[test]
+    fn test_ping_ack() {
+        let mut client = session(MimicryProfile::VkVideo, Role::Client, 9);
+        client.take_output();
+        let mut ping = BytesMut::new();
+        frame::encode_frame(&mut ping, kind::PING, 0, 0, b"12345678");
+        assert!(client.receive(&ping).unwrap().is_empty());
+
+        let mut frames = FrameDecoder::default();
+        frames.extend(&client.take_output());
+        let ack = frames.decode().unwrap().unwrap();
+        assert_eq!(
+            ack,
+            RawFrame::new(kind::PING, flags::ACK, 0, &b"12345678"[..])
+        );
+    }
+
+    #[test]
+    fn test_packets_before_goaway() {
+        let mut client = session(MimicryProfile::VkVideo, Role::Client, 12);
+        let mut server = session(MimicryProfile::VkVideo, Role::Server, 13);
+        pump(&mut client, &mut server);
+
+        // Последний пакет и GOAWAY приходят одним чтением
+        server.send(b"close_notify").unwrap();
+        server.close();
+        let output = server.take_output();
+        assert_eq!(
+            client.receive(&output).unwrap(),
+            vec![Bytes::from_static(b"close_notify")]
+        );
+        assert!(client.receive(&[]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_messages_over_stream() {
+        let (mut client_io, mut server_io) = tokio::io::duplex(64 * 1024);
+        let mut client = session(MimicryProfile::VkVideo, Role::Client, 10);
+        let mut server = session(MimicryProfile::VkVideo, Role::Server, 11);
+
+        let server_task = tokio::spawn(async move {
+            let request = read_messages(&mut server_io, &mut server).await.unwrap();
+            assert_eq!(request, vec![Bytes::from_static(b"request")]);
+            write_message(&mut server_io, &mut server, b"response")
+                .await
+                .unwrap();
+            // Поток закрыт клиентом
+            assert!(read_messages(&mut server_io, &mut server).await.is_err());
+        });
+
+        write_message(&mut client_io, &mut client, b"request")
+            .await
+            .unwrap();
+        let response = read_messages(&mut client_io, &mut client).await.unwrap();
+        assert_eq!(response, vec![Bytes::from_static(b"response")]);
+        drop(client_io);
+        server_task.await.unwrap();
+    }
+}
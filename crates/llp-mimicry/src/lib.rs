@@ -13,7 +13,23 @@
 //! - Генерация реалистичных HTTP заголовков
 //! - Имитация паттернов трафика (burst для видео, steady для аудио)
 //! - Случайные timing delays
+//! - Отправка пачками с паузами профиля и бюджетом задержки ([`shaper`])
+//! - Cover traffic по кривой битрейта профиля в простое ([`cover`])
 //! - Упаковка/распаковка LLP пакетов
+//! - Согласованная HTTP сессия на соединение: User-Agent, cookie, диапазоны байт, ETag ([`profiles::session`])
+//! - Медиаконтейнеры для тел ответов: MPEG-TS, MP3, AAC ADTS, fMP4 ([`container`])
+//! - Агрегация пакетов в сообщения размера chunk профиля ([`aggregate`])
+//! - Двусторонний обмен: запросы от клиента, ответы от сервера ([`Role`])
+//! - Подключаемые профили через трейт [`Profile`] и реестр [`registry`]
+//! - Профили из TOML шаблонов с перезагрузкой на лету ([`template`])
+//! - Разбор HTTP/1.1 потока без префиксов длины ([`codec`])
+//! - Потоковый режим: много пакетов в одном chunked ответе ([`stream`])
+//! - Внешний TLS 1.3 слой с SNI и ALPN из профиля ([`tls`])
+//! - HTTP/2 сессия: HPACK заголовки профиля и пакеты в DATA кадрах
+//!   ([`h2`]), выбирается по ALPN `h2`
+//! - WebSocket транспорт для CDN и обратных прокси ([`ws`])
+//! - Маскировка UDP датаграмм под QUIC и STUN/SRTP видеозвонка ([`datagram`])
+//! - Резервный DNS туннель через локальный резолвер ([`dns`])
 //!
 //! ## Пример использования
 //!
@@ -49,13 +65,31 @@
 #![deny(missing_docs)]
 #![warn(clippy::all)]
 
+pub mod aggregate;
+pub mod codec;
+pub mod container;
+pub mod cover;
+pub mod datagram;
+pub mod dns;
 pub mod error;
+pub mod exchange;
+pub mod h2;
 pub mod profiles;
+pub mod registry;
+pub mod shaper;
+pub mod stream;
+pub mod template;
 pub mod timing;
+pub mod tls;
 pub mod wrapper;
+pub mod ws;
 
 // Re-экспорт основных типов
+pub use codec::HttpDecoder;
 pub use error::{MimicryError, Result};
+pub use exchange::Role;
+pub use profiles::Profile;
+pub use registry::ProfileRegistry;
 pub use timing::TimingProfile;
 pub use wrapper::{PacketWrapper, QuickWrapper};
 
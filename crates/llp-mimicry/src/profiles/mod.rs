@@ -1,9 +1,141 @@
 //! Профили мимикрии для различных сервисов
+//!
+//! Каждый профиль реализует трейт [`Profile`]; [`crate::registry::ProfileRegistry`]
+//! сопоставляет идентификатор профиля на проводе с фабрикой реализации.
 
+use bytes::Bytes;
+use rand::RngCore;
+use std::time::Duration;
+
+use crate::cover::BitrateCurve;
+use crate::error::Result;
+use crate::timing::TimingProfile;
+
+pub mod passthrough;
 pub mod rutube;
+pub mod session;
 pub mod vk_video;
 pub mod yandex_music;
 
+pub use passthrough::PassthroughProfile;
 pub use rutube::{RuTubeParser, RuTubeProfile};
 pub use vk_video::{VkVideoParser, VkVideoProfile};
 pub use yandex_music::{YandexMusicParser, YandexMusicProfile};
+
+/// Источник случайности профиля
+///
+/// По умолчанию профили используют `OsRng`; для воспроизводимой
+/// генерации трафика можно передать RNG с фиксированным seed.
+pub type BoxedRng = Box<dyn RngCore + Send + Sync>;
+
+/// Идентификатор профиля на проводе (поле `mimicry_profile` заголовка LLP)
+pub type ProfileId = u16;
+
+/// Профиль мимикрии
+///
+/// Оформляет сериализованные LLP пакеты как трафик конкретного сервиса
+/// и извлекает их обратно. Реализации вне этого крейта регистрируются
+/// в [`crate::registry::ProfileRegistry`] под идентификатором из
+/// пользовательского диапазона (`CUSTOM_PROFILE_ID_MIN..`).
+pub trait Profile: Send + Sync {
+    /// Идентификатор профиля на проводе
+    fn id(&self) -> ProfileId;
+
+    /// Имя профиля (используется в конфигурации)
+    fn name(&self) -> &str;
+
+    /// Обернуть сериализованный LLP пакет (ответ сервиса)
+    fn wrap(&mut self, packet: &[u8]) -> Result<Bytes>;
+
+    /// Извлечь LLP пакет из обёрнутых данных
+    fn unwrap(&self, data: &[u8]) -> Result<Bytes>;
+
+    /// Обернуть сериализованный LLP пакет в запрос клиента
+    fn wrap_request(&mut self, packet: &[u8], chunk_index: u64) -> Result<Bytes>;
+
+    /// Извлечь LLP пакет из запроса клиента
+    ///
+    /// Запрос без payload (опрос сервера) даёт пустой результат.
+    fn unwrap_request(&self, data: &[u8]) -> Result<Bytes>;
+
+    /// Учесть запрос клиента, на который ответит сервер
+    ///
+    /// Ответы продолжают сессию, названную в запросе: идентификатор
+    /// сессии, cookie, файл и диапазон байт. По умолчанию ничего не
+    /// делает.
+    fn observe_request(&mut self, _request: &[u8]) {}
+
+    /// Учесть ответ сервера перед следующим запросом клиента
+    ///
+    /// По умолчанию ничего не делает.
+    fn observe_response(&mut self, _response: &[u8]) {}
+
+    /// Сгенерировать запрос клиента без payload для chunk с указанным номером
+    fn generate_request(&mut self, chunk_index: u64) -> Result<Bytes>;
+
+    /// Имитирует ли профиль обмен запрос/ответ
+    ///
+    /// Для таких профилей [`crate::PacketWrapper`] в ролях клиента и
+    /// сервера следит, чтобы каждый ответ соответствовал запросу.
+    fn http_exchange(&self) -> bool {
+        true
+    }
+
+    /// Имя сервера для SNI внешнего TLS слоя (см. [`crate::tls`])
+    ///
+    /// Совпадает с `Host` запросов профиля; `None` — профиль не задаёт
+    /// SNI.
+    fn tls_server_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Протоколы ALPN внешнего TLS слоя в порядке предпочтения
+    fn alpn_protocols(&self) -> Vec<Vec<u8>> {
+        vec![crate::tls::DEFAULT_ALPN.to_vec()]
+    }
+
+    /// Значение заголовка `Server` в ответах профиля
+    ///
+    /// Сервер подставляет его в собственные ответы на посторонние HTTP
+    /// запросы, чтобы они не расходились с трафиком профиля.
+    fn server_header(&self) -> Option<&str> {
+        None
+    }
+
+    /// Отпечаток ClientHello браузера для внешнего TLS слоя
+    ///
+    /// Настоящие клиенты сервиса — браузеры, поэтому профилю стоит
+    /// выбрать отпечаток их TLS стека. ClientHello rustls получает из
+    /// него только порядок cipher suites и групп (см. [`crate::tls::fingerprint`]).
+    fn tls_fingerprint(&self) -> crate::tls::Fingerprint {
+        crate::tls::Fingerprint::Rustls
+    }
+
+    /// Заголовок долгого chunked ответа для потокового режима
+    ///
+    /// `None` — профиль не поддерживает потоковый режим
+    /// (см. [`crate::stream`]).
+    fn stream_head(&mut self) -> Option<Bytes> {
+        None
+    }
+
+    /// Рекомендуемая задержка перед следующим пакетом
+    fn next_packet_timing(&mut self) -> Duration;
+
+    /// Паттерн отправки для планировщика [`crate::shaper`]
+    ///
+    /// `None` — профиль не задаёт паттерн, пакеты уходят без задержек.
+    fn timing_profile(&self) -> Option<&TimingProfile> {
+        None
+    }
+
+    /// Кривая битрейта для cover traffic в простое ([`crate::cover`])
+    ///
+    /// `None` — профиль не задаёт кривую, в простое поток молчит.
+    fn bitrate_curve(&self) -> Option<BitrateCurve> {
+        None
+    }
+
+    /// Рекомендуемый размер chunk
+    fn recommended_chunk_size(&mut self) -> usize;
+}
@@ -0,0 +1,58 @@
+//! Профиль без мимикрии
+//!
+//! Передаёт LLP пакеты как есть; соответствует `MimicryProfile::None`.
+
+use bytes::Bytes;
+use std::time::Duration;
+
+use crate::error::Result;
+use crate::profiles::{Profile, ProfileId};
+
+/// Рекомендуемый размер chunk без мимикрии (1 MB)
+const PASSTHROUGH_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Профиль без мимикрии
+#[derive(Debug, Default)]
+pub struct PassthroughProfile;
+
+impl Profile for PassthroughProfile {
+    fn id(&self) -> ProfileId {
+        0
+    }
+
+    fn name(&self) -> &str {
+        "none"
+    }
+
+    fn wrap(&mut self, packet: &[u8]) -> Result<Bytes> {
+        Ok(Bytes::copy_from_slice(packet))
+    }
+
+    fn unwrap(&self, data: &[u8]) -> Result<Bytes> {
+        Ok(Bytes::copy_from_slice(data))
+    }
+
+    fn wrap_request(&mut self, packet: &[u8], _chunk_index: u64) -> Result<Bytes> {
+        Ok(Bytes::copy_from_slice(packet))
+    }
+
+    fn unwrap_request(&self, data: &[u8]) -> Result<Bytes> {
+        Ok(Bytes::copy_from_slice(data))
+    }
+
+    fn generate_request(&mut self, _chunk_index: u64) -> Result<Bytes> {
+        Ok(Bytes::new())
+    }
+
+    fn http_exchange(&self) -> bool {
+        false
+    }
+
+    fn next_packet_timing(&mut self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn recommended_chunk_size(&mut self) -> usize {
+        PASSTHROUGH_CHUNK_SIZE
+    }
+}
@@ -4,12 +4,24 @@
 //! Генерирует реалистичные заголовки для HLS/DASH потоков.
 
 use bytes::{BufMut, Bytes, BytesMut};
-use rand::{rngs::OsRng, Rng, RngCore};
+use llp_core::clock::{SharedClock, SystemClock};
+use rand::{rngs::OsRng, Rng};
 use std::time::Duration;
 
+use crate::container::{self, Container};
+use crate::cover::BitrateCurve;
 use crate::error::{MimicryError, Result};
+use crate::exchange::{self, Carrier, MAX_INLINE_PAYLOAD};
+use crate::profiles::session::{self, HttpSession};
+use crate::profiles::{BoxedRng, Profile, ProfileId};
 use crate::timing::TimingProfile;
 
+/// Cookie, в которой клиент передаёт payload
+const SESSION_COOKIE: &str = "rt_sid";
+
+/// Cookie с идентификатором устройства
+const DEVICE_COOKIE: &str = "rt_did";
+
 /// User-Agent строки для RuTube клиентов
 const USER_AGENTS: &[&str] = &[
     "RuTube/4.2.1 (Android 13; SM-G998B)",
@@ -27,17 +39,52 @@ const VIDEO_QUALITIES: &[&str] = &["360p", "480p", "720p", "1080p"];
 /// Профиль мимикрии RuTube
 pub struct RuTubeProfile {
     /// Генератор случайных чисел
-    rng: OsRng,
+    rng: BoxedRng,
+    /// Источник времени (для заголовка Date)
+    clock: SharedClock,
     /// Timing профиль для имитации паттернов трафика
     timing: TimingProfile,
+    /// Состояние сессии соединения (User-Agent, cookie, номера сегментов)
+    session: HttpSession,
+    /// Качество видео сессии
+    quality: &'static str,
+    /// Формат сегментов сессии
+    format: &'static str,
+    /// Номер CDN сервера, отдающего сегменты
+    cdn: u8,
 }
 
 impl RuTubeProfile {
     /// Создать новый профиль
     pub fn new() -> Self {
+        Self::with_sources(Box::new(OsRng), SystemClock::shared())
+    }
+
+    /// Создать профиль с заданными источниками случайности и времени
+    ///
+    /// Используется для детерминированной генерации трафика в тестах
+    /// и симуляции (например, с `StdRng::seed_from_u64`).
+    pub fn with_sources(mut rng: BoxedRng, clock: SharedClock) -> Self {
+        let device_id = format!(
+            "{:08x}-{:04x}-{:04x}",
+            rng.gen::<u32>(),
+            rng.gen::<u16>(),
+            rng.gen::<u16>()
+        );
+        let session = HttpSession::new(&mut rng, USER_AGENTS, 12, clock.unix_time())
+            .with_cookie(DEVICE_COOKIE, device_id);
+        let quality = VIDEO_QUALITIES[rng.gen_range(0..VIDEO_QUALITIES.len())];
+        let format = VIDEO_FORMATS[rng.gen_range(0..VIDEO_FORMATS.len())];
+        let cdn = rng.gen_range(1..=10);
+
         Self {
-            rng: OsRng,
+            rng,
+            clock,
             timing: TimingProfile::video_streaming(),
+            session,
+            quality,
+            format,
+            cdn,
         }
     }
 
@@ -51,11 +98,70 @@ impl RuTubeProfile {
     /// X-RuTube-Session: abc123
     /// ```
     pub fn generate_request(&mut self, video_id: u64, segment_num: u32) -> Bytes {
-        let user_agent = self.random_user_agent();
-        let session_id = self.generate_session_id();
-        let quality = self.random_quality();
-        let format = self.random_format();
-        let device_id = self.generate_device_id();
+        self.segment_request(video_id, segment_num, None)
+    }
+
+    /// Генерация HTTP запроса с payload клиента
+    ///
+    /// Небольшой payload передаётся в cookie `rt_sid` Range запроса
+    /// сегмента, крупный — в теле POST статистики плеера о последнем
+    /// запрошенном сегменте:
+    /// ```text
+    /// GET /video/12345/720p/segment_00042.ts HTTP/1.1
+    /// Range: bytes=0-
+    /// Cookie: rt_did=1a2b3c4d-5e6f-7a8b; rt_sid=AAECAw
+    /// ```
+    pub fn generate_upload(&mut self, payload: &[u8], video_id: u64) -> Bytes {
+        if payload.len() <= MAX_INLINE_PAYLOAD {
+            let segment_num = self.session.next_segment();
+            return self.segment_request(video_id, segment_num, Some(payload));
+        }
+
+        let headers = format!(
+            "POST /api/play/stats/?video={}&segment={} HTTP/1.1\r\n\
+             Host: rutube.ru\r\n\
+             User-Agent: {}\r\n\
+             Accept: */*\r\n\
+             Accept-Encoding: gzip, deflate\r\n\
+             Content-Type: application/octet-stream\r\n\
+             Content-Length: {}\r\n\
+             Connection: keep-alive\r\n\
+             X-RuTube-Device-Id: {}\r\n\
+             Referer: https://rutube.ru/video/{}/\r\n\
+             Origin: https://rutube.ru\r\n\
+             \r\n",
+            video_id,
+            self.session.segment(),
+            self.session.user_agent(),
+            payload.len(),
+            self.device_id(),
+            video_id
+        );
+
+        let mut request = BytesMut::with_capacity(headers.len() + payload.len());
+        request.put(headers.as_bytes());
+        request.put(payload);
+        request.freeze()
+    }
+
+    /// Запрос HLS segment, с payload в cookie или без
+    fn segment_request(
+        &mut self,
+        video_id: u64,
+        segment_num: u32,
+        payload: Option<&[u8]>,
+    ) -> Bytes {
+        let mut cookies = self.session.cookie_header();
+        let mut range = "";
+        if let Some(payload) = payload {
+            cookies = format!(
+                "{}; {}={}",
+                cookies,
+                SESSION_COOKIE,
+                exchange::encode_inline(payload)
+            );
+            range = "Range: bytes=0-\r\n";
+        }
 
         let request = format!(
             "GET /video/{}/{}/segment_{:05}.{} HTTP/1.1\r\n\
@@ -69,16 +175,20 @@ impl RuTubeProfile {
              X-RuTube-Quality: {}\r\n\
              Referer: https://rutube.ru/video/{}/\r\n\
              Origin: https://rutube.ru\r\n\
+             {}\
+             Cookie: {}\r\n\
              \r\n",
             video_id,
-            quality,
+            self.quality,
             segment_num,
-            format,
-            user_agent,
-            session_id,
-            device_id,
-            quality,
-            video_id
+            self.format,
+            self.session.user_agent(),
+            self.session.session_id(),
+            self.device_id(),
+            self.quality,
+            video_id,
+            range,
+            cookies
         );
 
         Bytes::from(request)
@@ -87,19 +197,18 @@ impl RuTubeProfile {
     /// Генерация HTTP ответа с зашифрованными данными
     ///
     /// Обёртывает зашифрованный payload в HTTP 200 OK ответ,
-    /// имитируя HLS segment.
+    /// имитируя HLS segment: MPEG-TS или фрагмент fMP4 по формату.
     pub fn generate_response(&mut self, encrypted_payload: &[u8]) -> Bytes {
-        let session_id = self.generate_session_id();
-        let content_length = encrypted_payload.len();
-        let format = self.random_format();
-
         let mut response = BytesMut::new();
 
-        let content_type = match format {
+        let content_type = match self.format {
             "ts" => "video/mp2t",
             "m4s" => "video/iso.segment",
             _ => "video/mp2t",
         };
+        let body =
+            Container::for_content_type(content_type).encode(encrypted_payload, &mut self.rng);
+        let content_length = body.len();
 
         let headers = format!(
             "HTTP/1.1 200 OK\r\n\
@@ -111,6 +220,8 @@ impl RuTubeProfile {
              X-RuTube-Session: {}\r\n\
              X-RuTube-Server: cdn{}\r\n\
              X-RuTube-Cache: HIT\r\n\
+             ETag: {}\r\n\
+             Last-Modified: {}\r\n\
              Accept-Ranges: bytes\r\n\
              Cache-Control: public, max-age=604800\r\n\
              Access-Control-Allow-Origin: https://rutube.ru\r\n\
@@ -119,16 +230,44 @@ impl RuTubeProfile {
             self.current_http_date(),
             content_type,
             content_length,
-            session_id,
-            self.rng.gen_range(1..=10)
+            self.session.session_id(),
+            self.cdn,
+            self.session.etag(),
+            self.session.last_modified()
         );
 
         response.put(headers.as_bytes());
-        response.put(encrypted_payload);
+        response.put(body);
 
         response.freeze()
     }
 
+    /// Генерация заголовка долгого chunked ответа (потоковый режим)
+    ///
+    /// Имитирует live трансляцию, отдаваемую одним ответом без
+    /// `Content-Length` (см. [`crate::stream`]).
+    pub fn generate_stream_head(&mut self) -> Bytes {
+        let head = format!(
+            "HTTP/1.1 200 OK\r\n\
+             Server: nginx/1.21.6\r\n\
+             Date: {}\r\n\
+             Content-Type: video/mp2t\r\n\
+             Transfer-Encoding: chunked\r\n\
+             Connection: keep-alive\r\n\
+             X-RuTube-Session: {}\r\n\
+             X-RuTube-Server: cdn{}\r\n\
+             Cache-Control: no-cache\r\n\
+             Access-Control-Allow-Origin: https://rutube.ru\r\n\
+             Access-Control-Allow-Credentials: true\r\n\
+             \r\n",
+            self.current_http_date(),
+            self.session.session_id(),
+            self.cdn
+        );
+
+        Bytes::from(head)
+    }
+
     /// Получить timing для следующего пакета (burst для видео)
     pub fn next_packet_timing(&mut self) -> Duration {
         self.timing.next_delay(&mut self.rng)
@@ -139,48 +278,103 @@ impl RuTubeProfile {
         self.rng.gen_range(100 * 1024..500 * 1024)
     }
 
-    /// Генерация случайного session ID
-    fn generate_session_id(&mut self) -> String {
-        let mut bytes = [0u8; 12];
-        self.rng.fill_bytes(&mut bytes);
-        hex::encode(bytes)
+    /// Идентификатор устройства сессии
+    fn device_id(&self) -> &str {
+        self.session.cookie(DEVICE_COOKIE).unwrap_or_default()
     }
 
-    /// Генерация device ID
-    fn generate_device_id(&mut self) -> String {
-        format!(
-            "{:08x}-{:04x}-{:04x}",
-            self.rng.gen::<u32>(),
-            self.rng.gen::<u16>(),
-            self.rng.gen::<u16>()
-        )
+    /// Текущая дата в HTTP формате
+    fn current_http_date(&self) -> String {
+        use chrono::{DateTime, Utc};
+        let unix = self.clock.unix_time();
+        DateTime::<Utc>::from_timestamp(unix.as_secs() as i64, 0)
+            .unwrap_or_default()
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string()
     }
+}
 
-    /// Случайный User-Agent
-    fn random_user_agent(&mut self) -> &'static str {
-        USER_AGENTS[self.rng.gen_range(0..USER_AGENTS.len())]
+impl Default for RuTubeProfile {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    /// Случайное качество видео
-    fn random_quality(&mut self) -> &'static str {
-        VIDEO_QUALITIES[self.rng.gen_range(0..VIDEO_QUALITIES.len())]
+impl Profile for RuTubeProfile {
+    fn id(&self) -> ProfileId {
+        3
     }
 
-    /// Случайный формат видео
-    fn random_format(&mut self) -> &'static str {
-        VIDEO_FORMATS[self.rng.gen_range(0..VIDEO_FORMATS.len())]
+    fn name(&self) -> &str {
+        "rutube"
     }
 
-    /// Текущая дата в HTTP формате
-    fn current_http_date(&self) -> String {
-        use chrono::Utc;
-        Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+    fn tls_server_name(&self) -> Option<&str> {
+        Some("rutube.ru")
     }
-}
 
-impl Default for RuTubeProfile {
-    fn default() -> Self {
-        Self::new()
+    fn server_header(&self) -> Option<&str> {
+        Some("nginx/1.21.6")
+    }
+
+    fn tls_fingerprint(&self) -> crate::tls::Fingerprint {
+        crate::tls::Fingerprint::Firefox121
+    }
+
+    fn wrap(&mut self, packet: &[u8]) -> Result<Bytes> {
+        Ok(self.generate_response(packet))
+    }
+
+    fn unwrap(&self, data: &[u8]) -> Result<Bytes> {
+        RuTubeParser::extract_response_payload(data)
+    }
+
+    fn wrap_request(&mut self, packet: &[u8], _chunk_index: u64) -> Result<Bytes> {
+        Ok(self.generate_upload(packet, self.session.content_id()))
+    }
+
+    fn unwrap_request(&self, data: &[u8]) -> Result<Bytes> {
+        exchange::extract_request_payload(data, &Carrier::Cookie(SESSION_COOKIE.to_string()))
+    }
+
+    fn observe_request(&mut self, request: &[u8]) {
+        let observed =
+            self.session
+                .adopt_request(request, "X-RuTube-Session", Some(SESSION_COOKIE));
+        let video_id = observed.and_then(|request| {
+            session::number_after(&request.path, "/video/")
+                .or_else(|| session::number_after(&request.path, "video="))
+        });
+        if let Some(video_id) = video_id {
+            self.session.set_content_id(video_id);
+        }
+    }
+
+    fn generate_request(&mut self, _chunk_index: u64) -> Result<Bytes> {
+        // Одно видео на соединение, сегменты по порядку
+        let video_id = self.session.content_id();
+        let segment_num = self.session.next_segment();
+        Ok(RuTubeProfile::generate_request(self, video_id, segment_num))
+    }
+
+    fn stream_head(&mut self) -> Option<Bytes> {
+        Some(self.generate_stream_head())
+    }
+
+    fn next_packet_timing(&mut self) -> Duration {
+        RuTubeProfile::next_packet_timing(self)
+    }
+
+    fn timing_profile(&self) -> Option<&TimingProfile> {
+        Some(&self.timing)
+    }
+
+    fn bitrate_curve(&self) -> Option<BitrateCurve> {
+        Some(BitrateCurve::video_streaming())
+    }
+
+    fn recommended_chunk_size(&mut self) -> usize {
+        RuTubeProfile::recommended_chunk_size(self)
     }
 }
 
@@ -225,7 +419,8 @@ impl RuTubeParser {
             return Err(MimicryError::ParseError("No payload in response".to_string()).into());
         }
 
-        Ok(Bytes::copy_from_slice(&data[header_size..]))
+        // Тело — медиаконтейнер по Content-Type
+        container::decode_body(resp.headers, &data[header_size..])
     }
 }
 
@@ -257,8 +452,11 @@ mod tests {
         assert!(response_str.contains("X-RuTube-Session: "));
         assert!(response_str.contains("X-RuTube-Cache: HIT"));
 
-        // Проверка, что payload в конце
-        assert!(response.ends_with(payload));
+        // Тело — медиаконтейнер по Content-Type, а не голый payload
+        let content_type = response_str.split("Content-Type: ").nth(1).unwrap();
+        let content_type = content_type.split("\r\n").next().unwrap();
+        assert_ne!(Container::for_content_type(content_type), Container::Raw);
+        assert_eq!(&RuTubeParser::extract_response_payload(&response).unwrap()[..], payload);
     }
 
     #[test]
@@ -271,6 +469,64 @@ mod tests {
         assert_eq!(&extracted[..], original_payload);
     }
 
+    #[test]
+    fn test_upload_round_trip() {
+        let mut profile = RuTubeProfile::new();
+
+        let small = profile.generate_upload(b"upstream", 42);
+        let small_str = String::from_utf8_lossy(&small);
+        assert!(small_str.starts_with("GET /video/42/"));
+        assert!(small_str.contains("Range: bytes=0-\r\nCookie: rt_did="));
+        assert!(small_str.contains("; rt_sid="));
+        assert_eq!(&Profile::unwrap_request(&profile, &small).unwrap()[..], b"upstream");
+
+        let payload = vec![0x5au8; MAX_INLINE_PAYLOAD + 1];
+        let large = profile.generate_upload(&payload, 42);
+        assert!(large.starts_with(b"POST /api/play/stats/"));
+        assert_eq!(Profile::unwrap_request(&profile, &large).unwrap(), payload);
+    }
+
+    /// Значение заголовка сообщения
+    fn header<'a>(message: &'a str, name: &str) -> &'a str {
+        let start = message.find(&format!("{}: ", name)).unwrap() + name.len() + 2;
+        message[start..].split("\r\n").next().unwrap()
+    }
+
+    #[test]
+    fn test_segments_increase() {
+        let mut profile = RuTubeProfile::new();
+        let requests: Vec<_> = (0..3)
+            .map(|i| Profile::generate_request(&mut profile, i).unwrap())
+            .map(|r| String::from_utf8(r.to_vec()).unwrap())
+            .collect();
+
+        // Одно видео, качество и устройство; сегменты по порядку
+        let path = |request: &str| request.split(' ').nth(1).unwrap().to_string();
+        let first_path = path(&requests[0]);
+        let (prefix, _) = first_path.rsplit_once('/').unwrap();
+        for (i, request) in requests.iter().enumerate() {
+            assert!(path(request).starts_with(&format!("{}/segment_{:05}.", prefix, i + 1)));
+            for name in [
+                "User-Agent",
+                "X-RuTube-Session",
+                "X-RuTube-Device-Id",
+                "Cookie",
+            ] {
+                assert_eq!(header(request, name), header(&requests[0], name));
+            }
+        }
+
+        let first = profile.generate_response(b"a");
+        let second = profile.generate_response(b"b");
+        let (first, second) = (
+            String::from_utf8_lossy(&first),
+            String::from_utf8_lossy(&second),
+        );
+        for name in ["ETag", "Last-Modified", "X-RuTube-Server"] {
+            assert_eq!(header(&first, name), header(&second, name));
+        }
+    }
+
     #[test]
     fn test_chunk_size() {
         let mut profile = RuTubeProfile::new();
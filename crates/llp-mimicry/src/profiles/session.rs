@@ -0,0 +1,427 @@
+//! Состояние HTTP сессии профиля
+//!
+//! Экземпляр профиля обслуживает одно соединение, поэтому все его
+//! сообщения должны выглядеть как трафик одного устройства, которое
+//! смотрит или слушает один файл:
+//! - User-Agent, идентификатор сессии и cookie выбираются один раз;
+//!   сервер берёт их из запросов клиента ([`HttpSession::adopt_request`]);
+//! - диапазоны байт идут подряд внутри файла постоянного размера,
+//!   а когда файл кончается, начинается следующий;
+//! - номера сегментов растут;
+//! - ETag и Last-Modified меняются только вместе с файлом.
+
+use rand::{Rng, RngCore};
+use std::ops::Range;
+use std::time::Duration;
+
+/// Размер файла сессии
+const FILE_SIZE: Range<u64> = 20 * 1024 * 1024..200 * 1024 * 1024;
+
+/// Давность Last-Modified файла (секунды): от часа до месяца
+const FILE_AGE: Range<u64> = 3600..30 * 24 * 3600;
+
+/// Идентификатор видео или трека
+const CONTENT_ID: Range<u64> = 100_000..100_000_000;
+
+/// Диапазон байт ответа внутри файла
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    /// Первый байт
+    pub start: u64,
+    /// Последний байт (включительно)
+    pub end: u64,
+    /// Размер файла
+    pub total: u64,
+}
+
+impl ByteRange {
+    /// Значение заголовка `Content-Range`
+    pub fn content_range(&self) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, self.total)
+    }
+}
+
+/// Запрос клиента, который разобрал сервер
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObservedRequest {
+    /// Путь запроса с query
+    pub path: String,
+    /// Начало диапазона из заголовка `Range: bytes={start}-`
+    pub range_start: Option<u64>,
+}
+
+/// Файл, который скачивает сессия
+struct MediaFile {
+    size: u64,
+    offset: u64,
+    etag: String,
+    last_modified: u64,
+}
+
+impl MediaFile {
+    /// Новый файл не меньше `min_size` байт
+    fn new<R: RngCore + ?Sized>(rng: &mut R, min_size: u64, now: Duration) -> Self {
+        let size = rng.gen_range(FILE_SIZE).max(min_size);
+        let last_modified = now.as_secs().saturating_sub(rng.gen_range(FILE_AGE));
+
+        Self {
+            size,
+            offset: 0,
+            // Формат ETag nginx: время изменения и размер в hex
+            etag: format!("\"{:x}-{:x}\"", last_modified, size),
+            last_modified,
+        }
+    }
+}
+
+/// Состояние HTTP сессии одного соединения
+pub struct HttpSession {
+    user_agent: &'static str,
+    session_id: String,
+    content_id: u64,
+    cookies: Vec<(String, String)>,
+    file: MediaFile,
+    segment: u32,
+}
+
+impl HttpSession {
+    /// Начать сессию
+    ///
+    /// User-Agent выбирается из `user_agents`, идентификатор сессии —
+    /// `id_len` случайных байт в hex; `now` — текущее время UNIX.
+    pub fn new<R: RngCore + ?Sized>(
+        rng: &mut R,
+        user_agents: &[&'static str],
+        id_len: usize,
+        now: Duration,
+    ) -> Self {
+        let mut id = vec![0u8; id_len];
+        rng.fill_bytes(&mut id);
+
+        Self {
+            user_agent: user_agents[rng.gen_range(0..user_agents.len())],
+            session_id: hex::encode(id),
+            content_id: rng.gen_range(CONTENT_ID),
+            cookies: Vec::new(),
+            file: MediaFile::new(rng, 0, now),
+            segment: 0,
+        }
+    }
+
+    /// Добавить cookie в jar сессии
+    pub fn with_cookie(mut self, name: &str, value: String) -> Self {
+        self.cookies.push((name.to_string(), value));
+        self
+    }
+
+    /// User-Agent устройства
+    pub fn user_agent(&self) -> &'static str {
+        self.user_agent
+    }
+
+    /// Идентификатор сессии
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Идентификатор видео или трека сессии
+    pub fn content_id(&self) -> u64 {
+        self.content_id
+    }
+
+    /// Значение cookie из jar
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.cookies
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Значение заголовка `Cookie` со всеми cookie сессии
+    pub fn cookie_header(&self) -> String {
+        self.cookies
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    /// Сменить идентификатор видео или трека
+    pub fn set_content_id(&mut self, content_id: u64) {
+        self.content_id = content_id;
+    }
+
+    /// Продолжить сессию клиента по его запросу (роль сервера)
+    ///
+    /// Идентификатор сессии берётся из заголовка `session_header`, jar —
+    /// из `Cookie` без `skip_cookie` (в ней клиент передаёт payload).
+    /// Заголовков нет — значения сессии остаются прежними. `None` —
+    /// запрос не разобран.
+    pub fn adopt_request(
+        &mut self,
+        request: &[u8],
+        session_header: &str,
+        skip_cookie: Option<&str>,
+    ) -> Option<ObservedRequest> {
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut parsed = httparse::Request::new(&mut headers);
+        if !matches!(parsed.parse(request), Ok(httparse::Status::Complete(_))) {
+            return None;
+        }
+
+        let mut range_start = None;
+        for header in parsed.headers.iter() {
+            let value = String::from_utf8_lossy(header.value);
+            if header.name.eq_ignore_ascii_case(session_header) {
+                self.session_id = value.into_owned();
+            } else if header.name.eq_ignore_ascii_case("Cookie") {
+                let cookies: Vec<_> = value
+                    .split(';')
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .filter(|(name, _)| Some(*name) != skip_cookie)
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect();
+                if !cookies.is_empty() {
+                    self.cookies = cookies;
+                }
+            } else if header.name.eq_ignore_ascii_case("Range") {
+                range_start = value
+                    .strip_prefix("bytes=")
+                    .and_then(|range| range.split('-').next())
+                    .and_then(|start| start.parse().ok());
+            }
+        }
+
+        Some(ObservedRequest {
+            path: parsed.path?.to_string(),
+            range_start,
+        })
+    }
+
+    /// Номер следующего сегмента
+    pub fn next_segment(&mut self) -> u32 {
+        self.segment += 1;
+        self.segment
+    }
+
+    /// Номер последнего запрошенного сегмента
+    pub fn segment(&self) -> u32 {
+        self.segment
+    }
+
+    /// Смещение следующего байта файла
+    pub fn offset(&self) -> u64 {
+        self.file.offset
+    }
+
+    /// Диапазон следующих `len` байт файла
+    ///
+    /// Диапазоны идут подряд; если в файле не осталось `len` байт,
+    /// сессия переходит к новому файлу с новыми ETag и Last-Modified.
+    pub fn next_range<R: RngCore + ?Sized>(
+        &mut self,
+        len: usize,
+        rng: &mut R,
+        now: Duration,
+    ) -> ByteRange {
+        let len = (len as u64).max(1);
+        if self.file.offset + len > self.file.size {
+            self.file = MediaFile::new(rng, len, now);
+        }
+
+        let start = self.file.offset;
+        self.file.offset += len;
+        ByteRange {
+            start,
+            end: start + len - 1,
+            total: self.file.size,
+        }
+    }
+
+    /// Диапазон `len` байт файла с начала `Range` запроса клиента
+    ///
+    /// Запрос без `Range` или с `start`, после которого в файле не
+    /// помещается `len` байт, получает начало нового файла целиком
+    /// (`None`: ответ `200 OK` без `Content-Range`).
+    pub fn range_from<R: RngCore + ?Sized>(
+        &mut self,
+        start: Option<u64>,
+        len: usize,
+        rng: &mut R,
+        now: Duration,
+    ) -> Option<ByteRange> {
+        let len = (len as u64).max(1);
+        let start = match start {
+            Some(start) if start.saturating_add(len) <= self.file.size => start,
+            _ => {
+                self.file = MediaFile::new(rng, len, now);
+                self.file.offset = len;
+                return None;
+            }
+        };
+
+        self.file.offset = start + len;
+        Some(ByteRange {
+            start,
+            end: start + len - 1,
+            total: self.file.size,
+        })
+    }
+
+    /// Продолжить файл после ответа сервера (роль клиента)
+    ///
+    /// Следующий `Range` начинается за концом `Content-Range` ответа,
+    /// а после `200 OK` — за его телом.
+    pub fn follow_response(&mut self, response: &[u8]) {
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut parsed = httparse::Response::new(&mut headers);
+        if !matches!(parsed.parse(response), Ok(httparse::Status::Complete(_))) {
+            return;
+        }
+
+        let header = |name: &str| {
+            parsed
+                .headers
+                .iter()
+                .find(|header| header.name.eq_ignore_ascii_case(name))
+                .and_then(|header| std::str::from_utf8(header.value).ok())
+        };
+        let offset = match parsed.code {
+            Some(206) => header("Content-Range")
+                .and_then(|range| range.split(['-', '/']).nth(1))
+                .and_then(|end| end.parse::<u64>().ok())
+                .map(|end| end + 1),
+            Some(200) => header("Content-Length").and_then(|len| len.parse().ok()),
+            _ => None,
+        };
+        if let Some(offset) = offset {
+            self.file.offset = offset;
+        }
+    }
+
+    /// ETag текущего файла
+    pub fn etag(&self) -> &str {
+        &self.file.etag
+    }
+
+    /// Last-Modified текущего файла в HTTP формате
+    pub fn last_modified(&self) -> String {
+        http_date(Duration::from_secs(self.file.last_modified))
+    }
+}
+
+/// Число в пути запроса сразу после `prefix` (идентификатор контента)
+pub fn number_after(path: &str, prefix: &str) -> Option<u64> {
+    let rest = &path[path.find(prefix)? + prefix.len()..];
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..digits].parse().ok()
+}
+
+/// Время UNIX в HTTP формате (`Mon, 02 Jan 2006 15:04:05 GMT`)
+pub fn http_date(unix: Duration) -> String {
+    use chrono::{DateTime, Utc};
+    DateTime::<Utc>::from_timestamp(unix.as_secs() as i64, 0)
+        .unwrap_or_default()
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const NOW: Duration = Duration::from_secs(1_700_000_000);
+
+    #[test]
+    fn test_ranges_advance_within_file() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut session = HttpSession::new(&mut rng, &["ua"], 8, NOW);
+        let etag = session.etag().to_string();
+
+        let first = session.next_range(1000, &mut rng, NOW);
+        let second = session.next_range(500, &mut rng, NOW);
+        assert_eq!((first.start, first.end), (0, 999));
+        assert_eq!((second.start, second.end), (1000, 1499));
+        assert_eq!(first.total, second.total);
+        assert!(FILE_SIZE.contains(&first.total));
+        assert_eq!(session.etag(), etag);
+        assert_eq!(
+            first.content_range(),
+            format!("bytes 0-999/{}", first.total)
+        );
+
+        // Файл кончился — новый файл с новым ETag
+        let rest = (first.total - 1500) as usize;
+        session.next_range(rest, &mut rng, NOW);
+        let next = session.next_range(1000, &mut rng, NOW);
+        assert_eq!(next.start, 0);
+        assert_ne!(session.etag(), etag);
+    }
+
+    #[test]
+    fn test_range_follows_request() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut server = HttpSession::new(&mut rng, &["ua"], 8, NOW);
+        let mut client = HttpSession::new(&mut rng, &["ua"], 8, NOW);
+
+        let range = server
+            .range_from(Some(client.offset()), 1000, &mut rng, NOW)
+            .unwrap();
+        assert_eq!((range.start, range.end), (0, 999));
+        let response = format!(
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: {}\r\n\r\n",
+            range.content_range()
+        );
+        client.follow_response(response.as_bytes());
+        assert_eq!(client.offset(), 1000);
+
+        // Range за концом файла — новый файл целиком
+        let etag = server.etag().to_string();
+        assert_eq!(
+            server.range_from(Some(range.total), 500, &mut rng, NOW),
+            None
+        );
+        assert_ne!(server.etag(), etag);
+        client.follow_response(b"HTTP/1.1 200 OK\r\nContent-Length: 500\r\n\r\n");
+        assert_eq!((client.offset(), server.offset()), (500, 500));
+    }
+
+    #[test]
+    fn test_adopt_request() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut server =
+            HttpSession::new(&mut rng, &["ua"], 8, NOW).with_cookie("uid", "1".to_string());
+        let request = b"GET /video/42/seg.ts HTTP/1.1\r\nX-Session: abc\r\nRange: bytes=2048-\r\nCookie: uid=7; sid=payload\r\n\r\n";
+
+        let observed = server
+            .adopt_request(request, "x-session", Some("sid"))
+            .unwrap();
+        assert_eq!(observed.path, "/video/42/seg.ts");
+        assert_eq!(observed.range_start, Some(2048));
+        assert_eq!(server.session_id(), "abc");
+        assert_eq!(server.cookie_header(), "uid=7");
+        assert_eq!(server.adopt_request(b"GET /", "x-session", None), None);
+        assert_eq!(number_after(&observed.path, "/video/"), Some(42));
+        assert_eq!(number_after("/stats/?video=7&segment=1", "video="), Some(7));
+        assert_eq!(number_after("/video/abc", "/video/"), None);
+    }
+
+    #[test]
+    fn test_session_identity_is_stable() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut session = HttpSession::new(&mut rng, &["a", "b", "c"], 12, NOW)
+            .with_cookie("uid", "42".to_string())
+            .with_cookie("lang", "ru".to_string());
+
+        assert_eq!(session.session_id().len(), 24);
+        assert_eq!(session.cookie("uid"), Some("42"));
+        assert_eq!(session.cookie_header(), "uid=42; lang=ru");
+        assert_eq!(session.next_segment(), 1);
+        assert_eq!(session.next_segment(), 2);
+        assert_eq!(session.segment(), 2);
+        assert!(session.last_modified().ends_with(" GMT"));
+    }
+}
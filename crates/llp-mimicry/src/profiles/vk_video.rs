@@ -4,10 +4,17 @@
 //! Генерирует реалистичные заголовки, паттерны трафика и timing.
 
 use bytes::{BufMut, Bytes, BytesMut};
-use rand::{rngs::OsRng, Rng, RngCore};
+use llp_core::clock::{SharedClock, SystemClock};
+use rand::{rngs::OsRng, Rng};
+use std::collections::VecDeque;
 use std::time::Duration;
 
+use crate::container::{self, Container};
+use crate::cover::BitrateCurve;
 use crate::error::{MimicryError, Result};
+use crate::exchange::{self, Carrier};
+use crate::profiles::session::HttpSession;
+use crate::profiles::{BoxedRng, Profile, ProfileId};
 use crate::timing::TimingProfile;
 
 /// User-Agent строки для VK клиентов
@@ -27,17 +34,47 @@ const VIDEO_FORMATS: &[&str] = &["mp4", "webm", "ts"];
 /// Профиль мимикрии VK Video
 pub struct VkVideoProfile {
     /// Генератор случайных чисел
-    rng: OsRng,
+    rng: BoxedRng,
+    /// Источник времени (для заголовка Date)
+    clock: SharedClock,
     /// Timing профиль для имитации паттернов трафика
     timing: TimingProfile,
+    /// Состояние сессии соединения (User-Agent, cookie, диапазоны байт)
+    session: HttpSession,
+    /// Качество видео сессии
+    quality: &'static str,
+    /// Формат видео сессии
+    format: &'static str,
+    /// Начала `Range` запросов, на которые ещё нет ответа (роль сервера)
+    pending_ranges: VecDeque<Option<u64>>,
 }
 
 impl VkVideoProfile {
     /// Создать новый профиль
     pub fn new() -> Self {
+        Self::with_sources(Box::new(OsRng), SystemClock::shared())
+    }
+
+    /// Создать профиль с заданными источниками случайности и времени
+    ///
+    /// Используется для детерминированной генерации трафика в тестах
+    /// и симуляции (например, с `StdRng::seed_from_u64`).
+    pub fn with_sources(mut rng: BoxedRng, clock: SharedClock) -> Self {
+        let remixstid = hex::encode(rng.gen::<[u8; 12]>());
+        let session = HttpSession::new(&mut rng, USER_AGENTS, 16, clock.unix_time())
+            .with_cookie("remixlang", "0".to_string())
+            .with_cookie("remixstid", remixstid);
+        let quality = VIDEO_QUALITIES[rng.gen_range(0..VIDEO_QUALITIES.len())];
+        let format = VIDEO_FORMATS[rng.gen_range(0..VIDEO_FORMATS.len())];
+
         Self {
-            rng: OsRng,
+            rng,
+            clock,
             timing: TimingProfile::video_streaming(),
+            session,
+            quality,
+            format,
+            pending_ranges: VecDeque::new(),
         }
     }
 
@@ -49,13 +86,12 @@ impl VkVideoProfile {
     /// Host: vkvideo.ru
     /// User-Agent: VKClient/8.34
     /// X-VK-Session: a1b2c3d4e5f6
-    /// Range: bytes=1048576-2097152
+    /// Range: bytes=1048576-
     /// ```
+    ///
+    /// `Range` продолжает файл с конца последнего ответа сервера.
     pub fn generate_request(&mut self, chunk_id: u64) -> Bytes {
-        let user_agent = self.random_user_agent();
-        let session_id = self.generate_session_id();
-        let quality = self.random_quality();
-        let format = self.random_format();
+        let (quality, format) = (self.quality, self.format);
 
         let request = format!(
             "GET /video/chunk_{}_{}.{} HTTP/1.1\r\n\
@@ -66,57 +102,146 @@ impl VkVideoProfile {
              Connection: keep-alive\r\n\
              X-VK-Session: {}\r\n\
              X-VK-Quality: {}\r\n\
+             Range: bytes={}-\r\n\
              Referer: https://vk.com/video\r\n\
              Origin: https://vk.com\r\n\
+             Cookie: {}\r\n\
              \r\n",
-            chunk_id, quality, format, user_agent, session_id, quality
+            chunk_id,
+            quality,
+            format,
+            self.session.user_agent(),
+            self.session.session_id(),
+            quality,
+            self.session.offset(),
+            self.session.cookie_header()
         );
 
         Bytes::from(request)
     }
 
+    /// Генерация HTTP запроса с payload клиента
+    ///
+    /// Payload передаётся в теле POST, как статистика просмотра плеера:
+    /// ```text
+    /// POST /video/heartbeat?vid=1234&q=720 HTTP/1.1
+    /// Host: vkvideo.ru
+    /// Content-Type: application/octet-stream
+    /// Content-Length: 1400
+    /// ```
+    pub fn generate_upload(&mut self, payload: &[u8], chunk_id: u64) -> Bytes {
+        let headers = format!(
+            "POST /video/heartbeat?vid={}&q={} HTTP/1.1\r\n\
+             Host: vkvideo.ru\r\n\
+             User-Agent: {}\r\n\
+             Accept: */*\r\n\
+             Accept-Encoding: gzip, deflate\r\n\
+             Content-Type: application/octet-stream\r\n\
+             Content-Length: {}\r\n\
+             Connection: keep-alive\r\n\
+             X-VK-Session: {}\r\n\
+             Referer: https://vk.com/video\r\n\
+             Origin: https://vk.com\r\n\
+             Cookie: {}\r\n\
+             \r\n",
+            chunk_id,
+            self.quality,
+            self.session.user_agent(),
+            payload.len(),
+            self.session.session_id(),
+            self.session.cookie_header()
+        );
+
+        let mut request = BytesMut::with_capacity(headers.len() + payload.len());
+        request.put(headers.as_bytes());
+        request.put(payload);
+        request.freeze()
+    }
+
     /// Генерация HTTP ответа с зашифрованными данными
     ///
     /// Обёртывает зашифрованный payload в HTTP 206 Partial Content ответ,
-    /// имитируя chunk видео.
+    /// имитируя chunk видео; тело — пакеты MPEG-TS (см. [`crate::container`]).
+    /// Диапазон ответа начинается с `Range` запроса клиента; запрос без
+    /// `Range` получает `200 OK` с началом нового файла. Без запросов
+    /// (симметричная роль) диапазоны идут подряд внутри файла сессии.
     pub fn generate_response(&mut self, encrypted_payload: &[u8]) -> Bytes {
-        let session_id = self.generate_session_id();
-        let content_length = encrypted_payload.len();
-
-        // Генерируем реалистичные Range заголовки
-        let range_start = self.rng.gen_range(0..10_000_000);
-        let range_end = range_start + content_length;
+        let body = Container::MpegTs.encode(encrypted_payload, &mut self.rng);
+        let content_length = body.len();
+        let now = self.clock.unix_time();
+        let range = match self.pending_ranges.pop_front() {
+            Some(start) => self
+                .session
+                .range_from(start, content_length, &mut self.rng, now),
+            None => Some(self.session.next_range(content_length, &mut self.rng, now)),
+        };
+        let (status, content_range) = match range {
+            Some(range) => (
+                "206 Partial Content",
+                format!("Content-Range: {}\r\n", range.content_range()),
+            ),
+            None => ("200 OK", String::new()),
+        };
 
         let mut response = BytesMut::new();
 
         // HTTP заголовки
         let headers = format!(
-            "HTTP/1.1 206 Partial Content\r\n\
+            "HTTP/1.1 {}\r\n\
              Server: nginx/1.20.2\r\n\
              Date: {}\r\n\
              Content-Type: video/mp2t\r\n\
              Content-Length: {}\r\n\
-             Content-Range: bytes {}-{}/50000000\r\n\
+             {}\
              Connection: keep-alive\r\n\
              X-VK-Session: {}\r\n\
              X-VK-Server: vkvideo42\r\n\
+             ETag: {}\r\n\
+             Last-Modified: {}\r\n\
              Accept-Ranges: bytes\r\n\
              Cache-Control: public, max-age=31536000\r\n\
              Access-Control-Allow-Origin: https://vk.com\r\n\
              \r\n",
+            status,
             self.current_http_date(),
             content_length,
-            range_start,
-            range_end - 1,
-            session_id
+            content_range,
+            self.session.session_id(),
+            self.session.etag(),
+            self.session.last_modified()
         );
 
         response.put(headers.as_bytes());
-        response.put(encrypted_payload);
+        response.put(body);
 
         response.freeze()
     }
 
+    /// Генерация заголовка долгого chunked ответа (потоковый режим)
+    ///
+    /// Видео отдаётся одним ответом без `Content-Length`; тело идёт
+    /// chunk-ами (см. [`crate::stream`]).
+    pub fn generate_stream_head(&mut self) -> Bytes {
+        self.pending_ranges.pop_front();
+        let head = format!(
+            "HTTP/1.1 200 OK\r\n\
+             Server: nginx/1.20.2\r\n\
+             Date: {}\r\n\
+             Content-Type: video/mp2t\r\n\
+             Transfer-Encoding: chunked\r\n\
+             Connection: keep-alive\r\n\
+             X-VK-Session: {}\r\n\
+             X-VK-Server: vkvideo42\r\n\
+             Cache-Control: no-cache\r\n\
+             Access-Control-Allow-Origin: https://vk.com\r\n\
+             \r\n",
+            self.current_http_date(),
+            self.session.session_id()
+        );
+
+        Bytes::from(head)
+    }
+
     /// Получить timing для следующего пакета (burst для видео)
     pub fn next_packet_timing(&mut self) -> Duration {
         self.timing.next_delay(&mut self.rng)
@@ -127,38 +252,92 @@ impl VkVideoProfile {
         self.rng.gen_range(64 * 1024..256 * 1024)
     }
 
-    /// Генерация случайного session ID
-    fn generate_session_id(&mut self) -> String {
-        let mut bytes = [0u8; 16];
-        self.rng.fill_bytes(&mut bytes);
-        hex::encode(bytes)
+    /// Текущая дата в HTTP формате
+    fn current_http_date(&self) -> String {
+        use chrono::{DateTime, Utc};
+        let unix = self.clock.unix_time();
+        DateTime::<Utc>::from_timestamp(unix.as_secs() as i64, 0)
+            .unwrap_or_default()
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string()
     }
+}
 
-    /// Случайный User-Agent
-    fn random_user_agent(&mut self) -> &'static str {
-        USER_AGENTS[self.rng.gen_range(0..USER_AGENTS.len())]
+impl Default for VkVideoProfile {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    /// Случайное качество видео
-    fn random_quality(&mut self) -> &'static str {
-        VIDEO_QUALITIES[self.rng.gen_range(0..VIDEO_QUALITIES.len())]
+impl Profile for VkVideoProfile {
+    fn id(&self) -> ProfileId {
+        1
     }
 
-    /// Случайный формат видео
-    fn random_format(&mut self) -> &'static str {
-        VIDEO_FORMATS[self.rng.gen_range(0..VIDEO_FORMATS.len())]
+    fn name(&self) -> &str {
+        "vk_video"
     }
 
-    /// Текущая дата в HTTP формате
-    fn current_http_date(&self) -> String {
-        use chrono::Utc;
-        Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+    fn tls_server_name(&self) -> Option<&str> {
+        Some("vkvideo.ru")
     }
-}
 
-impl Default for VkVideoProfile {
-    fn default() -> Self {
-        Self::new()
+    fn server_header(&self) -> Option<&str> {
+        Some("nginx/1.20.2")
+    }
+
+    fn tls_fingerprint(&self) -> crate::tls::Fingerprint {
+        crate::tls::Fingerprint::Chrome120
+    }
+
+    fn wrap(&mut self, packet: &[u8]) -> Result<Bytes> {
+        Ok(self.generate_response(packet))
+    }
+
+    fn unwrap(&self, data: &[u8]) -> Result<Bytes> {
+        VkVideoParser::extract_response_payload(data)
+    }
+
+    fn wrap_request(&mut self, packet: &[u8], chunk_index: u64) -> Result<Bytes> {
+        Ok(self.generate_upload(packet, chunk_index))
+    }
+
+    fn unwrap_request(&self, data: &[u8]) -> Result<Bytes> {
+        exchange::extract_request_payload(data, &Carrier::Body)
+    }
+
+    fn observe_request(&mut self, request: &[u8]) {
+        let observed = self.session.adopt_request(request, "X-VK-Session", None);
+        self.pending_ranges
+            .push_back(observed.and_then(|request| request.range_start));
+    }
+
+    fn observe_response(&mut self, response: &[u8]) {
+        self.session.follow_response(response);
+    }
+
+    fn generate_request(&mut self, chunk_index: u64) -> Result<Bytes> {
+        Ok(VkVideoProfile::generate_request(self, chunk_index))
+    }
+
+    fn stream_head(&mut self) -> Option<Bytes> {
+        Some(self.generate_stream_head())
+    }
+
+    fn next_packet_timing(&mut self) -> Duration {
+        VkVideoProfile::next_packet_timing(self)
+    }
+
+    fn timing_profile(&self) -> Option<&TimingProfile> {
+        Some(&self.timing)
+    }
+
+    fn bitrate_curve(&self) -> Option<BitrateCurve> {
+        Some(BitrateCurve::video_streaming())
+    }
+
+    fn recommended_chunk_size(&mut self) -> usize {
+        VkVideoProfile::recommended_chunk_size(self)
     }
 }
 
@@ -205,7 +384,8 @@ impl VkVideoParser {
             return Err(MimicryError::ParseError("No payload in response".to_string()).into());
         }
 
-        Ok(Bytes::copy_from_slice(&data[header_size..]))
+        // Тело — медиаконтейнер по Content-Type
+        container::decode_body(resp.headers, &data[header_size..])
     }
 }
 
@@ -236,8 +416,92 @@ mod tests {
         assert!(response_str.contains("Content-Type: video/mp2t"));
         assert!(response_str.contains("X-VK-Session: "));
 
-        // Проверка, что payload в конце
-        assert!(response.ends_with(payload));
+        // Тело — пакеты MPEG-TS, а не голый payload
+        let body_start = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let body = &response[body_start..];
+        assert_eq!(body.len() % 188, 0);
+        assert!(body.chunks(188).all(|packet| packet[0] == 0x47));
+        assert_eq!(&VkVideoParser::extract_response_payload(&response).unwrap()[..], payload);
+    }
+
+    /// Значение заголовка ответа
+    fn header<'a>(message: &'a str, name: &str) -> &'a str {
+        let start = message.find(&format!("{}: ", name)).unwrap() + name.len() + 2;
+        message[start..].split("\r\n").next().unwrap()
+    }
+
+    #[test]
+    fn test_session_is_consistent() {
+        let mut profile = VkVideoProfile::new();
+        let first = profile.generate_response(&[1; 1000]);
+        let second = profile.generate_response(&[2; 1000]);
+        let (first, second) = (
+            String::from_utf8_lossy(&first),
+            String::from_utf8_lossy(&second),
+        );
+
+        for name in ["X-VK-Session", "ETag", "Last-Modified"] {
+            assert_eq!(header(&first, name), header(&second, name));
+        }
+
+        // Второй диапазон продолжает первый в том же файле
+        let range = |message: &str| {
+            let value = header(message, "Content-Range").trim_start_matches("bytes ");
+            let (span, total) = value.split_once('/').unwrap();
+            let (start, end) = span.split_once('-').unwrap();
+            let parse = |v: &str| v.parse::<u64>().unwrap();
+            (parse(start), parse(end), parse(total))
+        };
+        let (start, end, total) = range(&first);
+        assert_eq!(start, 0);
+        assert_eq!(range(&second), (end + 1, 2 * end + 1, total));
+
+        let requests = [
+            profile.generate_request(1),
+            profile.generate_upload(b"up", 2),
+        ];
+        let requests: Vec<_> = requests
+            .iter()
+            .map(|r| String::from_utf8_lossy(r))
+            .collect();
+        for name in ["User-Agent", "X-VK-Session", "Cookie"] {
+            assert_eq!(header(&requests[0], name), header(&requests[1], name));
+        }
+        assert_eq!(
+            header(&requests[0], "X-VK-Session"),
+            header(&first, "X-VK-Session")
+        );
+    }
+
+    #[test]
+    fn test_response_follows_request() {
+        let mut client = VkVideoProfile::new();
+        let mut server = VkVideoProfile::new();
+
+        for _ in 0..3 {
+            let request = client.generate_request(1);
+            server.observe_request(&request);
+            let response = server.generate_response(&[0; 1000]);
+            client.observe_response(&response);
+
+            let (request, response) = (
+                String::from_utf8_lossy(&request),
+                String::from_utf8_lossy(&response),
+            );
+            assert_eq!(
+                header(&request, "X-VK-Session"),
+                header(&response, "X-VK-Session")
+            );
+            let start = header(&request, "Range").trim_start_matches("bytes=");
+            assert!(header(&response, "Content-Range").starts_with(&format!("bytes {}", start)));
+        }
+
+        // Heartbeat без Range получает 200 без Content-Range
+        let upload = client.generate_upload(b"up", 2);
+        server.observe_request(&upload);
+        let response = server.generate_response(b"down");
+        assert!(response.starts_with(b"HTTP/1.1 200 OK\r\n"));
+        assert!(!String::from_utf8_lossy(&response).contains("Content-Range"));
     }
 
     #[test]
@@ -250,6 +514,19 @@ mod tests {
         assert_eq!(&extracted[..], original_payload);
     }
 
+    #[test]
+    fn test_upload_round_trip() {
+        let mut profile = VkVideoProfile::new();
+        let request = profile.generate_upload(b"upstream", 7);
+
+        let request_str = String::from_utf8_lossy(&request);
+        assert!(request_str.starts_with("POST /video/heartbeat?vid=7&q="));
+        assert!(request_str.contains("Content-Length: 8\r\n"));
+
+        let extracted = Profile::unwrap_request(&profile, &request).unwrap();
+        assert_eq!(&extracted[..], b"upstream");
+    }
+
     #[test]
     fn test_chunk_size() {
         let mut profile = VkVideoProfile::new();
@@ -4,12 +4,21 @@
 //! Генерирует реалистичные заголовки для MP3/AAC стриминга.
 
 use bytes::{BufMut, Bytes, BytesMut};
-use rand::{rngs::OsRng, Rng, RngCore};
+use llp_core::clock::{SharedClock, SystemClock};
+use rand::{rngs::OsRng, Rng};
 use std::time::Duration;
 
+use crate::container::{self, Container};
+use crate::cover::BitrateCurve;
 use crate::error::{MimicryError, Result};
+use crate::exchange::{self, Carrier, MAX_INLINE_PAYLOAD};
+use crate::profiles::session::{self, HttpSession};
+use crate::profiles::{BoxedRng, Profile, ProfileId};
 use crate::timing::TimingProfile;
 
+/// Параметр query, в котором клиент передаёт payload
+const SIGN_PARAM: &str = "sign";
+
 /// User-Agent строки для Яндекс.Музыка клиентов
 const USER_AGENTS: &[&str] = &[
     "YandexMusic/5.37 (Android 13; Pixel 6 Pro)",
@@ -27,17 +36,43 @@ const BITRATES: &[&str] = &["128", "192", "256", "320"];
 /// Профиль мимикрии Яндекс.Музыка
 pub struct YandexMusicProfile {
     /// Генератор случайных чисел
-    rng: OsRng,
+    rng: BoxedRng,
+    /// Источник времени (для заголовка Date)
+    clock: SharedClock,
     /// Timing профиль для имитации паттернов трафика
     timing: TimingProfile,
+    /// Состояние сессии соединения (User-Agent, cookie yandexuid)
+    session: HttpSession,
+    /// Формат аудио сессии
+    format: &'static str,
+    /// Битрейт сессии
+    bitrate: &'static str,
 }
 
 impl YandexMusicProfile {
     /// Создать новый профиль
     pub fn new() -> Self {
+        Self::with_sources(Box::new(OsRng), SystemClock::shared())
+    }
+
+    /// Создать профиль с заданными источниками случайности и времени
+    ///
+    /// Используется для детерминированной генерации трафика в тестах
+    /// и симуляции (например, с `StdRng::seed_from_u64`).
+    pub fn with_sources(mut rng: BoxedRng, clock: SharedClock) -> Self {
+        let yandexuid = hex::encode(rng.gen::<[u8; 10]>());
+        let session = HttpSession::new(&mut rng, USER_AGENTS, 20, clock.unix_time())
+            .with_cookie("yandexuid", yandexuid);
+        let format = AUDIO_FORMATS[rng.gen_range(0..AUDIO_FORMATS.len())];
+        let bitrate = BITRATES[rng.gen_range(0..BITRATES.len())];
+
         Self {
-            rng: OsRng,
+            rng,
+            clock,
             timing: TimingProfile::audio_streaming(),
+            session,
+            format,
+            bitrate,
         }
     }
 
@@ -51,14 +86,57 @@ impl YandexMusicProfile {
     /// X-Yandex-Music-Client: Android
     /// ```
     pub fn generate_request(&mut self, track_id: u64) -> Bytes {
-        let user_agent = self.random_user_agent();
-        let session_token = self.generate_session_token();
-        let format = self.random_format();
-        let bitrate = self.random_bitrate();
-        let hash = self.generate_hash();
+        self.track_request(track_id, "")
+    }
+
+    /// Генерация HTTP запроса с payload клиента
+    ///
+    /// Небольшой payload передаётся в параметре `sign` запроса трека,
+    /// крупный — в теле POST обратной связи плеера:
+    /// ```text
+    /// GET /get-mp3/12345_320.mp3?sign=AAECAw HTTP/1.1
+    /// POST /api/v2.1/handlers/feedback?track=12345 HTTP/1.1
+    /// ```
+    pub fn generate_upload(&mut self, payload: &[u8], track_id: u64) -> Bytes {
+        if payload.len() <= MAX_INLINE_PAYLOAD {
+            let query = format!("?{}={}", SIGN_PARAM, exchange::encode_inline(payload));
+            return self.track_request(track_id, &query);
+        }
+
+        let headers = format!(
+            "POST /api/v2.1/handlers/feedback?track={} HTTP/1.1\r\n\
+             Host: music.yandex.ru\r\n\
+             User-Agent: {}\r\n\
+             Accept: */*\r\n\
+             Accept-Encoding: gzip, deflate\r\n\
+             Content-Type: application/octet-stream\r\n\
+             Content-Length: {}\r\n\
+             Connection: keep-alive\r\n\
+             X-Yandex-Music-Client: web\r\n\
+             X-Yandex-Music-Session: {}\r\n\
+             Referer: https://music.yandex.ru/\r\n\
+             Origin: https://music.yandex.ru\r\n\
+             Cookie: {}\r\n\
+             \r\n",
+            track_id,
+            self.session.user_agent(),
+            payload.len(),
+            self.session.session_id(),
+            self.session.cookie_header()
+        );
+
+        let mut request = BytesMut::with_capacity(headers.len() + payload.len());
+        request.put(headers.as_bytes());
+        request.put(payload);
+        request.freeze()
+    }
+
+    /// Запрос аудио chunk с дополнительной query строкой
+    fn track_request(&mut self, track_id: u64, query: &str) -> Bytes {
+        let (format, bitrate) = (self.format, self.bitrate);
 
         let request = format!(
-            "GET /get-{}/{}_{}.{} HTTP/1.1\r\n\
+            "GET /get-{}/{}_{}.{}{} HTTP/1.1\r\n\
              Host: music.yandex.ru\r\n\
              User-Agent: {}\r\n\
              Accept: */*\r\n\
@@ -69,9 +147,17 @@ impl YandexMusicProfile {
              X-Yandex-Music-Bitrate: {}\r\n\
              Referer: https://music.yandex.ru/\r\n\
              Origin: https://music.yandex.ru\r\n\
-             Cookie: yandexuid={}\r\n\
+             Cookie: {}\r\n\
              \r\n",
-            format, track_id, bitrate, format, user_agent, session_token, bitrate, hash
+            format,
+            track_id,
+            bitrate,
+            format,
+            query,
+            self.session.user_agent(),
+            self.session.session_id(),
+            bitrate,
+            self.session.cookie_header()
         );
 
         Bytes::from(request)
@@ -80,21 +166,21 @@ impl YandexMusicProfile {
     /// Генерация HTTP ответа с зашифрованными данными
     ///
     /// Обёртывает зашифрованный payload в HTTP 200 OK ответ,
-    /// имитируя аудио stream.
+    /// имитируя аудио stream: кадры MP3, AAC ADTS или фрагмент fMP4.
     pub fn generate_response(&mut self, encrypted_payload: &[u8]) -> Bytes {
-        let session_token = self.generate_session_token();
-        let content_length = encrypted_payload.len();
-        let format = self.random_format();
-
         let mut response = BytesMut::new();
 
         // HTTP заголовки
-        let content_type = match format {
+        let content_type = match self.format {
             "mp3" => "audio/mpeg",
             "aac" => "audio/aac",
             "m4a" => "audio/mp4",
             _ => "audio/mpeg",
         };
+        let body =
+            Container::for_content_type(content_type).encode(encrypted_payload, &mut self.rng);
+        let content_length = body.len();
+        let request_id = self.generate_request_id();
 
         let headers = format!(
             "HTTP/1.1 200 OK\r\n\
@@ -105,6 +191,8 @@ impl YandexMusicProfile {
              Connection: keep-alive\r\n\
              X-Yandex-Music-Session: {}\r\n\
              X-Yandex-Req-Id: {}\r\n\
+             ETag: {}\r\n\
+             Last-Modified: {}\r\n\
              Accept-Ranges: bytes\r\n\
              Cache-Control: public, max-age=86400\r\n\
              Access-Control-Allow-Origin: https://music.yandex.ru\r\n\
@@ -113,12 +201,14 @@ impl YandexMusicProfile {
             self.current_http_date(),
             content_type,
             content_length,
-            session_token,
-            self.generate_request_id()
+            self.session.session_id(),
+            request_id,
+            self.session.etag(),
+            self.session.last_modified()
         );
 
         response.put(headers.as_bytes());
-        response.put(encrypted_payload);
+        response.put(body);
 
         response.freeze()
     }
@@ -133,20 +223,6 @@ impl YandexMusicProfile {
         self.rng.gen_range(16 * 1024..64 * 1024)
     }
 
-    /// Генерация случайного session token
-    fn generate_session_token(&mut self) -> String {
-        let mut bytes = [0u8; 20];
-        self.rng.fill_bytes(&mut bytes);
-        hex::encode(bytes)
-    }
-
-    /// Генерация хеша для cookie
-    fn generate_hash(&mut self) -> String {
-        let mut bytes = [0u8; 10];
-        self.rng.fill_bytes(&mut bytes);
-        hex::encode(bytes)
-    }
-
     /// Генерация request ID
     fn generate_request_id(&mut self) -> String {
         format!(
@@ -159,25 +235,14 @@ impl YandexMusicProfile {
         )
     }
 
-    /// Случайный User-Agent
-    fn random_user_agent(&mut self) -> &'static str {
-        USER_AGENTS[self.rng.gen_range(0..USER_AGENTS.len())]
-    }
-
-    /// Случайный формат аудио
-    fn random_format(&mut self) -> &'static str {
-        AUDIO_FORMATS[self.rng.gen_range(0..AUDIO_FORMATS.len())]
-    }
-
-    /// Случайный битрейт
-    fn random_bitrate(&mut self) -> &'static str {
-        BITRATES[self.rng.gen_range(0..BITRATES.len())]
-    }
-
     /// Текущая дата в HTTP формате
     fn current_http_date(&self) -> String {
-        use chrono::Utc;
-        Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+        use chrono::{DateTime, Utc};
+        let unix = self.clock.unix_time();
+        DateTime::<Utc>::from_timestamp(unix.as_secs() as i64, 0)
+            .unwrap_or_default()
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string()
     }
 }
 
@@ -187,6 +252,81 @@ impl Default for YandexMusicProfile {
     }
 }
 
+impl Profile for YandexMusicProfile {
+    fn id(&self) -> ProfileId {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "yandex_music"
+    }
+
+    fn tls_server_name(&self) -> Option<&str> {
+        Some("music.yandex.ru")
+    }
+
+    fn server_header(&self) -> Option<&str> {
+        Some("nginx")
+    }
+
+    fn tls_fingerprint(&self) -> crate::tls::Fingerprint {
+        crate::tls::Fingerprint::Chrome120
+    }
+
+    fn wrap(&mut self, packet: &[u8]) -> Result<Bytes> {
+        Ok(self.generate_response(packet))
+    }
+
+    fn unwrap(&self, data: &[u8]) -> Result<Bytes> {
+        YandexMusicParser::extract_response_payload(data)
+    }
+
+    fn wrap_request(&mut self, packet: &[u8], _chunk_index: u64) -> Result<Bytes> {
+        Ok(self.generate_upload(packet, self.session.content_id()))
+    }
+
+    fn unwrap_request(&self, data: &[u8]) -> Result<Bytes> {
+        exchange::extract_request_payload(data, &Carrier::Query(SIGN_PARAM.to_string()))
+    }
+
+    fn observe_request(&mut self, request: &[u8]) {
+        let observed = self
+            .session
+            .adopt_request(request, "X-Yandex-Music-Session", None);
+        let track_id = observed.and_then(|request| {
+            let path = request.path;
+            path.strip_prefix("/get-")
+                .and_then(|rest| session::number_after(rest, "/"))
+                .or_else(|| session::number_after(&path, "track="))
+        });
+        if let Some(track_id) = track_id {
+            self.session.set_content_id(track_id);
+        }
+    }
+
+    fn generate_request(&mut self, _chunk_index: u64) -> Result<Bytes> {
+        // Один трек на соединение
+        let track_id = self.session.content_id();
+        Ok(YandexMusicProfile::generate_request(self, track_id))
+    }
+
+    fn next_packet_timing(&mut self) -> Duration {
+        YandexMusicProfile::next_packet_timing(self)
+    }
+
+    fn timing_profile(&self) -> Option<&TimingProfile> {
+        Some(&self.timing)
+    }
+
+    fn bitrate_curve(&self) -> Option<BitrateCurve> {
+        Some(BitrateCurve::audio_streaming())
+    }
+
+    fn recommended_chunk_size(&mut self) -> usize {
+        YandexMusicProfile::recommended_chunk_size(self)
+    }
+}
+
 /// Парсинг Yandex Music запроса/ответа
 pub struct YandexMusicParser;
 
@@ -228,7 +368,8 @@ impl YandexMusicParser {
             return Err(MimicryError::ParseError("No payload in response".to_string()).into());
         }
 
-        Ok(Bytes::copy_from_slice(&data[header_size..]))
+        // Тело — медиаконтейнер по Content-Type
+        container::decode_body(resp.headers, &data[header_size..])
     }
 }
 
@@ -259,8 +400,11 @@ mod tests {
         assert!(response_str.contains("Content-Type: audio/"));
         assert!(response_str.contains("X-Yandex-Music-Session: "));
 
-        // Проверка, что payload в конце
-        assert!(response.ends_with(payload));
+        // Тело — медиаконтейнер по Content-Type, а не голый payload
+        let content_type = response_str.split("Content-Type: ").nth(1).unwrap();
+        let content_type = content_type.split("\r\n").next().unwrap();
+        assert_ne!(Container::for_content_type(content_type), Container::Raw);
+        assert_eq!(&YandexMusicParser::extract_response_payload(&response).unwrap()[..], payload);
     }
 
     #[test]
@@ -273,6 +417,70 @@ mod tests {
         assert_eq!(&extracted[..], original_payload);
     }
 
+    #[test]
+    fn test_upload_round_trip() {
+        let mut profile = YandexMusicProfile::new();
+
+        let small = profile.generate_upload(b"upstream", 12345);
+        assert!(small.starts_with(b"GET /get-"));
+        assert!(String::from_utf8_lossy(&small).contains("?sign="));
+        assert_eq!(&Profile::unwrap_request(&profile, &small).unwrap()[..], b"upstream");
+
+        let payload = vec![0xabu8; MAX_INLINE_PAYLOAD + 1];
+        let large = profile.generate_upload(&payload, 12345);
+        assert!(large.starts_with(b"POST /api/v2.1/handlers/feedback"));
+        assert_eq!(Profile::unwrap_request(&profile, &large).unwrap(), payload);
+    }
+
+    /// Значение заголовка сообщения
+    fn header<'a>(message: &'a str, name: &str) -> &'a str {
+        let start = message.find(&format!("{}: ", name)).unwrap() + name.len() + 2;
+        message[start..].split("\r\n").next().unwrap()
+    }
+
+    #[test]
+    fn test_session_is_consistent() {
+        let mut profile = YandexMusicProfile::new();
+        let requests = [
+            Profile::generate_request(&mut profile, 1).unwrap(),
+            Profile::wrap_request(&mut profile, b"up", 2).unwrap(),
+        ];
+        let requests: Vec<_> = requests
+            .iter()
+            .map(|r| String::from_utf8_lossy(r))
+            .collect();
+
+        // Тот же трек, устройство и cookie jar
+        let track = |request: &str| {
+            let target = request.split(' ').nth(1).unwrap();
+            target.split('?').next().unwrap().to_string()
+        };
+        assert_eq!(track(&requests[0]), track(&requests[1]));
+        for name in [
+            "User-Agent",
+            "X-Yandex-Music-Session",
+            "X-Yandex-Music-Bitrate",
+            "Cookie",
+        ] {
+            assert_eq!(header(&requests[0], name), header(&requests[1], name));
+        }
+
+        let first = profile.generate_response(b"a");
+        let second = profile.generate_response(b"b");
+        let (first, second) = (
+            String::from_utf8_lossy(&first),
+            String::from_utf8_lossy(&second),
+        );
+        for name in [
+            "Content-Type",
+            "X-Yandex-Music-Session",
+            "ETag",
+            "Last-Modified",
+        ] {
+            assert_eq!(header(&first, name), header(&second, name));
+        }
+    }
+
     #[test]
     fn test_chunk_size() {
         let mut profile = YandexMusicProfile::new();
@@ -0,0 +1,373 @@
+//! Реестр профилей мимикрии
+//!
+//! Сопоставляет идентификатор профиля на проводе с фабрикой реализации
+//! [`Profile`]. Встроенные профили (none, VK Video, Яндекс.Музыка, RuTube)
+//! регистрируются автоматически; сторонние крейты добавляют свои через
+//! [`ProfileRegistry::register`] или глобальный реестр [`global`], который
+//! используют [`crate::PacketWrapper::new`], клиент и сервер.
+//!
+//! ```rust,no_run
+//! use llp_mimicry::registry;
+//! use llp_mimicry::profiles::PassthroughProfile;
+//!
+//! # fn main() -> llp_mimicry::Result<()> {
+//! registry::register_global(0x8001, "my_service", |_rng, _clock| {
+//!     Box::new(PassthroughProfile)
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use llp_core::clock::{SharedClock, SystemClock};
+use llp_core::packet::{MimicryProfile, CUSTOM_PROFILE_ID_MIN};
+use rand::rngs::OsRng;
+
+use crate::error::{MimicryError, Result};
+use crate::profiles::{
+    BoxedRng, PassthroughProfile, Profile, ProfileId, RuTubeProfile, VkVideoProfile,
+    YandexMusicProfile,
+};
+
+/// Фабрика профиля: создаёт экземпляр с заданными источниками случайности и времени
+pub type ProfileFactory = Arc<dyn Fn(BoxedRng, SharedClock) -> Box<dyn Profile> + Send + Sync>;
+
+/// Зарегистрированный профиль
+#[derive(Clone)]
+struct Entry {
+    name: String,
+    factory: ProfileFactory,
+}
+
+/// Реестр профилей мимикрии, ключ — ID профиля
+#[derive(Clone, Default)]
+pub struct ProfileRegistry {
+    entries: BTreeMap<ProfileId, Entry>,
+}
+
+impl ProfileRegistry {
+    /// Пустой реестр
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Реестр со встроенными профилями
+    pub fn builtin() -> Self {
+        let mut registry = Self::empty();
+        let builtin: [(MimicryProfile, &str, ProfileFactory); 4] = [
+            (
+                MimicryProfile::None,
+                "none",
+                Arc::new(|_, _| Box::new(PassthroughProfile)),
+            ),
+            (
+                MimicryProfile::VkVideo,
+                "vk_video",
+                Arc::new(|rng, clock| Box::new(VkVideoProfile::with_sources(rng, clock))),
+            ),
+            (
+                MimicryProfile::YandexMusic,
+                "yandex_music",
+                Arc::new(|rng, clock| Box::new(YandexMusicProfile::with_sources(rng, clock))),
+            ),
+            (
+                MimicryProfile::RuTube,
+                "rutube",
+                Arc::new(|rng, clock| Box::new(RuTubeProfile::with_sources(rng, clock))),
+            ),
+        ];
+
+        for (profile, name, factory) in builtin {
+            registry.entries.insert(
+                profile.to_u16(),
+                Entry {
+                    name: name.to_string(),
+                    factory,
+                },
+            );
+        }
+        registry
+    }
+
+    /// Зарегистрировать профиль
+    ///
+    /// ID должен быть не меньше [`CUSTOM_PROFILE_ID_MIN`], ID и имя —
+    /// уникальны; уже зарегистрированный профиль не заменяется.
+    pub fn register<F>(&mut self, id: ProfileId, name: &str, factory: F) -> Result<()>
+    where
+        F: Fn(BoxedRng, SharedClock) -> Box<dyn Profile> + Send + Sync + 'static,
+    {
+        if self.entries.contains_key(&id) {
+            return Err(MimicryError::DuplicateProfile(format!("0x{:04x}", id)));
+        }
+        if self.id_by_name(name).is_some() {
+            return Err(MimicryError::DuplicateProfile(name.to_string()));
+        }
+        if id < CUSTOM_PROFILE_ID_MIN {
+            return Err(MimicryError::UnsupportedProfile(format!(
+                "ID 0x{:04x} зарезервирован за встроенными профилями",
+                id
+            )));
+        }
+
+        self.entries.insert(
+            id,
+            Entry {
+                name: name.to_string(),
+                factory: Arc::new(factory),
+            },
+        );
+        Ok(())
+    }
+
+    /// Зарегистрировать профиль или заменить уже зарегистрированный
+    ///
+    /// Используется шаблонами профилей при (пере)загрузке: ID встроенного
+    /// профиля можно переопределить, новый ID должен быть не меньше
+    /// [`CUSTOM_PROFILE_ID_MIN`]. Имя не должно принадлежать другому ID.
+    /// Уже созданные экземпляры профиля продолжают работать со старой
+    /// реализацией.
+    pub fn replace<F>(&mut self, id: ProfileId, name: &str, factory: F) -> Result<()>
+    where
+        F: Fn(BoxedRng, SharedClock) -> Box<dyn Profile> + Send + Sync + 'static,
+    {
+        if let Some(other) = self.id_by_name(name).filter(|other| *other != id) {
+            return Err(MimicryError::DuplicateProfile(format!(
+                "{} (ID 0x{:04x})",
+                name, other
+            )));
+        }
+        if id < CUSTOM_PROFILE_ID_MIN && !self.contains(id) {
+            return Err(MimicryError::UnsupportedProfile(format!(
+                "ID 0x{:04x} зарезервирован за встроенными профилями",
+                id
+            )));
+        }
+
+        self.entries.insert(
+            id,
+            Entry {
+                name: name.to_string(),
+                factory: Arc::new(factory),
+            },
+        );
+        Ok(())
+    }
+
+    /// Удалить профиль
+    ///
+    /// Для ID встроенного профиля восстанавливается встроенная реализация.
+    pub fn remove(&mut self, id: ProfileId) {
+        match ProfileRegistry::builtin().entries.remove(&id) {
+            Some(builtin) => {
+                self.entries.insert(id, builtin);
+            }
+            None => {
+                self.entries.remove(&id);
+            }
+        }
+    }
+
+    /// Зарегистрирован ли профиль
+    pub fn contains(&self, id: ProfileId) -> bool {
+        self.entries.contains_key(&id)
+    }
+
+    /// Имя профиля по ID
+    pub fn name(&self, id: ProfileId) -> Option<&str> {
+        self.entries.get(&id).map(|e| e.name.as_str())
+    }
+
+    /// ID профиля по имени (без учёта регистра)
+    pub fn id_by_name(&self, name: &str) -> Option<ProfileId> {
+        self.entries
+            .iter()
+            .find(|(_, e)| e.name.eq_ignore_ascii_case(name))
+            .map(|(id, _)| *id)
+    }
+
+    /// Профиль по имени из конфигурации
+    pub fn profile_by_name(&self, name: &str) -> Option<MimicryProfile> {
+        self.id_by_name(name).and_then(MimicryProfile::from_u16)
+    }
+
+    /// Зарегистрированные ID в порядке возрастания
+    pub fn ids(&self) -> impl Iterator<Item = ProfileId> + '_ {
+        self.entries.keys().copied()
+    }
+
+    /// Создать экземпляр профиля
+    pub fn create(&self, id: ProfileId) -> Result<Box<dyn Profile>> {
+        self.create_with_sources(id, Box::new(OsRng), SystemClock::shared())
+    }
+
+    /// Создать экземпляр профиля с заданными источниками случайности и времени
+    pub fn create_with_sources(
+        &self,
+        id: ProfileId,
+        rng: BoxedRng,
+        clock: SharedClock,
+    ) -> Result<Box<dyn Profile>> {
+        let entry = self
+            .entries
+            .get(&id)
+            .ok_or_else(|| MimicryError::UnsupportedProfile(format!("0x{:04x}", id)))?;
+        Ok((entry.factory)(rng, clock))
+    }
+}
+
+/// Глобальный реестр процесса (изначально — встроенные профили)
+pub fn global() -> &'static RwLock<ProfileRegistry> {
+    static GLOBAL: OnceLock<RwLock<ProfileRegistry>> = OnceLock::new();
+    GLOBAL.get_or_init(|| RwLock::new(ProfileRegistry::builtin()))
+}
+
+/// Зарегистрировать профиль в глобальном реестре
+pub fn register_global<F>(id: ProfileId, name: &str, factory: F) -> Result<()>
+where
+    F: Fn(BoxedRng, SharedClock) -> Box<dyn Profile> + Send + Sync + 'static,
+{
+    global()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .register(id, name, factory)
+}
+
+/// Профиль по имени из конфигурации (глобальный реестр)
+pub fn profile_by_name(name: &str) -> Option<MimicryProfile> {
+    global()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .profile_by_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use std::time::Duration;
+
+    /// Тестовый профиль: payload в base16 с префиксом
+    struct HexProfile;
+
+    impl Profile for HexProfile {
+        fn id(&self) -> ProfileId {
+            0x8001
+        }
+
+        fn name(&self) -> &str {
+            "hex"
+        }
+
+        fn wrap(&mut self, packet: &[u8]) -> Result<Bytes> {
+            Ok(Bytes::from(format!("HEX:{}", hex::encode(packet))))
+        }
+
+        fn unwrap(&self, data: &[u8]) -> Result<Bytes> {
+            let text = data
+                .strip_prefix(b"HEX:")
+                .ok_or_else(|| MimicryError::ParseError("нет префикса".to_string()))?;
+            hex::decode(text)
+                .map(Bytes::from)
+                .map_err(|e| MimicryError::ParseError(e.to_string()))
+        }
+
+        fn wrap_request(&mut self, packet: &[u8], _chunk_index: u64) -> Result<Bytes> {
+            self.wrap(packet)
+        }
+
+        fn unwrap_request(&self, data: &[u8]) -> Result<Bytes> {
+            Profile::unwrap(self, data)
+        }
+
+        fn generate_request(&mut self, chunk_index: u64) -> Result<Bytes> {
+            Ok(Bytes::from(format!("GET /{}", chunk_index)))
+        }
+
+        fn next_packet_timing(&mut self) -> Duration {
+            Duration::from_millis(5)
+        }
+
+        fn recommended_chunk_size(&mut self) -> usize {
+            4096
+        }
+    }
+
+    #[test]
+    fn test_builtin_profiles() {
+        let registry = ProfileRegistry::builtin();
+        assert_eq!(registry.ids().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert_eq!(registry.name(1), Some("vk_video"));
+        assert_eq!(registry.profile_by_name("RuTube"), Some(MimicryProfile::RuTube));
+
+        for id in registry.ids() {
+            let profile = registry.create(id).unwrap();
+            assert_eq!(profile.id(), id);
+            assert_eq!(Some(profile.name()), registry.name(id));
+        }
+    }
+
+    #[test]
+    fn test_register_custom_profile() {
+        let mut registry = ProfileRegistry::builtin();
+        registry
+            .register(0x8001, "hex", |_, _| Box::new(HexProfile))
+            .unwrap();
+
+        assert_eq!(registry.profile_by_name("hex"), Some(MimicryProfile::Custom(0x8001)));
+
+        let mut profile = registry.create(0x8001).unwrap();
+        let wrapped = profile.wrap(b"\x01\x02").unwrap();
+        assert_eq!(&wrapped[..], b"HEX:0102");
+        assert_eq!(&profile.unwrap(&wrapped).unwrap()[..], b"\x01\x02");
+    }
+
+    #[test]
+    fn test_duplicate_registration_rejected() {
+        let mut registry = ProfileRegistry::builtin();
+        assert!(matches!(
+            registry.register(1, "other", |_, _| Box::new(HexProfile)),
+            Err(MimicryError::DuplicateProfile(_))
+        ));
+        assert!(matches!(
+            registry.register(0x8002, "VK_VIDEO", |_, _| Box::new(HexProfile)),
+            Err(MimicryError::DuplicateProfile(_))
+        ));
+        assert!(matches!(
+            registry.register(0x0004, "low", |_, _| Box::new(HexProfile)),
+            Err(MimicryError::UnsupportedProfile(_))
+        ));
+    }
+
+    #[test]
+    fn test_replace_and_remove() {
+        let mut registry = ProfileRegistry::builtin();
+
+        registry.replace(1, "vk_video", |_, _| Box::new(HexProfile)).unwrap();
+        let mut profile = registry.create(1).unwrap();
+        assert_eq!(&profile.wrap(b"\x01").unwrap()[..], b"HEX:01");
+
+        assert!(registry.replace(0x8001, "vk_video", |_, _| Box::new(HexProfile)).is_err());
+        assert!(registry.replace(0x0010, "low", |_, _| Box::new(HexProfile)).is_err());
+
+        registry.remove(1);
+        assert_eq!(registry.create(1).unwrap().name(), "vk_video");
+        let mut profile = registry.create(1).unwrap();
+        assert!(profile.wrap(b"\x01").unwrap().starts_with(b"HTTP/1.1"));
+
+        registry.replace(0x8001, "hex", |_, _| Box::new(HexProfile)).unwrap();
+        registry.remove(0x8001);
+        assert!(!registry.contains(0x8001));
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let registry = ProfileRegistry::builtin();
+        assert!(matches!(
+            registry.create(0x8003),
+            Err(MimicryError::UnsupportedProfile(_))
+        ));
+    }
+}
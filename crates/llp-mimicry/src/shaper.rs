@@ -0,0 +1,405 @@
+//! Планировщик отправки по паттерну профиля мимикрии
+//!
+//! [`TrafficShaper`] стоит между чтением IP пакетов из TUN и транспортом:
+//! пакеты копятся в очереди и уходят пачками до `burst_size` штук с
+//! паузой [`TimingProfile::next_delay`] после каждой пачки. Время пакета
+//! в очереди ограничено `max_latency`, а чувствительный к задержке трафик
+//! (короткие пакеты, DSCP EF) идёт в обход очереди.
+//!
+//! ```text
+//! пачка          пауза          пачка
+//! ▮▮▮▮▮ ─── next_delay ──── ▮▮▮ ─── next_delay ─── ...
+//! ```
+//!
+//! Планировщик не держит таймеров: вызывающий ставит пакеты в очередь
+//! ([`TrafficShaper::push`]), ждёт [`TrafficShaper::wait`] и забирает
+//! наступившие пакеты ([`TrafficShaper::poll`]).
+
+use bytes::Bytes;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::error::{MimicryError, Result};
+use crate::profiles::BoxedRng;
+use crate::timing::TimingProfile;
+
+/// DSCP Expedited Forwarding (RFC 3246): голос и видеозвонки
+pub const DSCP_EF: u8 = 46;
+
+/// Параметры планировщика
+#[derive(Debug, Clone)]
+pub struct ShapingOptions {
+    /// Предельное время пакета в очереди
+    pub max_latency: Duration,
+    /// Длина очереди, сверх которой пакеты уходят без ожидания паузы
+    pub max_queue: usize,
+    /// Пакеты не длиннее стольких байт идут в обход очереди (0 — никакие)
+    pub bypass_size: usize,
+    /// Пакеты с DSCP EF идут в обход очереди
+    pub bypass_expedited: bool,
+}
+
+impl Default for ShapingOptions {
+    fn default() -> Self {
+        Self {
+            max_latency: Duration::from_millis(50),
+            max_queue: 256,
+            bypass_size: 128,
+            bypass_expedited: true,
+        }
+    }
+}
+
+impl ShapingOptions {
+    /// Идёт ли IP пакет в обход очереди
+    pub fn is_latency_sensitive(&self, packet: &[u8]) -> bool {
+        packet.len() <= self.bypass_size
+            || (self.bypass_expedited && ip_dscp(packet) == Some(DSCP_EF))
+    }
+}
+
+/// Секция `[shaping]` конфигурации клиента и сервера
+///
+/// Пакеты из TUN ждут в очереди не дольше `max_latency_ms`; короткие
+/// пакеты и пакеты с DSCP EF уходят сразу. Профиль без паттерна отправки
+/// (`none`) не задерживает пакеты.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShapingConfig {
+    /// Планировать отправку по паттерну профиля
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// Предельная задержка пакета в очереди (мс)
+    #[serde(default = "default_max_latency")]
+    pub max_latency_ms: u64,
+
+    /// Длина очереди, сверх которой пакеты уходят без паузы
+    #[serde(default = "default_max_queue")]
+    pub max_queue: usize,
+
+    /// Пакеты не длиннее стольких байт уходят сразу (0 — никакие)
+    #[serde(default = "default_bypass_size")]
+    pub bypass_size: usize,
+
+    /// Пакеты с DSCP EF (голос, видеозвонки) уходят сразу
+    #[serde(default = "default_bypass_expedited")]
+    pub bypass_expedited: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_max_latency() -> u64 {
+    50
+}
+
+fn default_max_queue() -> usize {
+    256
+}
+
+fn default_bypass_size() -> usize {
+    128
+}
+
+fn default_bypass_expedited() -> bool {
+    true
+}
+
+impl Default for ShapingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            max_latency_ms: default_max_latency(),
+            max_queue: default_max_queue(),
+            bypass_size: default_bypass_size(),
+            bypass_expedited: default_bypass_expedited(),
+        }
+    }
+}
+
+impl ShapingConfig {
+    /// Параметры планировщика отправки
+    pub fn options(&self) -> ShapingOptions {
+        ShapingOptions {
+            max_latency: Duration::from_millis(self.max_latency_ms),
+            max_queue: self.max_queue,
+            bypass_size: self.bypass_size,
+            bypass_expedited: self.bypass_expedited,
+        }
+    }
+
+    /// Проверить значения секции
+    pub fn validate(&self) -> Result<()> {
+        if self.enabled && self.max_queue == 0 {
+            return Err(MimicryError::InvalidConfig(
+                "shaping.max_queue должен быть > 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// DSCP из заголовка IPv4 или IPv6 пакета
+pub fn ip_dscp(packet: &[u8]) -> Option<u8> {
+    let first = *packet.first()?;
+    let second = *packet.get(1)?;
+    match first >> 4 {
+        4 => Some(second >> 2),
+        // Traffic Class разбит между первыми двумя байтами
+        6 => Some(((first & 0x0F) << 2) | (second >> 6)),
+        _ => None,
+    }
+}
+
+/// Очередь отправки с паттерном пачек и пауз профиля
+pub struct TrafficShaper {
+    timing: TimingProfile,
+    options: ShapingOptions,
+    rng: BoxedRng,
+    /// Пакеты с моментом постановки в очередь
+    queue: VecDeque<(Instant, Bytes)>,
+    /// Сколько пакетов ещё уйдёт в текущей пачке без паузы
+    burst_left: usize,
+    /// Конец паузы после прошлой пачки
+    pause_until: Option<Instant>,
+}
+
+impl TrafficShaper {
+    /// Планировщик для паттерна профиля
+    pub fn new(timing: TimingProfile, options: ShapingOptions) -> Self {
+        Self::with_rng(timing, options, Box::new(OsRng))
+    }
+
+    /// Планировщик с заданным источником случайности
+    pub fn with_rng(timing: TimingProfile, options: ShapingOptions, rng: BoxedRng) -> Self {
+        Self {
+            timing,
+            options,
+            rng,
+            queue: VecDeque::new(),
+            burst_left: 0,
+            pause_until: None,
+        }
+    }
+
+    /// Параметры планировщика
+    pub fn options(&self) -> &ShapingOptions {
+        &self.options
+    }
+
+    /// Пакетов в очереди
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Пуста ли очередь
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Поставить IP пакет в очередь
+    ///
+    /// Чувствительный к задержке пакет возвращается обратно: его нужно
+    /// отправить сразу.
+    pub fn push(&mut self, packet: Bytes, now: Instant) -> Option<Bytes> {
+        if self.options.is_latency_sensitive(&packet) {
+            return Some(packet);
+        }
+        self.queue.push_back((now, packet));
+        None
+    }
+
+    /// Момент, когда уйдёт первый пакет очереди (`None` — очередь пуста)
+    pub fn next_release(&self) -> Option<Instant> {
+        let (queued, _) = self.queue.front()?;
+        if self.burst_left > 0 || self.queue.len() > self.options.max_queue {
+            return Some(*queued);
+        }
+        let deadline = *queued + self.options.max_latency;
+        Some(match self.pause_until {
+            Some(pause) => pause.max(*queued).min(deadline),
+            None => *queued,
+        })
+    }
+
+    /// Забрать пакет, время отправки которого наступило
+    ///
+    /// Пачка заканчивается после `burst_size` пакетов или когда очередь
+    /// опустела; за ней следует пауза профиля.
+    pub fn poll(&mut self, now: Instant) -> Option<Bytes> {
+        if self.next_release()? > now {
+            return None;
+        }
+        let (_, packet) = self.queue.pop_front()?;
+
+        if self.burst_left == 0 {
+            self.burst_left = self.timing.burst_size().max(1);
+        }
+        self.burst_left -= 1;
+        if self.burst_left == 0 || self.queue.is_empty() {
+            self.burst_left = 0;
+            self.pause_until = Some(now + self.timing.next_delay(&mut self.rng));
+        }
+        Some(packet)
+    }
+
+    /// Дождаться момента отправки первого пакета очереди
+    ///
+    /// С пустой очередью не завершается; отмена безопасна, поэтому вызов
+    /// подходит для ветки `tokio::select!` рядом с чтением TUN.
+    pub async fn wait(&self) {
+        match self.next_release() {
+            Some(at) => tokio::time::sleep_until(at.into()).await,
+            None => std::future::pending().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn shaper(timing: TimingProfile, options: ShapingOptions) -> TrafficShaper {
+        TrafficShaper::with_rng(timing, options, Box::new(StdRng::seed_from_u64(7)))
+    }
+
+    fn packet(len: usize, tag: u8) -> Bytes {
+        let mut packet = vec![tag; len];
+        packet[0] = 0x45;
+        packet[1] = 0x00;
+        Bytes::from(packet)
+    }
+
+    fn drain(shaper: &mut TrafficShaper, now: Instant) -> Vec<u8> {
+        std::iter::from_fn(|| shaper.poll(now))
+            .map(|packet| packet[2])
+            .collect()
+    }
+
+    #[test]
+    fn test_shaping_config() {
+        let mut config: ShapingConfig = toml::from_str("max_latency_ms = 20").unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.options().max_latency, Duration::from_millis(20));
+        assert_eq!(config.options().max_queue, 256);
+        assert!(config.validate().is_ok());
+
+        // Планировщик без очереди
+        config.max_queue = 0;
+        assert!(config.validate().is_err());
+        config.enabled = false;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_bursts_and_pauses() {
+        let options = ShapingOptions {
+            max_latency: Duration::from_secs(1),
+            ..Default::default()
+        };
+        let mut shaper = shaper(TimingProfile::new(30, 60, 0.0, 3), options);
+        let start = Instant::now();
+        for tag in 0..7 {
+            assert!(shaper.push(packet(1000, tag), start).is_none());
+        }
+
+        // Пачка из burst_size пакетов, затем пауза профиля
+        assert_eq!(drain(&mut shaper, start), vec![0, 1, 2]);
+        let pause = shaper.next_release().unwrap() - start;
+        assert!(pause >= Duration::from_millis(30) && pause < Duration::from_millis(60));
+        assert!(shaper
+            .poll(start + pause - Duration::from_millis(1))
+            .is_none());
+
+        assert_eq!(drain(&mut shaper, start + pause), vec![3, 4, 5]);
+        let next = shaper.next_release().unwrap();
+        assert_eq!(drain(&mut shaper, next), vec![6]);
+        assert!(shaper.is_empty());
+        assert!(shaper.next_release().is_none());
+    }
+
+    #[test]
+    fn test_latency_budget() {
+        let options = ShapingOptions {
+            max_latency: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let mut shaper = shaper(TimingProfile::new(400, 500, 0.0, 1), options);
+        let start = Instant::now();
+        shaper.push(packet(1000, 0), start);
+        assert_eq!(drain(&mut shaper, start), vec![0]);
+
+        // Пауза профиля длиннее бюджета: пакет ждёт не дольше max_latency
+        let queued = start + Duration::from_millis(10);
+        shaper.push(packet(1000, 1), queued);
+        assert_eq!(
+            shaper.next_release(),
+            Some(queued + Duration::from_millis(50))
+        );
+        assert_eq!(
+            drain(&mut shaper, queued + Duration::from_millis(50)),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn test_queue_overflow_skips_pause() {
+        let options = ShapingOptions {
+            max_latency: Duration::from_secs(1),
+            max_queue: 2,
+            ..Default::default()
+        };
+        let mut shaper = shaper(TimingProfile::new(400, 500, 0.0, 1), options);
+        let start = Instant::now();
+        shaper.push(packet(1000, 0), start);
+        assert_eq!(drain(&mut shaper, start), vec![0]);
+
+        for tag in 1..5 {
+            shaper.push(packet(1000, tag), start);
+        }
+        // Сверх max_queue пакеты уходят сразу, остаток ждёт паузу
+        assert_eq!(drain(&mut shaper, start), vec![1, 2]);
+        assert_eq!(shaper.len(), 2);
+    }
+
+    #[test]
+    fn test_latency_sensitive_bypass() {
+        let options = ShapingOptions::default();
+        let mut shaper = shaper(TimingProfile::video_streaming(), options);
+        let now = Instant::now();
+
+        // Короткий пакет (например, TCP ACK)
+        assert!(shaper.push(packet(52, 0), now).is_some());
+
+        // IPv4 с DSCP EF
+        let mut voice = packet(300, 0).to_vec();
+        voice[1] = DSCP_EF << 2;
+        assert!(shaper.push(Bytes::from(voice), now).is_some());
+
+        // IPv6 с DSCP EF в Traffic Class
+        let mut voice6 = vec![0u8; 300];
+        voice6[0] = 0x60 | (DSCP_EF >> 2);
+        voice6[1] = (DSCP_EF & 0x03) << 6;
+        assert_eq!(ip_dscp(&voice6), Some(DSCP_EF));
+        assert!(shaper.push(Bytes::from(voice6), now).is_some());
+
+        assert!(shaper.push(packet(300, 0), now).is_none());
+        assert_eq!(shaper.len(), 1);
+
+        // Обход можно отключить
+        let mut strict = self::shaper(
+            TimingProfile::video_streaming(),
+            ShapingOptions {
+                bypass_size: 0,
+                bypass_expedited: false,
+                ..Default::default()
+            },
+        );
+        assert!(strict.push(packet(52, 0), now).is_none());
+    }
+}
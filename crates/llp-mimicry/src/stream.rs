@@ -0,0 +1,242 @@
+//! Потоковый режим: много LLP пакетов в одном chunked ответе
+//!
+//! Видео и музыкальные CDN часто отдают длинный ответ с
+//! `Transfer-Encoding: chunked` вместо отдельного `206` на каждый
+//! фрагмент. В этом режиме сервер открывает один долгий ответ
+//! ([`crate::Profile::stream_head`]) и пишет в его тело LLP пакеты,
+//! каждый с префиксом длины `u32`. Границы chunk выбираются случайно и
+//! не совпадают с границами пакетов.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use rand::Rng;
+
+use crate::error::{MimicryError, Result};
+use crate::profiles::BoxedRng;
+
+/// Размер префикса длины пакета в теле потока
+const PACKET_PREFIX: usize = 4;
+
+/// Минимальный размер chunk по умолчанию
+pub const DEFAULT_MIN_CHUNK: usize = 512;
+
+/// Максимальный размер chunk по умолчанию
+pub const DEFAULT_MAX_CHUNK: usize = 16 * 1024;
+
+/// Максимальный размер пакета в потоке
+pub const MAX_STREAM_PACKET: usize = 64 * 1024;
+
+/// Кодировщик тела потокового ответа
+///
+/// Пакеты накапливаются через [`Self::push`], а [`Self::encode`]
+/// нарезает накопленные данные на chunk случайного размера.
+pub struct StreamEncoder {
+    rng: BoxedRng,
+    min_chunk: usize,
+    max_chunk: usize,
+    /// Размер следующего chunk
+    next_chunk: usize,
+    /// Данные, ещё не отправленные в chunk
+    pending: BytesMut,
+}
+
+impl StreamEncoder {
+    /// Кодировщик с размерами chunk по умолчанию
+    pub fn new(rng: BoxedRng) -> Self {
+        Self::with_chunk_sizes(rng, DEFAULT_MIN_CHUNK, DEFAULT_MAX_CHUNK)
+    }
+
+    /// Кодировщик с размерами chunk из диапазона `min..=max`
+    pub fn with_chunk_sizes(mut rng: BoxedRng, min: usize, max: usize) -> Self {
+        let min = min.max(1);
+        let max = max.max(min);
+        let next_chunk = rng.gen_range(min..=max);
+        Self {
+            rng,
+            min_chunk: min,
+            max_chunk: max,
+            next_chunk,
+            pending: BytesMut::new(),
+        }
+    }
+
+    /// Добавить LLP пакет в поток
+    pub fn push(&mut self, packet: &[u8]) -> Result<()> {
+        put_packet(&mut self.pending, packet)
+    }
+
+    /// Количество данных, ещё не отправленных в chunk
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Нарезать накопленные данные на chunk
+    ///
+    /// Без `flush` остаток меньше очередного размера chunk ждёт
+    /// следующих пакетов; с `flush` он отправляется коротким chunk.
+    pub fn encode(&mut self, flush: bool) -> Bytes {
+        let mut out = BytesMut::new();
+        while self.pending.len() >= self.next_chunk {
+            let chunk = self.pending.split_to(self.next_chunk);
+            encode_chunk(&mut out, &chunk);
+            self.next_chunk = self.rng.gen_range(self.min_chunk..=self.max_chunk);
+        }
+        if flush && !self.pending.is_empty() {
+            let chunk = self.pending.split();
+            encode_chunk(&mut out, &chunk);
+        }
+        out.freeze()
+    }
+
+    /// Завершить поток: остаток данных и последний chunk
+    pub fn finish(&mut self) -> Bytes {
+        let mut out = BytesMut::from(&self.encode(true)[..]);
+        out.put_slice(b"0\r\n\r\n");
+        out.freeze()
+    }
+}
+
+/// Записать LLP пакет с префиксом длины
+///
+/// Тот же формат использует [`crate::aggregate`] для тел сообщений.
+pub(crate) fn put_packet(out: &mut BytesMut, packet: &[u8]) -> Result<()> {
+    if packet.len() > MAX_STREAM_PACKET {
+        return Err(MimicryError::WrapError(format!(
+            "пакет {} байт больше {} для потока",
+            packet.len(),
+            MAX_STREAM_PACKET
+        )));
+    }
+    out.put_u32(packet.len() as u32);
+    out.put_slice(packet);
+    Ok(())
+}
+
+/// Записать один chunk: `<hex размер>\r\n<данные>\r\n`
+pub fn encode_chunk(out: &mut BytesMut, data: &[u8]) {
+    out.put_slice(format!("{:x}\r\n", data.len()).as_bytes());
+    out.put_slice(data);
+    out.put_slice(b"\r\n");
+}
+
+/// Декодер пакетов из тела потокового ответа
+///
+/// Принимает данные [`crate::codec::Frame::StreamData`] в любых
+/// границах и собирает из них LLP пакеты.
+#[derive(Debug, Default)]
+pub struct StreamDecoder {
+    buf: BytesMut,
+}
+
+impl StreamDecoder {
+    /// Новый декодер
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Добавить данные тела
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Есть ли незавершённый пакет
+    pub fn has_partial(&self) -> bool {
+        !self.buf.is_empty()
+    }
+
+    /// Извлечь следующий целый пакет
+    pub fn next_packet(&mut self) -> Result<Option<Bytes>> {
+        if self.buf.len() < PACKET_PREFIX {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes([self.buf[0], self.buf[1], self.buf[2], self.buf[3]]) as usize;
+        if len > MAX_STREAM_PACKET {
+            return Err(MimicryError::UnwrapError(format!(
+                "пакет {} байт больше {} в потоке",
+                len, MAX_STREAM_PACKET
+            )));
+        }
+        if self.buf.len() < PACKET_PREFIX + len {
+            return Ok(None);
+        }
+        self.buf.advance(PACKET_PREFIX);
+        Ok(Some(self.buf.split_to(len).freeze()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Frame, HttpDecoder};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_chunks_decoupled_from_packets() {
+        let mut encoder =
+            StreamEncoder::with_chunk_sizes(Box::new(StdRng::seed_from_u64(7)), 100, 300);
+        let packets: Vec<Vec<u8>> = (0..20u8).map(|i| vec![i; 50 + i as usize * 20]).collect();
+
+        let mut decoder = HttpDecoder::new().with_streaming(true);
+        decoder.extend(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n");
+        for packet in &packets {
+            encoder.push(packet).unwrap();
+            decoder.extend(&encoder.encode(false));
+        }
+        decoder.extend(&encoder.finish());
+
+        assert!(matches!(decoder.decode_frame().unwrap(), Some(Frame::StreamHead(_))));
+        let mut stream = StreamDecoder::new();
+        let mut received = Vec::new();
+        let mut chunks = 0;
+        loop {
+            match decoder.decode_frame().unwrap() {
+                Some(Frame::StreamData(data)) => {
+                    assert!(data.len() <= 300);
+                    chunks += 1;
+                    stream.extend(&data);
+                    while let Some(packet) = stream.next_packet().unwrap() {
+                        received.push(packet.to_vec());
+                    }
+                }
+                Some(Frame::StreamEnd) => break,
+                other => panic!("unexpected frame: {:?}", other),
+            }
+        }
+
+        assert_eq!(received, packets);
+        assert!(!stream.has_partial());
+        assert_ne!(chunks, packets.len());
+        assert!(!decoder.in_stream());
+    }
+
+    #[test]
+    fn test_stream_data_across_reads() {
+        let mut encoder = StreamEncoder::new(Box::new(StdRng::seed_from_u64(1)));
+        encoder.push(b"llp packet").unwrap();
+        let body = encoder.encode(true);
+
+        let mut decoder = HttpDecoder::new().with_streaming(true);
+        decoder.extend(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n");
+        assert!(matches!(decoder.decode_frame().unwrap(), Some(Frame::StreamHead(_))));
+
+        let mut stream = StreamDecoder::new();
+        for byte in body.iter() {
+            decoder.extend(&[*byte]);
+            while let Some(Frame::StreamData(data)) = decoder.decode_frame().unwrap() {
+                stream.extend(&data);
+            }
+        }
+        assert_eq!(&stream.next_packet().unwrap().unwrap()[..], b"llp packet");
+        assert!(decoder.in_stream());
+    }
+
+    #[test]
+    fn test_oversized_packet_rejected() {
+        let mut encoder = StreamEncoder::new(Box::new(StdRng::seed_from_u64(1)));
+        assert!(encoder.push(&vec![0u8; MAX_STREAM_PACKET + 1]).is_err());
+
+        let mut stream = StreamDecoder::new();
+        stream.extend(&(MAX_STREAM_PACKET as u32 + 1).to_be_bytes());
+        assert!(stream.next_packet().is_err());
+    }
+}
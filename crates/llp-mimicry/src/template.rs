@@ -0,0 +1,1326 @@
+//! Профили мимикрии из TOML шаблонов
+//!
+//! Шаблон описывает стартовую строку и заголовки (в порядке следования)
+//! запроса и ответа, генераторы значений, timing и размер chunk. Шаблоны
+//! загружаются при старте сервера и клиента и перечитываются на лету
+//! ([`TemplateDir`]), так что замена User-Agent или путей не требует
+//! пересборки.
+//!
+//! ```toml
+//! id = 0x8001
+//! name = "cdn_video"
+//!
+//! [request]
+//! start_line = "GET /video/{chunk}_{quality}.ts HTTP/1.1"
+//! headers = [
+//!     ["Host", "cdn.example.ru"],
+//!     ["User-Agent", "{user_agent}"],
+//! ]
+//!
+//! [upload]
+//! start_line = "GET /video/{chunk}_{quality}.ts?sign={payload} HTTP/1.1"
+//! headers = [["Host", "cdn.example.ru"], ["Range", "bytes=0-"]]
+//! carrier = { query = "sign" }
+//!
+//! [response]
+//! start_line = "HTTP/1.1 206 Partial Content"
+//! headers = [
+//!     ["Date", "{date}"],
+//!     ["Content-Length", "{content_length}"],
+//!     ["Content-Range", "bytes {range_start}-{range_end}/50000000"],
+//! ]
+//!
+//! [vars]
+//! user_agent = { choice = ["Mozilla/5.0 ...", "VKClient/8.34 ..."] }
+//! quality = { choice = ["480", "720"] }
+//! range_start = { range = [0, 10000000] }
+//! range_end = { end_of = "range_start" }
+//!
+//! [timing]
+//! min_delay_ms = 10
+//! max_delay_ms = 100
+//! burst_probability = 0.7
+//! burst_size = 5
+//!
+//! [chunk_size]
+//! min = 65536
+//! max = 262144
+//!
+//! [bitrate]
+//! mean_kbps = 2500
+//! period_ms = 4000
+//! duty = 0.35
+//!
+//! [tls]
+//! server_name = "cdn.example.ru"
+//! alpn = ["http/1.1"]
+//! fingerprint = "chrome_120"
+//! ```
+//!
+//! Подстановки `{имя}` ссылаются на переменные из `[vars]` или встроенные
+//! значения: `chunk` (номер chunk запроса), `content_length` (длина
+//! payload), `date` (текущая дата в HTTP формате), `payload` (пакет в
+//! base64url, только в `[upload]`). `{{` и `}}` — литеральные
+//! скобки. Каждая переменная вычисляется один раз на сообщение.
+//!
+//! Секция `[tls]` необязательна: без `server_name` SNI берётся из
+//! заголовка `Host` запроса, если в нём нет подстановок. `fingerprint`
+//! выбирает отпечаток ClientHello (`rustls`, `chrome_120`,
+//! `firefox_121`), по умолчанию — `rustls`.
+//!
+//! Тело ответа укладывается в медиаконтейнер по заголовку `Content-Type`
+//! шаблона (`video/mp2t` — MPEG-TS, `audio/mpeg` — MP3 и т.д., см.
+//! [`crate::container`]); `content_length` — длина тела с контейнером.
+//!
+//! Секция `[bitrate]` задаёт кривую битрейта для cover traffic в простое
+//! ([`crate::cover::BitrateCurve`]); без неё поток в простое молчит.
+
+use bytes::{BufMut, Bytes, BytesMut};
+use llp_core::clock::{SharedClock, SystemClock};
+use llp_core::packet::CUSTOM_PROFILE_ID_MIN;
+use rand::{rngs::OsRng, Rng, RngCore};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use crate::container::{self, Container};
+use crate::cover::BitrateCurve;
+use crate::error::{MimicryError, Result};
+use crate::exchange::{self, Carrier, MAX_INLINE_PAYLOAD};
+use crate::profiles::{BoxedRng, Profile, ProfileId};
+use crate::registry::ProfileRegistry;
+use crate::timing::TimingProfile;
+
+/// Встроенные подстановки
+const BUILTIN_VARS: &[&str] = &["chunk", "content_length", "date", "payload"];
+
+/// Максимальная длина случайного hex значения (байт)
+const MAX_HEX_BYTES: usize = 64;
+
+/// Максимальное количество заголовков в сообщении
+const MAX_HEADERS: usize = 32;
+
+/// Шаблон профиля мимикрии (содержимое TOML файла)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileTemplate {
+    /// ID профиля на проводе (встроенный для переопределения или пользовательский)
+    pub id: ProfileId,
+
+    /// Имя профиля в конфигурации
+    pub name: String,
+
+    /// Шаблон HTTP запроса
+    pub request: MessageTemplate,
+
+    /// Шаблон HTTP ответа (в тело которого помещается LLP пакет)
+    pub response: MessageTemplate,
+
+    /// Шаблон запроса клиента с LLP пакетом
+    pub upload: UploadTemplate,
+
+    /// Генераторы значений
+    #[serde(default)]
+    pub vars: BTreeMap<String, Generator>,
+
+    /// Параметры timing
+    #[serde(default)]
+    pub timing: TimingTemplate,
+
+    /// Рекомендуемый размер chunk
+    #[serde(default)]
+    pub chunk_size: SizeRange,
+
+    /// Кривая битрейта для cover traffic
+    #[serde(default)]
+    pub bitrate: Option<BitrateTemplate>,
+
+    /// Параметры внешнего TLS слоя
+    #[serde(default)]
+    pub tls: TlsTemplate,
+}
+
+/// Шаблон HTTP сообщения
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MessageTemplate {
+    /// Стартовая строка (`GET /path HTTP/1.1` или `HTTP/1.1 200 OK`)
+    pub start_line: String,
+
+    /// Заголовки `[имя, значение]` в порядке отправки
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+}
+
+/// Шаблон запроса клиента с payload
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UploadTemplate {
+    /// Стартовая строка запроса
+    pub start_line: String,
+
+    /// Заголовки `[имя, значение]` в порядке отправки
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+
+    /// Место payload: `"body"`, `{ query = "имя" }` или `{ cookie = "имя" }`
+    pub carrier: Carrier,
+}
+
+/// Генератор значения переменной
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Generator {
+    /// Случайный элемент списка
+    Choice(Vec<String>),
+    /// Случайные байты в hex (количество байт)
+    Hex(usize),
+    /// Случайное число из полуинтервала `[min, max)`
+    Range([u64; 2]),
+    /// Конец диапазона: значение переменной-`range` + `content_length` - 1
+    EndOf(String),
+}
+
+/// Параметры timing шаблона
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimingTemplate {
+    /// Минимальная задержка между пакетами (мс)
+    pub min_delay_ms: u64,
+    /// Максимальная задержка между пакетами (мс)
+    pub max_delay_ms: u64,
+    /// Вероятность burst (0.0 - 1.0)
+    pub burst_probability: f64,
+    /// Размер burst (количество пакетов)
+    pub burst_size: usize,
+}
+
+impl Default for TimingTemplate {
+    fn default() -> Self {
+        Self {
+            min_delay_ms: 20,
+            max_delay_ms: 500,
+            burst_probability: 0.5,
+            burst_size: 3,
+        }
+    }
+}
+
+/// Диапазон размеров `[min, max]`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SizeRange {
+    /// Минимальный размер (байт)
+    pub min: usize,
+    /// Максимальный размер (байт)
+    pub max: usize,
+}
+
+impl Default for SizeRange {
+    fn default() -> Self {
+        Self {
+            min: 64 * 1024,
+            max: 256 * 1024,
+        }
+    }
+}
+
+/// Кривая битрейта шаблона (см. [`BitrateCurve`])
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BitrateTemplate {
+    /// Средний битрейт потока сервера (кбит/с)
+    pub mean_kbps: u32,
+    /// Период загрузки сегмента (мс)
+    pub period_ms: u64,
+    /// Доля периода, в которую идёт загрузка (0.0 - 1.0)
+    pub duty: f64,
+    /// Доля битрейта сервера в потоке клиента (0.0 - 1.0)
+    #[serde(default = "default_upload_ratio")]
+    pub upload_ratio: f64,
+}
+
+fn default_upload_ratio() -> f64 {
+    0.02
+}
+
+/// Параметры внешнего TLS слоя (см. [`crate::tls`])
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsTemplate {
+    /// SNI (по умолчанию — литеральный `Host` запроса)
+    #[serde(default)]
+    pub server_name: Option<String>,
+
+    /// Протоколы ALPN в порядке предпочтения
+    #[serde(default = "default_alpn")]
+    pub alpn: Vec<String>,
+
+    /// Отпечаток ClientHello
+    #[serde(default = "default_fingerprint")]
+    pub fingerprint: crate::tls::Fingerprint,
+}
+
+impl Default for TlsTemplate {
+    fn default() -> Self {
+        Self {
+            server_name: None,
+            alpn: default_alpn(),
+            fingerprint: default_fingerprint(),
+        }
+    }
+}
+
+fn default_alpn() -> Vec<String> {
+    vec![String::from_utf8_lossy(crate::tls::DEFAULT_ALPN).into_owned()]
+}
+
+fn default_fingerprint() -> crate::tls::Fingerprint {
+    crate::tls::Fingerprint::Rustls
+}
+
+impl ProfileTemplate {
+    /// Разобрать шаблон из TOML
+    pub fn from_toml(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| MimicryError::InvalidTemplate(e.to_string()))
+    }
+
+    /// Загрузить шаблон из файла
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            MimicryError::InvalidTemplate(format!("{}: {}", path.display(), e))
+        })?;
+        Self::from_toml(&content)
+    }
+
+    /// Проверить шаблон и подготовить его к генерации трафика
+    pub fn compile(&self) -> Result<CompiledTemplate> {
+        if self.name.is_empty() || !self.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return invalid(format!("имя профиля '{}' должно состоять из [A-Za-z0-9_]", self.name));
+        }
+        if self.id == 0 || (self.id > 3 && self.id < CUSTOM_PROFILE_ID_MIN) {
+            return invalid(format!("ID 0x{:04x} нельзя задать шаблоном", self.id));
+        }
+
+        for (name, generator) in &self.vars {
+            if BUILTIN_VARS.contains(&name.as_str()) {
+                return invalid(format!("переменная '{}' совпадает со встроенной", name));
+            }
+            match generator {
+                Generator::Choice(values) if values.is_empty() => {
+                    return invalid(format!("{}: пустой список choice", name));
+                }
+                Generator::Choice(values) => {
+                    for value in values {
+                        check_header_text(name, value)?;
+                    }
+                }
+                Generator::Hex(len) if *len == 0 || *len > MAX_HEX_BYTES => {
+                    return invalid(format!("{}: hex должен быть 1..={} байт", name, MAX_HEX_BYTES));
+                }
+                Generator::Range([min, max]) if min >= max => {
+                    return invalid(format!("{}: пустой диапазон [{}, {})", name, min, max));
+                }
+                Generator::EndOf(source)
+                    if !matches!(self.vars.get(source), Some(Generator::Range(_))) =>
+                {
+                    return invalid(format!("{}: end_of должен ссылаться на range", name));
+                }
+                _ => {}
+            }
+        }
+
+        let timing = &self.timing;
+        if timing.min_delay_ms >= timing.max_delay_ms {
+            return invalid("timing: min_delay_ms должен быть меньше max_delay_ms".to_string());
+        }
+        if !(0.0..=1.0).contains(&timing.burst_probability) {
+            return invalid("timing: burst_probability вне диапазона 0.0-1.0".to_string());
+        }
+        if self.chunk_size.min == 0 || self.chunk_size.min > self.chunk_size.max {
+            return invalid("chunk_size: требуется 0 < min <= max".to_string());
+        }
+        let bitrate = self.bitrate.as_ref().map(|bitrate| BitrateCurve {
+            mean_kbps: bitrate.mean_kbps,
+            period: Duration::from_millis(bitrate.period_ms),
+            duty: bitrate.duty,
+            upload_ratio: bitrate.upload_ratio,
+        });
+        if bitrate.is_some_and(|curve| !curve.is_valid()) {
+            return invalid("bitrate: требуется period_ms > 0, 0 < duty <= 1, upload_ratio 0.0-1.0".to_string());
+        }
+
+        let request = self.compile_message("request", &self.request)?;
+        let request_line = &self.request.start_line;
+        if request_line.split(' ').count() != 3 || !request_line.ends_with(" HTTP/1.1") {
+            return invalid("request: стартовая строка должна быть 'METHOD PATH HTTP/1.1'".to_string());
+        }
+
+        let upload_message = MessageTemplate {
+            start_line: self.upload.start_line.clone(),
+            headers: self.upload.headers.clone(),
+        };
+        let upload = self.compile_message("upload", &upload_message)?;
+        self.check_upload()?;
+
+        let response = self.compile_message("response", &self.response)?;
+        if !self.response.start_line.starts_with("HTTP/1.1 ") {
+            return invalid("response: стартовая строка должна начинаться с 'HTTP/1.1 '".to_string());
+        }
+        let has_length = self.response.headers.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case("content-length") && value == "{content_length}"
+        });
+        if !has_length {
+            return invalid("response: нужен заголовок 'Content-Length: {content_length}'".to_string());
+        }
+
+        let server_name = match &self.tls.server_name {
+            Some(name) => Some(name.clone()),
+            None => self
+                .request
+                .headers
+                .iter()
+                .find(|(name, value)| name.eq_ignore_ascii_case("host") && !value.contains('{'))
+                .map(|(_, value)| value.clone()),
+        };
+        let server_header = self
+            .response
+            .headers
+            .iter()
+            .find(|(name, value)| name.eq_ignore_ascii_case("server") && !value.contains('{'))
+            .map(|(_, value)| value.clone());
+        if let Some(name) = &server_name {
+            crate::tls::check_server_name(name)
+                .map_err(|e| MimicryError::InvalidTemplate(format!("tls: {}", e)))?;
+        }
+        if self.tls.alpn.is_empty()
+            || self.tls.alpn.iter().any(|p| p.is_empty() || p.len() > 255)
+        {
+            return invalid("tls: alpn должен содержать протоколы длиной 1..=255".to_string());
+        }
+
+        Ok(CompiledTemplate {
+            id: self.id,
+            name: self.name.clone(),
+            request,
+            upload,
+            carrier: self.upload.carrier.clone(),
+            response,
+            vars: self.vars.clone(),
+            timing: TimingProfile::new(
+                timing.min_delay_ms,
+                timing.max_delay_ms,
+                timing.burst_probability,
+                timing.burst_size,
+            ),
+            chunk_size: self.chunk_size.min..=self.chunk_size.max,
+            bitrate,
+            server_name,
+            alpn: self.tls.alpn.iter().map(|p| p.as_bytes().to_vec()).collect(),
+            fingerprint: self.tls.fingerprint,
+            server_header,
+        })
+    }
+
+    /// Проверить, что payload действительно попадает в выбранное место запроса
+    fn check_upload(&self) -> Result<()> {
+        let upload = &self.upload;
+        let start_line = &upload.start_line;
+        if start_line.split(' ').count() != 3 || !start_line.ends_with(" HTTP/1.1") {
+            return invalid("upload: стартовая строка должна быть 'METHOD PATH HTTP/1.1'".to_string());
+        }
+
+        let uses_payload = start_line.contains("{payload}")
+            || upload.headers.iter().any(|(_, value)| value.contains("{payload}"));
+
+        let placed = match &upload.carrier {
+            Carrier::Body => {
+                !uses_payload
+                    && upload.headers.iter().any(|(name, value)| {
+                        name.eq_ignore_ascii_case("content-length") && value == "{content_length}"
+                    })
+            }
+            Carrier::Query(param) => {
+                let pair = format!("{}={{payload}}", param);
+                start_line.contains(&format!("?{}", pair)) || start_line.contains(&format!("&{}", pair))
+            }
+            Carrier::Cookie(cookie) => {
+                let pair = format!("{}={{payload}}", cookie);
+                upload.headers.iter().any(|(name, value)| {
+                    name.eq_ignore_ascii_case("cookie") && value.contains(&pair)
+                })
+            }
+        };
+
+        if !placed {
+            let expected = match &upload.carrier {
+                Carrier::Body => "заголовок 'Content-Length: {content_length}' без {payload}".to_string(),
+                Carrier::Query(param) => format!("'?{}={{payload}}' в стартовой строке", param),
+                Carrier::Cookie(cookie) => format!("'Cookie: {}={{payload}}'", cookie),
+            };
+            return invalid(format!("upload: нужен {}", expected));
+        }
+        Ok(())
+    }
+
+    fn compile_message(&self, kind: &str, message: &MessageTemplate) -> Result<CompiledMessage> {
+        if message.headers.len() > MAX_HEADERS {
+            return invalid(format!("{}: больше {} заголовков", kind, MAX_HEADERS));
+        }
+
+        let start_line = self.compile_text(kind, &message.start_line)?;
+        let mut headers = Vec::with_capacity(message.headers.len());
+        for (name, value) in &message.headers {
+            let valid_name = !name.is_empty()
+                && name.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_".contains(&b));
+            if !valid_name {
+                return invalid(format!("{}: некорректное имя заголовка '{}'", kind, name));
+            }
+            headers.push((name.clone(), self.compile_text(kind, value)?));
+        }
+
+        Ok(CompiledMessage { start_line, headers })
+    }
+
+    fn compile_text(&self, kind: &str, text: &str) -> Result<Vec<Segment>> {
+        check_header_text(kind, text)?;
+        let segments = parse_segments(text)
+            .map_err(|e| MimicryError::InvalidTemplate(format!("{}: {}", kind, e)))?;
+
+        for segment in &segments {
+            if let Segment::Var(name) = segment {
+                if !self.vars.contains_key(name) && !BUILTIN_VARS.contains(&name.as_str()) {
+                    return invalid(format!("{}: неизвестная переменная '{{{}}}'", kind, name));
+                }
+                if name == "payload" && kind != "upload" {
+                    return invalid(format!("{}: {{payload}} допустим только в upload", kind));
+                }
+            }
+        }
+        Ok(segments)
+    }
+}
+
+fn invalid<T>(message: String) -> Result<T> {
+    Err(MimicryError::InvalidTemplate(message))
+}
+
+/// Значения заголовков не должны разрывать HTTP сообщение
+fn check_header_text(context: &str, text: &str) -> Result<()> {
+    if text.contains(['\r', '\n']) {
+        return invalid(format!("{}: перевод строки в значении", context));
+    }
+    Ok(())
+}
+
+/// Часть строки шаблона
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Var(String),
+}
+
+fn parse_segments(text: &str) -> std::result::Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) if c.is_ascii_alphanumeric() || c == '_' => name.push(c),
+                        _ => return Err(format!("незакрытая подстановка в '{}'", text)),
+                    }
+                }
+                if name.is_empty() {
+                    return Err(format!("пустая подстановка в '{}'", text));
+                }
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Var(name));
+            }
+            '}' => return Err(format!("лишняя '}}' в '{}'", text)),
+            c => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(segments)
+}
+
+/// Проверенный шаблон, готовый к генерации трафика
+#[derive(Debug, Clone)]
+pub struct CompiledTemplate {
+    id: ProfileId,
+    name: String,
+    request: CompiledMessage,
+    upload: CompiledMessage,
+    carrier: Carrier,
+    response: CompiledMessage,
+    vars: BTreeMap<String, Generator>,
+    timing: TimingProfile,
+    chunk_size: std::ops::RangeInclusive<usize>,
+    bitrate: Option<BitrateCurve>,
+    server_name: Option<String>,
+    alpn: Vec<Vec<u8>>,
+    fingerprint: crate::tls::Fingerprint,
+    server_header: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledMessage {
+    start_line: Vec<Segment>,
+    headers: Vec<(String, Vec<Segment>)>,
+}
+
+impl CompiledTemplate {
+    /// ID профиля
+    pub fn id(&self) -> ProfileId {
+        self.id
+    }
+
+    /// Имя профиля
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Зарегистрировать профиль в реестре (с заменой существующего)
+    pub fn install(self, registry: &mut ProfileRegistry) -> Result<()> {
+        let id = self.id;
+        let name = self.name.clone();
+        let template = Arc::new(self);
+        registry.replace(id, &name, move |rng, clock| {
+            Box::new(TemplateProfile::with_sources(Arc::clone(&template), rng, clock))
+        })
+    }
+}
+
+/// Профиль мимикрии, описанный шаблоном
+pub struct TemplateProfile {
+    template: Arc<CompiledTemplate>,
+    rng: BoxedRng,
+    clock: SharedClock,
+}
+
+impl TemplateProfile {
+    /// Создать профиль по шаблону
+    pub fn new(template: Arc<CompiledTemplate>) -> Self {
+        Self::with_sources(template, Box::new(OsRng), SystemClock::shared())
+    }
+
+    /// Создать профиль с заданными источниками случайности и времени
+    pub fn with_sources(template: Arc<CompiledTemplate>, rng: BoxedRng, clock: SharedClock) -> Self {
+        Self {
+            template,
+            rng,
+            clock,
+        }
+    }
+
+    /// Сформировать заголовок HTTP сообщения (до пустой строки включительно)
+    ///
+    /// `values` — уже вычисленные переменные сообщения.
+    fn render(
+        &mut self,
+        message: &CompiledMessage,
+        mut values: HashMap<String, String>,
+        chunk: u64,
+        content_length: usize,
+        payload: Option<String>,
+    ) -> String {
+        values.insert("chunk".to_string(), chunk.to_string());
+        values.insert("content_length".to_string(), content_length.to_string());
+        if let Some(payload) = payload {
+            values.insert("payload".to_string(), payload);
+        }
+
+        let mut out = String::new();
+        self.render_segments(&message.start_line, &mut values, content_length, &mut out);
+        out.push_str("\r\n");
+        for (name, value) in &message.headers {
+            out.push_str(name);
+            out.push_str(": ");
+            self.render_segments(value, &mut values, content_length, &mut out);
+            out.push_str("\r\n");
+        }
+        out.push_str("\r\n");
+        out
+    }
+
+    /// Контейнер тела ответа по заголовку `Content-Type` шаблона
+    ///
+    /// Переменные заголовка вычисляются здесь и сохраняются в `values`,
+    /// поэтому ответ получит тот же `Content-Type`.
+    fn response_container(&mut self, values: &mut HashMap<String, String>) -> Container {
+        let template = Arc::clone(&self.template);
+        let content_type = template
+            .response
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+        let Some((_, segments)) = content_type else {
+            return Container::Raw;
+        };
+        let mut content_type = String::new();
+        self.render_segments(segments, values, 0, &mut content_type);
+        Container::for_content_type(&content_type)
+    }
+
+    fn render_segments(
+        &mut self,
+        segments: &[Segment],
+        values: &mut HashMap<String, String>,
+        content_length: usize,
+        out: &mut String,
+    ) {
+        for segment in segments {
+            match segment {
+                Segment::Literal(text) => out.push_str(text),
+                Segment::Var(name) => out.push_str(&self.value(name, values, content_length)),
+            }
+        }
+    }
+
+    /// Значение переменной (вычисляется один раз на сообщение)
+    fn value(
+        &mut self,
+        name: &str,
+        values: &mut HashMap<String, String>,
+        content_length: usize,
+    ) -> String {
+        if let Some(value) = values.get(name) {
+            return value.clone();
+        }
+
+        let template = Arc::clone(&self.template);
+        let value = match (name, template.vars.get(name)) {
+            ("date", None) => http_date(&self.clock),
+            (_, Some(Generator::Choice(options))) => {
+                options[self.rng.gen_range(0..options.len())].clone()
+            }
+            (_, Some(Generator::Hex(len))) => {
+                let mut bytes = vec![0u8; *len];
+                self.rng.fill_bytes(&mut bytes);
+                hex::encode(bytes)
+            }
+            (_, Some(Generator::Range([min, max]))) => self.rng.gen_range(*min..*max).to_string(),
+            (_, Some(Generator::EndOf(source))) => {
+                let start: u64 = self
+                    .value(source, values, content_length)
+                    .parse()
+                    .unwrap_or_default();
+                (start + content_length as u64).saturating_sub(1).to_string()
+            }
+            // Неизвестные имена отсекаются при компиляции шаблона
+            _ => String::new(),
+        };
+
+        values.insert(name.to_string(), value.clone());
+        value
+    }
+}
+
+impl Profile for TemplateProfile {
+    fn id(&self) -> ProfileId {
+        self.template.id
+    }
+
+    fn name(&self) -> &str {
+        &self.template.name
+    }
+
+    fn tls_server_name(&self) -> Option<&str> {
+        self.template.server_name.as_deref()
+    }
+
+    fn alpn_protocols(&self) -> Vec<Vec<u8>> {
+        self.template.alpn.clone()
+    }
+
+    fn tls_fingerprint(&self) -> crate::tls::Fingerprint {
+        self.template.fingerprint
+    }
+
+    fn server_header(&self) -> Option<&str> {
+        self.template.server_header.as_deref()
+    }
+
+    fn wrap(&mut self, packet: &[u8]) -> Result<Bytes> {
+        let template = Arc::clone(&self.template);
+        let mut values = HashMap::new();
+        let container = self.response_container(&mut values);
+        let body = container.encode(packet, &mut self.rng);
+        let head = self.render(&template.response, values, 0, body.len(), None);
+
+        let mut response = BytesMut::with_capacity(head.len() + body.len());
+        response.put(head.as_bytes());
+        response.put(body);
+        Ok(response.freeze())
+    }
+
+    fn unwrap(&self, data: &[u8]) -> Result<Bytes> {
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut resp = httparse::Response::new(&mut headers);
+
+        let header_size = match resp
+            .parse(data)
+            .map_err(|e| MimicryError::ParseError(format!("HTTP parse error: {:?}", e)))?
+        {
+            httparse::Status::Complete(size) => size,
+            httparse::Status::Partial => {
+                return Err(MimicryError::ParseError("Incomplete HTTP response".to_string()));
+            }
+        };
+
+        if header_size >= data.len() {
+            return Err(MimicryError::ParseError("No payload in response".to_string()));
+        }
+
+        container::decode_body(resp.headers, &data[header_size..])
+    }
+
+    fn wrap_request(&mut self, packet: &[u8], chunk_index: u64) -> Result<Bytes> {
+        let template = Arc::clone(&self.template);
+
+        if template.carrier == Carrier::Body {
+            let head = self.render(&template.upload, HashMap::new(), chunk_index, packet.len(), None);
+            let mut request = BytesMut::with_capacity(head.len() + packet.len());
+            request.put(head.as_bytes());
+            request.put(packet);
+            return Ok(request.freeze());
+        }
+
+        if packet.len() > MAX_INLINE_PAYLOAD {
+            return Err(MimicryError::WrapError(format!(
+                "пакет {} байт не помещается в query/cookie (максимум {}), используйте carrier = \"body\"",
+                packet.len(),
+                MAX_INLINE_PAYLOAD
+            )));
+        }
+        let payload = exchange::encode_inline(packet);
+        Ok(Bytes::from(self.render(
+            &template.upload,
+            HashMap::new(),
+            chunk_index,
+            0,
+            Some(payload),
+        )))
+    }
+
+    fn unwrap_request(&self, data: &[u8]) -> Result<Bytes> {
+        exchange::extract_request_payload(data, &self.template.carrier)
+    }
+
+    fn generate_request(&mut self, chunk_index: u64) -> Result<Bytes> {
+        let template = Arc::clone(&self.template);
+        Ok(Bytes::from(self.render(&template.request, HashMap::new(), chunk_index, 0, None)))
+    }
+
+    fn next_packet_timing(&mut self) -> Duration {
+        self.template.timing.next_delay(&mut self.rng)
+    }
+
+    fn timing_profile(&self) -> Option<&TimingProfile> {
+        Some(&self.template.timing)
+    }
+
+    fn bitrate_curve(&self) -> Option<BitrateCurve> {
+        self.template.bitrate
+    }
+
+    fn recommended_chunk_size(&mut self) -> usize {
+        self.rng.gen_range(self.template.chunk_size.clone())
+    }
+}
+
+/// Текущая дата в HTTP формате
+fn http_date(clock: &SharedClock) -> String {
+    use chrono::{DateTime, Utc};
+    let unix = clock.unix_time();
+    DateTime::<Utc>::from_timestamp(unix.as_secs() as i64, 0)
+        .unwrap_or_default()
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+/// Итоги перезагрузки каталога шаблонов
+#[derive(Debug, Default)]
+pub struct ReloadReport {
+    /// Загруженные или обновлённые профили
+    pub loaded: Vec<String>,
+    /// ID профилей, чьи файлы удалены
+    pub removed: Vec<ProfileId>,
+    /// Файлы с ошибками (для них остаётся предыдущая версия)
+    pub errors: Vec<(PathBuf, MimicryError)>,
+}
+
+impl ReloadReport {
+    /// Были ли изменения
+    pub fn is_empty(&self) -> bool {
+        self.loaded.is_empty() && self.removed.is_empty() && self.errors.is_empty()
+    }
+}
+
+/// Состояние загруженного файла шаблона
+struct LoadedFile {
+    modified: Option<SystemTime>,
+    len: u64,
+    id: Option<ProfileId>,
+}
+
+/// Каталог шаблонов профилей (`*.toml`) с перезагрузкой при изменении
+pub struct TemplateDir {
+    path: PathBuf,
+    files: BTreeMap<PathBuf, LoadedFile>,
+}
+
+impl TemplateDir {
+    /// Каталог шаблонов (файлы читаются при первом [`Self::reload`])
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Путь к каталогу
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Первичная загрузка: любая ошибка в шаблоне — ошибка
+    pub fn load(&mut self, registry: &RwLock<ProfileRegistry>) -> Result<ReloadReport> {
+        let mut report = self.reload(registry)?;
+        match report.errors.pop() {
+            Some((path, error)) => invalid(format!("{}: {}", path.display(), error)),
+            None => Ok(report),
+        }
+    }
+
+    /// Перечитать изменённые, новые и удалённые файлы
+    ///
+    /// Шаблон с ошибкой не применяется, профиль продолжает работать
+    /// с предыдущей версией.
+    pub fn reload(&mut self, registry: &RwLock<ProfileRegistry>) -> Result<ReloadReport> {
+        let entries = std::fs::read_dir(&self.path).map_err(|e| {
+            MimicryError::InvalidTemplate(format!("{}: {}", self.path.display(), e))
+        })?;
+
+        let mut present = BTreeMap::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+                continue;
+            }
+            if let Ok(meta) = entry.metadata() {
+                present.insert(path, (meta.modified().ok(), meta.len()));
+            }
+        }
+
+        let mut report = ReloadReport::default();
+        let mut registry = registry.write().unwrap_or_else(|e| e.into_inner());
+
+        // Удалённые файлы
+        let removed: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter(|path| !present.contains_key(*path))
+            .cloned()
+            .collect();
+        for path in removed {
+            if let Some(id) = self.files.remove(&path).and_then(|f| f.id) {
+                registry.remove(id);
+                report.removed.push(id);
+            }
+        }
+
+        // Новые и изменённые файлы
+        for (path, (modified, len)) in present {
+            if let Some(loaded) = self.files.get(&path) {
+                if loaded.modified == modified && loaded.len == len {
+                    continue;
+                }
+            }
+
+            let previous = self.files.get(&path).and_then(|f| f.id);
+            let result = ProfileTemplate::from_file(&path)
+                .and_then(|template| template.compile())
+                .and_then(|compiled| {
+                    let owner = self
+                        .files
+                        .iter()
+                        .find(|(other, f)| **other != path && f.id == Some(compiled.id()));
+                    if let Some((other, _)) = owner {
+                        return invalid(format!(
+                            "ID 0x{:04x} уже задан в {}",
+                            compiled.id(),
+                            other.display()
+                        ));
+                    }
+                    let (id, name) = (compiled.id(), compiled.name().to_string());
+                    compiled.install(&mut registry)?;
+                    Ok((id, name))
+                });
+
+            let id = match result {
+                Ok((id, name)) => {
+                    if let Some(old) = previous.filter(|old| *old != id) {
+                        registry.remove(old);
+                        report.removed.push(old);
+                    }
+                    report.loaded.push(name);
+                    Some(id)
+                }
+                Err(error) => {
+                    report.errors.push((path.clone(), error));
+                    previous
+                }
+            };
+
+            self.files.insert(path, LoadedFile { modified, len, id });
+        }
+
+        Ok(report)
+    }
+
+    /// Периодически перечитывать каталог в фоне (глобальный реестр)
+    pub fn spawn_reloader(mut self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                match self.reload(crate::registry::global()) {
+                    Ok(report) => log_report(&self.path, &report),
+                    Err(e) => warn!("Не удалось перечитать шаблоны профилей: {}", e),
+                }
+            }
+        })
+    }
+}
+
+fn log_report(dir: &Path, report: &ReloadReport) {
+    if !report.loaded.is_empty() {
+        info!("Шаблоны профилей загружены из {}: {:?}", dir.display(), report.loaded);
+    }
+    for id in &report.removed {
+        info!("Шаблон профиля 0x{:04x} удалён", id);
+    }
+    for (path, error) in &report.errors {
+        warn!("Шаблон {} не применён: {}", path.display(), error);
+    }
+}
+
+/// Загрузить шаблоны каталога в глобальный реестр
+///
+/// Возвращает каталог для последующей перезагрузки ([`TemplateDir::spawn_reloader`]).
+pub fn load_global<P: Into<PathBuf>>(path: P) -> Result<TemplateDir> {
+    let mut dir = TemplateDir::new(path);
+    let report = dir.load(crate::registry::global())?;
+    log_report(&dir.path, &report);
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llp_core::clock::SimulatedClock;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const TEMPLATE: &str = r#"
+id = 0x8001
+name = "cdn_video"
+
+[request]
+start_line = "GET /video/{chunk}_{quality}.ts HTTP/1.1"
+headers = [
+    ["Host", "cdn.example.ru"],
+    ["User-Agent", "{user_agent}"],
+    ["X-Session", "{session}"],
+]
+
+[upload]
+start_line = "POST /stats/{chunk} HTTP/1.1"
+headers = [
+    ["Host", "cdn.example.ru"],
+    ["Content-Length", "{content_length}"],
+]
+carrier = "body"
+
+[response]
+start_line = "HTTP/1.1 206 Partial Content"
+headers = [
+    ["Date", "{date}"],
+    ["Content-Length", "{content_length}"],
+    ["Content-Range", "bytes {range_start}-{range_end}/50000000"],
+    ["X-Session", "{session}"],
+    ["X-Echo", "{session}{{x}}"],
+]
+
+[vars]
+user_agent = { choice = ["UA-1", "UA-2"] }
+quality = { choice = ["720"] }
+session = { hex = 4 }
+range_start = { range = [100, 101] }
+range_end = { end_of = "range_start" }
+
+[timing]
+min_delay_ms = 10
+max_delay_ms = 20
+burst_probability = 0.0
+burst_size = 1
+
+[chunk_size]
+min = 1000
+max = 1000
+"#;
+
+    fn profile() -> TemplateProfile {
+        let compiled = ProfileTemplate::from_toml(TEMPLATE).unwrap().compile().unwrap();
+        TemplateProfile::with_sources(
+            Arc::new(compiled),
+            Box::new(StdRng::seed_from_u64(1)),
+            SimulatedClock::new(1_700_000_000).shared(),
+        )
+    }
+
+    #[test]
+    fn test_render_response() {
+        let mut profile = profile();
+        let wrapped = profile.wrap(b"payload").unwrap();
+        let text = String::from_utf8_lossy(&wrapped);
+
+        assert!(text.starts_with("HTTP/1.1 206 Partial Content\r\nDate: Tue, 14 Nov 2023 22:13:20 GMT\r\n"));
+        assert!(text.contains("Content-Length: 7\r\n"));
+        assert!(text.contains("Content-Range: bytes 100-106/50000000\r\n"));
+
+        // Переменная вычисляется один раз на сообщение
+        let session = text.split("X-Session: ").nth(1).unwrap().split("\r\n").next().unwrap();
+        assert_eq!(session.len(), 8);
+        assert!(text.contains(&format!("X-Echo: {}{{x}}\r\n", session)));
+
+        assert_eq!(&profile.unwrap(&wrapped).unwrap()[..], b"payload");
+    }
+
+    #[test]
+    fn test_response_container() {
+        let toml = TEMPLATE
+            .replace(
+                r#"["X-Echo", "{session}{{x}}"],"#,
+                r#"["Content-Type", "{mime}"],"#,
+            )
+            .replace(
+                "[vars]\n",
+                "[vars]\nmime = { choice = [\"video/mp2t\", \"audio/mpeg\"] }\n",
+            );
+        let compiled = ProfileTemplate::from_toml(&toml).unwrap().compile().unwrap();
+        let mut profile = TemplateProfile::with_sources(
+            Arc::new(compiled),
+            Box::new(StdRng::seed_from_u64(5)),
+            SimulatedClock::new(1_700_000_000).shared(),
+        );
+
+        for _ in 0..8 {
+            let wrapped = profile.wrap(b"payload").unwrap();
+            let text = String::from_utf8_lossy(&wrapped);
+            let body_start = wrapped.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+            let body = &wrapped[body_start..];
+
+            // Content-Length — длина тела с контейнером
+            assert!(text.contains(&format!("Content-Length: {}\r\n", body.len())));
+            if text.contains("Content-Type: video/mp2t\r\n") {
+                assert_eq!(body[0], 0x47);
+                assert_eq!(body.len() % 188, 0);
+            } else {
+                assert!(text.contains("Content-Type: audio/mpeg\r\n"));
+                assert_eq!(&body[..2], &[0xFF, 0xFB]);
+            }
+            assert_eq!(&profile.unwrap(&wrapped).unwrap()[..], b"payload");
+        }
+    }
+
+    #[test]
+    fn test_render_request() {
+        let mut profile = profile();
+        let request = profile.generate_request(42).unwrap();
+        let text = String::from_utf8_lossy(&request);
+
+        assert!(text.starts_with("GET /video/42_720.ts HTTP/1.1\r\nHost: cdn.example.ru\r\n"));
+        assert!(text.contains("User-Agent: UA-"));
+        assert!(text.ends_with("\r\n\r\n"));
+
+        assert_eq!(profile.recommended_chunk_size(), 1000);
+        assert!(profile.next_packet_timing() >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_bitrate_curve() {
+        assert_eq!(profile().bitrate_curve(), None);
+
+        let source = format!("{}\n[bitrate]\nmean_kbps = 800\nperiod_ms = 6000\nduty = 0.25\n", TEMPLATE);
+        let compiled = ProfileTemplate::from_toml(&source).unwrap().compile().unwrap();
+        let curve = TemplateProfile::new(Arc::new(compiled)).bitrate_curve().unwrap();
+        assert_eq!(curve.mean_kbps, 800);
+        assert_eq!(curve.period, Duration::from_secs(6));
+        assert_eq!(curve.upload_ratio, 0.02);
+
+        let bad = source.replace("duty = 0.25", "duty = 1.5");
+        assert!(matches!(
+            ProfileTemplate::from_toml(&bad).unwrap().compile(),
+            Err(MimicryError::InvalidTemplate(_))
+        ));
+    }
+
+    #[test]
+    fn test_tls_parameters() {
+        let profile = profile();
+        assert_eq!(profile.tls_server_name(), Some("cdn.example.ru"));
+        assert_eq!(profile.alpn_protocols(), vec![b"http/1.1".to_vec()]);
+        assert_eq!(profile.tls_fingerprint(), crate::tls::Fingerprint::Rustls);
+        assert_eq!(profile.server_header(), None);
+
+        let source = format!("{}\n[tls]\nserver_name = \"edge.example.ru\"\nalpn = [\"h2\", \"http/1.1\"]\nfingerprint = \"firefox_121\"\n", TEMPLATE);
+        let compiled = ProfileTemplate::from_toml(&source).unwrap().compile().unwrap();
+        let profile = TemplateProfile::new(Arc::new(compiled));
+        assert_eq!(profile.tls_server_name(), Some("edge.example.ru"));
+        assert_eq!(profile.alpn_protocols()[0], b"h2");
+        assert_eq!(profile.tls_fingerprint(), crate::tls::Fingerprint::Firefox121);
+
+        let bad = format!("{}\n[tls]\nserver_name = \"bad name\"\n", TEMPLATE);
+        assert!(ProfileTemplate::from_toml(&bad).unwrap().compile().is_err());
+    }
+
+    #[test]
+    fn test_upload_carriers() {
+        let mut profile = profile();
+        let request = profile.wrap_request(b"upstream", 3).unwrap();
+        assert!(request.starts_with(b"POST /stats/3 HTTP/1.1\r\n"));
+        assert_eq!(&profile.unwrap_request(&request).unwrap()[..], b"upstream");
+
+        let source = TEMPLATE
+            .replace("POST /stats/{chunk} HTTP/1.1", "GET /seg/{chunk}.ts HTTP/1.1")
+            .replace("[\"Content-Length\", \"{content_length}\"],\n]\ncarrier = \"body\"",
+                "[\"Cookie\", \"uid={session}; sid={payload}\"],\n]\ncarrier = { cookie = \"sid\" }");
+        let compiled = ProfileTemplate::from_toml(&source).unwrap().compile().unwrap();
+        let mut profile = TemplateProfile::new(Arc::new(compiled));
+
+        let request = profile.wrap_request(b"upstream", 4).unwrap();
+        let text = String::from_utf8_lossy(&request);
+        assert!(text.starts_with("GET /seg/4.ts HTTP/1.1\r\n"));
+        assert!(text.contains("; sid="));
+        assert_eq!(&profile.unwrap_request(&request).unwrap()[..], b"upstream");
+
+        let oversized = vec![0u8; MAX_INLINE_PAYLOAD + 1];
+        assert!(matches!(
+            profile.wrap_request(&oversized, 5),
+            Err(MimicryError::WrapError(_))
+        ));
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let cases = [
+            ("{user_agent}", "{unknown}"),
+            ("name = \"cdn_video\"", "name = \"bad name\""),
+            ("id = 0x8001", "id = 0x0100"),
+            ("[\"Content-Length\", \"{content_length}\"],", ""),
+            ("{ hex = 4 }", "{ hex = 0 }"),
+            ("{ range = [100, 101] }", "{ range = [5, 5] }"),
+            ("{ end_of = \"range_start\" }", "{ end_of = \"session\" }"),
+            ("max_delay_ms = 20", "max_delay_ms = 5"),
+            ("\"UA-1\"", "\"UA\\r\\nX: 1\""),
+            ("carrier = \"body\"", "carrier = { query = \"sign\" }"),
+            ("/video/{chunk}_", "/video/{payload}_"),
+            ("/video/{chunk}_", "/video/{chunk_"),
+        ];
+
+        for (from, to) in cases {
+            let source = TEMPLATE.replacen(from, to, 1);
+            let result = ProfileTemplate::from_toml(&source).and_then(|t| t.compile());
+            assert!(
+                matches!(result, Err(MimicryError::InvalidTemplate(_))),
+                "шаблон с '{}' должен быть отклонён",
+                to
+            );
+        }
+
+        let unknown_field = TEMPLATE.replacen("[request]", "extra = 1\n[request]", 1);
+        assert!(ProfileTemplate::from_toml(&unknown_field).is_err());
+    }
+
+    #[test]
+    fn test_example_templates() {
+        for source in [
+            include_str!("../../../config/profiles/vk_video.toml.example"),
+            include_str!("../../../config/profiles/cdn_video.toml.example"),
+        ] {
+            let compiled = ProfileTemplate::from_toml(source).unwrap().compile().unwrap();
+            let mut registry = ProfileRegistry::builtin();
+            let id = compiled.id();
+            compiled.install(&mut registry).unwrap();
+
+            let mut profile = registry.create(id).unwrap();
+            assert!(profile.server_header().unwrap().starts_with("nginx"));
+            let wrapped = profile.wrap(b"llp").unwrap();
+            assert_eq!(&profile.unwrap(&wrapped).unwrap()[..], b"llp");
+
+            let request = profile.wrap_request(b"up", 1).unwrap();
+            assert_eq!(&profile.unwrap_request(&request).unwrap()[..], b"up");
+        }
+    }
+
+    #[test]
+    fn test_template_dir_reload() {
+        let dir = std::env::temp_dir().join(format!("llp-templates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("cdn.toml");
+        std::fs::write(&file, TEMPLATE).unwrap();
+
+        let registry = RwLock::new(ProfileRegistry::builtin());
+        let mut templates = TemplateDir::new(&dir);
+        let report = templates.load(&registry).unwrap();
+        assert_eq!(report.loaded, vec!["cdn_video".to_string()]);
+        assert!(registry.read().unwrap().contains(0x8001));
+
+        // Без изменений — ничего не перечитывается
+        assert!(templates.reload(&registry).unwrap().is_empty());
+
+        // Ошибочная правка не применяется, профиль остаётся
+        std::fs::write(&file, TEMPLATE.replace("{user_agent}", "{missing}")).unwrap();
+        let report = templates.reload(&registry).unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert!(registry.read().unwrap().contains(0x8001));
+
+        // Исправленный шаблон применяется
+        std::fs::write(&file, TEMPLATE.replace("UA-1", "UA-3 updated")).unwrap();
+        let report = templates.reload(&registry).unwrap();
+        assert_eq!(report.loaded.len(), 1);
+
+        std::fs::remove_file(&file).unwrap();
+        let report = templates.reload(&registry).unwrap();
+        assert_eq!(report.removed, vec![0x8001]);
+        assert!(!registry.read().unwrap().contains(0x8001));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
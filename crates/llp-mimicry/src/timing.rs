@@ -17,6 +17,23 @@ pub struct TimingProfile {
 }
 
 impl TimingProfile {
+    /// Профиль с произвольными параметрами
+    ///
+    /// Требуется `min_delay_ms < max_delay_ms` и `burst_probability` в `0.0..=1.0`.
+    pub fn new(
+        min_delay_ms: u64,
+        max_delay_ms: u64,
+        burst_probability: f64,
+        burst_size: usize,
+    ) -> Self {
+        Self {
+            min_delay_ms,
+            max_delay_ms,
+            burst_probability,
+            burst_size,
+        }
+    }
+
     /// Профиль для видеостриминга (burst паттерн)
     pub fn video_streaming() -> Self {
         Self {
@@ -0,0 +1,939 @@
+//! Отпечатки TLS ClientHello браузеров
+//!
+//! DPI классифицирует TLS клиентов по ClientHello: порядку cipher suites
+//! и расширений, группам, алгоритмам подписи, GREASE и padding (JA3,
+//! JA4). Профиль мимикрии выбирает отпечаток браузера
+//! ([`crate::Profile::tls_fingerprint`]).
+//!
+//! В сеть уходит ClientHello rustls ([`super::client_config`]): из
+//! отпечатка он получает только порядок TLS 1.3 cipher suites и групп
+//! обмена ключами. Расширения, их порядок, GREASE и padding остаются как
+//! у rustls, поэтому JA3/JA4 браузера не воспроизводятся: rustls собирает
+//! ClientHello сам и не даёт задать расширения, их порядок и GREASE.
+//!
+//! [`ClientHelloSpec`] — модель ClientHello браузера:
+//! [`ClientHelloSpec::build`] собирает запись по параметрам сессии
+//! ([`ClientHelloParams`]), а [`ClientHelloInfo`] разбирает любой
+//! ClientHello и считает JA3/JA4, чтобы сравнить отправляемый с моделью.
+//! Вывод модели закреплён побайтными снимками `vectors/client_hello`;
+//! с записями настоящих браузеров модель не сверена.
+
+use bytes::{BufMut, BytesMut};
+use rand::{Rng, RngCore};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::error::{MimicryError, Result};
+
+/// Значение в списке спецификации, заменяемое на GREASE сессии
+pub const GREASE: u16 = 0x0a0a;
+
+/// Идентификаторы расширений TLS
+pub mod ext {
+    /// server_name (SNI)
+    pub const SERVER_NAME: u16 = 0x0000;
+    /// status_request (OCSP)
+    pub const STATUS_REQUEST: u16 = 0x0005;
+    /// supported_groups
+    pub const SUPPORTED_GROUPS: u16 = 0x000a;
+    /// ec_point_formats
+    pub const EC_POINT_FORMATS: u16 = 0x000b;
+    /// signature_algorithms
+    pub const SIGNATURE_ALGORITHMS: u16 = 0x000d;
+    /// application_layer_protocol_negotiation
+    pub const ALPN: u16 = 0x0010;
+    /// signed_certificate_timestamp
+    pub const SCT: u16 = 0x0012;
+    /// padding
+    pub const PADDING: u16 = 0x0015;
+    /// extended_master_secret
+    pub const EXTENDED_MASTER_SECRET: u16 = 0x0017;
+    /// compress_certificate
+    pub const COMPRESS_CERTIFICATE: u16 = 0x001b;
+    /// record_size_limit
+    pub const RECORD_SIZE_LIMIT: u16 = 0x001c;
+    /// delegated_credentials
+    pub const DELEGATED_CREDENTIALS: u16 = 0x0022;
+    /// session_ticket
+    pub const SESSION_TICKET: u16 = 0x0023;
+    /// supported_versions
+    pub const SUPPORTED_VERSIONS: u16 = 0x002b;
+    /// psk_key_exchange_modes
+    pub const PSK_KEY_EXCHANGE_MODES: u16 = 0x002d;
+    /// key_share
+    pub const KEY_SHARE: u16 = 0x0033;
+    /// application_settings (ALPS)
+    pub const APPLICATION_SETTINGS: u16 = 0x4469;
+    /// encrypted_client_hello
+    pub const ECH: u16 = 0xfe0d;
+    /// renegotiation_info
+    pub const RENEGOTIATION_INFO: u16 = 0xff01;
+}
+
+/// Группы обмена ключами
+pub mod group {
+    /// x25519
+    pub const X25519: u16 = 0x001d;
+    /// secp256r1
+    pub const SECP256R1: u16 = 0x0017;
+    /// secp384r1
+    pub const SECP384R1: u16 = 0x0018;
+    /// secp521r1
+    pub const SECP521R1: u16 = 0x0019;
+    /// ffdhe2048
+    pub const FFDHE2048: u16 = 0x0100;
+    /// ffdhe3072
+    pub const FFDHE3072: u16 = 0x0101;
+}
+
+/// Является ли значение GREASE (RFC 8701)
+pub fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+/// Отпечаток ClientHello
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fingerprint {
+    /// ClientHello rustls без изменений
+    Rustls,
+    /// Chrome 120 (BoringSSL)
+    #[serde(rename = "chrome_120")]
+    Chrome120,
+    /// Firefox 121 (NSS)
+    #[serde(rename = "firefox_121")]
+    Firefox121,
+}
+
+impl Fingerprint {
+    /// Имя отпечатка в конфигурации
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fingerprint::Rustls => "rustls",
+            Fingerprint::Chrome120 => "chrome_120",
+            Fingerprint::Firefox121 => "firefox_121",
+        }
+    }
+
+    /// Отпечаток по имени из конфигурации
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Fingerprint::Rustls,
+            Fingerprint::Chrome120,
+            Fingerprint::Firefox121,
+        ]
+        .into_iter()
+        .find(|fingerprint| fingerprint.name() == name)
+    }
+
+    /// Спецификация ClientHello (`None` для [`Fingerprint::Rustls`])
+    pub fn spec(&self) -> Option<ClientHelloSpec> {
+        match self {
+            Fingerprint::Rustls => None,
+            Fingerprint::Chrome120 => Some(ClientHelloSpec::chrome_120()),
+            Fingerprint::Firefox121 => Some(ClientHelloSpec::firefox_121()),
+        }
+    }
+}
+
+/// Расширение ClientHello в спецификации
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Extension {
+    /// GREASE расширение в начале списка (пустое)
+    Grease,
+    /// GREASE расширение в конце списка (один нулевой байт)
+    GreaseTail,
+    /// SNI из параметров сессии
+    ServerName,
+    /// extended_master_secret
+    ExtendedMasterSecret,
+    /// renegotiation_info для первого рукопожатия
+    RenegotiationInfo,
+    /// Поддерживаемые группы ([`GREASE`] заменяется на GREASE сессии)
+    SupportedGroups(Vec<u16>),
+    /// ec_point_formats: только uncompressed
+    EcPointFormats,
+    /// Пустой session_ticket
+    SessionTicket,
+    /// ALPN из параметров сессии
+    Alpn,
+    /// status_request (OCSP)
+    StatusRequest,
+    /// Алгоритмы подписи
+    SignatureAlgorithms(Vec<u16>),
+    /// signed_certificate_timestamp
+    SignedCertificateTimestamp,
+    /// Группы, для которых отправляется key share
+    KeyShare(Vec<u16>),
+    /// psk_key_exchange_modes: psk_dhe_ke
+    PskKeyExchangeModes,
+    /// Версии ([`GREASE`] заменяется на GREASE сессии)
+    SupportedVersions(Vec<u16>),
+    /// Алгоритмы сжатия сертификата
+    CompressCertificate(Vec<u16>),
+    /// ALPS (Chrome) со списком протоколов
+    ApplicationSettings(Vec<Vec<u8>>),
+    /// GREASE ECH из параметров сессии
+    EchGrease,
+    /// Алгоритмы подписи delegated credentials
+    DelegatedCredentials(Vec<u16>),
+    /// Максимальный размер записи
+    RecordSizeLimit(u16),
+}
+
+/// Спецификация ClientHello браузера
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientHelloSpec {
+    /// Cipher suites в порядке браузера
+    pub cipher_suites: Vec<u16>,
+    /// Расширения в порядке браузера
+    pub extensions: Vec<Extension>,
+}
+
+impl ClientHelloSpec {
+    /// Chrome 120 на десктопе
+    pub fn chrome_120() -> Self {
+        Self {
+            cipher_suites: vec![
+                GREASE, 0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8,
+                0xc013, 0xc014, 0x009c, 0x009d, 0x002f, 0x0035,
+            ],
+            extensions: vec![
+                Extension::Grease,
+                Extension::ServerName,
+                Extension::ExtendedMasterSecret,
+                Extension::RenegotiationInfo,
+                Extension::SupportedGroups(vec![
+                    GREASE,
+                    group::X25519,
+                    group::SECP256R1,
+                    group::SECP384R1,
+                ]),
+                Extension::EcPointFormats,
+                Extension::SessionTicket,
+                Extension::Alpn,
+                Extension::StatusRequest,
+                Extension::SignatureAlgorithms(vec![
+                    0x0403, 0x0804, 0x0401, 0x0503, 0x0805, 0x0501, 0x0806, 0x0601,
+                ]),
+                Extension::SignedCertificateTimestamp,
+                Extension::KeyShare(vec![GREASE, group::X25519]),
+                Extension::PskKeyExchangeModes,
+                Extension::SupportedVersions(vec![GREASE, 0x0304, 0x0303]),
+                Extension::CompressCertificate(vec![0x0002]),
+                Extension::ApplicationSettings(vec![b"h2".to_vec()]),
+                Extension::EchGrease,
+                Extension::GreaseTail,
+            ],
+        }
+    }
+
+    /// Firefox 121 на десктопе
+    pub fn firefox_121() -> Self {
+        Self {
+            cipher_suites: vec![
+                0x1301, 0x1303, 0x1302, 0xc02b, 0xc02f, 0xcca9, 0xcca8, 0xc02c, 0xc030, 0xc00a,
+                0xc009, 0xc013, 0xc014, 0x009c, 0x009d, 0x002f, 0x0035,
+            ],
+            extensions: vec![
+                Extension::ServerName,
+                Extension::ExtendedMasterSecret,
+                Extension::RenegotiationInfo,
+                Extension::SupportedGroups(vec![
+                    group::X25519,
+                    group::SECP256R1,
+                    group::SECP384R1,
+                    group::SECP521R1,
+                    group::FFDHE2048,
+                    group::FFDHE3072,
+                ]),
+                Extension::EcPointFormats,
+                Extension::SessionTicket,
+                Extension::Alpn,
+                Extension::StatusRequest,
+                Extension::DelegatedCredentials(vec![0x0403, 0x0503, 0x0603, 0x0203]),
+                Extension::KeyShare(vec![group::X25519, group::SECP256R1]),
+                Extension::SupportedVersions(vec![0x0304, 0x0303]),
+                Extension::SignatureAlgorithms(vec![
+                    0x0403, 0x0503, 0x0603, 0x0804, 0x0805, 0x0806, 0x0401, 0x0501, 0x0601, 0x0203,
+                    0x0201,
+                ]),
+                Extension::PskKeyExchangeModes,
+                Extension::RecordSizeLimit(0x4001),
+                Extension::EchGrease,
+            ],
+        }
+    }
+
+    /// Группы обмена ключами в порядке предпочтения (без GREASE)
+    pub fn groups(&self) -> Vec<u16> {
+        self.extensions
+            .iter()
+            .find_map(|extension| match extension {
+                Extension::SupportedGroups(groups) => Some(groups.clone()),
+                _ => None,
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|group| !is_grease(*group))
+            .collect()
+    }
+
+    /// Собрать TLS запись с ClientHello
+    pub fn build(&self, params: &ClientHelloParams) -> Result<Vec<u8>> {
+        let mut extensions = BytesMut::new();
+        for extension in &self.extensions {
+            let (id, data) = self.encode_extension(extension, params)?;
+            extensions.put_u16(id);
+            extensions.put_u16(data.len() as u16);
+            extensions.put_slice(&data);
+        }
+
+        let mut body = BytesMut::new();
+        body.put_u16(0x0303);
+        body.put_slice(&params.random);
+        body.put_u8(params.session_id.len() as u8);
+        body.put_slice(&params.session_id);
+        body.put_u16((self.cipher_suites.len() * 2) as u16);
+        for suite in &self.cipher_suites {
+            body.put_u16(params.grease.resolve(*suite, params.grease.cipher));
+        }
+        body.put_slice(&[0x01, 0x00]);
+        body.put_u16(extensions.len() as u16);
+        body.put_slice(&extensions);
+
+        let mut record = Vec::with_capacity(body.len() + 9);
+        record.put_u8(0x16);
+        record.put_u16(0x0301);
+        record.put_u16((body.len() + 4) as u16);
+        record.put_u8(0x01);
+        record.put_uint(body.len() as u64, 3);
+        record.put_slice(&body);
+        Ok(record)
+    }
+
+    fn encode_extension(
+        &self,
+        extension: &Extension,
+        params: &ClientHelloParams,
+    ) -> Result<(u16, Vec<u8>)> {
+        let grease = &params.grease;
+        let mut data = Vec::new();
+        let id = match extension {
+            Extension::Grease => grease.extension_first,
+            Extension::GreaseTail => {
+                data.put_u8(0);
+                grease.extension_last
+            }
+            Extension::ServerName => {
+                let name = params.server_name.as_bytes();
+                data.put_u16(name.len() as u16 + 3);
+                data.put_u8(0);
+                data.put_u16(name.len() as u16);
+                data.put_slice(name);
+                ext::SERVER_NAME
+            }
+            Extension::ExtendedMasterSecret => ext::EXTENDED_MASTER_SECRET,
+            Extension::RenegotiationInfo => {
+                data.put_u8(0);
+                ext::RENEGOTIATION_INFO
+            }
+            Extension::SupportedGroups(groups) => {
+                data.put_u16(groups.len() as u16 * 2);
+                for value in groups {
+                    data.put_u16(grease.resolve(*value, grease.group));
+                }
+                ext::SUPPORTED_GROUPS
+            }
+            Extension::EcPointFormats => {
+                data.put_slice(&[0x01, 0x00]);
+                ext::EC_POINT_FORMATS
+            }
+            Extension::SessionTicket => ext::SESSION_TICKET,
+            Extension::Alpn => {
+                put_protocols(&mut data, &params.alpn);
+                ext::ALPN
+            }
+            Extension::StatusRequest => {
+                data.put_slice(&[0x01, 0x00, 0x00, 0x00, 0x00]);
+                ext::STATUS_REQUEST
+            }
+            Extension::SignatureAlgorithms(algorithms) => {
+                put_u16_list(&mut data, algorithms);
+                ext::SIGNATURE_ALGORITHMS
+            }
+            Extension::SignedCertificateTimestamp => ext::SCT,
+            Extension::KeyShare(groups) => {
+                let mut shares = Vec::new();
+                for value in groups {
+                    if is_grease(*value) {
+                        shares.put_u16(grease.group);
+                        shares.put_u16(1);
+                        shares.put_u8(0);
+                        continue;
+                    }
+                    let key = params
+                        .key_shares
+                        .iter()
+                        .find(|(group, _)| group == value)
+                        .map(|(_, key)| key)
+                        .ok_or_else(|| {
+                            MimicryError::Tls(format!("нет key share для группы {:#06x}", value))
+                        })?;
+                    shares.put_u16(*value);
+                    shares.put_u16(key.len() as u16);
+                    shares.put_slice(key);
+                }
+                data.put_u16(shares.len() as u16);
+                data.put_slice(&shares);
+                ext::KEY_SHARE
+            }
+            Extension::PskKeyExchangeModes => {
+                data.put_slice(&[0x01, 0x01]);
+                ext::PSK_KEY_EXCHANGE_MODES
+            }
+            Extension::SupportedVersions(versions) => {
+                data.put_u8(versions.len() as u8 * 2);
+                for value in versions {
+                    data.put_u16(grease.resolve(*value, grease.version));
+                }
+                ext::SUPPORTED_VERSIONS
+            }
+            Extension::CompressCertificate(algorithms) => {
+                data.put_u8(algorithms.len() as u8 * 2);
+                for value in algorithms {
+                    data.put_u16(*value);
+                }
+                ext::COMPRESS_CERTIFICATE
+            }
+            Extension::ApplicationSettings(protocols) => {
+                put_protocols(&mut data, protocols);
+                ext::APPLICATION_SETTINGS
+            }
+            Extension::EchGrease => {
+                data.put_slice(&params.ech_grease);
+                ext::ECH
+            }
+            Extension::DelegatedCredentials(algorithms) => {
+                put_u16_list(&mut data, algorithms);
+                ext::DELEGATED_CREDENTIALS
+            }
+            Extension::RecordSizeLimit(limit) => {
+                data.put_u16(*limit);
+                ext::RECORD_SIZE_LIMIT
+            }
+        };
+        Ok((id, data))
+    }
+}
+
+/// Значения GREASE сессии
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GreaseValues {
+    /// GREASE в списке cipher suites
+    pub cipher: u16,
+    /// GREASE в группах и key share
+    pub group: u16,
+    /// GREASE в supported_versions
+    pub version: u16,
+    /// Первое GREASE расширение
+    pub extension_first: u16,
+    /// Последнее GREASE расширение
+    pub extension_last: u16,
+}
+
+impl GreaseValues {
+    /// Случайные значения GREASE; первое и последнее расширения различны
+    pub fn random(rng: &mut impl RngCore) -> Self {
+        let mut value = || {
+            let nibble = rng.gen_range(0u16..16);
+            (nibble << 12) | 0x0a00 | (nibble << 4) | 0x0a
+        };
+        let extension_first = value();
+        let mut extension_last = value();
+        while extension_last == extension_first {
+            extension_last = value();
+        }
+        Self {
+            cipher: value(),
+            group: value(),
+            version: value(),
+            extension_first,
+            extension_last,
+        }
+    }
+
+    fn resolve(&self, value: u16, grease: u16) -> u16 {
+        if value == GREASE {
+            grease
+        } else {
+            value
+        }
+    }
+}
+
+/// Параметры конкретного ClientHello
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientHelloParams {
+    /// Поле random
+    pub random: [u8; 32],
+    /// Legacy session_id (32 байта в режиме совместимости)
+    pub session_id: Vec<u8>,
+    /// SNI
+    pub server_name: String,
+    /// Протоколы ALPN
+    pub alpn: Vec<Vec<u8>>,
+    /// Значения GREASE
+    pub grease: GreaseValues,
+    /// Публичные ключи key share по группам
+    pub key_shares: Vec<(u16, Vec<u8>)>,
+    /// Тело GREASE расширения ECH
+    pub ech_grease: Vec<u8>,
+}
+
+impl ClientHelloParams {
+    /// Случайные параметры сессии
+    ///
+    /// Ключи key share — случайные байты нужной длины: такой ClientHello
+    /// неотличим на проводе, но не годится для завершения рукопожатия.
+    pub fn random(rng: &mut impl RngCore, server_name: &str, alpn: Vec<Vec<u8>>) -> Self {
+        let mut random = [0u8; 32];
+        rng.fill_bytes(&mut random);
+        let mut session_id = vec![0u8; 32];
+        rng.fill_bytes(&mut session_id);
+
+        let mut x25519 = vec![0u8; 32];
+        rng.fill_bytes(&mut x25519);
+        let mut secp256r1 = vec![0u8; 65];
+        rng.fill_bytes(&mut secp256r1);
+        secp256r1[0] = 0x04;
+
+        Self {
+            random,
+            session_id,
+            server_name: server_name.to_string(),
+            alpn,
+            grease: GreaseValues::random(rng),
+            key_shares: vec![(group::X25519, x25519), (group::SECP256R1, secp256r1)],
+            ech_grease: ech_grease(rng),
+        }
+    }
+}
+
+/// Тело GREASE ECH как у Chrome и Firefox: HPKE X25519/HKDF-SHA256/AES-128-GCM
+fn ech_grease(rng: &mut impl RngCore) -> Vec<u8> {
+    let mut enc = [0u8; 32];
+    rng.fill_bytes(&mut enc);
+    // Длина payload браузеров кратна 32 байтам
+    let mut payload = vec![0u8; 32 * rng.gen_range(4..8)];
+    rng.fill_bytes(&mut payload);
+
+    let mut data = Vec::with_capacity(enc.len() + payload.len() + 10);
+    data.put_u8(0); // outer
+    data.put_u16(0x0001); // HKDF-SHA256
+    data.put_u16(0x0001); // AES-128-GCM
+    data.put_u8(rng.gen());
+    data.put_u16(enc.len() as u16);
+    data.put_slice(&enc);
+    data.put_u16(payload.len() as u16);
+    data.put_slice(&payload);
+    data
+}
+
+fn put_u16_list(out: &mut Vec<u8>, values: &[u16]) {
+    out.put_u16(values.len() as u16 * 2);
+    for value in values {
+        out.put_u16(*value);
+    }
+}
+
+fn put_protocols(out: &mut Vec<u8>, protocols: &[Vec<u8>]) {
+    let len: usize = protocols.iter().map(|p| p.len() + 1).sum();
+    out.put_u16(len as u16);
+    for protocol in protocols {
+        out.put_u8(protocol.len() as u8);
+        out.put_slice(protocol);
+    }
+}
+
+/// Разобранный ClientHello
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientHelloInfo {
+    /// Поле legacy_version
+    pub legacy_version: u16,
+    /// Cipher suites в порядке ClientHello
+    pub cipher_suites: Vec<u16>,
+    /// Расширения в порядке ClientHello: (тип, данные)
+    pub extensions: Vec<(u16, Vec<u8>)>,
+}
+
+impl ClientHelloInfo {
+    /// Разобрать TLS запись (или сообщение handshake) с ClientHello
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader(data);
+        if data.first() == Some(&0x16) {
+            reader.take(5)?;
+        }
+        if reader.u8()? != 0x01 {
+            return Err(parse_error("не ClientHello"));
+        }
+        let len = reader.u24()?;
+        let mut hello = Reader(reader.take(len)?);
+
+        let legacy_version = hello.u16()?;
+        hello.take(32)?;
+        let session_len = hello.u8()? as usize;
+        hello.take(session_len)?;
+        let suites_len = hello.u16()? as usize;
+        let mut suites = Reader(hello.take(suites_len)?);
+        let mut cipher_suites = Vec::with_capacity(suites_len / 2);
+        while !suites.0.is_empty() {
+            cipher_suites.push(suites.u16()?);
+        }
+        let compression_len = hello.u8()? as usize;
+        hello.take(compression_len)?;
+
+        let mut extensions = Vec::new();
+        if !hello.0.is_empty() {
+            let extensions_len = hello.u16()? as usize;
+            let mut list = Reader(hello.take(extensions_len)?);
+            while !list.0.is_empty() {
+                let id = list.u16()?;
+                let len = list.u16()? as usize;
+                extensions.push((id, list.take(len)?.to_vec()));
+            }
+        }
+
+        Ok(Self {
+            legacy_version,
+            cipher_suites,
+            extensions,
+        })
+    }
+
+    /// Данные расширения
+    pub fn extension(&self, id: u16) -> Option<&[u8]> {
+        self.extensions
+            .iter()
+            .find(|(ext_id, _)| *ext_id == id)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    /// Строка JA3 (без MD5)
+    pub fn ja3(&self) -> String {
+        let groups = self
+            .extension(ext::SUPPORTED_GROUPS)
+            .map(|data| u16_list(data.get(2..).unwrap_or_default()))
+            .unwrap_or_default();
+        let formats: Vec<u16> = self
+            .extension(ext::EC_POINT_FORMATS)
+            .map(|data| data.iter().skip(1).map(|f| *f as u16).collect())
+            .unwrap_or_default();
+        let extensions: Vec<u16> = self.extensions.iter().map(|(id, _)| *id).collect();
+
+        format!(
+            "{},{},{},{},{}",
+            self.legacy_version,
+            join_decimal(&self.cipher_suites),
+            join_decimal(&extensions),
+            join_decimal(&groups),
+            join_decimal(&formats)
+        )
+    }
+
+    /// MD5 строки JA3
+    pub fn ja3_hash(&self) -> String {
+        format!("{:x}", md5::compute(self.ja3()))
+    }
+
+    /// Отпечаток JA4 (TCP)
+    pub fn ja4(&self) -> String {
+        let version = self
+            .extension(ext::SUPPORTED_VERSIONS)
+            .and_then(|data| u16_list(data.get(1..)?).into_iter().max())
+            .unwrap_or(self.legacy_version);
+        let version = match version {
+            0x0304 => "13",
+            0x0303 => "12",
+            0x0302 => "11",
+            0x0301 => "10",
+            _ => "00",
+        };
+        let sni = if self.extension(ext::SERVER_NAME).is_some() {
+            'd'
+        } else {
+            'i'
+        };
+        let alpn = self
+            .extension(ext::ALPN)
+            .and_then(|data| {
+                let len = *data.get(2)? as usize;
+                let first = data.get(3..3 + len)?;
+                Some(format!(
+                    "{}{}",
+                    *first.first()? as char,
+                    *first.last()? as char
+                ))
+            })
+            .unwrap_or_else(|| "00".to_string());
+
+        let mut ciphers: Vec<u16> = self
+            .cipher_suites
+            .iter()
+            .copied()
+            .filter(|c| !is_grease(*c))
+            .collect();
+        let extensions: Vec<u16> = self
+            .extensions
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| !is_grease(*id))
+            .collect();
+        ciphers.sort_unstable();
+        let mut hashed: Vec<u16> = extensions
+            .iter()
+            .copied()
+            .filter(|id| *id != ext::SERVER_NAME && *id != ext::ALPN)
+            .collect();
+        hashed.sort_unstable();
+        let algorithms = self
+            .extension(ext::SIGNATURE_ALGORITHMS)
+            .map(|data| u16_list(data.get(2..).unwrap_or_default()))
+            .unwrap_or_default();
+
+        let mut extension_part = join_hex(&hashed);
+        if !algorithms.is_empty() {
+            extension_part.push('_');
+            extension_part.push_str(&join_hex(&algorithms));
+        }
+
+        format!(
+            "t{}{}{:02}{:02}{}_{}_{}",
+            version,
+            sni,
+            ciphers.len().min(99),
+            extensions.len().min(99),
+            alpn,
+            truncated_sha256(&join_hex(&ciphers)),
+            truncated_sha256(&extension_part)
+        )
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(parse_error("ClientHello обрезан"));
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u24(&mut self) -> Result<usize> {
+        let bytes = self.take(3)?;
+        Ok((bytes[0] as usize) << 16 | (bytes[1] as usize) << 8 | bytes[2] as usize)
+    }
+}
+
+fn parse_error(message: &str) -> MimicryError {
+    MimicryError::Tls(message.to_string())
+}
+
+fn u16_list(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .filter(|value| !is_grease(*value))
+        .collect()
+}
+
+fn join_decimal(values: &[u16]) -> String {
+    values
+        .iter()
+        .filter(|value| !is_grease(**value))
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn join_hex(values: &[u16]) -> String {
+    values
+        .iter()
+        .map(|value| format!("{:04x}", value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn truncated_sha256(input: &str) -> String {
+    if input.is_empty() {
+        return "000000000000".to_string();
+    }
+    let digest = Sha256::digest(input.as_bytes());
+    digest[..6].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Фиксированные параметры сессии снимков из `vectors/client_hello`
+    fn reference_params() -> ClientHelloParams {
+        let mut ech = vec![0x00, 0x00, 0x01, 0x00, 0x01, 0x55, 0x00, 0x20];
+        ech.extend_from_slice(&[0x66; 32]);
+        ech.extend_from_slice(&[0x00, 0xd0]);
+        ech.extend_from_slice(&[0x77; 0xd0]);
+
+        let mut secp256r1 = vec![0x04];
+        secp256r1.extend_from_slice(&[0x44; 64]);
+
+        ClientHelloParams {
+            random: [0x11; 32],
+            session_id: vec![0x22; 32],
+            server_name: "vkvideo.ru".to_string(),
+            alpn: vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            grease: GreaseValues {
+                cipher: 0x2a2a,
+                group: 0x6a6a,
+                version: 0xdada,
+                extension_first: 0x8a8a,
+                extension_last: 0x3a3a,
+            },
+            key_shares: vec![
+                (group::X25519, vec![0x33; 32]),
+                (group::SECP256R1, secp256r1),
+            ],
+            ech_grease: ech,
+        }
+    }
+
+    fn reference(hex_dump: &str) -> Vec<u8> {
+        let hex: String = hex_dump
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .flat_map(|line| line.chars())
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_reference_snapshots() {
+        let params = reference_params();
+        let chrome = ClientHelloSpec::chrome_120().build(&params).unwrap();
+        assert_eq!(
+            chrome,
+            reference(include_str!("../../vectors/client_hello/chrome_120.hex"))
+        );
+        let firefox = ClientHelloSpec::firefox_121().build(&params).unwrap();
+        assert_eq!(
+            firefox,
+            reference(include_str!("../../vectors/client_hello/firefox_121.hex"))
+        );
+    }
+
+    #[test]
+    fn test_chrome_fingerprint() {
+        let mut rng = rand::thread_rng();
+        let alpn = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        let spec = ClientHelloSpec::chrome_120();
+        let mut ja4 = None;
+        for _ in 0..8 {
+            let params = ClientHelloParams::random(&mut rng, "vkvideo.ru", alpn.clone());
+            let record = spec.build(&params).unwrap();
+            let info = ClientHelloInfo::parse(&record).unwrap();
+            assert_eq!(
+                info.extension(ext::SERVER_NAME).unwrap()[5..],
+                *b"vkvideo.ru"
+            );
+            assert!(is_grease(info.cipher_suites[0]));
+            assert!(is_grease(info.extensions[0].0));
+            // JA4 не зависит от GREASE и случайных полей
+            let current = info.ja4();
+            assert_eq!(*ja4.get_or_insert_with(|| current.clone()), current);
+        }
+        assert_eq!(ja4.unwrap(), "t13d1516h2_8daaf6152771_02713d6af862");
+    }
+
+    #[test]
+    fn test_firefox_fingerprint() {
+        let params = reference_params();
+        let record = ClientHelloSpec::firefox_121().build(&params).unwrap();
+        let info = ClientHelloInfo::parse(&record).unwrap();
+
+        assert!(info.ja4().starts_with("t13d1715h2_"));
+        assert!(info.extension(ext::PADDING).is_none());
+        assert_eq!(
+            info.ja3(),
+            "771,4865-4867-4866-49195-49199-52393-52392-49196-49200-49162-49161-49171-49172-156-157-47-53,\
+             0-23-65281-10-11-35-16-5-34-51-43-13-45-28-65037,29-23-24-25-256-257,0"
+        );
+        assert_eq!(info.ja3_hash().len(), 32);
+    }
+
+    #[test]
+    fn test_rustls_hello_on_wire() {
+        use std::sync::Arc;
+
+        let config = super::super::client_config_unverified(
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            Fingerprint::Chrome120,
+        )
+        .unwrap();
+        let name = rustls::pki_types::ServerName::try_from("vkvideo.ru").unwrap();
+        let mut connection = rustls::ClientConnection::new(Arc::clone(&config), name).unwrap();
+        let mut record = Vec::new();
+        connection.write_tls(&mut record).unwrap();
+
+        let info = ClientHelloInfo::parse(&record).unwrap();
+        assert_eq!(
+            info.extension(ext::SERVER_NAME).unwrap()[5..],
+            *b"vkvideo.ru"
+        );
+
+        // Порядок TLS 1.3 cipher suites и групп взят из отпечатка
+        assert_eq!(&info.cipher_suites[..3], &[0x1301, 0x1302, 0x1303]);
+        let groups = u16_list(&info.extension(ext::SUPPORTED_GROUPS).unwrap()[2..]);
+        assert_eq!(
+            groups,
+            vec![group::X25519, group::SECP256R1, group::SECP384R1]
+        );
+
+        // Остальное — как у rustls: JA4 браузера не получается
+        let chrome = ClientHelloSpec::chrome_120()
+            .build(&reference_params())
+            .unwrap();
+        assert_ne!(info.ja4(), ClientHelloInfo::parse(&chrome).unwrap().ja4());
+        assert!(!is_grease(info.cipher_suites[0]));
+    }
+
+    #[test]
+    fn test_fingerprint_names() {
+        for fingerprint in [
+            Fingerprint::Rustls,
+            Fingerprint::Chrome120,
+            Fingerprint::Firefox121,
+        ] {
+            assert_eq!(
+                Fingerprint::from_name(fingerprint.name()),
+                Some(fingerprint)
+            );
+        }
+        assert!(Fingerprint::from_name("safari").is_none());
+        assert!(Fingerprint::Rustls.spec().is_none());
+        assert!(ClientHelloInfo::parse(&[0x16, 0x03, 0x01, 0x00, 0x05, 0x02]).is_err());
+    }
+}
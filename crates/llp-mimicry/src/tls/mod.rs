@@ -0,0 +1,450 @@
+//! Внешний TLS 1.3 слой
+//!
+//! Настоящий трафик VK, Яндекса и RuTube идёт только по TLS, поэтому
+//! HTTP мимикрия может работать внутри TLS 1.3 сессии (rustls). Клиент
+//! берёт SNI и ALPN из активного профиля ([`crate::Profile::tls_server_name`],
+//! [`crate::Profile::alpn_protocols`]), сервер предъявляет сертификат из
+//! конфигурации. Согласованный ALPN `h2` переводит соединение на HTTP/2
+//! ([`crate::h2`]).
+//!
+//! [`Transport`] объединяет обычный TCP поток, TLS поток и поток туннеля
+//! без TCP (DNS, [`crate::dns`]), так что код поверх него не зависит от
+//! того, как передаются байты.
+//!
+//! ClientHello клиента — rustls с порядком cipher suites и групп
+//! браузера из профиля ([`crate::Profile::tls_fingerprint`], модуль
+//! [`fingerprint`]); JA3/JA4 остаются как у rustls.
+
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_rustls::{TlsAcceptor, TlsConnector, TlsStream};
+
+use crate::error::{MimicryError, Result};
+
+pub mod fingerprint;
+
+pub use fingerprint::Fingerprint;
+
+/// ALPN по умолчанию: мимикрия говорит на HTTP/1.1
+pub const DEFAULT_ALPN: &[u8] = b"http/1.1";
+
+/// Поток транспорта: TCP, TLS поверх TCP или поток туннеля
+pub enum Transport {
+    /// Обычный TCP
+    Plain(TcpStream),
+    /// TLS 1.3 поверх TCP
+    Tls(Box<TlsStream<TcpStream>>),
+    /// Поток туннеля без TCP (например, DNS); TLS поверх него не используется
+    Tunnel(DuplexStream),
+}
+
+impl Transport {
+    /// Используется ли TLS
+    pub fn is_tls(&self) -> bool {
+        matches!(self, Transport::Tls(_))
+    }
+
+    /// Согласованный ALPN протокол
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        match self {
+            Transport::Plain(_) | Transport::Tunnel(_) => None,
+            Transport::Tls(stream) => stream.get_ref().1.alpn_protocol(),
+        }
+    }
+
+    /// Базовый TCP поток (`None` у туннеля)
+    pub fn tcp(&self) -> Option<&TcpStream> {
+        match self {
+            Transport::Plain(stream) => Some(stream),
+            Transport::Tls(stream) => Some(stream.get_ref().0),
+            Transport::Tunnel(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Debug for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transport::Plain(stream) => f.debug_tuple("Plain").field(stream).finish(),
+            Transport::Tls(stream) => f.debug_tuple("Tls").field(stream.get_ref().0).finish(),
+            Transport::Tunnel(_) => f.write_str("Tunnel"),
+        }
+    }
+}
+
+impl From<TcpStream> for Transport {
+    fn from(stream: TcpStream) -> Self {
+        Transport::Plain(stream)
+    }
+}
+
+impl AsyncRead for Transport {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Transport::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+            Transport::Tunnel(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Transport {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Transport::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Transport::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+            Transport::Tunnel(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            Transport::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+            Transport::Tunnel(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            Transport::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+            Transport::Tunnel(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Загрузить цепочку сертификатов из PEM файла
+pub fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let mut reader = io::BufReader::new(std::fs::File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader).collect::<io::Result<Vec<_>>>()?;
+    if certs.is_empty() {
+        return Err(MimicryError::Tls(format!(
+            "{}: нет сертификатов",
+            path.display()
+        )));
+    }
+    Ok(certs)
+}
+
+/// Загрузить приватный ключ из PEM файла (PKCS#8, PKCS#1 или SEC1)
+pub fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let mut reader = io::BufReader::new(std::fs::File::open(path)?);
+    rustls_pemfile::private_key(&mut reader)?
+        .ok_or_else(|| MimicryError::Tls(format!("{}: нет приватного ключа", path.display())))
+}
+
+/// Конфигурация TLS сервера: только TLS 1.3
+///
+/// `alpn` — принимаемые протоколы в порядке предпочтения сервера
+/// (например, `h2` перед [`DEFAULT_ALPN`]).
+pub fn server_config(
+    certs: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    alpn: Vec<Vec<u8>>,
+) -> Result<Arc<rustls::ServerConfig>> {
+    let mut config = rustls::ServerConfig::builder_with_provider(provider())
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(tls_error)?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(tls_error)?;
+    config.alpn_protocols = alpn;
+    Ok(Arc::new(config))
+}
+
+/// Конфигурация TLS клиента: только TLS 1.3
+///
+/// `roots` — доверенные сертификаты (сертификат сервера или его CA);
+/// `alpn` — протоколы из профиля в порядке предпочтения;
+/// `fingerprint` — отпечаток браузера, порядок cipher suites и групп
+/// которого получает ClientHello.
+pub fn client_config(
+    roots: &[CertificateDer<'static>],
+    alpn: Vec<Vec<u8>>,
+    fingerprint: Fingerprint,
+) -> Result<Arc<rustls::ClientConfig>> {
+    let mut store = rustls::RootCertStore::empty();
+    for cert in roots {
+        store.add(cert.clone()).map_err(tls_error)?;
+    }
+
+    let mut config = rustls::ClientConfig::builder_with_provider(client_provider(fingerprint))
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(tls_error)?
+        .with_root_certificates(store)
+        .with_no_client_auth();
+    config.alpn_protocols = alpn;
+    Ok(Arc::new(config))
+}
+
+/// Конфигурация TLS клиента без проверки сертификата сервера
+///
+/// Только для отладки (`verify_server = false`): подпись рукопожатия
+/// проверяется, но цепочка сертификатов и имя — нет.
+pub fn client_config_unverified(
+    alpn: Vec<Vec<u8>>,
+    fingerprint: Fingerprint,
+) -> Result<Arc<rustls::ClientConfig>> {
+    let provider = client_provider(fingerprint);
+    let verifier = Arc::new(NoVerification(Arc::clone(&provider)));
+
+    let mut config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(tls_error)?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier)
+        .with_no_client_auth();
+    config.alpn_protocols = alpn;
+    Ok(Arc::new(config))
+}
+
+/// Проверка сертификата, принимающая любой сертификат сервера
+#[derive(Debug)]
+struct NoVerification(Arc<rustls::crypto::CryptoProvider>);
+
+impl rustls::client::danger::ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls::pki_types::UnixTime,
+    ) -> std::result::Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::danger::ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Установить TLS сессию поверх TCP со стороны клиента
+pub async fn connect(
+    stream: TcpStream,
+    config: Arc<rustls::ClientConfig>,
+    server_name: &str,
+) -> Result<Transport> {
+    let name = ServerName::try_from(server_name.to_string())
+        .map_err(|e| MimicryError::Tls(format!("SNI '{}': {}", server_name, e)))?;
+    let stream = TlsConnector::from(config).connect(name, stream).await?;
+    Ok(Transport::Tls(Box::new(stream.into())))
+}
+
+/// Принять TLS сессию поверх TCP со стороны сервера
+pub async fn accept(stream: TcpStream, config: Arc<rustls::ServerConfig>) -> Result<Transport> {
+    let stream = TlsAcceptor::from(config).accept(stream).await?;
+    Ok(Transport::Tls(Box::new(stream.into())))
+}
+
+/// Проверить, что строка годится как SNI
+pub fn check_server_name(server_name: &str) -> Result<()> {
+    ServerName::try_from(server_name)
+        .map(|_| ())
+        .map_err(|e| MimicryError::Tls(format!("SNI '{}': {}", server_name, e)))
+}
+
+fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Провайдер клиента с порядком cipher suites и групп из отпечатка
+///
+/// Группы, которых нет в отпечатке, не предлагаются; cipher suites
+/// вне отпечатка остаются в конце списка.
+fn client_provider(fingerprint: Fingerprint) -> Arc<rustls::crypto::CryptoProvider> {
+    let mut provider = rustls::crypto::ring::default_provider();
+    let Some(spec) = fingerprint.spec() else {
+        return Arc::new(provider);
+    };
+
+    let rank =
+        |order: &[u16], value: u16| order.iter().position(|v| *v == value).unwrap_or(usize::MAX);
+    provider
+        .cipher_suites
+        .sort_by_key(|suite| rank(&spec.cipher_suites, u16::from(suite.suite())));
+
+    let groups = spec.groups();
+    let mut kx_groups: Vec<_> = provider
+        .kx_groups
+        .iter()
+        .copied()
+        .filter(|kx| groups.contains(&u16::from(kx.name())))
+        .collect();
+    kx_groups.sort_by_key(|kx| rank(&groups, u16::from(kx.name())));
+    if !kx_groups.is_empty() {
+        provider.kx_groups = kx_groups;
+    }
+    Arc::new(provider)
+}
+
+fn tls_error(error: rustls::Error) -> MimicryError {
+    MimicryError::Tls(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn self_signed(name: &str) -> (CertificateDer<'static>, PrivateKeyDer<'static>) {
+        let cert = rcgen::generate_simple_self_signed(vec![name.to_string()]).unwrap();
+        let key = PrivateKeyDer::Pkcs8(cert.key_pair.serialize_der().into());
+        (cert.cert.der().clone(), key)
+    }
+
+    #[test]
+    fn test_profiles_offer_only_http11() {
+        use llp_core::packet::MimicryProfile;
+
+        // h2 добавляет конфигурация транспорта, профили предлагают HTTP/1.1
+        for profile in [
+            MimicryProfile::VkVideo,
+            MimicryProfile::YandexMusic,
+            MimicryProfile::RuTube,
+        ] {
+            let wrapper = crate::PacketWrapper::new(profile);
+            assert_eq!(wrapper.alpn_protocols(), vec![DEFAULT_ALPN.to_vec()]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tls13_round_trip_with_sni_and_alpn() {
+        let (cert, key) = self_signed("vkvideo.ru");
+        let server = server_config(vec![cert.clone()], key, vec![DEFAULT_ALPN.to_vec()]).unwrap();
+        let client = client_config(
+            &[cert],
+            vec![crate::h2::ALPN_H2.to_vec(), DEFAULT_ALPN.to_vec()],
+            Fingerprint::Chrome120,
+        )
+        .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server_task = tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut transport = accept(tcp, server).await.unwrap();
+            let Transport::Tls(stream) = &transport else {
+                panic!("expected TLS");
+            };
+            let TlsStream::Server(stream) = stream.as_ref() else {
+                panic!("expected server stream");
+            };
+            assert_eq!(stream.get_ref().1.server_name(), Some("vkvideo.ru"));
+            assert_eq!(transport.alpn_protocol(), Some(DEFAULT_ALPN));
+
+            let mut buf = [0u8; 4];
+            transport.read_exact(&mut buf).await.unwrap();
+            transport.write_all(&buf).await.unwrap();
+            transport.flush().await.unwrap();
+        });
+
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        let mut transport = connect(tcp, client, "vkvideo.ru").await.unwrap();
+        assert!(transport.is_tls());
+        assert_eq!(transport.alpn_protocol(), Some(DEFAULT_ALPN));
+        if let Transport::Tls(stream) = &transport {
+            assert_eq!(
+                stream.get_ref().1.protocol_version(),
+                Some(rustls::ProtocolVersion::TLSv1_3)
+            );
+        }
+
+        transport.write_all(b"ping").await.unwrap();
+        transport.flush().await.unwrap();
+        let mut buf = [0u8; 4];
+        transport.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+        server_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wrong_sni_rejected() {
+        let (cert, key) = self_signed("vkvideo.ru");
+        let server = server_config(vec![cert.clone()], key, vec![DEFAULT_ALPN.to_vec()]).unwrap();
+        let client =
+            client_config(&[cert], vec![DEFAULT_ALPN.to_vec()], Fingerprint::Rustls).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let _ = accept(tcp, server).await;
+        });
+
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        assert!(connect(tcp, client, "music.yandex.ru").await.is_err());
+        assert!(check_server_name("not a host").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unverified_client() {
+        let (cert, key) = self_signed("rutube.ru");
+        let server = server_config(
+            vec![cert],
+            key,
+            vec![crate::h2::ALPN_H2.to_vec(), DEFAULT_ALPN.to_vec()],
+        )
+        .unwrap();
+        let client =
+            client_config_unverified(vec![DEFAULT_ALPN.to_vec()], Fingerprint::Firefox121).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let _ = accept(tcp, server).await;
+        });
+
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        let transport = connect(tcp, client, "music.yandex.ru").await.unwrap();
+        assert!(transport.is_tls());
+        // Клиент без h2 получает HTTP/1.1 и от сервера, принимающего h2
+        assert_eq!(transport.alpn_protocol(), Some(DEFAULT_ALPN));
+    }
+}
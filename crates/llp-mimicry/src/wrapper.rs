@@ -2,81 +2,309 @@
 //!
 //! Этот модуль отвечает за упаковку зашифрованных LLP пакетов
 //! в HTTP-подобный трафик выбранного профиля мимикрии.
+//!
+//! В ролях [`Role::Client`] и [`Role::Server`] клиент передаёт пакеты
+//! в запросах, сервер — в ответах, причём каждый ответ соответствует
+//! ранее полученному запросу. Когда у клиента нет открытых запросов,
+//! он отправляет запрос без payload ([`PacketWrapper::poll_request`]),
+//! чтобы сервер мог передать данные.
+//!
+//! Сервер может ответить на запрос долгим chunked ответом
+//! ([`PacketWrapper::start_stream`]): пока он открыт, пакеты идут в его
+//! теле без отдельных HTTP заголовков (см. [`crate::stream`]). Короткий
+//! остаток chunk отправляет [`PacketWrapper::flush_stream`] — когда,
+//! решает вызывающий код (планировщик или таймер).
+//!
+//! В режиме агрегации ([`PacketWrapper::with_aggregation`]) сообщение
+//! несёт не один пакет, а часть общего потока пакетов размером chunk
+//! профиля (см. [`crate::aggregate`]). Режим должен быть включён на обеих
+//! сторонах.
 
 use bytes::Bytes;
+use llp_core::clock::{SharedClock, SystemClock};
 use llp_core::packet::MimicryProfile;
+use rand::rngs::{OsRng, StdRng};
+use rand::{RngCore, SeedableRng};
 use std::time::Duration;
 
-use crate::error::Result;
-use crate::profiles::{
-    RuTubeParser, RuTubeProfile, VkVideoParser, VkVideoProfile, YandexMusicParser,
-    YandexMusicProfile,
-};
+use crate::aggregate::{AggregationOptions, Aggregator};
+use crate::codec::Frame;
+use crate::cover::BitrateCurve;
+use crate::error::{MimicryError, Result};
+use crate::exchange::Role;
+use crate::profiles::{BoxedRng, Profile, ProfileId};
+use crate::registry;
+use crate::stream::{StreamDecoder, StreamEncoder};
+use crate::timing::TimingProfile;
 
 /// Обёртка для пакетов LLP
 ///
 /// Упаковывает зашифрованные LLP пакеты в HTTP-трафик
-/// в зависимости от выбранного профиля мимикрии.
+/// в зависимости от выбранного профиля мимикрии. Реализация профиля
+/// берётся из глобального [`registry`].
 pub struct PacketWrapper {
-    profile: WrapperProfile,
+    profile: Box<dyn Profile>,
+    role: Role,
+    /// Запросы без ответа (клиент — отправленные, сервер — полученные)
+    open_requests: u64,
     chunk_counter: u64,
-}
-
-/// Внутреннее представление профиля
-enum WrapperProfile {
-    None,
-    VkVideo(VkVideoProfile),
-    YandexMusic(YandexMusicProfile),
-    RuTube(RuTubeProfile),
+    /// Открытый потоковый ответ (отправляющая сторона)
+    stream: Option<StreamEncoder>,
+    /// Открытый потоковый ответ (принимающая сторона)
+    incoming: Option<StreamDecoder>,
+    /// Агрегация пакетов в сообщения (None — по пакету в сообщении)
+    aggregator: Option<Aggregator>,
+    /// Источник дочерних RNG (потоковые ответы, кадры WebSocket)
+    rng: BoxedRng,
 }
 
 impl PacketWrapper {
     /// Создать новую обёртку для указанного профиля
+    ///
+    /// # Panics
+    /// Если `MimicryProfile::Custom` не зарегистрирован в глобальном
+    /// реестре. Для профилей, пришедших из сети, используйте [`Self::try_new`].
     pub fn new(profile: MimicryProfile) -> Self {
-        let wrapper_profile = match profile {
-            MimicryProfile::None => WrapperProfile::None,
-            MimicryProfile::VkVideo => WrapperProfile::VkVideo(VkVideoProfile::new()),
-            MimicryProfile::YandexMusic => {
-                WrapperProfile::YandexMusic(YandexMusicProfile::new())
-            }
-            MimicryProfile::RuTube => WrapperProfile::RuTube(RuTubeProfile::new()),
-        };
+        Self::with_sources(profile, Box::new(OsRng), SystemClock::shared())
+    }
+
+    /// Создать обёртку с заданными источниками случайности и времени
+    ///
+    /// При одинаковом seed RNG и одинаковом времени генерируемый
+    /// HTTP-трафик совпадает побайтно.
+    ///
+    /// # Panics
+    /// Если профиль не зарегистрирован (см. [`Self::try_with_sources`]).
+    pub fn with_sources(profile: MimicryProfile, rng: BoxedRng, clock: SharedClock) -> Self {
+        Self::try_with_sources(profile, rng, clock)
+            .unwrap_or_else(|e| panic!("профиль {} недоступен: {}", profile, e))
+    }
+
+    /// Создать обёртку, вернув ошибку для незарегистрированного профиля
+    pub fn try_new(profile: MimicryProfile) -> Result<Self> {
+        Self::try_with_sources(profile, Box::new(OsRng), SystemClock::shared())
+    }
+
+    /// Создать обёртку с заданными источниками, вернув ошибку для
+    /// незарегистрированного профиля
+    pub fn try_with_sources(
+        profile: MimicryProfile,
+        mut rng: BoxedRng,
+        clock: SharedClock,
+    ) -> Result<Self> {
+        // Дочерний RNG берёт seed до профиля, чтобы оба зависели от seed
+        let child = seeded_child(&mut rng);
+        let profile = registry::global()
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .create_with_sources(profile.to_u16(), rng, clock)?;
+        let mut wrapper = Self::from_profile(profile);
+        wrapper.rng = child;
+        Ok(wrapper)
+    }
 
+    /// Создать обёртку поверх готового экземпляра профиля
+    pub fn from_profile(profile: Box<dyn Profile>) -> Self {
         Self {
-            profile: wrapper_profile,
+            profile,
+            role: Role::Symmetric,
+            open_requests: 0,
             chunk_counter: 0,
+            stream: None,
+            incoming: None,
+            aggregator: None,
+            rng: Box::new(OsRng),
         }
     }
 
+    /// Задать роль в HTTP обмене
+    pub fn with_role(mut self, role: Role) -> Self {
+        self.role = role;
+        self
+    }
+
+    /// Включить агрегацию пакетов в сообщения размера профиля
+    ///
+    /// Тела сообщений становятся частями общего потока пакетов в обоих
+    /// направлениях, поэтому режим должен совпадать у клиента и сервера.
+    pub fn with_aggregation(mut self, options: AggregationOptions) -> Self {
+        self.aggregator = Some(Aggregator::new(options));
+        self
+    }
+
+    /// Включена ли агрегация пакетов
+    pub fn is_aggregating(&self) -> bool {
+        self.aggregator.is_some()
+    }
+
+    /// Роль в HTTP обмене
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// ID профиля обёртки
+    pub fn profile_id(&self) -> ProfileId {
+        self.profile.id()
+    }
+
+    /// Имя профиля обёртки
+    pub fn profile_name(&self) -> &str {
+        self.profile.name()
+    }
+
+    /// SNI внешнего TLS слоя для профиля
+    pub fn tls_server_name(&self) -> Option<&str> {
+        self.profile.tls_server_name()
+    }
+
+    /// Протоколы ALPN внешнего TLS слоя для профиля
+    pub fn alpn_protocols(&self) -> Vec<Vec<u8>> {
+        self.profile.alpn_protocols()
+    }
+
+    /// Заголовок `Server` ответов профиля
+    pub fn server_header(&self) -> Option<&str> {
+        self.profile.server_header()
+    }
+
+    /// Отпечаток ClientHello для профиля
+    pub fn tls_fingerprint(&self) -> crate::tls::Fingerprint {
+        self.profile.tls_fingerprint()
+    }
+
+    /// Сообщения обёртки — HTTP и разделяются [`crate::codec::HttpDecoder`]
+    ///
+    /// Иначе (профиль без мимикрии) в потоке нужен префикс длины.
+    pub fn is_http(&self) -> bool {
+        self.profile.http_exchange()
+    }
+
     /// Обернуть сериализованный LLP пакет в HTTP-трафик
     ///
     /// # Параметры
     /// - `packet_data`: Сериализованный LLP пакет (уже зашифрованный)
     ///
     /// # Возвращает
-    /// HTTP request/response в зависимости от профиля
+    /// HTTP запрос (роль клиента) или ответ (роли сервера и симметричная)
+    ///
+    /// В режиме агрегации сообщение несёт этот пакет вместе со всеми
+    /// накопленными ранее, без деления по размеру профиля.
+    ///
+    /// В открытом потоке результат — только полные chunk и может быть
+    /// пуст: остаток ждёт следующих пакетов или [`Self::flush_stream`].
+    ///
+    /// # Ошибки
+    /// В роли сервера — если нет запроса клиента, на который можно ответить.
+    /// Пакет при этом не принимается и остаётся у вызывающего.
     pub fn wrap(&mut self, packet_data: &[u8]) -> Result<Bytes> {
-        match &mut self.profile {
-            WrapperProfile::None => {
-                // Без мимикрии — возвращаем данные как есть
-                Ok(Bytes::copy_from_slice(packet_data))
+        if let Some(stream) = self.stream.as_mut() {
+            stream.push(packet_data)?;
+            self.chunk_counter += 1;
+            return Ok(stream.encode(false));
+        }
+        if !self.can_respond() {
+            return Err(MimicryError::WrapError(
+                "нет запроса клиента, на который можно ответить".to_string(),
+            ));
+        }
+
+        match self.aggregator.as_mut() {
+            Some(aggregator) => {
+                aggregator.push(packet_data)?;
+                let body = aggregator.take_all();
+                self.wrap_message(&body)
             }
-            WrapperProfile::VkVideo(profile) => {
-                let response = profile.generate_response(packet_data);
-                self.chunk_counter += 1;
-                Ok(response)
+            None => self.wrap_message(packet_data),
+        }
+    }
+
+    /// Сколько пакетов [`Self::wrap_batch`] примет сейчас
+    ///
+    /// Ограничено только в роли сервера без агрегации и потока: по пакету
+    /// на открытый запрос. С агрегацией неотправленное ждёт в обёртке.
+    pub fn batch_capacity(&self) -> usize {
+        if self.stream.is_some() || self.aggregator.is_some() || !self.has_request_limit() {
+            return usize::MAX;
+        }
+        self.open_requests as usize
+    }
+
+    /// Обернуть пачку LLP пакетов
+    ///
+    /// Без агрегации каждый пакет идёт отдельным сообщением, как в
+    /// [`Self::wrap`]. С агрегацией пакеты объединяются и делятся на
+    /// сообщения размера chunk профиля; последнее сообщение короче. В роли
+    /// сервера сообщений не больше, чем открытых запросов: остаток уходит
+    /// со следующей пачкой.
+    ///
+    /// # Ошибки
+    /// Если пакетов больше [`Self::batch_capacity`]; тогда ни один пакет
+    /// не принимается.
+    pub fn wrap_batch<P: AsRef<[u8]>>(&mut self, packets: &[P]) -> Result<Vec<Bytes>> {
+        if packets.len() > self.batch_capacity() {
+            return Err(MimicryError::WrapError(format!(
+                "{} пакетов на {} открытых запросов",
+                packets.len(),
+                self.open_requests
+            )));
+        }
+        if self.stream.is_some() || self.aggregator.is_none() {
+            return packets
+                .iter()
+                .map(|packet| self.wrap(packet.as_ref()))
+                .collect();
+        }
+
+        if let Some(aggregator) = self.aggregator.as_mut() {
+            for packet in packets {
+                aggregator.push(packet.as_ref())?;
             }
-            WrapperProfile::YandexMusic(profile) => {
-                let response = profile.generate_response(packet_data);
-                self.chunk_counter += 1;
-                Ok(response)
+        }
+
+        let mut messages = Vec::new();
+        while self.can_respond() {
+            let profile = &mut self.profile;
+            let body = match self.aggregator.as_mut() {
+                Some(aggregator) => {
+                    aggregator.next_body(true, || profile.recommended_chunk_size())
+                }
+                None => None,
+            };
+            let Some(body) = body else { break };
+            messages.push(self.wrap_message(&body)?);
+        }
+        Ok(messages)
+    }
+
+    /// Обернуть тело одного сообщения с учётом роли
+    fn wrap_message(&mut self, packet_data: &[u8]) -> Result<Bytes> {
+        let paired = self.profile.http_exchange();
+
+        let wrapped = match self.role {
+            Role::Symmetric => self.profile.wrap(packet_data)?,
+            Role::Client => {
+                let request = self.profile.wrap_request(packet_data, self.chunk_counter)?;
+                if paired {
+                    self.open_requests += 1;
+                }
+                request
             }
-            WrapperProfile::RuTube(profile) => {
-                let response = profile.generate_response(packet_data);
-                self.chunk_counter += 1;
-                Ok(response)
+            Role::Server => {
+                if paired && self.open_requests == 0 {
+                    return Err(MimicryError::WrapError(
+                        "нет запроса клиента, на который можно ответить".to_string(),
+                    ));
+                }
+                let response = self.profile.wrap(packet_data)?;
+                if paired {
+                    self.open_requests -= 1;
+                }
+                response
             }
-        }
+        };
+
+        self.chunk_counter += 1;
+        Ok(wrapped)
     }
 
     /// Извлечь LLP пакет из HTTP-трафика
@@ -85,57 +313,228 @@ impl PacketWrapper {
     /// - `wrapped_data`: HTTP request/response с упакованным пакетом
     ///
     /// # Возвращает
-    /// Сериализованный LLP пакет
-    pub fn unwrap(&self, wrapped_data: &[u8]) -> Result<Bytes> {
-        match &self.profile {
-            WrapperProfile::None => {
-                // Без мимикрии — возвращаем данные как есть
-                Ok(Bytes::copy_from_slice(wrapped_data))
+    /// Сериализованный LLP пакет; в роли сервера запрос без payload
+    /// (опрос) даёт пустой результат
+    ///
+    /// # Ошибки
+    /// В режиме агрегации сообщение может нести часть пакета или
+    /// несколько пакетов — используйте [`Self::unwrap_batch`].
+    pub fn unwrap(&mut self, wrapped_data: &[u8]) -> Result<Bytes> {
+        if self.aggregator.is_some() {
+            return Err(MimicryError::UnwrapError(
+                "в режиме агрегации используйте unwrap_batch".to_string(),
+            ));
+        }
+        self.unwrap_message(wrapped_data)
+    }
+
+    /// Извлечь LLP пакеты из HTTP-трафика
+    ///
+    /// Без агрегации результат — один пакет, как у [`Self::unwrap`]. С
+    /// агрегацией — все пакеты, завершённые этим сообщением (возможно, ни
+    /// одного).
+    pub fn unwrap_batch(&mut self, wrapped_data: &[u8]) -> Result<Vec<Bytes>> {
+        let body = self.unwrap_message(wrapped_data)?;
+        match self.aggregator.as_mut() {
+            Some(aggregator) => aggregator.unpack(&body),
+            None => Ok(vec![body]),
+        }
+    }
+
+    /// Извлечь тело одного сообщения с учётом роли
+    fn unwrap_message(&mut self, wrapped_data: &[u8]) -> Result<Bytes> {
+        let paired = self.profile.http_exchange();
+
+        match self.role {
+            Role::Symmetric => self.profile.unwrap(wrapped_data),
+            Role::Client => {
+                let packet = self.profile.unwrap(wrapped_data)?;
+                self.profile.observe_response(wrapped_data);
+                if paired {
+                    self.open_requests = self.open_requests.saturating_sub(1);
+                }
+                Ok(packet)
+            }
+            Role::Server => {
+                let packet = self.profile.unwrap_request(wrapped_data)?;
+                self.profile.observe_request(wrapped_data);
+                if paired {
+                    self.open_requests += 1;
+                }
+                Ok(packet)
+            }
+        }
+    }
+
+    /// Открыть потоковый chunked ответ
+    ///
+    /// Возвращает заголовок ответа; дальнейшие [`Self::wrap`] отдают
+    /// chunk тела этого ответа, пока поток не закрыт [`Self::end_stream`].
+    /// В роли сервера поток отвечает на один запрос клиента.
+    ///
+    /// # Ошибки
+    /// Если профиль не поддерживает потоковый режим, поток уже открыт,
+    /// обёртка в роли клиента или нет запроса, на который можно ответить.
+    pub fn start_stream(&mut self) -> Result<Bytes> {
+        if self.stream.is_some() {
+            return Err(MimicryError::WrapError("поток уже открыт".to_string()));
+        }
+        if self.role == Role::Client {
+            return Err(MimicryError::WrapError(
+                "клиент не может открыть потоковый ответ".to_string(),
+            ));
+        }
+        if !self.can_respond() {
+            return Err(MimicryError::WrapError(
+                "нет запроса клиента, на который можно ответить".to_string(),
+            ));
+        }
+        let head = self.profile.stream_head().ok_or_else(|| {
+            MimicryError::UnsupportedProfile(format!(
+                "{}: потоковый режим не поддерживается",
+                self.profile.name()
+            ))
+        })?;
+
+        if self.role == Role::Server {
+            self.open_requests -= 1;
+        }
+        self.stream = Some(StreamEncoder::new(self.child_rng()));
+        Ok(head)
+    }
+
+    /// Отправить остаток потока коротким chunk
+    ///
+    /// Пустой результат — отправлять нечего.
+    pub fn flush_stream(&mut self) -> Result<Bytes> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| MimicryError::WrapError("поток не открыт".to_string()))?;
+        Ok(stream.encode(true))
+    }
+
+    /// Закрыть потоковый ответ: остаток данных и последний chunk
+    pub fn end_stream(&mut self) -> Result<Bytes> {
+        let mut stream = self
+            .stream
+            .take()
+            .ok_or_else(|| MimicryError::WrapError("поток не открыт".to_string()))?;
+        Ok(stream.finish())
+    }
+
+    /// RNG с seed из источника обёртки
+    ///
+    /// Для кадров WebSocket ([`crate::ws::WsCodec::with_rng`]) и других
+    /// частей трафика вне профиля: при фиксированном seed обёртки они
+    /// тоже воспроизводимы.
+    pub fn child_rng(&mut self) -> BoxedRng {
+        seeded_child(&mut self.rng)
+    }
+
+    /// Открыт ли потоковый ответ (в любом направлении)
+    pub fn is_streaming(&self) -> bool {
+        self.stream.is_some() || self.incoming.is_some()
+    }
+
+    /// Извлечь LLP пакеты из результата [`crate::codec::HttpDecoder::decode_frame`]
+    ///
+    /// Целое сообщение обрабатывается как в [`Self::unwrap_batch`]; части
+    /// потокового ответа могут дать ноль или несколько пакетов.
+    pub fn unwrap_frame(&mut self, frame: Frame) -> Result<Vec<Bytes>> {
+        match frame {
+            Frame::Message(message) => self.unwrap_batch(&message),
+            Frame::StreamHead(_) => {
+                if self.incoming.is_some() {
+                    return Err(MimicryError::UnwrapError("поток уже открыт".to_string()));
+                }
+                if self.role == Role::Client && self.profile.http_exchange() {
+                    self.open_requests = self.open_requests.saturating_sub(1);
+                }
+                self.incoming = Some(StreamDecoder::new());
+                Ok(Vec::new())
             }
-            WrapperProfile::VkVideo(_) => VkVideoParser::extract_response_payload(wrapped_data),
-            WrapperProfile::YandexMusic(_) => {
-                YandexMusicParser::extract_response_payload(wrapped_data)
+            Frame::StreamData(data) => {
+                let incoming = self
+                    .incoming
+                    .as_mut()
+                    .ok_or_else(|| MimicryError::UnwrapError("поток не открыт".to_string()))?;
+                incoming.extend(&data);
+                let mut packets = Vec::new();
+                while let Some(packet) = incoming.next_packet()? {
+                    packets.push(packet);
+                }
+                Ok(packets)
             }
-            WrapperProfile::RuTube(_) => RuTubeParser::extract_response_payload(wrapped_data),
+            Frame::StreamEnd => match self.incoming.take() {
+                Some(incoming) if incoming.has_partial() => Err(MimicryError::UnwrapError(
+                    "поток закрыт посреди пакета".to_string(),
+                )),
+                Some(_) => Ok(Vec::new()),
+                None => Err(MimicryError::UnwrapError("поток не открыт".to_string())),
+            },
         }
     }
 
+    /// Количество запросов без ответа
+    pub fn open_requests(&self) -> u64 {
+        self.open_requests
+    }
+
+    /// Может ли сервер сейчас отправить ответ
+    pub fn can_respond(&self) -> bool {
+        !self.has_request_limit() || self.open_requests > 0
+    }
+
+    /// Отвечает ли обёртка только на запросы клиента
+    fn has_request_limit(&self) -> bool {
+        self.role == Role::Server && self.profile.http_exchange()
+    }
+
+    /// Нужно ли клиенту отправить опрос, чтобы сервер мог передать данные
+    ///
+    /// Пока открыт потоковый ответ, данные сервера приходят в нём.
+    pub fn needs_poll(&self) -> bool {
+        self.role == Role::Client
+            && self.profile.http_exchange()
+            && self.open_requests == 0
+            && self.incoming.is_none()
+    }
+
+    /// Сформировать запрос клиента без payload (опрос сервера)
+    pub fn poll_request(&mut self) -> Result<Bytes> {
+        let request = self.profile.generate_request(self.chunk_counter)?;
+        if self.role == Role::Client && self.profile.http_exchange() {
+            self.open_requests += 1;
+        }
+        Ok(request)
+    }
+
     /// Получить рекомендуемую задержку для следующего пакета
     pub fn next_packet_timing(&mut self) -> Duration {
-        match &mut self.profile {
-            WrapperProfile::None => Duration::from_millis(0),
-            WrapperProfile::VkVideo(profile) => profile.next_packet_timing(),
-            WrapperProfile::YandexMusic(profile) => profile.next_packet_timing(),
-            WrapperProfile::RuTube(profile) => profile.next_packet_timing(),
-        }
+        self.profile.next_packet_timing()
+    }
+
+    /// Паттерн отправки профиля для [`crate::shaper::TrafficShaper`]
+    pub fn timing_profile(&self) -> Option<TimingProfile> {
+        self.profile.timing_profile().cloned()
+    }
+
+    /// Кривая битрейта профиля для [`crate::cover::CoverTraffic`]
+    pub fn bitrate_curve(&self) -> Option<BitrateCurve> {
+        self.profile.bitrate_curve()
     }
 
     /// Получить рекомендуемый размер chunk для профиля
     pub fn recommended_chunk_size(&mut self) -> usize {
-        match &mut self.profile {
-            WrapperProfile::None => 1024 * 1024, // 1 MB по умолчанию
-            WrapperProfile::VkVideo(profile) => profile.recommended_chunk_size(),
-            WrapperProfile::YandexMusic(profile) => profile.recommended_chunk_size(),
-            WrapperProfile::RuTube(profile) => profile.recommended_chunk_size(),
-        }
+        self.profile.recommended_chunk_size()
     }
 
     /// Сгенерировать HTTP запрос для профиля (опционально)
     ///
     /// Используется для имитации двустороннего HTTP-трафика.
     pub fn generate_request(&mut self) -> Result<Bytes> {
-        match &mut self.profile {
-            WrapperProfile::None => Ok(Bytes::new()),
-            WrapperProfile::VkVideo(profile) => {
-                Ok(profile.generate_request(self.chunk_counter))
-            }
-            WrapperProfile::YandexMusic(profile) => {
-                Ok(profile.generate_request(self.chunk_counter))
-            }
-            WrapperProfile::RuTube(profile) => {
-                Ok(profile.generate_request(self.chunk_counter, 0))
-            }
-        }
+        self.profile.generate_request(self.chunk_counter)
     }
 
     /// Получить текущий счётчик chunk
@@ -156,11 +555,18 @@ impl QuickWrapper {
 
     /// Извлечь пакет из профиля мимикрии
     pub fn unwrap(profile: MimicryProfile, wrapped_data: &[u8]) -> Result<Bytes> {
-        let wrapper = PacketWrapper::new(profile);
+        let mut wrapper = PacketWrapper::new(profile);
         wrapper.unwrap(wrapped_data)
     }
 }
 
+/// RNG с seed, взятым из `rng`
+fn seeded_child(rng: &mut BoxedRng) -> BoxedRng {
+    let mut seed = <StdRng as SeedableRng>::Seed::default();
+    rng.fill_bytes(&mut seed);
+    Box::new(StdRng::from_seed(seed))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -197,6 +603,72 @@ mod tests {
         assert_eq!(&unwrapped[..], original_data);
     }
 
+    #[test]
+    fn test_wrapper_deterministic_sources() {
+        use llp_core::clock::SimulatedClock;
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let clock = SimulatedClock::new(1_700_000_000);
+        let make = |seed| {
+            PacketWrapper::with_sources(
+                MimicryProfile::RuTube,
+                Box::new(StdRng::seed_from_u64(seed)),
+                clock.shared(),
+            )
+        };
+
+        let mut a = make(9);
+        let mut b = make(9);
+        let wrapped_a = a.wrap(b"payload").unwrap();
+        assert_eq!(wrapped_a, b.wrap(b"payload").unwrap());
+        assert_eq!(a.next_packet_timing(), b.next_packet_timing());
+
+        let text = String::from_utf8_lossy(&wrapped_a);
+        assert!(text.contains("Date: Tue, 14 Nov 2023 22:13:20 GMT"));
+
+        let mut c = make(10);
+        assert_ne!(wrapped_a, c.wrap(b"payload").unwrap());
+    }
+
+    #[test]
+    fn test_deterministic_stream_and_websocket() {
+        use crate::ws::WsCodec;
+        use llp_core::clock::SimulatedClock;
+
+        let clock = SimulatedClock::new(1_700_000_000);
+        let run = |seed| {
+            let make = |role| {
+                PacketWrapper::with_sources(
+                    MimicryProfile::VkVideo,
+                    Box::new(StdRng::seed_from_u64(seed)),
+                    clock.shared(),
+                )
+                .with_role(role)
+            };
+            let mut client = make(Role::Client);
+            let mut server = make(Role::Server);
+
+            // Размеры chunk потокового ответа берутся из RNG обёртки
+            server.unwrap(&client.poll_request().unwrap()).unwrap();
+            let mut wire = server.start_stream().unwrap().to_vec();
+            for i in 0..20u8 {
+                wire.extend_from_slice(&server.wrap(&[i; 700]).unwrap());
+            }
+            wire.extend_from_slice(&server.end_stream().unwrap());
+
+            // Маска кадра клиента — из дочернего RNG
+            let mut codec = WsCodec::client().with_rng(client.child_rng());
+            (wire, codec.binary(b"packet"))
+        };
+
+        let (wire, frame) = run(3);
+        assert_eq!((wire.clone(), frame.clone()), run(3));
+
+        let (other_wire, other_frame) = run(4);
+        assert_ne!(wire, other_wire);
+        assert_ne!(frame, other_frame);
+    }
+
     #[test]
     fn test_wrapper_none() {
         let mut wrapper = PacketWrapper::new(MimicryProfile::None);
@@ -237,6 +709,196 @@ mod tests {
         let mut wrapper = PacketWrapper::new(MimicryProfile::VkVideo);
         let timing = wrapper.next_packet_timing();
         assert!(timing.as_millis() <= 1000);
+        assert_eq!(wrapper.timing_profile().unwrap().burst_size(), 5);
+
+        // Без мимикрии паттерна отправки нет
+        assert!(PacketWrapper::new(MimicryProfile::None)
+            .timing_profile()
+            .is_none());
+        assert_eq!(wrapper.bitrate_curve(), Some(BitrateCurve::video_streaming()));
+    }
+
+    #[test]
+    fn test_custom_profile_from_registry() {
+        use crate::profiles::PassthroughProfile;
+
+        assert!(PacketWrapper::try_new(MimicryProfile::Custom(0x8f00)).is_err());
+
+        registry::register_global(0x8f00, "wrapper_test", |_, _| Box::new(PassthroughProfile))
+            .unwrap();
+        let mut wrapper = PacketWrapper::try_new(MimicryProfile::Custom(0x8f00)).unwrap();
+        assert_eq!(wrapper.profile_name(), "none");
+
+        let wrapped = wrapper.wrap(b"custom").unwrap();
+        assert_eq!(&wrapper.unwrap(&wrapped).unwrap()[..], b"custom");
+    }
+
+    #[test]
+    fn test_client_server_exchange() {
+        for profile in [
+            MimicryProfile::VkVideo,
+            MimicryProfile::YandexMusic,
+            MimicryProfile::RuTube,
+        ] {
+            let mut client = PacketWrapper::new(profile).with_role(Role::Client);
+            let mut server = PacketWrapper::new(profile).with_role(Role::Server);
+
+            // Клиент → сервер: запрос с payload
+            let request = client.wrap(b"upstream").unwrap();
+            assert!(!request.starts_with(b"HTTP/"));
+            assert_eq!(&server.unwrap(&request).unwrap()[..], b"upstream");
+            assert_eq!(client.open_requests(), 1);
+
+            // Сервер → клиент: ответ на этот запрос
+            let response = server.wrap(b"downstream").unwrap();
+            assert!(response.starts_with(b"HTTP/1.1 "));
+            assert_eq!(&client.unwrap(&response).unwrap()[..], b"downstream");
+
+            // Ответ продолжает сессию, названную в запросе
+            let session = |message: &[u8]| {
+                let message = String::from_utf8_lossy(message);
+                let start = message.find("-Session: ").unwrap() + 10;
+                message[start..].split("\r\n").next().unwrap().to_string()
+            };
+            assert_eq!(session(&response), session(&request));
+
+            // Без открытого запроса сервер не отвечает
+            assert!(!server.can_respond());
+            assert!(server.wrap(b"unsolicited").is_err());
+
+            // Опрос клиента даёт серверу возможность ответить
+            assert!(client.needs_poll());
+            let poll = client.poll_request().unwrap();
+            assert!(server.unwrap(&poll).unwrap().is_empty());
+            assert!(!client.needs_poll());
+            assert!(server.wrap(b"pushed").is_ok());
+        }
+    }
+
+    #[test]
+    fn test_streaming_response() {
+        use crate::codec::HttpDecoder;
+
+        for profile in [MimicryProfile::VkVideo, MimicryProfile::RuTube] {
+            let mut client = PacketWrapper::new(profile).with_role(Role::Client);
+            let mut server = PacketWrapper::new(profile).with_role(Role::Server);
+
+            // Поток открывается только в ответ на запрос
+            assert!(server.start_stream().is_err());
+            server.unwrap(&client.poll_request().unwrap()).unwrap();
+
+            let mut wire = server.start_stream().unwrap().to_vec();
+            assert!(server.start_stream().is_err());
+            // Пакет меньше chunk ждёт, пока вызывающий код не сбросит поток
+            assert!(server.wrap(b"small").unwrap().is_empty());
+            let flushed = server.flush_stream().unwrap();
+            assert!(flushed.ends_with(b"\r\n") && !flushed.is_empty());
+            assert!(server.flush_stream().unwrap().is_empty());
+            wire.extend_from_slice(&flushed);
+
+            let mut packets: Vec<Vec<u8>> = vec![b"small".to_vec()];
+            packets.extend((0..10u8).map(|i| vec![i; 700]));
+            for packet in &packets[1..] {
+                wire.extend_from_slice(&server.wrap(packet).unwrap());
+            }
+            wire.extend_from_slice(&server.end_stream().unwrap());
+            assert!(server.flush_stream().is_err());
+            assert!(!server.can_respond());
+
+            let mut decoder = HttpDecoder::new().with_streaming(true);
+            decoder.extend(&wire);
+            let mut received = Vec::new();
+            while let Some(frame) = decoder.decode_frame().unwrap() {
+                if frame != Frame::StreamEnd {
+                    assert!(client.is_streaming() || matches!(frame, Frame::StreamHead(_)));
+                    assert!(!client.needs_poll());
+                }
+                received.extend(client.unwrap_frame(frame).unwrap());
+            }
+
+            assert_eq!(received, packets);
+            assert!(!client.is_streaming());
+            assert!(client.needs_poll());
+        }
+
+        let mut music = PacketWrapper::new(MimicryProfile::YandexMusic);
+        assert!(music.start_stream().is_err());
+    }
+
+    #[test]
+    fn test_aggregated_exchange() {
+        let options = AggregationOptions::default();
+        let mut client = PacketWrapper::new(MimicryProfile::VkVideo)
+            .with_role(Role::Client)
+            .with_aggregation(options.clone());
+        let mut server = PacketWrapper::new(MimicryProfile::VkVideo)
+            .with_role(Role::Server)
+            .with_aggregation(options);
+        assert!(client.is_aggregating());
+
+        // Клиент → сервер: пачка в одном запросе
+        let upload = client.wrap_batch(&[&b"first"[..], &b"second"[..]]).unwrap();
+        assert_eq!(upload.len(), 1);
+        let received = server.unwrap_batch(&upload[0]).unwrap();
+        assert_eq!(received, vec![&b"first"[..], &b"second"[..]]);
+        assert!(server.unwrap(&upload[0]).is_err());
+
+        // Сервер → клиент: пачка делится на сообщения размера chunk профиля,
+        // но не больше, чем открытых запросов. Данных больше двух chunk
+        // максимального размера (256 KB), так что сообщений не меньше трёх
+        let packets: Vec<Vec<u8>> = (0..400u16).map(|i| vec![i as u8; 1400]).collect();
+        let mut messages = server.wrap_batch(&packets).unwrap();
+        assert_eq!(messages.len(), 1);
+        while messages.len() < 20 {
+            server.unwrap_batch(&client.poll_request().unwrap()).unwrap();
+            let next = server.wrap_batch(&[] as &[&[u8]]).unwrap();
+            if next.is_empty() {
+                break;
+            }
+            messages.extend(next);
+        }
+        assert!(messages.len() > 2);
+        for message in &messages[..messages.len() - 1] {
+            assert!(message.len() >= 64 * 1024);
+        }
+
+        let mut received = Vec::new();
+        for message in &messages {
+            received.extend(client.unwrap_batch(message).unwrap());
+        }
+        assert_eq!(received, packets);
+    }
+
+    #[test]
+    fn test_wrap_without_request_keeps_data() {
+        let mut server = PacketWrapper::new(MimicryProfile::VkVideo)
+            .with_role(Role::Server)
+            .with_aggregation(AggregationOptions::default());
+        assert!(server.wrap(b"packet").is_err());
+        assert_eq!(server.aggregator.as_ref().unwrap().pending(), 0);
+
+        // Без агрегации пачка сверх открытых запросов не принимается целиком
+        let mut client = PacketWrapper::new(MimicryProfile::VkVideo).with_role(Role::Client);
+        let mut server = PacketWrapper::new(MimicryProfile::VkVideo).with_role(Role::Server);
+        server.unwrap(&client.poll_request().unwrap()).unwrap();
+        assert_eq!(server.batch_capacity(), 1);
+        assert!(server.wrap_batch(&[&b"a"[..], &b"b"[..]]).is_err());
+        assert_eq!(server.open_requests(), 1);
+        let sent = server.wrap_batch(&[&b"a"[..]]).unwrap();
+        assert_eq!(&client.unwrap(&sent[0]).unwrap()[..], b"a");
+        assert_eq!(server.batch_capacity(), 0);
+    }
+
+    #[test]
+    fn test_passthrough_roles_unpaired() {
+        let mut client = PacketWrapper::new(MimicryProfile::None).with_role(Role::Client);
+        let mut server = PacketWrapper::new(MimicryProfile::None).with_role(Role::Server);
+
+        assert!(!client.needs_poll());
+        assert!(server.can_respond());
+        let wrapped = server.wrap(b"data").unwrap();
+        assert_eq!(&client.unwrap(&wrapped).unwrap()[..], b"data");
+        assert_eq!(&server.unwrap(&client.wrap(b"up").unwrap()).unwrap()[..], b"up");
     }
 
     #[test]
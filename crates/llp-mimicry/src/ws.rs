@@ -0,0 +1,790 @@
+//! WebSocket транспорт (RFC 6455)
+//!
+//! CDN и обратные прокси охотно пропускают WebSocket, но не произвольный
+//! поток байт. Клиент открывает соединение запросом HTTP Upgrade с
+//! заголовками профиля мимикрии ([`upgrade_request`]) на настраиваемый
+//! путь, сервер отвечает `101 Switching Protocols` с `Server` профиля
+//! ([`upgrade_response`]). Дальше каждый LLP пакет — одно бинарное
+//! сообщение: от клиента кадры маскированы, от сервера — нет. Ping/pong
+//! служат keepalive.
+//!
+//! [`WsCodec`] не выполняет ввод-вывод, как и [`crate::h2::H2Session`];
+//! [`read_message`] и [`write_message`] — обёртки над async потоком.
+//!
+//! ```text
+//! GET /ws HTTP/1.1
+//! Host: vkvideo.ru
+//! Connection: Upgrade
+//! Upgrade: websocket
+//! Origin: https://vkvideo.ru
+//! Sec-WebSocket-Version: 13
+//! Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==
+//! ```
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use rand::rngs::OsRng;
+use rand::{Rng, RngCore};
+use sha1::{Digest, Sha1};
+use std::fmt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::error::{MimicryError, Result};
+use crate::exchange::Role;
+use crate::profiles::BoxedRng;
+use crate::wrapper::PacketWrapper;
+
+/// GUID для вычисления `Sec-WebSocket-Accept`
+pub const WS_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Максимальный размер сообщения по умолчанию
+pub const DEFAULT_MAX_MESSAGE: usize = 256 * 1024;
+
+/// Максимальный размер заголовка HTTP Upgrade
+pub const MAX_HEAD_SIZE: usize = 8 * 1024;
+
+/// Коды операций кадров
+pub mod opcode {
+    /// Продолжение фрагментированного сообщения
+    pub const CONTINUATION: u8 = 0x0;
+    /// Текстовое сообщение
+    pub const TEXT: u8 = 0x1;
+    /// Бинарное сообщение
+    pub const BINARY: u8 = 0x2;
+    /// Закрытие соединения
+    pub const CLOSE: u8 = 0x8;
+    /// Проверка соединения
+    pub const PING: u8 = 0x9;
+    /// Ответ на ping
+    pub const PONG: u8 = 0xA;
+}
+
+/// Коды закрытия соединения
+pub mod close_code {
+    /// Штатное закрытие
+    pub const NORMAL: u16 = 1000;
+    /// Нарушение протокола
+    pub const PROTOCOL_ERROR: u16 = 1002;
+    /// Неподдерживаемый тип данных
+    pub const UNSUPPORTED_DATA: u16 = 1003;
+    /// Сообщение слишком большое
+    pub const MESSAGE_TOO_BIG: u16 = 1009;
+}
+
+/// Разобранное сообщение
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// Бинарное сообщение (LLP пакет)
+    Binary(Bytes),
+    /// Ping с payload
+    Ping(Bytes),
+    /// Pong с payload
+    Pong(Bytes),
+    /// Закрытие с кодом, если он передан
+    Close(Option<u16>),
+}
+
+/// Кодек кадров WebSocket
+///
+/// Клиент маскирует отправляемые кадры и требует немаскированные
+/// входящие, сервер — наоборот.
+pub struct WsCodec {
+    role: Role,
+    buf: BytesMut,
+    /// Накопленные фрагменты сообщения
+    fragments: Option<BytesMut>,
+    max_message: usize,
+    close_sent: bool,
+    /// Источник масок кадров клиента
+    rng: BoxedRng,
+}
+
+impl fmt::Debug for WsCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WsCodec")
+            .field("role", &self.role)
+            .field("buffered", &self.buf.len())
+            .field("max_message", &self.max_message)
+            .field("close_sent", &self.close_sent)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WsCodec {
+    /// Кодек клиентской стороны
+    pub fn client() -> Self {
+        Self::new(Role::Client)
+    }
+
+    /// Кодек серверной стороны
+    pub fn server() -> Self {
+        Self::new(Role::Server)
+    }
+
+    fn new(role: Role) -> Self {
+        Self {
+            role,
+            buf: BytesMut::new(),
+            fragments: None,
+            max_message: DEFAULT_MAX_MESSAGE,
+            close_sent: false,
+            rng: Box::new(OsRng),
+        }
+    }
+
+    /// Задать источник масок кадров (по умолчанию `OsRng`)
+    ///
+    /// С RNG из [`PacketWrapper::child_rng`] кадры клиента
+    /// воспроизводимы при фиксированном seed обёртки.
+    pub fn with_rng(mut self, rng: BoxedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Ограничить размер принимаемого сообщения
+    pub fn with_max_message(mut self, max_message: usize) -> Self {
+        self.max_message = max_message;
+        self
+    }
+
+    /// Роль в соединении
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Добавить прочитанные данные
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Количество накопленных байт
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Отправлен ли уже кадр закрытия
+    pub fn close_sent(&self) -> bool {
+        self.close_sent
+    }
+
+    /// Кадр бинарного сообщения
+    pub fn binary(&mut self, payload: &[u8]) -> Bytes {
+        self.encode(opcode::BINARY, payload)
+    }
+
+    /// Кадр ping
+    pub fn ping(&mut self, payload: &[u8]) -> Bytes {
+        self.encode(opcode::PING, &payload[..payload.len().min(125)])
+    }
+
+    /// Кадр pong
+    pub fn pong(&mut self, payload: &[u8]) -> Bytes {
+        self.encode(opcode::PONG, &payload[..payload.len().min(125)])
+    }
+
+    /// Кадр закрытия
+    pub fn close(&mut self, code: u16) -> Bytes {
+        self.close_sent = true;
+        self.encode(opcode::CLOSE, &code.to_be_bytes())
+    }
+
+    /// Закодировать один кадр с флагом FIN
+    pub fn encode(&mut self, opcode: u8, payload: &[u8]) -> Bytes {
+        let mask = match self.role {
+            Role::Client => Some(self.rng.gen::<[u8; 4]>()),
+            _ => None,
+        };
+        let mut out = BytesMut::with_capacity(payload.len() + 14);
+        encode_frame(&mut out, opcode, payload, mask);
+        out.freeze()
+    }
+
+    /// Извлечь следующее целое сообщение
+    ///
+    /// Управляющие кадры возвращаются сразу, даже посреди фрагментированного
+    /// сообщения.
+    pub fn decode(&mut self) -> Result<Option<Message>> {
+        loop {
+            let Some((fin, code, payload)) = self.decode_frame()? else {
+                return Ok(None);
+            };
+            match code {
+                opcode::PING => return Ok(Some(Message::Ping(payload))),
+                opcode::PONG => return Ok(Some(Message::Pong(payload))),
+                opcode::CLOSE => {
+                    let code = match payload.len() {
+                        0 => None,
+                        1 => return Err(ws_error("обрыв кода закрытия")),
+                        _ => Some(u16::from_be_bytes([payload[0], payload[1]])),
+                    };
+                    return Ok(Some(Message::Close(code)));
+                }
+                opcode::BINARY if self.fragments.is_none() => {
+                    if fin {
+                        return Ok(Some(Message::Binary(payload)));
+                    }
+                    self.fragments = Some(BytesMut::from(&payload[..]));
+                }
+                opcode::CONTINUATION => {
+                    let message = self
+                        .fragments
+                        .as_mut()
+                        .ok_or_else(|| ws_error("продолжение без начала сообщения"))?;
+                    if message.len() + payload.len() > self.max_message {
+                        return Err(ws_error("сообщение больше ограничения"));
+                    }
+                    message.extend_from_slice(&payload);
+                    if fin {
+                        let message = self.fragments.take().unwrap_or_default();
+                        return Ok(Some(Message::Binary(message.freeze())));
+                    }
+                }
+                opcode::BINARY => {
+                    return Err(ws_error("новое сообщение внутри фрагментированного"))
+                }
+                opcode::TEXT => return Err(ws_error("текстовые сообщения не поддерживаются")),
+                other => return Err(ws_error(&format!("неизвестный opcode {:#x}", other))),
+            }
+        }
+    }
+
+    /// Извлечь следующий кадр: FIN, opcode и снятый с маски payload
+    fn decode_frame(&mut self) -> Result<Option<(bool, u8, Bytes)>> {
+        if self.buf.len() < 2 {
+            return Ok(None);
+        }
+        let (first, second) = (self.buf[0], self.buf[1]);
+        if first & 0x70 != 0 {
+            return Err(ws_error("установлены биты RSV без расширений"));
+        }
+        let fin = first & 0x80 != 0;
+        let code = first & 0x0F;
+        let masked = second & 0x80 != 0;
+        if masked != (self.role == Role::Server) {
+            return Err(ws_error(match self.role {
+                Role::Server => "кадр клиента без маски",
+                _ => "кадр сервера с маской",
+            }));
+        }
+
+        let (len, header) = match second & 0x7F {
+            126 if self.buf.len() >= 4 => {
+                (u16::from_be_bytes([self.buf[2], self.buf[3]]) as u64, 4)
+            }
+            127 if self.buf.len() >= 10 => {
+                let mut len = [0u8; 8];
+                len.copy_from_slice(&self.buf[2..10]);
+                (u64::from_be_bytes(len), 10)
+            }
+            126 | 127 => return Ok(None),
+            len => (len as u64, 2),
+        };
+        if code & 0x8 != 0 && (!fin || len > 125) {
+            return Err(ws_error("некорректный управляющий кадр"));
+        }
+        if len > self.max_message as u64 {
+            return Err(ws_error(&format!(
+                "кадр {} байт больше ограничения {}",
+                len, self.max_message
+            )));
+        }
+
+        let header = header + if masked { 4 } else { 0 };
+        let total = header + len as usize;
+        if self.buf.len() < total {
+            return Ok(None);
+        }
+        let mut frame = self.buf.split_to(total);
+        let mask = masked.then(|| {
+            [
+                frame[header - 4],
+                frame[header - 3],
+                frame[header - 2],
+                frame[header - 1],
+            ]
+        });
+        frame.advance(header);
+        if let Some(mask) = mask {
+            apply_mask(&mut frame, mask);
+        }
+        Ok(Some((fin, code, frame.freeze())))
+    }
+}
+
+/// Записать кадр с флагом FIN в `out`
+pub fn encode_frame(out: &mut BytesMut, opcode: u8, payload: &[u8], mask: Option<[u8; 4]>) {
+    out.put_u8(0x80 | opcode);
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len @ 0..=125 => out.put_u8(mask_bit | len as u8),
+        len @ 126..=0xFFFF => {
+            out.put_u8(mask_bit | 126);
+            out.put_u16(len as u16);
+        }
+        len => {
+            out.put_u8(mask_bit | 127);
+            out.put_u64(len as u64);
+        }
+    }
+    match mask {
+        Some(mask) => {
+            out.put_slice(&mask);
+            let start = out.len();
+            out.put_slice(payload);
+            apply_mask(&mut out[start..], mask);
+        }
+        None => out.put_slice(payload),
+    }
+}
+
+fn apply_mask(data: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
+
+fn ws_error(message: &str) -> MimicryError {
+    MimicryError::ParseError(format!("WebSocket: {}", message))
+}
+
+/// Случайное значение `Sec-WebSocket-Key`
+pub fn generate_key<R: RngCore>(rng: &mut R) -> String {
+    let mut nonce = [0u8; 16];
+    rng.fill_bytes(&mut nonce);
+    STANDARD.encode(nonce)
+}
+
+/// Значение `Sec-WebSocket-Accept` для ключа клиента
+pub fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(WS_GUID.as_bytes());
+    STANDARD.encode(hasher.finalize())
+}
+
+/// Бинарное значение в виде подпротокола (`Sec-WebSocket-Protocol`)
+pub fn encode_protocol(value: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(value)
+}
+
+/// Бинарное значение из подпротокола
+pub fn decode_protocol(protocol: &str) -> Option<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(protocol).ok()
+}
+
+/// Запрос HTTP Upgrade с заголовками профиля
+///
+/// `User-Agent`, `Accept-Language` и `Cookie` берутся из запроса профиля,
+/// `Host` — из него же, если не задан `host` (например, домен CDN).
+pub fn upgrade_request(
+    wrapper: &mut PacketWrapper,
+    host: Option<&str>,
+    path: &str,
+    key: &str,
+    protocol: Option<&str>,
+) -> Result<Bytes> {
+    let template = wrapper.generate_request()?;
+    let mut parsed = [httparse::EMPTY_HEADER; 64];
+    let mut request = httparse::Request::new(&mut parsed);
+    // У профилей без HTTP запроса заголовков нет: остаётся только host
+    let _ = request.parse(&template);
+    let header = |name: &str| {
+        request
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+            .map(|h| String::from_utf8_lossy(h.value).into_owned())
+    };
+
+    let host = host
+        .map(str::to_string)
+        .or_else(|| header("host"))
+        .ok_or_else(|| {
+            MimicryError::WrapError("профиль не задаёт Host, укажите host".to_string())
+        })?;
+
+    let mut out = format!(
+        "GET {} HTTP/1.1\r\n\
+         Host: {}\r\n\
+         Connection: Upgrade\r\n\
+         Pragma: no-cache\r\n\
+         Cache-Control: no-cache\r\n",
+        path, host
+    );
+    if let Some(user_agent) = header("user-agent") {
+        out.push_str(&format!("User-Agent: {}\r\n", user_agent));
+    }
+    out.push_str(&format!(
+        "Upgrade: websocket\r\n\
+         Origin: https://{}\r\n\
+         Sec-WebSocket-Version: 13\r\n\
+         Accept-Encoding: gzip, deflate, br\r\n",
+        host
+    ));
+    for name in ["Accept-Language", "Cookie"] {
+        if let Some(value) = header(name) {
+            out.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    out.push_str(&format!(
+        "Sec-WebSocket-Key: {}\r\n\
+         Sec-WebSocket-Extensions: permessage-deflate; client_max_window_bits\r\n",
+        key
+    ));
+    if let Some(protocol) = protocol {
+        out.push_str(&format!("Sec-WebSocket-Protocol: {}\r\n", protocol));
+    }
+    out.push_str("\r\n");
+    Ok(Bytes::from(out))
+}
+
+/// Разобранный запрос HTTP Upgrade
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upgrade {
+    /// Путь запроса без query
+    pub path: String,
+    /// `Sec-WebSocket-Key`
+    pub key: String,
+    /// Предложенные подпротоколы
+    pub protocols: Vec<String>,
+}
+
+/// Разобрать запрос HTTP Upgrade
+///
+/// Ошибка — запрос не является корректным открытием WebSocket.
+pub fn parse_upgrade(head: &[u8]) -> Result<Upgrade> {
+    let mut parsed = [httparse::EMPTY_HEADER; 64];
+    let mut request = httparse::Request::new(&mut parsed);
+    if request
+        .parse(head)
+        .map_err(|e| MimicryError::ParseError(format!("HTTP parse error: {:?}", e)))?
+        .is_partial()
+    {
+        return Err(MimicryError::ParseError(
+            "Incomplete HTTP request".to_string(),
+        ));
+    }
+    if request.method != Some("GET") || request.version != Some(1) {
+        return Err(ws_error("Upgrade требует GET и HTTP/1.1"));
+    }
+
+    let values = |name: &str| -> Vec<String> {
+        request
+            .headers
+            .iter()
+            .filter(|h| h.name.eq_ignore_ascii_case(name))
+            .flat_map(|h| {
+                String::from_utf8_lossy(h.value)
+                    .split(',')
+                    .map(|v| v.trim().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|v| !v.is_empty())
+            .collect()
+    };
+    let has = |name: &str, token: &str| values(name).iter().any(|v| v.eq_ignore_ascii_case(token));
+    if !has("upgrade", "websocket") || !has("connection", "upgrade") {
+        return Err(ws_error("нет Upgrade: websocket"));
+    }
+    if !has("sec-websocket-version", "13") {
+        return Err(ws_error("неподдерживаемая версия"));
+    }
+    let key = values("sec-websocket-key")
+        .into_iter()
+        .next()
+        .filter(|key| STANDARD.decode(key).map(|k| k.len() == 16).unwrap_or(false))
+        .ok_or_else(|| ws_error("некорректный Sec-WebSocket-Key"))?;
+
+    let path = request.path.unwrap_or("/");
+    Ok(Upgrade {
+        path: path.split('?').next().unwrap_or(path).to_string(),
+        key,
+        protocols: values("sec-websocket-protocol"),
+    })
+}
+
+/// Ответ `101 Switching Protocols` в стиле nginx
+pub fn upgrade_response(key: &str, server: Option<&str>, protocol: Option<&str>) -> Bytes {
+    let mut out = String::from("HTTP/1.1 101 Switching Protocols\r\n");
+    out.push_str(&format!("Server: {}\r\n", server.unwrap_or("nginx")));
+    out.push_str(&format!(
+        "Date: {}\r\n",
+        chrono::Utc::now().format("%a, %d %b %Y %H:%M:%S GMT")
+    ));
+    out.push_str(&format!(
+        "Connection: upgrade\r\n\
+         Upgrade: websocket\r\n\
+         Sec-WebSocket-Accept: {}\r\n",
+        accept_key(key)
+    ));
+    if let Some(protocol) = protocol {
+        out.push_str(&format!("Sec-WebSocket-Protocol: {}\r\n", protocol));
+    }
+    out.push_str("\r\n");
+    Bytes::from(out)
+}
+
+/// Проверить ответ сервера на запрос Upgrade с ключом `key`
+pub fn check_upgrade_response(head: &[u8], key: &str) -> Result<()> {
+    let mut parsed = [httparse::EMPTY_HEADER; 64];
+    let mut response = httparse::Response::new(&mut parsed);
+    if response
+        .parse(head)
+        .map_err(|e| MimicryError::ParseError(format!("HTTP parse error: {:?}", e)))?
+        .is_partial()
+    {
+        return Err(MimicryError::ParseError(
+            "Incomplete HTTP response".to_string(),
+        ));
+    }
+    if response.code != Some(101) {
+        return Err(ws_error(&format!(
+            "сервер ответил {} вместо 101",
+            response.code.unwrap_or(0)
+        )));
+    }
+    let accept = response
+        .headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case("sec-websocket-accept"))
+        .map(|h| h.value);
+    if accept != Some(accept_key(key).as_bytes()) {
+        return Err(ws_error("неверный Sec-WebSocket-Accept"));
+    }
+    Ok(())
+}
+
+/// Дочитать заголовок HTTP сообщения в `buf`
+///
+/// Возвращает длину заголовка; `None` — поток закончился или заголовок
+/// длиннее `limit`. Данные после заголовка остаются в `buf`.
+pub async fn read_head<R>(reader: &mut R, buf: &mut BytesMut, limit: usize) -> Result<Option<usize>>
+where
+    R: AsyncRead + Unpin,
+{
+    loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            return Ok(Some(end + 4));
+        }
+        if buf.len() > limit || reader.read_buf(buf).await? == 0 {
+            return Ok(None);
+        }
+    }
+}
+
+/// Отправить бинарное сообщение
+pub async fn write_message<W>(writer: &mut W, codec: &mut WsCodec, payload: &[u8]) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let frame = codec.binary(payload);
+    writer.write_all(&frame).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Прочитать следующее бинарное сообщение
+///
+/// На ping сразу отправляется pong, pong пропускаются. Закрытие
+/// собеседником подтверждается и возвращается как конец потока.
+pub async fn read_message<S>(stream: &mut S, codec: &mut WsCodec) -> Result<Bytes>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    loop {
+        let message = match codec.decode() {
+            Ok(message) => message,
+            Err(e) => {
+                let _ = stream
+                    .write_all(&codec.close(close_code::PROTOCOL_ERROR))
+                    .await;
+                return Err(e);
+            }
+        };
+        match message {
+            Some(Message::Binary(data)) => return Ok(data),
+            Some(Message::Ping(payload)) => {
+                let pong = codec.pong(&payload);
+                stream.write_all(&pong).await?;
+                stream.flush().await?;
+            }
+            Some(Message::Pong(_)) => {}
+            Some(Message::Close(code)) => {
+                if !codec.close_sent() {
+                    let reply = codec.close(code.unwrap_or(close_code::NORMAL));
+                    let _ = stream.write_all(&reply).await;
+                    let _ = stream.flush().await;
+                }
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+            None => {
+                if stream.read_buf(&mut codec.buf).await? == 0 {
+                    return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llp_core::packet::MimicryProfile;
+
+    #[test]
+    fn test_accept_key_rfc_example() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_upgrade_round_trip() {
+        let mut wrapper = PacketWrapper::new(MimicryProfile::VkVideo);
+        let key = generate_key(&mut rand::thread_rng());
+        let token = encode_protocol(&[7u8; 40]);
+        let request =
+            upgrade_request(&mut wrapper, None, "/ws/live?v=2", &key, Some(&token)).unwrap();
+        let text = String::from_utf8(request.to_vec()).unwrap();
+        assert!(text.starts_with("GET /ws/live?v=2 HTTP/1.1\r\nHost: vkvideo.ru\r\n"));
+        assert!(text.contains("Origin: https://vkvideo.ru\r\n"));
+        assert!(text.contains("User-Agent: "));
+
+        let upgrade = parse_upgrade(&request).unwrap();
+        assert_eq!(upgrade.path, "/ws/live");
+        assert_eq!(upgrade.key, key);
+        assert_eq!(upgrade.protocols, vec![token.clone()]);
+        assert_eq!(
+            decode_protocol(&upgrade.protocols[0]).unwrap(),
+            vec![7u8; 40]
+        );
+
+        let response = upgrade_response(&upgrade.key, Some("nginx/1.20.2"), Some(&token));
+        assert!(
+            response.starts_with(b"HTTP/1.1 101 Switching Protocols\r\nServer: nginx/1.20.2\r\n")
+        );
+        check_upgrade_response(&response, &key).unwrap();
+        assert!(check_upgrade_response(&response, &generate_key(&mut rand::thread_rng())).is_err());
+
+        let mut wrapper = PacketWrapper::new(MimicryProfile::None);
+        assert!(upgrade_request(&mut wrapper, None, "/", &key, None).is_err());
+        let request = upgrade_request(&mut wrapper, Some("cdn.example"), "/", &key, None).unwrap();
+        assert!(request.starts_with(b"GET / HTTP/1.1\r\nHost: cdn.example\r\n"));
+
+        assert!(parse_upgrade(b"GET / HTTP/1.1\r\nHost: vkvideo.ru\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn test_frames_masked_by_role() {
+        let mut client = WsCodec::client();
+        let mut server = WsCodec::server();
+        let large = vec![0x5Au8; 70_000];
+
+        let mut wire = BytesMut::new();
+        wire.extend_from_slice(&client.binary(b"llp"));
+        wire.extend_from_slice(&client.binary(&large));
+        wire.extend_from_slice(&client.ping(b"keepalive"));
+        // Маска применена: payload не виден в открытом виде
+        assert!(!wire.windows(3).any(|w| w == b"llp"));
+
+        for byte in wire.iter() {
+            server.extend(&[*byte]);
+        }
+        assert_eq!(
+            server.decode().unwrap(),
+            Some(Message::Binary(Bytes::from_static(b"llp")))
+        );
+        assert_eq!(
+            server.decode().unwrap(),
+            Some(Message::Binary(Bytes::from(large)))
+        );
+        assert_eq!(
+            server.decode().unwrap(),
+            Some(Message::Ping(Bytes::from_static(b"keepalive")))
+        );
+        assert_eq!(server.decode().unwrap(), None);
+
+        let reply = server.pong(b"keepalive");
+        assert_eq!(&reply[..2], &[0x8A, 9]);
+        client.extend(&reply);
+        assert_eq!(
+            client.decode().unwrap(),
+            Some(Message::Pong(Bytes::from_static(b"keepalive")))
+        );
+
+        // Сервер не принимает немаскированный кадр, клиент — маскированный
+        let mut unmasked = WsCodec::server();
+        unmasked.extend(&WsCodec::server().binary(b"x"));
+        assert!(unmasked.decode().is_err());
+        let mut masked = WsCodec::client();
+        masked.extend(&WsCodec::client().binary(b"x"));
+        assert!(masked.decode().is_err());
+    }
+
+    #[test]
+    fn test_fragments_and_limits() {
+        let mut client = WsCodec::client();
+        let mut wire = BytesMut::new();
+        // Фрагменты без FIN, ping между ними
+        wire.put_u8(opcode::BINARY);
+        wire.put_u8(2);
+        wire.put_slice(b"ab");
+        encode_frame(&mut wire, opcode::PING, b"", None);
+        wire.put_u8(0x80 | opcode::CONTINUATION);
+        wire.put_u8(1);
+        wire.put_slice(b"c");
+        client.extend(&wire);
+        assert_eq!(client.decode().unwrap(), Some(Message::Ping(Bytes::new())));
+        assert_eq!(
+            client.decode().unwrap(),
+            Some(Message::Binary(Bytes::from_static(b"abc")))
+        );
+
+        let mut small = WsCodec::client().with_max_message(16);
+        let mut wire = BytesMut::new();
+        encode_frame(&mut wire, opcode::BINARY, &[0u8; 17], None);
+        small.extend(&wire);
+        assert!(small.decode().is_err());
+
+        let mut closing = WsCodec::client();
+        closing.extend(&WsCodec::server().close(close_code::NORMAL));
+        assert_eq!(
+            closing.decode().unwrap(),
+            Some(Message::Close(Some(close_code::NORMAL)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_message_answers_ping() {
+        let (mut client_io, mut server_io) = tokio::io::duplex(4096);
+        let mut client = WsCodec::client();
+        let mut server = WsCodec::server();
+
+        client_io.write_all(&client.ping(b"hb")).await.unwrap();
+        write_message(&mut client_io, &mut client, b"packet")
+            .await
+            .unwrap();
+        assert_eq!(
+            &read_message(&mut server_io, &mut server).await.unwrap()[..],
+            b"packet"
+        );
+
+        // Pong пришёл раньше данных сервера и пропущен
+        write_message(&mut server_io, &mut server, b"reply")
+            .await
+            .unwrap();
+        assert_eq!(
+            &read_message(&mut client_io, &mut client).await.unwrap()[..],
+            b"reply"
+        );
+
+        client_io
+            .write_all(&client.close(close_code::NORMAL))
+            .await
+            .unwrap();
+        assert!(read_message(&mut server_io, &mut server).await.is_err());
+        assert!(read_message(&mut client_io, &mut client).await.is_err());
+    }
+}
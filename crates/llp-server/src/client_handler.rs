@@ -5,12 +5,21 @@
 //! - Дешифровку ChaCha20-Poly1305
 //! - Извлечение IP пакетов
 //! - Маршрутизацию через NAT gateway
-//! - Отправку обратного трафика клиенту
+//! - Отправку обратного трафика клиенту (пачками по паттерну профиля)
+//! - Cover traffic в простое по кривой битрейта профиля
 
 use bytes::Bytes;
+use llp_core::alert::Alert;
+use llp_core::cover;
 use llp_core::crypto::{AeadCipher, SessionKey, CHACHA20_NONCE_SIZE, POLY1305_TAG_SIZE};
+use llp_core::session::ReplayWindow;
+use llp_core::LlpError;
+use llp_mimicry::cover::CoverTraffic;
+use llp_mimicry::datagram::DatagramDisguise;
+use llp_mimicry::shaper::TrafficShaper;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tokio::net::UdpSocket;
 use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, error, info, warn};
@@ -20,6 +29,9 @@ use crate::nat::NatGateway;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
+/// Маскировка датаграмм клиента, общая для listener и задачи отправки
+pub type SharedDisguise = Arc<Mutex<Box<dyn DatagramDisguise>>>;
+
 /// Обработчик клиента VPN (UDP версия)
 pub struct ClientHandler {
     session_id: u64,
@@ -28,6 +40,12 @@ pub struct ClientHandler {
     session_key: SessionKey,
     nat_gateway: Option<Arc<RwLock<NatGateway>>>,
     client_registry: Arc<ClientRegistry>,
+    /// Маскировка датаграмм (None — датаграммы без изменений)
+    disguise: Option<SharedDisguise>,
+    /// Планировщик отправки (None — пакеты уходят сразу)
+    shaper: Option<TrafficShaper>,
+    /// Cover traffic в простое (None — выключен)
+    cover: Option<CoverTraffic>,
     send_counter: u64,
     receive_counter: u64,
     /// VPN IP адрес клиента
@@ -43,6 +61,7 @@ impl ClientHandler {
         session_key: SessionKey,
         nat_gateway: Option<Arc<RwLock<NatGateway>>>,
         client_registry: Arc<ClientRegistry>,
+        disguise: Option<SharedDisguise>,
     ) -> Self {
         // Назначаем VPN IP на основе session_id
         let vpn_ip = IpAddr::V4(Ipv4Addr::new(
@@ -60,12 +79,27 @@ impl ClientHandler {
             session_key,
             nat_gateway,
             client_registry,
+            disguise,
+            shaper: None,
+            cover: None,
             send_counter: 0,
             receive_counter: 0,
             vpn_ip,
         }
     }
 
+    /// Отправлять пакеты клиенту через планировщик по паттерну профиля
+    pub fn with_shaper(mut self, shaper: Option<TrafficShaper>) -> Self {
+        self.shaper = shaper;
+        self
+    }
+
+    /// Отправлять клиенту фиктивные пакеты, пока туннель простаивает
+    pub fn with_cover(mut self, cover: Option<CoverTraffic>) -> Self {
+        self.cover = cover;
+        self
+    }
+
     /// Запустить обработку клиента (основной цикл)
     pub async fn run(self) -> Result<()> {
         info!(
@@ -97,50 +131,87 @@ impl ClientHandler {
         let peer_addr = self.peer_addr;
         let session_key_clone = self.session_key.clone();
         let session_id = self.session_id;
+        let disguise = self.disguise.clone();
+        let mut shaper = self.shaper;
+        let mut cover = self.cover;
 
         let send_task = tokio::spawn(async move {
             let mut send_counter = 0u64;
 
-            while let Some(ip_packet) = rx.recv().await {
-                // Создаём новый шифратор для каждого пакета
-                let mut encrypt_cipher = AeadCipher::new(&session_key_clone, session_id);
-
-                // Пропускаем счётчик до текущего
-                for _ in 0..send_counter {
-                    let _ = encrypt_cipher.encrypt(&[], &[]);
+            'send: loop {
+                // Пакеты, время отправки которых наступило
+                let mut ready = Vec::new();
+                tokio::select! {
+                    packet = rx.recv() => {
+                        let Some(packet) = packet else { break };
+                        if let Some(cover) = cover.as_mut() {
+                            cover.record_activity(Instant::now());
+                        }
+                        ready.extend(Self::schedule(shaper.as_mut(), packet));
+                    }
+                    _ = async {
+                        match &shaper {
+                            Some(shaper) => shaper.wait().await,
+                            None => std::future::pending().await,
+                        }
+                    } => {
+                        if let Some(shaper) = shaper.as_mut() {
+                            ready.extend(std::iter::from_fn(|| shaper.poll(Instant::now())));
+                        }
+                    }
+                    // Простой: фиктивные пакеты по кривой битрейта профиля
+                    _ = async {
+                        match &cover {
+                            Some(cover) => cover.wait().await,
+                            None => std::future::pending().await,
+                        }
+                    } => {
+                        if let Some(cover) = cover.as_mut() {
+                            while let Some(len) = cover.poll(Instant::now()) {
+                                ready.extend(Self::schedule(shaper.as_mut(), cover::payload(len)));
+                            }
+                        }
+                    }
                 }
 
-                // Шифруем IP пакет
-                let ciphertext_with_tag = match encrypt_cipher.encrypt(&ip_packet, &[]) {
-                    Ok(data) => data,
-                    Err(e) => {
-                        error!("Ошибка шифрования пакета для {}: {}", session_id, e);
-                        continue;
+                for ip_packet in ready {
+                    // Формат UDP пакета: [nonce:12][ciphertext+tag]
+                    let udp_packet = match Self::seal_datagram(
+                        &session_key_clone,
+                        session_id,
+                        send_counter,
+                        &ip_packet,
+                    ) {
+                        Ok(data) => data,
+                        Err(e) => {
+                            error!("Ошибка шифрования пакета для {}: {}", session_id, e);
+                            continue;
+                        }
+                    };
+                    let udp_packets =
+                        match Self::wrap_datagram(disguise.as_ref(), Some(udp_packet)) {
+                            Ok(data) => data,
+                            Err(e) => {
+                                error!("Ошибка маскировки пакета для {}: {}", session_id, e);
+                                continue;
+                            }
+                        };
+
+                    // Отправляем пакет (после служебных датаграмм маскировки)
+                    for udp_packet in &udp_packets {
+                        if let Err(e) = socket_clone.send_to(udp_packet, peer_addr).await {
+                            error!("Ошибка отправки UDP пакета клиенту {}: {}", session_id, e);
+                            break 'send;
+                        }
                     }
-                };
-
-                // Строим nonce
-                let mut nonce = [0u8; CHACHA20_NONCE_SIZE];
-                nonce[0..8].copy_from_slice(&send_counter.to_le_bytes());
-                nonce[8..12].copy_from_slice(&((session_id & 0xFFFFFFFF) as u32).to_le_bytes());
-
-                // Формат UDP пакета: [nonce:12][ciphertext+tag]
-                let mut udp_packet = Vec::with_capacity(CHACHA20_NONCE_SIZE + ciphertext_with_tag.len());
-                udp_packet.extend_from_slice(&nonce);
-                udp_packet.extend_from_slice(&ciphertext_with_tag);
-
-                // Отправляем пакет
-                if let Err(e) = socket_clone.send_to(&udp_packet, peer_addr).await {
-                    error!("Ошибка отправки UDP пакета клиенту {}: {}", session_id, e);
-                    break;
-                }
 
-                send_counter += 1;
+                    send_counter += 1;
 
-                debug!(
-                    "Отправлен UDP пакет клиенту {} ({}): {} байт IP данных",
-                    session_id, peer_addr, ip_packet.len()
-                );
+                    debug!(
+                        "Отправлен UDP пакет клиенту {} ({}): {} байт IP данных",
+                        session_id, peer_addr, ip_packet.len()
+                    );
+                }
             }
 
             info!("Задача отправки для клиента {} завершена", session_id);
@@ -166,16 +237,78 @@ impl ClientHandler {
         Ok(())
     }
 
+    /// Поставить пакет в очередь планировщика
+    ///
+    /// Возвращает пакет, если его нужно отправить сразу: планировщика нет
+    /// или пакет идёт в обход очереди.
+    fn schedule(shaper: Option<&mut TrafficShaper>, packet: Bytes) -> Option<Bytes> {
+        match shaper {
+            Some(shaper) => shaper.push(packet, Instant::now()),
+            None => Some(packet),
+        }
+    }
+
+    /// Зашифровать plaintext в UDP датаграмму `[nonce:12][ciphertext+tag]`
+    ///
+    /// Plaintext — IP пакет или управляющее сообщение (alert).
+    pub fn seal_datagram(
+        session_key: &SessionKey,
+        session_id: u64,
+        counter: u64,
+        plaintext: &[u8],
+    ) -> llp_core::Result<Vec<u8>> {
+        let encrypt_cipher = AeadCipher::new(session_key, session_id);
+        let ciphertext_with_tag = encrypt_cipher.encrypt_at(plaintext, &[], counter)?;
+
+        // Строим nonce
+        let mut nonce = [0u8; CHACHA20_NONCE_SIZE];
+        nonce[0..8].copy_from_slice(&counter.to_le_bytes());
+        nonce[8..12].copy_from_slice(&((session_id & 0xFFFFFFFF) as u32).to_le_bytes());
+
+        let mut udp_packet = Vec::with_capacity(CHACHA20_NONCE_SIZE + ciphertext_with_tag.len());
+        udp_packet.extend_from_slice(&nonce);
+        udp_packet.extend_from_slice(&ciphertext_with_tag);
+        Ok(udp_packet)
+    }
+
+    /// Обернуть датаграмму маскировкой клиента, если она включена
+    ///
+    /// Возвращает датаграммы к отправке по порядку: сначала служебные
+    /// датаграммы маскировки, затем обёрнутую `datagram` (без неё — только
+    /// служебные).
+    pub fn wrap_datagram(
+        disguise: Option<&SharedDisguise>,
+        datagram: Option<Vec<u8>>,
+    ) -> Result<Vec<Vec<u8>>> {
+        let Some(disguise) = disguise else {
+            return Ok(datagram.into_iter().collect());
+        };
+        let mut disguise = disguise.lock().unwrap_or_else(|e| e.into_inner());
+        let wrapped = datagram
+            .map(|datagram| disguise.wrap(&datagram))
+            .transpose()?;
+        let mut datagrams: Vec<Vec<u8>> = disguise
+            .take_control()
+            .into_iter()
+            .map(|control| control.to_vec())
+            .collect();
+        datagrams.extend(wrapped.map(|wrapped| wrapped.to_vec()));
+        Ok(datagrams)
+    }
+
     /// Обработка входящего VPN пакета от клиента (вызывается из listener)
+    ///
+    /// Возвращает alert, если клиент прислал управляющее сообщение,
+    /// и ошибку [`LlpError`], если пакет не прошёл аутентификацию.
     pub async fn handle_incoming_packet(
         session_id: u64,
         packet: &[u8],
         decrypt_cipher: &AeadCipher,
         nat_gateway: &Option<Arc<RwLock<NatGateway>>>,
-        receive_counter: &mut u64,
+        replay_window: &mut ReplayWindow,
         client_registry: &Arc<ClientRegistry>,
         vpn_ip: IpAddr,
-    ) -> Result<()> {
+    ) -> Result<Option<Alert>> {
         // Проверяем минимальный размер (nonce + tag)
         if packet.len() < CHACHA20_NONCE_SIZE + POLY1305_TAG_SIZE {
             warn!(
@@ -183,7 +316,7 @@ impl ClientHandler {
                 session_id,
                 packet.len()
             );
-            return Ok(());
+            return Ok(None);
         }
 
         // Извлекаем nonce
@@ -192,15 +325,16 @@ impl ClientHandler {
         // Извлекаем counter из nonce (первые 8 байт, little-endian)
         let nonce_counter = u64::from_le_bytes(nonce[0..8].try_into().unwrap());
 
-        // Проверка порядка пакетов (простая защита от replay)
-        if nonce_counter < *receive_counter {
+        // Replay protection: переупорядоченные пакеты внутри окна допустимы
+        if !replay_window.check(nonce_counter) {
             warn!(
-                "Получен старый пакет от {} (counter: {}, expected: {})",
-                session_id, nonce_counter, receive_counter
+                "Отброшен повторный или устаревший пакет от {} (counter: {}, max: {})",
+                session_id,
+                nonce_counter,
+                replay_window.highest_seq()
             );
-            return Ok(());
+            return Ok(None);
         }
-        *receive_counter = nonce_counter + 1;
 
         // Ciphertext + tag
         let ciphertext_with_tag = &packet[CHACHA20_NONCE_SIZE..];
@@ -209,11 +343,14 @@ impl ClientHandler {
         let plaintext = match decrypt_cipher.decrypt(ciphertext_with_tag, &[], nonce_counter) {
             Ok(data) => data,
             Err(e) => {
-                error!("Ошибка дешифровки пакета от {}: {}", session_id, e);
-                return Ok(());
+                debug!("Ошибка дешифровки пакета от {}: {}", session_id, e);
+                return Err(Box::new(LlpError::from(e)));
             }
         };
 
+        // Окно сдвигается только после успешной аутентификации пакета
+        replay_window.update(nonce_counter);
+
         debug!(
             "Получен UDP пакет от {}: {} байт (расшифровано: {})",
             session_id,
@@ -221,6 +358,19 @@ impl ClientHandler {
             plaintext.len()
         );
 
+        // Управляющее сообщение от клиента (например, close_notify)
+        if Alert::is_alert(&plaintext) {
+            let alert = Alert::deserialize(&plaintext)?;
+            info!("Получен {} от клиента {}", alert, session_id);
+            return Ok(Some(alert));
+        }
+
+        // Фиктивный пакет cover traffic: аутентифицирован, данных нет
+        if cover::is_cover(&plaintext) {
+            debug!("Отброшен фиктивный пакет от {}: {} байт", session_id, plaintext.len());
+            return Ok(None);
+        }
+
         // TODO: Временное эхо для тестирования - убрать после настройки NAT
         // Просто отправляем полученный IP пакет обратно клиенту
         debug!("ECHO TEST: Отправка пакета обратно клиенту {} (эхо-тест)", vpn_ip);
@@ -246,7 +396,7 @@ impl ClientHandler {
             }
         }
 
-        Ok(())
+        Ok(None)
     }
 }
 
@@ -266,4 +416,37 @@ mod tests {
         let version = (ipv6_packet[0] >> 4) & 0x0F;
         assert_eq!(version, 6);
     }
+
+    #[test]
+    fn test_seal_datagram_matches_vectors() {
+        use llp_core::vectors::{VectorSet, VectorType};
+
+        let set = VectorSet::published().unwrap();
+        let vectors: Vec<_> = set
+            .vectors
+            .iter()
+            .filter(|v| v.vector_type == VectorType::DatagramSeal)
+            .collect();
+        assert!(!vectors.is_empty());
+
+        for vector in vectors {
+            let hex_field = |name: &str| hex::decode(vector.input[name].as_str().unwrap()).unwrap();
+            let key: [u8; 32] = hex_field("session_key").try_into().unwrap();
+
+            let datagram = ClientHandler::seal_datagram(
+                &SessionKey::from_bytes(&key),
+                vector.input["session_id"].as_u64().unwrap(),
+                vector.input["counter"].as_u64().unwrap(),
+                &hex_field("plaintext"),
+            )
+            .unwrap();
+
+            assert_eq!(
+                hex::encode(datagram),
+                vector.expected["datagram"].as_str().unwrap(),
+                "{}",
+                vector.name
+            );
+        }
+    }
 }
@@ -47,6 +47,18 @@ impl ClientRegistry {
         debug!("Клиент удалён из реестра: VPN IP = {}", vpn_ip);
     }
 
+    /// Поставить пакет в очередь отправки клиента с указанным VPN IP
+    ///
+    /// Возвращает `false`, если клиент не зарегистрирован или его
+    /// задача отправки уже завершилась.
+    pub async fn send_to(&self, vpn_ip: IpAddr, packet: Bytes) -> bool {
+        let clients = self.clients.read().await;
+        match clients.get(&vpn_ip) {
+            Some(tx) => tx.send(packet).is_ok(),
+            None => false,
+        }
+    }
+
     /// Отправить IP пакет клиенту по назначению
     pub async fn route_to_client(&self, packet: &[u8]) -> Result<()> {
         // Извлекаем destination IP из пакета
@@ -157,4 +169,21 @@ mod tests {
         registry.unregister_client(vpn_ip).await;
         assert_eq!(registry.active_count().await, 0);
     }
+
+    #[tokio::test]
+    async fn test_send_to_drains_after_unregister() {
+        let registry = ClientRegistry::new();
+        let vpn_ip = IpAddr::V4(std::net::Ipv4Addr::new(10, 8, 0, 3));
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        registry.register_client(vpn_ip, tx).await.unwrap();
+
+        assert!(registry.send_to(vpn_ip, Bytes::from_static(b"alert")).await);
+        registry.unregister_client(vpn_ip).await;
+        assert!(!registry.send_to(vpn_ip, Bytes::from_static(b"late")).await);
+
+        // Поставленный в очередь пакет доставляется до закрытия канала
+        assert_eq!(rx.recv().await.unwrap(), Bytes::from_static(b"alert"));
+        assert!(rx.recv().await.is_none());
+    }
 }
@@ -2,11 +2,23 @@
 //!
 //! Этот модуль отвечает за загрузку и валидацию конфигурации сервера.
 
+use llp_core::access::AccessKey;
 use llp_core::packet::MimicryProfile;
+use llp_mimicry::datagram::DatagramMode;
+use llp_mimicry::dns::tunnel::query_capacity;
+use llp_mimicry::aggregate::AggregationConfig;
+use llp_mimicry::cover::{CoverConfig, CoverTraffic};
+use llp_mimicry::shaper::{ShapingConfig, TrafficShaper};
+use llp_mimicry::template::TemplateDir;
+use llp_mimicry::{PacketWrapper, Role};
+use llp_core::session::{
+    DEFAULT_REPLAY_WINDOW_SIZE, MAX_REPLAY_WINDOW_SIZE, MIN_REPLAY_WINDOW_SIZE,
+};
 use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::path::Path;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Конфигурация сервера LLP
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +32,34 @@ pub struct ServerConfig {
     /// Настройки безопасности
     pub security: SecurityConfig,
 
+    /// Внешний TLS слой
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// Отдача неаутентифицированных подключений настоящему сайту
+    #[serde(default)]
+    pub fallback: FallbackConfig,
+
+    /// WebSocket транспорт
+    #[serde(default)]
+    pub websocket: WebSocketConfig,
+
+    /// DNS туннель
+    #[serde(default)]
+    pub dns: DnsConfig,
+
+    /// Отправка клиенту по паттерну профиля мимикрии
+    #[serde(default)]
+    pub shaping: ShapingConfig,
+
+    /// Cover traffic в простое (UDP датаграммы сессии)
+    #[serde(default)]
+    pub cover: CoverConfig,
+
+    /// Агрегация пакетов в сообщения размера профиля
+    #[serde(default)]
+    pub aggregation: AggregationConfig,
+
     /// Настройки логирования
     pub logging: LoggingConfig,
 }
@@ -42,6 +82,14 @@ pub struct NetworkConfig {
     /// Таймаут для установления соединения (секунды)
     #[serde(default = "default_connection_timeout")]
     pub connection_timeout_secs: u64,
+
+    /// Маскировка UDP датаграмм: `none`, `quic` или `webrtc`
+    #[serde(default = "default_udp_disguise")]
+    pub udp_disguise: String,
+
+    /// Отвечать TCP клиентам долгим chunked ответом (если профиль умеет)
+    #[serde(default)]
+    pub stream_responses: bool,
 }
 
 /// Настройки VPN
@@ -86,6 +134,112 @@ pub struct SecurityConfig {
     /// Максимальный drift времени (секунды)
     #[serde(default = "default_max_timestamp_drift")]
     pub max_timestamp_drift_secs: u64,
+
+    /// Размер окна replay protection (количество пакетов)
+    #[serde(default = "default_replay_window_size")]
+    pub replay_window_size: usize,
+
+    /// Каталог TOML шаблонов профилей мимикрии (`*.toml`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile_templates_dir: Option<PathBuf>,
+
+    /// Интервал проверки изменений шаблонов (секунды, 0 — без перезагрузки)
+    #[serde(default = "default_profile_reload_interval")]
+    pub profile_reload_interval_secs: u64,
+
+    /// Ключ доступа (hex, 32 байта): TCP подключение начинается с токена,
+    /// подписанного этим ключом (см. `llp_core::access`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_key: Option<String>,
+}
+
+/// Внешний TLS 1.3 слой
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// Включить TLS поверх TCP (должно совпадать с клиентом)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// PEM цепочка сертификатов, предъявляемая клиентам
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert_file: Option<PathBuf>,
+
+    /// PEM приватный ключ сертификата
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<PathBuf>,
+
+    /// Принимать HTTP/2 (ALPN `h2`) от клиентов, которые его предлагают
+    #[serde(default)]
+    pub http2: bool,
+}
+
+/// Отдача неаутентифицированных TCP подключений upstream сайту
+///
+/// Подключение без верного токена доступа в первых байтах (зонд цензора,
+/// обычный браузер) прозрачно проксируется на `upstream`, так что снаружи
+/// сервер выглядит как этот сайт. Без `upstream` сервер отвечает сам
+/// встроенным сайтом-приманкой в стиле nginx (см. [`crate::decoy`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackConfig {
+    /// Включить отдачу чужих подключений (требует `security.access_key`)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Адрес настоящего веб-сервера (`host:port`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<String>,
+
+    /// Каталог статических файлов сайта-приманки (без `upstream`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_dir: Option<PathBuf>,
+
+    /// Сколько ждать токен доступа от нового подключения (миллисекунды)
+    #[serde(default = "default_auth_timeout")]
+    pub auth_timeout_ms: u64,
+}
+
+/// WebSocket транспорт для работы за CDN и обратными прокси
+///
+/// Клиент открывает соединение запросом HTTP Upgrade на `path`, токен
+/// доступа передаётся в `Sec-WebSocket-Protocol`. Запросы на другие пути
+/// и без верного токена отдаются так же, как в [`FallbackConfig`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketConfig {
+    /// Принимать клиентов по WebSocket (должно совпадать с клиентом)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Путь запроса Upgrade
+    #[serde(default = "default_websocket_path")]
+    pub path: String,
+}
+
+/// DNS туннель для сетей, где проходит только DNS
+///
+/// Сервер отвечает как авторитетный DNS сервер зоны `domain`: NS запись
+/// зоны должна указывать на `bind`. Сессии туннеля обслуживаются как TCP
+/// подключения (токен доступа, WebSocket), но без слоя TLS.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsConfig {
+    /// Принимать клиентов через DNS туннель
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Зона туннеля (например, `t.example.com`)
+    #[serde(default)]
+    pub domain: String,
+
+    /// Адрес авторитетного DNS сервера
+    #[serde(default = "default_dns_bind")]
+    pub bind: SocketAddr,
+
+    /// Максимальное количество одновременных сессий туннеля
+    #[serde(default = "default_dns_max_sessions")]
+    pub max_sessions: usize,
+
+    /// Через сколько секунд без запросов сессия туннеля закрывается
+    #[serde(default = "default_dns_idle_timeout")]
+    pub idle_timeout_secs: u64,
 }
 
 /// Настройки логирования
@@ -121,6 +275,10 @@ fn default_connection_timeout() -> u64 {
     30
 }
 
+fn default_udp_disguise() -> String {
+    DatagramMode::None.name().to_string()
+}
+
 fn default_vpn_subnet() -> String {
     "10.8.0.0/24".to_string()
 }
@@ -160,6 +318,34 @@ fn default_max_timestamp_drift() -> u64 {
     5 * 60 // 5 минут
 }
 
+fn default_replay_window_size() -> usize {
+    DEFAULT_REPLAY_WINDOW_SIZE
+}
+
+fn default_profile_reload_interval() -> u64 {
+    5
+}
+
+fn default_auth_timeout() -> u64 {
+    2000
+}
+
+fn default_websocket_path() -> String {
+    "/ws".to_string()
+}
+
+fn default_dns_bind() -> SocketAddr {
+    SocketAddr::from(([0, 0, 0, 0], 53))
+}
+
+fn default_dns_max_sessions() -> usize {
+    64
+}
+
+fn default_dns_idle_timeout() -> u64 {
+    60
+}
+
 fn default_log_level() -> String {
     "info".to_string()
 }
@@ -178,6 +364,13 @@ impl Default for ServerConfig {
             network: NetworkConfig::default(),
             vpn: VpnConfig::default(),
             security: SecurityConfig::default(),
+            tls: TlsConfig::default(),
+            fallback: FallbackConfig::default(),
+            websocket: WebSocketConfig::default(),
+            dns: DnsConfig::default(),
+            shaping: ShapingConfig::default(),
+            cover: CoverConfig::default(),
+            aggregation: AggregationConfig::default(),
             logging: LoggingConfig::default(),
         }
     }
@@ -190,6 +383,8 @@ impl Default for NetworkConfig {
             port: default_bind_port(),
             max_connections: default_max_connections(),
             connection_timeout_secs: default_connection_timeout(),
+            udp_disguise: default_udp_disguise(),
+            stream_responses: false,
         }
     }
 }
@@ -213,6 +408,42 @@ impl Default for SecurityConfig {
             keepalive_interval_secs: default_keepalive_interval(),
             keepalive_timeout_secs: default_keepalive_timeout(),
             max_timestamp_drift_secs: default_max_timestamp_drift(),
+            replay_window_size: default_replay_window_size(),
+            profile_templates_dir: None,
+            profile_reload_interval_secs: default_profile_reload_interval(),
+            access_key: None,
+        }
+    }
+}
+
+impl Default for FallbackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            upstream: None,
+            site_dir: None,
+            auth_timeout_ms: default_auth_timeout(),
+        }
+    }
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_websocket_path(),
+        }
+    }
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            domain: String::new(),
+            bind: default_dns_bind(),
+            max_sessions: default_dns_max_sessions(),
+            idle_timeout_secs: default_dns_idle_timeout(),
         }
     }
 }
@@ -255,14 +486,72 @@ impl ServerConfig {
             anyhow::bail!("max_connections должен быть > 0");
         }
 
-        // Проверка профиля мимикрии
-        self.parse_mimicry_profile()?;
+        // Проверка профиля мимикрии. Имя из каталога шаблонов известно
+        // только после их загрузки, поэтому тогда его проверяет main
+        if self.security.profile_templates_dir.is_none() {
+            self.parse_mimicry_profile()?;
+        }
+
+        // Проверка маскировки UDP
+        self.udp_disguise()?;
 
         // Проверка MTU
         if self.vpn.mtu < 576 || self.vpn.mtu > 9000 {
             anyhow::bail!("MTU должен быть в диапазоне 576-9000");
         }
 
+        // Проверка окна replay protection
+        if self.security.replay_window_size < MIN_REPLAY_WINDOW_SIZE
+            || self.security.replay_window_size > MAX_REPLAY_WINDOW_SIZE
+        {
+            anyhow::bail!(
+                "replay_window_size должен быть в диапазоне {}-{}",
+                MIN_REPLAY_WINDOW_SIZE,
+                MAX_REPLAY_WINDOW_SIZE
+            );
+        }
+
+        // Проверка TLS: сертификат и ключ должны загружаться
+        self.tls_server_config()?;
+        if self.tls.http2 && !self.tls.enabled {
+            anyhow::bail!("tls.http2 требует tls.enabled = true");
+        }
+
+        // Проверка ключа доступа и fallback
+        let access_key = self.access_key()?;
+        if self.fallback.enabled {
+            if access_key.is_none() {
+                anyhow::bail!("fallback.enabled требует security.access_key");
+            }
+            if let Some(dir) = &self.fallback.site_dir {
+                if !dir.is_dir() {
+                    anyhow::bail!("fallback.site_dir не является каталогом: {}", dir.display());
+                }
+            }
+        }
+
+        // Проверка пути WebSocket
+        if self.websocket.enabled && !self.websocket.path.starts_with('/') {
+            anyhow::bail!("websocket.path должен начинаться с '/'");
+        }
+
+        // Проверка зоны DNS туннеля
+        if self.dns.enabled {
+            if self.dns.domain.trim_end_matches('.').is_empty()
+                || query_capacity(self.dns.domain.trim_end_matches('.')) == 0
+            {
+                anyhow::bail!("dns.domain должен быть задан и оставлять место для данных");
+            }
+            if self.dns.max_sessions == 0 {
+                anyhow::bail!("dns.max_sessions должен быть > 0");
+            }
+        }
+
+        // Планировщик отправки, cover traffic и агрегация
+        self.shaping.validate()?;
+        self.cover.validate(self.vpn.mtu as usize)?;
+        self.aggregation.validate()?;
+
         Ok(())
     }
 
@@ -271,15 +560,104 @@ impl ServerConfig {
         SocketAddr::new(self.network.bind_ip, self.network.port)
     }
 
+    /// Загрузить шаблоны профилей мимикрии в глобальный реестр
+    ///
+    /// Возвращает каталог для перезагрузки на лету, если он задан.
+    pub fn load_profile_templates(&self) -> Result<Option<TemplateDir>, anyhow::Error> {
+        match &self.security.profile_templates_dir {
+            Some(dir) => Ok(Some(llp_mimicry::template::load_global(dir)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Интервал перезагрузки шаблонов профилей (None — отключена)
+    pub fn profile_reload_interval(&self) -> Option<Duration> {
+        match self.security.profile_reload_interval_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// Конфигурация TLS сервера (None — TLS выключен)
+    ///
+    /// С `tls.http2` сервер предпочитает ALPN `h2`, остальные клиенты
+    /// получают `http/1.1`.
+    pub fn tls_server_config(&self) -> Result<Option<Arc<rustls::ServerConfig>>, anyhow::Error> {
+        if !self.tls.enabled {
+            return Ok(None);
+        }
+        let (Some(cert_file), Some(key_file)) = (&self.tls.cert_file, &self.tls.key_file) else {
+            anyhow::bail!("tls.cert_file и tls.key_file обязательны при tls.enabled = true");
+        };
+        let certs = llp_mimicry::tls::load_certs(cert_file)?;
+        let key = llp_mimicry::tls::load_private_key(key_file)?;
+        let mut alpn = vec![llp_mimicry::tls::DEFAULT_ALPN.to_vec()];
+        if self.tls.http2 {
+            alpn.insert(0, llp_mimicry::h2::ALPN_H2.to_vec());
+        }
+        Ok(Some(llp_mimicry::tls::server_config(certs, key, alpn)?))
+    }
+
+    /// Ключ доступа (None — подключения не аутентифицируются токеном)
+    pub fn access_key(&self) -> Result<Option<AccessKey>, anyhow::Error> {
+        match &self.security.access_key {
+            Some(value) => AccessKey::from_hex(value)
+                .map(Some)
+                .ok_or_else(|| anyhow::anyhow!("access_key должен быть hex строкой из 32 байт")),
+            None => Ok(None),
+        }
+    }
+
+    /// Таймаут ожидания токена доступа
+    pub fn auth_timeout(&self) -> Duration {
+        Duration::from_millis(self.fallback.auth_timeout_ms)
+    }
+
     /// Парсинг профиля мимикрии из строки
+    ///
+    /// Имя ищется в глобальном реестре профилей, поэтому доступны и
+    /// зарегистрированные пользовательские профили.
     pub fn parse_mimicry_profile(&self) -> Result<MimicryProfile, anyhow::Error> {
-        match self.security.default_mimicry_profile.as_str() {
-            "none" => Ok(MimicryProfile::None),
-            "vk_video" => Ok(MimicryProfile::VkVideo),
-            "yandex_music" => Ok(MimicryProfile::YandexMusic),
-            "rutube" => Ok(MimicryProfile::RuTube),
-            unknown => anyhow::bail!("Неизвестный профиль мимикрии: {}", unknown),
+        let name = self.security.default_mimicry_profile.as_str();
+        llp_mimicry::registry::profile_by_name(name)
+            .ok_or_else(|| anyhow::anyhow!("Неизвестный профиль мимикрии: {}", name))
+    }
+
+    /// Режим маскировки UDP датаграмм
+    pub fn udp_disguise(&self) -> Result<DatagramMode, anyhow::Error> {
+        let name = self.network.udp_disguise.as_str();
+        DatagramMode::from_name(name)
+            .ok_or_else(|| anyhow::anyhow!("Неизвестная маскировка UDP: {}", name))
+    }
+
+    /// Планировщик отправки клиенту с профилем `profile`
+    ///
+    /// `None` — планирование выключено или профиль не задаёт паттерн.
+    pub fn traffic_shaper(
+        &self,
+        profile: MimicryProfile,
+    ) -> Result<Option<TrafficShaper>, anyhow::Error> {
+        if !self.shaping.enabled {
+            return Ok(None);
+        }
+        let timing = PacketWrapper::try_new(profile)?.timing_profile();
+        Ok(timing.map(|timing| TrafficShaper::new(timing, self.shaping.options())))
+    }
+
+    /// Генератор cover traffic для клиента с профилем `profile`
+    ///
+    /// `None` — cover traffic выключен или профиль не задаёт кривую битрейта.
+    pub fn cover_traffic(
+        &self,
+        profile: MimicryProfile,
+    ) -> Result<Option<CoverTraffic>, anyhow::Error> {
+        if !self.cover.enabled {
+            return Ok(None);
         }
+        let curve = PacketWrapper::try_new(profile)?.bitrate_curve();
+        Ok(curve.map(|curve| {
+            CoverTraffic::new(curve, self.cover.options(), Role::Server, Instant::now())
+        }))
     }
 
     /// Получить таймаут подключения
@@ -337,6 +715,18 @@ mod tests {
         assert!(config.parse_mimicry_profile().is_err());
     }
 
+    #[test]
+    fn test_template_profile_checked_after_loading() {
+        let mut config = ServerConfig::default();
+        config.security.default_mimicry_profile = "custom_stream".to_string();
+        assert!(config.validate().is_err());
+
+        // Шаблоны загружает main, валидация реестр не трогает
+        config.security.profile_templates_dir = Some(PathBuf::from("profiles"));
+        assert!(config.validate().is_ok());
+        assert!(llp_mimicry::registry::profile_by_name("custom_stream").is_none());
+    }
+
     #[test]
     fn test_bind_address() {
         let config = ServerConfig::default();
@@ -364,6 +754,106 @@ mod tests {
         // Невалидный MTU
         config.vpn.mtu = 100;
         assert!(config.validate().is_err());
+        config.vpn.mtu = 1420;
+
+        // Неизвестная маскировка UDP
+        config.network.udp_disguise = "dtls".to_string();
+        assert!(config.validate().is_err());
+        config.network.udp_disguise = "quic".to_string();
+        assert_eq!(config.udp_disguise().unwrap(), DatagramMode::Quic);
+        config.network.udp_disguise = "webrtc".to_string();
+        assert_eq!(config.udp_disguise().unwrap(), DatagramMode::WebRtc);
+
+        // Невалидный размер окна replay protection
+        config.security.replay_window_size = 8;
+        assert!(config.validate().is_err());
+        config.security.replay_window_size = DEFAULT_REPLAY_WINDOW_SIZE;
+
+        // HTTP/2 без TLS
+        config.tls.http2 = true;
+        assert!(config.validate().is_err());
+
+        // TLS без сертификата
+        config.tls.enabled = true;
+        assert!(config.validate().is_err());
+        config.tls.cert_file = Some(PathBuf::from("/nonexistent/cert.pem"));
+        config.tls.key_file = Some(PathBuf::from("/nonexistent/key.pem"));
+        assert!(config.validate().is_err());
+        config.tls = TlsConfig::default();
+
+        // Fallback без ключа доступа
+        config.fallback.enabled = true;
+        assert!(config.validate().is_err());
+        config.security.access_key = Some("00".repeat(32));
+        assert!(config.validate().is_ok());
+        config.fallback.site_dir = Some(PathBuf::from("/nonexistent/site"));
+        assert!(config.validate().is_err());
+        config.fallback.site_dir = None;
+        config.fallback.upstream = Some("127.0.0.1:8080".to_string());
+        assert!(config.validate().is_ok());
+        config.security.access_key = Some("not hex".to_string());
+        assert!(config.validate().is_err());
+        config.security.access_key = None;
+        config.fallback = FallbackConfig::default();
+
+        // Путь WebSocket без ведущего '/'
+        config.websocket.enabled = true;
+        config.websocket.path = "ws".to_string();
+        assert!(config.validate().is_err());
+        config.websocket.path = "/ws".to_string();
+
+        // DNS туннель без зоны
+        config.dns.enabled = true;
+        assert!(config.validate().is_err());
+        config.dns.domain = "t.example.com".to_string();
+        assert!(config.validate().is_ok());
+        config.dns.max_sessions = 0;
+        assert!(config.validate().is_err());
+        config.dns = DnsConfig::default();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_traffic_shaper() {
+        let mut config = ServerConfig::default();
+        config.shaping.max_latency_ms = 20;
+        let shaper = config
+            .traffic_shaper(MimicryProfile::VkVideo)
+            .unwrap()
+            .unwrap();
+        assert_eq!(shaper.options().max_latency, Duration::from_millis(20));
+
+        // Без паттерна профиля и при выключенном планировщике пакеты идут сразу
+        assert!(config
+            .traffic_shaper(MimicryProfile::None)
+            .unwrap()
+            .is_none());
+        config.shaping.enabled = false;
+        assert!(config
+            .traffic_shaper(MimicryProfile::VkVideo)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_cover_traffic() {
+        let mut config = ServerConfig::default();
+        assert!(config
+            .cover_traffic(MimicryProfile::VkVideo)
+            .unwrap()
+            .is_none());
+
+        config.cover.enabled = true;
+        config.cover.max_kbps = 256;
+        let cover = config
+            .cover_traffic(MimicryProfile::VkVideo)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cover.options().max_rate, 256_000);
+        assert!(config
+            .cover_traffic(MimicryProfile::None)
+            .unwrap()
+            .is_none());
     }
 
     #[test]
@@ -0,0 +1,507 @@
+//! Встроенный сайт-приманка для неаутентифицированных подключений
+//!
+//! Облегчённая альтернатива проксированию на upstream: сервер сам отвечает
+//! на HTTP запросы без токена доступа так, как ответил бы nginx, который
+//! стоит за профилем мимикрии. Заголовок `Server` берётся из профиля
+//! ([`llp_mimicry::Profile::server_header`]), поэтому зонд видит тот же
+//! сервер, что и в трафике клиентов. Если задан каталог сайта, из него
+//! отдаются статические файлы; иначе на любой запрос возвращаются
+//! стандартные страницы ошибок nginx.
+
+use chrono::{DateTime, Utc};
+use llp_core::clock::SharedClock;
+use llp_core::packet::MimicryProfile;
+use llp_mimicry::PacketWrapper;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Заголовок `Server`, если профиль его не задаёт
+const DEFAULT_SERVER: &str = "nginx";
+
+/// Максимальный размер заголовка запроса (как `large_client_header_buffers` nginx)
+const MAX_HEAD_SIZE: usize = 8 * 1024;
+
+/// Время ожидания следующего запроса (как `keepalive_timeout` nginx)
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(75);
+
+/// Разобранный заголовок HTTP запроса
+#[derive(Debug, PartialEq, Eq)]
+struct Request {
+    method: String,
+    target: String,
+    keep_alive: bool,
+    content_length: usize,
+}
+
+/// Ответ сайта
+#[derive(Debug)]
+struct Response {
+    /// Сериализованный ответ
+    bytes: Vec<u8>,
+    /// Оставить соединение открытым
+    keep_alive: bool,
+}
+
+/// Сайт-приманка
+pub struct DecoySite {
+    /// Каталог статических файлов (None — только страницы ошибок)
+    root: Option<PathBuf>,
+    /// Значение заголовка `Server`
+    server: String,
+    /// Часы для заголовка `Date`
+    clock: SharedClock,
+}
+
+impl DecoySite {
+    /// Создать сайт с заданным заголовком `Server`
+    pub fn new(server: impl Into<String>, root: Option<PathBuf>, clock: SharedClock) -> Self {
+        Self {
+            root,
+            server: server.into(),
+            clock,
+        }
+    }
+
+    /// Создать сайт, совпадающий с ответами профиля мимикрии
+    pub fn for_profile(profile: MimicryProfile, root: Option<PathBuf>, clock: SharedClock) -> Self {
+        let server = PacketWrapper::try_new(profile)
+            .ok()
+            .and_then(|wrapper| wrapper.server_header().map(str::to_string))
+            .unwrap_or_else(|| DEFAULT_SERVER.to_string());
+        Self::new(server, root, clock)
+    }
+
+    /// Значение заголовка `Server`
+    pub fn server(&self) -> &str {
+        &self.server
+    }
+
+    /// Обслуживать HTTP/1.x соединение, начиная с уже прочитанных байт
+    ///
+    /// Запросы обрабатываются по очереди, пока клиент держит соединение
+    /// открытым; тело запроса читается и отбрасывается.
+    pub async fn serve<S>(&self, stream: &mut S, prefix: &[u8]) -> std::io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut buf = prefix.to_vec();
+        let mut chunk = [0u8; 4096];
+
+        loop {
+            let head_len = loop {
+                if let Some(pos) = find_head_end(&buf) {
+                    break Some(pos);
+                }
+                if buf.len() > MAX_HEAD_SIZE {
+                    break None;
+                }
+                match tokio::time::timeout(KEEPALIVE_TIMEOUT, stream.read(&mut chunk)).await {
+                    Ok(Ok(0)) | Err(_) => return Ok(()),
+                    Ok(Ok(n)) => buf.extend_from_slice(&chunk[..n]),
+                    Ok(Err(e)) => return Err(e),
+                }
+            };
+
+            let request = head_len.and_then(|len| parse_request(&buf[..len]));
+            let Some(request) = request else {
+                let response = self.error(400, "Bad Request", false, true);
+                stream.write_all(&response.bytes).await?;
+                return stream.flush().await;
+            };
+            buf.drain(..head_len.unwrap_or(0));
+
+            // Тело запроса не нужно, но его байты не должны попасть в следующий запрос
+            let mut body = request.content_length;
+            let buffered = body.min(buf.len());
+            buf.drain(..buffered);
+            body -= buffered;
+            while body > 0 {
+                let want = body.min(chunk.len());
+                match tokio::time::timeout(KEEPALIVE_TIMEOUT, stream.read(&mut chunk[..want])).await
+                {
+                    Ok(Ok(0)) | Err(_) => return Ok(()),
+                    Ok(Ok(n)) => body -= n,
+                    Ok(Err(e)) => return Err(e),
+                }
+            }
+
+            let response = self.respond(&request).await;
+            stream.write_all(&response.bytes).await?;
+            stream.flush().await?;
+            if !response.keep_alive {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Ответ на один запрос
+    async fn respond(&self, request: &Request) -> Response {
+        let head_only = request.method == "HEAD";
+        if request.method != "GET" && !head_only {
+            return self.error(405, "Not Allowed", request.keep_alive, false);
+        }
+
+        let Some(path) = self.resolve(&request.target).await else {
+            return self.error(404, "Not Found", request.keep_alive, head_only);
+        };
+        let is_dir = tokio::fs::metadata(&path)
+            .await
+            .is_ok_and(|meta| meta.is_dir());
+        let path = if is_dir {
+            path.join("index.html")
+        } else {
+            path
+        };
+
+        let (meta, body) = match tokio::fs::metadata(&path).await {
+            Ok(meta) if meta.is_file() => match tokio::fs::read(&path).await {
+                Ok(body) => (meta, body),
+                Err(_) => return self.error(403, "Forbidden", request.keep_alive, head_only),
+            },
+            // Каталог без index.html: nginx без autoindex отвечает 403
+            _ if is_dir => return self.error(403, "Forbidden", request.keep_alive, head_only),
+            _ => return self.error(404, "Not Found", request.keep_alive, head_only),
+        };
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default()
+            .as_secs();
+
+        let head = format!(
+            "HTTP/1.1 200 OK\r\n\
+             Server: {}\r\n\
+             Date: {}\r\n\
+             Content-Type: {}\r\n\
+             Content-Length: {}\r\n\
+             Last-Modified: {}\r\n\
+             Connection: {}\r\n\
+             ETag: \"{:x}-{:x}\"\r\n\
+             Accept-Ranges: bytes\r\n\
+             \r\n",
+            self.server,
+            http_date(self.clock.unix_secs()),
+            content_type(&path),
+            body.len(),
+            http_date(modified),
+            connection(request.keep_alive),
+            modified,
+            body.len(),
+        );
+
+        let mut bytes = head.into_bytes();
+        if !head_only {
+            bytes.extend_from_slice(&body);
+        }
+        Response {
+            bytes,
+            keep_alive: request.keep_alive,
+        }
+    }
+
+    /// Путь файла для цели запроса внутри каталога сайта
+    ///
+    /// Выход за пределы каталога (`..`, символические ссылки наружу)
+    /// считается отсутствующим файлом.
+    async fn resolve(&self, target: &str) -> Option<PathBuf> {
+        let root = self.root.as_ref()?;
+        let path = target.split(['?', '#']).next().unwrap_or_default();
+        if !path.starts_with('/') {
+            return None;
+        }
+        let decoded = percent_decode(path)?;
+
+        let mut relative = PathBuf::new();
+        for component in Path::new(decoded.trim_start_matches('/')).components() {
+            match component {
+                Component::Normal(part) => relative.push(part),
+                Component::CurDir => {}
+                _ => return None,
+            }
+        }
+
+        let root = tokio::fs::canonicalize(root).await.ok()?;
+        let full = tokio::fs::canonicalize(root.join(relative)).await.ok()?;
+        full.starts_with(&root).then_some(full)
+    }
+
+    /// Страница ошибки в формате nginx
+    fn error(&self, status: u16, reason: &str, keep_alive: bool, head_only: bool) -> Response {
+        let body = format!(
+            "<html>\r\n\
+             <head><title>{status} {reason}</title></head>\r\n\
+             <body>\r\n\
+             <center><h1>{status} {reason}</h1></center>\r\n\
+             <hr><center>{}</center>\r\n\
+             </body>\r\n\
+             </html>\r\n",
+            self.server
+        );
+        let head = format!(
+            "HTTP/1.1 {status} {reason}\r\n\
+             Server: {}\r\n\
+             Date: {}\r\n\
+             Content-Type: text/html\r\n\
+             Content-Length: {}\r\n\
+             Connection: {}\r\n\
+             \r\n",
+            self.server,
+            http_date(self.clock.unix_secs()),
+            body.len(),
+            connection(keep_alive),
+        );
+
+        let mut bytes = head.into_bytes();
+        if !head_only {
+            bytes.extend_from_slice(body.as_bytes());
+        }
+        Response { bytes, keep_alive }
+    }
+}
+
+/// Длина заголовка запроса вместе с завершающей пустой строкой
+fn find_head_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map(|pos| pos + 4)
+}
+
+/// Разобрать заголовок HTTP/1.x запроса
+fn parse_request(head: &[u8]) -> Option<Request> {
+    let head = std::str::from_utf8(head).ok()?;
+    let mut lines = head.split("\r\n");
+
+    let mut start = lines.next()?.split(' ');
+    let (method, target, version) = (start.next()?, start.next()?, start.next()?);
+    if start.next().is_some()
+        || method.is_empty()
+        || !method.bytes().all(|b| b.is_ascii_uppercase())
+    {
+        return None;
+    }
+    let mut keep_alive = match version {
+        "HTTP/1.1" => true,
+        "HTTP/1.0" => false,
+        _ => return None,
+    };
+
+    let mut content_length = 0;
+    for line in lines.take_while(|line| !line.is_empty()) {
+        let (name, value) = line.split_once(':')?;
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().ok()?;
+        } else if name.eq_ignore_ascii_case("connection") {
+            if value.eq_ignore_ascii_case("close") {
+                keep_alive = false;
+            } else if value.eq_ignore_ascii_case("keep-alive") {
+                keep_alive = true;
+            }
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            // Тело chunked не пропустить без разбора; такой зонд получит 400
+            return None;
+        }
+    }
+
+    Some(Request {
+        method: method.to_string(),
+        target: target.to_string(),
+        keep_alive,
+        content_length,
+    })
+}
+
+/// Декодировать `%XX` в пути запроса
+fn percent_decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    let decoded = String::from_utf8(out).ok()?;
+    (!decoded.contains('\0') && !decoded.contains('\\')).then_some(decoded)
+}
+
+/// Значение `Content-Type` по расширению файла (как `mime.types` nginx)
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("html" | "htm") => "text/html",
+        Some("css") => "text/css",
+        Some("js") => "application/javascript",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain",
+        Some("xml") => "text/xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        Some("webp") => "image/webp",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("mp4") => "video/mp4",
+        Some("ts") => "video/mp2t",
+        Some("m3u8") => "application/vnd.apple.mpegurl",
+        _ => "application/octet-stream",
+    }
+}
+
+fn connection(keep_alive: bool) -> &'static str {
+    if keep_alive {
+        "keep-alive"
+    } else {
+        "close"
+    }
+}
+
+/// Дата в формате HTTP
+fn http_date(unix_secs: u64) -> String {
+    DateTime::<Utc>::from_timestamp(unix_secs as i64, 0)
+        .unwrap_or_default()
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llp_core::clock::SimulatedClock;
+
+    fn site(root: Option<PathBuf>) -> DecoySite {
+        DecoySite::new(
+            "nginx/1.20.2",
+            root,
+            SimulatedClock::new(1_700_000_000).shared(),
+        )
+    }
+
+    fn site_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("llp-decoy-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+        std::fs::write(dir.join("index.html"), "<h1>Видео</h1>").unwrap();
+        std::fs::write(dir.join("assets/app.css"), "body{}").unwrap();
+        dir
+    }
+
+    async fn exchange(site: &DecoySite, request: &[u8]) -> String {
+        let (mut client, mut server) = tokio::io::duplex(64 * 1024);
+        client.write_all(request).await.unwrap();
+        client.shutdown().await.unwrap();
+        site.serve(&mut server, &[]).await.unwrap();
+        drop(server);
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).await.unwrap();
+        String::from_utf8(response).unwrap()
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let request =
+            parse_request(b"GET /a?b=1 HTTP/1.1\r\nHost: x\r\nContent-Length: 3\r\n\r\n").unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.target, "/a?b=1");
+        assert!(request.keep_alive);
+        assert_eq!(request.content_length, 3);
+
+        assert!(!parse_request(b"GET / HTTP/1.0\r\n\r\n").unwrap().keep_alive);
+        assert!(
+            !parse_request(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+                .unwrap()
+                .keep_alive
+        );
+        assert!(parse_request(b"\x16\x03\x01\x02\x00\r\n\r\n").is_none());
+        assert!(parse_request(b"GET / SSH-2.0\r\n\r\n").is_none());
+        assert!(parse_request(b"GET /\r\n\r\n").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_static_files() {
+        let dir = site_dir("static");
+        let site = site(Some(dir.clone()));
+
+        let index = exchange(&site, b"GET / HTTP/1.0\r\n\r\n").await;
+        assert!(index.starts_with(
+            "HTTP/1.1 200 OK\r\nServer: nginx/1.20.2\r\nDate: Tue, 14 Nov 2023 22:13:20 GMT\r\n"
+        ));
+        assert!(index.contains("Content-Type: text/html\r\n"));
+        assert!(index.contains("ETag: \""));
+        assert!(index.ends_with("\r\n\r\n<h1>Видео</h1>"));
+
+        let css = exchange(&site, b"HEAD /assets/app.css?v=2 HTTP/1.0\r\n\r\n").await;
+        assert!(css.contains("Content-Type: text/css\r\nContent-Length: 6\r\n"));
+        assert!(css.ends_with("\r\n\r\n"));
+
+        let empty = exchange(&site, b"GET /empty/ HTTP/1.0\r\n\r\n").await;
+        assert!(empty.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+
+        for target in [
+            "/missing.html",
+            "/../etc/passwd",
+            "/assets/%2e%2e/%2e%2e/etc/passwd",
+        ] {
+            let request = format!("GET {} HTTP/1.0\r\n\r\n", target);
+            let response = exchange(&site, request.as_bytes()).await;
+            assert!(
+                response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+                "{}",
+                target
+            );
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_nginx_error_pages() {
+        let site = site(None);
+
+        let missing = exchange(&site, b"GET /index.html HTTP/1.0\r\n\r\n").await;
+        assert!(missing.starts_with("HTTP/1.1 404 Not Found\r\nServer: nginx/1.20.2\r\n"));
+        assert!(missing.contains("Connection: close\r\n"));
+        assert!(missing.ends_with("<hr><center>nginx/1.20.2</center>\r\n</body>\r\n</html>\r\n"));
+
+        let post = exchange(&site, b"POST / HTTP/1.0\r\nContent-Length: 4\r\n\r\nbody").await;
+        assert!(post.starts_with("HTTP/1.1 405 Not Allowed\r\n"));
+
+        let garbage = exchange(&site, b"\x16\x03\x01\x00\xa5garbage\r\n\r\n").await;
+        assert!(garbage.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(garbage.contains("Connection: close\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive() {
+        let site = site(None);
+        let response = exchange(
+            &site,
+            b"POST /a HTTP/1.1\r\nHost: x\r\nContent-Length: 2\r\n\r\nhiGET /b HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+        assert_eq!(response.matches("HTTP/1.1 ").count(), 2);
+        assert!(response.starts_with("HTTP/1.1 405 Not Allowed\r\n"));
+        assert!(response.contains("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn test_server_header_from_profile() {
+        let clock = SimulatedClock::new(0).shared();
+        let vk = DecoySite::for_profile(MimicryProfile::VkVideo, None, clock.clone());
+        assert_eq!(vk.server(), "nginx/1.20.2");
+        let rutube = DecoySite::for_profile(MimicryProfile::RuTube, None, clock);
+        assert_eq!(rutube.server(), "nginx/1.21.6");
+    }
+}
@@ -0,0 +1,326 @@
+//! Авторитетный DNS сервер для DNS туннеля
+//!
+//! Этот модуль отвечает за:
+//! - Ответы на запросы к зоне туннеля (`dns.domain`) и отказ (REFUSED)
+//!   для остальных имён
+//! - Сборку потока клиента из имён запросов и отправку потока сервера
+//!   в записях TXT/NULL ([`llp_mimicry::dns::tunnel`])
+//! - Передачу каждой сессии туннеля в [`LlpTcpListener::serve`] как
+//!   обычного TCP подключения без TLS
+//! - Закрытие сессий без запросов дольше `dns.idle_timeout_secs`
+
+use bytes::Bytes;
+use llp_mimicry::dns::tunnel::Upstream;
+use llp_mimicry::dns::{self, rcode, tunnel, RecordType, TunnelSession};
+use llp_mimicry::tls::Transport;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+use crate::config::ServerConfig;
+use crate::tcp_listener::LlpTcpListener;
+
+/// Сколько неподтверждённых данных сервера держит сессия
+const DOWNSTREAM_WINDOW: usize = 64 * 1024;
+
+/// Буфер потока между сессией туннеля и обработчиком подключения
+const STREAM_BUFFER: usize = 64 * 1024;
+
+/// Как часто проверяются сессии без запросов
+const SWEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Результат обработки запроса
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Сессия туннеля
+struct DnsSession {
+    tunnel: Arc<Mutex<TunnelSession>>,
+    /// Данные клиента для обработчика подключения
+    to_server: mpsc::UnboundedSender<Bytes>,
+    last_seen: Instant,
+    task: JoinHandle<()>,
+}
+
+/// DNS listener сервера
+pub struct DnsListener {
+    config: Arc<ServerConfig>,
+    /// Зона туннеля в нижнем регистре без завершающей точки
+    domain: String,
+    socket: UdpSocket,
+    /// Обработчик подключений, которому передаются сессии
+    tcp: Arc<LlpTcpListener>,
+    sessions: Mutex<HashMap<u32, DnsSession>>,
+}
+
+impl DnsListener {
+    /// Создать listener на `dns.bind`
+    pub async fn bind(config: Arc<ServerConfig>, tcp: Arc<LlpTcpListener>) -> Result<Self> {
+        let socket = UdpSocket::bind(config.dns.bind).await?;
+        let domain = config.dns.domain.trim_end_matches('.').to_ascii_lowercase();
+        info!(
+            "DNS туннель: авторитетный сервер зоны {} на {}",
+            domain,
+            socket.local_addr()?
+        );
+        Ok(Self {
+            config,
+            domain,
+            socket,
+            tcp,
+            sessions: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Адрес, на котором принимаются запросы
+    #[allow(dead_code)]
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Запустить listener (основной цикл)
+    pub async fn run(self: Arc<Self>) -> Result<()> {
+        let mut buf = vec![0u8; u16::MAX as usize];
+        let mut sweep = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            tokio::select! {
+                received = self.socket.recv_from(&mut buf) => {
+                    let (len, peer_addr) = received?;
+                    if let Some(response) = self.handle_query(&buf[..len], peer_addr) {
+                        if let Err(e) = self.socket.send_to(&response, peer_addr).await {
+                            debug!("Ошибка отправки DNS ответа {}: {}", peer_addr, e);
+                        }
+                    }
+                }
+                _ = sweep.tick() => self.expire_sessions(),
+            }
+        }
+    }
+
+    /// Ответ на запрос (`None` — не DNS запрос, отбрасывается молча)
+    fn handle_query(&self, message: &[u8], peer_addr: SocketAddr) -> Option<Bytes> {
+        let question = dns::parse_query(message).ok()?;
+        let respond = |code, answer: Option<(RecordType, &[u8])>| {
+            dns::encode_response(&question, code, answer).ok()
+        };
+
+        if !tunnel::in_zone(&question.name, &self.domain) {
+            return respond(rcode::REFUSED, None);
+        }
+        // Другие типы записей в зоне существуют, но пусты (NODATA)
+        let Some(record) = RecordType::from_code(question.qtype) else {
+            return respond(rcode::NO_ERROR, None);
+        };
+        let Ok(upstream) = Upstream::from_name(&question.name, &self.domain) else {
+            return respond(rcode::NX_DOMAIN, None);
+        };
+
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        if !sessions.contains_key(&upstream.session) {
+            // Сессия заводится только первым запросом клиента
+            if upstream.offset != 0
+                || upstream.ack != 0
+                || sessions.len() >= self.config.dns.max_sessions
+            {
+                return respond(rcode::NX_DOMAIN, None);
+            }
+            debug!(
+                "Новая сессия DNS туннеля {:08x} через {}",
+                upstream.session, peer_addr
+            );
+            sessions.insert(upstream.session, self.open_session(peer_addr));
+        }
+        let session = sessions.get_mut(&upstream.session)?;
+        session.last_seen = Instant::now();
+
+        let (data, reply) = {
+            let mut tunnel = session.tunnel.lock().unwrap_or_else(|e| e.into_inner());
+            let data = tunnel.receive(&upstream);
+            (data, tunnel.reply(question.answer_capacity(record)))
+        };
+        // Обработчик завершился и клиент получил всё: сессии больше нет
+        if session.task.is_finished() && reply.len() == tunnel::RESPONSE_HEADER_LEN {
+            sessions.remove(&upstream.session);
+            return respond(rcode::NX_DOMAIN, None);
+        }
+        if !data.is_empty() {
+            let _ = session.to_server.send(data);
+        }
+        respond(rcode::NO_ERROR, Some((record, &reply)))
+    }
+
+    /// Завести сессию и передать её поток обработчику подключений
+    fn open_session(&self, peer_addr: SocketAddr) -> DnsSession {
+        let tunnel = Arc::new(Mutex::new(TunnelSession::new()));
+        let (to_server, from_client) = mpsc::unbounded_channel();
+        let (local, remote) = tokio::io::duplex(STREAM_BUFFER);
+
+        let tcp = Arc::clone(&self.tcp);
+        let pump = pump(local, Arc::clone(&tunnel), from_client);
+        let task = tokio::spawn(async move {
+            let serve = tcp.serve(Transport::Tunnel(remote), peer_addr);
+            let (result, _) = tokio::join!(serve, pump);
+            if let Err(e) = result {
+                debug!("Ошибка обработки сессии DNS туннеля {}: {}", peer_addr, e);
+            }
+        });
+        DnsSession {
+            tunnel,
+            to_server,
+            last_seen: Instant::now(),
+            task,
+        }
+    }
+
+    /// Закрыть сессии без запросов и завершённые сессии, чьи данные
+    /// клиент уже подтвердил
+    fn expire_sessions(&self) {
+        let idle_timeout = Duration::from_secs(self.config.dns.idle_timeout_secs);
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|id, session| {
+            let delivered = session.task.is_finished()
+                && session
+                    .tunnel
+                    .lock()
+                    .map(|tunnel| tunnel.pending() == 0)
+                    .unwrap_or(true);
+            let keep = !delivered && session.last_seen.elapsed() < idle_timeout;
+            if !keep {
+                debug!("Сессия DNS туннеля {:08x} закрыта", id);
+                session.task.abort();
+            }
+            keep
+        });
+    }
+}
+
+/// Перекачка между потоком обработчика и сессией туннеля
+async fn pump(
+    stream: DuplexStream,
+    tunnel: Arc<Mutex<TunnelSession>>,
+    mut from_client: mpsc::UnboundedReceiver<Bytes>,
+) {
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let upstream = async move {
+        while let Some(data) = from_client.recv().await {
+            if writer.write_all(&data).await.is_err() {
+                break;
+            }
+        }
+    };
+    let downstream = async move {
+        let mut buf = vec![0u8; 4096];
+        loop {
+            // Клиент забирает данные только опросами: не копим больше окна
+            while tunnel.lock().map(|t| t.pending()).unwrap_or(0) >= DOWNSTREAM_WINDOW {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            match reader.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if let Ok(mut tunnel) = tunnel.lock() {
+                        tunnel.send(&buf[..n]);
+                    }
+                }
+            }
+        }
+    };
+    // Поток закрыт обработчиком — приём от клиента больше не нужен
+    tokio::select! {
+        _ = upstream => {}
+        _ = downstream => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::Router;
+    use llp_core::session::SessionManager;
+    use llp_mimicry::dns::{ClientOptions, TunnelClient};
+    use tokio::sync::RwLock;
+
+    async fn spawn_listener() -> SocketAddr {
+        let mut config = ServerConfig::default();
+        config.network.bind_ip = "127.0.0.1".parse().unwrap();
+        config.network.port = 0;
+        config.dns.enabled = true;
+        config.dns.domain = "T.Example.com.".to_string();
+        config.dns.bind = "127.0.0.1:0".parse().unwrap();
+        let config = Arc::new(config);
+
+        let session_manager = Arc::new(RwLock::new(SessionManager::new()));
+        let router = Router::new(session_manager.clone());
+        let tcp = Arc::new(
+            LlpTcpListener::bind(Arc::clone(&config), session_manager, router.handle())
+                .await
+                .unwrap(),
+        );
+        let listener = DnsListener::bind(config, tcp).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(Arc::new(listener).run());
+        addr
+    }
+
+    async fn ask(addr: SocketAddr, query: &[u8]) -> dns::Response {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        socket.send_to(query, addr).await.unwrap();
+        let mut buf = vec![0u8; 4096];
+        let len = socket.recv(&mut buf).await.unwrap();
+        dns::parse_response(&buf[..len]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_authoritative_answers() {
+        let addr = spawn_listener().await;
+
+        let foreign = dns::encode_query(7, "vk.com", 1).unwrap();
+        assert_eq!(ask(addr, &foreign).await.rcode, rcode::REFUSED);
+
+        let apex = dns::encode_query(8, "t.example.com", 1).unwrap();
+        let response = ask(addr, &apex).await;
+        assert_eq!(response.rcode, rcode::NO_ERROR);
+        assert!(response.data.is_none());
+
+        let garbage = dns::encode_query(9, "not-base32!.t.example.com", 16).unwrap();
+        assert_eq!(ask(addr, &garbage).await.rcode, rcode::NX_DOMAIN);
+
+        // Продолжение неизвестной сессии не заводит новую
+        let mut payload = vec![0u8; tunnel::QUERY_HEADER_LEN];
+        payload[2..6].copy_from_slice(&0x1234_5678u32.to_be_bytes());
+        payload[6..10].copy_from_slice(&100u32.to_be_bytes());
+        let name = format!("{}.t.example.com", dns::encode_base32(&payload));
+        let stale = dns::encode_query(10, &name, 16).unwrap();
+        assert_eq!(ask(addr, &stale).await.rcode, rcode::NX_DOMAIN);
+
+        // Первый запрос клиента заводит сессию
+        let mut client = TunnelClient::new("t.example.com", RecordType::Txt).unwrap();
+        let (_, query) = client.query().unwrap();
+        let response = ask(addr, &query).await;
+        assert_eq!(response.rcode, rcode::NO_ERROR);
+        assert_eq!(response.data.unwrap().len(), tunnel::RESPONSE_HEADER_LEN);
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_reaches_connection_handler() {
+        let addr = spawn_listener().await;
+        let mut options = ClientOptions::new(addr, "t.example.com");
+        options.record = RecordType::Null;
+        let mut stream = dns::connect(options).await.unwrap();
+
+        // Без токена доступа обработчик ждёт CLIENT_HELLO: мусор закрывает сессию
+        stream.write_all(&[0xFF; 64]).await.unwrap();
+        let mut buf = [0u8; 16];
+        let read = tokio::time::timeout(Duration::from_secs(15), stream.read(&mut buf)).await;
+        assert!(
+            matches!(read, Ok(Ok(0)) | Ok(Err(_))),
+            "поток закрыт: {:?}",
+            read
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Защита от активного зондирования
+//!
+//! Цензор может подключиться к порту сервера и отправить обычный HTTP или
+//! TLS запрос: без защиты сервер ответил бы ошибкой разбора handshake и
+//! выдал себя. Поэтому TCP подключение сначала должно предъявить токен
+//! доступа (`llp_core::access`). Подключение без верного токена
+//! проксируется на настоящий веб-сервер вместе с уже прочитанными
+//! байтами, и зонд видит ответы этого сайта.
+
+use llp_core::access::{AccessKey, ReplayFilter, ACCESS_TOKEN_SIZE};
+use llp_core::clock::SharedClock;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::debug;
+
+/// Результат проверки первых байт подключения
+#[derive(Debug, PartialEq, Eq)]
+pub enum Probe {
+    /// Клиент LLP: токен прочитан и принят
+    Authenticated,
+    /// Чужое подключение и байты, прочитанные из него
+    Foreign(Vec<u8>),
+}
+
+/// Проверка токенов доступа новых подключений
+pub struct AccessGuard {
+    key: AccessKey,
+    replay: Mutex<ReplayFilter>,
+    max_drift_secs: u64,
+    clock: SharedClock,
+}
+
+impl AccessGuard {
+    /// Создать проверку с допустимым расхождением времени `max_drift_secs`
+    pub fn new(key: AccessKey, max_drift_secs: u64, clock: SharedClock) -> Self {
+        Self {
+            key,
+            replay: Mutex::new(ReplayFilter::new(max_drift_secs)),
+            max_drift_secs,
+            clock,
+        }
+    }
+
+    /// Прочитать и проверить токен в начале потока
+    ///
+    /// Чтение заканчивается на размере токена, конце потока, истечении
+    /// `timeout` или полном заголовке HTTP запроса: зонд с коротким
+    /// запросом не должен ждать таймаута, которого нет у настоящего сайта.
+    pub async fn probe<S>(&self, stream: &mut S, timeout: Duration) -> std::io::Result<Probe>
+    where
+        S: AsyncRead + Unpin,
+    {
+        let mut buf = Vec::with_capacity(ACCESS_TOKEN_SIZE);
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        while buf.len() < ACCESS_TOKEN_SIZE && !is_http_head(&buf) {
+            let mut chunk = [0u8; ACCESS_TOKEN_SIZE];
+            let want = ACCESS_TOKEN_SIZE - buf.len();
+            match tokio::time::timeout_at(deadline, stream.read(&mut chunk[..want])).await {
+                Ok(Ok(0)) | Err(_) => return Ok(Probe::Foreign(buf)),
+                Ok(Ok(n)) => buf.extend_from_slice(&chunk[..n]),
+                Ok(Err(e)) => return Err(e),
+            }
+        }
+
+        if self.check(&buf) {
+            Ok(Probe::Authenticated)
+        } else {
+            Ok(Probe::Foreign(buf))
+        }
+    }
+
+    /// Проверить токен и отметить его nonce
+    pub fn check(&self, token: &[u8]) -> bool {
+        let now = self.clock.unix_secs();
+        let Some((nonce, timestamp)) = self.key.verify(token, now, self.max_drift_secs) else {
+            return false;
+        };
+        let fresh = self
+            .replay
+            .lock()
+            .map(|mut replay| replay.check(nonce, timestamp, now))
+            .unwrap_or(false);
+        if !fresh {
+            debug!("Повтор токена доступа отклонён");
+        }
+        fresh
+    }
+}
+
+/// Завершён ли в буфере заголовок HTTP запроса
+fn is_http_head(buf: &[u8]) -> bool {
+    buf.windows(4).any(|w| w == b"\r\n\r\n")
+}
+
+/// Проксировать подключение на upstream, начиная с уже прочитанных байт
+///
+/// Возвращает количество байт, переданных в каждую сторону.
+pub async fn proxy<S>(client: &mut S, prefix: &[u8], upstream: &str) -> std::io::Result<(u64, u64)>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut upstream = TcpStream::connect(upstream).await?;
+    upstream.write_all(prefix).await?;
+    let (to_upstream, to_client) = tokio::io::copy_bidirectional(client, &mut upstream).await?;
+    Ok((to_upstream + prefix.len() as u64, to_client))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llp_core::clock::SystemClock;
+    use rand::rngs::OsRng;
+
+    fn guard() -> (AccessKey, AccessGuard) {
+        let key = AccessKey::generate(&mut OsRng);
+        let guard = AccessGuard::new(key.clone(), 120, SystemClock::shared());
+        (key, guard)
+    }
+
+    #[tokio::test]
+    async fn test_probe_classification() {
+        let (key, guard) = guard();
+        let timeout = Duration::from_secs(5);
+
+        let token = key.token(&mut OsRng, SystemClock::shared().unix_secs());
+        let mut stream: &[u8] = &token;
+        assert_eq!(
+            guard.probe(&mut stream, timeout).await.unwrap(),
+            Probe::Authenticated
+        );
+
+        // Повтор того же токена — уже чужое подключение
+        let mut stream: &[u8] = &token;
+        assert!(matches!(
+            guard.probe(&mut stream, timeout).await.unwrap(),
+            Probe::Foreign(_)
+        ));
+
+        // Короткий HTTP запрос не ждёт таймаута
+        let request = b"GET / HTTP/1.0\r\n\r\n";
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        client.write_all(request).await.unwrap();
+        let probe = guard
+            .probe(&mut server, Duration::from_secs(30))
+            .await
+            .unwrap();
+        assert_eq!(probe, Probe::Foreign(request.to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_silent_connection_times_out() {
+        let (_, guard) = guard();
+        let (_client, mut server) = tokio::io::duplex(1024);
+        let probe = guard
+            .probe(&mut server, Duration::from_millis(20))
+            .await
+            .unwrap();
+        assert_eq!(probe, Probe::Foreign(Vec::new()));
+    }
+}
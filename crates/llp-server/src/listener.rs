@@ -2,30 +2,45 @@
 //!
 //! Этот модуль отвечает за:
 //! - Прослушивание UDP порта
+//! - Маскировку датаграмм (например, под QUIC), если она включена
 //! - Обработку handshake с клиентами
 //! - Регистрацию сессий
 //! - Маршрутизацию пакетов между клиентами
+//! - Закрытие сессий с отправкой alert (истечение, отказ, остановка сервера)
 
 use llp_core::{
+    alert::{Alert, AlertCode},
     crypto::{AeadCipher, SessionKey},
+    error::SessionError,
     handshake::ServerHandshake,
     packet::MimicryProfile,
-    session::SessionManager,
+    session::{ReplayWindow, SessionManager},
+    LlpError,
 };
+use llp_mimicry::datagram::DatagramMode;
+use llp_mimicry::Role;
 use rand::rngs::OsRng;
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::net::UdpSocket;
 use tokio::sync::RwLock;
-use tracing::{debug, error, info};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
 
-use crate::client_handler::ClientHandler;
+use crate::client_handler::{ClientHandler, SharedDisguise};
 use crate::client_registry::ClientRegistry;
 use crate::config::ServerConfig;
 use crate::nat::NatGateway;
 use crate::router::RouterHandle;
 
+/// Сколько ждать отправки alert при закрытии сессии
+const CLOSE_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Сколько хранить незавершённый handshake и маскировку его адреса
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Результат обработки подключения
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
@@ -41,8 +56,10 @@ enum HandshakeState {
 struct ClientSession {
     session_id: u64,
     session_key: SessionKey,
-    receive_counter: u64,
+    replay_window: ReplayWindow,
     vpn_ip: IpAddr,
+    /// Задача обработчика клиента (отправка обратного трафика)
+    handler_task: JoinHandle<()>,
 }
 
 /// UDP Listener сервера
@@ -59,10 +76,14 @@ pub struct LlpListener {
     nat_gateway: Option<Arc<RwLock<NatGateway>>>,
     /// Реестр клиентов для обратной маршрутизации
     client_registry: Arc<ClientRegistry>,
-    /// Состояния handshake для клиентов (peer_addr -> state)
-    handshake_states: Arc<RwLock<HashMap<SocketAddr, HandshakeState>>>,
+    /// Состояния handshake для клиентов (peer_addr -> state, время начала)
+    handshake_states: Arc<RwLock<HashMap<SocketAddr, (HandshakeState, Instant)>>>,
     /// Подключённые клиенты (peer_addr -> session info)
     client_sessions: Arc<RwLock<HashMap<SocketAddr, ClientSession>>>,
+    /// Режим маскировки датаграмм
+    disguise_mode: DatagramMode,
+    /// Маскировка датаграмм клиентов (peer_addr -> состояние)
+    disguises: Mutex<HashMap<SocketAddr, SharedDisguise>>,
 }
 
 impl LlpListener {
@@ -76,8 +97,13 @@ impl LlpListener {
     ) -> Result<Self> {
         let bind_addr = config.bind_address();
         let socket = UdpSocket::bind(bind_addr).await?;
+        let disguise_mode = config.udp_disguise()?;
 
-        info!("LLP сервер запущен на {} (UDP)", bind_addr);
+        info!(
+            "LLP сервер запущен на {} (UDP, маскировка: {})",
+            bind_addr,
+            disguise_mode.name()
+        );
 
         Ok(Self {
             config,
@@ -88,13 +114,21 @@ impl LlpListener {
             client_registry,
             handshake_states: Arc::new(RwLock::new(HashMap::new())),
             client_sessions: Arc::new(RwLock::new(HashMap::new())),
+            disguise_mode,
+            disguises: Mutex::new(HashMap::new()),
         })
     }
 
+    /// Адрес, на котором принимаются датаграммы
+    #[allow(dead_code)]
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
     /// Запустить listener (основной цикл)
-    pub async fn run(self) -> Result<()> {
+    pub async fn run(self: Arc<Self>) -> Result<()> {
         let socket = self.socket.clone();
-        let listener = Arc::new(self);
+        let listener = self;
         let mut buf = vec![0u8; 65536]; // Максимальный размер UDP пакета
 
         loop {
@@ -123,6 +157,17 @@ impl LlpListener {
 
     /// Обработка входящего пакета
     async fn handle_packet(&self, packet: Vec<u8>, peer_addr: SocketAddr) -> Result<()> {
+        let packet = self.unwrap_datagram(packet, peer_addr)?;
+
+        // Ответы маскировки (например, STUN) уходят сразу
+        let control = ClientHandler::wrap_datagram(self.disguise_of(peer_addr).as_ref(), None)?;
+        for datagram in control {
+            self.socket.send_to(&datagram, peer_addr).await?;
+        }
+        let Some(packet) = packet else {
+            return Ok(());
+        };
+
         // Сначала проверяем, есть ли подключённая сессия
         let has_session = {
             let sessions = self.client_sessions.read().await;
@@ -143,7 +188,10 @@ impl LlpListener {
         let mut states = self.handshake_states.write().await;
 
         // Получаем или создаём состояние
-        let state = states.entry(peer_addr).or_insert(HandshakeState::WaitingClientHello);
+        let state = &mut states
+            .entry(peer_addr)
+            .or_insert_with(|| (HandshakeState::WaitingClientHello, Instant::now()))
+            .0;
 
         match state {
             HandshakeState::WaitingClientHello => {
@@ -159,7 +207,7 @@ impl LlpListener {
                     .process_client_hello(&mut rng, &packet)?;
 
                 // Отправка SERVER_HELLO
-                self.socket.send_to(&server_hello, peer_addr).await?;
+                self.send_datagram(server_hello.to_vec(), peer_addr).await?;
                 debug!("Отправлен SERVER_HELLO к {} ({} байт)", peer_addr, server_hello.len());
 
                 // Обновляем состояние (сохраняем session_id отдельно)
@@ -173,9 +221,15 @@ impl LlpListener {
 
                 // Отправка SERVER_VERIFY
                 let server_verify = server_handshake.send_server_verify()?;
-                self.socket.send_to(&server_verify, peer_addr).await?;
+                self.send_datagram(server_verify.to_vec(), peer_addr).await?;
                 debug!("Отправлен SERVER_VERIFY к {}", peer_addr);
 
+                // Дальше датаграммы сессии: короткие заголовки маскировки
+                let disguise = self.disguise_of(peer_addr);
+                if let Some(disguise) = &disguise {
+                    disguise.lock().unwrap_or_else(|e| e.into_inner()).establish();
+                }
+
                 // Handshake завершён
                 let session_id = *session_id_stored;
                 let session_key = server_handshake
@@ -189,9 +243,33 @@ impl LlpListener {
                 );
 
                 // Регистрация сессии
-                {
+                let registered = {
+                    let connected = self.client_sessions.read().await.len();
                     let mut manager = self.session_manager.write().await;
-                    manager.add_session(session_id, session_key.clone(), *mimicry_profile)?;
+
+                    if connected >= self.config.network.max_connections {
+                        Err(SessionError::TooManySessions {
+                            current: connected,
+                            max: self.config.network.max_connections,
+                        }
+                        .into())
+                    } else {
+                        manager.add_session(session_id, session_key.clone(), *mimicry_profile)
+                    }
+                };
+
+                if let Err(e) = registered {
+                    warn!("Отказ в регистрации сессии {}: {}", session_id, e);
+                    states.remove(&peer_addr);
+
+                    // Ключ уже согласован, поэтому отказ отправляется
+                    // аутентифицированным alert (первый пакет сессии, counter 0)
+                    let alert = Alert::from_error(&e);
+                    let datagram =
+                        ClientHandler::seal_datagram(&session_key, session_id, 0, &alert.serialize())?;
+                    self.send_datagram(datagram, peer_addr).await?;
+                    self.remove_disguise(peer_addr);
+                    return Ok(());
                 }
 
                 info!("Клиент зарегистрирован: session_id={}", session_id);
@@ -204,16 +282,22 @@ impl LlpListener {
                     (2 + (session_id % 253)) as u8,
                 ));
 
-                // Сохраняем информацию о сессии для обработки VPN пакетов
-                {
-                    let mut sessions = self.client_sessions.write().await;
-                    sessions.insert(peer_addr, ClientSession {
-                        session_id,
-                        session_key: session_key.clone(),
-                        receive_counter: 0,
-                        vpn_ip,
+                // Отправка клиенту по паттерну выбранного им профиля
+                let shaper = self
+                    .config
+                    .traffic_shaper(*mimicry_profile)
+                    .unwrap_or_else(|e| {
+                        warn!("Планировщик отправки для {} не создан: {}", session_id, e);
+                        None
+                    });
+
+                let cover = self
+                    .config
+                    .cover_traffic(*mimicry_profile)
+                    .unwrap_or_else(|e| {
+                        warn!("Cover traffic для {} не создан: {}", session_id, e);
+                        None
                     });
-                }
 
                 // Запуск обработчика клиента
                 let socket_clone = Arc::clone(&self.socket);
@@ -224,17 +308,34 @@ impl LlpListener {
                     session_id,
                     socket_clone,
                     peer_addr,
-                    session_key,
+                    session_key.clone(),
                     nat_clone,
                     registry_clone,
-                );
+                    disguise,
+                )
+                .with_shaper(shaper)
+                .with_cover(cover);
 
-                tokio::spawn(async move {
+                let handler_task = tokio::spawn(async move {
                     if let Err(e) = handler.run().await {
                         error!("Ошибка обработчика клиента {}: {}", session_id, e);
                     }
                 });
 
+                // Сохраняем информацию о сессии для обработки VPN пакетов
+                {
+                    let mut sessions = self.client_sessions.write().await;
+                    sessions.insert(peer_addr, ClientSession {
+                        session_id,
+                        session_key,
+                        replay_window: ReplayWindow::new(
+                            self.config.security.replay_window_size,
+                        ),
+                        vpn_ip,
+                        handler_task,
+                    });
+                }
+
                 // Удаляем состояние handshake
                 states.remove(&peer_addr);
             }
@@ -243,36 +344,229 @@ impl LlpListener {
         Ok(())
     }
 
+    /// Снять маскировку с датаграммы клиента
+    ///
+    /// Состояние маскировки заводится только для адреса, приславшего
+    /// корректный пакет: посторонние датаграммы отбрасываются без следа.
+    /// `None` — служебная датаграмма маскировки без данных LLP.
+    fn unwrap_datagram(&self, packet: Vec<u8>, peer_addr: SocketAddr) -> Result<Option<Vec<u8>>> {
+        let mut disguises = self.disguises.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(disguise) = disguises.get(&peer_addr) {
+            let mut disguise = disguise.lock().unwrap_or_else(|e| e.into_inner());
+            return Ok(disguise.unwrap(&packet)?.map(|payload| payload.to_vec()));
+        }
+
+        let Some(mut disguise) = self.disguise_mode.create(Role::Server, peer_addr)? else {
+            return Ok(Some(packet));
+        };
+        let payload = disguise.unwrap(&packet)?;
+        disguises.insert(peer_addr, Arc::new(Mutex::new(disguise)));
+        Ok(payload.map(|payload| payload.to_vec()))
+    }
+
+    /// Отправить датаграмму клиенту через его маскировку
+    async fn send_datagram(&self, datagram: Vec<u8>, peer_addr: SocketAddr) -> Result<()> {
+        let disguise = self.disguise_of(peer_addr);
+        for datagram in ClientHandler::wrap_datagram(disguise.as_ref(), Some(datagram))? {
+            self.socket.send_to(&datagram, peer_addr).await?;
+        }
+        Ok(())
+    }
+
+    /// Маскировка датаграмм клиента
+    fn disguise_of(&self, peer_addr: SocketAddr) -> Option<SharedDisguise> {
+        let disguises = self.disguises.lock().unwrap_or_else(|e| e.into_inner());
+        disguises.get(&peer_addr).cloned()
+    }
+
+    /// Забыть маскировку клиента (задача отправки держит свою ссылку)
+    fn remove_disguise(&self, peer_addr: SocketAddr) {
+        let mut disguises = self.disguises.lock().unwrap_or_else(|e| e.into_inner());
+        disguises.remove(&peer_addr);
+    }
+
     /// Обработка VPN пакета от уже подключённого клиента
     async fn handle_vpn_packet(&self, packet: Vec<u8>, peer_addr: SocketAddr) -> Result<()> {
         // Ищем сессию клиента
         let mut sessions = self.client_sessions.write().await;
 
-        if let Some(session) = sessions.get_mut(&peer_addr) {
-            let session_id = session.session_id;
-            let vpn_ip = session.vpn_ip;
-
-            // Создаём дешифратор для этого пакета
-            let decrypt_cipher = AeadCipher::new(&session.session_key, session_id);
-
-            // Обрабатываем пакет через ClientHandler
-            if let Err(e) = ClientHandler::handle_incoming_packet(
-                session_id,
-                &packet,
-                &decrypt_cipher,
-                &self.nat_gateway,
-                &mut session.receive_counter,
-                &self.client_registry,
-                vpn_ip,
-            ).await {
+        let Some(session) = sessions.get_mut(&peer_addr) else {
+            debug!("Получен VPN пакет от неизвестного клиента: {}", peer_addr);
+            return Ok(());
+        };
+
+        let session_id = session.session_id;
+        let vpn_ip = session.vpn_ip;
+
+        // Создаём дешифратор для этого пакета
+        let decrypt_cipher = AeadCipher::new(&session.session_key, session_id);
+
+        // Обрабатываем пакет через ClientHandler
+        let result = ClientHandler::handle_incoming_packet(
+            session_id,
+            &packet,
+            &decrypt_cipher,
+            &self.nat_gateway,
+            &mut session.replay_window,
+            &self.client_registry,
+            vpn_ip,
+        )
+        .await;
+
+        let close_with = match result {
+            Ok(received_alert) => {
+                // Любой аутентифицированный пакет подтверждает, что клиент жив
+                if let Ok(s) = self.session_manager.write().await.get_session_mut(session_id) {
+                    s.mark_keepalive_received();
+                }
+
+                match received_alert {
+                    // Клиент закрывает сессию — отвечаем close_notify
+                    Some(alert) if alert.is_fatal() => Some(Alert::close_notify()),
+                    _ => None,
+                }
+            }
+            Err(e) => {
                 debug!("Ошибка обработки VPN пакета от {}: {}", peer_addr, e);
+
+                // Адрес UDP легко подделать: неаутентифицированный пакет
+                // молча отбрасывается и не влияет на сессию. Alert — только
+                // на ошибку в аутентифицированном сообщении клиента
+                match e.downcast_ref::<LlpError>() {
+                    Some(llp_error) => {
+                        let alert = Alert::from_error(llp_error);
+                        (alert.code != AlertCode::AuthenticationFailed).then_some(alert)
+                    }
+                    None => None,
+                }
             }
-        } else {
-            debug!("Получен VPN пакет от неизвестного клиента: {}", peer_addr);
+        };
+
+        drop(sessions);
+
+        if let Some(alert) = close_with {
+            self.close_session(peer_addr, alert).await;
         }
 
         Ok(())
     }
+
+    /// Закрыть сессию клиента, отправив ему alert
+    ///
+    /// Alert ставится в очередь обработчика клиента и шифруется следующим
+    /// nonce сессии, как обычный пакет. Затем регистрация снимается:
+    /// задача отправки дочитывает очередь и завершается.
+    pub async fn close_session(&self, peer_addr: SocketAddr, alert: Alert) {
+        let session = self.client_sessions.write().await.remove(&peer_addr);
+        self.remove_disguise(peer_addr);
+
+        if let Some(session) = session {
+            info!(
+                "Закрытие сессии {} ({}): {}",
+                session.session_id, peer_addr, alert
+            );
+            let task = self.detach_session(session, &alert).await;
+            let _ = tokio::time::timeout(CLOSE_FLUSH_TIMEOUT, task).await;
+        }
+    }
+
+    /// Закрыть все сессии (например, при остановке сервера)
+    pub async fn close_all(&self, alert: Alert) {
+        let sessions: Vec<_> = self.client_sessions.write().await.drain().collect();
+        self.disguises
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+
+        if sessions.is_empty() {
+            return;
+        }
+
+        info!("Закрытие {} сессий: {}", sessions.len(), alert);
+
+        let mut tasks = Vec::with_capacity(sessions.len());
+        for (_, session) in sessions {
+            tasks.push(self.detach_session(session, &alert).await);
+        }
+
+        let _ = tokio::time::timeout(CLOSE_FLUSH_TIMEOUT, async {
+            for task in tasks {
+                let _ = task.await;
+            }
+        })
+        .await;
+    }
+
+    /// Удалить истёкшие сессии и уведомить их клиентов
+    ///
+    /// Возвращает количество закрытых сессий.
+    pub async fn expire_sessions(&self) -> usize {
+        self.expire_handshakes(HANDSHAKE_TIMEOUT).await;
+
+        let expired = self.session_manager.write().await.take_expired();
+        let count = expired.len();
+
+        for error in expired {
+            let session_id = match error {
+                SessionError::SessionExpired { session_id }
+                | SessionError::KeepaliveTimeout { session_id } => session_id,
+                _ => continue,
+            };
+
+            let peer_addr = {
+                let sessions = self.client_sessions.read().await;
+                sessions
+                    .iter()
+                    .find(|(_, s)| s.session_id == session_id)
+                    .map(|(addr, _)| *addr)
+            };
+
+            if let Some(peer_addr) = peer_addr {
+                let alert = Alert::new(AlertCode::from(&LlpError::from(error)));
+                self.close_session(peer_addr, alert).await;
+            }
+        }
+
+        count
+    }
+
+    /// Удалить handshake старше `timeout` и маскировку адресов, у которых
+    /// нет ни handshake, ни сессии
+    async fn expire_handshakes(&self, timeout: Duration) {
+        let mut states = self.handshake_states.write().await;
+        states.retain(|_, (_, started)| started.elapsed() < timeout);
+
+        let sessions = self.client_sessions.read().await;
+        self.disguises
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|addr, _| states.contains_key(addr) || sessions.contains_key(addr));
+    }
+
+    /// Отправить alert через очередь обработчика и снять регистрацию клиента
+    async fn detach_session(&self, session: ClientSession, alert: &Alert) -> JoinHandle<()> {
+        if !self
+            .client_registry
+            .send_to(session.vpn_ip, alert.serialize())
+            .await
+        {
+            debug!(
+                "Alert для сессии {} не отправлен: обработчик не зарегистрирован",
+                session.session_id
+            );
+        }
+
+        self.client_registry.unregister_client(session.vpn_ip).await;
+
+        // Сессия могла уже быть удалена при истечении
+        let _ = self
+            .session_manager
+            .write()
+            .await
+            .remove_session(session.session_id);
+
+        session.handler_task
+    }
 }
 
 #[cfg(test)]
@@ -298,4 +592,197 @@ mod tests {
         let result = LlpListener::bind(test_config, session_manager, router_handle, None, client_registry).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_quic_disguised_handshake() {
+        use crate::client_registry::ClientRegistry;
+        use llp_core::handshake::ClientHandshake;
+        use llp_mimicry::datagram::{DatagramDisguise, QuicDisguise};
+
+        let mut config = ServerConfig::default();
+        config.network.bind_ip = "127.0.0.1".parse().unwrap();
+        config.network.port = 0;
+        config.network.udp_disguise = "quic".to_string();
+        let session_manager = Arc::new(RwLock::new(SessionManager::new()));
+        let router = Router::new(session_manager.clone());
+        let listener = LlpListener::bind(
+            Arc::new(config),
+            session_manager,
+            router.handle(),
+            None,
+            Arc::new(ClientRegistry::new()),
+        )
+        .await
+        .unwrap();
+        let server = listener.local_addr().unwrap();
+        tokio::spawn(Arc::new(listener).run());
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut quic = QuicDisguise::new(Role::Client);
+        let mut buf = vec![0u8; 65536];
+
+        // Посторонняя датаграмма остаётся без ответа
+        socket.send_to(b"\x00garbage", server).await.unwrap();
+
+        let mut client = ClientHandshake::new(&mut OsRng, MimicryProfile::VkVideo);
+        let hello = quic.wrap(&client.start(&mut OsRng).unwrap()).unwrap();
+        socket.send_to(&hello, server).await.unwrap();
+
+        let (len, _) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!(buf[0] & 0xF0, 0xC0, "SERVER_HELLO в пакете Initial");
+        let server_hello = quic.unwrap(&buf[..len]).unwrap().unwrap();
+        client.process_server_hello(&server_hello).unwrap();
+
+        let verify = quic.wrap(&client.send_client_verify().unwrap()).unwrap();
+        socket.send_to(&verify, server).await.unwrap();
+
+        let (len, _) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!(buf[0] & 0xF0, 0xE0, "SERVER_VERIFY в пакете Handshake");
+        let server_verify = quic.unwrap(&buf[..len]).unwrap().unwrap();
+        client.process_server_verify(&server_verify).unwrap();
+        assert!(client.is_completed());
+    }
+
+    #[tokio::test]
+    async fn test_stale_handshake_expires_with_disguise() {
+        use crate::client_registry::ClientRegistry;
+        use llp_core::handshake::ClientHandshake;
+        use llp_mimicry::datagram::{DatagramDisguise, QuicDisguise};
+
+        let mut config = ServerConfig::default();
+        config.network.bind_ip = "127.0.0.1".parse().unwrap();
+        config.network.port = 0;
+        config.network.udp_disguise = "quic".to_string();
+        let session_manager = Arc::new(RwLock::new(SessionManager::new()));
+        let router = Router::new(session_manager.clone());
+        let listener = LlpListener::bind(
+            Arc::new(config),
+            session_manager,
+            router.handle(),
+            None,
+            Arc::new(ClientRegistry::new()),
+        )
+        .await
+        .unwrap();
+
+        // Клиент прислал CLIENT_HELLO и пропал
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let peer = socket.local_addr().unwrap();
+        let mut client = ClientHandshake::new(&mut OsRng, MimicryProfile::VkVideo);
+        let hello = QuicDisguise::new(Role::Client)
+            .wrap(&client.start(&mut OsRng).unwrap())
+            .unwrap();
+        listener.handle_packet(hello.to_vec(), peer).await.unwrap();
+        assert_eq!(listener.handshake_states.read().await.len(), 1);
+        assert!(listener.disguise_of(peer).is_some());
+
+        listener.expire_handshakes(HANDSHAKE_TIMEOUT).await;
+        assert!(listener.disguise_of(peer).is_some(), "handshake ещё не истёк");
+
+        listener.expire_handshakes(Duration::ZERO).await;
+        assert!(listener.handshake_states.read().await.is_empty());
+        assert!(listener.disguise_of(peer).is_none(), "маскировка удалена");
+    }
+
+    #[tokio::test]
+    async fn test_unauthenticated_packets_keep_session() {
+        use crate::client_registry::ClientRegistry;
+        use llp_core::handshake::ClientHandshake;
+
+        let mut config = ServerConfig::default();
+        config.network.bind_ip = "127.0.0.1".parse().unwrap();
+        config.network.port = 0;
+        let session_manager = Arc::new(RwLock::new(SessionManager::new()));
+        let router = Router::new(session_manager.clone());
+        let listener = Arc::new(
+            LlpListener::bind(
+                Arc::new(config),
+                session_manager,
+                router.handle(),
+                None,
+                Arc::new(ClientRegistry::new()),
+            )
+            .await
+            .unwrap(),
+        );
+        let server = listener.local_addr().unwrap();
+        tokio::spawn(Arc::clone(&listener).run());
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut buf = vec![0u8; 65536];
+
+        let mut client = ClientHandshake::new(&mut OsRng, MimicryProfile::VkVideo);
+        socket.send_to(&client.start(&mut OsRng).unwrap(), server).await.unwrap();
+        let (len, _) = socket.recv_from(&mut buf).await.unwrap();
+        client.process_server_hello(&buf[..len]).unwrap();
+        socket.send_to(&client.send_client_verify().unwrap(), server).await.unwrap();
+        let (len, _) = socket.recv_from(&mut buf).await.unwrap();
+        client.process_server_verify(&buf[..len]).unwrap();
+        assert!(client.is_completed());
+
+        // Поддельные пакеты с адреса клиента не закрывают сессию и не
+        // вызывают alert
+        for i in 0..64u8 {
+            socket.send_to(&[i; 64], server).await.unwrap();
+        }
+        let reply =
+            tokio::time::timeout(Duration::from_millis(300), socket.recv_from(&mut buf)).await;
+        assert!(reply.is_err(), "ответа на неаутентифицированные пакеты нет");
+        assert_eq!(listener.client_sessions.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_webrtc_disguised_handshake() {
+        use crate::client_registry::ClientRegistry;
+        use llp_core::handshake::ClientHandshake;
+        use llp_mimicry::datagram::{DatagramDisguise, WebRtcDisguise};
+
+        let mut config = ServerConfig::default();
+        config.network.bind_ip = "127.0.0.1".parse().unwrap();
+        config.network.port = 0;
+        config.network.udp_disguise = "webrtc".to_string();
+        let session_manager = Arc::new(RwLock::new(SessionManager::new()));
+        let router = Router::new(session_manager.clone());
+        let listener = LlpListener::bind(
+            Arc::new(config),
+            session_manager,
+            router.handle(),
+            None,
+            Arc::new(ClientRegistry::new()),
+        )
+        .await
+        .unwrap();
+        let server = listener.local_addr().unwrap();
+        tokio::spawn(Arc::new(listener).run());
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut webrtc = WebRtcDisguise::new(Role::Client, server);
+        let mut buf = vec![0u8; 65536];
+
+        // Binding request уходит раньше CLIENT_HELLO
+        let mut client = ClientHandshake::new(&mut OsRng, MimicryProfile::VkVideo);
+        let hello = webrtc.wrap(&client.start(&mut OsRng).unwrap()).unwrap();
+        for request in webrtc.take_control() {
+            socket.send_to(&request, server).await.unwrap();
+        }
+        socket.send_to(&hello, server).await.unwrap();
+
+        let (len, _) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..2], &[0x01, 0x01], "binding success response");
+        assert!(webrtc.unwrap(&buf[..len]).unwrap().is_none());
+        assert!(webrtc.is_connected());
+
+        let (len, _) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!(buf[0], 0x80, "SERVER_HELLO в пакете RTP");
+        let server_hello = webrtc.unwrap(&buf[..len]).unwrap().unwrap();
+        client.process_server_hello(&server_hello).unwrap();
+
+        let verify = webrtc.wrap(&client.send_client_verify().unwrap()).unwrap();
+        socket.send_to(&verify, server).await.unwrap();
+
+        let (len, _) = socket.recv_from(&mut buf).await.unwrap();
+        let server_verify = webrtc.unwrap(&buf[..len]).unwrap().unwrap();
+        client.process_server_verify(&server_verify).unwrap();
+        assert!(client.is_completed());
+    }
 }
@@ -5,19 +5,26 @@
 mod client_handler;
 mod client_registry;
 mod config;
+mod decoy;
+mod dns_listener;
 mod dpi_bypass;
+mod fallback;
 mod listener;
 mod nat;
 mod router;
+mod tcp_listener;
 mod tun_device;
 
 use clap::Parser;
 use client_registry::ClientRegistry;
 use config::ServerConfig;
+use dns_listener::DnsListener;
 use listener::LlpListener;
+use llp_core::alert::{Alert, AlertCode};
 use llp_core::session::SessionManager;
 use nat::NatGateway;
 use router::Router;
+use tcp_listener::LlpTcpListener;
 use std::net::{IpAddr, Ipv4Addr};
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -130,6 +137,41 @@ async fn main() {
     info!("  • VPN подсеть: {}", config.vpn.subnet);
     info!("  • Профиль мимикрии: {}", config.security.default_mimicry_profile);
 
+    // Шаблоны профилей мимикрии перечитываются на лету
+    match config.load_profile_templates() {
+        Ok(Some(templates)) => {
+            info!("  • Шаблоны профилей: {}", templates.path().display());
+            if let Some(interval) = config.profile_reload_interval() {
+                templates.spawn_reloader(interval);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("Ошибка загрузки шаблонов профилей: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Err(e) = config.parse_mimicry_profile() {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+
+    // Внешний TLS слой (сертификат проверен при загрузке конфигурации)
+    if config.tls.enabled {
+        if let Some(cert) = &config.tls.cert_file {
+            info!("  • TLS 1.3: {}", cert.display());
+        }
+    }
+
+    // Подключения без токена доступа отдаются настоящему сайту или приманке
+    if config.fallback.enabled {
+        match (&config.fallback.upstream, &config.fallback.site_dir) {
+            (Some(upstream), _) => info!("  • Fallback: {}", upstream),
+            (None, Some(dir)) => info!("  • Fallback: сайт-приманка {}", dir.display()),
+            (None, None) => info!("  • Fallback: страницы ошибок nginx"),
+        }
+    }
+
     let config = Arc::new(config);
 
     // Запуск сервера
@@ -142,9 +184,9 @@ async fn main() {
 /// Запуск сервера
 async fn run_server(config: Arc<ServerConfig>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Создание менеджера сессий
-    let session_manager = Arc::new(RwLock::new(SessionManager::with_lifetime(
-        config.session_lifetime(),
-    )));
+    let mut session_manager = SessionManager::with_lifetime(config.session_lifetime());
+    session_manager.set_replay_window_size(config.security.replay_window_size);
+    let session_manager = Arc::new(RwLock::new(session_manager));
 
     // Создание TUN interface
     let tun_device = match ServerTunDevice::new("llp0".to_string()) {
@@ -176,7 +218,9 @@ async fn run_server(config: Arc<ServerConfig>) -> Result<(), Box<dyn std::error:
     let client_registry = Arc::new(ClientRegistry::new());
 
     // Создание роутера
-    let router = Router::new(Arc::clone(&session_manager));
+    let mut router = Router::new(Arc::clone(&session_manager));
+    router.set_aggregation(config.aggregation.options());
+    router.set_streaming(config.network.stream_responses);
     let router_handle = router.handle();
 
     // Запуск роутера в отдельной задаче
@@ -218,23 +262,54 @@ async fn run_server(config: Arc<ServerConfig>) -> Result<(), Box<dyn std::error:
         });
     }
 
+    // TCP listener: клиенты поверх TCP/TLS и отдача зондов upstream
+    let tcp_listener = Arc::new(
+        LlpTcpListener::bind(
+            Arc::clone(&config),
+            session_manager.clone(),
+            router_handle.clone(),
+        )
+        .await?,
+    );
+    let tcp_runner = Arc::clone(&tcp_listener);
+    tokio::spawn(async move {
+        if let Err(e) = tcp_runner.run().await {
+            error!("Ошибка TCP listener: {}", e);
+        }
+    });
+
+    // DNS туннель: сессии обслуживаются тем же обработчиком, что и TCP
+    if config.dns.enabled {
+        let dns_listener =
+            Arc::new(DnsListener::bind(Arc::clone(&config), Arc::clone(&tcp_listener)).await?);
+        tokio::spawn(async move {
+            if let Err(e) = dns_listener.run().await {
+                error!("Ошибка DNS listener: {}", e);
+            }
+        });
+    }
+
     // Создание и запуск listener с NAT gateway и client registry
-    let listener = LlpListener::bind(
-        Arc::clone(&config),
-        session_manager.clone(),
-        router_handle,
-        Some(nat_gateway.clone()),
-        Arc::clone(&client_registry),
-    )
-    .await?;
+    let listener = Arc::new(
+        LlpListener::bind(
+            Arc::clone(&config),
+            session_manager.clone(),
+            router_handle.clone(),
+            Some(nat_gateway.clone()),
+            Arc::clone(&client_registry),
+        )
+        .await?,
+    );
 
     // Запуск фоновой задачи для очистки истёкших сессий
-    let session_manager_cleanup = session_manager.clone();
+    let listener_cleanup = Arc::clone(&listener);
+    let router_cleanup = router_handle.clone();
     tokio::spawn(async move {
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
-            let mut manager = session_manager_cleanup.write().await;
-            let removed = manager.cleanup_expired();
+            // Сначала TCP сессии: роутеру нужен ключ сессии для alert
+            let closed = router_cleanup.close_expired().await.unwrap_or(0);
+            let removed = closed + listener_cleanup.expire_sessions().await;
             if removed > 0 {
                 info!("Очищено {} истёкших сессий", removed);
             }
@@ -243,7 +318,7 @@ async fn run_server(config: Arc<ServerConfig>) -> Result<(), Box<dyn std::error:
 
     // Обработка сигналов для graceful shutdown
     tokio::select! {
-        result = listener.run() => {
+        result = Arc::clone(&listener).run() => {
             if let Err(e) = result {
                 error!("Ошибка listener: {}", e);
             }
@@ -253,6 +328,13 @@ async fn run_server(config: Arc<ServerConfig>) -> Result<(), Box<dyn std::error:
         }
     }
 
+    // Уведомляем клиентов, чтобы они переподключились позже
+    let shutdown = Alert::new(AlertCode::ServerShutdown);
+    if let Err(e) = router_handle.close_all(shutdown.clone()).await {
+        error!("Ошибка закрытия TCP сессий: {}", e);
+    }
+    listener.close_all(shutdown).await;
+
     info!("Сервер остановлен");
     Ok(())
 }
@@ -336,6 +418,12 @@ mtu = {}
 mimicry_profile = "{}"
 enable_replay_protection = true
 max_packet_age_sec = 60
+{}
+[tls]
+# Внешний TLS 1.3 слой должен совпадать с сервером
+enabled = {}
+# Скопируйте сертификат сервера на клиент
+# ca_cert = "server.crt"
 
 [reconnect]
 enable = true
@@ -348,7 +436,14 @@ level = "info"
 "#,
         server_address,
         server_config.vpn.mtu,
-        server_config.security.default_mimicry_profile
+        server_config.security.default_mimicry_profile,
+        server_config
+            .security
+            .access_key
+            .as_ref()
+            .map(|key| format!("# Ключ доступа должен совпадать с сервером\naccess_key = \"{}\"\n", key))
+            .unwrap_or_default(),
+        server_config.tls.enabled
     );
 
     // Сохранение в файл
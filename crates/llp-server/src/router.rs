@@ -6,23 +6,39 @@
 //! - Расшифровку и валидацию пакетов
 //! - Маршрутизацию IP пакетов
 //! - Отправку пакетов клиентам
+//! - Закрытие сессий с отправкой alert (истечение, остановка сервера,
+//!   close_notify клиента)
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use llp_core::{
-    packet::MimicryProfile,
+    alert::{Alert, AlertCode},
+    error::{LlpError, SessionError},
+    packet::{LlpPacket, MimicryProfile},
     session::SessionManager,
 };
-use llp_mimicry::PacketWrapper;
-use std::collections::HashMap;
+use llp_mimicry::aggregate::AggregationOptions;
+use llp_mimicry::codec::{self, HttpDecoder};
+use llp_mimicry::h2::{self, H2Session};
+use llp_mimicry::tls::Transport;
+use llp_mimicry::ws::{self, WsCodec};
+use llp_mimicry::{MimicryError, PacketWrapper, Role};
+use std::collections::{HashMap, VecDeque};
 use std::net::IpAddr;
 use std::sync::Arc;
-use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
-use tokio::sync::{mpsc, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::sync::{mpsc, oneshot, RwLock};
 use tracing::{debug, error, info};
 
 use crate::nat::NatGateway;
 
+/// Период отправки короткого остатка потоковых ответов
+const STREAM_FLUSH_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Сколько закрываемый клиент может не присылать запрос, на который
+/// отправляется alert
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
 /// Команды для роутера
@@ -32,7 +48,9 @@ pub enum RouterCommand {
     /// Зарегистрировать нового клиента
     RegisterClient {
         session_id: u64,
-        stream: TcpStream,
+        stream: Transport,
+        websocket: Option<WsCodec>,
+        h2: Option<Box<H2Session>>,
         profile: MimicryProfile,
     },
     /// Отправить пакет клиенту
@@ -44,6 +62,23 @@ pub enum RouterCommand {
     RemoveClient {
         session_id: u64,
     },
+    /// Сообщение, прочитанное от клиента
+    FromClient {
+        session_id: u64,
+        message: Bytes,
+    },
+    /// Ping WebSocket от клиента
+    Ping {
+        session_id: u64,
+        payload: Bytes,
+    },
+    /// Закрыть сессии всех клиентов (например, при остановке сервера)
+    CloseAll {
+        alert: Alert,
+        done: oneshot::Sender<()>,
+    },
+    /// Закрыть истёкшие сессии клиентов; ответ — их количество
+    CloseExpired { done: oneshot::Sender<usize> },
 }
 
 /// Handle для взаимодействия с роутером
@@ -54,16 +89,23 @@ pub struct RouterHandle {
 
 impl RouterHandle {
     /// Зарегистрировать клиента
+    ///
+    /// `websocket` — кодек кадров, если клиент подключён по WebSocket;
+    /// `h2` — соединение после handshake, если согласован ALPN `h2`.
     pub async fn register_client(
         &self,
         session_id: u64,
-        stream: TcpStream,
+        stream: Transport,
+        websocket: Option<WsCodec>,
+        h2: Option<H2Session>,
         profile: MimicryProfile,
     ) -> Result<()> {
         self.tx
             .send(RouterCommand::RegisterClient {
                 session_id,
                 stream,
+                websocket,
+                h2: h2.map(Box::new),
                 profile,
             })
             .map_err(|e| format!("Не удалось отправить команду: {}", e))?;
@@ -87,14 +129,50 @@ impl RouterHandle {
             .map_err(|e| format!("Не удалось отправить команду: {}", e))?;
         Ok(())
     }
+
+    /// Закрыть сессии всех клиентов, отправив им alert
+    ///
+    /// Завершается, когда alert зашифрован и отправлен клиентам, у которых
+    /// есть открытый запрос; остальные получат его в ответ на следующий.
+    pub async fn close_all(&self, alert: Alert) -> Result<()> {
+        let (done, wait) = oneshot::channel();
+        self.tx
+            .send(RouterCommand::CloseAll { alert, done })
+            .map_err(|e| format!("Не удалось отправить команду: {}", e))?;
+        wait.await?;
+        Ok(())
+    }
+
+    /// Закрыть истёкшие сессии клиентов роутера
+    ///
+    /// Возвращает количество закрытых сессий. Вызывается до
+    /// [`SessionManager::take_expired`], который удаляет сессии без alert.
+    pub async fn close_expired(&self) -> Result<usize> {
+        let (done, wait) = oneshot::channel();
+        self.tx
+            .send(RouterCommand::CloseExpired { done })
+            .map_err(|e| format!("Не удалось отправить команду: {}", e))?;
+        Ok(wait.await?)
+    }
 }
 
 /// Информация о подключённом клиенте
 #[allow(dead_code)]
 struct ClientInfo {
     session_id: u64,
-    stream: TcpStream,
+    /// Половина потока для записи; чтение — в задаче клиента
+    stream: WriteHalf<Transport>,
+    /// Кадры WebSocket вместо HTTP мимикрии (None — обычный поток)
+    websocket: Option<WsCodec>,
+    /// HTTP/2 соединение вместо HTTP/1.1 мимикрии (None — обычный поток)
+    h2: Option<H2Session>,
     wrapper: PacketWrapper,
+    /// Данные, ждущие запроса клиента, на который можно ответить
+    pending: VecDeque<Bytes>,
+    /// Открывать потоковый ответ (выключается, если профиль не умеет)
+    stream_responses: bool,
+    /// Сессия закрыта: клиент отключается, как только очередь отправлена
+    closing: bool,
     vpn_ip: Option<IpAddr>,
 }
 
@@ -109,6 +187,10 @@ pub struct Router {
     ip_to_session: HashMap<IpAddr, u64>,
     /// NAT gateway
     nat_gateway: Option<NatGateway>,
+    /// Агрегация пакетов в сообщения (None — по пакету в сообщении)
+    aggregation: Option<AggregationOptions>,
+    /// Отвечать клиентам потоковыми chunked ответами
+    streaming: bool,
     /// Канал команд
     rx: mpsc::UnboundedReceiver<RouterCommand>,
     /// Sender для handle
@@ -125,6 +207,8 @@ impl Router {
             clients: HashMap::new(),
             ip_to_session: HashMap::new(),
             nat_gateway: None,
+            aggregation: None,
+            streaming: false,
             rx,
             tx,
         }
@@ -143,48 +227,125 @@ impl Router {
         self.nat_gateway = Some(nat);
     }
 
+    /// Объединять пакеты клиентам в сообщения размера профиля
+    pub fn set_aggregation(&mut self, options: Option<AggregationOptions>) {
+        self.aggregation = options;
+    }
+
+    /// Отвечать клиентам долгими chunked ответами
+    ///
+    /// Короткий остаток потока отправляется раз в [`STREAM_FLUSH_INTERVAL`].
+    pub fn set_streaming(&mut self, enabled: bool) {
+        self.streaming = enabled;
+    }
+
     /// Запустить роутер (основной цикл)
     pub async fn run(mut self) {
         info!("Роутер запущен");
 
-        while let Some(command) = self.rx.recv().await {
-            match command {
-                RouterCommand::RegisterClient {
-                    session_id,
-                    stream,
-                    profile,
-                } => {
-                    if let Err(e) = self.register_client(session_id, stream, profile).await {
-                        error!("Ошибка регистрации клиента {}: {}", session_id, e);
-                    }
+        let mut flush = tokio::time::interval(STREAM_FLUSH_INTERVAL);
+        flush.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            tokio::select! {
+                command = self.rx.recv() => match command {
+                    Some(command) => self.handle_command(command).await,
+                    None => break,
+                },
+                _ = flush.tick(), if self.streaming => self.flush_streams().await,
+            }
+        }
+
+        info!("Роутер остановлен");
+    }
+
+    /// Выполнить команду
+    async fn handle_command(&mut self, command: RouterCommand) {
+        match command {
+            RouterCommand::RegisterClient {
+                session_id,
+                stream,
+                websocket,
+                h2,
+                profile,
+            } => {
+                if let Err(e) = self
+                    .register_client(session_id, stream, websocket, h2.map(|h2| *h2), profile)
+                    .await
+                {
+                    error!("Ошибка регистрации клиента {}: {}", session_id, e);
                 }
-                RouterCommand::SendToClient { session_id, data } => {
-                    if let Err(e) = self.send_to_client(session_id, data).await {
-                        error!("Ошибка отправки клиенту {}: {}", session_id, e);
-                    }
+            }
+            RouterCommand::SendToClient { session_id, data } => {
+                if let Err(e) = self.send_to_client(session_id, data).await {
+                    error!("Ошибка отправки клиенту {}: {}", session_id, e);
+                }
+            }
+            RouterCommand::RemoveClient { session_id } => {
+                self.remove_client(session_id).await;
+            }
+            RouterCommand::FromClient {
+                session_id,
+                message,
+            } => {
+                if let Err(e) = self.receive_from_client(session_id, message).await {
+                    error!("Ошибка обработки сообщения клиента {}: {}", session_id, e);
+                }
+            }
+            RouterCommand::Ping {
+                session_id,
+                payload,
+            } => {
+                if let Err(e) = self.pong(session_id, &payload).await {
+                    error!("Ошибка отправки pong клиенту {}: {}", session_id, e);
+                }
+            }
+            RouterCommand::CloseAll { alert, done } => {
+                let sessions: Vec<u64> = self.clients.keys().copied().collect();
+                if !sessions.is_empty() {
+                    info!("Закрытие {} TCP сессий: {}", sessions.len(), alert);
                 }
-                RouterCommand::RemoveClient { session_id } => {
-                    self.remove_client(session_id).await;
+                for session_id in sessions {
+                    self.close_client(session_id, alert.clone()).await;
                 }
+                let _ = done.send(());
+            }
+            RouterCommand::CloseExpired { done } => {
+                let _ = done.send(self.close_expired().await);
             }
         }
-
-        info!("Роутер остановлен");
     }
 
     /// Зарегистрировать клиента
     async fn register_client(
         &mut self,
         session_id: u64,
-        stream: TcpStream,
+        stream: Transport,
+        websocket: Option<WsCodec>,
+        h2: Option<H2Session>,
         profile: MimicryProfile,
     ) -> Result<()> {
-        let wrapper = PacketWrapper::new(profile);
+        // Профиль выбран клиентом: незарегистрированный ID — ошибка, а не panic
+        // Сервер передаёт данные только в ответах на запросы клиента
+        let mut wrapper = PacketWrapper::try_new(profile)?.with_role(Role::Server);
+        if let Some(options) = self.aggregation.clone() {
+            wrapper = wrapper.with_aggregation(options);
+        }
+
+        let http = wrapper.is_http();
+        let is_websocket = websocket.is_some();
+        let is_h2 = h2.is_some();
+        let (reader, writer) = tokio::io::split(stream);
 
         let client_info = ClientInfo {
             session_id,
-            stream,
+            stream: writer,
+            websocket,
+            h2,
             wrapper,
+            pending: VecDeque::new(),
+            stream_responses: self.streaming,
+            closing: false,
             vpn_ip: None, // TODO: Назначить IP из пула
         };
 
@@ -194,59 +355,388 @@ impl Router {
 
         // Запустить задачу чтения для этого клиента
         let handle = self.handle();
-        let session_manager = Arc::clone(&self.session_manager);
 
         tokio::spawn(async move {
-            if let Err(e) =
-                Self::client_read_loop(session_id, handle, session_manager).await
-            {
-                error!("Ошибка в client_read_loop для {}: {}", session_id, e);
+            let result = if is_websocket {
+                Self::websocket_read_loop(session_id, reader, &handle).await
+            } else if is_h2 {
+                Self::h2_read_loop(session_id, reader, &handle).await
+            } else {
+                Self::client_read_loop(session_id, reader, http, &handle).await
+            };
+            if let Err(e) = result {
+                debug!("Цикл чтения клиента {} завершён: {}", session_id, e);
             }
+
+            // Удаляем клиента при отключении
+            let _ = handle.remove_client(session_id).await;
         });
 
         Ok(())
     }
 
-    /// Цикл чтения от клиента (заглушка)
+    /// Цикл чтения сообщений мимикрии от клиента
+    ///
+    /// Сообщения разбирает роутер: запросы клиента открывают ответы сервера.
     async fn client_read_loop(
         session_id: u64,
-        handle: RouterHandle,
-        _session_manager: Arc<RwLock<SessionManager>>,
+        mut reader: ReadHalf<Transport>,
+        http: bool,
+        handle: &RouterHandle,
     ) -> Result<()> {
-        info!("Запущен цикл чтения для клиента {} (заглушка)", session_id);
+        let mut decoder = HttpDecoder::new();
+        loop {
+            let message = codec::read_frame(&mut reader, &mut decoder, http).await?;
+            handle
+                .tx
+                .send(RouterCommand::FromClient {
+                    session_id,
+                    message,
+                })
+                .map_err(|_| "Роутер остановлен")?;
+        }
+    }
 
-        // NOTE: ClientHandler теперь занимается чтением пакетов
-        // Эта функция - заглушка для совместимости с router
-        tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await;
+    /// Цикл чтения кадров WebSocket от клиента
+    ///
+    /// Pong отправляет роутер: ему принадлежит половина потока для записи.
+    async fn websocket_read_loop(
+        session_id: u64,
+        mut reader: ReadHalf<Transport>,
+        handle: &RouterHandle,
+    ) -> Result<()> {
+        let mut codec = WsCodec::server();
+        let mut buf = BytesMut::with_capacity(16 * 1024);
+        loop {
+            let command = match codec.decode()? {
+                Some(ws::Message::Binary(message)) => RouterCommand::FromClient {
+                    session_id,
+                    message,
+                },
+                Some(ws::Message::Ping(payload)) => RouterCommand::Ping {
+                    session_id,
+                    payload,
+                },
+                Some(ws::Message::Pong(_)) => continue,
+                Some(ws::Message::Close(_)) => return Ok(()),
+                None => {
+                    buf.clear();
+                    if reader.read_buf(&mut buf).await? == 0 {
+                        return Ok(());
+                    }
+                    codec.extend(&buf);
+                    continue;
+                }
+            };
+            handle.tx.send(command).map_err(|_| "Роутер остановлен")?;
+        }
+    }
+
+    /// Цикл чтения байт HTTP/2 соединения клиента
+    ///
+    /// Кадры разбирает роутер: сессия HTTP/2 отвечает на них в той же
+    /// половине потока, в которую пишет данные.
+    async fn h2_read_loop(
+        session_id: u64,
+        mut reader: ReadHalf<Transport>,
+        handle: &RouterHandle,
+    ) -> Result<()> {
+        let mut buf = BytesMut::with_capacity(16 * 1024);
+        loop {
+            if reader.read_buf(&mut buf).await? == 0 {
+                return Ok(());
+            }
+            handle
+                .tx
+                .send(RouterCommand::FromClient {
+                    session_id,
+                    message: buf.split().freeze(),
+                })
+                .map_err(|_| "Роутер остановлен")?;
+        }
+    }
+
+    /// Обработать сообщение клиента
+    ///
+    /// Пакеты расшифровываются сессией клиента и уходят в маршрутизацию;
+    /// каждый запрос позволяет отправить клиенту ждущие данные. Пакет,
+    /// который не прошёл проверку, отбрасывается, а остальные пакеты
+    /// пачки обрабатываются.
+    async fn receive_from_client(&mut self, session_id: u64, message: Bytes) -> Result<()> {
+        let client = self
+            .clients
+            .get_mut(&session_id)
+            .ok_or("Клиент не найден")?;
+
+        // По WebSocket сообщение — LLP пакет целиком, по HTTP/2 — байты
+        // кадров, из которых сессия собирает пакеты
+        let packets = match (&client.websocket, client.h2.as_mut()) {
+            (Some(_), _) => vec![message],
+            (None, Some(h2)) => h2.receive(&message)?,
+            (None, None) => client.wrapper.unwrap_batch(&message)?,
+        };
+
+        for packet in packets {
+            // Запрос без payload — опрос сервера
+            if packet.is_empty() {
+                continue;
+            }
+            if let Err(e) = self.process_client_packet(session_id, &packet).await {
+                debug!("Пакет клиента {} отброшен: {}", session_id, e);
+            }
+        }
 
-        info!("Цикл чтения завершён для клиента {}", session_id);
+        self.flush_client(session_id).await
+    }
 
-        // Удаляем клиента при отключении
-        let _ = handle.remove_client(session_id).await;
+    /// Расшифровать пакет клиента и передать данные в маршрутизацию
+    ///
+    /// На fatal alert клиента (close_notify) сервер отвечает close_notify
+    /// и закрывает сессию.
+    async fn process_client_packet(&mut self, session_id: u64, packet: &[u8]) -> Result<()> {
+        let packet = LlpPacket::deserialize(packet)?;
+        let (plaintext, received_alert) = {
+            let mut manager = self.session_manager.write().await;
+            let session = manager.get_session_mut(session_id)?;
+            let plaintext = session.open_packet(&packet)?;
+            // Любой аутентифицированный пакет подтверждает, что клиент жив
+            session.mark_keepalive_received();
+            let received_alert = session.process_alert(packet.header.flags, &plaintext);
+            (plaintext, received_alert)
+        };
 
+        match received_alert {
+            Ok(Some(alert)) => debug!("Клиент {} прислал {}", session_id, alert),
+            Ok(None) => {}
+            Err(LlpError::SessionError(SessionError::ClosedByPeer { code, .. })) => {
+                info!("Клиент {} закрыл сессию: {}", session_id, code);
+                self.close_client(session_id, Alert::close_notify()).await;
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        if packet.is_data() {
+            self.route_ip_packet(session_id, &plaintext).await?;
+        }
+        Ok(())
+    }
+
+    /// Ответить pong на ping WebSocket
+    async fn pong(&mut self, session_id: u64, payload: &[u8]) -> Result<()> {
+        let client = self
+            .clients
+            .get_mut(&session_id)
+            .ok_or("Клиент не найден")?;
+        if let Some(websocket) = client.websocket.as_mut() {
+            client.stream.write_all(&websocket.pong(payload)).await?;
+            client.stream.flush().await?;
+        }
         Ok(())
     }
 
     /// Отправить данные клиенту
+    ///
+    /// В роли сервера ответ возможен только на запрос клиента: данные
+    /// ждут в очереди, пока он не придёт.
     async fn send_to_client(&mut self, session_id: u64, data: Bytes) -> Result<()> {
         let client = self
             .clients
             .get_mut(&session_id)
             .ok_or("Клиент не найден")?;
+        client.pending.push_back(data);
 
-        // Обернуть данные в мимикрию
-        let wrapped = client.wrapper.wrap(&data)?;
+        self.flush_client(session_id).await
+    }
 
-        // Отправить через TCP
-        client.stream.write_u32(wrapped.len() as u32).await?;
-        client.stream.write_all(&wrapped).await?;
-        client.stream.flush().await?;
+    /// Отправить клиенту ждущие данные и отключить закрытого клиента,
+    /// если его очередь отправлена
+    async fn flush_client(&mut self, session_id: u64) -> Result<()> {
+        let result = self.write_pending(session_id).await;
 
-        debug!("Отправлено {} байт клиенту {}", wrapped.len(), session_id);
+        let closed = match self.clients.get_mut(&session_id) {
+            Some(client)
+                if client.closing
+                    && client.pending.is_empty()
+                    && client.h2.as_ref().is_none_or(|h2| h2.pending() == 0) =>
+            {
+                if let Some(h2) = client.h2.as_mut() {
+                    h2.close();
+                    h2::write_output(&mut client.stream, h2).await?;
+                }
+                // Alert мог остаться в коротком остатке потокового ответа
+                if client.wrapper.is_streaming() {
+                    let tail = client.wrapper.end_stream()?;
+                    codec::write_frame(&mut client.stream, true, &tail).await?;
+                }
+                let _ = client.stream.shutdown().await;
+                true
+            }
+            _ => false,
+        };
+        if closed {
+            self.remove_client(session_id).await;
+        }
+
+        result
+    }
+
+    /// Отправить клиенту ждущие данные, на которые есть запросы
+    async fn write_pending(&mut self, session_id: u64) -> Result<()> {
+        let client = self
+            .clients
+            .get_mut(&session_id)
+            .ok_or("Клиент не найден")?;
+
+        // По WebSocket пакет идёт целиком в бинарном сообщении
+        if let Some(websocket) = client.websocket.as_mut() {
+            while let Some(data) = client.pending.pop_front() {
+                ws::write_message(&mut client.stream, websocket, &data).await?;
+                debug!(
+                    "Отправлено {} байт клиенту {} (WebSocket)",
+                    data.len(),
+                    session_id
+                );
+            }
+            return Ok(());
+        }
+
+        // HTTP/2 сессия сама ждёт окон и GET запросов клиента; вывод
+        // пишется и без данных — в нём подтверждения кадров клиента
+        if let Some(h2) = client.h2.as_mut() {
+            while let Some(data) = client.pending.pop_front() {
+                h2.send(&data)?;
+            }
+            h2::write_output(&mut client.stream, h2).await?;
+            return Ok(());
+        }
+
+        // Потоковый ответ открывается на запрос, когда есть данные
+        if client.stream_responses
+            && !client.pending.is_empty()
+            && !client.wrapper.is_streaming()
+            && client.wrapper.can_respond()
+        {
+            match client.wrapper.start_stream() {
+                Ok(head) => {
+                    codec::write_frame(&mut client.stream, true, &head).await?;
+                    debug!("Открыт потоковый ответ клиенту {}", session_id);
+                }
+                Err(MimicryError::UnsupportedProfile(_)) => client.stream_responses = false,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        // Без агрегации и потока — по пакету на открытый запрос, остальные
+        // ждут в очереди; агрегация сама делит пачку по размеру профиля
+        let count = client.pending.len().min(client.wrapper.batch_capacity());
+        if count == 0 && !client.wrapper.is_aggregating() {
+            return Ok(());
+        }
+        let batch: Vec<Bytes> = client.pending.drain(..count).collect();
+
+        // В потоке — только полные chunk, поэтому сообщения бывают пусты
+        for wrapped in client.wrapper.wrap_batch(&batch)? {
+            if wrapped.is_empty() {
+                continue;
+            }
+
+            // Отправить через TCP
+            codec::write_frame(&mut client.stream, client.wrapper.is_http(), &wrapped).await?;
+
+            debug!("Отправлено {} байт клиенту {}", wrapped.len(), session_id);
+        }
 
         Ok(())
     }
 
+    /// Отправить короткий остаток открытых потоковых ответов
+    async fn flush_streams(&mut self) {
+        for (session_id, client) in self.clients.iter_mut() {
+            if !client.wrapper.is_streaming() {
+                continue;
+            }
+            let result = match client.wrapper.flush_stream() {
+                Ok(chunk) if chunk.is_empty() => Ok(()),
+                Ok(chunk) => codec::write_frame(&mut client.stream, true, &chunk)
+                    .await
+                    .map_err(Into::into),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                error!("Ошибка отправки потока клиенту {}: {}", session_id, e);
+            }
+        }
+    }
+
+    /// Закрыть сессию клиента, отправив ему alert
+    ///
+    /// Alert шифруется следующим nonce сессии и ждёт запроса клиента, как
+    /// обычный ответ, а сессия удаляется сразу. Клиент отключается, когда
+    /// alert отправлен, или через [`CLOSE_TIMEOUT`].
+    async fn close_client(&mut self, session_id: u64, alert: Alert) {
+        let sealed = {
+            let mut manager = self.session_manager.write().await;
+            let sealed = manager
+                .get_session_mut(session_id)
+                .and_then(|session| session.seal_alert(&alert))
+                .and_then(|packet| packet.serialize());
+            let _ = manager.remove_session(session_id);
+            sealed
+        };
+
+        let Some(client) = self.clients.get_mut(&session_id) else {
+            return;
+        };
+        if client.closing {
+            return;
+        }
+        client.closing = true;
+        match sealed {
+            Ok(packet) => client.pending.push_back(packet),
+            Err(e) => debug!("Alert для клиента {} не зашифрован: {}", session_id, e),
+        }
+        info!("Закрытие сессии {}: {}", session_id, alert);
+
+        if let Err(e) = self.flush_client(session_id).await {
+            debug!("Alert клиенту {} не отправлен: {}", session_id, e);
+            self.remove_client(session_id).await;
+            return;
+        }
+
+        // Клиент без запроса отключается по таймауту
+        if self.clients.contains_key(&session_id) {
+            let handle = self.handle();
+            tokio::spawn(async move {
+                tokio::time::sleep(CLOSE_TIMEOUT).await;
+                let _ = handle.remove_client(session_id).await;
+            });
+        }
+    }
+
+    /// Закрыть сессии клиентов, истёкшие в менеджере
+    async fn close_expired(&mut self) -> usize {
+        let expired = self.session_manager.read().await.expired();
+
+        let mut count = 0;
+        for error in expired {
+            let session_id = match error {
+                SessionError::SessionExpired { session_id }
+                | SessionError::KeepaliveTimeout { session_id } => session_id,
+                _ => continue,
+            };
+            if !self.clients.contains_key(&session_id) {
+                continue;
+            }
+
+            let alert = Alert::new(AlertCode::from(&LlpError::from(error)));
+            self.close_client(session_id, alert).await;
+            count += 1;
+        }
+
+        count
+    }
+
     /// Удалить клиента
     async fn remove_client(&mut self, session_id: u64) {
         if let Some(client) = self.clients.remove(&session_id) {
@@ -262,12 +752,19 @@ impl Router {
         }
     }
 
-    /// Маршрутизация IP пакета
-    #[allow(dead_code)]
-    async fn route_ip_packet(&mut self, _packet: &[u8]) -> Result<()> {
-        // TODO: Парсинг IP заголовка и маршрутизация
-        // Заглушка на будущее
-        Ok(())
+    /// Маршрутизация IP пакета клиента
+    async fn route_ip_packet(&mut self, session_id: u64, packet: &[u8]) -> Result<()> {
+        match self.nat_gateway.as_mut() {
+            Some(nat) => nat.route_packet(packet, session_id).await,
+            None => {
+                debug!(
+                    "NAT gateway не задан: пакет клиента {} ({} байт) отброшен",
+                    session_id,
+                    packet.len()
+                );
+                Ok(())
+            }
+        }
     }
 }
 
@@ -285,4 +782,350 @@ mod tests {
         let handle2 = handle.clone();
         assert!(std::mem::size_of_val(&handle2) > 0);
     }
+
+    /// Клиент [`ServerConnection`] отправляет пакет через роутер и получает
+    /// ответ сервера, поставленный в очередь до запроса
+    async fn exchange(streaming: bool) {
+        use llp_client::{ClientConfig, ServerConnection};
+        use llp_core::crypto::SessionKey;
+        use llp_core::packet::PacketFlags;
+        use llp_core::session::Session;
+
+        let session_id = 0x1122_3344;
+        let key = SessionKey::from_bytes(&[7u8; 32]);
+        let profile = MimicryProfile::VkVideo;
+
+        let session_manager = Arc::new(RwLock::new(SessionManager::new()));
+        session_manager
+            .write()
+            .await
+            .add_session(session_id, key.clone(), profile)
+            .unwrap();
+        let mut router = Router::new(Arc::clone(&session_manager));
+        router.set_streaming(streaming);
+        let handle = router.handle();
+        tokio::spawn(router.run());
+
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        handle
+            .register_client(
+                session_id,
+                Transport::Tunnel(server_io),
+                None,
+                None,
+                profile,
+            )
+            .await
+            .unwrap();
+
+        // Ответ сервера ждёт запроса клиента
+        let reply = session_manager
+            .write()
+            .await
+            .get_session_mut(session_id)
+            .unwrap()
+            .seal_packet(PacketFlags::DATA, b"pong")
+            .unwrap();
+        handle
+            .send_to_client(session_id, reply.serialize().unwrap())
+            .await
+            .unwrap();
+
+        let mut client = ServerConnection::with_session(
+            Arc::new(ClientConfig::default()),
+            Transport::Tunnel(client_io),
+            Session::new(session_id, key, profile),
+        );
+        client.send_packet(b"ping").await.unwrap();
+
+        let received = tokio::time::timeout(Duration::from_secs(5), client.receive_packet())
+            .await
+            .expect("ответ сервера не пришёл")
+            .unwrap();
+        assert_eq!(&received[..], b"pong");
+
+        // Сервер расшифровал пакет клиента
+        let manager = session_manager.read().await;
+        let window = manager.get_session(session_id).unwrap().replay_window();
+        assert!(!window.check(0));
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_packet_in_batch() {
+        use llp_core::crypto::SessionKey;
+        use llp_core::packet::PacketFlags;
+        use llp_core::session::Session;
+
+        let session_id = 0x5566_7788;
+        let key = SessionKey::from_bytes(&[9u8; 32]);
+        let profile = MimicryProfile::VkVideo;
+        let options = AggregationOptions::default();
+
+        let session_manager = Arc::new(RwLock::new(SessionManager::new()));
+        session_manager
+            .write()
+            .await
+            .add_session(session_id, key.clone(), profile)
+            .unwrap();
+        let mut router = Router::new(Arc::clone(&session_manager));
+        router.set_aggregation(Some(options.clone()));
+        let handle = router.handle();
+        tokio::spawn(router.run());
+
+        let (mut client_io, server_io) = tokio::io::duplex(64 * 1024);
+        handle
+            .register_client(
+                session_id,
+                Transport::Tunnel(server_io),
+                None,
+                None,
+                profile,
+            )
+            .await
+            .unwrap();
+        let reply = session_manager
+            .write()
+            .await
+            .get_session_mut(session_id)
+            .unwrap()
+            .seal_packet(PacketFlags::DATA, b"pong")
+            .unwrap();
+        handle
+            .send_to_client(session_id, reply.serialize().unwrap())
+            .await
+            .unwrap();
+
+        // Три пакета в одном запросе, у среднего испорчен auth tag
+        let mut client_session = Session::new(session_id, key, profile);
+        let mut packets: Vec<Vec<u8>> = (0..3)
+            .map(|_| {
+                let packet = client_session
+                    .seal_packet(PacketFlags::DATA, b"data")
+                    .unwrap();
+                packet.serialize().unwrap().to_vec()
+            })
+            .collect();
+        *packets[1].last_mut().unwrap() ^= 0xFF;
+
+        let mut client = PacketWrapper::new(profile)
+            .with_role(Role::Client)
+            .with_aggregation(options);
+        let requests = client.wrap_batch(&packets).unwrap();
+        assert_eq!(requests.len(), 1);
+        codec::write_frame(&mut client_io, true, &requests[0])
+            .await
+            .unwrap();
+
+        // Ответ всё равно отправлен
+        let mut decoder = HttpDecoder::new();
+        let response = tokio::time::timeout(
+            Duration::from_secs(5),
+            codec::read_frame(&mut client_io, &mut decoder, true),
+        )
+        .await
+        .expect("ответ сервера не пришёл")
+        .unwrap();
+        let received = client.unwrap_batch(&response).unwrap();
+        let packet = LlpPacket::deserialize(&received[0]).unwrap();
+        assert_eq!(client_session.open_packet(&packet).unwrap(), b"pong");
+
+        // Пакеты вокруг испорченного приняты
+        let manager = session_manager.read().await;
+        let window = manager.get_session(session_id).unwrap().replay_window();
+        assert!(!window.check(0));
+        assert!(window.check(1));
+        assert!(!window.check(2));
+    }
+
+    /// Сессия клиента [`connected_client`]
+    const CLIENT_SESSION_ID: u64 = 0x99AA_BBCC;
+
+    /// Роутер с клиентом [`ServerConnection`]; обе стороны живут по общим
+    /// виртуальным часам
+    async fn connected_client(
+        clock: &llp_core::clock::SimulatedClock,
+    ) -> (
+        Arc<RwLock<SessionManager>>,
+        RouterHandle,
+        llp_client::ServerConnection,
+    ) {
+        use llp_client::{ClientConfig, ServerConnection};
+        use llp_core::crypto::SessionKey;
+        use llp_core::session::Session;
+
+        let session_id = CLIENT_SESSION_ID;
+        let key = SessionKey::from_bytes(&[5u8; 32]);
+        let profile = MimicryProfile::VkVideo;
+
+        let mut manager = SessionManager::with_lifetime(Duration::from_secs(3600));
+        manager.set_clock(clock.shared());
+        manager
+            .add_session(session_id, key.clone(), profile)
+            .unwrap();
+        let session_manager = Arc::new(RwLock::new(manager));
+        let router = Router::new(Arc::clone(&session_manager));
+        let handle = router.handle();
+        tokio::spawn(router.run());
+
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        handle
+            .register_client(
+                session_id,
+                Transport::Tunnel(server_io),
+                None,
+                None,
+                profile,
+            )
+            .await
+            .unwrap();
+
+        let session = Session::with_clock(session_id, key, profile, 1024, clock.shared());
+        let client = ServerConnection::with_session(
+            Arc::new(ClientConfig::default()),
+            Transport::Tunnel(client_io),
+            session,
+        );
+        (session_manager, handle, client)
+    }
+
+    #[tokio::test]
+    async fn test_client_sees_close_alerts() {
+        use llp_client::connection::peer_alert_code;
+        use llp_core::clock::SimulatedClock;
+
+        for (code, idle) in [
+            (AlertCode::ServerShutdown, Duration::ZERO),
+            (AlertCode::KeepaliveTimeout, Duration::from_secs(100)),
+            (AlertCode::SessionExpired, Duration::from_secs(3601)),
+        ] {
+            let clock = SimulatedClock::new(1_700_000_000);
+            let (session_manager, handle, mut client) = connected_client(&clock).await;
+
+            clock.advance(idle);
+            if code == AlertCode::ServerShutdown {
+                handle.close_all(Alert::new(code)).await.unwrap();
+            } else {
+                assert_eq!(handle.close_expired().await.unwrap(), 1);
+            }
+
+            // Alert приходит в ответ на опрос клиента
+            let error = tokio::time::timeout(Duration::from_secs(5), client.receive_packet())
+                .await
+                .expect("alert не пришёл")
+                .unwrap_err();
+            assert_eq!(peer_alert_code(&*error), Some(code));
+            assert_eq!(session_manager.read().await.session_count(), 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_read_keeps_stream() {
+        use llp_core::clock::SimulatedClock;
+        use llp_core::packet::PacketFlags;
+
+        let clock = SimulatedClock::new(1_700_000_000);
+        let (session_manager, handle, mut client) = connected_client(&clock).await;
+
+        // Как в цикле клиента: опрос до select!, чтение отменяется
+        // другими ветками, пока сервер молчит
+        for _ in 0..3 {
+            client.send_pending().await.unwrap();
+            let read = tokio::time::timeout(Duration::from_millis(20), client.read_incoming());
+            assert!(read.await.is_err());
+        }
+
+        let reply = session_manager
+            .write()
+            .await
+            .get_session_mut(CLIENT_SESSION_ID)
+            .unwrap()
+            .seal_packet(PacketFlags::DATA, b"pong")
+            .unwrap();
+        handle
+            .send_to_client(CLIENT_SESSION_ID, reply.serialize().unwrap())
+            .await
+            .unwrap();
+
+        let received = tokio::time::timeout(Duration::from_secs(5), client.receive_packet())
+            .await
+            .expect("ответ сервера не пришёл")
+            .unwrap();
+        assert_eq!(&received[..], b"pong");
+    }
+
+    #[tokio::test]
+    async fn test_close_notify_reply() {
+        use llp_core::crypto::SessionKey;
+        use llp_core::session::Session;
+
+        let session_id = CLIENT_SESSION_ID;
+        let key = SessionKey::from_bytes(&[5u8; 32]);
+        let profile = MimicryProfile::VkVideo;
+        let session_manager = Arc::new(RwLock::new(SessionManager::new()));
+        session_manager
+            .write()
+            .await
+            .add_session(session_id, key.clone(), profile)
+            .unwrap();
+        let router = Router::new(Arc::clone(&session_manager));
+        let handle = router.handle();
+        tokio::spawn(router.run());
+        let (mut client_io, server_io) = tokio::io::duplex(64 * 1024);
+        handle
+            .register_client(
+                session_id,
+                Transport::Tunnel(server_io),
+                None,
+                None,
+                profile,
+            )
+            .await
+            .unwrap();
+
+        // Клиент закрывает сессию и ждёт ответный close_notify
+        let mut client_session = Session::new(session_id, key, profile);
+        let close_notify = client_session.seal_alert(&Alert::close_notify()).unwrap();
+        let mut client = PacketWrapper::new(profile).with_role(Role::Client);
+        let request = client.wrap(&close_notify.serialize().unwrap()).unwrap();
+        codec::write_frame(&mut client_io, true, &request)
+            .await
+            .unwrap();
+
+        let mut decoder = HttpDecoder::new();
+        let response = tokio::time::timeout(
+            Duration::from_secs(5),
+            codec::read_frame(&mut client_io, &mut decoder, true),
+        )
+        .await
+        .expect("close_notify не пришёл")
+        .unwrap();
+        let reply = LlpPacket::deserialize(&client.unwrap(&response).unwrap()).unwrap();
+        let plaintext = client_session.open_packet(&reply).unwrap();
+        let closed = client_session
+            .process_alert(reply.header.flags, &plaintext)
+            .unwrap_err();
+        assert!(matches!(
+            closed,
+            LlpError::SessionError(SessionError::ClosedByPeer {
+                code: AlertCode::CloseNotify,
+                ..
+            })
+        ));
+
+        // Сервер закрыл поток и удалил сессию
+        let mut rest = Vec::new();
+        client_io.read_to_end(&mut rest).await.unwrap();
+        assert!(!session_manager.read().await.has_session(session_id));
+    }
+
+    #[tokio::test]
+    async fn test_client_server_client_exchange() {
+        exchange(false).await;
+    }
+
+    #[tokio::test]
+    async fn test_streaming_exchange() {
+        // Пакет меньше chunk приходит по таймеру сброса потока
+        exchange(true).await;
+    }
 }
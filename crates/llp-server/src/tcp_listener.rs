@@ -0,0 +1,792 @@
+//! TCP Listener для клиентов поверх TCP и TLS
+//!
+//! Этот модуль отвечает за:
+//! - Прослушивание TCP порта (тот же номер, что и у UDP listener)
+//! - Внешний TLS 1.3 слой, если он включён
+//! - Проверку токена доступа в первых байтах ([`crate::fallback`])
+//! - Приём WebSocket Upgrade с токеном в `Sec-WebSocket-Protocol`
+//! - HTTP/2 для клиентов, согласовавших ALPN `h2` ([`llp_mimicry::h2`])
+//! - Отдачу неаутентифицированных подключений upstream сайту или
+//!   встроенному сайту-приманке ([`crate::decoy`])
+//! - Handshake LLP и передачу потока роутеру
+//! - Alert `TooManySessions` клиенту, которому не хватило места
+//! - Те же шаги для потоков DNS туннеля ([`LlpTcpListener::serve`])
+
+use llp_core::{
+    access::DEFAULT_ACCESS_DRIFT_SECS,
+    alert::{Alert, AlertCode},
+    clock::SystemClock,
+    error::SessionError,
+    handshake::ServerHandshake,
+    session::{Session, SessionManager},
+};
+use bytes::{Bytes, BytesMut};
+use llp_mimicry::codec::{self, HttpDecoder};
+use llp_mimicry::h2::{self, H2Session};
+use llp_mimicry::tls::{self, Transport};
+use llp_mimicry::ws::{self, WsCodec};
+use llp_mimicry::{PacketWrapper, Role};
+use rand::rngs::OsRng;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tracing::{debug, error, info};
+
+use crate::config::ServerConfig;
+use crate::decoy::DecoySite;
+use crate::fallback::{self, AccessGuard, Probe};
+use crate::router::RouterHandle;
+
+/// Максимальный размер сообщения handshake
+const MAX_HANDSHAKE_MESSAGE: usize = 4096;
+
+/// Результат обработки подключения
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// TCP Listener сервера
+pub struct LlpTcpListener {
+    /// Конфигурация сервера
+    config: Arc<ServerConfig>,
+    /// TCP socket
+    listener: TcpListener,
+    /// Конфигурация TLS (None — TLS выключен)
+    tls: Option<Arc<rustls::ServerConfig>>,
+    /// Проверка токена доступа (None — токен не требуется)
+    access: Option<AccessGuard>,
+    /// Сайт-приманка (None — fallback выключен или задан upstream)
+    decoy: Option<DecoySite>,
+    /// Заголовок `Server` профиля мимикрии для ответа WebSocket Upgrade
+    server_header: Option<String>,
+    /// Менеджер сессий
+    session_manager: Arc<RwLock<SessionManager>>,
+    /// Роутер для передачи данных
+    router: RouterHandle,
+}
+
+impl LlpTcpListener {
+    /// Создать новый listener
+    pub async fn bind(
+        config: Arc<ServerConfig>,
+        session_manager: Arc<RwLock<SessionManager>>,
+        router: RouterHandle,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind(config.bind_address()).await?;
+        let tls = config.tls_server_config()?;
+        let access = config
+            .access_key()?
+            .map(|key| AccessGuard::new(key, DEFAULT_ACCESS_DRIFT_SECS, SystemClock::shared()));
+        let decoy = match (config.fallback.enabled, &config.fallback.upstream) {
+            (true, None) => Some(DecoySite::for_profile(
+                config.parse_mimicry_profile()?,
+                config.fallback.site_dir.clone(),
+                SystemClock::shared(),
+            )),
+            _ => None,
+        };
+        let server_header = PacketWrapper::try_new(config.parse_mimicry_profile()?)?
+            .server_header()
+            .map(str::to_string);
+
+        info!("LLP сервер запущен на {} (TCP)", listener.local_addr()?);
+        if let Some(decoy) = &decoy {
+            info!("Сайт-приманка отвечает как {}", decoy.server());
+        }
+        if config.websocket.enabled {
+            info!("WebSocket клиенты принимаются на {}", config.websocket.path);
+        }
+        if config.tls.http2 {
+            info!("HTTP/2 клиенты принимаются по ALPN h2");
+        }
+
+        Ok(Self {
+            config,
+            listener,
+            tls,
+            access,
+            decoy,
+            server_header,
+            session_manager,
+            router,
+        })
+    }
+
+    /// Адрес, на котором принимаются подключения
+    #[allow(dead_code)]
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Запустить listener (основной цикл)
+    pub async fn run(self: Arc<Self>) -> Result<()> {
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, peer_addr)) => {
+                    let listener = Arc::clone(&self);
+                    tokio::spawn(async move {
+                        if let Err(e) = listener.handle_connection(stream, peer_addr).await {
+                            debug!("Ошибка обработки подключения {}: {}", peer_addr, e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    error!("Ошибка приёма TCP подключения: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Обработка нового подключения
+    ///
+    /// При включённом TLS зонд без TLS получает ту же ошибку рукопожатия,
+    /// что и от любого HTTPS сайта; токен проверяется уже внутри TLS.
+    async fn handle_connection(&self, stream: TcpStream, peer_addr: SocketAddr) -> Result<()> {
+        let transport = match &self.tls {
+            Some(config) => tls::accept(stream, Arc::clone(config)).await?,
+            None => Transport::Plain(stream),
+        };
+        self.serve(transport, peer_addr).await
+    }
+
+    /// Обслужить поток клиента: токен доступа или WebSocket Upgrade,
+    /// затем handshake LLP
+    ///
+    /// Клиент, согласовавший ALPN `h2`, присылает токен перед префейсом
+    /// HTTP/2 и дальше говорит на HTTP/2. Потоки DNS туннеля
+    /// ([`crate::dns_listener`]) попадают сюда напрямую, минуя TLS.
+    pub async fn serve(&self, mut transport: Transport, peer_addr: SocketAddr) -> Result<()> {
+        let http2 = transport.alpn_protocol() == Some(h2::ALPN_H2);
+        let websocket = if self.config.websocket.enabled && !http2 {
+            match self.upgrade(&mut transport).await? {
+                Ok(codec) => Some(codec),
+                Err(prefix) => return self.reject(transport, &prefix, peer_addr).await,
+            }
+        } else {
+            if let Some(access) = &self.access {
+                match access
+                    .probe(&mut transport, self.config.auth_timeout())
+                    .await?
+                {
+                    Probe::Authenticated => {}
+                    Probe::Foreign(prefix) => {
+                        return self.reject(transport, &prefix, peer_addr).await
+                    }
+                }
+            }
+            None
+        };
+        let h2 = if http2 {
+            let profile = self.config.parse_mimicry_profile()?;
+            Some(H2Session::try_new(profile, Role::Server)?)
+        } else {
+            None
+        };
+
+        tokio::time::timeout(
+            self.config.connection_timeout(),
+            self.perform_handshake(transport, websocket, h2, peer_addr),
+        )
+        .await?
+    }
+
+    /// Принять запрос WebSocket Upgrade
+    ///
+    /// `Err` — байты чужого запроса (другой путь, не Upgrade, нет верного
+    /// токена доступа): такое подключение отдаётся как и без токена.
+    async fn upgrade(
+        &self,
+        transport: &mut Transport,
+    ) -> Result<std::result::Result<WsCodec, Vec<u8>>> {
+        let mut buf = BytesMut::new();
+        let head_len = match tokio::time::timeout(
+            self.config.auth_timeout(),
+            ws::read_head(transport, &mut buf, ws::MAX_HEAD_SIZE),
+        )
+        .await
+        {
+            Ok(result) => result?,
+            Err(_) => None,
+        };
+        let Some(head_len) = head_len else {
+            return Ok(Err(buf.to_vec()));
+        };
+        let upgrade = match ws::parse_upgrade(&buf[..head_len]) {
+            Ok(upgrade) if upgrade.path == self.config.websocket.path => upgrade,
+            _ => return Ok(Err(buf.to_vec())),
+        };
+
+        let protocol = match &self.access {
+            Some(access) => {
+                let accepted = upgrade.protocols.iter().find(|protocol| {
+                    ws::decode_protocol(protocol).is_some_and(|token| access.check(&token))
+                });
+                match accepted {
+                    Some(protocol) => Some(protocol.as_str()),
+                    None => return Ok(Err(buf.to_vec())),
+                }
+            }
+            None => None,
+        };
+
+        let response =
+            ws::upgrade_response(&upgrade.key, self.server_header.as_deref(), protocol);
+        transport.write_all(&response).await?;
+        transport.flush().await?;
+
+        let mut codec = WsCodec::server();
+        codec.extend(&buf[head_len..]);
+        Ok(Ok(codec))
+    }
+
+    /// Отдать неаутентифицированное подключение upstream, приманке или закрыть его
+    async fn reject(
+        &self,
+        mut transport: Transport,
+        prefix: &[u8],
+        peer_addr: SocketAddr,
+    ) -> Result<()> {
+        if let Some(decoy) = &self.decoy {
+            debug!("Подключение {} без токена доступа отдано приманке", peer_addr);
+            decoy.serve(&mut transport, prefix).await?;
+            return Ok(());
+        }
+
+        let upstream = match (self.config.fallback.enabled, &self.config.fallback.upstream) {
+            (true, Some(upstream)) => upstream,
+            _ => {
+                debug!("Подключение {} без токена доступа закрыто", peer_addr);
+                return Ok(());
+            }
+        };
+
+        debug!(
+            "Подключение {} без токена доступа передано {}",
+            peer_addr, upstream
+        );
+        let (sent, received) = fallback::proxy(&mut transport, prefix, upstream).await?;
+        debug!(
+            "Проксирование {} завершено: {} байт к upstream, {} байт от upstream",
+            peer_addr, sent, received
+        );
+        Ok(())
+    }
+
+    /// Handshake LLP поверх потока и регистрация клиента в роутере
+    async fn perform_handshake(
+        &self,
+        mut transport: Transport,
+        mut websocket: Option<WsCodec>,
+        mut h2: Option<H2Session>,
+        peer_addr: SocketAddr,
+    ) -> Result<()> {
+        let mut rng = OsRng;
+        let session_id = rand::random::<u64>();
+        let mut server_handshake = ServerHandshake::new(&mut rng, session_id);
+
+        // Handshake приходит в запросах мимикрии профиля сервера, как и данные
+        let mut wrapper =
+            PacketWrapper::try_new(self.config.parse_mimicry_profile()?)?.with_role(Role::Server);
+        let mut framing = Framing {
+            websocket: websocket.as_mut(),
+            h2: h2.as_mut(),
+            received: VecDeque::new(),
+            wrapper: &mut wrapper,
+            decoder: HttpDecoder::new(),
+        };
+
+        // 1. CLIENT_HELLO -> SERVER_HELLO
+        let client_hello = framing.read_message(&mut transport).await?;
+        let (server_hello, mimicry_profile) =
+            server_handshake.process_client_hello(&mut rng, &client_hello)?;
+        framing.write_message(&mut transport, &server_hello).await?;
+
+        // 2. CLIENT_VERIFY -> SERVER_VERIFY
+        let client_verify = framing.read_message(&mut transport).await?;
+        server_handshake.process_client_verify(&client_verify)?;
+        let server_verify = server_handshake.send_server_verify()?;
+        framing
+            .write_message(&mut transport, &server_verify)
+            .await?;
+
+        let session_key = server_handshake
+            .session_key()
+            .ok_or("Сессионный ключ не получен")?
+            .clone();
+
+        info!(
+            "Handshake завершён: session_id={}, profile={}, peer={} ({})",
+            session_id,
+            mimicry_profile,
+            peer_addr,
+            match (&websocket, &h2) {
+                (Some(_), _) => "WebSocket",
+                (None, Some(_)) => "HTTP/2",
+                (None, None) => "TCP",
+            }
+        );
+
+        let rejected = {
+            let mut manager = self.session_manager.write().await;
+            let connected = manager.session_count();
+            if connected >= self.config.network.max_connections {
+                Some(SessionError::TooManySessions {
+                    current: connected,
+                    max: self.config.network.max_connections,
+                })
+            } else {
+                manager.add_session(session_id, session_key.clone(), mimicry_profile)?;
+                None
+            }
+        };
+
+        // Клиент узнаёт причину отказа из alert, зашифрованного ключом сессии
+        if let Some(error) = rejected {
+            let session = Session::new(session_id, session_key, mimicry_profile);
+            let alert = Alert::new(AlertCode::TooManySessions);
+            if let Err(e) = self
+                .send_alert(&mut transport, websocket.as_mut(), h2.as_mut(), session, &alert)
+                .await
+            {
+                debug!("Alert клиенту {} не отправлен: {}", peer_addr, e);
+            }
+            return Err(error.into());
+        }
+
+        self.router
+            .register_client(session_id, transport, websocket, h2, mimicry_profile)
+            .await
+    }
+
+    /// Отправить alert в сессии, которая не передаётся роутеру
+    ///
+    /// В HTTP профиле сервер отвечает только на запрос, поэтому alert
+    /// уходит в ответе на первый запрос клиента после handshake.
+    async fn send_alert(
+        &self,
+        transport: &mut Transport,
+        websocket: Option<&mut WsCodec>,
+        h2: Option<&mut H2Session>,
+        mut session: Session,
+        alert: &Alert,
+    ) -> Result<()> {
+        let packet = session.seal_alert(alert)?.serialize()?;
+        if let Some(websocket) = websocket {
+            return Ok(ws::write_message(transport, websocket, &packet).await?);
+        }
+        // GET запросы клиента для ответов сервера открыты с префейса
+        if let Some(h2) = h2 {
+            return Ok(h2::write_message(transport, h2, &packet).await?);
+        }
+
+        let mut wrapper =
+            PacketWrapper::try_new(session.mimicry_profile())?.with_role(Role::Server);
+        if let Some(options) = self.config.aggregation.options() {
+            wrapper = wrapper.with_aggregation(options);
+        }
+        if wrapper.is_http() {
+            let mut decoder = HttpDecoder::new();
+            let request = codec::read_frame(transport, &mut decoder, true).await?;
+            wrapper.unwrap_batch(&request)?;
+        }
+        let wrapped = wrapper.wrap(&packet)?;
+        codec::write_frame(transport, wrapper.is_http(), &wrapped).await?;
+        Ok(())
+    }
+}
+
+/// Разметка сообщений handshake: сообщения WebSocket, HTTP/2 или мимикрии
+struct Framing<'a> {
+    /// Кадры WebSocket (None — сообщения мимикрии)
+    websocket: Option<&'a mut WsCodec>,
+    /// HTTP/2 соединение (None — сообщения мимикрии)
+    h2: Option<&'a mut H2Session>,
+    /// Пакеты HTTP/2, прочитанные вместе с предыдущим сообщением
+    received: VecDeque<Bytes>,
+    /// Обёртка профиля в роли сервера
+    wrapper: &'a mut PacketWrapper,
+    /// Декодер запросов клиента
+    decoder: HttpDecoder,
+}
+
+impl Framing<'_> {
+    /// Прочитать сообщение handshake из запроса мимикрии, DATA HTTP/2 или
+    /// сообщения WebSocket
+    async fn read_message(&mut self, transport: &mut Transport) -> Result<Vec<u8>> {
+        let message = if let Some(websocket) = self.websocket.as_mut() {
+            ws::read_message(transport, websocket).await?
+        } else if let Some(session) = self.h2.as_mut() {
+            loop {
+                if let Some(message) = self.received.pop_front() {
+                    break message;
+                }
+                self.received
+                    .extend(h2::read_messages(transport, session).await?);
+            }
+        } else {
+            let http = self.wrapper.is_http();
+            let request = codec::read_frame(transport, &mut self.decoder, http).await?;
+            self.wrapper.unwrap(&request)?
+        };
+        if message.len() > MAX_HANDSHAKE_MESSAGE {
+            return Err(
+                format!("Сообщение handshake слишком большое: {} байт", message.len()).into(),
+            );
+        }
+        Ok(message.to_vec())
+    }
+
+    /// Записать сообщение handshake в ответе мимикрии, DATA HTTP/2 или
+    /// сообщении WebSocket
+    async fn write_message(&mut self, transport: &mut Transport, message: &[u8]) -> Result<()> {
+        if let Some(websocket) = self.websocket.as_mut() {
+            ws::write_message(transport, websocket, message).await?;
+        } else if let Some(session) = self.h2.as_mut() {
+            h2::write_message(transport, session, message).await?;
+        } else {
+            let response = self.wrapper.wrap(message)?;
+            codec::write_frame(transport, self.wrapper.is_http(), &response).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::Router;
+    use llp_core::access::AccessKey;
+    use llp_core::clock::Clock;
+    use llp_core::handshake::ClientHandshake;
+    use llp_core::packet::MimicryProfile;
+    use tokio::io::AsyncReadExt;
+
+    const UPSTREAM_PAGE: &[u8] =
+        b"HTTP/1.1 200 OK\r\nServer: nginx\r\nContent-Length: 12\r\nConnection: close\r\n\r\nhello, world";
+
+    /// Локальная замена настоящего сайта: на любой запрос отвечает одной страницей
+    async fn spawn_upstream() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let _ = stream.write_all(UPSTREAM_PAGE).await;
+                });
+            }
+        });
+        addr
+    }
+
+    async fn spawn_server(
+        key: &AccessKey,
+        upstream: Option<SocketAddr>,
+        websocket: bool,
+    ) -> SocketAddr {
+        let mut config = ServerConfig::default();
+        config.websocket.enabled = websocket;
+        config.network.bind_ip = "127.0.0.1".parse().unwrap();
+        config.network.port = 0;
+        config.security.access_key = Some(key.to_hex());
+        config.fallback.enabled = true;
+        config.fallback.upstream = upstream.map(|addr| addr.to_string());
+        spawn_with_config(config).await
+    }
+
+    async fn spawn_with_config(config: ServerConfig) -> SocketAddr {
+        let session_manager = Arc::new(RwLock::new(SessionManager::new()));
+        let router = Router::new(Arc::clone(&session_manager));
+        let handle = router.handle();
+        tokio::spawn(router.run());
+
+        let listener = Arc::new(
+            LlpTcpListener::bind(Arc::new(config), session_manager, handle)
+                .await
+                .unwrap(),
+        );
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(listener.run());
+        addr
+    }
+
+    async fn fetch(addr: SocketAddr, request: &[u8]) -> Vec<u8> {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request).await.unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_probe_sees_upstream_site() {
+        let key = AccessKey::generate(&mut OsRng);
+        let upstream = spawn_upstream().await;
+        let server = spawn_server(&key, Some(upstream), false).await;
+
+        for request in [
+            &b"GET / HTTP/1.0\r\n\r\n"[..],
+            b"GET /index.html HTTP/1.1\r\nHost: vkvideo.ru\r\nUser-Agent: probe\r\n\r\n",
+        ] {
+            let direct = fetch(upstream, request).await;
+            let proxied = fetch(server, request).await;
+            assert_eq!(direct, UPSTREAM_PAGE);
+            assert_eq!(proxied, direct);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_probe_sees_decoy_site() {
+        let key = AccessKey::generate(&mut OsRng);
+        let server = spawn_server(&key, None, false).await;
+
+        let response = fetch(server, b"GET /admin HTTP/1.1\r\nHost: vkvideo.ru\r\nConnection: close\r\n\r\n").await;
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\nServer: nginx/1.20.2\r\n"));
+        assert!(response.contains("<hr><center>nginx/1.20.2</center>"));
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_client_gets_handshake() {
+        let key = AccessKey::generate(&mut OsRng);
+        let upstream = spawn_upstream().await;
+        let server = spawn_server(&key, Some(upstream), false).await;
+
+        let mut stream = TcpStream::connect(server).await.unwrap();
+        let token = key.token(&mut OsRng, SystemClock.unix_secs());
+        stream.write_all(&token).await.unwrap();
+
+        // Handshake — в HTTP запросах и ответах профиля, без префикса длины
+        let mut wrapper = PacketWrapper::new(MimicryProfile::VkVideo).with_role(Role::Client);
+        let mut decoder = HttpDecoder::new();
+        let mut client = ClientHandshake::new(&mut OsRng, MimicryProfile::VkVideo);
+        let client_hello = wrapper.wrap(&client.start(&mut OsRng).unwrap()).unwrap();
+        assert!(client_hello.starts_with(b"GET ") || client_hello.starts_with(b"POST "));
+        codec::write_frame(&mut stream, true, &client_hello)
+            .await
+            .unwrap();
+
+        let response = codec::read_frame(&mut stream, &mut decoder, true)
+            .await
+            .unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 "));
+        client
+            .process_server_hello(&wrapper.unwrap(&response).unwrap())
+            .unwrap();
+
+        let client_verify = wrapper.wrap(&client.send_client_verify().unwrap()).unwrap();
+        codec::write_frame(&mut stream, true, &client_verify)
+            .await
+            .unwrap();
+
+        let response = codec::read_frame(&mut stream, &mut decoder, true)
+            .await
+            .unwrap();
+        client
+            .process_server_verify(&wrapper.unwrap(&response).unwrap())
+            .unwrap();
+        assert!(client.is_completed());
+    }
+
+    #[tokio::test]
+    async fn test_too_many_sessions_alert() {
+        use llp_client::connection::peer_alert_code;
+        use llp_client::{ClientConfig, ServerConnection};
+        use llp_core::alert::AlertCode;
+
+        let key = AccessKey::generate(&mut OsRng);
+        let mut config = ServerConfig::default();
+        config.network.bind_ip = "127.0.0.1".parse().unwrap();
+        config.network.port = 0;
+        config.network.max_connections = 0;
+        config.security.access_key = Some(key.to_hex());
+        let server = spawn_with_config(config).await;
+
+        let mut client_config = ClientConfig::default();
+        client_config.server.host = server.ip().to_string();
+        client_config.server.port = server.port();
+        client_config.security.access_key = Some(key.to_hex());
+        let mut client = ServerConnection::new(Arc::new(client_config));
+        client.connect().await.unwrap();
+
+        // Handshake проходит, но в сессии сервер сразу отказывает
+        let error =
+            tokio::time::timeout(std::time::Duration::from_secs(5), client.receive_packet())
+                .await
+                .expect("alert не пришёл")
+                .unwrap_err();
+        assert_eq!(peer_alert_code(&*error), Some(AlertCode::TooManySessions));
+        assert_eq!(
+            client.info().read().await.last_alert,
+            Some(AlertCode::TooManySessions)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http2_tunnel_round_trip() {
+        use llp_client::{ClientConfig, ServerConnection};
+        use llp_core::packet::PacketFlags;
+        use std::time::Duration;
+
+        let cert = rcgen::generate_simple_self_signed(vec!["vkvideo.ru".to_string()]).unwrap();
+        let dir = std::env::temp_dir().join(format!("llp-h2-{:016x}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("cert.pem"), cert.cert.pem()).unwrap();
+        std::fs::write(dir.join("key.pem"), cert.key_pair.serialize_pem()).unwrap();
+
+        let key = AccessKey::generate(&mut OsRng);
+        let mut config = ServerConfig::default();
+        config.network.bind_ip = "127.0.0.1".parse().unwrap();
+        config.network.port = 0;
+        config.security.access_key = Some(key.to_hex());
+        config.tls.enabled = true;
+        config.tls.cert_file = Some(dir.join("cert.pem"));
+        config.tls.key_file = Some(dir.join("key.pem"));
+        config.tls.http2 = true;
+
+        let session_manager = Arc::new(RwLock::new(SessionManager::new()));
+        let router = Router::new(Arc::clone(&session_manager));
+        let handle = router.handle();
+        tokio::spawn(router.run());
+        let listener = LlpTcpListener::bind(
+            Arc::new(config),
+            Arc::clone(&session_manager),
+            handle.clone(),
+        )
+        .await
+        .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let server = listener.local_addr().unwrap();
+        tokio::spawn(Arc::new(listener).run());
+
+        let mut client_config = ClientConfig::default();
+        client_config.server.host = server.ip().to_string();
+        client_config.server.port = server.port();
+        client_config.security.access_key = Some(key.to_hex());
+        client_config.security.verify_server = false;
+        client_config.tls.enabled = true;
+        client_config.tls.http2 = true;
+        let mut client = ServerConnection::new(Arc::new(client_config));
+        client.connect().await.unwrap();
+        assert!(client.is_http2());
+        let session_id = client.info().read().await.session_id.unwrap();
+
+        // Клиент → сервер: пакеты в POST запросах расшифрованы сессией сервера
+        let up: Vec<Vec<u8>> = (0..20u8).map(|i| vec![i; 1200]).collect();
+        client.send_packets(&up).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let highest = session_manager
+                    .read()
+                    .await
+                    .get_session(session_id)
+                    .map(|session| session.replay_window().highest_seq())
+                    .unwrap();
+                if highest == up.len() as u64 - 1 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("сервер не получил пакеты клиента");
+
+        // Сервер → клиент: пакеты в ответах на GET запросы
+        let down: Vec<Vec<u8>> = (0..20u8).map(|i| vec![!i; 3000]).collect();
+        for data in &down {
+            let packet = session_manager
+                .write()
+                .await
+                .get_session_mut(session_id)
+                .unwrap()
+                .seal_packet(PacketFlags::DATA, data)
+                .unwrap();
+            handle
+                .send_to_client(session_id, packet.serialize().unwrap())
+                .await
+                .unwrap();
+        }
+        for data in &down {
+            let packet = tokio::time::timeout(Duration::from_secs(5), client.receive_packet())
+                .await
+                .expect("пакет сервера не пришёл")
+                .unwrap();
+            assert_eq!(&packet[..], &data[..]);
+        }
+
+        // close_notify и GOAWAY клиента закрывают сессию на сервере
+        client.close().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while session_manager.read().await.has_session(session_id) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("сессия не закрыта");
+    }
+
+    #[tokio::test]
+    async fn test_websocket_client_gets_handshake() {
+        let key = AccessKey::generate(&mut OsRng);
+        let server = spawn_server(&key, None, true).await;
+        let upgrade = |protocol: Option<String>| {
+            let mut wrapper = PacketWrapper::new(MimicryProfile::VkVideo);
+            let ws_key = ws::generate_key(&mut OsRng);
+            let request =
+                ws::upgrade_request(&mut wrapper, None, "/ws", &ws_key, protocol.as_deref())
+                    .unwrap();
+            (ws_key, request)
+        };
+
+        // Без токена запрос Upgrade видит сайт-приманку
+        let (_, request) = upgrade(None);
+        let mut stream = TcpStream::connect(server).await.unwrap();
+        stream.write_all(&request).await.unwrap();
+        let mut response = BytesMut::new();
+        ws::read_head(&mut stream, &mut response, ws::MAX_HEAD_SIZE)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 404 Not Found\r\nServer: nginx/1.20.2\r\n"));
+
+        let token = key.token(&mut OsRng, SystemClock.unix_secs());
+        let (ws_key, request) = upgrade(Some(ws::encode_protocol(&token)));
+        let mut stream = TcpStream::connect(server).await.unwrap();
+        stream.write_all(&request).await.unwrap();
+
+        let mut buf = BytesMut::new();
+        let head_len = ws::read_head(&mut stream, &mut buf, ws::MAX_HEAD_SIZE)
+            .await
+            .unwrap()
+            .unwrap();
+        ws::check_upgrade_response(&buf[..head_len], &ws_key).unwrap();
+        assert!(buf.starts_with(b"HTTP/1.1 101 Switching Protocols\r\nServer: nginx/1.20.2\r\n"));
+
+        let mut codec = WsCodec::client();
+        codec.extend(&buf[head_len..]);
+        let mut client = ClientHandshake::new(&mut OsRng, MimicryProfile::VkVideo);
+        let client_hello = client.start(&mut OsRng).unwrap();
+        ws::write_message(&mut stream, &mut codec, &client_hello)
+            .await
+            .unwrap();
+        let server_hello = ws::read_message(&mut stream, &mut codec).await.unwrap();
+        client.process_server_hello(&server_hello).unwrap();
+
+        let client_verify = client.send_client_verify().unwrap();
+        ws::write_message(&mut stream, &mut codec, &client_verify)
+            .await
+            .unwrap();
+        let server_verify = ws::read_message(&mut stream, &mut codec).await.unwrap();
+        client.process_server_verify(&server_verify).unwrap();
+        assert!(client.is_completed());
+    }
+}